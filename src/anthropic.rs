@@ -0,0 +1,333 @@
+//! Anthropic's Messages API, for Claude models.
+//!
+//! Requests are built in OpenAI's shape like every other one and translated here: the system prompt
+//! becomes a top-level field, tool calls and results become content blocks, and the streamed events
+//! are turned back into the same deltas the OpenAI stream produces.
+
+use async_openai::{
+    error::{ApiError, OpenAIError},
+    types::CreateChatCompletionRequest,
+};
+use futures::{future::BoxFuture, FutureExt, StreamExt};
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::OnceLock};
+use tokio::sync::mpsc;
+
+use crate::{
+    config::Config,
+    msg::{self, Msg},
+    provider::{Backend, Delta, DeltaStream, Provider},
+    usage::Usage,
+};
+
+pub const API_VERSION: &str = "2023-06-01";
+// Anthropic requires max_tokens, which every request here sets; this is only a safety net.
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+fn api_error(r#type: &str, message: String) -> OpenAIError {
+    OpenAIError::ApiError(ApiError {
+        message,
+        r#type: Some(r#type.to_string()),
+        param: None,
+        code: None,
+    })
+}
+
+/// The text of a message's content, which is either a string or a list of parts.
+fn text_of(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
+
+/// Translates an OpenAI-shaped request into a Messages API body.
+pub fn body(request: &CreateChatCompletionRequest) -> Value {
+    let mut system = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+    for message in &request.messages {
+        let message = serde_json::to_value(message).unwrap_or_default();
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let content = message.get("content");
+        let (role, blocks) = match role {
+            "system" | "developer" => {
+                system.push(text_of(content));
+                continue;
+            }
+            "assistant" => {
+                let mut blocks = Vec::new();
+                let text = text_of(content);
+                if !text.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                for call in message
+                    .get("tool_calls")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call["id"],
+                        "name": call["function"]["name"],
+                        "input": serde_json::from_str::<Value>(arguments).unwrap_or(json!({})),
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            // Tool results go back as the user's turn.
+            "tool" => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message["tool_call_id"],
+                    "content": text_of(content),
+                })],
+            ),
+            _ => (
+                "user",
+                vec![json!({"type": "text", "text": text_of(content)})],
+            ),
+        };
+        // The API wants turns to alternate, so consecutive blocks from one role share a message.
+        match messages.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(existing) = last["content"].as_array_mut() {
+                    existing.extend(blocks);
+                }
+            }
+            _ => messages.push(json!({"role": role, "content": blocks})),
+        }
+    }
+
+    let mut body = json!({
+        "model": request.model,
+        "max_tokens": request.max_completion_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "messages": messages,
+        "stream": true,
+    });
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+    if let Some(ref tools) = request.tools {
+        body["tools"] = tools
+            .iter()
+            .map(|t| {
+                json!({
+                    "name": t.function.name,
+                    "description": t.function.description,
+                    "input_schema": t.function.parameters.clone().unwrap_or(json!({"type": "object"})),
+                })
+            })
+            .collect();
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    body
+}
+
+/// Keeps track of a streamed message, turning its events into deltas.
+#[derive(Default)]
+struct Events {
+    usage: Usage,
+    // Content block index -> position among the message's tool calls.
+    tool_blocks: HashMap<u64, usize>,
+}
+
+impl Events {
+    fn handle(&mut self, event: &Value) -> Result<Vec<Delta>, OpenAIError> {
+        let index = event.get("index").and_then(Value::as_u64).unwrap_or(0);
+        Ok(match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => {
+                let usage = &event["message"]["usage"];
+                let count = |field: &str| usage[field].as_u64().unwrap_or(0) as u32;
+                // input_tokens leaves out whatever was read from or written to the prompt cache.
+                self.usage.cached = count("cache_read_input_tokens");
+                self.usage.prompt = count("input_tokens")
+                    + self.usage.cached
+                    + count("cache_creation_input_tokens");
+                self.usage.completion = count("output_tokens");
+                Vec::new()
+            }
+            Some("content_block_start") => {
+                let block = &event["content_block"];
+                match block["type"].as_str() {
+                    Some("tool_use") => {
+                        let position = self.tool_blocks.len();
+                        self.tool_blocks.insert(index, position);
+                        vec![Delta::ToolCall {
+                            index: position,
+                            id: block["id"].as_str().map(str::to_string),
+                            name: block["name"].as_str().map(str::to_string),
+                            arguments: None,
+                        }]
+                    }
+                    Some("text") => match block["text"].as_str() {
+                        Some(text) if !text.is_empty() => vec![Delta::Text(text.to_string())],
+                        _ => Vec::new(),
+                    },
+                    _ => Vec::new(),
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        vec![Delta::Text(
+                            delta["text"].as_str().unwrap_or_default().to_string(),
+                        )]
+                    }
+                    Some("input_json_delta") => match self.tool_blocks.get(&index) {
+                        Some(&position) => vec![Delta::ToolCall {
+                            index: position,
+                            id: None,
+                            name: None,
+                            arguments: delta["partial_json"].as_str().map(str::to_string),
+                        }],
+                        None => Vec::new(),
+                    },
+                    _ => Vec::new(),
+                }
+            }
+            Some("message_delta") => {
+                if let Some(output) = event["usage"]["output_tokens"].as_u64() {
+                    self.usage.completion = output as u32;
+                }
+                match event["delta"]["stop_reason"].as_str() {
+                    Some(reason) => vec![Delta::Finish(reason.to_string())],
+                    None => Vec::new(),
+                }
+            }
+            Some("message_stop") => vec![Delta::Usage(self.usage)],
+            Some("error") => {
+                let error = &event["error"];
+                return Err(api_error(
+                    error["type"].as_str().unwrap_or("api_error"),
+                    error["message"]
+                        .as_str()
+                        .unwrap_or(msg::get(Msg::UnknownError))
+                        .to_string(),
+                ));
+            }
+            _ => Vec::new(),
+        })
+    }
+}
+
+/// Claude models, through the Messages API.
+pub struct Anthropic;
+
+impl Backend for Anthropic {
+    fn chat_url(&self, base: &str, _model: &str, _config: &Config) -> String {
+        format!("{base}/messages")
+    }
+
+    fn headers(&self, key: &str, _config: &Config) -> Vec<(&'static str, String)> {
+        vec![
+            ("x-api-key", key.to_string()),
+            ("anthropic-version", API_VERSION.to_string()),
+        ]
+    }
+
+    fn body(&self, request: &Value) -> Value {
+        serde_json::from_value::<CreateChatCompletionRequest>(request.clone())
+            .map_or_else(|_| request.clone(), |r| body(&r))
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: CreateChatCompletionRequest,
+        base: String,
+        config: &'a Config,
+    ) -> BoxFuture<'a, Result<DeltaStream, OpenAIError>> {
+        async move { stream(&request, &base, config).await }.boxed()
+    }
+}
+
+/// Sends a request and streams the answer back as deltas.
+async fn stream(
+    request: &CreateChatCompletionRequest,
+    api_base: &str,
+    config: &Config,
+) -> Result<DeltaStream, OpenAIError> {
+    let key = Provider::Anthropic.api_key().unwrap_or_default();
+    let response = Anthropic
+        .headers(&key, config)
+        .into_iter()
+        .fold(
+            http_client().post(Anthropic.chat_url(api_base, &request.model, config)),
+            |request, (name, value)| request.header(name, value),
+        )
+        .json(&body(request))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        let error: Value = serde_json::from_str(&text).unwrap_or_default();
+        return Err(api_error(
+            error["error"]["type"].as_str().unwrap_or("api_error"),
+            error["error"]["message"]
+                .as_str()
+                .map_or_else(|| format!("{status}: {text}"), str::to_string),
+        ));
+    }
+
+    // Parsed on a task and handed over through a channel; dropping the stream ends the task.
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut bytes = response.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut events = Events::default();
+        while let Some(chunk) = bytes.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    let _ = tx.send(Err(OpenAIError::StreamError(e.to_string())));
+                    return;
+                }
+            };
+            // Split on whole lines before decoding, so a character cut between chunks survives.
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim_end().strip_prefix("data:") else {
+                    continue;
+                };
+                let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+                    continue;
+                };
+                let deltas = match events.handle(&event) {
+                    Ok(deltas) => deltas,
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                };
+                for delta in deltas {
+                    if tx.send(Ok(delta)).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    Ok(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)).boxed())
+}
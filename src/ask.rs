@@ -0,0 +1,175 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestUserMessageArgs, ChatCompletionStreamOptions,
+        CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use std::io::{self, Write};
+
+use crate::{
+    cli::AskArgs,
+    config::Config,
+    error::{AppError, Result},
+    fsutil,
+    msg::{self, Msg},
+    provider, sensitive,
+    settings::MaxTokens,
+    tokens::estimate_prompt_tokens,
+    usage::Purpose,
+    window,
+    wrap::Wrapper,
+    Message,
+};
+
+/// Runs `gpt ask`: the files, each in a fence labelled with its name, then the question, in one
+/// request. Only the answer is printed.
+pub async fn run(
+    args: AskArgs,
+    client: &Client<OpenAIConfig>,
+    config: &Config,
+    model: &str,
+) -> Result<()> {
+    let prompt = prompt(&args, config)?;
+
+    let max_tokens = config
+        .max_tokens
+        .map_or(MaxTokens::Auto, MaxTokens::Fixed)
+        .resolve(model)
+        .0;
+    let mut counted = vec![Message::user(prompt.clone())];
+    let mut messages: Vec<ChatCompletionRequestMessage> = Vec::new();
+    if let Some(ref system) = args.system {
+        counted.push(Message::user(system.clone()));
+        messages.push(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system.as_str())
+                .build()?
+                .into(),
+        );
+    }
+    if let Some(message) =
+        window::overflow_message(model, estimate_prompt_tokens(model, &counted), max_tokens)
+    {
+        return Err(AppError::TooLong(message));
+    }
+    messages.push(
+        ChatCompletionRequestUserMessageArgs::default()
+            .content(prompt)
+            .build()?
+            .into(),
+    );
+
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .max_completion_tokens(max_tokens)
+        .messages(messages)
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()?;
+    let (answer, _) = provider::complete(client, request, config, Purpose::Answer).await?;
+    let mut out = io::stdout().lock();
+    writeln!(out, "{}", answer.trim_end())?;
+    Ok(())
+}
+
+/// The files, each in a fence labelled with its name, then the question, wrapped as the config
+/// says. Binary files are left out with a note.
+fn prompt(args: &AskArgs, config: &Config) -> Result<String> {
+    let mut prompt = String::new();
+    for path in &args.files {
+        sensitive::check(path, &config.sensitive_files, args.force)?;
+        if sensitive::file_is_binary(path).unwrap_or(false) {
+            eprintln!(
+                "{}",
+                msg::fmt(Msg::AskSkippedBinary, &[("path", &path.display())])
+            );
+            continue;
+        }
+        let text = fsutil::read_text(path).map_err(|e| {
+            AppError::Command(msg::fmt(
+                Msg::ReadFailed,
+                &[("path", &path.display()), ("error", &e)],
+            ))
+        })?;
+        prompt.push_str(&format!(
+            "{}:\n```\n{}\n```\n\n",
+            path.display(),
+            text.trim_end()
+        ));
+    }
+    prompt.push_str(&args.question.join(" "));
+    if let Some(wrapper) = Wrapper::from_config(config) {
+        prompt = wrapper.apply(&prompt);
+    }
+    Ok(prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Args, Command};
+    use clap::Parser;
+    use std::{fs, path::PathBuf, process};
+
+    fn parse(line: &[&str]) -> AskArgs {
+        match Args::try_parse_from(line).unwrap().command {
+            Some(Command::Ask(args)) => args,
+            other => panic!("not gpt ask: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn files_model_and_system_come_from_flags() {
+        let args = parse(&[
+            "gpt",
+            "ask",
+            "-f",
+            "Cargo.toml",
+            "--file",
+            "src/main.rs",
+            "-m",
+            "4o-mini",
+            "--system",
+            "be terse",
+            "why does this fail",
+            "to compile?",
+        ]);
+        assert_eq!(
+            args.files,
+            [PathBuf::from("Cargo.toml"), PathBuf::from("src/main.rs")]
+        );
+        assert_eq!(args.model.as_deref(), Some("4o-mini"));
+        assert_eq!(args.system.as_deref(), Some("be terse"));
+        assert_eq!(args.question, ["why does this fail", "to compile?"]);
+    }
+
+    #[test]
+    fn a_question_is_needed() {
+        assert!(Args::try_parse_from(["gpt", "ask", "-f", "Cargo.toml"]).is_err());
+    }
+
+    #[test]
+    fn each_file_is_fenced_under_its_name_before_the_question() {
+        let dir = std::env::temp_dir().join(format!("gpt-cli-ask-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (first, second) = (dir.join("a.toml"), dir.join("b.rs"));
+        fs::write(&first, "[package]\n\n").unwrap();
+        fs::write(&second, "fn main() {}\n").unwrap();
+        let mut args = parse(&["gpt", "ask", "what", "is", "this?"]);
+        args.files = vec![first.clone(), second.clone()];
+        let prompt = prompt(&args, &Config::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            prompt,
+            format!(
+                "{}:\n```\n[package]\n```\n\n{}:\n```\nfn main() {{}}\n```\n\nwhat is this?",
+                first.display(),
+                second.display()
+            )
+        );
+    }
+}
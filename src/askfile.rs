@@ -0,0 +1,310 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestUserMessageArgs, ChatCompletionStreamOptions,
+        CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use std::{io::Write, ops::Range, path::PathBuf};
+
+use crate::{
+    chat::{self, status_writer},
+    error::{AppError, Result},
+    input, models,
+    msg::{self, Msg},
+    provider, sensitive, signals, style,
+    tokens::count_tokens,
+    usage::{self, fmt_thousands, Purpose},
+    AppState, Message,
+};
+
+// How much of the end of one chunk is repeated at the start of the next.
+const OVERLAP_TOKENS: u32 = 200;
+// Room for the instructions and the question around each chunk.
+const PROMPT_MARGIN: u32 = 500;
+// Chunks are sized to the window, but models without one get something modest.
+const DEFAULT_CHUNK_TOKENS: u32 = 8000;
+// Asked of a pasted document that doesn't end in a short question.
+const DEFAULT_QUESTION: &str = "Summarise this document.";
+
+/// What `:ask-file` was asked.
+#[derive(Debug)]
+pub struct AskFile {
+    pub path: PathBuf,
+    /// Send it even if it looks like it holds secrets, as `!` in front of the path asks.
+    pub force: bool,
+    pub question: String,
+}
+
+/// Answers a question about a file too big to send in one request: the question is asked of every
+/// chunk, then the answers are combined in a final request, which is the only one shown as it streams.
+/// The context is left as it was.
+pub async fn run(ask: AskFile, client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<()> {
+    let saved = std::mem::take(&mut state.context);
+    let result = tokio::select! {
+        result = ask_file(&ask, client, state) => result,
+        _ = signals::interrupt() => Err(AppError::Cancelled),
+    };
+    state.context = saved;
+    result
+}
+
+async fn ask_file(
+    ask: &AskFile,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let text = sensitive::read(&ask.path, &state.config.sensitive_files, ask.force)?;
+    let name = ask.path.display().to_string();
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    answer_in_parts(&name, &lines, &ask.question, client, state).await
+}
+
+/// Offers to answer the message just added to the context in parts, as `:ask-file` does, when it's
+/// too big for the model's window on its own. The question is its last paragraph. Answered that
+/// way, the message is replaced in the context by the question, and followed by the answer; the
+/// document itself is left out because it wouldn't fit. Returns whether it was answered.
+pub async fn offer_parts(client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<bool> {
+    let Some(info) = models::lookup(&state.model) else {
+        return Ok(false);
+    };
+    let Some(message) = state.context.last().filter(|m| m.is_user()) else {
+        return Ok(false);
+    };
+    let tokens = count_tokens(&state.model, &message.content);
+    if !state.interactive || tokens + state.max_tokens() <= info.context_window {
+        return Ok(false);
+    }
+    let message = message.content.clone();
+    let (document, question) = split_question(&state.model, &message)?;
+    // Pasted text can be a few huge lines, which have to be split to fit.
+    let lines = split_long_lines(&state.model, document, chunk_budget(state))?;
+    let parts = chunk_lines(&state.model, &lines, chunk_budget(state), OVERLAP_TOKENS).len();
+    if parts <= 1 {
+        return Ok(false);
+    }
+
+    let saved = std::mem::take(&mut state.context);
+    let result = tokio::select! {
+        result = async {
+            println!(
+                "{}",
+                msg::fmt(
+                    Msg::AskFileTooLong,
+                    &[
+                        ("tokens", &fmt_thousands(tokens as u64)),
+                        ("model", &state.model),
+                        ("window", &fmt_thousands(info.context_window as u64)),
+                    ],
+                )
+            );
+            let prompt = msg::fmt(
+                Msg::AskFileInParts,
+                &[
+                    ("parts", &parts),
+                    ("question", &truncate(question, 60)),
+                    ("requests", &(parts + 1)),
+                ],
+            );
+            let answer = input::ask(state, &prompt).await?;
+            if !answer.is_some_and(|a| a.eq_ignore_ascii_case("u")) {
+                return Ok(false);
+            }
+            answer_in_parts("the pasted document", &lines, question, client, state).await?;
+            Ok(true)
+        } => result,
+        _ = signals::interrupt() => Err(AppError::Cancelled),
+    };
+    let answer = std::mem::replace(&mut state.context, saved).pop();
+    if let (Ok(true), Some(answer)) = (&result, answer.filter(|m| !m.is_user())) {
+        if let Some(last) = state.context.last_mut() {
+            last.content = question.to_string();
+        }
+        state.context.push(answer);
+    }
+    result
+}
+
+/// Splits a pasted message into the document and the question after it, which is its last
+/// paragraph if that's short. Otherwise the document is summarised.
+fn split_question<'a>(model: &str, message: &'a str) -> Result<(&'a str, &'a str)> {
+    let message = message.trim_end();
+    if let Some((document, question)) = message.rsplit_once("\n\n") {
+        if count_tokens(model, question) <= PROMPT_MARGIN {
+            return Ok((document, question.trim()));
+        }
+    }
+    Ok((message, DEFAULT_QUESTION))
+}
+
+/// The lines of `text`, with any longer than `budget` tokens split at spaces into pieces that fit.
+fn split_long_lines<'a>(model: &str, text: &'a str, budget: u32) -> Result<Vec<&'a str>> {
+    // Nearly every script takes at least a token per three bytes.
+    let max_bytes = budget as usize * 3;
+    let mut lines = Vec::new();
+    for line in text.split_inclusive('\n') {
+        let mut rest = line;
+        if count_tokens(model, line) <= budget {
+            lines.push(line);
+            continue;
+        }
+        while rest.len() > max_bytes {
+            let mut end = max_bytes;
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            if let Some(space) = rest[..end].rfind(' ').filter(|&i| i > 0) {
+                end = space + 1;
+            }
+            lines.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        lines.push(rest);
+    }
+    Ok(lines)
+}
+
+fn truncate(text: &str, chars: usize) -> String {
+    match text.char_indices().nth(chars) {
+        Some((i, _)) => format!("{}...", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+/// How many tokens of the text go in each request, leaving room for the answer and the prompt.
+fn chunk_budget(state: &AppState) -> u32 {
+    match models::lookup(&state.model) {
+        Some(info) => info
+            .context_window
+            .saturating_sub(state.max_tokens() + PROMPT_MARGIN)
+            .max(OVERLAP_TOKENS * 4),
+        None => DEFAULT_CHUNK_TOKENS,
+    }
+}
+
+/// Asks `question` of every chunk of the lines, then combines the answers in a final request, which
+/// is the only one shown as it streams. Leaves the context as the final request and its answer.
+async fn answer_in_parts(
+    name: &str,
+    lines: &[&str],
+    question: &str,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let chunks = chunk_lines(&state.model, lines, chunk_budget(state), OVERLAP_TOKENS);
+
+    // A file that fits is just sent with the question.
+    if chunks.len() <= 1 {
+        let text = lines.concat();
+        state.context = vec![Message::user(format!(
+            "{name}:\n```\n{text}\n```\n\n{question}"
+        ))];
+        return chat::stream_response(client, state).await;
+    }
+
+    let mut status = status_writer(state.stdout_tty);
+    let mut notes = String::new();
+    let before = usage::session().clone();
+    for (i, range) in chunks.iter().enumerate() {
+        // Progress always goes to stderr, even in a terminal, so it never ends up in the answer.
+        eprintln!(
+            "{}",
+            style::dim(&format!("(chunk {}/{})", i + 1, chunks.len()), state.color)
+        );
+        let prompt = format!(
+            "This is part {} of {} of {name}, lines {}-{}:\n```\n{}\n```\n\nAnswer the question below using only this part, quoting line numbers where they help. If this part has nothing relevant, reply \"Nothing relevant.\"\n\nQuestion: {}",
+            i + 1,
+            chunks.len(),
+            range.start + 1,
+            range.end,
+            lines[range.clone()].concat(),
+            question
+        );
+        let request = CreateChatCompletionRequestArgs::default()
+            .model(state.get_model())
+            .max_completion_tokens(state.max_tokens())
+            .messages([ChatCompletionRequestUserMessageArgs::default()
+                .content(prompt)
+                .build()?
+                .into()])
+            .stream_options(ChatCompletionStreamOptions {
+                include_usage: true,
+            })
+            .build()?;
+        let (answer, _) =
+            provider::complete(client, request, &state.config, Purpose::Answer).await?;
+        notes.push_str(&format!(
+            "Part {} (lines {}-{}):\n{}\n\n",
+            i + 1,
+            range.start + 1,
+            range.end,
+            answer.trim()
+        ));
+    }
+
+    state.context = vec![Message::user(format!(
+        "These are notes from reading {name} in {} parts to answer a question. Some parts may have had nothing relevant.\n\n{notes}Using these notes, answer the question, citing line numbers where they help.\n\nQuestion: {}",
+        chunks.len(),
+        question
+    ))];
+    chat::stream_response(client, state).await?;
+    let stats = usage::session().clone();
+    let tokens = stats.prompt_tokens + stats.completion_tokens
+        - (before.prompt_tokens + before.completion_tokens);
+    let total = msg::fmt(
+        Msg::AskFileTotal,
+        &[
+            ("requests", &(stats.requests - before.requests)),
+            ("tokens", &fmt_thousands(tokens)),
+            (
+                "cost",
+                &state.config.currency.format(stats.cost - before.cost),
+            ),
+        ],
+    );
+    writeln!(status, "{}", style::dim(&total, state.color))?;
+    Ok(())
+}
+
+/// Splits the lines into chunks of at most `budget` tokens of `model`, preferring to end a chunk at a blank
+/// line, which in code usually falls between functions. Up to `overlap` tokens of each chunk are
+/// repeated at the start of the next, so nothing is only seen cut in half.
+pub fn chunk_lines(model: &str, lines: &[&str], budget: u32, overlap: u32) -> Vec<Range<usize>> {
+    let tokens: Vec<u32> = lines.iter().map(|line| count_tokens(model, line)).collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut sum = 0;
+        let mut last_blank = None;
+        while end < lines.len() && sum + tokens[end] <= budget {
+            sum += tokens[end];
+            end += 1;
+            if lines[end - 1].trim().is_empty() {
+                last_blank = Some(end);
+            }
+        }
+        // Ending at a blank line is only worth it if it doesn't make the chunk much smaller.
+        if end < lines.len() {
+            if let Some(blank) = last_blank.filter(|b| *b > start + (end - start) / 2) {
+                end = blank;
+            }
+        }
+        // A single line over the budget still has to go somewhere.
+        end = end.max(start + 1);
+        chunks.push(start..end);
+        if end == lines.len() {
+            break;
+        }
+
+        let mut next = end;
+        let mut repeated = 0;
+        while next > start + 1 && repeated + tokens[next - 1] <= overlap {
+            next -= 1;
+            repeated += tokens[next];
+        }
+        start = next;
+    }
+    chunks
+}
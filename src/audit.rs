@@ -0,0 +1,143 @@
+use async_openai::types::CreateChatCompletionRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{env, fmt::Display, io, path::PathBuf, time::Duration};
+
+use crate::{
+    fsutil,
+    msg::{self, Msg},
+    schema,
+    usage::Usage,
+};
+
+/// The `[audit]` table of the config. Nothing is logged unless `path` is set.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// The JSONL file every request is appended to. It can be shared by several users.
+    pub path: Option<PathBuf>,
+    /// Also log what was sent and received, as `content_policy` says.
+    pub content_logging: bool,
+    pub content_policy: ContentPolicy,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentPolicy {
+    /// SHA-256 of each message, enough to match a request to a transcript without storing it.
+    #[default]
+    Hash,
+    Full,
+}
+
+/// One line of the log.
+#[derive(Serialize)]
+struct Entry<'a> {
+    timestamp: String,
+    user: String,
+    model: &'a str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    /// USD, or null when the model's prices aren't known.
+    price: Option<f64>,
+    duration_ms: u128,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<Content>,
+}
+
+#[derive(Serialize)]
+struct Content {
+    policy: ContentPolicy,
+    /// Each message as sent, or `{role, sha256}` when hashing.
+    messages: Vec<Value>,
+    /// The response text, or its hash.
+    response: String,
+}
+
+/// What happened to one request, for [`record`].
+pub struct Outcome<'a> {
+    pub usage: &'a Usage,
+    pub duration: Duration,
+    pub response: &'a str,
+    pub error: Option<&'a dyn Display>,
+}
+
+/// Appends a request to the audit log, if there is one. A log that can't be written is reported
+/// on stderr rather than failing the request.
+pub fn record(config: &AuditConfig, request: &CreateChatCompletionRequest, outcome: Outcome) {
+    let Some(ref path) = config.path else {
+        return;
+    };
+    let entry = Entry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        user: user(),
+        model: &request.model,
+        prompt_tokens: outcome.usage.prompt,
+        completion_tokens: outcome.usage.completion,
+        price: outcome.usage.price(&request.model),
+        duration_ms: outcome.duration.as_millis(),
+        success: outcome.error.is_none(),
+        error: outcome.error.map(|e| e.to_string()),
+        content: config
+            .content_logging
+            .then(|| content(config.content_policy, request, outcome.response)),
+    };
+    let line = schema::encode(&entry, false).map_err(io::Error::from);
+    if let Err(e) = line.and_then(|line| fsutil::append_line(path, &line)) {
+        eprintln!(
+            "{}",
+            msg::fmt(
+                Msg::AuditNotWritten,
+                &[("path", &path.display()), ("error", &e)]
+            )
+        );
+    }
+}
+
+// The OS user, which on a shared host says who made the request.
+fn user() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn sha256(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn content(
+    policy: ContentPolicy,
+    request: &CreateChatCompletionRequest,
+    response: &str,
+) -> Content {
+    let messages = request
+        .messages
+        .iter()
+        .map(|message| {
+            let value = serde_json::to_value(message).unwrap_or(Value::Null);
+            match policy {
+                ContentPolicy::Full => value,
+                ContentPolicy::Hash => serde_json::json!({
+                    "role": value["role"],
+                    "sha256": sha256(&value["content"].to_string()),
+                }),
+            }
+        })
+        .collect();
+    let response = match policy {
+        ContentPolicy::Full => response.to_string(),
+        ContentPolicy::Hash => sha256(response),
+    };
+    Content {
+        policy,
+        messages,
+        response,
+    }
+}
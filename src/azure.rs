@@ -0,0 +1,215 @@
+//! Azure OpenAI: OpenAI's requests, sent to a deployment of the model on an Azure resource. A base
+//! URL on one of Azure's hosts is taken to be one, whatever the provider, so `api_bases` can list
+//! Azure regions alongside OpenAI's own endpoint. Each request goes to the deployment the
+//! `[azure]` table names for its model, with the table's `api_version`.
+
+use async_openai::{
+    config::AzureConfig as Deployment, error::OpenAIError, types::CreateChatCompletionRequest,
+    Client,
+};
+use futures::{future::BoxFuture, FutureExt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, env};
+
+use crate::{
+    config::Config,
+    provider::{self, Backend, DeltaStream},
+};
+
+/// The environment variable holding the key for Azure endpoints. `[api_keys]` has it as `azure`.
+pub const KEY_VAR: &str = "AZURE_OPENAI_API_KEY";
+
+// Azure's own resource hosts; anything else, like a proxy in front of one, is sent OpenAI's way.
+const HOSTS: &[&str] = &[
+    ".openai.azure.com",
+    ".cognitiveservices.azure.com",
+    ".services.ai.azure.com",
+];
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AzureConfig {
+    /// The API version every request asks for.
+    pub api_version: String,
+    /// The deployment that serves each model, for those not deployed under the model's own name.
+    pub deployments: BTreeMap<String, String>,
+}
+
+impl Default for AzureConfig {
+    fn default() -> Self {
+        AzureConfig {
+            api_version: "2024-10-21".to_string(),
+            deployments: BTreeMap::new(),
+        }
+    }
+}
+
+impl AzureConfig {
+    /// The deployment requests for `model` go to.
+    pub fn deployment<'a>(&'a self, model: &'a str) -> &'a str {
+        self.deployments.get(model).map_or(model, String::as_str)
+    }
+}
+
+/// Whether `base` is an Azure resource's URL.
+pub fn is_azure(base: &str) -> bool {
+    reqwest::Url::parse(base)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        .is_some_and(|host| HOSTS.iter().any(|suffix| host.ends_with(suffix)))
+}
+
+/// The key for Azure endpoints: its environment variable, or else `azure` in `[api_keys]`.
+pub fn api_key() -> Option<String> {
+    env::var(KEY_VAR)
+        .ok()
+        .or_else(|| provider::config_key("azure"))
+}
+
+/// Where the resource at `base` lists its models, for checking the key.
+pub fn models_url(base: &str, config: &Config) -> String {
+    format!(
+        "{base}/openai/models?api-version={}",
+        config.azure.api_version
+    )
+}
+
+/// A client for the deployment of `model` on the resource at `base`.
+pub fn client(base: &str, model: &str, config: &Config) -> Client<Deployment> {
+    Client::with_config(
+        Deployment::new()
+            .with_api_base(base.trim_end_matches('/'))
+            .with_api_version(&config.azure.api_version)
+            .with_deployment_id(config.azure.deployment(model))
+            .with_api_key(api_key().unwrap_or_default()),
+    )
+}
+
+/// Chat completions from a deployment, which take OpenAI's requests as they are.
+pub struct Azure;
+
+impl Backend for Azure {
+    fn chat_url(&self, base: &str, model: &str, config: &Config) -> String {
+        format!(
+            "{base}/openai/deployments/{}/chat/completions?api-version={}",
+            config.azure.deployment(model),
+            config.azure.api_version
+        )
+    }
+
+    fn headers(&self, key: &str, _config: &Config) -> Vec<(&'static str, String)> {
+        vec![("api-key", key.to_string())]
+    }
+
+    fn body(&self, request: &Value) -> Value {
+        let mut body = request.clone();
+        body["stream"] = Value::Bool(true);
+        body
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: CreateChatCompletionRequest,
+        base: String,
+        config: &'a Config,
+    ) -> BoxFuture<'a, Result<DeltaStream, OpenAIError>> {
+        async move {
+            let client = client(&base, &request.model, config);
+            Ok(provider::deltas(
+                client.chat().create_stream(request).await?,
+            ))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Delta;
+    use futures::StreamExt;
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    fn config() -> Config {
+        Config {
+            azure: AzureConfig {
+                deployments: BTreeMap::from([("gpt-4o".to_string(), "chat-4o".to_string())]),
+                ..AzureConfig::default()
+            },
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn only_azure_hosts_are_azure() {
+        assert!(is_azure("https://eastus.openai.azure.com"));
+        assert!(is_azure("https://West-Europe.OpenAI.Azure.com/"));
+        assert!(is_azure("https://mine.cognitiveservices.azure.com"));
+        assert!(!is_azure("https://api.openai.com/v1"));
+        assert!(!is_azure("https://openai.azure.com.example.com/v1"));
+        assert!(!is_azure("not a url"));
+    }
+
+    #[test]
+    fn a_model_goes_to_its_deployment() {
+        let config = config();
+        assert_eq!(
+            Azure.chat_url("https://east.openai.azure.com", "gpt-4o", &config),
+            "https://east.openai.azure.com/openai/deployments/chat-4o/chat/completions?api-version=2024-10-21"
+        );
+        // Deployed under its own name.
+        assert_eq!(config.azure.deployment("gpt-4o-mini"), "gpt-4o-mini");
+        assert_eq!(Azure.headers("k", &config), [("api-key", "k".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn a_request_is_sent_to_the_deployment_with_the_api_version() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&head).contains("\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                head.extend_from_slice(&buf[..n]);
+            }
+            let chunk = r#"{"id":"1","object":"chat.completion.chunk","created":0,"model":"gpt-4o","choices":[{"index":0,"delta":{"content":"ok"},"finish_reason":null}]}"#;
+            let body = format!("data: {chunk}\n\ndata: [DONE]\n\n");
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&head).to_string()
+        });
+
+        let request = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+        }))
+        .unwrap();
+        let config = config();
+        let mut deltas = Azure.stream(request, base, &config).await.unwrap();
+        let mut text = String::new();
+        while let Some(delta) = deltas.next().await {
+            if let Delta::Text(t) = delta.unwrap() {
+                text.push_str(&t);
+            }
+        }
+        assert_eq!(text, "ok");
+        let head = server.await.unwrap();
+        assert!(
+            head.starts_with(
+                "POST /openai/deployments/chat-4o/chat/completions?api-version=2024-10-21 "
+            ),
+            "{head}"
+        );
+        assert!(head.to_ascii_lowercase().contains("\r\napi-key:"), "{head}");
+    }
+}
@@ -0,0 +1,202 @@
+//! Bookmarked messages, kept in one file for every session and project. Each has a copy of the
+//! message, so it still shows after the session is deleted or changed, and keeps its number when
+//! others are deleted.
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fs, io, path::PathBuf};
+
+use crate::{
+    cli::BookmarksAction,
+    error::{AppError, Result},
+    fsutil,
+    msg::{self, Msg},
+    paths, schema, shellcmd, style, Message,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Bookmark {
+    /// Never reused, so a number seen in a list stays right.
+    pub id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created: DateTime<Utc>,
+    /// The session it was in, if that had been saved or loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Where it was in the context, as `:show` numbers it.
+    pub index: usize,
+    pub role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Bookmarks {
+    /// The number the next bookmark gets.
+    next: u32,
+    bookmarks: Vec<Bookmark>,
+}
+
+fn file() -> PathBuf {
+    paths::data_dir().join("bookmarks.json")
+}
+
+fn load() -> Result<Bookmarks> {
+    let path = file();
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Bookmarks::default()),
+        Err(e) => return Err(e.into()),
+    };
+    schema::decode(&text).map_err(|e| AppError::Command(e.describe(&path.display().to_string())))
+}
+
+fn save(bookmarks: &Bookmarks) -> Result<()> {
+    paths::ensure_dir(&paths::data_dir())?;
+    let json = schema::encode(bookmarks, true).map_err(io::Error::from)?;
+    fsutil::write_atomic(&file(), &json)?;
+    Ok(())
+}
+
+/// Bookmarks message `index` of the context, returning its number.
+pub fn add(
+    context: &[Message],
+    index: usize,
+    label: Option<String>,
+    session: Option<String>,
+) -> Result<u32> {
+    let msg = context.get(index).ok_or_else(|| {
+        AppError::Command(msg::fmt(
+            Msg::NoSuchMessage,
+            &[("index", &index), ("count", &context.len())],
+        ))
+    })?;
+    let mut file = load()?;
+    let id = file.next.max(1);
+    file.next = id + 1;
+    file.bookmarks.push(Bookmark {
+        id,
+        label,
+        created: Utc::now(),
+        session,
+        index,
+        role: msg.role().to_string(),
+        model: msg.model.clone(),
+        text: msg.content().to_string(),
+    });
+    save(&file)?;
+    Ok(id)
+}
+
+/// The sessions with a bookmark in them, which `gpt gc` never removes.
+pub fn sessions() -> Result<BTreeSet<String>> {
+    Ok(load()?
+        .bookmarks
+        .into_iter()
+        .filter_map(|b| b.session)
+        .collect())
+}
+
+/// Runs `gpt bookmarks` and `:bookmarks`: without an action, lists them.
+pub fn run(action: Option<BookmarksAction>, color: bool) -> Result<()> {
+    let mut file = load()?;
+    let bookmarks = &mut file.bookmarks;
+    let find = |bookmarks: &[Bookmark], id: u32| {
+        bookmarks
+            .iter()
+            .position(|b| b.id == id)
+            .ok_or_else(|| AppError::Command(msg::fmt(Msg::NoSuchBookmark, &[("id", &id)])))
+    };
+    match action {
+        None => {
+            if bookmarks.is_empty() {
+                println!("{}", msg::get(Msg::NoBookmarks));
+            }
+            for bookmark in bookmarks.iter() {
+                print_line(bookmark, color);
+            }
+        }
+        Some(BookmarksAction::Show { id }) => {
+            let bookmark = &bookmarks[find(bookmarks, id)?];
+            print_line(bookmark, color);
+            println!("{}", bookmark.text.trim_end());
+        }
+        Some(BookmarksAction::Copy { id }) => {
+            let bookmark = &bookmarks[find(bookmarks, id)?];
+            let tool = shellcmd::copy_to_clipboard(&bookmark.text).map_err(|e| {
+                AppError::Command(msg::fmt(
+                    Msg::BookmarkNotCopied,
+                    &[("id", &id), ("error", &e)],
+                ))
+            })?;
+            println!(
+                "{}",
+                msg::fmt(Msg::BookmarkCopied, &[("id", &id), ("tool", &tool)])
+            );
+        }
+        Some(BookmarksAction::Delete { id }) => {
+            let removed = bookmarks.remove(find(bookmarks, id)?);
+            save(&file)?;
+            println!(
+                "{}",
+                msg::fmt(
+                    Msg::BookmarkDeleted,
+                    &[("id", &id), ("summary", &summary(&removed))]
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parses what follows `:bookmarks`: nothing, or `show`, `copy` or `delete` and a number.
+pub fn parse_action(args: &str) -> Option<Option<BookmarksAction>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let action = match words.as_slice() {
+        [] => return Some(None),
+        ["show", id] => BookmarksAction::Show {
+            id: id.parse().ok()?,
+        },
+        ["copy", id] => BookmarksAction::Copy {
+            id: id.parse().ok()?,
+        },
+        ["delete", id] => BookmarksAction::Delete {
+            id: id.parse().ok()?,
+        },
+        _ => return None,
+    };
+    Some(Some(action))
+}
+
+fn summary(bookmark: &Bookmark) -> String {
+    match bookmark.label {
+        Some(ref label) => label.clone(),
+        None => {
+            let first = bookmark.text.lines().find(|l| !l.trim().is_empty());
+            let first = first.unwrap_or_default().trim();
+            match first.chars().count() > 60 {
+                true => format!("{}…", first.chars().take(59).collect::<String>()),
+                false => first.to_string(),
+            }
+        }
+    }
+}
+
+fn print_line(bookmark: &Bookmark, color: bool) {
+    let from = match bookmark.session {
+        Some(ref session) => format!("{session} [{}]", bookmark.index),
+        None => format!("unsaved [{}]", bookmark.index),
+    };
+    println!(
+        "{:>3}  {}  {}  {}",
+        bookmark.id,
+        bookmark
+            .created
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M"),
+        summary(bookmark),
+        style::dim(&format!("({from})"), color)
+    );
+}
@@ -0,0 +1,127 @@
+//! `conversation_budget`: a soft limit on what a conversation costs. Before each message is sent,
+//! its worst case, the whole context plus as long an answer as it may get, is priced. One that
+//! could go over the budget is either asked about or, with `downgrade_on_budget`, sent to a
+//! cheaper model.
+
+use serde::Deserialize;
+use std::io::Write;
+
+use crate::{
+    chat::status_writer,
+    error::{AppError, Result},
+    input, models,
+    msg::{self, Msg},
+    style,
+    tokens::estimate_prompt_tokens,
+    usage::{self, Usage},
+    AppState,
+};
+
+/// What the budget is counted against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetScope {
+    /// Everything since the REPL started or `:new`, as `:stats` shows it.
+    #[default]
+    Session,
+    /// The answers in the context, so clearing it starts again.
+    Context,
+}
+
+impl BudgetScope {
+    pub fn parse(value: &str) -> Option<BudgetScope> {
+        match value {
+            "session" => Some(BudgetScope::Session),
+            "context" => Some(BudgetScope::Context),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BudgetScope::Session => "session",
+            BudgetScope::Context => "context",
+        }
+    }
+}
+
+/// What's been spent so far against the budget, in USD.
+pub fn spent(state: &AppState) -> f64 {
+    match state.config.budget_scope {
+        BudgetScope::Session => usage::session().cost,
+        BudgetScope::Context => state.context.iter().filter_map(|m| m.cost_usd).sum(),
+    }
+}
+
+/// What's left of the budget in USD, or `None` without one. It's negative once it's been gone
+/// over.
+pub fn left(state: &AppState) -> Option<f64> {
+    let budget = state.config.conversation_budget?;
+    Some(budget / state.config.currency.rate - spent(state))
+}
+
+/// The most the next request could cost with `model`, or `None` if its price isn't known.
+fn worst_case(state: &AppState, model: &str) -> Result<Option<f64>> {
+    let usage = Usage {
+        prompt: estimate_prompt_tokens(model, &state.context),
+        cached: 0,
+        completion: state.max_tokens.resolve(model).0,
+    };
+    Ok(usage.price(model))
+}
+
+/// Checks the message at the end of the context against the budget. Returns the model to send it
+/// to instead, if it's been downgraded, and an error if it shouldn't be sent at all.
+pub async fn check(state: &mut AppState) -> Result<Option<String>> {
+    let Some(left) = left(state) else {
+        return Ok(None);
+    };
+    let Some(cost) = worst_case(state, &state.model)? else {
+        return Ok(None);
+    };
+    if cost <= left {
+        return Ok(None);
+    }
+    let currency = &state.config.currency;
+    let over = msg::fmt(
+        Msg::BudgetOver,
+        &[
+            ("cost", &currency.format(cost)),
+            ("left", &currency.format(left.max(0.0))),
+            (
+                "budget",
+                &currency.format(state.config.conversation_budget.unwrap_or(0.0) / currency.rate),
+            ),
+        ],
+    );
+    if state.config.downgrade_on_budget {
+        let cheap = state
+            .config
+            .budget_model
+            .clone()
+            .unwrap_or_else(|| state.config.routing.cheap_model.clone());
+        let cheap = models::resolve(&cheap).unwrap_or(cheap);
+        let fits = cheap != state.model && worst_case(state, &cheap)?.is_some_and(|c| c <= left);
+        if fits {
+            let notice = msg::fmt(
+                Msg::BudgetDowngrade,
+                &[("model", &state.model), ("over", &over), ("cheap", &cheap)],
+            );
+            writeln!(
+                status_writer(state.stdout_tty),
+                "{}",
+                style::dim(&notice, state.color)
+            )?;
+            return Ok(Some(cheap));
+        }
+    }
+    let question = msg::fmt(Msg::BudgetAsk, &[("over", &over)]);
+    match input::ask(state, &question).await?.as_deref() {
+        Some("y" | "Y" | "yes") => Ok(None),
+        Some(_) => Err(AppError::Command(msg::get(Msg::BudgetNotSent).to_string())),
+        None => Err(AppError::Command(msg::fmt(
+            Msg::BudgetNotSentNoTty,
+            &[("over", &over)],
+        ))),
+    }
+}
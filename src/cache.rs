@@ -0,0 +1,84 @@
+use async_openai::types::CreateChatCompletionRequest;
+use serde::{Deserialize, Serialize};
+
+use crate::{fsutil, paths, schema};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CachedResponse {
+    pub created: u64,
+    pub content: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        ResponseCache { dir, ttl }
+    }
+
+    /// Hashes everything that affects the answer: model, messages, and sampling parameters.
+    pub fn key(request: &CreateChatCompletionRequest) -> String {
+        let json = serde_json::to_vec(request).unwrap_or_default();
+        let digest = Sha256::digest(&json);
+        digest.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let text = fs::read_to_string(self.path(key)).ok()?;
+        // One from a newer gpt is a miss, to be overwritten by this one's answer.
+        let entry: CachedResponse = schema::decode(&text).ok()?;
+        if now().saturating_sub(entry.created) > self.ttl.as_secs() {
+            let _ = fs::remove_file(self.path(key));
+            return None;
+        }
+        Some(entry)
+    }
+
+    pub fn put(&self, key: &str, content: &str) -> io::Result<()> {
+        paths::ensure_dir(&self.dir)?;
+        let entry = CachedResponse {
+            created: now(),
+            content: content.to_string(),
+        };
+        fsutil::write_atomic(&self.path(key), &schema::encode(&entry, false)?)
+    }
+
+    /// Removes every cached response, returning how many were deleted.
+    pub fn clear(&self) -> io::Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
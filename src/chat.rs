@@ -0,0 +1,1281 @@
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{
+        ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestToolMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionStreamOptions, ChatCompletionTool, ChatCompletionToolType,
+        CreateChatCompletionRequest, CreateChatCompletionRequestArgs, FunctionCall, FunctionObject,
+    },
+    Client,
+};
+use futures::StreamExt;
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    askfile,
+    audit::{self, Outcome},
+    budget,
+    cache::ResponseCache,
+    config::OnStop,
+    curl, emit,
+    error::{AppError, Result},
+    fallback,
+    filter::Filter,
+    footer::{self, Footer, Timing},
+    grapheme::GraphemeBuffer,
+    input, large, ledger,
+    math::{MathMode, MathWriter},
+    models,
+    msg::{self, Msg},
+    notify, overrides, preset,
+    provider::{self, Choice, Delta},
+    route, seed, signals,
+    softwrap::SoftWrap,
+    stage,
+    streamto::StreamFile,
+    style, subst,
+    tee::Tee,
+    titles,
+    tokens::{self, count_tokens, estimate_prompt_tokens, Drift},
+    tools::Tools,
+    usage::{fmt_thousands, Purpose, Usage},
+    vars, window,
+    wrap::Wrapper,
+    AppState, Message, Role,
+};
+
+// Stops a model that keeps calling tools without ever answering.
+const MAX_TOOL_ROUNDS: u32 = 8;
+// A connection that keeps dropping isn't going to get better.
+const MAX_RESUMES: u32 = 3;
+const RESUME_PROMPT: &str = "Your last answer was cut off. Continue exactly where it stopped, without repeating anything or commenting on the interruption.";
+
+/// What one request to one model produced.
+struct Attempt {
+    text: String,
+    /// Escape was pressed before the response finished.
+    stopped: bool,
+    usage: Option<Usage>,
+    error: Option<OpenAIError>,
+    tool_calls: Vec<ChatCompletionMessageToolCall>,
+    refusal: String,
+    finish_reason: Option<String>,
+    /// The base URL that answered, see [`crate::endpoints`].
+    endpoint: Option<String>,
+    /// Answers besides the first, when the provider sent more than one.
+    others: Vec<Choice>,
+}
+
+pub async fn send_prompt(
+    mut input: String,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let keep_context = input.starts_with('|') || state.auto_pipe;
+    if input.starts_with('|') {
+        input.remove(0);
+    }
+    // Checked before anything is sent, and undone once the answer is in.
+    let (overrides, input) = overrides::parse(&input)?;
+    if overrides.is_empty() {
+        return send_expanded(input, keep_context, client, state).await;
+    }
+    if input.trim().is_empty() {
+        return Err(AppError::Command(
+            msg::get(Msg::NothingAfterOverrides).to_string(),
+        ));
+    }
+    let saved = overrides::apply(state, &overrides)?;
+    state.overrides = Some(overrides::describe(&overrides));
+    let result = send_expanded(input, keep_context, client, state).await;
+    state.overrides = None;
+    overrides::restore(state, saved);
+    result
+}
+
+/// Expands variables, `$(command)` and the quote in a message, and sends it.
+async fn send_expanded(
+    input: String,
+    keep_context: bool,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let (mut input, undefined) = vars::substitute(&input, &state.vars);
+    if !undefined.is_empty() {
+        let notice = msg::fmt(Msg::UndefinedVars, &[("names", &undefined.join(", "))]);
+        writeln!(
+            status_writer(state.stdout_tty),
+            "{}",
+            style::dim(&notice, state.color)
+        )?;
+    }
+    // Expanded before anything else changes, so a failing command leaves the context alone.
+    if std::mem::take(&mut state.subst_once) || state.config.substitution {
+        input = subst::expand(&input)?;
+    }
+    if let Some(limit) = state.config.confirm_send_over_tokens {
+        let tokens = count_tokens(&state.model, &input);
+        if tokens > limit {
+            let question = msg::fmt(
+                Msg::ConfirmLong,
+                &[
+                    ("tokens", &fmt_thousands(tokens as u64)),
+                    ("limit", &fmt_thousands(limit as u64)),
+                ],
+            );
+            // Nobody there to ask, like with piped input, means it goes.
+            let answer = input::ask(state, &question).await?;
+            if answer.is_some_and(|a| !a.eq_ignore_ascii_case("y")) {
+                println!("{}", msg::get(Msg::NotSent));
+                return Ok(());
+            }
+        }
+    }
+    // The quote is sent as it is, like the answer it came from.
+    if let Some(quote) = state.quote.take() {
+        input = format!("{quote}\n\n{input}");
+    }
+
+    send(input, keep_context, client, state).await
+}
+
+/// Sends a message as it is, in the current conversation when `keep_context` is set and in a new one otherwise.
+pub async fn send(
+    input: String,
+    keep_context: bool,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    // A selection from :send-only is of the context, so it's kept.
+    let selection = state.send_only.take();
+    // Without context, the previous conversation is cleared. Otherwise, we keep building it.
+    let mut dropped = false;
+    if !keep_context && selection.is_none() {
+        // Files staged for this message aren't part of the conversation it leaves behind.
+        let staged = stage::take_pending(&mut state.context);
+        if state.config.warn_on_context_drop && !state.context.is_empty() {
+            let notice = match state.context.len() {
+                1 => msg::get(Msg::ContextDroppedOne).to_string(),
+                n => msg::fmt(Msg::ContextDropped, &[("count", &n)]),
+            };
+            writeln!(
+                status_writer(state.stdout_tty),
+                "{}",
+                style::dim(&notice, state.color)
+            )?;
+        }
+        dropped = !state.context.is_empty();
+        state.drop_context();
+        state.context = staged;
+    }
+    let wrapper = match state.wrap_prompt {
+        true => Wrapper::from_config(&state.config),
+        false => None,
+    };
+    state.context.push(Message {
+        wrapper,
+        ..Message::user(input)
+    });
+    let index = state.context.len() - 1;
+
+    // Ctrl+C cancels the response, which is then handled like any other failed request.
+    let result = match askfile::offer_parts(client, state).await {
+        Ok(true) => Ok(()),
+        Ok(false) => match budget::check(state).await {
+            Ok(downgrade) => {
+                // Only this message goes to the cheaper model.
+                let saved = downgrade.map(|model| {
+                    let model = std::mem::replace(&mut state.model, model);
+                    (model, std::mem::replace(&mut state.auto_route, false))
+                });
+                let (system, seeded) = (system_prompt(state), seed::messages(state));
+                let result = tokio::select! {
+                    result = stream_messages(client, state, selection, system, seeded) => result,
+                    _ = signals::interrupt() => Err(AppError::Cancelled),
+                };
+                if let Some((model, auto_route)) = saved {
+                    state.model = model;
+                    state.auto_route = auto_route;
+                }
+                result
+            }
+            Err(err) => Err(err),
+        },
+        Err(err) => Err(err),
+    };
+    if let Err(ref err) = result {
+        // Only says anything when an answer had started.
+        emit::error(&err.to_string());
+    }
+    // A dry run took the question back itself, having sent nothing.
+    let unsent = result.is_ok() && provider::dry_run();
+    if unsent || result.is_err() && state.context.last().is_some_and(Message::is_user) {
+        // Nothing came back, so don't keep a question without an answer, and put back what it replaced.
+        if !unsent {
+            state.context.pop();
+        }
+        if dropped {
+            // The files staged for it are left for the next try.
+            let staged = std::mem::take(&mut state.context);
+            state.context = state.last_dropped.take().unwrap_or_default();
+            state.context.extend(staged);
+        }
+    } else {
+        state.exchanges += 1;
+        if result.is_ok() {
+            large::shrink(client, state, index).await;
+        }
+        titles::maybe_start(client, state);
+    }
+    match result {
+        Ok(()) if !unsent => check_expected(client, state).await,
+        result => result,
+    }
+}
+
+/// Warns when the answer isn't what `:expect` asked for, and with `expect_retry` asks once more.
+async fn check_expected(client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<()> {
+    let Some(expect) = state.expect.clone() else {
+        return Ok(());
+    };
+    let problem = |state: &AppState| {
+        let answer = state.context.last().filter(|m| !m.is_user())?;
+        expect.check(&answer.content)
+    };
+    let Some(first) = problem(state) else {
+        return Ok(());
+    };
+    let mut status = status_writer(state.stdout_tty);
+    let warning = msg::fmt(
+        Msg::ExpectFailed,
+        &[("shape", &expect.name()), ("problem", &first)],
+    );
+    writeln!(status, "{}", style::highlight(&warning, state.color))?;
+    if !state.config.expect_retry {
+        state.expect_failed = true;
+        return Ok(());
+    }
+    writeln!(
+        status,
+        "{}",
+        style::dim(msg::get(Msg::AskingAgain), state.color)
+    )?;
+    state.context.push(Message::user(expect.correction(&first)));
+    let result = tokio::select! {
+        result = stream_response(client, state) => result,
+        _ = signals::interrupt() => Err(AppError::Cancelled),
+    };
+    if result.is_err() && state.context.last().is_some_and(Message::is_user) {
+        state.context.pop();
+        return result;
+    }
+    if let Some(again) = problem(state) {
+        let warning = msg::fmt(Msg::SecondAnswerWrong, &[("problem", &again)]);
+        writeln!(status, "{}", style::highlight(&warning, state.color))?;
+        state.expect_failed = true;
+    }
+    result
+}
+
+pub async fn stream_response(client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<()> {
+    let (system, seeded) = (system_prompt(state), seed::messages(state));
+    stream_messages(client, state, None, system, seeded).await
+}
+
+/// Like [`stream_response`], with only `system` before the context: not the project's prompt, the
+/// seed or the preset's instruction, which are the conversation's. For requests made apart from
+/// it, like `:duo`'s and `:translate`'s.
+pub async fn stream_with_system(
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+    system: Option<String>,
+) -> Result<()> {
+    stream_messages(client, state, None, system, Vec::new()).await
+}
+
+/// What goes before the context: the project's prompt and files, read now so they're current, then
+/// the seed's system prompt if the conversation started from one, then the preset's instruction.
+pub fn system_prompt(state: &AppState) -> Option<String> {
+    let system = state
+        .project
+        .as_ref()
+        .and_then(|p| p.system_prompt(&state.config.sensitive_files));
+    let system = match (system, seed::system(state)) {
+        (Some(system), Some(seed)) => Some(format!("{system}\n\n{seed}")),
+        (system, seed) => system.or(seed.map(str::to_string)),
+    };
+    match (system, preset::instruction(state)) {
+        (Some(system), Some(instruction)) => Some(format!("{system}\n\n{instruction}")),
+        (system, instruction) => system.or(instruction),
+    }
+}
+
+/// Streams the answer to the context. With a `selection` of indices, only those messages are sent,
+/// followed by the last one, the message being answered; the rest stay in the context.
+async fn stream_messages(
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+    selection: Option<Vec<usize>>,
+    system: Option<String>,
+    seeded: Vec<Message>,
+) -> Result<()> {
+    if state.auto_route {
+        let decision = route::decide(client, state).await?;
+        state.model = decision.model.clone();
+        state.last_route = Some(decision);
+    }
+    // What's sent besides the context, counted when fitting the window as well.
+    let mut overhead = 0;
+    if !seeded.is_empty() {
+        overhead += estimate_prompt_tokens(&state.model, &seeded) - tokens::TOKENS_PER_REPLY;
+    }
+    if let Some(ref system) = system {
+        overhead += count_tokens(&state.model, system);
+    }
+
+    let selected: Vec<Message>;
+    // Where each message sent is in the context, for errors about one of them.
+    let positions: Vec<usize>;
+    let (context, sent) = match selection {
+        Some(indices) => {
+            let stored = state.context.len().saturating_sub(1);
+            if let Some(i) = indices.iter().find(|&&i| i >= stored) {
+                return Err(AppError::Command(msg::fmt(
+                    Msg::NoLongerInContext,
+                    &[("index", i), ("count", &stored)],
+                )));
+            }
+            selected = indices
+                .iter()
+                .map(|&i| state.context[i].clone())
+                .chain(state.context.last().cloned())
+                .collect();
+            let estimate = estimate_prompt_tokens(&state.model, &selected) + overhead;
+            if let Some(message) =
+                window::overflow_message(&state.model, estimate, state.max_tokens())
+            {
+                return Err(AppError::TooLong(message));
+            }
+            positions = indices.iter().copied().chain([stored]).collect();
+            (selected.as_slice(), Some((indices.len(), stored)))
+        }
+        None => {
+            let start = window::fit_context(state, overhead).await?;
+            if start > 0 {
+                println!("{}", msg::fmt(Msg::Trimmed, &[("count", &start)]));
+            }
+            positions = (start..state.context.len()).collect();
+            (&state.context[start..], None)
+        }
+    };
+
+    let mut messages = convert_context(&seeded, &[])?;
+    messages.extend(convert_context(context, &positions)?);
+    if let Some(ref system) = system {
+        messages.insert(
+            0,
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system.as_str())
+                .build()?
+                .into(),
+        );
+    }
+
+    // Get input tokens
+    let input_tokens = estimate_prompt_tokens(&state.model, context) + overhead;
+
+    let mut request = CreateChatCompletionRequestArgs::default()
+        .model(state.get_model())
+        .max_completion_tokens(state.max_tokens())
+        .messages(messages)
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()?;
+    request.temperature = state
+        .temperature
+        .filter(|_| !models::fixed_temperature(&state.model));
+    // Tools are part of the request, so they're attached before it's hashed for the cache.
+    request.tools = tool_definitions(&state.tools);
+
+    if provider::dry_run() {
+        // The request as it would have gone, kept for :curl and :diff-request like any other.
+        state.requests.record(&request);
+        if let Some(request) = state.requests.last() {
+            println!("{}", curl::command(request, &state.config));
+        }
+        // Like a failed request, a question without its answer isn't kept.
+        if state.context.last().is_some_and(Message::is_user) {
+            state.context.pop();
+        }
+        return Ok(());
+    }
+
+    // Math is only rewritten for a terminal, so piped output is what the model wrote.
+    let math = match state.stdout_tty {
+        true => state.config.math,
+        false => MathMode::Off,
+    };
+    let shown = match state.stdout_tty && state.config.word_wrap {
+        true => SoftWrap::terminal(io::stdout().lock()),
+        false => SoftWrap::new(io::stdout().lock(), None),
+    };
+    let mut out = MathWriter::new(shown, math);
+    let mut status = status_writer(state.stdout_tty);
+    let mut tee = state.tee.as_ref().map(Tee::begin).transpose()?;
+    let stream_target = state.stream_to.take();
+    let mut stream_to = stream_target.as_ref().map(StreamFile::begin).transpose()?;
+    if let Some(ref file) = stream_to {
+        let notice = msg::fmt(Msg::StreamingTo, &[("path", &file.path().display())]);
+        writeln!(status, "{}", style::dim(&notice, state.color))?;
+    }
+
+    let filter = state.filter.clone();
+    // Unless teeing, only the filter's output is shown. With :stream-to --quiet, only the file's.
+    let hidden =
+        filter.as_ref().is_some_and(|f| !f.tee) || stream_target.as_ref().is_some_and(|t| t.quiet);
+    let mut sink = io::sink();
+
+    let cache_key = ResponseCache::key(&request);
+    if state.config.cache {
+        if let Some(cached) = state.cache.get(&cache_key) {
+            if !hidden {
+                writeln!(out, "{}", cached.content)?;
+                out.finish()?;
+            }
+            if let Some(ref filter) = filter {
+                show_filtered(
+                    filter,
+                    &cached.content,
+                    out.get_mut(),
+                    &mut status,
+                    state.color,
+                )
+                .await?;
+            }
+            if let Some(mut tee) = tee {
+                tee.write(&cached.content)?;
+                tee.finish()?;
+            }
+            if let Some(mut file) = stream_to {
+                file.write(&cached.content)?;
+                file.finish(&msg::fmt(Msg::StreamFromCache, &[("model", &state.model)]))?;
+            }
+            if state.stdout_tty {
+                writeln!(out)?;
+            }
+            let usage = Usage {
+                prompt: input_tokens,
+                cached: 0,
+                completion: count_tokens(&state.model, &cached.content),
+            };
+            emit::start(&state.model);
+            emit::delta(&cached.content);
+            emit::done(&state.model, &usage);
+            let estimated = tokens::approximate(&state.model);
+            state.context.push(Message {
+                model: Some(state.model.clone()),
+                tokens: Some(usage.completion),
+                cost_usd: Some(0.0),
+                ..Message::assistant(cached.content)
+            });
+            let footer = Footer {
+                usage: &usage,
+                model: &state.model,
+                from_cache: true,
+                resumed: false,
+                routed: state.auto_route,
+                sent,
+                reused: None,
+                currency: &state.config.currency,
+                timing: None,
+                estimated,
+                overrides: state.overrides.as_deref(),
+                drift: None,
+            };
+            footer::print(&mut status, &footer, state.footer)?;
+            writeln!(status)?;
+            return Ok(());
+        }
+    }
+
+    state.requests.record(&request);
+    if state.print_curl {
+        if let Some(request) = state.requests.last() {
+            let command = curl::command(request, &state.config);
+            writeln!(status_writer(state.stdout_tty), "{command}\n")?;
+        }
+    }
+    let reused = match state.prefix.compare(&request) {
+        Some((reuse, previous)) => {
+            if let Some(warning) = reuse.warning(previous) {
+                writeln!(status, "{}", style::dim(&warning, state.color))?;
+            }
+            Some(reuse)
+        }
+        None => None,
+    };
+
+    // The model that answers: the current one, unless it fails and a fallback takes over.
+    let mut model = state.model.clone();
+    let mut fallbacks = fallback::chain(&state.model, &state.config.fallback_models).into_iter();
+    let mut timing = Timing::start();
+    let mut response_save = String::new();
+    let mut usage = Usage::default();
+    // Whether any of it had to be estimated without the tokenizer.
+    let mut estimated = false;
+    let mut tool_rounds = 0;
+    let mut resumes = 0;
+    let mut stopped = false;
+    // Where the answer came from, or its last part when it was resumed elsewhere.
+    let mut endpoint = None;
+    // How the estimate compared with the prompt tokens billed, and for which model.
+    let mut drift = None;
+    let mut others = Vec::new();
+    emit::start(&model);
+    let stream_error = loop {
+        request.model = model.clone();
+        request.temperature = state
+            .temperature
+            .filter(|_| !models::fixed_temperature(&model));
+        let attempt_start = Instant::now();
+        let mut attempt = stream_once(
+            client,
+            request.clone(),
+            state,
+            &mut timing,
+            if hidden { &mut sink } else { &mut out },
+            &mut tee,
+            &mut stream_to,
+        )
+        .await?;
+        endpoint = attempt.endpoint.take().or(endpoint);
+        // Prefer the usage reported by the API, falling back to local estimates if the stream died early.
+        let attempt_usage = match attempt.usage {
+            Some(usage) => usage,
+            None => {
+                estimated |= tokens::approximate(&model);
+                Usage {
+                    prompt: input_tokens + count_tokens(&model, &response_save),
+                    cached: 0,
+                    completion: count_tokens(&model, &attempt.text),
+                }
+            }
+        };
+        // Only the first request of an answer is made of what was estimated: a resumed or tool-call
+        // round adds to it. Tool definitions aren't counted in the estimate, and without the
+        // tokenizer it's known to be rough, so neither is compared.
+        let estimate = (resumes == 0
+            && tool_rounds == 0
+            && request.tools.is_none()
+            && !tokens::approximate(&model))
+        .then_some(input_tokens)
+        .filter(|_| attempt.usage.is_some());
+        if let Some(estimate) = estimate {
+            drift = Some((
+                model.clone(),
+                Drift {
+                    estimate,
+                    actual: attempt_usage.prompt,
+                },
+            ));
+        }
+        // A request that failed without an answer isn't billed, so don't log an estimate for it.
+        let failed = attempt.error.is_some() && attempt.text.is_empty() && attempt.usage.is_none();
+        let outcome = Outcome {
+            usage: if failed {
+                &Usage::default()
+            } else {
+                &attempt_usage
+            },
+            duration: attempt_start.elapsed(),
+            response: &attempt.text,
+            error: attempt.error.as_ref().map(|e| e as &dyn Display),
+        };
+        audit::record(&state.config.audit, &request, outcome);
+        if !failed {
+            ledger::record(&state.config, &attempt_usage, &model, estimate);
+        }
+        others.append(&mut attempt.others);
+        if let Some(err) = attempt
+            .error
+            .take_if(|_| attempt.text.is_empty() && attempt.tool_calls.is_empty())
+        {
+            // Only a request that produced nothing, and isn't continuing an answer, is retried,
+            // so an answer is never stitched from two models.
+            if let Some(next) = fallbacks
+                .next()
+                .filter(|_| resumes == 0 && fallback::is_retryable(&err))
+            {
+                let notice = msg::fmt(
+                    Msg::FallingBack,
+                    &[("model", &model), ("error", &err), ("next", &next)],
+                );
+                writeln!(status, "{notice}")?;
+                model = next;
+                continue;
+            }
+            if response_save.is_empty() {
+                return Err(err.into());
+            }
+            break Some(err);
+        }
+        usage.add(&attempt_usage);
+        response_save.push_str(&attempt.text);
+        if let Some(err) = attempt
+            .error
+            .take_if(|err| resumes < MAX_RESUMES && fallback::is_resumable(err))
+        {
+            if !should_resume(state, &err, &mut status).await? {
+                break Some(err);
+            }
+            // The model picks up from its own partial answer, which the continuation is appended to.
+            resumes += 1;
+            request.messages.push(
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .content(attempt.text)
+                    .build()?
+                    .into(),
+            );
+            request.messages.push(
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(RESUME_PROMPT)
+                    .build()?
+                    .into(),
+            );
+            continue;
+        }
+        if attempt.stopped {
+            stopped = true;
+            break None;
+        }
+        if attempt.error.is_some() || attempt.tool_calls.is_empty() {
+            break attempt.error;
+        }
+        if tool_rounds == MAX_TOOL_ROUNDS {
+            let notice = msg::fmt(Msg::ToolRoundsStopped, &[("rounds", &MAX_TOOL_ROUNDS)]);
+            writeln!(status, "{notice}")?;
+            break None;
+        }
+        tool_rounds += 1;
+
+        // Run the tools and ask again with their results, until the model answers in words.
+        request.messages.push(
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .content(attempt.text)
+                .tool_calls(attempt.tool_calls.clone())
+                .build()?
+                .into(),
+        );
+        // Anything held back is shown before the notices.
+        out.finish()?;
+        for call in attempt.tool_calls {
+            let notice = msg::fmt(Msg::Calling, &[("tool", &call.function.name)]);
+            writeln!(status, "{}", style::dim(&notice, state.color))?;
+            let result = state
+                .tools
+                .call(&call.function.name, &call.function.arguments)
+                .await
+                .unwrap_or_else(|e| format!("Error: {e}"));
+            request.messages.push(
+                ChatCompletionRequestToolMessageArgs::default()
+                    .content(result)
+                    .tool_call_id(call.id)
+                    .build()?
+                    .into(),
+            );
+        }
+    };
+    timing.finish();
+    match stream_error {
+        Some(ref err) => emit::error(&err.to_string()),
+        None => emit::done(&model, &usage),
+    }
+    if !hidden {
+        writeln!(out)?;
+        if state.stdout_tty {
+            writeln!(out)?;
+        }
+        out.finish()?;
+    }
+    for choice in &others {
+        writeln!(status, "{}", style::dim(&other_choice(choice), state.color))?;
+    }
+    let keep = !stopped || keep_stopped(state, &mut status).await?;
+    if let Some(ref filter) = filter.filter(|_| keep && !response_save.is_empty()) {
+        show_filtered(
+            filter,
+            &response_save,
+            out.get_mut(),
+            &mut status,
+            state.color,
+        )
+        .await?;
+        if state.stdout_tty {
+            writeln!(out)?;
+        }
+    }
+    // An interrupted response is dropped from the file rather than committed.
+    if let Some(tee) = tee.filter(|_| stream_error.is_none() && keep) {
+        tee.finish()?;
+    }
+    if let Some(file) = stream_to {
+        let path = file.path().display().to_string();
+        match stream_error {
+            Some(ref err) => file.interrupt(&err.to_string())?,
+            None if stopped => file.interrupt(msg::get(Msg::StoppedWithEscape))?,
+            None => {
+                let price = match usage.price(&model) {
+                    Some(price) => state.config.currency.format(price),
+                    None => msg::get(Msg::PriceUnknown).to_string(),
+                };
+                file.finish(&msg::fmt(
+                    Msg::StreamFinished,
+                    &[
+                        ("model", &model),
+                        ("prompt", &fmt_thousands(usage.prompt as u64)),
+                        ("completion", &fmt_thousands(usage.completion as u64)),
+                        ("price", &price),
+                    ],
+                ))?;
+                let notice = msg::fmt(Msg::AnswerIn, &[("path", &path)]);
+                writeln!(status, "{}", style::dim(&notice, state.color))?;
+            }
+        }
+    }
+    // A fallback's answer isn't what the original request would have got, so it isn't cached under it.
+    // Neither is one that used tools, whose results can change, or one that was stopped.
+    if state.config.cache
+        && stream_error.is_none()
+        && !stopped
+        && model == state.model
+        && tool_rounds == 0
+    {
+        if let Err(e) = state.cache.put(&cache_key, &response_save) {
+            writeln!(
+                status,
+                "{}",
+                msg::fmt(Msg::CacheWriteFailed, &[("error", &e)])
+            )?;
+        }
+    }
+    notify::response_finished(
+        state.config.notify_after_secs,
+        timing.total(),
+        &model,
+        &response_save,
+    );
+    if keep {
+        state.context.push(Message {
+            model: Some(model.clone()),
+            duration_ms: Some(timing.total().as_millis() as u64),
+            tokens: Some(usage.completion),
+            prompt_tokens: Some(usage.prompt),
+            cost_usd: usage.price(&model),
+            endpoint,
+            ..Message::assistant(response_save)
+        });
+    } else if state.context.last().is_some_and(Message::is_user) {
+        // Like a failed request, a question without its answer isn't kept.
+        state.context.pop();
+    }
+
+    let footer = Footer {
+        usage: &usage,
+        model: &model,
+        from_cache: false,
+        resumed: resumes > 0,
+        routed: state.auto_route,
+        sent,
+        reused,
+        currency: &state.config.currency,
+        timing: Some(&timing),
+        estimated,
+        overrides: state.overrides.as_deref(),
+        drift: drift
+            .as_ref()
+            .filter(|_| resumes == 0 && tool_rounds == 0)
+            .map(|(_, drift)| *drift),
+    };
+    footer::print(&mut status, &footer, state.footer)?;
+    if let Some((model, drift)) = drift {
+        warn_drift(state, &model, drift, &mut status)?;
+    }
+    writeln!(status)?;
+    match stream_error {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+/// Says, once for each model, when its prompt estimate was far from what was billed.
+fn warn_drift(
+    state: &mut AppState,
+    model: &str,
+    drift: Drift,
+    status: &mut impl Write,
+) -> Result<()> {
+    if !drift.worth_reporting() || state.drift_warned.contains(model) {
+        return Ok(());
+    }
+    state.drift_warned.insert(model.to_string());
+    let notice = msg::fmt(
+        Msg::Drift,
+        &[
+            ("model", &model),
+            ("drift", &drift.describe()),
+            ("url", &tokens::ISSUES),
+        ],
+    );
+    writeln!(status, "{}", style::dim(&notice, state.color))?;
+    Ok(())
+}
+
+/// Shows a finished response through the filter. If the filter fails, the raw response is shown
+/// instead, unless it already was.
+async fn show_filtered(
+    filter: &Filter,
+    text: &str,
+    out: &mut impl Write,
+    status: &mut impl Write,
+    color: bool,
+) -> Result<()> {
+    match filter.apply(text).await {
+        Ok(filtered) => writeln!(out, "{}", filtered.trim_end())?,
+        Err(e) => {
+            let notice = msg::fmt(Msg::FilterFailed, &[("error", &e)]);
+            writeln!(status, "{}", style::dim(&notice, color))?;
+            if !filter.tee {
+                writeln!(out, "{text}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether to keep an answer stopped with Escape in the context, as `on_stop` says or the user
+/// answers. Without anyone to ask, it's kept.
+async fn keep_stopped(state: &mut AppState, status: &mut impl Write) -> Result<bool> {
+    writeln!(
+        status,
+        "{}",
+        style::dim(msg::get(Msg::Stopped), state.color)
+    )?;
+    Ok(match state.config.on_stop {
+        OnStop::Keep => true,
+        OnStop::Drop => false,
+        OnStop::Ask => {
+            let answer = input::ask(state, msg::get(Msg::KeepPartial)).await?;
+            !answer.is_some_and(|a| a.eq_ignore_ascii_case("d"))
+        }
+    })
+}
+
+/// Whether to ask for the rest of an answer whose stream dropped partway: always with `auto_resume`,
+/// otherwise if the user says so.
+async fn should_resume(
+    state: &mut AppState,
+    err: &OpenAIError,
+    status: &mut impl Write,
+) -> Result<bool> {
+    let notice = msg::fmt(Msg::StreamDropped, &[("error", err)]);
+    writeln!(status, "\n{}", style::dim(&notice, state.color))?;
+    if state.config.auto_resume {
+        return Ok(true);
+    }
+    let answer = input::ask(state, msg::get(Msg::ResumeOrStop)).await?;
+    Ok(answer.is_some_and(|a| a.eq_ignore_ascii_case("r")))
+}
+
+/// The messages as the API takes them. A message that can't be converted is reported by its
+/// position in the context, from `positions`, rather than ending the session.
+fn convert_context(
+    context: &[Message],
+    positions: &[usize],
+) -> Result<Vec<ChatCompletionRequestMessage>> {
+    let mut res = Vec::with_capacity(context.len());
+
+    for (i, ctx) in context.iter().enumerate() {
+        let new = convert_message(ctx).map_err(|e| {
+            let index = positions.get(i).copied().unwrap_or(i);
+            AppError::Command(msg::fmt(
+                Msg::MessageNotSendable,
+                &[("index", &index), ("error", &e)],
+            ))
+        })?;
+        res.push(new);
+    }
+
+    Ok(res)
+}
+
+fn convert_message(
+    message: &Message,
+) -> std::result::Result<ChatCompletionRequestMessage, OpenAIError> {
+    // Only a hand-edited session or seed gets one this far. The API would turn the whole request
+    // down without saying which message it was.
+    if message.is_user() && message.sent().trim().is_empty() {
+        return Err(OpenAIError::InvalidArgument(
+            msg::get(Msg::MessageHasNoText).to_string(),
+        ));
+    }
+    Ok(match message {
+        Message {
+            role: Role::User, ..
+        } => ChatCompletionRequestUserMessageArgs::default()
+            .content(message.sent().into_owned())
+            .build()?
+            .into(),
+        Message {
+            role: Role::Assistant,
+            content,
+            ..
+        } => ChatCompletionRequestAssistantMessageArgs::default()
+            .content(content.as_str())
+            .build()?
+            .into(),
+    })
+}
+
+/// Resolves once `after` has passed, or never without it.
+async fn stall_timer(after: Option<Duration>) {
+    match after {
+        Some(after) => tokio::time::sleep(after).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn stalled(after: Option<Duration>) -> OpenAIError {
+    let secs = after.unwrap_or_default().as_secs();
+    OpenAIError::StreamError(msg::fmt(Msg::NothingArrived, &[("secs", &secs)]))
+}
+
+/// Resolves when Escape or Ctrl+C is pressed.
+async fn pressed(typing: &mut Option<input::Typing>) -> input::Pressed {
+    match typing {
+        Some(typing) => typing.pressed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Where footers and warnings go: stdout in a terminal, stderr when stdout is piped so they don't mix with the answer.
+pub fn status_writer(stdout_tty: bool) -> Box<dyn Write> {
+    if stdout_tty {
+        Box::new(io::stdout())
+    } else {
+        Box::new(io::stderr())
+    }
+}
+
+// Requests only ever ask for one answer, so any other a provider sends is mentioned and dropped.
+fn other_choice(choice: &Choice) -> String {
+    match choice.refusal.is_empty() {
+        true => msg::fmt(
+            Msg::OtherChoice,
+            &[
+                ("index", &choice.index),
+                ("chars", &choice.text.chars().count()),
+                ("reason", &choice.finish_reason.as_deref().unwrap_or("none")),
+            ],
+        ),
+        false => msg::fmt(
+            Msg::OtherChoiceRefused,
+            &[("index", &choice.index), ("refusal", &choice.refusal)],
+        ),
+    }
+}
+
+/// Streams one request, printing its text as it arrives and collecting any tool calls.
+async fn stream_once(
+    client: &Client<OpenAIConfig>,
+    request: CreateChatCompletionRequest,
+    state: &mut AppState,
+    timing: &mut Timing,
+    out: &mut dyn Write,
+    tee: &mut Option<Tee>,
+    stream_to: &mut Option<StreamFile>,
+) -> Result<Attempt> {
+    let mut attempt = Attempt {
+        text: String::new(),
+        stopped: false,
+        usage: None,
+        error: None,
+        tool_calls: Vec::new(),
+        refusal: String::new(),
+        finish_reason: None,
+        endpoint: None,
+        others: Vec::new(),
+    };
+    let mut stream = provider::stream(client, request, &state.config, Purpose::Answer);
+
+    let mut typing = input::Typing::start(state.interactive);
+    // Newlines go back to the start of the line while the keys are being read.
+    let mut out = input::RawLines(out);
+    let stall_after = (state.config.stall_after_secs > 0)
+        .then(|| Duration::from_secs(state.config.stall_after_secs));
+    // The wait for the first chunk isn't timed, since reasoning models can think for minutes first.
+    let mut started = false;
+    let mut warned = false;
+    let mut graphemes = GraphemeBuffer::default();
+    // Whitespace the answer starts with is held back until some text follows it, so an answer of
+    // nothing else prints nothing before it's reported as empty.
+    let mut shown = false;
+    loop {
+        let timer = stall_after.filter(|_| started && !warned);
+        let result = tokio::select! {
+            result = stream.next() => Some(result),
+            _ = stall_timer(timer) => None,
+            key = pressed(&mut typing) => {
+                if let input::Pressed::Interrupt = key {
+                    return Err(AppError::Cancelled);
+                }
+                // After a stall has been reported, giving up on the stream lets it be resumed.
+                match warned {
+                    true => attempt.error = Some(stalled(stall_after)),
+                    false => attempt.stopped = true,
+                }
+                break;
+            }
+        };
+        let Some(result) = result else {
+            // Someone at the keyboard can decide whether it's worth waiting for; otherwise it's
+            // dropped like a broken connection.
+            if !state.interactive {
+                attempt.error = Some(stalled(stall_after));
+                break;
+            }
+            let notice = msg::fmt(Msg::Stalled, &[("secs", &state.config.stall_after_secs)]);
+            write!(out, "{}", graphemes.finish())?;
+            let newline = if graphemes.column() > 0 { "\n" } else { "" };
+            writeln!(out, "{newline}{}", style::dim(&notice, state.color))?;
+            out.flush()?;
+            warned = true;
+            continue;
+        };
+        started = true;
+        warned = false;
+        let Some(result) = result else {
+            break;
+        };
+        match result {
+            Ok(Delta::Text(mut content)) => {
+                timing.chunk();
+                attempt.text.push_str(&content);
+                if !shown {
+                    if attempt.text.trim().is_empty() {
+                        continue;
+                    }
+                    shown = true;
+                    content = attempt.text.clone();
+                }
+                write!(out, "{}", graphemes.push(&content))?;
+                if let Some(ref mut tee) = tee {
+                    tee.write(&content)?;
+                }
+                if let Some(ref mut file) = stream_to {
+                    file.write(&content)?;
+                }
+                emit::delta(&content);
+            }
+            Ok(Delta::ToolCall {
+                index,
+                id,
+                name,
+                arguments,
+            }) => {
+                while attempt.tool_calls.len() <= index {
+                    attempt.tool_calls.push(ChatCompletionMessageToolCall {
+                        id: String::new(),
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionCall {
+                            name: String::new(),
+                            arguments: String::new(),
+                        },
+                    });
+                }
+                let call = &mut attempt.tool_calls[index];
+                call.id.push_str(id.as_deref().unwrap_or_default());
+                call.function
+                    .name
+                    .push_str(name.as_deref().unwrap_or_default());
+                call.function
+                    .arguments
+                    .push_str(arguments.as_deref().unwrap_or_default());
+            }
+            Ok(Delta::Usage(usage)) => attempt.usage = Some(usage),
+            Ok(Delta::Refusal(refusal)) => attempt.refusal.push_str(&refusal),
+            Ok(Delta::Finish(reason)) => attempt.finish_reason = Some(reason),
+            Ok(Delta::Candidate(choice)) => attempt.others.push(choice),
+            Ok(Delta::Endpoint(base)) => {
+                attempt.endpoint = Some(base);
+            }
+            Err(err) => {
+                // The underlying event source would keep reconnecting, so give up on the first error.
+                attempt.error = Some(err);
+                break;
+            }
+        }
+        out.flush()?;
+    }
+    write!(out, "{}", graphemes.finish())?;
+    out.flush()?;
+    // Some providers answer a blocked prompt with nothing at all rather than an error. Whitespace
+    // counts as nothing, so it never becomes an empty turn in the context.
+    if !attempt.stopped
+        && attempt.error.is_none()
+        && attempt.text.trim().is_empty()
+        && attempt.tool_calls.is_empty()
+    {
+        attempt.text.clear();
+        attempt.error = Some(if attempt.refusal.is_empty() {
+            let reason = attempt.finish_reason.as_deref().unwrap_or("none");
+            OpenAIError::StreamError(msg::fmt(Msg::EmptyResponse, &[("reason", &reason)]))
+        } else {
+            provider::refused(&attempt.refusal)
+        });
+    }
+    Ok(attempt)
+}
+
+/// What the model is told about each enabled tool, or None when none are.
+fn tool_definitions(tools: &Tools) -> Option<Vec<ChatCompletionTool>> {
+    let tools: Vec<ChatCompletionTool> = tools
+        .enabled()
+        .map(|tool| ChatCompletionTool {
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionObject {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: Some(tool.schema.clone()),
+                strict: None,
+            },
+        })
+        .collect();
+    (!tools.is_empty()).then_some(tools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noted(mut message: Message, note: &str) -> Message {
+        message.note = Some(note.to_string());
+        message
+    }
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::user("What's 2 + 2?".to_string()),
+            Message::assistant("5".to_string()),
+            Message::user("Are you sure?".to_string()),
+        ]
+    }
+
+    fn tool(name: &str) -> crate::tools::Tool {
+        crate::tools::Tool {
+            name: name.to_string(),
+            source: crate::tools::Source::Plugin(name.into()),
+            description: None,
+            schema: serde_json::json!({"type": "object"}),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn tools_change_the_cache_key() {
+        let key = |tools: &Tools| {
+            let mut request = CreateChatCompletionRequestArgs::default()
+                .model("gpt-4o")
+                .messages(convert_context(&conversation(), &[]).unwrap())
+                .build()
+                .unwrap();
+            request.tools = tool_definitions(tools);
+            ResponseCache::key(&request)
+        };
+        let none = Tools::default();
+        let mut weather = Tools::default();
+        weather.add(tool("weather"));
+        let mut search = Tools::default();
+        search.add(tool("search"));
+        assert_ne!(key(&none), key(&weather));
+        assert_ne!(key(&weather), key(&search));
+
+        // A disabled tool isn't sent, so it doesn't count.
+        weather.tools[0].enabled = false;
+        assert_eq!(key(&none), key(&weather));
+    }
+
+    #[test]
+    fn notes_are_not_sent() {
+        let plain = conversation();
+        let mut with_notes = plain.clone();
+        with_notes[1] = noted(with_notes[1].clone(), "wrong, see the docs");
+        with_notes[2] = noted(with_notes[2].clone(), "asked again");
+
+        let sent = convert_context(&with_notes, &[1, 2, 3]).unwrap();
+        assert_eq!(
+            serde_json::to_value(&sent).unwrap(),
+            serde_json::to_value(convert_context(&plain, &[1, 2, 3]).unwrap()).unwrap()
+        );
+        let json = serde_json::to_string(&sent).unwrap();
+        assert!(!json.contains("docs") && !json.contains("asked again"));
+    }
+
+    #[test]
+    fn a_message_that_cannot_be_built_is_named() {
+        let mut context = conversation();
+        context[2].content = " \n".to_string();
+        let err = convert_context(&context, &[4, 5, 6]).unwrap_err();
+        assert!(matches!(err, AppError::Command(_)));
+        assert!(
+            err.to_string().starts_with("Message 6 couldn't be sent"),
+            "{err}"
+        );
+        // Without positions, it's the index in what was converted.
+        let err = convert_context(&context[1..], &[]).unwrap_err();
+        assert!(err.to_string().starts_with("Message 1 "), "{err}");
+    }
+
+    #[test]
+    fn an_empty_answer_is_still_sent() {
+        // As kept when a response is stopped before anything arrived.
+        let mut context = conversation();
+        context[1].content.clear();
+        assert_eq!(convert_context(&context, &[]).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn notes_are_not_counted() {
+        let plain = conversation();
+        let with_notes: Vec<Message> = plain
+            .iter()
+            .map(|m| {
+                noted(
+                    m.clone(),
+                    "a fairly long note that would cost tokens if it were sent",
+                )
+            })
+            .collect();
+        assert_eq!(
+            tokens::estimate_prompt_tokens("gpt-4o", &with_notes),
+            tokens::estimate_prompt_tokens("gpt-4o", &plain)
+        );
+    }
+
+    #[test]
+    fn notes_are_saved_with_the_message() {
+        let message = noted(Message::assistant("5".to_string()), "wrong");
+        let json = serde_json::to_string(&message).unwrap();
+        let loaded: Message = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.note.as_deref(), Some("wrong"));
+        // A message without one doesn't write it at all.
+        let plain = serde_json::to_string(&Message::assistant("5".to_string())).unwrap();
+        assert!(!plain.contains("note"));
+    }
+}
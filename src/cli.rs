@@ -0,0 +1,304 @@
+use chrono::NaiveDate;
+use clap::{
+    builder::{PossibleValue, TypedValueParser},
+    Parser, Subcommand, ValueEnum,
+};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+use crate::{
+    models,
+    msg::{self, Msg},
+    route,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Query OpenAI's chat completion models from the terminal",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// The model to start with, either a shortcut (e.g. 4o) or a full model id.
+    #[arg(short, long, value_parser = ModelParser, hide_possible_values = true)]
+    pub model: Option<String>,
+    /// Read the config from this file instead of the default location (also settable with GPT_CLI_CONFIG).
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Always call the API, even if response caching is enabled in the config.
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Show timing in the footer of one-shot answers.
+    #[arg(short, long)]
+    pub verbose: bool,
+    /// Check the API key before sending a one-shot prompt, as the interactive prompt does.
+    #[arg(long)]
+    pub check_auth: bool,
+    /// Print each request as a curl command before it's sent.
+    #[arg(long)]
+    pub print_curl: bool,
+    /// Print each message's request as a curl command instead of sending it. Nothing is sent.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Ignore any .gpt-cli.toml, and save and list sessions outside of its project.
+    #[arg(long)]
+    pub global: bool,
+    /// Turn off everything with side effects: shell commands, filters, tools, the clipboard,
+    /// .gpt-cli.toml and writing outside the data directory.
+    #[arg(long)]
+    pub safe: bool,
+    /// Don't offer the first-run setup when there's no key or config file.
+    #[arg(long)]
+    pub no_wizard: bool,
+    /// Warn, and exit with 5, if the answer isn't json, code, code:strict or regex:<pattern>.
+    #[arg(long, value_name = "SPEC")]
+    pub expect: Option<String>,
+    /// Keep a one-shot prompt for later if there's no connection, instead of failing.
+    #[arg(long, requires = "prompt")]
+    pub queue: bool,
+    /// Also write the raw answer to this file.
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+    /// Stream answers as JSON lines to this Unix socket or named pipe, too.
+    #[arg(long, value_name = "PATH")]
+    pub emit_socket: Option<PathBuf>,
+    /// Start from the system prompt, messages, model and files in this .gptseed.toml.
+    #[arg(long, value_name = "FILE")]
+    pub seed_file: Option<PathBuf>,
+    /// Run each line of this file as if it had been typed at the prompt, then carry on.
+    #[arg(long, value_name = "FILE", conflicts_with = "prompt")]
+    pub script: Option<PathBuf>,
+    /// Exit once the --script has run, instead of starting the REPL.
+    #[arg(long, requires = "script")]
+    pub script_exit: bool,
+    /// Send a single prompt, print the answer and exit instead of starting the REPL.
+    pub prompt: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print a shell completion script to stdout.
+    Completions { shell: Shell },
+    /// Embed the text files under a directory, so :ask can answer questions about them.
+    Index { dir: PathBuf },
+    /// Review a change: the unstaged changes by default, the staged ones or a patch file.
+    Review(ReviewArgs),
+    /// Summarise the usage recorded by every run, without calling the API.
+    Report(ReportArgs),
+    /// Ask one question about some files and print only the answer.
+    Ask(AskArgs),
+    /// Suggest a shell command for a task, or explain one, and copy it to the clipboard.
+    Cmd(CmdArgs),
+    /// Read a saved session, or list them, without loading it or needing a key.
+    View(ViewArgs),
+    /// Check the key, the network, paths and the terminal, for when something doesn't work.
+    Doctor(DoctorArgs),
+    /// List the bookmarked messages of every session, or show, copy or delete one.
+    Bookmarks {
+        #[command(subcommand)]
+        action: Option<BookmarksAction>,
+    },
+    /// Send the prompts queued with --queue while there was no connection, in order.
+    Flush,
+    /// Remove old sessions, cached responses and usage records, as the retention settings say.
+    Gc {
+        /// Only list what would be removed.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Upgrade the sessions, bookmarks and other files written by an older gpt to this one's format.
+    Migrate {
+        /// Only list what would be upgraded.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Work with the model metadata in models.toml.
+    #[command(subcommand)]
+    Models(ModelsCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ModelsCommand {
+    /// Check a models.toml for unknown keys, malformed prices and the like.
+    Validate {
+        /// The file to check, instead of the one next to the config file.
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ViewArgs {
+    /// A saved session's name, or the path to a session file.
+    #[arg(required_unless_present = "list")]
+    pub session: Option<String>,
+    /// List the saved sessions with their titles, dates, message counts and costs.
+    #[arg(long, conflicts_with_all = ["session", "format"])]
+    pub list: bool,
+    /// Print the session in this format instead of showing it in a pager.
+    #[arg(long, value_enum)]
+    pub format: Option<ViewFormat>,
+    /// Show messages as they were sent, with the prompt prefix and suffix, instead of as typed.
+    #[arg(long)]
+    pub with_wrapper: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ViewFormat {
+    Json,
+    Md,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BookmarksAction {
+    /// Print a bookmark's message in full.
+    Show { id: u32 },
+    /// Copy a bookmark's message to the clipboard.
+    Copy { id: u32 },
+    /// Delete a bookmark. The others keep their numbers.
+    Delete { id: u32 },
+}
+
+#[derive(clap::Args, Debug)]
+pub struct DoctorArgs {
+    /// Print the results as JSON, to paste into a bug report.
+    #[arg(long)]
+    pub json: bool,
+    /// Also send a one-token request to the model, to time a real answer.
+    #[arg(long)]
+    pub completion: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CmdArgs {
+    /// Run the command once you've confirmed it.
+    #[arg(short = 'x', long)]
+    pub execute: bool,
+    /// Explain the given command instead of suggesting one.
+    #[arg(long)]
+    pub explain: bool,
+    /// The model to ask, either a shortcut (e.g. 4o) or a full model id.
+    #[arg(short, long, value_parser = ModelParser, hide_possible_values = true)]
+    pub model: Option<String>,
+    /// What the command should do, or with --explain, the command.
+    #[arg(required = true)]
+    pub text: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct AskArgs {
+    /// Include this file in the prompt, labelled with its name. Can be given more than once.
+    #[arg(short, long = "file", value_name = "FILE")]
+    pub files: Vec<PathBuf>,
+    /// Send files that look like they hold secrets, like .env or an SSH key, instead of refusing.
+    #[arg(long)]
+    pub force: bool,
+    /// The model to ask, either a shortcut (e.g. 4o) or a full model id.
+    #[arg(short, long, value_parser = ModelParser, hide_possible_values = true)]
+    pub model: Option<String>,
+    /// Send this as the system prompt.
+    #[arg(short, long, value_name = "PROMPT")]
+    pub system: Option<String>,
+    #[arg(required = true)]
+    pub question: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ReportArgs {
+    /// Only count requests made on or after this day, as YYYY-MM-DD.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub since: Option<NaiveDate>,
+    #[arg(long, value_enum, default_value_t = GroupBy::Model)]
+    pub group_by: GroupBy,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    /// How the local estimates of prompt tokens compared with what was billed, instead of costs.
+    #[arg(long)]
+    pub drift: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Model,
+    Day,
+    /// The OpenAI organization billed.
+    Org,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// An aligned table with totals.
+    Table,
+    Csv,
+    Json,
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| msg::fmt(Msg::InvalidDate, &[("value", &value)]))
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ReviewArgs {
+    /// Review this patch file instead of `git diff`, or `-` to read one from stdin.
+    #[arg(value_name = "PATCH")]
+    pub patch: Option<PathBuf>,
+    /// Review the staged changes instead of the unstaged ones.
+    #[arg(long, conflicts_with = "patch")]
+    pub cached: bool,
+    #[arg(long, value_enum, default_value_t = ReviewFormat::Text)]
+    pub format: ReviewFormat,
+    /// The model to review with, either a shortcut (e.g. 4o) or a full model id.
+    #[arg(short, long, value_parser = ModelParser, hide_possible_values = true)]
+    pub model: Option<String>,
+    /// The most diff tokens sent in one request. Bigger diffs are reviewed in parts.
+    #[arg(long, value_name = "TOKENS", default_value_t = 6000)]
+    pub chunk_tokens: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReviewFormat {
+    /// A report grouped by file.
+    Text,
+    /// The issues as JSON, for CI.
+    Json,
+}
+
+/// Accepts any model name, but offers the shortcuts to shell completion.
+#[derive(Clone)]
+struct ModelParser;
+
+impl TypedValueParser for ModelParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        _cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<String, clap::Error> {
+        Ok(value.to_string_lossy().into_owned())
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            models::shortcuts()
+                .iter()
+                .map(|(short, id)| PossibleValue::new(*short).help(*id))
+                .chain([PossibleValue::new(route::AUTO).help("a model picked for each message")]),
+        ))
+    }
+}
+
+pub fn print_completions(shell: Shell) -> std::io::Result<()> {
+    use clap::CommandFactory;
+    use std::io::Write;
+
+    let mut cmd = Args::command();
+    let name = cmd.get_name().to_string();
+    // clap_complete panics on write errors, so generate into memory and write it out ourselves.
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut script);
+    std::io::stdout().write_all(&script)
+}
@@ -0,0 +1,586 @@
+//! `:code-context`: the shape of the cargo workspace gpt was started in, as one message in the
+//! context, so a question about the project doesn't start with pasting files in one by one. It has
+//! the crates and their dependencies, the source files and the signatures of their public items,
+//! with bodies left out, under `code_context_tokens`. When the signatures don't all fit, each file
+//! gets its first items before any gets more, so every part of the workspace shows. Files wanted
+//! whole are added on top with `--full`.
+
+use quote::ToTokens;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+use syn::{Fields, ImplItem, Item, TraitItem, Visibility};
+
+use crate::{
+    error::{AppError, Result},
+    input,
+    msg::{self, Msg},
+    sensitive,
+    stage::{self, StagedFile},
+    tokens::count_tokens,
+    usage::fmt_thousands,
+    AppState,
+};
+
+/// `:code-context`, with the files to add whole.
+pub struct CodeContext {
+    pub full: Vec<String>,
+}
+
+impl CodeContext {
+    pub fn parse(args: &str) -> Option<CodeContext> {
+        let mut full = Vec::new();
+        let mut words = args.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "--full" => full.push(words.next()?.to_string()),
+                _ if !full.is_empty() && !word.starts_with("--") => full.push(word.to_string()),
+                _ => return None,
+            }
+        }
+        Some(CodeContext { full })
+    }
+}
+
+struct Crate {
+    name: String,
+    version: String,
+    dir: PathBuf,
+    targets: Vec<String>,
+    /// By kind: "dependencies", "dev-dependencies" and "build-dependencies".
+    dependencies: BTreeMap<&'static str, Vec<String>>,
+}
+
+/// A source file's public items, each as its signature.
+struct Source {
+    path: String,
+    items: Vec<String>,
+}
+
+/// Builds the workspace's context and stages it, once it's been looked at if someone's there.
+pub async fn run(cmd: CodeContext, state: &mut AppState) -> Result<()> {
+    let (root, crates) = metadata()?;
+    let files = files(&root)?;
+    let budget = state.config.code_context_tokens;
+
+    let mut head = format!(
+        "The cargo workspace at {}, with the signatures of the public items but not their bodies.\n\n## Crates\n",
+        root.display()
+    );
+    for krate in &crates {
+        let dir = relative(&krate.dir, &root);
+        head.push_str(&format!(
+            "{} {} ({}) in {}\n",
+            krate.name,
+            krate.version,
+            krate.targets.join(", "),
+            if dir.is_empty() { "." } else { &dir }
+        ));
+        for (kind, deps) in &krate.dependencies {
+            head.push_str(&format!("  {kind}: {}\n", deps.join(", ")));
+        }
+    }
+    head.push_str("\n## Files\n");
+    head.push_str(&tree(&state.model, &files, budget / 4));
+
+    let mut sources = Vec::new();
+    let mut unparsed = 0;
+    for file in files.iter().filter(|f| f.ends_with(".rs")) {
+        match fs::read_to_string(root.join(file))
+            .ok()
+            .and_then(|text| syn::parse_file(&text).ok())
+        {
+            Some(parsed) => {
+                let mut items = Vec::new();
+                signatures(&parsed.items, "", &mut items);
+                if !items.is_empty() {
+                    sources.push(Source {
+                        path: file.clone(),
+                        items,
+                    });
+                }
+            }
+            None => unparsed += 1,
+        }
+    }
+
+    // Breadth first: a round of each file's next item at a time, until the budget runs out. A
+    // file stops at the first item that doesn't fit, so what it shows is always its first items.
+    // The counts are estimates, so what's over once it's put together is taken off again after.
+    let mut used = count_tokens(
+        &state.model,
+        &render(&head, &sources, &vec![0; sources.len()]),
+    );
+    let mut taken = vec![0; sources.len()];
+    let mut stopped = vec![false; sources.len()];
+    loop {
+        let mut added = false;
+        for (i, source) in sources.iter().enumerate() {
+            let Some(item) = source.items.get(taken[i]).filter(|_| !stopped[i]) else {
+                continue;
+            };
+            let mut tokens = count_tokens(&state.model, item) + 1;
+            if taken[i] == 0 {
+                tokens += count_tokens(&state.model, &source.path) + 8;
+            }
+            if used + tokens > budget {
+                stopped[i] = true;
+                continue;
+            }
+            used += tokens;
+            taken[i] += 1;
+            added = true;
+        }
+        if !added {
+            break;
+        }
+    }
+    let mut pack = render(&head, &sources, &taken);
+    while count_tokens(&state.model, &pack) > budget {
+        // From the file showing the most, to keep the breadth.
+        let Some(most) = (0..taken.len())
+            .max_by_key(|&i| taken[i])
+            .filter(|&i| taken[i] > 0)
+        else {
+            break;
+        };
+        taken[most] -= 1;
+        pack = render(&head, &sources, &taken);
+    }
+    let total: usize = sources.iter().map(|s| s.items.len()).sum();
+    let shown: usize = taken.iter().sum();
+    let pack_tokens = count_tokens(&state.model, &pack);
+
+    for path in &cmd.full {
+        let (path, force) = sensitive::parse_forced(path);
+        let text = sensitive::read(Path::new(path), &state.config.sensitive_files, force)?;
+        pack.push_str(&format!("\n## {path}\n```\n{}\n```\n", text.trim_end()));
+    }
+    let tokens = count_tokens(&state.model, &pack);
+
+    let mut summary = msg::fmt(
+        Msg::CodeContextSummary,
+        &[
+            ("root", &root.display()),
+            ("crates", &count(crates.len(), Msg::OneCrate, Msg::Crates)),
+            ("files", &count(files.len(), Msg::OneFile, Msg::Files)),
+            ("shown", &shown),
+            ("total", &total),
+            ("tokens", &fmt_thousands(pack_tokens as u64)),
+            ("budget", &fmt_thousands(budget as u64)),
+        ],
+    );
+    if !cmd.full.is_empty() {
+        summary.push_str(&msg::fmt(
+            Msg::CodeContextWhole,
+            &[
+                ("files", &count(cmd.full.len(), Msg::OneFile, Msg::Files)),
+                (
+                    "tokens",
+                    &fmt_thousands(tokens.saturating_sub(pack_tokens) as u64),
+                ),
+                ("all", &fmt_thousands(tokens as u64)),
+            ],
+        ));
+    }
+    println!("{summary}.");
+    if unparsed > 0 {
+        println!(
+            "{}",
+            msg::fmt(
+                Msg::CodeContextUnparsed,
+                &[("files", &count(unparsed, Msg::OneFile, Msg::Files))]
+            )
+        );
+    }
+    loop {
+        let answer = input::ask(state, msg::get(Msg::CodeContextAsk)).await?;
+        match answer.as_deref() {
+            Some("v" | "V") => print!("{pack}"),
+            Some("n" | "N") => return Ok(()),
+            _ => break,
+        }
+    }
+
+    // The workspace's root stands for the file, so running it again replaces the copy staged.
+    let label = msg::fmt(Msg::CodeContextLabel, &[("root", &root.display())]);
+    let staged = StagedFile {
+        hash: stage::hash(&pack),
+        path: root,
+    };
+    stage::put(state, &label, staged, pack).await
+}
+
+/// The pack: `head`, then the first `taken` items of each of the `sources`.
+fn render(head: &str, sources: &[Source], taken: &[usize]) -> String {
+    let mut pack = head.to_string();
+    pack.push_str("\n## Signatures\n```rust\n");
+    for (source, &n) in sources.iter().zip(taken).filter(|(_, &n)| n > 0) {
+        match source.items.len() - n {
+            0 => pack.push_str(&format!("// {}\n", source.path)),
+            left => pack.push_str(&format!("// {} ({left} more left out)\n", source.path)),
+        }
+        for item in &source.items[..n] {
+            pack.push_str(item);
+            pack.push('\n');
+        }
+    }
+    pack.push_str("```\n");
+    let left_out = taken.iter().filter(|&&n| n == 0).count();
+    if left_out > 0 {
+        pack.push_str(&format!(
+            "The public items of {} didn't fit and are left out.\n",
+            plural(left_out, "more file")
+        ));
+    }
+    pack
+}
+
+/// The workspace's root and its crates, from `cargo metadata`.
+fn metadata() -> Result<(PathBuf, Vec<Crate>)> {
+    let output = process::Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .output()
+        .map_err(|e| AppError::Command(msg::fmt(Msg::CargoFailedToRun, &[("error", &e)])))?;
+    if !output.status.success() {
+        let cwd = env::current_dir()?;
+        return Err(AppError::Command(msg::fmt(
+            Msg::CargoWorkspaceUnreadable,
+            &[
+                ("path", &cwd.display()),
+                ("error", &String::from_utf8_lossy(&output.stderr).trim()),
+            ],
+        )));
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| AppError::Command(msg::fmt(Msg::CargoMetadataUnreadable, &[("error", &e)])))?;
+    let root = PathBuf::from(metadata["workspace_root"].as_str().unwrap_or("."));
+    let mut crates = Vec::new();
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let str = |v: &Value| v.as_str().unwrap_or_default().to_string();
+        let manifest = PathBuf::from(str(&package["manifest_path"]));
+        let targets = package["targets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|t| {
+                let kind = t["kind"][0].as_str().unwrap_or("lib");
+                format!("{kind} {}", str(&t["name"]))
+            })
+            .collect();
+        let mut dependencies: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+        for dep in package["dependencies"].as_array().into_iter().flatten() {
+            let kind = match dep["kind"].as_str() {
+                Some("dev") => "dev-dependencies",
+                Some("build") => "build-dependencies",
+                _ => "dependencies",
+            };
+            let name = dep["rename"]
+                .as_str()
+                .map_or(str(&dep["name"]), str::to_string);
+            let req = str(&dep["req"]);
+            let req = req.strip_prefix('^').unwrap_or(&req);
+            dependencies
+                .entry(kind)
+                .or_default()
+                .push(format!("{name} {req}"));
+        }
+        crates.push(Crate {
+            name: str(&package["name"]),
+            version: str(&package["version"]),
+            dir: manifest.parent().map(Path::to_path_buf).unwrap_or_default(),
+            targets,
+            dependencies,
+        });
+    }
+    Ok((root, crates))
+}
+
+/// The `.rs` files and manifests under `root`, relative to it. git says which, so what's ignored
+/// is left out, and outside a repository hidden directories and `target` are skipped instead.
+fn files(root: &Path) -> Result<Vec<String>> {
+    let git = process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .output();
+    let mut files: Vec<String> = match git {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|f| !f.is_empty() && root.join(f).is_file())
+            .map(str::to_string)
+            .collect(),
+        _ => {
+            let mut files = Vec::new();
+            walk(root, root, &mut files)?;
+            files
+        }
+    };
+    files.retain(|f| f.ends_with(".rs") || f.ends_with("Cargo.toml"));
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+        if path.is_dir() {
+            walk(root, &path, files)?;
+        } else {
+            files.push(relative(&path, root));
+        }
+    }
+    Ok(())
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The files, a line for each directory. Only how many are in each when that's over `budget` tokens
+/// of `model`.
+fn tree(model: &str, files: &[String], budget: u32) -> String {
+    let mut dirs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for file in files {
+        let (dir, name) = file.rsplit_once('/').unwrap_or(("", file));
+        dirs.entry(dir).or_default().push(name);
+    }
+    let line = |dir: &str| match dir {
+        "" => "./".to_string(),
+        dir => format!("{dir}/"),
+    };
+    let full: String = dirs
+        .iter()
+        .map(|(dir, names)| format!("{} {}\n", line(dir), names.join(", ")))
+        .collect();
+    if count_tokens(model, &full) <= budget {
+        return full;
+    }
+    dirs.iter()
+        .map(|(dir, names)| format!("{} {}\n", line(dir), plural(names.len(), "file")))
+        .collect()
+}
+
+/// The signatures of the public items in `items`, inside the modules named by `module`.
+fn signatures(items: &[Item], module: &str, out: &mut Vec<String>) {
+    for item in items {
+        let signature = match item {
+            Item::Fn(f) if public(&f.vis) && !test(&f.attrs) => {
+                Some(format!("pub {};", tidy(&f.sig)))
+            }
+            Item::Struct(s) if public(&s.vis) => {
+                let fields = match s.fields {
+                    Fields::Named(ref fields) => {
+                        let public: Vec<String> = fields
+                            .named
+                            .iter()
+                            .filter(|f| public(&f.vis))
+                            .map(|f| {
+                                let name = f.ident.as_ref().map(ToString::to_string);
+                                format!("pub {}: {}", name.unwrap_or_default(), tidy(&f.ty))
+                            })
+                            .collect();
+                        let rest = fields.named.len() > public.len();
+                        match (public.is_empty(), rest) {
+                            (true, true) => " { .. }".to_string(),
+                            (true, false) => " {}".to_string(),
+                            (false, true) => format!(" {{ {}, .. }}", public.join(", ")),
+                            (false, false) => format!(" {{ {} }}", public.join(", ")),
+                        }
+                    }
+                    Fields::Unnamed(ref fields) => {
+                        let types: Vec<String> = fields
+                            .unnamed
+                            .iter()
+                            .map(|f| match public(&f.vis) {
+                                true => format!("pub {}", tidy(&f.ty)),
+                                false => "_".to_string(),
+                            })
+                            .collect();
+                        format!("({});", types.join(", "))
+                    }
+                    Fields::Unit => ";".to_string(),
+                };
+                Some(format!(
+                    "pub struct {}{}{fields}",
+                    s.ident,
+                    tidy(&s.generics)
+                ))
+            }
+            Item::Enum(e) if public(&e.vis) => {
+                let variants: Vec<String> = e
+                    .variants
+                    .iter()
+                    .map(|v| match v.fields {
+                        Fields::Named(_) => format!("{} {{ .. }}", v.ident),
+                        Fields::Unnamed(_) => format!("{}(..)", v.ident),
+                        Fields::Unit => v.ident.to_string(),
+                    })
+                    .collect();
+                Some(format!(
+                    "pub enum {}{} {{ {} }}",
+                    e.ident,
+                    tidy(&e.generics),
+                    variants.join(", ")
+                ))
+            }
+            Item::Trait(t) if public(&t.vis) => {
+                let mut signature = format!("pub trait {}{}", t.ident, tidy(&t.generics));
+                if !t.supertraits.is_empty() {
+                    signature.push_str(&format!(": {}", tidy(&t.supertraits)));
+                }
+                let methods: Vec<String> = t
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        TraitItem::Fn(f) => Some(format!("    {};", tidy(&f.sig))),
+                        TraitItem::Type(t) => Some(format!("    type {};", t.ident)),
+                        _ => None,
+                    })
+                    .collect();
+                match methods.is_empty() {
+                    true => signature.push_str(" {}"),
+                    false => signature.push_str(&format!(" {{\n{}\n}}", methods.join("\n"))),
+                }
+                Some(signature)
+            }
+            Item::Impl(i) if !test(&i.attrs) => {
+                let head = match i.trait_ {
+                    Some((_, ref path, _)) => format!(
+                        "impl{} {} for {}",
+                        tidy(&i.generics),
+                        tidy(path),
+                        tidy(&i.self_ty)
+                    ),
+                    None => format!("impl{} {}", tidy(&i.generics), tidy(&i.self_ty)),
+                };
+                let methods: Vec<String> = i
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        ImplItem::Fn(f) if public(&f.vis) => {
+                            Some(format!("    pub {};", tidy(&f.sig)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                match (i.trait_.is_some(), methods.is_empty()) {
+                    // Which traits a type has is worth a line; that it has no public methods isn't.
+                    (true, _) => Some(head),
+                    (false, true) => None,
+                    (false, false) => Some(format!("{head} {{\n{}\n}}", methods.join("\n"))),
+                }
+            }
+            Item::Type(t) if public(&t.vis) => Some(format!(
+                "pub type {}{} = {};",
+                t.ident,
+                tidy(&t.generics),
+                tidy(&t.ty)
+            )),
+            Item::Const(c) if public(&c.vis) => {
+                Some(format!("pub const {}: {};", c.ident, tidy(&c.ty)))
+            }
+            Item::Static(s) if public(&s.vis) => {
+                Some(format!("pub static {}: {};", s.ident, tidy(&s.ty)))
+            }
+            Item::Use(u) if public(&u.vis) => Some(format!("pub use {};", tidy(&u.tree))),
+            Item::Mod(m) if !test(&m.attrs) => {
+                if let Some((_, ref items)) = m.content {
+                    signatures(items, &format!("{module}{}::", m.ident), out);
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some(signature) = signature {
+            match module {
+                "" => out.push(signature),
+                module => out.push(format!(
+                    "// in {}\n{signature}",
+                    module.trim_end_matches("::")
+                )),
+            }
+        }
+    }
+}
+
+fn public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// Whether the item is only built for tests.
+fn test(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .any(|a| a.path().is_ident("cfg") && a.meta.to_token_stream().to_string().contains("test"))
+}
+
+/// Tokens as they'd be written, more or less: `Vec < u8 >` printed as `Vec<u8>`, and so on.
+fn tidy(tokens: &impl ToTokens) -> String {
+    let mut text = tokens.to_token_stream().to_string();
+    for (from, to) in [
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" : ", ": "),
+        (" ,", ","),
+        (" ;", ";"),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" <", "<"),
+        ("& ", "&"),
+        ("? ", "?"),
+        ("! ", "!"),
+    ] {
+        text = text.replace(from, to);
+    }
+    // No space before an argument list, but one is kept after `->` and the like.
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let before_parens = c == ' ' && chars.peek() == Some(&'(');
+        if before_parens && out.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '>') {
+            continue;
+        }
+        out.push(c);
+    }
+    // A trailing comma, from a list that was written over several lines.
+    out.replace("->(", "-> (")
+        .replace(",)", ")")
+        .replace(",>", ">")
+}
+
+fn count(n: usize, one: Msg, many: Msg) -> String {
+    match n {
+        1 => msg::get(one).to_string(),
+        n => msg::fmt(many, &[("count", &n)]),
+    }
+}
+
+fn plural(n: usize, what: &str) -> String {
+    match n {
+        1 => format!("1 {what}"),
+        n => format!("{n} {what}s"),
+    }
+}
@@ -0,0 +1,2123 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    askfile::AskFile,
+    bookmarks,
+    budget::{self, BudgetScope},
+    codectx::CodeContext,
+    compare::{self, Compare},
+    curl,
+    currency::Currency,
+    draft,
+    duo::{self, Duo},
+    endpoints,
+    error::{AppError, Result},
+    expect::Expect,
+    filter::Filter,
+    fix::Fix,
+    grep,
+    lock::{Attempt, SessionLock},
+    models,
+    msg::{self, Msg},
+    paths,
+    preset::{self, Preset},
+    project,
+    provider::{self, Provider},
+    quote, ratelimit, route,
+    safe::{self, Feature},
+    script::Script,
+    seed, sensitive,
+    session::{self, Session},
+    settings,
+    shellcmd::ShellCmd,
+    stage::{self, Stage},
+    streamto::StreamTarget,
+    style, summary,
+    tee::TeeTarget,
+    titles,
+    tokens::{self, count_tokens},
+    translate::{self, Translate},
+    usage::{self, fmt_thousands, SessionStats},
+    vars,
+    watch::{self, Watch},
+    wrap::Wrapper,
+    AppState, Message,
+};
+
+pub enum CommandOutcome {
+    Continue,
+    Exit(u8),
+    /// Send this as a message, as if it had been typed without a command.
+    Send(String),
+    Watch(Watch),
+    AskFile(AskFile),
+    /// Answer this from the index.
+    Ask(String),
+    Translate(Translate),
+    Duo(Duo),
+    Compare(Compare),
+    Fix(Fix),
+    Cmd(ShellCmd),
+    /// Recap the conversation with the cheap model, for `:summary --llm`.
+    Recap,
+    /// Search the prompts and answers as keys are typed.
+    Search,
+    /// Pick a model from a list, for `:model` on its own.
+    PickModel,
+    /// Start a new conversation from the seed file at this path.
+    Seed(PathBuf),
+    Stage(Stage),
+    CodeContext(CodeContext),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Session,
+    Model,
+    Output,
+    Files,
+}
+
+impl Category {
+    const ALL: [Category; 4] = [
+        Category::Session,
+        Category::Model,
+        Category::Output,
+        Category::Files,
+    ];
+
+    fn title(self) -> &'static str {
+        msg::get(match self {
+            Category::Session => Msg::CategorySession,
+            Category::Model => Msg::CategoryModel,
+            Category::Output => Msg::CategoryOutput,
+            Category::Files => Msg::CategoryFiles,
+        })
+    }
+}
+
+/// Everything the REPL knows about a command. `:help` is generated from this table, with the
+/// longer help each command has in [`msg::help`].
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub category: Category,
+    pub summary: Msg,
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "quit",
+        aliases: &["q"],
+        category: Category::Session,
+        summary: Msg::CmdQuit,
+    },
+    CommandSpec {
+        name: "context",
+        aliases: &["c"],
+        category: Category::Session,
+        summary: Msg::CmdContext,
+    },
+    CommandSpec {
+        name: "clear",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdClear,
+    },
+    CommandSpec {
+        name: "new",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdNew,
+    },
+    CommandSpec {
+        name: "seed",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSeed,
+    },
+    CommandSpec {
+        name: "restore",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdRestore,
+    },
+    CommandSpec {
+        name: "stats",
+        aliases: &["s", "cost"],
+        category: Category::Session,
+        summary: Msg::CmdStats,
+    },
+    CommandSpec {
+        name: "endpoints",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdEndpoints,
+    },
+    CommandSpec {
+        name: "summary",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSummary,
+    },
+    CommandSpec {
+        name: "debug",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdDebug,
+    },
+    CommandSpec {
+        name: "diff-request",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdDiffRequest,
+    },
+    CommandSpec {
+        name: "curl",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdCurl,
+    },
+    CommandSpec {
+        name: "help",
+        aliases: &["h"],
+        category: Category::Session,
+        summary: Msg::CmdHelp,
+    },
+    CommandSpec {
+        name: "show",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdShow,
+    },
+    CommandSpec {
+        name: "quote",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdQuote,
+    },
+    CommandSpec {
+        name: "grep",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdGrep,
+    },
+    CommandSpec {
+        name: "draft",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdDraft,
+    },
+    CommandSpec {
+        name: "send",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSend,
+    },
+    CommandSpec {
+        name: "search",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSearch,
+    },
+    CommandSpec {
+        name: "save",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSave,
+    },
+    CommandSpec {
+        name: "load",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdLoad,
+    },
+    CommandSpec {
+        name: "sessions",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSessions,
+    },
+    CommandSpec {
+        name: "project",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdProject,
+    },
+    CommandSpec {
+        name: "title",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdTitle,
+    },
+    CommandSpec {
+        name: "send-only",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSendOnly,
+    },
+    CommandSpec {
+        name: "wrap-prompt",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdWrapPrompt,
+    },
+    CommandSpec {
+        name: "note",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdNote,
+    },
+    CommandSpec {
+        name: "bookmark",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdBookmark,
+    },
+    CommandSpec {
+        name: "bookmarks",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdBookmarks,
+    },
+    CommandSpec {
+        name: "var",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdVar,
+    },
+    CommandSpec {
+        name: "subst",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdSubst,
+    },
+    CommandSpec {
+        name: "alias",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdAlias,
+    },
+    CommandSpec {
+        name: "unalias",
+        aliases: &[],
+        category: Category::Session,
+        summary: Msg::CmdUnalias,
+    },
+    CommandSpec {
+        name: "tools",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdTools,
+    },
+    CommandSpec {
+        name: "model",
+        aliases: &["m"],
+        category: Category::Model,
+        summary: Msg::CmdModel,
+    },
+    CommandSpec {
+        name: "why",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdWhy,
+    },
+    CommandSpec {
+        name: "models",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdModels,
+    },
+    CommandSpec {
+        name: "provider",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdProvider,
+    },
+    CommandSpec {
+        name: "org",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdOrg,
+    },
+    CommandSpec {
+        name: "max",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdMax,
+    },
+    CommandSpec {
+        name: "brief",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdBrief,
+    },
+    CommandSpec {
+        name: "normal",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdNormal,
+    },
+    CommandSpec {
+        name: "detailed",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdDetailed,
+    },
+    CommandSpec {
+        name: "tokens",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdTokens,
+    },
+    CommandSpec {
+        name: "get",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdGet,
+    },
+    CommandSpec {
+        name: "set",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdSet,
+    },
+    CommandSpec {
+        name: "cache",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdCache,
+    },
+    CommandSpec {
+        name: "paths",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdPaths,
+    },
+    CommandSpec {
+        name: "theme",
+        aliases: &[],
+        category: Category::Output,
+        summary: Msg::CmdTheme,
+    },
+    CommandSpec {
+        name: "math",
+        aliases: &[],
+        category: Category::Output,
+        summary: Msg::CmdMath,
+    },
+    CommandSpec {
+        name: "translate",
+        aliases: &["tr"],
+        category: Category::Output,
+        summary: Msg::CmdTranslate,
+    },
+    CommandSpec {
+        name: "duo",
+        aliases: &["roleplay"],
+        category: Category::Model,
+        summary: Msg::CmdDuo,
+    },
+    CommandSpec {
+        name: "compare",
+        aliases: &[],
+        category: Category::Model,
+        summary: Msg::CmdCompare,
+    },
+    CommandSpec {
+        name: "expect",
+        aliases: &[],
+        category: Category::Output,
+        summary: Msg::CmdExpect,
+    },
+    CommandSpec {
+        name: "filter",
+        aliases: &[],
+        category: Category::Output,
+        summary: Msg::CmdFilter,
+    },
+    CommandSpec {
+        name: "tee",
+        aliases: &[],
+        category: Category::Output,
+        summary: Msg::CmdTee,
+    },
+    CommandSpec {
+        name: "stream-to",
+        aliases: &[],
+        category: Category::Output,
+        summary: Msg::CmdStreamTo,
+    },
+    CommandSpec {
+        name: "ask",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdAsk,
+    },
+    CommandSpec {
+        name: "cmd",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdCmd,
+    },
+    CommandSpec {
+        name: "fix",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdFix,
+    },
+    CommandSpec {
+        name: "file",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdFile,
+    },
+    CommandSpec {
+        name: "code-context",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdCodeContext,
+    },
+    CommandSpec {
+        name: "ask-file",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdAskFile,
+    },
+    CommandSpec {
+        name: "script",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdScript,
+    },
+    CommandSpec {
+        name: "watch",
+        aliases: &[],
+        category: Category::Files,
+        summary: Msg::CmdWatch,
+    },
+];
+
+/// Finds a command by name or alias, with or without the leading ':'.
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    let name = name.strip_prefix(':').unwrap_or(name);
+    COMMANDS
+        .iter()
+        .find(|c| c.name == name || c.aliases.contains(&name))
+}
+
+/// The command whose name starts with `prefix`, if exactly one does, so `:mod` works as `:model`.
+pub fn unique_prefix(prefix: &str) -> Option<&'static CommandSpec> {
+    let prefix = prefix.strip_prefix(':').unwrap_or(prefix);
+    if prefix.is_empty() {
+        return None;
+    }
+    let mut matches = COMMANDS.iter().filter(|c| c.name.starts_with(prefix));
+    match (matches.next(), matches.next()) {
+        (Some(spec), None) => Some(spec),
+        _ => None,
+    }
+}
+
+/// The registered name or alias closest to `name`, if any is close enough to be a plausible typo.
+pub fn closest(name: &str) -> Option<&'static str> {
+    let name = name.strip_prefix(':').unwrap_or(name);
+    COMMANDS
+        .iter()
+        .flat_map(|c| std::iter::once(&c.name).chain(c.aliases.iter()))
+        // Everything is one edit away from a one letter alias, so those would just be noise.
+        .filter(|candidate| candidate.len() >= 3)
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(dist, candidate)| *dist <= if candidate.len() <= 4 { 1 } else { 2 })
+        .min_by_key(|(dist, _)| *dist)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, counting a swap of two adjacent characters as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Expands `:name rest` if `name` is an alias. The result is never expanded again.
+pub fn expand_alias(input: &str, aliases: &BTreeMap<String, String>) -> Option<String> {
+    let command = input.strip_prefix(':')?;
+    let (name, rest) = command
+        .split_once(char::is_whitespace)
+        .unwrap_or((command, ""));
+    let expansion = aliases.get(&name.to_lowercase())?;
+    let rest = rest.trim();
+    // Prefixes like "translate to German: " bring their own space.
+    if rest.is_empty() || expansion.ends_with(char::is_whitespace) {
+        Some(format!("{expansion}{rest}"))
+    } else {
+        Some(format!("{expansion} {rest}"))
+    }
+}
+
+fn validate_alias(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with(':') || name.contains(char::is_whitespace) {
+        return Err(AppError::Command(msg::fmt(
+            Msg::AliasName,
+            &[("name", &name)],
+        )));
+    }
+    match lookup(name) {
+        Some(spec) => Err(AppError::Command(msg::fmt(
+            Msg::AliasBuiltIn,
+            &[("name", &name), ("command", &spec.name)],
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Strips the quotes around an alias expansion, which keep leading or trailing spaces.
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+pub fn parse_command(command: String, state: &mut AppState) -> Result<CommandOutcome> {
+    let (command, args) = {
+        let command = command.trim();
+        let (cmd, args) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        (cmd.to_lowercase(), args.trim().to_string())
+    };
+
+    let Some(spec) = lookup(&command).or_else(|| unique_prefix(&command)) else {
+        return Err(AppError::Command(match closest(&command) {
+            Some(name) => msg::fmt(
+                Msg::UnknownCommandDidYouMean,
+                &[("command", &command), ("name", &name)],
+            ),
+            None => msg::get(Msg::UnknownCommand).to_string(),
+        }));
+    };
+
+    match spec.name {
+        "quit" => return Ok(CommandOutcome::Exit(0)),
+        "context" => {
+            state.auto_pipe = !state.auto_pipe;
+            match state.auto_pipe {
+                true => println!("{}", msg::get(Msg::ContextOn)),
+                false => println!("{}", msg::get(Msg::ContextOff)),
+            }
+        }
+        "model" if args == route::AUTO => {
+            state.auto_route = true;
+            let routing = &state.config.routing;
+            println!(
+                "{}",
+                msg::fmt(
+                    Msg::RoutingOn,
+                    &[
+                        ("cheap", &routing.cheap_model),
+                        ("expensive", &routing.expensive_model)
+                    ]
+                )
+            );
+        }
+        "model" if args.is_empty() => return Ok(CommandOutcome::PickModel),
+        "model" => {
+            let Some(model) = models::resolve(&args) else {
+                return Err(AppError::Command(msg::fmt(
+                    Msg::UnknownModel,
+                    &[("options", &shortcut_list())],
+                )));
+            };
+            switch_model(state, model);
+        }
+        "clear" => {
+            let n = state.context.len();
+            state.drop_context();
+            println!("{}", msg::fmt(Msg::Cleared, &[("count", &n)]));
+        }
+        "new" => {
+            new_conversation(state);
+            println!("{}", msg::get(Msg::NewConversation));
+        }
+        "seed" if args.is_empty() => return Err(usage_error(spec)),
+        "seed" => match args.strip_prefix("export") {
+            Some(path) if path.starts_with(char::is_whitespace) => {
+                seed::export(state, Path::new(unquote(path.trim())))?
+            }
+            Some("") => return Err(usage_error(spec)),
+            _ => return Ok(CommandOutcome::Seed(PathBuf::from(unquote(&args)))),
+        },
+        "restore" => match state.last_dropped.take() {
+            Some(mut dropped) => {
+                let n = dropped.len();
+                dropped.append(&mut state.context);
+                state.context = dropped;
+                println!("{}", msg::fmt(Msg::Restored, &[("count", &n)]));
+            }
+            None => println!("{}", msg::get(Msg::NothingToRestore)),
+        },
+        "provider" => {
+            if !args.is_empty() {
+                settings::set(state, "provider", &args.to_lowercase())?;
+            }
+            println!("provider = {}", settings::get(state, "provider")?);
+        }
+        "org" => {
+            if !args.is_empty() {
+                settings::set(state, "organization", &args)?;
+            }
+            println!("organization = {}", settings::get(state, "organization")?);
+        }
+        "max" => {
+            if args.is_empty() {
+                return Err(usage_error(spec));
+            }
+            settings::set(state, "max_tokens", &args.to_lowercase())?;
+            let value = settings::get(state, "max_tokens")?;
+            println!("{}", msg::fmt(Msg::MaxTokensSet, &[("value", &value)]));
+        }
+        "brief" | "normal" | "detailed" => {
+            let preset = Preset::parse(spec.name).expect("each preset has a command");
+            preset::apply(state, preset);
+            println!("{}", preset::describe(state));
+        }
+        "tokens" => {
+            let (detail, message) = match args.strip_prefix("--detail") {
+                Some(rest) => (true, rest.trim()),
+                None => (false, args.as_str()),
+            };
+            print_tokens(state, detail, message)?;
+        }
+        "get" => {
+            if args.is_empty() {
+                for setting in settings::SETTINGS {
+                    println!(
+                        "{:<22} {:<24} {}",
+                        setting.name,
+                        settings::get(state, setting.name)?,
+                        msg::get(setting.summary)
+                    );
+                }
+            } else {
+                println!("{}", settings::get(state, &args)?);
+            }
+        }
+        "set" => {
+            let Some((name, value)) = args.split_once(char::is_whitespace) else {
+                return Err(usage_error(spec));
+            };
+            settings::set(state, name, value.trim())?;
+            println!("{name} = {}", settings::get(state, name)?);
+        }
+        "endpoints" => endpoints::print(&state.config, state.color),
+        "stats" => {
+            print_stats(&usage::session(), &state.config.currency);
+            if let Some(left) = budget::left(state) {
+                let currency = &state.config.currency;
+                let budget = state.config.conversation_budget.unwrap_or(0.0) / currency.rate;
+                let scope = match state.config.budget_scope {
+                    BudgetScope::Session => Msg::BudgetSession,
+                    BudgetScope::Context => Msg::BudgetContext,
+                };
+                println!(
+                    "{}",
+                    msg::fmt(
+                        Msg::BudgetLeft,
+                        &[
+                            ("left", &currency.format(left.max(0.0))),
+                            ("budget", &currency.format(budget)),
+                            ("scope", &msg::get(scope)),
+                        ]
+                    )
+                );
+            }
+        }
+        "theme" => {
+            if !args.is_empty() {
+                settings::set(state, "theme", &args.to_lowercase())?;
+            }
+            let theme = settings::get(state, "theme")?;
+            println!("{}", msg::fmt(Msg::ThemeIs, &[("theme", &theme)]));
+        }
+        "math" => {
+            if !args.is_empty() {
+                settings::set(state, "math", &args.to_lowercase())?;
+            }
+            let math = settings::get(state, "math")?;
+            println!("{}", msg::fmt(Msg::MathIs, &[("math", &math)]));
+        }
+        "cache" => match args.as_str() {
+            "on" => {
+                state.config.cache = true;
+                println!("{}", msg::get(Msg::CacheOn));
+            }
+            "off" => {
+                state.config.cache = false;
+                println!("{}", msg::get(Msg::CacheOff));
+            }
+            "clear" => {
+                let n = state.cache.clear()?;
+                println!("{}", msg::fmt(Msg::CacheCleared, &[("count", &n)]));
+            }
+            _ => return Err(usage_error(spec)),
+        },
+        "debug" => {
+            let max_tokens = settings::get(state, "max_tokens")?;
+            println!(
+                "{}",
+                msg::fmt(
+                    Msg::DebugModel,
+                    &[("model", &state.model), ("max_tokens", &max_tokens)]
+                )
+            );
+            println!(
+                "{}",
+                msg::fmt(Msg::DebugLimiter, &[("limiter", &ratelimit::describe())])
+            );
+        }
+        "curl" => match args.as_str() {
+            "" => match state.requests.last() {
+                Some(request) => println!("{}", curl::command(request, &state.config)),
+                None => println!("{}", msg::get(Msg::NoRequestYet)),
+            },
+            "on" => {
+                state.print_curl = true;
+                provider::set_dry_run(false);
+                println!("{}", msg::get(Msg::CurlOn));
+            }
+            "off" => {
+                state.print_curl = false;
+                provider::set_dry_run(false);
+                println!("{}", msg::get(Msg::CurlOff));
+            }
+            "dry" => {
+                provider::set_dry_run(true);
+                println!("{}", msg::get(Msg::CurlDry));
+            }
+            _ => return Err(usage_error(spec)),
+        },
+        "stream-to" => {
+            let words: Vec<&str> = args.split_whitespace().collect();
+            match words.as_slice() {
+                [] => return Err(usage_error(spec)),
+                ["off"] => {
+                    state.stream_to = None;
+                    println!("{}", msg::get(Msg::StreamToOff));
+                }
+                [path] | [path, "--quiet"] | ["--quiet", path] => {
+                    let quiet = words.contains(&"--quiet");
+                    safe::check_write(Path::new(path))?;
+                    let notice = match quiet {
+                        true => Msg::StreamToQuiet,
+                        false => Msg::StreamToOn,
+                    };
+                    println!("{}", msg::fmt(notice, &[("path", path)]));
+                    state.stream_to = Some(StreamTarget {
+                        path: PathBuf::from(path),
+                        quiet,
+                    });
+                }
+                _ => return Err(usage_error(spec)),
+            }
+        }
+        "diff-request" => match state.requests.diff(state.color) {
+            Some(diff) => print!("{diff}"),
+            None => println!("{}", msg::get(Msg::NothingToCompare)),
+        },
+        "tee" => match args.as_str() {
+            "" => return Err(usage_error(spec)),
+            "off" => {
+                state.tee = None;
+                println!("{}", msg::get(Msg::TeeOff));
+            }
+            path => {
+                safe::check_write(Path::new(path))?;
+                println!("{}", msg::fmt(Msg::TeeOn, &[("path", &path)]));
+                state.tee = Some(TeeTarget {
+                    path: PathBuf::from(path),
+                    append: true,
+                });
+            }
+        },
+        "filter" => match args.as_str() {
+            "" => match state.filter {
+                Some(ref filter) => {
+                    let notice = match filter.tee {
+                        true => Msg::FilterTee,
+                        false => Msg::FilterIs,
+                    };
+                    println!("{}", msg::fmt(notice, &[("command", &filter.command)]))
+                }
+                None => println!("{}", msg::get(Msg::NoFilter)),
+            },
+            "off" => {
+                state.filter = None;
+                println!("{}", msg::get(Msg::FilterOff));
+            }
+            args => {
+                safe::check(Feature::Filter)?;
+                let filter = Filter::parse(args).ok_or_else(|| usage_error(spec))?;
+                println!(
+                    "{}",
+                    msg::fmt(Msg::FilterOn, &[("command", &filter.command)])
+                );
+                state.filter = Some(filter);
+            }
+        },
+        "show" => show(state, &args)?,
+        "wrap-prompt" => {
+            let Some(wrapper) = Wrapper::from_config(&state.config) else {
+                return Err(AppError::Command(msg::get(Msg::NoWrapper).to_string()));
+            };
+            match args.as_str() {
+                "" => {}
+                "on" => state.wrap_prompt = true,
+                "off" => state.wrap_prompt = false,
+                _ => return Err(usage_error(spec)),
+            }
+            let notice = match state.wrap_prompt {
+                true => Msg::WrapOn,
+                false => Msg::WrapOff,
+            };
+            println!("{}", msg::fmt(notice, &[("wrapper", &wrapper.describe())]));
+        }
+        "send-only" => match args.as_str() {
+            "" => match state.send_only {
+                Some(ref indices) => println!(
+                    "{}",
+                    msg::fmt(Msg::SendOnlyIs, &[("indices", &fmt_indices(indices))])
+                ),
+                None => return Err(usage_error(spec)),
+            },
+            "off" => {
+                state.send_only = None;
+                println!("{}", msg::get(Msg::SendOnlyOff));
+            }
+            selection => {
+                let indices = parse_selection(selection, state.context.len())?;
+                println!(
+                    "{}",
+                    msg::fmt(
+                        Msg::SendOnlySet,
+                        &[
+                            ("indices", &fmt_indices(&indices)),
+                            ("count", &state.context.len())
+                        ]
+                    )
+                );
+                state.send_only = Some(indices);
+            }
+        },
+        "bookmark" => {
+            let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((&args, ""));
+            let (index, label) = match first.parse::<usize>() {
+                Ok(_) => (parse_index(first, state.context.len())?, rest.trim()),
+                Err(_) => {
+                    let last = state.context.iter().rposition(|m| !m.is_user());
+                    let last = last.ok_or_else(|| {
+                        AppError::Command(msg::get(Msg::NothingToBookmark).to_string())
+                    })?;
+                    (last, args.trim())
+                }
+            };
+            let label = Some(label.to_string()).filter(|l| !l.is_empty());
+            let id = bookmarks::add(&state.context, index, label, state.session.clone())?;
+            println!(
+                "{}",
+                msg::fmt(Msg::Bookmarked, &[("index", &index), ("id", &id)])
+            );
+        }
+        "bookmarks" => {
+            let action = bookmarks::parse_action(&args).ok_or_else(|| usage_error(spec))?;
+            bookmarks::run(action, state.color)?;
+        }
+        "note" => match args.split_once(char::is_whitespace) {
+            None if args.is_empty() => {
+                let mut any = false;
+                for (i, msg) in state.context.iter().enumerate() {
+                    if let Some(ref note) = msg.note {
+                        println!("[{i}] {}", style::dim(note, state.color));
+                        any = true;
+                    }
+                }
+                if !any {
+                    println!("{}", msg::get(Msg::NoNotes));
+                }
+            }
+            None => {
+                let i = parse_index(&args, state.context.len())?;
+                match state.context[i].note {
+                    Some(ref note) => println!("{note}"),
+                    None => println!("{}", msg::fmt(Msg::NoNote, &[("index", &i)])),
+                }
+            }
+            Some(("unset", index)) => {
+                let i = parse_index(index.trim(), state.context.len())?;
+                match state.context[i].note.take() {
+                    Some(_) => println!("{}", msg::fmt(Msg::NoteRemoved, &[("index", &i)])),
+                    None => println!("{}", msg::fmt(Msg::NoNote, &[("index", &i)])),
+                }
+            }
+            Some((index, text)) => {
+                let i = parse_index(index, state.context.len())?;
+                state.context[i].note = Some(text.trim().to_string());
+                println!("{}", msg::fmt(Msg::Noted, &[("index", &i)]));
+            }
+        },
+        "quote" => match args.as_str() {
+            "" => match state.quote {
+                Some(ref quote) => println!("{quote}"),
+                None => return Err(usage_error(spec)),
+            },
+            "off" => {
+                state.quote = None;
+                println!("{}", msg::get(Msg::QuoteDropped));
+            }
+            selection => {
+                let quote = quote::block(&quote::select(&state.context, selection)?);
+                println!("{quote}");
+                println!("{}", msg::get(Msg::QuoteWaiting));
+                state.quote = Some(quote);
+            }
+        },
+        "grep" => grep::run(&args, &state.context, state.color)?,
+        "search" if !args.is_empty() => return Err(usage_error(spec)),
+        "search" => return Ok(CommandOutcome::Search),
+        "draft" if !matches!(args.as_str(), "" | "show" | "clear") => {
+            return Err(usage_error(spec))
+        }
+        "draft" => draft::command(&args, state)?,
+        "send" => return draft::send(state),
+        "file" if args.is_empty() => return Err(usage_error(spec)),
+        "file" => return Ok(CommandOutcome::Stage(Stage { path: args })),
+        "code-context" => {
+            return CodeContext::parse(&args)
+                .map(CommandOutcome::CodeContext)
+                .ok_or_else(|| usage_error(spec))
+        }
+        "save" => {
+            titles::collect(state);
+            let name = match args.as_str() {
+                "" => default_session_name(state),
+                name => name.to_string(),
+            };
+            attach(state, &name, |pid| {
+                msg::fmt(Msg::SaveHeld, &[("name", &name), ("pid", &pid)])
+            })?;
+            let saved = Session {
+                title: state.title.clone(),
+                model: state.model.clone(),
+                messages: state.context.clone(),
+            };
+            session::save(&name, &saved)?;
+            println!(
+                "{}",
+                msg::fmt(
+                    Msg::Saved,
+                    &[("count", &saved.messages.len()), ("name", &name)]
+                )
+            );
+            state.session = Some(name);
+        }
+        "load" => {
+            let words: Vec<&str> = args.split_whitespace().collect();
+            let (mode, name) = match words.as_slice() {
+                ["--read-only", name] => (LoadMode::ReadOnly, *name),
+                ["--fork", new, name] => (LoadMode::Fork(new), *name),
+                [flag, ..] if flag.starts_with("--") => return Err(usage_error(spec)),
+                [] => return Err(usage_error(spec)),
+                _ => (LoadMode::Attach, args.as_str()),
+            };
+            let saved = session::load(name)?;
+            match mode {
+                LoadMode::Attach => attach(state, name, |pid| {
+                    msg::fmt(Msg::LoadHeld, &[("name", &name), ("pid", &pid)])
+                })?,
+                LoadMode::ReadOnly => state.lock = None,
+                LoadMode::Fork(new) => {
+                    if session::path(new).exists() {
+                        return Err(AppError::Command(msg::fmt(
+                            Msg::SessionExists,
+                            &[("name", &new)],
+                        )));
+                    }
+                    attach(state, new, |pid| {
+                        msg::fmt(Msg::SessionHeld, &[("name", &new), ("pid", &pid)])
+                    })?;
+                    session::save(new, &saved)?;
+                }
+            }
+            state.model = saved.model;
+            state.context = saved.messages;
+            state.title = saved.title;
+            state.pending_title = None;
+            let count = state.context.len();
+            match mode {
+                LoadMode::Attach => {
+                    println!(
+                        "{}",
+                        msg::fmt(Msg::Loaded, &[("count", &count), ("name", &name)])
+                    );
+                    state.session = Some(name.to_string());
+                }
+                LoadMode::ReadOnly => {
+                    println!(
+                        "{}",
+                        msg::fmt(Msg::LoadedReadOnly, &[("count", &count), ("name", &name)])
+                    );
+                    state.session = None;
+                }
+                LoadMode::Fork(new) => {
+                    println!(
+                        "{}",
+                        msg::fmt(
+                            Msg::LoadedFork,
+                            &[("count", &count), ("name", &name), ("new", &new)]
+                        )
+                    );
+                    state.session = Some(new.to_string());
+                }
+            }
+        }
+        "sessions" => {
+            let names = session::list()?;
+            if names.is_empty() {
+                println!("{}", msg::get(Msg::NoSessions));
+            }
+            for name in names {
+                match session::title(&name) {
+                    Some(title) => println!("{name} - {title}"),
+                    None => println!("{name}"),
+                }
+            }
+        }
+        "title" => {
+            if args.is_empty() {
+                titles::collect(state);
+                match state.title {
+                    Some(ref title) => println!("{title}"),
+                    None => println!("{}", msg::get(Msg::NoTitle)),
+                }
+            } else {
+                state.title = Some(args.clone());
+                state.pending_title = None;
+                println!("{}", msg::get(Msg::TitleSet));
+            }
+        }
+        "var" => match args.split_once(char::is_whitespace) {
+            None if args.is_empty() => {
+                if state.vars.is_empty() {
+                    println!("{}", msg::get(Msg::NoVars));
+                }
+                for (name, value) in &state.vars {
+                    println!("{name} = {value}");
+                }
+            }
+            None => match state.vars.get(&args) {
+                Some(value) => println!("{value}"),
+                None => {
+                    return Err(AppError::Command(msg::fmt(
+                        Msg::VarUnset,
+                        &[("name", &args)],
+                    )))
+                }
+            },
+            Some(("unset", name)) => match state.vars.remove(name.trim()) {
+                Some(_) => println!("{}", msg::fmt(Msg::VarRemoved, &[("name", &name.trim())])),
+                None => {
+                    return Err(AppError::Command(msg::fmt(
+                        Msg::VarUnset,
+                        &[("name", &name.trim())],
+                    )))
+                }
+            },
+            Some((name, value)) => {
+                vars::validate_name(name)?;
+                state
+                    .vars
+                    .insert(name.to_string(), value.trim().to_string());
+                println!("{name} = {}", value.trim());
+            }
+        },
+        "subst" => {
+            if args.is_empty() {
+                return Err(usage_error(spec));
+            }
+            state.subst_once = true;
+            return Ok(CommandOutcome::Send(args));
+        }
+        "models" if args == "refresh" => {
+            let problems = models::refresh();
+            let path = paths::models_file();
+            println!(
+                "{}",
+                msg::fmt(
+                    Msg::ModelsRead,
+                    &[("path", &path.display()), ("count", &models::all().count())]
+                )
+            );
+            for problem in problems {
+                println!("{}", msg::fmt(Msg::ModelIgnored, &[("problem", &problem)]));
+            }
+        }
+        "models" if args == "info" || args.starts_with("info ") => {
+            let name = args["info".len()..].trim();
+            if name.is_empty() {
+                return Err(usage_error(spec));
+            }
+            let id = models::resolve(name).unwrap_or_else(|| name.to_string());
+            let Some((info, from)) = models::sources(&id) else {
+                return Err(AppError::Command(msg::fmt(Msg::NoMetadata, &[("id", &id)])));
+            };
+            let currency = &state.config.currency;
+            let per_million = |price: [f64; 3]| {
+                msg::fmt(
+                    Msg::PricePerMillion,
+                    &[
+                        ("input", &currency.format(price[0])),
+                        ("cached", &currency.format(price[1])),
+                        ("output", &currency.format(price[2])),
+                    ],
+                )
+            };
+            println!("{}", info.id);
+            let rows = [
+                (
+                    "price",
+                    match info.price {
+                        Some(price) => per_million(price),
+                        None => msg::get(Msg::NoFineTunedPrice).to_string(),
+                    },
+                    from.price,
+                ),
+                (
+                    "default_output",
+                    fmt_thousands(info.default_output as u64),
+                    from.default_output,
+                ),
+                (
+                    "max_output",
+                    fmt_thousands(info.max_output as u64),
+                    from.max_output,
+                ),
+                (
+                    "context_window",
+                    fmt_thousands(info.context_window as u64),
+                    from.context_window,
+                ),
+                (
+                    "capabilities",
+                    info.capabilities
+                        .map_or(msg::get(Msg::Unknown).to_string(), |c| c.summary()),
+                    from.capabilities,
+                ),
+                ("encoding", info.encoding.name().to_string(), from.encoding),
+            ];
+            let fine_tuned = info.fine_tuned_price.map(|price| {
+                (
+                    "fine_tuned_price",
+                    per_million(price),
+                    from.fine_tuned_price,
+                )
+            });
+            for (field, value, layer) in rows.into_iter().chain(fine_tuned) {
+                println!(
+                    "  {field:<16} {value:<40} {}",
+                    style::dim(&format!("({layer})"), state.color)
+                );
+            }
+        }
+        "models" => {
+            if state.config.provider == Provider::OpenRouter && !models::catalog_loaded() {
+                println!("{}", msg::get(Msg::CatalogNotLoaded));
+            }
+            let filter = args.to_lowercase();
+            println!(
+                "{:<44} {:>10} {:>10} {:>10}",
+                msg::get(Msg::ColumnModel),
+                msg::get(Msg::ColumnInput),
+                msg::get(Msg::ColumnOutput),
+                msg::get(Msg::ColumnWindow)
+            );
+            let currency = &state.config.currency;
+            for info in models::all().filter(|m| m.id.contains(&filter)) {
+                // Per million tokens, like the table.
+                let (input, output) = info.list_prices(currency);
+                println!(
+                    "{:<44} {input:>10} {output:>10} {:>10}",
+                    info.id,
+                    fmt_thousands(info.context_window as u64)
+                );
+            }
+            let fine_tuned: Vec<&String> = models::fine_tuned_models()
+                .unwrap_or_default()
+                .iter()
+                .filter(|id| id.to_lowercase().contains(&filter))
+                .collect();
+            if !fine_tuned.is_empty() {
+                println!("\n{}", msg::get(Msg::FineTunedModels));
+                for id in fine_tuned {
+                    let price = match models::lookup(id) {
+                        Some(info) => {
+                            let (input, output) = info.list_prices(currency);
+                            format!(
+                                "{input:>10} {output:>10} {:>10}",
+                                fmt_thousands(info.context_window as u64)
+                            )
+                        }
+                        None => format!("({})", msg::get(Msg::PriceUnknown)),
+                    };
+                    println!("{id:<44} {price}");
+                }
+            }
+        }
+        "alias" => match args.split_once(char::is_whitespace) {
+            None if args.is_empty() => {
+                if state.aliases.is_empty() {
+                    println!("{}", msg::get(Msg::NoAliases));
+                }
+                for (name, expansion) in &state.aliases {
+                    println!(":{name} = \"{expansion}\"");
+                }
+            }
+            None => match state.aliases.get(&args.to_lowercase()) {
+                Some(expansion) => println!("\"{expansion}\""),
+                None => {
+                    return Err(AppError::Command(msg::fmt(
+                        Msg::NoAlias,
+                        &[("name", &args)],
+                    )))
+                }
+            },
+            Some((name, expansion)) => {
+                let name = name.to_lowercase();
+                validate_alias(&name)?;
+                let expansion = unquote(expansion.trim());
+                println!(":{name} = \"{expansion}\"");
+                state.aliases.insert(name, expansion.to_string());
+            }
+        },
+        "unalias" => {
+            if args.is_empty() {
+                return Err(usage_error(spec));
+            }
+            match state.aliases.remove(&args.to_lowercase()) {
+                Some(_) => println!("{}", msg::fmt(Msg::AliasRemoved, &[("name", &args)])),
+                None => {
+                    return Err(AppError::Command(msg::fmt(
+                        Msg::NoAlias,
+                        &[("name", &args)],
+                    )))
+                }
+            }
+        }
+        "project" => print_project(state)?,
+        "paths" => {
+            let config_file = paths::config_file();
+            let config_row = match config_file.exists() {
+                true => Msg::PathsConfig,
+                false => Msg::PathsConfigMissing,
+            };
+            let rows = [
+                (config_row, config_file),
+                (Msg::PathsData, paths::data_dir()),
+                (Msg::PathsSessions, paths::sessions_dir()),
+                (Msg::PathsCache, paths::cache_dir()),
+                (Msg::PathsTools, paths::tools_dir()),
+            ];
+            for (row, path) in rows {
+                println!("{}", msg::fmt(row, &[("path", &path.display())]));
+            }
+            let config = &state.config;
+            let org = match config.organization {
+                Some(ref org) => org.as_str(),
+                None => msg::get(Msg::PathsOrgDefault),
+            };
+            println!("{}", msg::fmt(Msg::PathsOrg, &[("org", &org)]));
+            if let Some(ref project) = config.project {
+                println!("{}", msg::fmt(Msg::PathsProject, &[("project", project)]));
+            }
+        }
+        "tools" => match args.split_once(char::is_whitespace) {
+            None if args.is_empty() => {
+                if state.tools.tools.is_empty() {
+                    println!("{}", msg::get(Msg::NoTools));
+                }
+                for tool in &state.tools.tools {
+                    let mark = if tool.enabled { "x" } else { " " };
+                    let description = tool.description.as_deref().unwrap_or_default();
+                    let description = description.lines().next().unwrap_or_default();
+                    println!("[{mark}] {} ({}) {description}", tool.name, tool.origin());
+                }
+            }
+            Some((action @ ("enable" | "disable"), name)) => {
+                let Some(tool) = state.tools.find_mut(name.trim()) else {
+                    return Err(AppError::Command(msg::fmt(
+                        Msg::NoTool,
+                        &[("name", &name.trim())],
+                    )));
+                };
+                tool.enabled = action == "enable";
+                let done = match tool.enabled {
+                    true => Msg::ToolEnabled,
+                    false => Msg::ToolDisabled,
+                };
+                println!("{}", msg::fmt(done, &[("name", &tool.name)]));
+            }
+            _ => return Err(usage_error(spec)),
+        },
+        "ask" => {
+            if args.is_empty() {
+                return Err(usage_error(spec));
+            }
+            return Ok(CommandOutcome::Ask(args));
+        }
+        "ask-file" => {
+            let Some((path, question)) = args.split_once(char::is_whitespace) else {
+                return Err(usage_error(spec));
+            };
+            let (path, force) = sensitive::parse_forced(path);
+            return Ok(CommandOutcome::AskFile(AskFile {
+                path: PathBuf::from(path),
+                force,
+                question: unquote(question.trim()).to_string(),
+            }));
+        }
+        "watch" => return Ok(CommandOutcome::Watch(parse_watch(&args, spec)?)),
+        "script" => {
+            if args.is_empty() {
+                return Err(usage_error(spec));
+            }
+            if state.script.is_some() {
+                return Err(AppError::Command(msg::get(Msg::ScriptRunning).to_string()));
+            }
+            state.script = Some(Script::load(PathBuf::from(&args), false)?);
+        }
+        "cmd" => {
+            let cmd = ShellCmd::parse(&args).ok_or_else(|| usage_error(spec))?;
+            return Ok(CommandOutcome::Cmd(cmd));
+        }
+        "expect" => match args.as_str() {
+            "" => match state.expect {
+                Some(ref expect) => {
+                    println!("{}", msg::fmt(Msg::ExpectIs, &[("shape", &expect.name())]))
+                }
+                None => println!("{}", msg::get(Msg::NoExpect)),
+            },
+            "off" => {
+                state.expect = None;
+                println!("{}", msg::get(Msg::ExpectOff));
+            }
+            spec => {
+                let expect = Expect::parse(spec).map_err(AppError::Command)?;
+                println!("{}", msg::fmt(Msg::ExpectSet, &[("shape", &expect.name())]));
+                state.expect = Some(expect);
+            }
+        },
+        "fix" => {
+            let fix = Fix::parse(&args).ok_or_else(|| usage_error(spec))?;
+            return Ok(CommandOutcome::Fix(fix));
+        }
+        "duo" => return Ok(CommandOutcome::Duo(duo::parse(&args, &state.config.duo)?)),
+        "compare" => return Ok(CommandOutcome::Compare(compare::parse(&args)?)),
+        "translate" => {
+            let translate =
+                translate::parse(&args, &mut state.translation, &state.config.translate)?;
+            return Ok(CommandOutcome::Translate(translate));
+        }
+        "summary" => {
+            let llm = match args.as_str() {
+                "" => false,
+                "--llm" => true,
+                _ => return Err(usage_error(spec)),
+            };
+            summary::print(state);
+            if llm && !state.context.is_empty() {
+                return Ok(CommandOutcome::Recap);
+            }
+        }
+        "why" => match (&state.last_route, state.auto_route) {
+            (_, false) => println!("{}", msg::get(Msg::RoutingOff)),
+            (None, true) => println!("{}", msg::get(Msg::NotRouted)),
+            (Some(decision), true) => println!("{}: {}.", decision.model, decision.reason),
+        },
+        "help" => print_help(&args)?,
+        _ => unreachable!("command {} is registered but not handled", spec.name),
+    }
+
+    Ok(CommandOutcome::Continue)
+}
+
+fn parse_watch(args: &str, spec: &CommandSpec) -> Result<Watch> {
+    let mut max_iterations = watch::DEFAULT_MAX_ITERATIONS;
+    let mut budget = None;
+    let mut rest = args;
+    while let Some(flag) = rest.strip_prefix("--") {
+        let (flag, after) = flag
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| usage_error(spec))?;
+        let (value, after) = after
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| usage_error(spec))?;
+        let invalid = || {
+            AppError::Command(msg::fmt(
+                Msg::InvalidFlag,
+                &[("value", &value), ("flag", &flag)],
+            ))
+        };
+        match flag {
+            "max-iterations" => {
+                max_iterations = value.parse().ok().filter(|n| *n > 0).ok_or_else(invalid)?
+            }
+            "budget" => budget = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(usage_error(spec)),
+        }
+        rest = after.trim_start();
+    }
+    let Some((path, prompt)) = rest.split_once(char::is_whitespace) else {
+        return Err(usage_error(spec));
+    };
+    let (path, force) = sensitive::parse_forced(path);
+    Ok(Watch {
+        path: PathBuf::from(path),
+        force,
+        prompt: unquote(prompt.trim()).to_string(),
+        max_iterations,
+        budget,
+    })
+}
+
+fn show(state: &AppState, args: &str) -> Result<()> {
+    if args.is_empty() {
+        if state.context.is_empty() {
+            println!("{}", msg::get(Msg::ContextEmpty));
+        }
+        for (i, msg) in state.context.iter().enumerate() {
+            let first = msg.content().lines().next().unwrap_or_default();
+            let preview: String = first.chars().take(80).collect();
+            let more = if preview.len() < msg.content().len() {
+                "…"
+            } else {
+                ""
+            };
+            let when = msg
+                .timestamp
+                .map(|t| format!(" ({})", ago(t)))
+                .unwrap_or_default();
+            let shortened = match msg.original {
+                Some(_) => style::dim(&format!(" ({})", msg::get(Msg::Shortened)), state.color),
+                None => String::new(),
+            };
+            let wrapped = match msg.wrapper {
+                Some(_) => style::dim(&format!(" ({})", msg::get(Msg::Wrapped)), state.color),
+                None => String::new(),
+            };
+            println!(
+                "[{i}] {}{when}{shortened}{wrapped}: {preview}{more}",
+                msg.role()
+            );
+            if let Some(ref note) = msg.note {
+                let note = msg::fmt(Msg::ListNote, &[("note", note)]);
+                println!("    {}", style::dim(&note, state.color));
+            }
+        }
+        if let Some(wrapper) = Wrapper::from_config(&state.config).filter(|_| state.wrap_prompt) {
+            let notice = msg::fmt(Msg::ListWrapper, &[("wrapper", &wrapper.describe())]);
+            println!("{}", style::dim(&notice, state.color));
+        }
+        return Ok(());
+    }
+
+    let msg = parse_index(args, state.context.len()).map(|i| &state.context[i])?;
+    let mut details = Vec::new();
+    if let Some(t) = msg.timestamp {
+        details.push(format!(
+            "{}, {}",
+            ago(t),
+            t.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ));
+    }
+    if let Some(ref model) = msg.model {
+        details.push(model.clone());
+    }
+    if let Some(ms) = msg.duration_ms {
+        details.push(format!("{:.1}s", ms as f64 / 1000.));
+    }
+    if let Some(tokens) = msg.tokens {
+        let tokens = fmt_thousands(tokens as u64);
+        details.push(msg::fmt(Msg::Tokens, &[("count", &tokens)]));
+    }
+    match details.is_empty() {
+        true => println!("{}:\n{}", msg.role(), msg.content()),
+        false => println!(
+            "{} ({}):\n{}",
+            msg.role(),
+            details.join(" | "),
+            msg.content()
+        ),
+    }
+    if let Some(ref original) = msg.original {
+        let notice = msg::fmt(Msg::ShortenedFrom, &[("path", &original.display())]);
+        println!("{}", style::dim(&notice, state.color));
+    }
+    if let Some(ref wrapper) = msg.wrapper {
+        let notice = msg::fmt(Msg::SentWith, &[("wrapper", &wrapper.describe())]);
+        println!("{}", style::dim(&notice, state.color));
+    }
+    if let Some(ref note) = msg.note {
+        let note = msg::fmt(Msg::Note, &[("note", note)]);
+        println!("{}", style::dim(&note, state.color));
+    }
+    Ok(())
+}
+
+/// How long ago `time` was, roughly: "just now", "4m ago", "3d ago".
+fn ago(time: DateTime<Utc>) -> String {
+    let secs = (Utc::now() - time).num_seconds().max(0);
+    match secs {
+        0..60 => msg::get(Msg::JustNow).to_string(),
+        60..3600 => msg::fmt(Msg::MinutesAgo, &[("n", &(secs / 60))]),
+        3600..86400 => msg::fmt(Msg::HoursAgo, &[("n", &(secs / 3600))]),
+        _ => msg::fmt(Msg::DaysAgo, &[("n", &(secs / 86400))]),
+    }
+}
+
+/// Parses a message index as shown by :show and :grep.
+fn parse_index(arg: &str, len: usize) -> Result<usize> {
+    match arg.parse::<usize>() {
+        Ok(i) if i < len => Ok(i),
+        _ => Err(AppError::Command(msg::fmt(
+            Msg::NotAnIndex,
+            &[("arg", &arg), ("count", &len)],
+        ))),
+    }
+}
+
+/// Parses message indices like `2,7-9`, sorted and without repeats.
+fn parse_selection(arg: &str, len: usize) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for part in arg.split(',').map(str::trim) {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from = parse_index(from.trim(), len)?;
+                let to = parse_index(to.trim(), len)?;
+                if from > to {
+                    return Err(AppError::Command(msg::fmt(
+                        Msg::Backwards,
+                        &[("range", &part), ("from", &from), ("to", &to)],
+                    )));
+                }
+                indices.extend(from..=to);
+            }
+            None => indices.push(parse_index(part, len)?),
+        }
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
+fn fmt_indices(indices: &[usize]) -> String {
+    indices
+        .iter()
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Clone, Copy)]
+enum LoadMode<'a> {
+    Attach,
+    ReadOnly,
+    Fork(&'a str),
+}
+
+/// Locks the session `name` for this gpt, unless it already holds it, and lets go of the one it
+/// held before. `held` says what to do when another running gpt has it.
+fn attach(state: &mut AppState, name: &str, held: impl FnOnce(u32) -> String) -> Result<()> {
+    if state.lock.as_ref().is_some_and(|l| l.name() == name) {
+        return Ok(());
+    }
+    match SessionLock::acquire(name)? {
+        Attempt::Locked { lock, stale } => {
+            if let Some(pid) = stale {
+                let notice = msg::fmt(Msg::LockTakenOver, &[("name", &name), ("pid", &pid)]);
+                println!("{}", style::dim(&notice, state.color));
+            }
+            state.lock = Some(lock);
+            Ok(())
+        }
+        Attempt::Held(pid) => Err(AppError::Command(held(pid))),
+    }
+}
+
+/// What [`autosave`] did.
+pub enum Autosave {
+    Saved {
+        name: String,
+        count: usize,
+    },
+    /// The context is a different conversation from the one saved, so it wasn't saved over it.
+    Diverged(String),
+}
+
+/// Saves the session this gpt holds the lock on, if messages or a title were added since it was
+/// saved. Nothing happens without a lock, as after :load --read-only or before the first :save.
+pub fn autosave(state: &mut AppState) -> Result<Option<Autosave>> {
+    let Some(name) = state.lock.as_ref().map(|l| l.name().to_string()) else {
+        return Ok(None);
+    };
+    titles::collect(state);
+    let saved = match session::path(&name).exists() {
+        true => Some(session::load(&name)?),
+        false => None,
+    };
+    if let Some(ref saved) = saved {
+        if !extends(&saved.messages, &state.context) {
+            return Ok(Some(Autosave::Diverged(name)));
+        }
+        if saved.messages.len() == state.context.len() && saved.title == state.title {
+            return Ok(None);
+        }
+    }
+    let session = Session {
+        title: state.title.clone(),
+        model: state.model.clone(),
+        messages: state.context.clone(),
+    };
+    session::save(&name, &session)?;
+    Ok(Some(Autosave::Saved {
+        name,
+        count: session.messages.len(),
+    }))
+}
+
+// Whether `context` is `saved` with more added, rather than another conversation.
+fn extends(saved: &[Message], context: &[Message]) -> bool {
+    let encode = |messages: &[Message]| serde_json::to_value(messages).ok();
+    saved.len() <= context.len() && encode(saved) == encode(&context[..saved.len()])
+}
+
+fn default_session_name(state: &AppState) -> String {
+    match state.title.as_deref().map(titles::slugify) {
+        Some(slug) if !slug.is_empty() => slug,
+        _ => chrono::Local::now().format("%Y-%m-%d-%H%M%S").to_string(),
+    }
+}
+
+/// Drops the context and what was counted of it, as `:new` does. The seed it started from goes
+/// too, so its system prompt isn't sent with what follows.
+pub fn new_conversation(state: &mut AppState) {
+    state.drop_context();
+    *usage::session() = SessionStats::default();
+    state.exchanges = 0;
+    state.title = None;
+    state.pending_title = None;
+    state.seed = None;
+}
+
+/// Makes `model` the one new messages go to, as `:model` does.
+pub fn switch_model(state: &mut AppState, model: String) {
+    let provider = Provider::for_model(&model, state.config.provider);
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::Swapped,
+            &[("model", &model), ("provider", &provider.name())]
+        )
+    );
+    state.model = model;
+    state.auto_route = false;
+}
+
+fn shortcut_list() -> String {
+    models::shortcuts()
+        .iter()
+        .map(|(short, _)| *short)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn usage_error(spec: &CommandSpec) -> AppError {
+    let usage = msg::help(spec.name).usage;
+    AppError::Command(msg::fmt(Msg::HelpUsage, &[("usage", &usage)]))
+}
+
+fn display_name(spec: &CommandSpec) -> String {
+    if spec.aliases.is_empty() {
+        format!(":{}", spec.name)
+    } else {
+        format!(":{} ({})", spec.name, spec.aliases.join(", "))
+    }
+}
+
+fn print_help(topic: &str) -> Result<()> {
+    if topic.is_empty() {
+        for category in Category::ALL {
+            let cmds: Vec<_> = COMMANDS.iter().filter(|c| c.category == category).collect();
+            if cmds.is_empty() {
+                continue;
+            }
+            println!("{}:", category.title());
+            for cmd in cmds {
+                println!("  {:<18} {}", display_name(cmd), msg::get(cmd.summary));
+            }
+        }
+        println!("\n{}", msg::get(Msg::HelpMore));
+        return Ok(());
+    }
+
+    match lookup(topic).or_else(|| unique_prefix(topic)) {
+        Some(spec) => {
+            let help = msg::help(spec.name);
+            println!("{} - {}", display_name(spec), msg::get(spec.summary));
+            println!("{}", msg::fmt(Msg::HelpUsage, &[("usage", &help.usage)]));
+            println!("{}", help.details);
+            println!(
+                "{}",
+                msg::fmt(Msg::HelpExample, &[("example", &help.example)])
+            );
+            Ok(())
+        }
+        None => Err(AppError::Command(match closest(topic) {
+            Some(name) => msg::fmt(Msg::NoHelpDidYouMean, &[("topic", &topic), ("name", &name)]),
+            None => msg::fmt(Msg::NoHelp, &[("topic", &topic)]),
+        })),
+    }
+}
+
+/// The prompt tokens the next request would have, with `message` if given.
+fn print_tokens(state: &AppState, detail: bool, message: &str) -> Result<()> {
+    let keep = message.is_empty() || message.starts_with('|') || state.auto_pipe;
+    let message = message.strip_prefix('|').unwrap_or(message);
+    let new_message = (!message.is_empty()).then(|| Message {
+        wrapper: match state.wrap_prompt {
+            true => Wrapper::from_config(&state.config),
+            false => None,
+        },
+        ..Message::user(message.to_string())
+    });
+    let system: Vec<String> = state
+        .project
+        .as_ref()
+        .and_then(|p| p.system.clone())
+        .into_iter()
+        .chain(seed::system(state).map(str::to_string))
+        .chain(preset::instruction(state))
+        .collect();
+    let system = system.join("\n\n");
+    let files = state
+        .project
+        .as_ref()
+        .map(|p| p.file_blocks(&state.config.sensitive_files))
+        .unwrap_or_default();
+    let context = match keep {
+        true => state.context.as_slice(),
+        // Files staged with :file still go with a message that starts over.
+        false => stage::pending(&state.context),
+    };
+    let seeded = seed::messages(state);
+    let breakdown = tokens::breakdown(&tokens::Sources {
+        model: &state.model,
+        system: (!system.is_empty()).then_some(system.as_str()),
+        files: &files,
+        seeded: &seeded,
+        context,
+        new_message: new_message.as_ref(),
+    });
+    if detail {
+        println!("{}", tokens::format_breakdown(&breakdown));
+    }
+    let window = match models::lookup(&state.model) {
+        Some(info) => msg::fmt(
+            Msg::TokensWindow,
+            &[
+                ("model", &state.model),
+                ("window", &fmt_thousands(info.context_window as u64)),
+                ("answer", &fmt_thousands(state.max_tokens() as u64)),
+            ],
+        ),
+        None => String::new(),
+    };
+    let estimate = match tokens::approximate(&state.model) {
+        true => msg::get(Msg::TokensApproximate),
+        false => "",
+    };
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::TokensTotal,
+            &[
+                ("count", &fmt_thousands(breakdown.total() as u64)),
+                ("window", &window),
+                ("estimate", &estimate),
+            ]
+        )
+    );
+    if !keep && !state.context.is_empty() {
+        let notice = match state.context.len() {
+            1 => msg::get(Msg::TokensContextDroppedOne).to_string(),
+            n => msg::fmt(Msg::TokensContextDropped, &[("count", &n)]),
+        };
+        println!("{}", style::dim(&notice, state.color));
+    }
+    Ok(())
+}
+
+fn print_project(state: &AppState) -> Result<()> {
+    let Some(ref project) = state.project else {
+        println!(
+            "{}",
+            msg::fmt(Msg::NotInProject, &[("file", &project::FILE_NAME)])
+        );
+        return Ok(());
+    };
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::ProjectName,
+            &[
+                ("name", &project.namespace),
+                ("path", &project.path().display())
+            ]
+        )
+    );
+    if let Some(ref model) = project.model {
+        println!("{}", msg::fmt(Msg::ProjectModel, &[("model", model)]));
+    }
+    match project.system {
+        Some(ref system) => println!(
+            "{}",
+            msg::fmt(
+                Msg::ProjectSystem,
+                &[
+                    ("line", &system.lines().next().unwrap_or_default()),
+                    (
+                        "tokens",
+                        &fmt_thousands(count_tokens(&state.model, system) as u64)
+                    ),
+                ]
+            )
+        ),
+        None => println!("{}", msg::get(Msg::ProjectNoSystem)),
+    }
+    for (i, file) in project.files.iter().enumerate() {
+        let row = if i == 0 {
+            Msg::ProjectFirstFile
+        } else {
+            Msg::ProjectFile
+        };
+        let size = match project.read(file, &state.config.sensitive_files) {
+            Ok(text) => {
+                let tokens = fmt_thousands(count_tokens(&state.model, &text) as u64);
+                msg::fmt(Msg::ProjectFileTokens, &[("tokens", &tokens)])
+            }
+            Err(e) => msg::fmt(Msg::ProjectFileNotSent, &[("error", &e)]),
+        };
+        println!(
+            "{}",
+            msg::fmt(row, &[("path", &file.display()), ("size", &size)])
+        );
+    }
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::ProjectSessions,
+            &[("path", &paths::sessions_dir().display())]
+        )
+    );
+    Ok(())
+}
+
+fn print_stats(stats: &SessionStats, currency: &Currency) {
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::StatsTokens,
+            &[
+                ("requests", &stats.requests),
+                ("prompt", &fmt_thousands(stats.prompt_tokens)),
+                ("cached", &fmt_thousands(stats.cached_tokens)),
+                ("completion", &fmt_thousands(stats.completion_tokens)),
+            ]
+        )
+    );
+    let unpriced = match stats.unpriced {
+        0 => String::new(),
+        1 => msg::get(Msg::StatsUnpricedOne).to_string(),
+        n => msg::fmt(Msg::StatsUnpriced, &[("count", &n)]),
+    };
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::StatsPrice,
+            &[
+                ("cost", &currency.format(stats.cost)),
+                ("unpriced", &unpriced),
+                ("saving", &currency.format(stats.cache_saving)),
+            ]
+        )
+    );
+    let side = [
+        (Msg::StatsTitles, stats.title_requests, stats.title_cost),
+        (
+            Msg::StatsRouting,
+            stats.routing_requests,
+            stats.routing_cost,
+        ),
+        (Msg::StatsRecaps, stats.recap_requests, stats.recap_cost),
+    ];
+    for (row, requests, cost) in side.into_iter().filter(|(_, n, _)| *n > 0) {
+        println!(
+            "{}",
+            msg::fmt(
+                row,
+                &[("count", &requests), ("cost", &currency.format(cost))]
+            )
+        );
+    }
+    if !stats.endpoints.is_empty() {
+        let endpoints: Vec<String> = stats
+            .endpoints
+            .iter()
+            .map(|(base, requests)| format!("{base} {requests}"))
+            .collect();
+        println!(
+            "{}",
+            msg::fmt(
+                Msg::StatsEndpoints,
+                &[("endpoints", &endpoints.join(" | "))]
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(texts: &[&str]) -> Vec<Message> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| match i % 2 {
+                0 => Message::user(text.to_string()),
+                _ => Message::assistant(text.to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_longer_context_extends_what_was_saved() {
+        let saved = conversation(&["hi", "hello"]);
+        let mut context = saved.clone();
+        context.push(Message::user("more".to_string()));
+        assert!(extends(&saved, &context));
+        assert!(extends(&saved, &saved));
+        assert!(extends(&[], &saved));
+    }
+
+    #[test]
+    fn a_new_conversation_does_not_extend_what_was_saved() {
+        let saved = conversation(&["hi", "hello"]);
+        assert!(!extends(&saved, &saved[..1]));
+        let mut context = saved.clone();
+        context[0] = Message::user("other".to_string());
+        context.push(Message::user("more".to_string()));
+        assert!(!extends(&saved, &context));
+        assert!(!extends(&saved, &[]));
+    }
+
+    #[test]
+    fn every_command_has_help() {
+        for spec in COMMANDS {
+            assert!(!msg::get(spec.summary).is_empty(), ":{}", spec.name);
+            let help = msg::help(spec.name);
+            assert!(
+                help.usage.starts_with(&format!(":{}", spec.name)),
+                ":{} has no usage of its own",
+                spec.name
+            );
+            for topic in std::iter::once(&spec.name).chain(spec.aliases) {
+                assert!(print_help(topic).is_ok(), ":help {topic}");
+            }
+        }
+    }
+
+    #[test]
+    fn names_and_aliases_are_not_shared() {
+        let mut seen = std::collections::BTreeSet::new();
+        for spec in COMMANDS {
+            for name in std::iter::once(&spec.name).chain(spec.aliases) {
+                assert!(seen.insert(*name), ":{name} is registered twice");
+            }
+        }
+    }
+
+    #[test]
+    fn help_for_an_unknown_topic_suggests_a_command() {
+        let err = print_help("modle").unwrap_err().to_string();
+        assert!(err.contains("Did you mean 'model'?"), "{err}");
+        let err = print_help("xyzzy").unwrap_err().to_string();
+        assert!(!err.contains("Did you mean"), "{err}");
+    }
+
+    #[test]
+    fn typos_suggest_the_nearest_command() {
+        assert_eq!(closest("modle"), Some("model"));
+        assert_eq!(closest("contxt"), Some("context"));
+        assert_eq!(closest(":sumary"), Some("summary"));
+        assert_eq!(closest("tokesn"), Some("tokens"));
+    }
+
+    #[test]
+    fn nothing_is_suggested_for_what_is_not_a_typo() {
+        assert_eq!(closest("xyzzy"), None);
+        // Two edits is too many for a short name.
+        assert_eq!(closest("gxx"), None);
+        // One letter aliases are never offered.
+        assert_eq!(closest("z"), None);
+    }
+
+    #[test]
+    fn a_unique_prefix_names_its_command() {
+        assert_eq!(unique_prefix("wat").map(|c| c.name), Some("watch"));
+        assert_eq!(unique_prefix(":summ").map(|c| c.name), Some("summary"));
+    }
+
+    #[test]
+    fn an_ambiguous_prefix_names_nothing() {
+        // :model and :models.
+        assert!(unique_prefix("mod").is_none());
+        assert!(unique_prefix("s").is_none());
+        assert!(unique_prefix("").is_none());
+        assert!(unique_prefix(":").is_none());
+        // An exact name is looked up first, so it isn't lost to a longer one.
+        assert_eq!(lookup("model").map(|c| c.name), Some("model"));
+    }
+}
@@ -0,0 +1,159 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestUserMessageArgs, ChatCompletionStreamOptions,
+        CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use futures::future::join_all;
+
+use crate::{
+    chat,
+    error::{AppError, Result},
+    models,
+    msg::{self, Msg},
+    provider, signals, style,
+    usage::{fmt_thousands, Purpose},
+    AppState,
+};
+
+/// What `:compare` was asked: one question, for each of the models.
+#[derive(Debug, PartialEq)]
+pub struct Compare {
+    pub models: Vec<String>,
+    pub question: String,
+}
+
+/// Parses `<model>,<model>[,...] <question>`, resolving each model like `:model` does.
+pub fn parse(args: &str) -> Result<Compare> {
+    let usage = || AppError::Command(msg::get(Msg::CompareUsage).to_string());
+    let (names, question) = args
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(usage)?;
+    let models = names
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            models::resolve(name)
+                .ok_or_else(|| AppError::Command(msg::fmt(Msg::ModelUnknown, &[("name", &name)])))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let question = question.trim();
+    if models.len() < 2 || question.is_empty() {
+        return Err(usage());
+    }
+    Ok(Compare {
+        models,
+        question: question.to_string(),
+    })
+}
+
+/// Asks every model at once, each under the in-flight cap, and shows the answers in the order the
+/// models were given. Only the question and the system prompt are sent, and the answers stay out
+/// of the context. A model that fails doesn't stop the others'.
+pub async fn run(
+    compare: Compare,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let messages = messages(chat::system_prompt(state), &compare.question)?;
+    let requests = compare
+        .models
+        .iter()
+        .map(|model| {
+            CreateChatCompletionRequestArgs::default()
+                .model(model)
+                .max_completion_tokens(state.max_tokens.resolve(model).0)
+                .messages(messages.clone())
+                .stream_options(ChatCompletionStreamOptions {
+                    include_usage: true,
+                })
+                .build()
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let config = &state.config;
+    let answers = join_all(
+        requests
+            .into_iter()
+            .map(|request| provider::complete(client, request, config, Purpose::Answer)),
+    );
+    // Ctrl+C drops them together, which gives up every slot they hold.
+    let answers = tokio::select! {
+        answers = answers => answers,
+        _ = signals::interrupt() => return Err(AppError::Cancelled),
+    };
+
+    for (model, answer) in compare.models.iter().zip(answers) {
+        let header = msg::fmt(Msg::CompareHeader, &[("model", model)]);
+        println!(
+            "{}",
+            style::wrap(&header, style::accent_code(), state.color)
+        );
+        match answer {
+            Ok((text, usage)) => {
+                println!("{}", text.trim());
+                let price = match usage.price(model) {
+                    Some(price) => state.config.currency.format(price),
+                    None => msg::get(Msg::PriceUnknown).to_string(),
+                };
+                let notice = msg::fmt(
+                    Msg::CompareNotice,
+                    &[
+                        ("tokens", &fmt_thousands(usage.total() as u64)),
+                        ("price", &price),
+                    ],
+                );
+                println!("{}\n", style::dim(&notice, state.color));
+            }
+            Err(e) => println!("{}\n", AppError::from(e)),
+        }
+    }
+    Ok(())
+}
+
+fn messages(system: Option<String>, question: &str) -> Result<Vec<ChatCompletionRequestMessage>> {
+    let mut messages = Vec::new();
+    if let Some(system) = system {
+        messages.push(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system)
+                .build()?
+                .into(),
+        );
+    }
+    messages.push(
+        ChatCompletionRequestUserMessageArgs::default()
+            .content(question)
+            .build()?
+            .into(),
+    );
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_are_resolved_and_the_rest_is_the_question() {
+        let compare = parse("4o,4o-mini  What is a monad? ").unwrap();
+        assert_eq!(compare.models, ["gpt-4o", "gpt-4o-mini"]);
+        assert_eq!(compare.question, "What is a monad?");
+    }
+
+    #[test]
+    fn two_models_and_a_question_are_needed() {
+        for args in ["", "4o What?", "4o, What?", "4o,4o-mini", "4o,4o-mini   "] {
+            let err = parse(args).unwrap_err();
+            assert!(
+                err.to_string().starts_with("Usage: :compare"),
+                "{args:?}: {err}"
+            );
+        }
+        let err = parse("4o,no-such-model What?").unwrap_err();
+        assert!(err.to_string().contains("no-such-model"), "{err}");
+    }
+}
@@ -0,0 +1,248 @@
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    audit::AuditConfig, azure::AzureConfig, budget::BudgetScope, currency::Currency,
+    duo::DuoConfig, error::Result, footer::FooterStyle, index::IndexConfig, inflight,
+    large::LargeMessageConfig, math::MathMode, mcp, preset::PresetsConfig, prompt::PromptTemplate,
+    provider::Provider, route::RoutingConfig, sensitive::SensitiveFiles, stage::Restage,
+    style::Theme, translate::TranslateConfig, vars::Vars,
+};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnStop {
+    #[default]
+    Ask,
+    Keep,
+    Drop,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Who requests go to: openai (the default) or openrouter.
+    pub provider: Provider,
+    /// The model to start with: a shortcut, a model id or "auto". Defaults to the provider's.
+    pub model: Option<String>,
+    /// How "auto" picks a model for each message.
+    pub routing: RoutingConfig,
+    /// API keys by provider name, for when the environment variable isn't set.
+    pub api_keys: BTreeMap<String, String>,
+    /// Overrides the provider's base URL, e.g. for a proxy or a compatible server.
+    pub api_base: Option<String>,
+    /// Base URLs tried in order, passing over one that's down, see [`crate::endpoints`].
+    pub api_bases: Vec<String>,
+    /// The API version and deployments for base URLs on Azure, see [`crate::azure`].
+    pub azure: AzureConfig,
+    /// Moves saved sessions and other data away from the platform's data directory.
+    pub data_dir: Option<PathBuf>,
+    /// Moves the response cache away from the platform's cache directory.
+    pub cache_dir: Option<PathBuf>,
+    /// Models tried in order when a request fails for reasons unrelated to the request itself.
+    pub fallback_models: Vec<String>,
+    /// Reuse responses to identical requests instead of calling the API again.
+    pub cache: bool,
+    pub cache_ttl_secs: u64,
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+    /// The most requests sent at once, counting background ones like titles.
+    pub max_in_flight: usize,
+    /// Print a notice when a message without '|' throws away the previous context.
+    pub warn_on_context_drop: bool,
+    /// A Unix socket or named pipe answers are streamed to as JSON lines, see [`crate::emit`].
+    pub emit_socket: Option<PathBuf>,
+    /// Ask before sending a message longer than this many tokens, like a paste sent by accident.
+    pub confirm_send_over_tokens: Option<u32>,
+    /// Name the session in the background after this many exchanges. 0 turns it off.
+    pub title_after_exchanges: u32,
+    pub footer: FooterStyle,
+    /// How prices are shown. USD by default.
+    pub currency: Currency,
+    /// A soft limit on what a conversation may cost, in the currency prices are shown in.
+    pub conversation_budget: Option<f64>,
+    /// Send a message that could go over the budget to `budget_model` instead of asking.
+    pub downgrade_on_budget: bool,
+    /// Defaults to the cheap model of `[routing]`.
+    pub budget_model: Option<String>,
+    /// Whether the budget is for the whole session or only what's in the context.
+    pub budget_scope: BudgetScope,
+    /// The REPL prompt, with placeholders like `{model}`. See `:help theme`.
+    pub prompt: PromptTemplate,
+    pub theme: Theme,
+    /// Shows LaTeX math in answers as Unicode when `unicode`. Off by default.
+    pub math: MathMode,
+    /// Wrap answers at word boundaries instead of wherever the terminal's edge falls. Off by default.
+    pub word_wrap: bool,
+    /// Fixed max_tokens for every model. When unset, each model's own default is used.
+    pub max_tokens: Option<u32>,
+    /// What `:brief`, `:normal` and `:detailed` set, if not the built-in values.
+    pub presets: PresetsConfig,
+    /// Drop the oldest messages from requests that don't fit the model's window instead of asking.
+    pub auto_trim: bool,
+    /// Continue an answer whose stream dropped partway without asking.
+    pub auto_resume: bool,
+    /// How long a stream may go without sending anything, once it's started, before it counts as
+    /// stalled. 0 waits forever.
+    pub stall_after_secs: u64,
+    /// Put before and after every message typed, when it's sent. See `:help wrap-prompt`.
+    pub prompt_prefix: Option<String>,
+    pub prompt_suffix: Option<String>,
+    /// What's done with a message too big to keep sending with every request.
+    pub large_message: LargeMessageConfig,
+    /// Files that aren't sent unless asked for with `!` or `--force`, like `.env` and SSH keys.
+    pub sensitive_files: SensitiveFiles,
+    /// A command every finished response is piped through for display, as given to `:filter`.
+    pub response_filter: Option<String>,
+    /// The OpenAI organization and project to bill, overridden by OPENAI_ORG_ID and OPENAI_PROJECT_ID.
+    pub organization: Option<String>,
+    pub project: Option<String>,
+    /// Save the session gpt is attached to when it exits, with :quit, the end of input, SIGTERM or
+    /// SIGHUP. Only what was added to it is saved: after :new, the saved one is left alone.
+    pub autosave: bool,
+    /// Check the API key when the interactive prompt starts, so a bad one is reported before anything is typed.
+    pub check_auth: bool,
+    /// What happens to an answer stopped with Escape: ask, keep or drop.
+    pub on_stop: OnStop,
+    /// What `:file` does with a file already in the context: replace, keep-both or ask.
+    pub restage_files: Restage,
+    /// The most `:code-context` puts in the context, not counting the files added with `--full`.
+    pub code_context_tokens: u32,
+    /// Keep only this many saved sessions, removing those saved longest ago. See [`crate::gc`].
+    pub max_sessions: Option<usize>,
+    /// Remove saved sessions that haven't been saved for this many days.
+    pub session_max_age_days: Option<u32>,
+    /// Remove the oldest cached responses once there are more than this many megabytes of them.
+    pub cache_max_mb: Option<u64>,
+    /// Drop the requests older than this many days from the usage ledger.
+    pub usage_retention_days: Option<u32>,
+    /// Run `gpt gc` when gpt starts, at most once a day.
+    pub gc_on_start: bool,
+    /// Variables available to every session, as `{{name}}` in prompts.
+    pub vars: Vars,
+    /// Expand `${VAR}` and `$(command)` in every message. Off by default since it runs commands.
+    pub substitution: bool,
+    /// Ring the bell and show a desktop notification when a response takes at least this long.
+    pub notify_after_secs: Option<u64>,
+    /// Shortcuts usable as `:name`, see `:help alias`.
+    pub aliases: BTreeMap<String, String>,
+    /// MCP servers whose tools the model can call, by name.
+    pub mcp_servers: BTreeMap<String, mcp::ServerConfig>,
+    /// Executables in the tools directory that may be run. Anything else there is ignored.
+    pub allow_tools: Vec<String>,
+    /// How long a plugin tool may run before it's stopped.
+    pub tool_timeout_secs: u64,
+    /// Where `gpt index` stores embeddings for `:ask`, and how it chunks files.
+    pub index: IndexConfig,
+    /// The model and default source language for `:translate`.
+    pub translate: TranslateConfig,
+    /// Where `:fix` finds the last command, if not where `$SHELL` keeps its history.
+    pub shell_history: Option<PathBuf>,
+    /// The shape answers should have: json, code, code:strict or regex:<pattern>. See `:expect`.
+    pub expect: Option<String>,
+    /// Ask once more, saying what was wrong, when an answer isn't what `expect` says.
+    pub expect_retry: bool,
+    /// How many turns `:duo` runs for and the phrase that stops it.
+    pub duo: DuoConfig,
+    /// An append-only log of every request, off unless a path is set.
+    pub audit: AuditConfig,
+    /// Queue a one-shot prompt that fails for want of a connection, as `--queue` does.
+    pub queue_on_network_error: bool,
+    /// Start in safe mode, as `--safe` does. It can't be turned off from the REPL.
+    pub safe_mode: bool,
+    /// Record each request's tokens and cost for `gpt report`.
+    pub usage_ledger: bool,
+    /// The language of gpt's own messages, like `de`. English when unset or not one gpt has.
+    pub lang: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            provider: Provider::default(),
+            model: None,
+            routing: RoutingConfig::default(),
+            api_keys: BTreeMap::new(),
+            api_base: None,
+            api_bases: Vec::new(),
+            azure: AzureConfig::default(),
+            data_dir: None,
+            cache_dir: None,
+            fallback_models: Vec::new(),
+            cache: false,
+            cache_ttl_secs: 7 * 24 * 60 * 60,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            max_in_flight: inflight::DEFAULT_MAX_IN_FLIGHT,
+            warn_on_context_drop: true,
+            title_after_exchanges: 2,
+            footer: FooterStyle::default(),
+            currency: Currency::default(),
+            conversation_budget: None,
+            downgrade_on_budget: false,
+            budget_model: None,
+            budget_scope: BudgetScope::default(),
+            prompt: PromptTemplate::default(),
+            theme: Theme::default(),
+            math: MathMode::default(),
+            word_wrap: false,
+            max_tokens: None,
+            presets: PresetsConfig::default(),
+            auto_trim: false,
+            auto_resume: false,
+            stall_after_secs: 30,
+            prompt_prefix: None,
+            prompt_suffix: None,
+            large_message: LargeMessageConfig::default(),
+            sensitive_files: SensitiveFiles::default(),
+            response_filter: None,
+            organization: None,
+            project: None,
+            autosave: true,
+            check_auth: true,
+            on_stop: OnStop::default(),
+            restage_files: Restage::default(),
+            code_context_tokens: 8000,
+            max_sessions: None,
+            session_max_age_days: None,
+            cache_max_mb: None,
+            usage_retention_days: None,
+            gc_on_start: false,
+            confirm_send_over_tokens: None,
+            emit_socket: None,
+            vars: Vars::new(),
+            substitution: false,
+            notify_after_secs: None,
+            aliases: BTreeMap::new(),
+            mcp_servers: BTreeMap::new(),
+            allow_tools: Vec::new(),
+            tool_timeout_secs: 30,
+            index: IndexConfig::default(),
+            translate: TranslateConfig::default(),
+            duo: DuoConfig::default(),
+            expect: None,
+            expect_retry: false,
+            shell_history: None,
+            audit: AuditConfig::default(),
+            queue_on_network_error: false,
+            safe_mode: false,
+            usage_ledger: true,
+            lang: None,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file, using the defaults if it doesn't exist.
+    pub fn load(path: &Path) -> Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
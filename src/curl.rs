@@ -0,0 +1,184 @@
+//! `:curl` and `--print-curl`: a request as a `curl` command that sends it the way gpt does, for
+//! trying it against the raw API or attaching to a bug report. The key is left to the environment
+//! variable gpt reads it from, so the command can be shared as it is.
+
+use serde_json::Value;
+
+use crate::{
+    config::Config,
+    provider::{self, Provider},
+};
+
+/// The command for `request`, as recorded for `:diff-request`.
+pub fn command(request: &Value, config: &Config) -> String {
+    let model = request["model"].as_str().unwrap_or_default();
+    let provider = Provider::for_model(model, config.provider);
+    let base = match provider == config.provider {
+        true => provider::api_base(config),
+        false => provider.default_base().to_string(),
+    };
+    let base = base.trim_end_matches('/');
+    let backend = provider::backend_at(provider, base);
+    let key = format!("${}", provider::key_var_at(provider, base));
+    let mut headers = vec![quote("Content-Type: application/json")];
+    for (name, value) in backend.headers(&key, config) {
+        let header = format!("{name}: {value}");
+        // Bearer tokens and the like: double quotes, so the shell fills in the key.
+        headers.push(match value.contains(&key) {
+            true => format!("\"{header}\""),
+            false => quote(&header),
+        });
+    }
+    let url = backend.chat_url(base, model, config);
+    let body = backend.body(request);
+    let body = serde_json::to_string_pretty(&body).unwrap_or_default();
+    let mut res = format!("curl -N {}", quote(&url));
+    for header in headers {
+        res.push_str(&format!(" \\\n  -H {header}"));
+    }
+    // --data-raw, since -d would read a file for a body starting with @.
+    res.push_str(&format!(" \\\n  --data-raw {}", quote(&body)));
+    res
+}
+
+/// `text` as a single POSIX shell word: in single quotes, inside which nothing is special but the
+/// single quote itself, which is closed, escaped and reopened. Newlines and any other characters
+/// are kept as they are.
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Awkward for a shell in every way that's come up.
+    const HOSTILE: &[&str] = &[
+        "",
+        "plain",
+        "it's",
+        "''",
+        "'\\''",
+        "\"double\" and `back` quotes",
+        "$HOME ${PATH} $(rm -rf ~) !! \\$",
+        "back\\slash \\n \\\\",
+        "two\nlines\n\nand a tab\t",
+        "héllo wörld, 日本語, 🎉 and \u{200b}",
+        "@file",
+        "-- --data",
+        "; | & > < * ? [ ] # ~",
+    ];
+
+    #[test]
+    fn quoting_is_single_quotes_with_escaped_single_quotes() {
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("$HOME\n"), "'$HOME\n'");
+        assert_eq!(quote("日本"), "'日本'");
+    }
+
+    // What sh makes of `words`, split back into arguments.
+    #[cfg(unix)]
+    fn sh_args(words: &str) -> Vec<String> {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf '%s\\0' {words}"))
+            .env("OPENAI_API_KEY", "sk-shell")
+            .env("ANTHROPIC_API_KEY", "sk-shell")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let out = String::from_utf8(output.stdout).unwrap();
+        out.split_terminator('\0').map(str::to_string).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_shell_gets_back_exactly_what_was_quoted() {
+        for text in HOSTILE {
+            assert_eq!(sh_args(&quote(text)), [*text], "{text:?}");
+        }
+    }
+
+    fn request(model: &str) -> Value {
+        json!({
+            "model": model,
+            "messages": HOSTILE
+                .iter()
+                .map(|text| json!({"role": "user", "content": text}))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    #[test]
+    fn openai_requests_stream_with_the_key_left_to_the_shell() {
+        let config = Config {
+            api_base: Some("https://example.com/v1/".to_string()),
+            organization: Some("org-1".to_string()),
+            ..Config::default()
+        };
+        let command = command(&request("gpt-4o"), &config);
+        let mut lines = command.lines();
+        assert_eq!(
+            lines.next(),
+            Some("curl -N 'https://example.com/v1/chat/completions' \\")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("  -H 'Content-Type: application/json' \\")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("  -H \"Authorization: Bearer $OPENAI_API_KEY\" \\")
+        );
+        assert_eq!(lines.next(), Some("  -H 'OpenAI-Organization: org-1' \\"));
+        assert!(!command.contains("sk-"));
+    }
+
+    #[test]
+    fn anthropic_requests_go_to_its_own_api() {
+        let command = command(&request("claude-3-5-sonnet-latest"), &Config::default());
+        assert!(command.starts_with("curl -N 'https://api.anthropic.com/v1/messages'"));
+        assert!(command.contains("-H \"x-api-key: $ANTHROPIC_API_KEY\""));
+        assert!(!command.contains("Authorization"));
+    }
+
+    #[test]
+    fn azure_requests_go_to_the_deployment() {
+        let mut config = Config {
+            api_base: Some("https://east.openai.azure.com".to_string()),
+            ..Config::default()
+        };
+        config
+            .azure
+            .deployments
+            .insert("gpt-4o".to_string(), "chat-4o".to_string());
+        let command = command(&request("gpt-4o"), &config);
+        assert!(command.starts_with(
+            "curl -N 'https://east.openai.azure.com/openai/deployments/chat-4o/chat/completions?api-version=2024-10-21'"
+        ));
+        assert!(command.contains("-H \"api-key: $AZURE_OPENAI_API_KEY\""));
+        assert!(!command.contains("Authorization"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_body_the_shell_passes_on_is_the_request() {
+        let request = request("gpt-4o");
+        let args = sh_args(command(&request, &Config::default()).trim_start_matches("curl"));
+        assert_eq!(args[args.len() - 2], "--data-raw");
+        let body: Value = serde_json::from_str(args.last().unwrap()).unwrap();
+        assert_eq!(body["messages"], request["messages"]);
+        assert_eq!(body["stream"], true);
+        assert!(args.contains(&"Authorization: Bearer sk-shell".to_string()));
+
+        let request = self::request("claude-3-5-sonnet-latest");
+        let args = sh_args(command(&request, &Config::default()).trim_start_matches("curl"));
+        // Still JSON, however the messages were rearranged for it.
+        let body: Value = serde_json::from_str(args.last().unwrap()).unwrap();
+        assert_eq!(body["model"], "claude-3-5-sonnet-latest");
+        assert!(args.contains(&"x-api-key: sk-shell".to_string()));
+    }
+}
@@ -0,0 +1,99 @@
+use serde::Deserialize;
+
+/// How prices are shown, from the `[currency]` table of the config. Prices are kept in USD and
+/// converted only for display.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Currency {
+    pub symbol: String,
+    /// Units of this currency per USD, e.g. 79 for pence.
+    pub rate: f64,
+    /// Whether the symbol goes after the amount, as in "0.29p".
+    pub after: bool,
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency {
+            symbol: "$".to_string(),
+            rate: 1.0,
+            after: false,
+        }
+    }
+}
+
+// Anything smaller is shown as "<0.001" rather than rounded to a misleading zero.
+const SMALLEST: f64 = 0.001;
+
+impl Currency {
+    /// Formats a USD amount in this currency, with enough decimal places to show two significant
+    /// digits of small amounts, e.g. $0.0031 or 0.29p.
+    pub fn format(&self, usd: f64) -> String {
+        let amount = usd * self.rate;
+        let number = if amount == 0.0 {
+            "0".to_string()
+        } else if amount < SMALLEST {
+            "<0.001".to_string()
+        } else {
+            let decimals = (1 - amount.log10().floor() as i32).max(2) as usize;
+            format!("{amount:.decimals$}")
+        };
+        match (self.after, number.strip_prefix('<')) {
+            (true, _) => format!("{number}{}", self.symbol),
+            (false, Some(rest)) => format!("<{}{rest}", self.symbol),
+            (false, None) => format!("{}{number}", self.symbol),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pence() -> Currency {
+        Currency {
+            symbol: "p".to_string(),
+            rate: 79.0,
+            after: true,
+        }
+    }
+
+    fn euro() -> Currency {
+        Currency {
+            symbol: "€".to_string(),
+            rate: 0.92,
+            after: false,
+        }
+    }
+
+    #[test]
+    fn small_amounts_keep_two_significant_digits() {
+        let usd = Currency::default();
+        assert_eq!(usd.format(0.0031), "$0.0031");
+        assert_eq!(usd.format(0.05), "$0.050");
+        assert_eq!(pence().format(0.0031), "0.24p");
+        assert_eq!(euro().format(0.0031), "€0.0029");
+    }
+
+    #[test]
+    fn larger_amounts_have_two_decimal_places() {
+        let usd = Currency::default();
+        assert_eq!(usd.format(1.0), "$1.00");
+        assert_eq!(usd.format(12.5), "$12.50");
+        assert_eq!(usd.format(1234.5), "$1234.50");
+    }
+
+    #[test]
+    fn tiny_amounts_are_not_rounded_to_zero() {
+        assert_eq!(Currency::default().format(0.0004), "<$0.001");
+        assert_eq!(pence().format(0.000001), "<0.001p");
+        assert_eq!(Currency::default().format(0.0), "$0");
+    }
+
+    #[test]
+    fn the_config_table_can_leave_things_out() {
+        let currency: Currency = toml::from_str("symbol = \"£\"\nrate = 0.79").unwrap();
+        assert!(!currency.after);
+        assert_eq!(currency.format(2.0), "£1.58");
+    }
+}
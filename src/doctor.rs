@@ -0,0 +1,458 @@
+//! `gpt doctor`: the facts worth having in a bug report, from where the key comes from to whether
+//! the API answers, checked one by one so a single failure doesn't hide the rest.
+
+use async_openai::types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs};
+use serde::Serialize;
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::Path,
+    process,
+    time::Instant,
+};
+
+use crate::{
+    azure,
+    cli::DoctorArgs,
+    config::Config,
+    error::Result,
+    models::{self, Layer},
+    msg::{self, Msg},
+    paths, provider, route, style,
+    usage::Purpose,
+};
+
+// Proxy variables reqwest picks up, in both cases since either works.
+const PROXY_VARS: [&str; 8] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "NO_PROXY",
+    "no_proxy",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    version: &'static str,
+    os: &'static str,
+    checks: Vec<Check>,
+}
+
+/// Runs every check and prints them, as text or with `--json` as JSON. Exits with 1 if one failed.
+pub async fn run(args: &DoctorArgs, config: &Config) -> Result<u8> {
+    let mut checks = vec![api_key(config)];
+    checks.push(model(config));
+    checks.push(Check {
+        name: "config",
+        status: Status::Ok,
+        detail: match paths::config_file() {
+            file if file.exists() => file.display().to_string(),
+            file => msg::fmt(Msg::DoctorNoConfig, &[("path", &file.display())]),
+        },
+    });
+    checks.push(writable("data dir", &paths::data_dir()));
+    checks.push(writable("cache dir", &paths::cache_dir()));
+    checks.push(registry());
+    checks.push(proxy());
+    checks.push(network(config).await);
+    if args.completion {
+        checks.push(completion(config).await);
+    }
+    checks.push(terminal());
+
+    let failed = checks.iter().any(|c| c.status == Status::Fail);
+    let report = Report {
+        version: env!("CARGO_PKG_VERSION"),
+        os: env::consts::OS,
+        checks,
+    };
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        );
+    } else {
+        let color = style::color_enabled(io::stdout().is_terminal());
+        println!(
+            "{}",
+            msg::fmt(
+                Msg::DoctorHeader,
+                &[("version", &report.version), ("os", &report.os)]
+            )
+        );
+        for check in &report.checks {
+            let (label, code) = match check.status {
+                Status::Ok => ("ok", "32"),
+                Status::Warn => ("warn", "33"),
+                Status::Fail => ("FAIL", "31"),
+            };
+            println!(
+                "{} {:<11} {}",
+                style::wrap(&format!("{label:<4}"), code, color),
+                check.name,
+                check.detail
+            );
+        }
+    }
+    Ok(u8::from(failed))
+}
+
+/// Which of the provider's keys is used, shown only by its ends.
+fn api_key(config: &Config) -> Check {
+    let provider = config.provider;
+    // A base on Azure takes Azure's key, whichever provider it's for.
+    let (var, name) = match azure::is_azure(&provider::api_base(config)) {
+        true => (azure::KEY_VAR, "azure"),
+        false => (provider.key_var(), provider.name()),
+    };
+    let from_env = env::var(var).ok().filter(|k| !k.is_empty());
+    let from_config = config.api_keys.get(name);
+    let (status, detail) = match (from_env, from_config) {
+        (Some(key), Some(_)) => (
+            Status::Ok,
+            msg::fmt(Msg::DoctorKeyBoth, &[("key", &redact(&key)), ("var", &var)]),
+        ),
+        (Some(key), None) => (
+            Status::Ok,
+            msg::fmt(Msg::DoctorKeyEnv, &[("key", &redact(&key)), ("var", &var)]),
+        ),
+        (None, Some(key)) => (
+            Status::Ok,
+            msg::fmt(
+                Msg::DoctorKeyConfig,
+                &[("key", &redact(key)), ("provider", &name)],
+            ),
+        ),
+        (None, None) => (
+            Status::Fail,
+            msg::fmt(Msg::DoctorNoKey, &[("provider", &name), ("var", &var)]),
+        ),
+    };
+    Check {
+        name: "api key",
+        status,
+        detail,
+    }
+}
+
+fn redact(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 12 {
+        return msg::fmt(Msg::DoctorKeyShort, &[("count", &chars.len())]);
+    }
+    let start: String = chars[..3].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{start}…{end}")
+}
+
+/// The model a session would start with, or why it wouldn't start.
+fn model(config: &Config) -> Check {
+    let (status, detail) = match start_model(config) {
+        Ok(model) => (
+            Status::Ok,
+            msg::fmt(
+                Msg::DoctorModel,
+                &[("model", &model), ("provider", &config.provider.name())],
+            ),
+        ),
+        Err(name) => (
+            Status::Fail,
+            msg::fmt(Msg::DoctorUnknownModel, &[("name", &name)]),
+        ),
+    };
+    Check {
+        name: "model",
+        status,
+        detail,
+    }
+}
+
+fn start_model(config: &Config) -> std::result::Result<String, String> {
+    match config.model.as_deref() {
+        Some(route::AUTO) => Ok(config.routing.cheap_model.clone()),
+        Some(name) => models::resolve(name).ok_or_else(|| name.to_string()),
+        None => Ok(config.provider.default_model().to_string()),
+    }
+}
+
+/// Whether something can be written to `dir`, by writing something. A directory that doesn't
+/// exist yet is fine as long as it can be created.
+fn writable(name: &'static str, dir: &Path) -> Check {
+    let Some(existing) = dir.ancestors().find(|d| d.is_dir()) else {
+        return Check {
+            name,
+            status: Status::Fail,
+            detail: msg::fmt(Msg::DoctorNoParent, &[("path", &dir.display())]),
+        };
+    };
+    let probe = existing.join(format!(".gpt-doctor.{}.tmp", process::id()));
+    let result = fs::write(&probe, b"");
+    let _ = fs::remove_file(&probe);
+    let (status, detail) = match (result, existing == dir) {
+        (Ok(()), true) => (
+            Status::Ok,
+            msg::fmt(Msg::DoctorWritable, &[("path", &dir.display())]),
+        ),
+        (Ok(()), false) => (
+            Status::Ok,
+            msg::fmt(Msg::DoctorCreatedLater, &[("path", &dir.display())]),
+        ),
+        (Err(e), _) => (
+            Status::Fail,
+            msg::fmt(
+                Msg::DoctorNotWritable,
+                &[
+                    ("path", &dir.display()),
+                    ("existing", &existing.display()),
+                    ("error", &e),
+                ],
+            ),
+        ),
+    };
+    Check {
+        name,
+        status,
+        detail,
+    }
+}
+
+/// How many models are known, and how many of them models.toml changed.
+fn registry() -> Check {
+    let total = models::all().count();
+    let changed = models::all()
+        .filter_map(|m| models::sources(m.id))
+        .filter(|(_, from)| {
+            [
+                from.price,
+                from.default_output,
+                from.max_output,
+                from.context_window,
+                from.fine_tuned_price,
+            ]
+            .contains(&Layer::User)
+        })
+        .count();
+    let file = paths::models_file();
+    let problems = models::problems().len();
+    let mut detail = msg::fmt(Msg::DoctorModels, &[("count", &total)]);
+    if file.exists() {
+        detail.push_str(&msg::fmt(
+            Msg::DoctorModelsChanged,
+            &[("count", &changed), ("path", &file.display())],
+        ));
+    }
+    if problems > 0 {
+        detail.push_str(&msg::fmt(
+            Msg::DoctorModelsProblems,
+            &[("count", &problems)],
+        ));
+    }
+    Check {
+        name: "models",
+        status: match problems {
+            0 => Status::Ok,
+            _ => Status::Warn,
+        },
+        detail,
+    }
+}
+
+fn proxy() -> Check {
+    let set: Vec<String> = PROXY_VARS
+        .iter()
+        .filter_map(|var| {
+            let value = env::var(var).ok().filter(|v| !v.is_empty())?;
+            Some(format!("{var}={}", redact_url(&value)))
+        })
+        .collect();
+    Check {
+        name: "proxy",
+        status: Status::Ok,
+        detail: match set.is_empty() {
+            true => msg::get(Msg::DoctorNone).to_string(),
+            false => set.join(", "),
+        },
+    }
+}
+
+/// A proxy URL without the password it may carry.
+fn redact_url(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    match rest.split_once('@') {
+        Some((_, host)) if !scheme.is_empty() => format!("{scheme}://***@{host}"),
+        _ => url.to_string(),
+    }
+}
+
+/// Whether the base URL answers, and whether it takes the key.
+async fn network(config: &Config) -> Check {
+    let base = provider::api_base(config);
+    let (status, detail) = match provider::probe(config).await {
+        Ok((code, took)) => {
+            let took = format!("{}ms", took.as_millis());
+            match code {
+                code if code.is_success() => (
+                    Status::Ok,
+                    msg::fmt(Msg::DoctorAnswered, &[("what", &base), ("took", &took)]),
+                ),
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => (
+                    Status::Fail,
+                    msg::fmt(
+                        Msg::DoctorKeyRejected,
+                        &[("base", &base), ("code", &code), ("took", &took)],
+                    ),
+                ),
+                code => (
+                    Status::Warn,
+                    msg::fmt(
+                        Msg::DoctorListing,
+                        &[("base", &base), ("code", &code), ("took", &took)],
+                    ),
+                ),
+            }
+        }
+        Err(e) => (
+            Status::Fail,
+            msg::fmt(Msg::DoctorUnreachable, &[("base", &base), ("error", &e)]),
+        ),
+    };
+    Check {
+        name: "network",
+        status,
+        detail,
+    }
+}
+
+/// A one-token request to the starting model, for `--completion`.
+async fn completion(config: &Config) -> Check {
+    let fail = |detail: String| Check {
+        name: "completion",
+        status: Status::Fail,
+        detail,
+    };
+    let Ok(model) = start_model(config) else {
+        return fail(msg::get(Msg::DoctorSkippedModel).to_string());
+    };
+    let client = match provider::client(config) {
+        Ok(Some(client)) => client,
+        Ok(None) => return fail(msg::get(Msg::DoctorSkippedKey).to_string()),
+        Err(e) => return fail(e.to_string()),
+    };
+    let request = ChatCompletionRequestUserMessageArgs::default()
+        .content("Say ok.")
+        .build()
+        .and_then(|message| {
+            CreateChatCompletionRequestArgs::default()
+                .model(&model)
+                .max_completion_tokens(1u32)
+                .messages([message.into()])
+                .build()
+        });
+    let request = match request {
+        Ok(request) => request,
+        Err(e) => return fail(e.to_string()),
+    };
+    let start = Instant::now();
+    match provider::complete(&client, request, config, Purpose::Answer).await {
+        Ok(_) => Check {
+            name: "completion",
+            status: Status::Ok,
+            detail: msg::fmt(
+                Msg::DoctorAnswered,
+                &[
+                    ("what", &model),
+                    ("took", &format!("{}ms", start.elapsed().as_millis())),
+                ],
+            ),
+        },
+        Err(e) => fail(format!("{model}: {e}")),
+    }
+}
+
+fn terminal() -> Check {
+    let tty = |is: bool| {
+        msg::get(if is {
+            Msg::DoctorTty
+        } else {
+            Msg::DoctorNotTty
+        })
+    };
+    let stdout_tty = io::stdout().is_terminal();
+    let mut detail = msg::fmt(
+        Msg::DoctorTerminal,
+        &[
+            ("stdin", &tty(io::stdin().is_terminal())),
+            ("stdout", &tty(stdout_tty)),
+            ("stderr", &tty(io::stderr().is_terminal())),
+            (
+                "color",
+                &msg::get(if style::color_enabled(stdout_tty) {
+                    Msg::DoctorOn
+                } else {
+                    Msg::DoctorOff
+                }),
+            ),
+        ],
+    );
+    if let Some(width) = width() {
+        detail.push_str(&msg::fmt(Msg::DoctorColumns, &[("width", &width)]));
+    }
+    if let Ok(term) = env::var("TERM") {
+        detail.push_str(&format!(", TERM={term}"));
+    }
+    Check {
+        name: "terminal",
+        status: Status::Ok,
+        detail,
+    }
+}
+
+#[cfg(unix)]
+pub fn width() -> Option<u16> {
+    // SAFETY: TIOCGWINSZ only fills in the winsize it's given.
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        let ok = libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0;
+        (ok && size.ws_col > 0).then_some(size.ws_col)
+    }
+}
+
+#[cfg(windows)]
+pub fn width() -> Option<u16> {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleScreenBufferInfo, GetStdHandle, CONSOLE_SCREEN_BUFFER_INFO, STD_OUTPUT_HANDLE,
+    };
+
+    // SAFETY: the handle comes straight from GetStdHandle and the info is a plain out-parameter.
+    unsafe {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        if GetConsoleScreenBufferInfo(GetStdHandle(STD_OUTPUT_HANDLE), &mut info) == 0 {
+            return None;
+        }
+        let columns = info.srWindow.Right - info.srWindow.Left + 1;
+        u16::try_from(columns).ok()
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn width() -> Option<u16> {
+    None
+}
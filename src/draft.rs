@@ -0,0 +1,109 @@
+//! `:draft`: a message written over as many lines as it takes and sent only by `:send`, so an
+//! Enter hit halfway through doesn't send half of it. Commands still work while drafting. The
+//! draft is written to the data directory with every line, so a crash doesn't lose it, and the
+//! next gpt picks it up where it was left.
+
+use std::{fs, io, path::PathBuf};
+
+use crate::{
+    commands::CommandOutcome,
+    error::{AppError, Result},
+    fsutil,
+    msg::{self, Msg},
+    paths, style, AppState,
+};
+
+/// The prompt shown while drafting, in place of the usual one.
+pub const PROMPT: &str = "…> ";
+
+fn file() -> PathBuf {
+    paths::data_dir().join("draft.md")
+}
+
+/// The draft a previous gpt left unsent, if any.
+pub fn load() -> Option<String> {
+    fs::read_to_string(file()).ok()
+}
+
+fn save(draft: &str) -> Result<()> {
+    paths::ensure_dir(&paths::data_dir())?;
+    fsutil::write_atomic(&file(), draft.as_bytes())?;
+    Ok(())
+}
+
+fn remove() -> Result<()> {
+    match fs::remove_file(file()) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Adds a typed line to the draft, as it was typed.
+pub fn append(state: &mut AppState, line: &str) -> Result<()> {
+    let Some(ref mut draft) = state.draft else {
+        return Ok(());
+    };
+    draft.push_str(line);
+    draft.push('\n');
+    save(draft)
+}
+
+/// Picks up the draft a previous gpt left, saying so.
+pub fn restore(state: &mut AppState) {
+    let Some(draft) = load() else {
+        return;
+    };
+    let notice = msg::fmt(Msg::DraftRestored, &[("lines", &lines(&draft))]);
+    println!("{}", style::dim(&notice, state.color));
+    state.draft = Some(draft);
+}
+
+/// `:draft`, or with `show` or `clear`.
+pub fn command(args: &str, state: &mut AppState) -> Result<()> {
+    match (args, &state.draft) {
+        ("", None) => {
+            save("")?;
+            state.draft = Some(String::new());
+            println!("{}", msg::get(Msg::Drafting));
+        }
+        ("", Some(draft)) => println!(
+            "{}",
+            msg::fmt(Msg::AlreadyDrafting, &[("lines", &lines(draft))])
+        ),
+        ("clear", Some(draft)) => {
+            println!(
+                "{}",
+                msg::fmt(Msg::DraftDropped, &[("lines", &lines(draft))])
+            );
+            remove()?;
+            state.draft = None;
+        }
+        (_, Some(draft)) if draft.trim().is_empty() => println!("{}", msg::get(Msg::DraftEmpty)),
+        (_, Some(draft)) => print!("{draft}"),
+        (_, None) => println!("{}", msg::get(Msg::NoDraft)),
+    }
+    Ok(())
+}
+
+/// `:send`: sends the draft as one message and stops drafting.
+pub fn send(state: &mut AppState) -> Result<CommandOutcome> {
+    let Some(ref draft) = state.draft else {
+        return Err(AppError::Command(msg::get(Msg::NoDraftToSend).to_string()));
+    };
+    let text = draft.trim_end().to_string();
+    if text.trim().is_empty() {
+        return Err(AppError::Command(
+            msg::get(Msg::DraftEmptyNotSent).to_string(),
+        ));
+    }
+    remove()?;
+    state.draft = None;
+    Ok(CommandOutcome::Send(text))
+}
+
+fn lines(draft: &str) -> String {
+    match draft.lines().count() {
+        1 => msg::get(Msg::OneLine).to_string(),
+        n => msg::fmt(Msg::Lines, &[("count", &n)]),
+    }
+}
@@ -0,0 +1,233 @@
+//! `:duo`: two models talking to each other, for made-up dialogues or to see one press another.
+//! Each side has its own model and system prompt and sees the other's answers as the user's
+//! messages. That system prompt is all either is sent besides: the project's prompt, the seed the
+//! REPL's conversation started from and the preset's instruction belong to that conversation,
+//! which is left alone. The transcript is saved as a session.
+
+use async_openai::{config::OpenAIConfig, Client};
+use serde::Deserialize;
+
+use crate::{
+    chat,
+    error::{AppError, Result},
+    models,
+    msg::{self, Msg},
+    session::{self, Session},
+    signals, style, AppState, Message,
+};
+
+/// The `[duo]` table of the config.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DuoConfig {
+    /// Answers in all, from both sides, unless `--turns` says otherwise.
+    pub turns: u32,
+    /// Ends the conversation early when an answer contains it, ignoring case.
+    pub stop: Option<String>,
+}
+
+impl Default for DuoConfig {
+    fn default() -> Self {
+        DuoConfig {
+            turns: 6,
+            stop: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Side {
+    pub model: String,
+    pub system: Option<String>,
+}
+
+/// What `:duo` was asked.
+#[derive(Debug)]
+pub struct Duo {
+    pub sides: [Side; 2],
+    pub turns: u32,
+    pub stop: Option<String>,
+    /// What side A answers first.
+    pub seed: String,
+}
+
+/// The next word of `text` and what follows it. Double or single quotes keep spaces in a word.
+fn next_word(text: &str) -> Option<(String, &str)> {
+    let text = text.trim_start();
+    let quote = text.chars().next().filter(|c| *c == '"' || *c == '\'');
+    let (word, rest) = match quote {
+        Some(q) => {
+            let inner = &text[1..];
+            match inner.find(q) {
+                Some(end) => (&inner[..end], &inner[end + 1..]),
+                None => (inner, ""),
+            }
+        }
+        None => text.split_once(char::is_whitespace).unwrap_or((text, "")),
+    };
+    (!text.is_empty()).then(|| (word.to_string(), rest))
+}
+
+/// Parses `<model a> <model b> [flags] <first message>`.
+pub fn parse(args: &str, config: &DuoConfig) -> Result<Duo> {
+    let usage = || AppError::Command(msg::get(Msg::DuoUsage).to_string());
+    let model = |name: String| {
+        models::resolve(&name)
+            .ok_or_else(|| AppError::Command(msg::fmt(Msg::ModelUnknown, &[("name", &name)])))
+    };
+    let (a, rest) = next_word(args).ok_or_else(usage)?;
+    let (b, mut rest) = next_word(rest).ok_or_else(usage)?;
+    let mut duo = Duo {
+        sides: [
+            Side {
+                model: model(a)?,
+                system: None,
+            },
+            Side {
+                model: model(b)?,
+                system: None,
+            },
+        ],
+        turns: config.turns,
+        stop: config.stop.clone(),
+        seed: String::new(),
+    };
+    while let Some((flag, after)) = next_word(rest).filter(|(w, _)| w.starts_with("--")) {
+        let (value, after) = next_word(after).ok_or_else(usage)?;
+        match flag.as_str() {
+            "--system-a" => duo.sides[0].system = Some(value),
+            "--system-b" => duo.sides[1].system = Some(value),
+            "--turns" => {
+                duo.turns = value.parse().ok().filter(|&n| n > 0).ok_or_else(|| {
+                    AppError::Command(msg::fmt(Msg::DuoTurns, &[("value", &value)]))
+                })?
+            }
+            "--stop" => duo.stop = Some(value).filter(|p| !p.is_empty()),
+            _ => return Err(usage()),
+        }
+        rest = after;
+    }
+    let rest = rest.trim();
+    // A quoted message is one word, but one that isn't is everything left.
+    duo.seed = match next_word(rest) {
+        Some((word, after)) if rest.starts_with(['"', '\'']) && after.trim().is_empty() => word,
+        _ => rest.to_string(),
+    };
+    if duo.seed.is_empty() {
+        return Err(usage());
+    }
+    Ok(duo)
+}
+
+/// Runs the conversation, then restores the REPL's and saves the transcript.
+pub async fn run(duo: Duo, client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<()> {
+    let saved_context = std::mem::take(&mut state.context);
+    let saved_model = state.model.clone();
+    let saved_route = std::mem::replace(&mut state.auto_route, false);
+    // Nor should the REPL's next request be compared with one of these.
+    let saved_prefix = std::mem::take(&mut state.prefix);
+    let mut transcript = vec![Message::user(duo.seed.clone())];
+    let result = converse(&duo, client, state, &mut transcript).await;
+    state.context = saved_context;
+    state.model = saved_model;
+    state.auto_route = saved_route;
+    state.prefix = saved_prefix;
+
+    let answers = &transcript[1..];
+    if answers.is_empty() {
+        return result;
+    }
+    let name = format!("duo-{}", chrono::Local::now().format("%Y-%m-%d-%H%M%S"));
+    let [ref a, ref b] = duo.sides;
+    let saved = Session {
+        title: Some(msg::fmt(Msg::DuoTitle, &[("a", &a.model), ("b", &b.model)])),
+        model: a.model.clone(),
+        messages: transcript.clone(),
+    };
+    session::save(&name, &saved)?;
+    let cost: f64 = answers.iter().filter_map(|m| m.cost_usd).sum();
+    let turns = match answers.len() {
+        1 => msg::get(Msg::DuoOneTurn).to_string(),
+        n => msg::fmt(Msg::DuoTurnCount, &[("count", &n)]),
+    };
+    let summary = msg::fmt(
+        Msg::DuoSummary,
+        &[
+            ("turns", &turns),
+            ("cost", &state.config.currency.format(cost)),
+            ("name", &name),
+        ],
+    );
+    println!("{}", style::dim(&summary, state.color));
+    result
+}
+
+async fn converse(
+    duo: &Duo,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+    transcript: &mut Vec<Message>,
+) -> Result<()> {
+    let stop = duo.stop.as_ref().map(|p| p.to_lowercase());
+    for turn in 0..duo.turns as usize {
+        let who = turn % 2;
+        let side = &duo.sides[who];
+        state.context = context(transcript, who);
+        state.model = side.model.clone();
+
+        let header = msg::fmt(
+            Msg::DuoHeader,
+            &[
+                ("side", &label(who)),
+                ("model", &side.model),
+                ("turn", &(turn + 1)),
+                ("turns", &duo.turns),
+            ],
+        );
+        println!(
+            "{}",
+            style::wrap(&header, style::accent_code(), state.color)
+        );
+        tokio::select! {
+            result = chat::stream_with_system(client, state, side.system.clone()) => result?,
+            _ = signals::interrupt() => return Err(AppError::Cancelled),
+        }
+        let Some(answer) = state.context.pop().filter(|m| !m.is_user()) else {
+            break;
+        };
+        let stopped = stop
+            .as_ref()
+            .is_some_and(|p| answer.content.to_lowercase().contains(p.as_str()));
+        transcript.push(Message {
+            note: Some(msg::fmt(Msg::DuoNote, &[("side", &label(who))])),
+            ..answer
+        });
+        if stopped {
+            let notice = msg::fmt(Msg::DuoStopped, &[("side", &label(who))]);
+            println!("{}", style::dim(&notice, state.color));
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn label(side: usize) -> &'static str {
+    match side {
+        0 => "A",
+        _ => "B",
+    }
+}
+
+/// The transcript as `side` sees it: its own answers as its own, and the other side's as the
+/// user's. The first message is only for A.
+fn context(transcript: &[Message], side: usize) -> Vec<Message> {
+    transcript
+        .iter()
+        .enumerate()
+        .filter_map(|(i, msg)| match i {
+            0 => (side == 0).then(|| msg.clone()),
+            i if (i - 1) % 2 == side => Some(msg.clone()),
+            _ => Some(Message::user(msg.content.clone())),
+        })
+        .collect()
+}
@@ -0,0 +1,235 @@
+//! `--emit-socket` and `emit_socket`: every answer is also written, as it streams, to a Unix
+//! socket or named pipe as newline-delimited JSON, for editors, speech daemons and anything else
+//! that wants the text without reading the terminal:
+//!
+//! ```text
+//! {"type":"start","model":"gpt-4o"}
+//! {"type":"delta","text":"Hello"}
+//! {"type":"done","model":"gpt-4o","usage":{"prompt":12,"cached":0,"completion":5}}
+//! {"type":"error","message":"..."}
+//! ```
+//!
+//! Writes never wait: what the reader doesn't take in time is kept up to a limit and events past it
+//! are dropped, so a slow or missing reader can't hold up the chat. Problems are reported once.
+
+use serde::Serialize;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crate::{
+    input,
+    msg::{self, Msg},
+    usage::Usage,
+};
+
+// What's kept for a reader that's fallen behind before events are dropped.
+const MAX_PENDING: usize = 1 << 20;
+
+static EMITTER: Mutex<Option<Emitter>> = Mutex::new(None);
+
+// Set once a problem has been reported, so it's only said once a run.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Event<'a> {
+    Start { model: &'a str },
+    Delta { text: &'a str },
+    Done { model: &'a str, usage: EventUsage },
+    Error { message: &'a str },
+}
+
+#[derive(Serialize)]
+struct EventUsage {
+    prompt: u32,
+    cached: u32,
+    completion: u32,
+}
+
+struct Emitter {
+    path: PathBuf,
+    conn: Option<imp::Conn>,
+    /// Written but not yet taken by the reader.
+    pending: Vec<u8>,
+    /// Between a start and its done or error.
+    answering: bool,
+}
+
+/// Starts emitting to `path`. A reader that isn't there yet is connected to with the next answer.
+pub fn init(path: PathBuf) {
+    let mut emitter = Emitter {
+        path,
+        conn: None,
+        pending: Vec::new(),
+        answering: false,
+    };
+    emitter.connect();
+    *EMITTER.lock().unwrap_or_else(|e| e.into_inner()) = Some(emitter);
+}
+
+/// An answer from `model` is starting.
+pub fn start(model: &str) {
+    with(|emitter| {
+        if emitter.conn.is_none() {
+            emitter.connect();
+        }
+        emitter.answering = true;
+        emitter.send(Event::Start { model });
+    });
+}
+
+pub fn delta(text: &str) {
+    with(|emitter| {
+        if emitter.answering {
+            emitter.send(Event::Delta { text });
+        }
+    });
+}
+
+/// The answer finished, having used `usage`.
+pub fn done(model: &str, usage: &Usage) {
+    with(|emitter| {
+        if std::mem::take(&mut emitter.answering) {
+            let usage = EventUsage {
+                prompt: usage.prompt,
+                cached: usage.cached,
+                completion: usage.completion,
+            };
+            emitter.send(Event::Done { model, usage });
+        }
+    });
+}
+
+/// The answer failed, or was cancelled.
+pub fn error(message: &str) {
+    with(|emitter| {
+        if std::mem::take(&mut emitter.answering) {
+            emitter.send(Event::Error { message });
+        }
+    });
+}
+
+fn with(f: impl FnOnce(&mut Emitter)) {
+    if let Some(ref mut emitter) = *EMITTER.lock().unwrap_or_else(|e| e.into_inner()) {
+        f(emitter);
+    }
+}
+
+fn warn(problem: &str) {
+    if !WARNED.swap(true, Ordering::SeqCst) {
+        let notice = msg::fmt(Msg::EmitFailing, &[("problem", &problem)]);
+        eprint!("{notice}{}", input::newline());
+    }
+}
+
+impl Emitter {
+    fn connect(&mut self) {
+        match imp::Conn::open(&self.path) {
+            Ok(conn) => self.conn = Some(conn),
+            Err(e) => warn(&msg::fmt(
+                Msg::EmitNoConnection,
+                &[("path", &self.path.display()), ("error", &e)],
+            )),
+        }
+    }
+
+    fn send(&mut self, event: Event) {
+        let Some(ref mut conn) = self.conn else {
+            return;
+        };
+        let Ok(mut line) = serde_json::to_string(&event) else {
+            return;
+        };
+        line.push('\n');
+        // Whole events or none, so the reader never gets half a line followed by another.
+        if self.pending.len() + line.len() > MAX_PENDING {
+            warn(msg::get(Msg::EmitReaderBehind));
+            return;
+        }
+        self.pending.extend_from_slice(line.as_bytes());
+        match conn.write(&self.pending) {
+            Ok(n) => {
+                self.pending.drain(..n);
+            }
+            Err(e) => {
+                warn(&msg::fmt(
+                    Msg::EmitNotWritten,
+                    &[("path", &self.path.display()), ("error", &e)],
+                ));
+                self.conn = None;
+                self.pending.clear();
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        fs::{File, OpenOptions},
+        io::{self, Write},
+        os::unix::{
+            fs::{FileTypeExt, OpenOptionsExt},
+            net::UnixStream,
+        },
+        path::Path,
+    };
+
+    pub enum Conn {
+        Socket(UnixStream),
+        Pipe(File),
+    }
+
+    impl Conn {
+        pub fn open(path: &Path) -> io::Result<Conn> {
+            if path.metadata()?.file_type().is_fifo() {
+                // Fails rather than waiting when nothing is reading the pipe yet.
+                let pipe = OpenOptions::new()
+                    .write(true)
+                    .custom_flags(libc::O_NONBLOCK)
+                    .open(path)?;
+                return Ok(Conn::Pipe(pipe));
+            }
+            let socket = UnixStream::connect(path)?;
+            socket.set_nonblocking(true)?;
+            Ok(Conn::Socket(socket))
+        }
+
+        /// Writes what it can of `buf` without waiting, returning how much that was.
+        pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let result = match self {
+                Conn::Socket(socket) => socket.write(buf),
+                Conn::Pipe(pipe) => pipe.write(buf),
+            };
+            match result {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(0),
+                result => result,
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::{io, path::Path};
+
+    pub enum Conn {}
+
+    impl Conn {
+        pub fn open(_path: &Path) -> io::Result<Conn> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                msg::get(Msg::EmitUnixOnly),
+            ))
+        }
+
+        pub fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            match *self {}
+        }
+    }
+}
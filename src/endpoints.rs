@@ -0,0 +1,195 @@
+//! Failover between base URLs: with `api_bases` listing more than one, requests go to the first
+//! that's healthy. One that can't be reached, or keeps answering with a 5xx, is passed over for a
+//! while and the request goes to the next. Only a request that hasn't streamed anything yet moves:
+//! an answer that drops partway goes through the resume path instead, so nothing is said twice.
+
+use async_openai::error::OpenAIError;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::Config,
+    fallback,
+    msg::{self, Msg},
+    provider, style,
+};
+
+// How long an endpoint that's down is passed over before it's tried again.
+const COOLDOWN: Duration = Duration::from_secs(60);
+
+// 5xx answers in a row that mark an endpoint down. A single one could be the request's bad luck.
+const SERVER_ERRORS_BEFORE_DOWN: u32 = 2;
+
+static ENDPOINTS: Mutex<Vec<Endpoint>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Debug)]
+struct Endpoint {
+    base: String,
+    /// Failures since the last request that got through.
+    failures: u32,
+    down_until: Option<Instant>,
+    last_error: Option<String>,
+    requests: u32,
+    /// Until the first byte of the answer, summed over `requests`.
+    latency: Duration,
+}
+
+/// How a request failed, if its endpoint is to blame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// Never got an answer.
+    Unreachable,
+    /// Answered with a 5xx.
+    ServerError,
+}
+
+/// Sets up the endpoints in `config.api_bases`. Only the first call counts.
+pub fn init(config: &Config) {
+    let mut endpoints = ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner());
+    if !endpoints.is_empty() {
+        return;
+    }
+    for base in &config.api_bases {
+        let base = base.trim_end_matches('/').to_string();
+        if !endpoints.iter().any(|e| e.base == base) {
+            endpoints.push(Endpoint {
+                base,
+                failures: 0,
+                down_until: None,
+                last_error: None,
+                requests: 0,
+                latency: Duration::ZERO,
+            });
+        }
+    }
+}
+
+/// Whether there's more than one endpoint to fail over between.
+pub fn enabled() -> bool {
+    ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner()).len() > 1
+}
+
+/// The endpoints to try, in order: the healthy ones as listed, then those that are down, soonest
+/// back first, so a request still goes somewhere when they all are.
+pub fn order() -> Vec<String> {
+    let endpoints = ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    let (mut up, mut down): (Vec<&Endpoint>, Vec<&Endpoint>) = endpoints
+        .iter()
+        .partition(|e| e.down_until.is_none_or(|until| until <= now));
+    down.sort_by_key(|e| e.down_until);
+    up.append(&mut down);
+    up.into_iter().map(|e| e.base.clone()).collect()
+}
+
+/// Whether `err` is the endpoint's fault rather than the request's, and how.
+pub fn failure(err: &OpenAIError) -> Option<Failure> {
+    if fallback::is_offline(err) {
+        return Some(Failure::Unreachable);
+    }
+    let server_error = match err {
+        // The stream's errors only carry the status line, e.g. "Invalid status code: 503 ...".
+        OpenAIError::StreamError(e) => e.contains("code: 5"),
+        OpenAIError::ApiError(e) => {
+            e.r#type.as_deref() == Some("server_error") || e.code.as_deref() == Some("server_error")
+        }
+        _ => false,
+    };
+    server_error.then_some(Failure::ServerError)
+}
+
+/// Records a request to `base` that failed, marking it down when it's unreachable or keeps failing.
+/// Returns whether it is now down.
+pub fn failed(base: &str, failure: Failure, err: &OpenAIError) -> bool {
+    let mut endpoints = ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(endpoint) = endpoints.iter_mut().find(|e| e.base == base) else {
+        return false;
+    };
+    endpoint.failures += 1;
+    endpoint.last_error = Some(err.to_string());
+    let down = failure == Failure::Unreachable || endpoint.failures >= SERVER_ERRORS_BEFORE_DOWN;
+    if down {
+        endpoint.down_until = Some(Instant::now() + COOLDOWN);
+    }
+    down
+}
+
+/// Records a request to `base` that got an answer, `latency` after it was sent.
+pub fn succeeded(base: &str, latency: Duration) {
+    let mut endpoints = ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(endpoint) = endpoints.iter_mut().find(|e| e.base == base) {
+        endpoint.failures = 0;
+        endpoint.down_until = None;
+        endpoint.requests += 1;
+        endpoint.latency += latency;
+    }
+}
+
+/// `:endpoints`: each endpoint's health, in the order they're tried.
+pub fn print(config: &Config, color: bool) {
+    let endpoints = ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if endpoints.len() < 2 {
+        println!(
+            "{}",
+            msg::fmt(Msg::EndpointsOne, &[("base", &provider::api_base(config))])
+        );
+        return;
+    }
+    println!("{}", msg::get(Msg::EndpointsHeader));
+    let width = endpoints.iter().map(|e| e.base.len()).max().unwrap_or(0);
+    let now = Instant::now();
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        let health = match endpoint.down_until.filter(|until| *until > now) {
+            Some(until) => msg::fmt(
+                Msg::EndpointDown,
+                &[("seconds", &((until - now).as_secs() + 1))],
+            ),
+            None if endpoint.failures > 0 => msg::fmt(
+                Msg::EndpointFailing,
+                &[("requests", &requests(endpoint.failures))],
+            ),
+            None => msg::get(Msg::EndpointUp).to_string(),
+        };
+        let requests = match endpoint.requests {
+            0 => msg::get(Msg::EndpointNoRequests).to_string(),
+            n => requests(n),
+        };
+        let latency = match endpoint.requests {
+            0 => String::new(),
+            n => msg::fmt(
+                Msg::EndpointLatency,
+                &[(
+                    "seconds",
+                    &format!("{:.2}", (endpoint.latency / n).as_secs_f64()),
+                )],
+            ),
+        };
+        println!(
+            "  {}. {:<width$}  {health} | {requests}{latency}",
+            i + 1,
+            endpoint.base
+        );
+        if let Some(ref error) = endpoint
+            .last_error
+            .as_ref()
+            .filter(|_| endpoint.failures > 0)
+        {
+            println!(
+                "     {}",
+                style::dim(
+                    &msg::fmt(Msg::EndpointLastError, &[("error", error)]),
+                    color
+                )
+            );
+        }
+    }
+}
+
+fn requests(n: u32) -> String {
+    match n {
+        1 => msg::get(Msg::OneRequest).to_string(),
+        n => msg::fmt(Msg::Requests, &[("count", &n)]),
+    }
+}
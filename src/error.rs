@@ -0,0 +1,75 @@
+use async_openai::error::OpenAIError;
+use std::io;
+use thiserror::Error;
+
+use crate::msg::{self, Msg};
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("{}", msg::fmt(Msg::ErrorApi, &[("error", .0)]))]
+    Api(#[from] OpenAIError),
+    #[error("{}", msg::fmt(Msg::ErrorIo, &[("error", .0)]))]
+    Io(#[from] io::Error),
+    #[error("{}", msg::fmt(Msg::ErrorConfig, &[("error", .0)]))]
+    Config(String),
+    #[error("{0}")]
+    Command(String),
+    /// The prompt doesn't fit the model's context window.
+    #[error("{0}")]
+    TooLong(String),
+    /// The user pressed Ctrl+C during a response.
+    #[error("{}", msg::get(Msg::Cancelled))]
+    Cancelled,
+}
+
+impl AppError {
+    /// Whether this is stdout going away (e.g. piped into `head`), which should end the program quietly.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, AppError::Io(e) if e.kind() == io::ErrorKind::BrokenPipe)
+    }
+}
+
+impl From<toml::de::Error> for AppError {
+    fn from(e: toml::de::Error) -> Self {
+        AppError::Config(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // Stdout after the reader has gone, as with `gpt ... | head -1`.
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    fn answer(out: &mut impl Write) -> Result<()> {
+        write!(out, "Hello")?;
+        out.flush()?;
+        Ok(())
+    }
+
+    #[test]
+    fn writing_to_a_closed_pipe_is_a_quiet_error() {
+        let err = answer(&mut Closed).unwrap_err();
+        assert!(err.is_broken_pipe());
+    }
+
+    #[test]
+    fn other_errors_are_not_a_broken_pipe() {
+        assert!(!AppError::Io(io::ErrorKind::PermissionDenied.into()).is_broken_pipe());
+        assert!(!AppError::Cancelled.is_broken_pipe());
+    }
+}
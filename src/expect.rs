@@ -0,0 +1,205 @@
+//! Checks that an answer is in the shape asked for, set by `expect` or `:expect`: JSON, code, or
+//! text matching a pattern. Models asked for "only JSON" still sometimes wrap it in prose, which
+//! breaks whatever reads the output. A mismatch is warned about, and can be asked about once.
+
+use regex::Regex;
+
+use crate::msg::{self, Msg};
+
+/// What answers should look like.
+#[derive(Clone, Debug)]
+pub enum Expect {
+    /// The whole answer parses as JSON.
+    Json,
+    /// At least one fenced code block, and with `strict` nothing but whitespace outside them.
+    Code { strict: bool },
+    /// The answer matches the pattern somewhere.
+    Regex(Regex),
+}
+
+impl Expect {
+    /// Parses `json`, `code`, `code:strict` or `regex:<pattern>`.
+    pub fn parse(spec: &str) -> Result<Expect, String> {
+        match spec.trim() {
+            "json" => Ok(Expect::Json),
+            "code" => Ok(Expect::Code { strict: false }),
+            "code:strict" => Ok(Expect::Code { strict: true }),
+            spec => match spec.strip_prefix("regex:") {
+                Some(pattern) => Regex::new(pattern).map(Expect::Regex).map_err(|e| {
+                    msg::fmt(
+                        Msg::ExpectBadPattern,
+                        &[("pattern", &pattern), ("error", &e)],
+                    )
+                }),
+                None => Err(msg::fmt(Msg::ExpectUnknown, &[("spec", &spec)])),
+            },
+        }
+    }
+
+    /// The spec it was parsed from.
+    pub fn name(&self) -> String {
+        match self {
+            Expect::Json => "json".to_string(),
+            Expect::Code { strict: false } => "code".to_string(),
+            Expect::Code { strict: true } => "code:strict".to_string(),
+            Expect::Regex(re) => format!("regex:{}", re.as_str()),
+        }
+    }
+
+    /// What's wrong with `answer`, if anything. In English whatever the language, since it goes
+    /// back to the model in [`correction`](Expect::correction).
+    pub fn check(&self, answer: &str) -> Option<String> {
+        match self {
+            Expect::Json => serde_json::from_str::<serde_json::Value>(answer.trim())
+                .err()
+                .map(|e| format!("it isn't valid JSON ({e})")),
+            Expect::Code { strict } => {
+                let (blocks, outside) = fences(answer);
+                if blocks == 0 {
+                    Some("it has no fenced code block".to_string())
+                } else if *strict && !outside.trim().is_empty() {
+                    Some("it has text outside its code blocks".to_string())
+                } else {
+                    None
+                }
+            }
+            Expect::Regex(re) => {
+                (!re.is_match(answer)).then(|| format!("it doesn't match /{}/", re.as_str()))
+            }
+        }
+    }
+
+    /// The follow-up that asks for the answer again, after `problem`.
+    pub fn correction(&self, problem: &str) -> String {
+        let wanted = match self {
+            Expect::Json => {
+                "only valid JSON: no code fences, explanations or anything else around it"
+                    .to_string()
+            }
+            Expect::Code { strict: true } => {
+                "only fenced code blocks, with no explanation before, between or after them"
+                    .to_string()
+            }
+            Expect::Code { strict: false } => "the code in a fenced code block".to_string(),
+            Expect::Regex(re) => format!("an answer that matches the regex {}", re.as_str()),
+        };
+        format!("That answer can't be used: {problem}. Reply again with {wanted}.")
+    }
+}
+
+/// The number of complete fenced blocks in `text`, and everything outside them. A fence is a line
+/// starting with three or more backticks or tildes, closed by a line of the same character.
+fn fences(text: &str) -> (usize, String) {
+    let mut blocks = 0;
+    let mut outside = String::new();
+    let mut open: Option<(char, usize)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let fence = ['`', '~'].into_iter().find_map(|c| {
+            let n = trimmed.chars().take_while(|&x| x == c).count();
+            (n >= 3).then_some((c, n))
+        });
+        match (open, fence) {
+            (None, Some(f)) => open = Some(f),
+            (Some((c, n)), Some((fc, fnum)))
+                if c == fc && fnum >= n && trimmed[fnum * fc.len_utf8()..].trim().is_empty() =>
+            {
+                blocks += 1;
+                open = None;
+            }
+            (Some(_), _) => {}
+            (None, None) => {
+                outside.push_str(line);
+                outside.push('\n');
+            }
+        }
+    }
+    (blocks, outside)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(spec: &str, answer: &str) -> Option<String> {
+        Expect::parse(spec).unwrap().check(answer)
+    }
+
+    #[test]
+    fn specs_round_trip() {
+        for spec in ["json", "code", "code:strict", "regex:^\\d+$"] {
+            assert_eq!(Expect::parse(spec).unwrap().name(), spec);
+        }
+        assert_eq!(Expect::parse("  json ").unwrap().name(), "json");
+        assert!(Expect::parse("yaml")
+            .unwrap_err()
+            .contains("isn't something to expect"));
+        assert!(Expect::parse("regex:(")
+            .unwrap_err()
+            .contains("isn't a valid pattern"));
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(check("json", " {\"a\": [1, 2]}\n"), None);
+        assert_eq!(check("json", "42"), None);
+        let problem = check("json", "Here you go:\n{\"a\": 1}").unwrap();
+        assert!(problem.starts_with("it isn't valid JSON"), "{problem}");
+        // Fenced JSON is still not JSON.
+        assert!(check("json", "```json\n{}\n```").is_some());
+        assert!(check("json", "").is_some());
+    }
+
+    #[test]
+    fn code() {
+        let fenced = "Run this:\n```sh\nls\n```\nThen that.";
+        assert_eq!(check("code", fenced), None);
+        assert_eq!(
+            check("code:strict", fenced).as_deref(),
+            Some("it has text outside its code blocks")
+        );
+        assert_eq!(
+            check("code:strict", "\n```\nls\n```\n\n~~~\npwd\n~~~\n"),
+            None
+        );
+        assert_eq!(
+            check("code", "Just run ls.").as_deref(),
+            Some("it has no fenced code block")
+        );
+        // Never closed.
+        assert!(check("code", "```sh\nls\n").is_some());
+    }
+
+    #[test]
+    fn fences_close_only_on_a_matching_line() {
+        // Shorter, different or followed by text, none of these closes the four-backtick fence.
+        let text = "a\n````md\n```\n~~~~\n```` not yet\n````\nb\n";
+        assert_eq!(fences(text), (1, "a\nb\n".to_string()));
+        assert_eq!(fences("  ```\n  x\n  ```\n"), (1, String::new()));
+        assert_eq!(fences("``x``\n"), (0, "``x``\n".to_string()));
+    }
+
+    #[test]
+    fn regex() {
+        assert_eq!(check("regex:^\\d+$", "123"), None);
+        assert_eq!(check("regex:yes|no", "I'd say yes."), None);
+        assert_eq!(
+            check("regex:^\\d+$", "12 apples").as_deref(),
+            Some("it doesn't match /^\\d+$/")
+        );
+    }
+
+    #[test]
+    fn corrections_say_what_was_wrong_and_what_is_wanted() {
+        let expect = Expect::parse("code:strict").unwrap();
+        let problem = expect.check("Sure!\n```\nls\n```").unwrap();
+        let correction = expect.correction(&problem);
+        assert!(correction.contains("it has text outside its code blocks"));
+        assert!(correction.contains("only fenced code blocks"));
+        let correction = Expect::parse("regex:^ok$").unwrap().correction("x");
+        assert!(
+            correction.ends_with("matches the regex ^ok$."),
+            "{correction}"
+        );
+    }
+}
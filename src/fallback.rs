@@ -0,0 +1,62 @@
+use async_openai::error::OpenAIError;
+
+// Statuses that say nothing about the request itself: rate limited, missing model, or trouble on their side.
+const RETRYABLE_STATUSES: &[&str] = &["404", "408", "429", "500", "502", "503", "504", "529"];
+
+/// Whether a failed request is worth sending again to another model.
+/// Problems with the request itself, like an invalid argument or a prompt that's too long, aren't.
+pub fn is_retryable(err: &OpenAIError) -> bool {
+    match err {
+        OpenAIError::ApiError(e) => {
+            matches!(
+                e.code.as_deref(),
+                Some("model_not_found" | "rate_limit_exceeded" | "server_error")
+            ) || matches!(
+                e.r#type.as_deref(),
+                Some(
+                    "server_error"
+                        | "overloaded_error"
+                        | "requests"
+                        | "tokens"
+                        // Anthropic's names for a rate limit, an error on their side and a missing model.
+                        | "rate_limit_error"
+                        | "api_error"
+                        | "not_found_error"
+                )
+            )
+        }
+        // Failed before getting a response: the stream's errors only carry the status line, e.g. "Invalid status code: 429 Too Many Requests".
+        OpenAIError::StreamError(e) => RETRYABLE_STATUSES
+            .iter()
+            .any(|status| e.contains(&format!("code: {status}"))),
+        OpenAIError::Reqwest(e) => e.is_timeout() || e.is_connect(),
+        _ => false,
+    }
+}
+
+/// Whether a stream that failed partway is worth picking up where it stopped.
+/// Dropped connections are; an answer blocked by the provider isn't.
+pub fn is_resumable(err: &OpenAIError) -> bool {
+    matches!(err, OpenAIError::StreamError(_) | OpenAIError::Reqwest(_)) || is_retryable(err)
+}
+
+/// Whether a request failed for want of a connection, rather than anything the API said.
+pub fn is_offline(err: &OpenAIError) -> bool {
+    match err {
+        OpenAIError::Reqwest(e) => e.is_connect() || e.is_timeout(),
+        // What the event source reports when the request never got anywhere.
+        OpenAIError::StreamError(e) => e.starts_with("error sending request"),
+        _ => false,
+    }
+}
+
+/// The models to try after `primary`, in order, without repeating it.
+pub fn chain(primary: &str, fallbacks: &[String]) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    for model in fallbacks {
+        if model != primary && !res.contains(model) {
+            res.push(model.clone());
+        }
+    }
+    res
+}
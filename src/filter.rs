@@ -0,0 +1,107 @@
+//! Post-processing of responses with a shell command, set by `response_filter` or `:filter`.
+//!
+//! The finished response goes to the command's stdin and whatever it prints is shown in its place,
+//! or after it with `--tee`. The context always keeps the raw response.
+
+use std::{process::Stdio, time::Duration};
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+
+use crate::{
+    msg::{self, Msg},
+    safe::{self, Feature},
+};
+
+// A filter is for rendering, which shouldn't take long.
+const TIMEOUT: Duration = Duration::from_secs(10);
+// More output than this is cut off rather than flooding the terminal.
+const MAX_OUTPUT: usize = 1_000_000;
+
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pub command: String,
+    /// Stream the raw response as usual, and show the filtered one after it.
+    pub tee: bool,
+}
+
+impl Filter {
+    /// Parses `[--tee] <command>`, as given to `:filter` or in the config.
+    pub fn parse(text: &str) -> Option<Filter> {
+        let text = text.trim();
+        let (tee, command) = match text.strip_prefix("--tee") {
+            Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+                (true, rest.trim_start())
+            }
+            _ => (false, text),
+        };
+        (!command.is_empty()).then(|| Filter {
+            command: command.to_string(),
+            tee,
+        })
+    }
+
+    /// Runs the command with `text` on its stdin, returning what it printed, or why it failed.
+    pub async fn apply(&self, text: &str) -> Result<String, String> {
+        if let Some(why) = safe::disabled(Feature::Filter) {
+            return Err(why);
+        }
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.args(["/C", &self.command]);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.args(["-c", &self.command]);
+            command
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                msg::fmt(
+                    Msg::FilterFailedToRun,
+                    &[("command", &self.command), ("error", &e)],
+                )
+            })?;
+        // Written alongside reading, so a command that prints as it reads can't block on a full pipe.
+        if let Some(mut stdin) = child.stdin.take() {
+            let text = text.to_string();
+            tokio::spawn(async move {
+                let _ = stdin.write_all(text.as_bytes()).await;
+            });
+        }
+        let output = timeout(TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| {
+                msg::fmt(
+                    Msg::FilterTimeout,
+                    &[("command", &self.command), ("seconds", &TIMEOUT.as_secs())],
+                )
+            })?
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut message = msg::fmt(
+                Msg::FilterExited,
+                &[("command", &self.command), ("status", &output.status)],
+            );
+            if !stderr.trim().is_empty() {
+                message.push_str(&format!(": {}", stderr.trim()));
+            }
+            return Err(message);
+        }
+        let mut out = String::from_utf8_lossy(&output.stdout).into_owned();
+        if out.len() > MAX_OUTPUT {
+            let mut end = MAX_OUTPUT;
+            while !out.is_char_boundary(end) {
+                end -= 1;
+            }
+            out.truncate(end);
+            out.push('\n');
+            out.push_str(msg::get(Msg::FilterTruncated));
+        }
+        Ok(out)
+    }
+}
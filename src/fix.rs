@@ -0,0 +1,359 @@
+//! `:fix`: asks what to do about the command that just failed in the shell. The command is the
+//! last one in the shell's history file, run again to see what it prints, and the answer is a
+//! command of its own, offered to run the way `:cmd --execute` does.
+
+use async_openai::{config::OpenAIConfig, Client};
+use directories::BaseDirs;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use crate::{
+    error::{AppError, Result},
+    input,
+    msg::{self, Msg},
+    safe::{self, Feature},
+    shellcmd::{self, ShellCmd},
+    AppState,
+};
+
+// The end of what the command printed is kept, since that's where errors usually are.
+const OUTPUT_LIMIT: usize = 6000;
+
+/// What `:fix` was asked.
+#[derive(Debug)]
+pub enum Fix {
+    /// Run the last command again to see how it fails.
+    Rerun,
+    /// What the command printed, pasted in.
+    Pasted(String),
+}
+
+impl Fix {
+    /// Parses `[--no-rerun <pasted output>]`.
+    pub fn parse(args: &str) -> Option<Fix> {
+        let args = args.trim();
+        match args.strip_prefix("--no-rerun") {
+            Some(pasted) if pasted.trim().is_empty() => None,
+            Some(pasted) => Some(Fix::Pasted(pasted.trim().to_string())),
+            None if args.is_empty() => Some(Fix::Rerun),
+            None => None,
+        }
+    }
+}
+
+/// How a shell writes its history file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Runs `:fix`.
+pub async fn run(fix: Fix, client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<()> {
+    let (_, shell) = shellcmd::shell();
+    let report = match fix {
+        Fix::Pasted(output) => format!(
+            "A shell command failed with this output:\n\n```\n{output}\n```\n\nHow do I fix it?"
+        ),
+        Fix::Rerun => {
+            // Before the history is read, since the command couldn't be run anyway.
+            safe::check(Feature::Shell)?;
+            let command = last_command(&shell, state.config.shell_history.as_deref())?;
+            println!(
+                "{}",
+                msg::fmt(Msg::FixLastCommand, &[("command", &command)])
+            );
+            let mut rerun = shellcmd::shell_command(&command)?;
+            let question = msg::get(Msg::FixRerun).to_string();
+            match input::ask(state, &question).await?.as_deref() {
+                Some("y" | "Y" | "yes") => {}
+                _ => return Err(AppError::Command(msg::get(Msg::FixNotRun).to_string())),
+            }
+            let output = rerun.stdin(Stdio::null()).output().map_err(|e| {
+                AppError::Command(msg::fmt(Msg::CommandFailedToRun, &[("error", &e)]))
+            })?;
+            let status = match output.status.code() {
+                Some(0) => "It exited with 0 this time".to_string(),
+                Some(code) => format!("It exited with {code}"),
+                None => "It was killed by a signal".to_string(),
+            };
+            let mut report = format!("This command failed:\n\n```\n{command}\n```\n\n{status}");
+            let streams = [("stdout", &output.stdout), ("stderr", &output.stderr)];
+            let printed: Vec<String> = streams
+                .iter()
+                .map(|(name, bytes)| (name, tail(&String::from_utf8_lossy(bytes))))
+                .filter(|(_, text)| !text.is_empty())
+                .map(|(name, text)| format!("Its {name}:\n\n```\n{text}\n```"))
+                .collect();
+            match printed.is_empty() {
+                true => report.push_str(" without printing anything."),
+                false => {
+                    report.push_str(".\n\n");
+                    report.push_str(&printed.join("\n\n"));
+                }
+            }
+            report.push_str("\n\nHow do I fix it?");
+            report
+        }
+    };
+    let cmd = ShellCmd {
+        text: report,
+        execute: true,
+        explain: false,
+        fix: true,
+    };
+    shellcmd::run_repl(cmd, client, state).await
+}
+
+/// The end of `text`, at most `OUTPUT_LIMIT` bytes of it.
+fn tail(text: &str) -> String {
+    let text = text.trim_end();
+    if text.len() <= OUTPUT_LIMIT {
+        return text.to_string();
+    }
+    let mut start = text.len() - OUTPUT_LIMIT;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[...]\n{}", &text[start..])
+}
+
+/// The last command in the history of `shell`, other than the one that started gpt.
+fn last_command(shell: &str, configured: Option<&Path>) -> Result<String> {
+    let format = match shell {
+        "bash" | "sh" => Format::Bash,
+        "zsh" => Format::Zsh,
+        "fish" => Format::Fish,
+        _ if configured.is_some() => Format::Bash,
+        _ => {
+            return Err(AppError::Command(msg::fmt(
+                Msg::FixUnknownShell,
+                &[("shell", &shell)],
+            )))
+        }
+    };
+    let path = match configured {
+        Some(path) => path.to_path_buf(),
+        None => history_file(format)
+            .ok_or_else(|| AppError::Command(msg::get(Msg::FixNoHome).to_string()))?,
+    };
+    let bytes = fs::read(&path).map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::ReadFailed,
+            &[("path", &path.display()), ("error", &e)],
+        ))
+    })?;
+    let commands = match format {
+        Format::Bash => parse_bash(&String::from_utf8_lossy(&bytes)),
+        Format::Zsh => parse_zsh(&unmetafy(&bytes)),
+        Format::Fish => parse_fish(&String::from_utf8_lossy(&bytes)),
+    };
+    let ours = own_name();
+    commands
+        .into_iter()
+        .rev()
+        .find(|c| !c.trim().is_empty() && !is_ours(c, &ours))
+        .ok_or_else(|| {
+            AppError::Command(msg::fmt(Msg::FixNoCommands, &[("path", &path.display())]))
+        })
+}
+
+fn history_file(format: Format) -> Option<PathBuf> {
+    let dirs = BaseDirs::new()?;
+    let histfile = env::var_os("HISTFILE").filter(|f| !f.is_empty());
+    Some(match format {
+        Format::Fish => dirs.data_dir().join("fish").join("fish_history"),
+        _ if histfile.is_some() => PathBuf::from(histfile?),
+        Format::Bash => dirs.home_dir().join(".bash_history"),
+        Format::Zsh => dirs.home_dir().join(".zsh_history"),
+    })
+}
+
+fn own_name() -> String {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "gpt".to_string())
+}
+
+/// Whether the command ran gpt, which is the last thing in the history when `:fix` is used.
+fn is_ours(command: &str, name: &str) -> bool {
+    command
+        .split_whitespace()
+        .next()
+        .and_then(|word| Path::new(word).file_stem())
+        .is_some_and(|stem| stem == name)
+}
+
+/// One command a line, with `#<seconds>` lines before them when `HISTTIMEFORMAT` is set.
+fn parse_bash(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| {
+            !line
+                .strip_prefix('#')
+                .is_some_and(|t| !t.is_empty() && t.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Either plain lines or `: <start>:<elapsed>;<command>` with `EXTENDED_HISTORY`. A command of
+/// several lines has a backslash at the end of all but the last.
+fn parse_zsh(text: &str) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    let mut continued = false;
+    for line in text.lines() {
+        let (body, more) = match line.strip_suffix('\\') {
+            Some(body) => (body, true),
+            None => (line, false),
+        };
+        match commands.last_mut() {
+            Some(last) if continued => {
+                last.push('\n');
+                last.push_str(body);
+            }
+            _ => commands.push(strip_zsh_timestamp(body).to_string()),
+        }
+        continued = more;
+    }
+    commands
+}
+
+fn strip_zsh_timestamp(line: &str) -> &str {
+    let Some(rest) = line.strip_prefix(": ") else {
+        return line;
+    };
+    match rest.split_once(';') {
+        Some((stamp, command))
+            if stamp
+                .split(':')
+                .all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) =>
+        {
+            command
+        }
+        _ => line,
+    }
+}
+
+/// zsh writes some bytes as 0x83 followed by the byte XOR 0x20, so they can't be mistaken for
+/// its own markers.
+fn unmetafy(bytes: &[u8]) -> String {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&b) = iter.next() {
+        match b {
+            0x83 => {
+                if let Some(&next) = iter.next() {
+                    out.push(next ^ 0x20);
+                }
+            }
+            b => out.push(b),
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Entries like `- cmd: <command>`, followed by indented `when:` and `paths:` lines. Newlines
+/// and backslashes in a command are escaped.
+fn parse_fish(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.strip_prefix("- cmd: "))
+        .map(|cmd| {
+            let mut out = String::with_capacity(cmd.len());
+            let mut chars = cmd.chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some('n')) => {
+                        out.push('\n');
+                        chars.next();
+                    }
+                    ('\\', Some('\\')) => {
+                        out.push('\\');
+                        chars.next();
+                    }
+                    (c, _) => out.push(c),
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bash_skips_timestamps() {
+        let text = "ls\n#1700000000\ncargo build\n# a comment\n#\n";
+        assert_eq!(
+            parse_bash(text),
+            ["ls", "cargo build", "# a comment", "#"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn zsh_plain_extended_and_continued() {
+        let text = "ls\n: 1700000000:0;cargo build\n: 1700000001:3;for f in *; do\\\n  echo $f\\\ndone\n: not a stamp;x\n";
+        assert_eq!(
+            parse_zsh(text),
+            [
+                "ls",
+                "cargo build",
+                "for f in *; do\n  echo $f\ndone",
+                ": not a stamp;x"
+            ]
+            .map(String::from)
+        );
+    }
+
+    #[test]
+    fn zsh_metafied_bytes_are_restored() {
+        // "é" is 0xC3 0xA9; zsh writes the 0xA9 as 0x83 0x89.
+        assert_eq!(unmetafy(b"echo caf\xc3\x83\x89"), "echo café");
+    }
+
+    #[test]
+    fn fish_commands_are_unescaped() {
+        let text = "- cmd: ls\n  when: 1700000000\n- cmd: echo a\\nb \\\\n\n  when: 1700000001\n  paths:\n    - b\n";
+        assert_eq!(parse_fish(text), ["ls", "echo a\nb \\n"].map(String::from));
+    }
+
+    #[test]
+    fn gpt_itself_is_skipped() {
+        assert!(is_ours("gpt :fix", "gpt"));
+        assert!(is_ours("/usr/local/bin/gpt", "gpt"));
+        assert!(!is_ours("gpta", "gpt"));
+        assert!(!is_ours("cargo run gpt", "gpt"));
+    }
+
+    #[test]
+    fn last_command_reads_the_configured_file_in_the_shells_format() {
+        let dir = env::temp_dir().join(format!("gpt-cli-fix-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history");
+        let ours = own_name();
+
+        fs::write(
+            &path,
+            format!("make\n#1700000000\ncargo test\n{ours} :fix\n\n"),
+        )
+        .unwrap();
+        assert_eq!(last_command("bash", Some(&path)).unwrap(), "cargo test");
+        // Shells it doesn't know are read as bash when there's a file to read.
+        assert_eq!(last_command("nu", Some(&path)).unwrap(), "cargo test");
+        fs::write(&path, ": 1700000000:0;make\\\n  all\n").unwrap();
+        assert_eq!(last_command("zsh", Some(&path)).unwrap(), "make\n  all");
+        fs::write(&path, "- cmd: git push\n  when: 1700000000\n").unwrap();
+        assert_eq!(last_command("fish", Some(&path)).unwrap(), "git push");
+
+        fs::write(&path, "").unwrap();
+        assert!(last_command("bash", Some(&path)).is_err());
+        assert!(last_command("nu", None).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use serde::Deserialize;
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    currency::Currency,
+    error::Result,
+    msg::{self, Msg},
+    prefix::Reuse,
+    tokens::Drift,
+    usage::{fmt_thousands, Usage},
+};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FooterStyle {
+    Off,
+    /// Tokens and price.
+    Minimal,
+    /// Tokens, price and timing.
+    #[default]
+    Full,
+}
+
+/// Timing of a single streamed response.
+#[derive(Clone, Copy, Debug)]
+pub struct Timing {
+    start: Instant,
+    first_chunk: Option<Instant>,
+    last_chunk: Option<Instant>,
+    end: Option<Instant>,
+}
+
+impl Timing {
+    pub fn start() -> Self {
+        Timing {
+            start: Instant::now(),
+            first_chunk: None,
+            last_chunk: None,
+            end: None,
+        }
+    }
+
+    /// Call whenever a chunk with content arrives.
+    pub fn chunk(&mut self) {
+        let now = Instant::now();
+        self.first_chunk.get_or_insert(now);
+        self.last_chunk = Some(now);
+    }
+
+    pub fn finish(&mut self) {
+        self.end = Some(Instant::now());
+    }
+
+    pub fn total(&self) -> Duration {
+        self.end.unwrap_or_else(Instant::now) - self.start
+    }
+
+    pub fn ttft(&self) -> Option<Duration> {
+        self.first_chunk.map(|t| t - self.start)
+    }
+
+    /// Completion tokens per second, measured between the first and last content chunk.
+    pub fn tokens_per_sec(&self, completion_tokens: u32) -> Option<f64> {
+        let generating = (self.last_chunk? - self.first_chunk?).as_secs_f64();
+        (generating > 0.).then(|| completion_tokens as f64 / generating)
+    }
+}
+
+pub struct Footer<'a> {
+    pub usage: &'a Usage,
+    pub model: &'a str,
+    pub from_cache: bool,
+    /// The stream dropped and the rest of the answer came from a follow-up request.
+    pub resumed: bool,
+    /// The model was picked by `model = "auto"`, so the footer says which.
+    pub routed: bool,
+    /// With :send-only, how many of the context's messages were sent, and how many it has.
+    pub sent: Option<(usize, usize)>,
+    /// How much of the request started the same as the last one, shown with the timing.
+    pub reused: Option<Reuse>,
+    pub currency: &'a Currency,
+    pub timing: Option<&'a Timing>,
+    /// The usage was counted locally without the tokenizer, so it's only a rough estimate.
+    pub estimated: bool,
+    /// The `@` overrides the message was sent with.
+    pub overrides: Option<&'a str>,
+    /// How the prompt's estimate compared with what was billed, when the API said.
+    pub drift: Option<Drift>,
+}
+
+pub fn print(out: &mut impl Write, footer: &Footer, style: FooterStyle) -> Result<()> {
+    if style == FooterStyle::Off {
+        return Ok(());
+    }
+    let usage = footer.usage;
+    let tokens = |n: u32| match footer.estimated {
+        true => format!("~{}", fmt_thousands(n as u64)),
+        false => fmt_thousands(n as u64),
+    };
+    let prompt = if usage.cached > 0 {
+        msg::fmt(
+            Msg::FooterCachedTokens,
+            &[
+                ("prompt", &tokens(usage.prompt)),
+                ("cached", &fmt_thousands(usage.cached as u64)),
+            ],
+        )
+    } else {
+        tokens(usage.prompt)
+    };
+    let price = match footer.from_cache {
+        true => Some(0.0),
+        false => usage.price(footer.model),
+    };
+    let price = match price {
+        Some(price) => footer.currency.format(price),
+        None => msg::get(Msg::PriceUnknown).to_string(),
+    };
+    let flag = |on: bool, flag: Msg| if on { msg::get(flag) } else { "" };
+    let line = msg::fmt(
+        Msg::FooterTokens,
+        &[
+            ("prompt", &prompt),
+            ("completion", &tokens(usage.completion)),
+            ("total", &tokens(usage.total())),
+            ("estimated", &flag(footer.estimated, Msg::FooterEstimated)),
+            ("price", &price),
+            ("cached", &flag(footer.from_cache, Msg::FooterFromCache)),
+            ("resumed", &flag(footer.resumed, Msg::FooterResumed)),
+        ],
+    );
+    write!(out, "{line}")?;
+    if footer.routed {
+        let routed = msg::fmt(Msg::FooterRouted, &[("model", &footer.model)]);
+        write!(out, " | {routed}")?;
+    }
+    if let Some((sent, stored)) = footer.sent {
+        let sent = msg::fmt(Msg::FooterSent, &[("sent", &sent), ("stored", &stored)]);
+        write!(out, " | {sent}")?;
+    }
+    if let Some(overrides) = footer.overrides {
+        write!(out, " | {overrides}")?;
+    }
+
+    if let (FooterStyle::Full, Some(timing)) = (style, footer.timing) {
+        write!(out, " | {:.1}s", timing.total().as_secs_f64())?;
+        let mut details = Vec::new();
+        if let Some(ttft) = timing.ttft() {
+            details.push(format!("ttft {:.1}s", ttft.as_secs_f64()));
+        }
+        if let Some(speed) = timing.tokens_per_sec(usage.completion) {
+            details.push(format!("{speed:.0} tok/s"));
+        }
+        if !details.is_empty() {
+            write!(out, " ({})", details.join(", "))?;
+        }
+        if let Some(drift) = footer.drift {
+            write!(out, " | {}", drift.describe())?;
+        }
+        if let Some(reused) = footer.reused {
+            let reused = msg::fmt(
+                Msg::FooterReused,
+                &[
+                    ("same", &fmt_thousands(reused.same as u64)),
+                    ("total", &fmt_thousands(reused.total as u64)),
+                ],
+            );
+            write!(out, " | {reused}")?;
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
@@ -0,0 +1,344 @@
+use chrono::Utc;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{input, safe};
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}.tmp", process::id()))
+}
+
+/// The PID of the process writing `path`, if it's one of [`AtomicFile`]'s temporary files.
+pub fn temp_writer(path: &Path) -> Option<u32> {
+    let name = path.file_name()?.to_str()?;
+    let rest = name.strip_prefix('.')?.strip_suffix(".tmp")?;
+    rest.rsplit_once('.')?.1.parse().ok()
+}
+
+/// A file that is written to a temporary path next to its destination and renamed into place on
+/// `commit`, so an interrupted write never leaves something that looks complete.
+pub struct AtomicFile {
+    path: PathBuf,
+    tmp: PathBuf,
+    file: Option<File>,
+}
+
+impl AtomicFile {
+    /// Starts a new version of `path`. With `append`, the new version starts with the current contents.
+    pub fn create(path: &Path, append: bool) -> io::Result<Self> {
+        Self::open(path, append, false)
+    }
+
+    /// Starts a new version of `path` that only its owner can read, for files holding keys.
+    pub fn create_private(path: &Path) -> io::Result<Self> {
+        Self::open(path, false, true)
+    }
+
+    fn open(path: &Path, append: bool, private: bool) -> io::Result<Self> {
+        safe::check_write(path)?;
+        let tmp = temp_path(path);
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Set when the file is created, so it's never readable by anyone else, not even briefly.
+        #[cfg(unix)]
+        if private {
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        }
+        #[cfg(not(unix))]
+        let _ = private;
+        let mut file = options.open(&tmp)?;
+        if append {
+            match fs::read(path) {
+                Ok(existing) => file.write_all(&existing)?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    let _ = fs::remove_file(&tmp);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(AtomicFile {
+            path: path.to_path_buf(),
+            tmp,
+            file: Some(file),
+        })
+    }
+
+    /// Whether anything has been written yet (including existing contents when appending).
+    pub fn is_empty(&self) -> bool {
+        self.file
+            .as_ref()
+            .and_then(|f| f.metadata().ok())
+            .is_none_or(|m| m.len() == 0)
+    }
+
+    pub fn commit(mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+            file.sync_all()?;
+        }
+        fs::rename(&self.tmp, &self.path)?;
+        // The rename is only sure to survive a crash once the directory is synced too.
+        #[cfg(unix)]
+        {
+            let dir = match self.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.file {
+            Some(ref mut f) => f.write(buf),
+            None => Ok(0),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file {
+            Some(ref mut f) => f.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        // Not committed, so throw the partial write away.
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// Reads a text file with its line endings normalized to `\n`.
+pub fn read_text(path: &Path) -> io::Result<String> {
+    fs::read_to_string(path).map(|text| input::normalize_newlines(&text))
+}
+
+/// Appends `line` and a newline as a single write under an exclusive lock, so lines appended by
+/// several processes at once never interleave. If the last line was cut short, say by a crash, it's
+/// ended first, so only that line is lost rather than the new one with it.
+pub fn append_line(path: &Path, line: &[u8]) -> io::Result<()> {
+    safe::check_write(path)?;
+    let _lock = lock_lines(path)?;
+    let mut buf = Vec::with_capacity(line.len() + 2);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    if ends_mid_line(&mut file)? {
+        buf.push(b'\n');
+    }
+    buf.extend_from_slice(line);
+    buf.push(b'\n');
+    file.write_all(&buf)?;
+    file.sync_data()
+}
+
+/// Replaces the file of lines at `path` with what `rewrite` makes of its text, unless it returns
+/// `None`. The lock [`append_line`] takes is held from the read to the rename, so a line appended
+/// meanwhile by another gpt waits for the new file rather than going into the old one. Does
+/// nothing when there's no file.
+pub fn rewrite_lines(path: &Path, rewrite: impl FnOnce(&str) -> Option<Vec<u8>>) -> io::Result<()> {
+    safe::check_write(path)?;
+    let _lock = lock_lines(path)?;
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    match rewrite(&text) {
+        Some(contents) => write_atomic(path, &contents),
+        None => Ok(()),
+    }
+}
+
+// The lock on a file of lines, released when it's dropped. It's on a file of its own next to it,
+// since a rewrite renames a new file into place and a lock on the old one would guard nothing.
+fn lock_lines(path: &Path) -> io::Result<File> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_file_name(format!(".{name}.lock")))?;
+    lock.lock()?;
+    Ok(lock)
+}
+
+fn ends_mid_line(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] != b'\n')
+}
+
+/// Moves a file that couldn't be parsed out of the way, to `<name>.corrupt-<timestamp>` next to it,
+/// so it can be looked at but no longer gets in the way. Returns where it went.
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let moved = path.with_file_name(format!(
+        "{name}.corrupt-{}",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    fs::rename(path, &moved)?;
+    Ok(moved)
+}
+
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = AtomicFile::create(path, false)?;
+    file.write_all(contents)?;
+    file.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory of its own for each test, since they run at the same time.
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gpt-cli-fsutil-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn a_write_cut_short_leaves_the_old_file() {
+        let dir = dir("cut-short");
+        let path = dir.join("session.json");
+        fs::write(&path, "{\"old\": true}").unwrap();
+        let mut file = AtomicFile::create(&path, false).unwrap();
+        file.write_all(b"{\"new\": tr").unwrap();
+        // Dropped without a commit, as when the process is stopped partway.
+        drop(file);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"old\": true}");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_commit_replaces_the_file() {
+        let dir = dir("commit");
+        let path = dir.join("history");
+        fs::write(&path, "one\n").unwrap();
+        let mut file = AtomicFile::create(&path, true).unwrap();
+        assert!(!file.is_empty());
+        file.write_all(b"two\n").unwrap();
+        file.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn temporary_files_say_who_writes_them() {
+        let tmp = temp_path(Path::new("/data/usage.jsonl"));
+        assert_eq!(temp_writer(&tmp), Some(process::id()));
+        assert_eq!(temp_writer(Path::new("/data/usage.jsonl")), None);
+        assert_eq!(temp_writer(Path::new("/data/.hidden.tmp")), None);
+    }
+
+    #[test]
+    fn a_line_cut_short_is_ended_before_the_next() {
+        let dir = dir("append");
+        let path = dir.join("usage.jsonl");
+        fs::write(&path, "{\"a\": 1}\n{\"b\":").unwrap();
+        append_line(&path, b"{\"c\": 3}").unwrap();
+        append_line(&path, b"{\"d\": 4}").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"a\": 1}\n{\"b\":\n{\"c\": 3}\n{\"d\": 4}\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn no_line_appended_during_a_rewrite_is_lost() {
+        let dir = dir("rewrite");
+        let path = dir.join("usage.jsonl");
+        let old: String = (0..200).map(|n| format!("old {n}\n")).collect();
+        fs::write(&path, old).unwrap();
+        let appenders: Vec<_> = (0..4)
+            .map(|t| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for n in 0..50 {
+                        append_line(&path, format!("new {t}-{n}").as_bytes()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        // Drops a few of the old lines at a time while the new ones go in.
+        for _ in 0..40 {
+            rewrite_lines(&path, |text| {
+                let mut dropped = 0;
+                let kept: String = text
+                    .lines()
+                    .filter(|line| {
+                        let old = line.starts_with("old") && dropped < 5;
+                        dropped += usize::from(old);
+                        !old
+                    })
+                    .map(|line| format!("{line}\n"))
+                    .collect();
+                Some(kept.into_bytes())
+            })
+            .unwrap();
+        }
+        for appender in appenders {
+            appender.join().unwrap();
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let new = text.lines().filter(|l| l.starts_with("new")).count();
+        assert_eq!(new, 200);
+        assert!(!text.lines().any(|l| l.starts_with("old")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rewriting_a_missing_file_leaves_it_missing() {
+        let dir = dir("rewrite-missing");
+        let path = dir.join("usage.jsonl");
+        rewrite_lines(&path, |_| panic!("there's nothing to rewrite")).unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_corrupt_file_is_moved_aside() {
+        let dir = dir("quarantine");
+        let path = dir.join("x.json");
+        fs::write(&path, "{\"trunc").unwrap();
+        let moved = quarantine(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "{\"trunc");
+        let name = moved.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("x.json.corrupt-"), "{name}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+//! `gpt gc`: removes what the retention settings say is no longer wanted, so the data and cache
+//! directories don't grow forever. The settings are off unless set:
+//!
+//! - `max_sessions` and `session_max_age_days`: saved sessions, oldest first. One with a bookmark
+//!   in it, or that a running gpt is attached to, is always kept.
+//! - `cache_max_mb`: cached responses, oldest first, after the ones past `cache_ttl_secs`, which
+//!   always go.
+//! - `usage_retention_days`: requests in the usage ledger.
+//!
+//! Temporary files left by a write that was cut short go too, but never one whose writer is still
+//! running. With `gc_on_start` it runs when gpt starts, at most once a day.
+
+use chrono::{Duration as ChronoDuration, Utc};
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    bookmarks,
+    config::Config,
+    error::Result,
+    fsutil, ledger, lock,
+    msg::{self, Msg},
+    paths, style,
+};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// A temporary file is only removed once it's this old, even when its writer has gone, in case the
+// PID is a process on another host sharing the directory.
+const TEMP_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+// When gc last ran on start, by the file's modification time.
+fn stamp() -> PathBuf {
+    paths::data_dir().join("gc.last")
+}
+
+struct Gc {
+    dry_run: bool,
+    /// Print each file as it's removed, rather than just the totals.
+    list: bool,
+    removed: usize,
+    freed: u64,
+    /// Dropped from the usage ledger.
+    requests: usize,
+}
+
+struct Entry {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+/// Runs `gpt gc`, listing what it removes, or with `dry_run` what it would.
+pub fn run(config: &Config, dry_run: bool, color: bool) -> Result<u8> {
+    let mut gc = Gc {
+        dry_run,
+        list: true,
+        removed: 0,
+        freed: 0,
+        requests: 0,
+    };
+    gc.all(config)?;
+    let summary = match gc.summary() {
+        Some(summary) => format!("({summary})"),
+        None => msg::get(Msg::GcNothing).to_string(),
+    };
+    eprintln!("{}", style::dim(&summary, color));
+    Ok(0)
+}
+
+/// With `gc_on_start`, runs gc if it hasn't run for a day, saying as much as what it removed.
+pub fn on_start(config: &Config, color: bool) {
+    if !config.gc_on_start {
+        return;
+    }
+    let ran = fs::metadata(stamp())
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok());
+    if ran.is_some_and(|elapsed| elapsed < DAY) {
+        return;
+    }
+    let mut gc = Gc {
+        dry_run: false,
+        list: false,
+        removed: 0,
+        freed: 0,
+        requests: 0,
+    };
+    let result = paths::ensure_dir(&paths::data_dir())
+        .and_then(|_| fsutil::write_atomic(&stamp(), Utc::now().to_rfc3339().as_bytes()))
+        .map_err(Into::into)
+        .and_then(|_| gc.all(config));
+    match result.map(|_| gc.summary()) {
+        Err(e) => eprintln!("{}", msg::fmt(Msg::GcFailed, &[("error", &e)])),
+        Ok(Some(summary)) => {
+            let summary = msg::fmt(Msg::GcOnStart, &[("summary", &summary)]);
+            eprintln!("{}", style::dim(&summary, color));
+        }
+        Ok(None) => {}
+    }
+}
+
+impl Gc {
+    fn all(&mut self, config: &Config) -> Result<()> {
+        self.temp_files(&paths::data_dir())?;
+        self.temp_files(&paths::cache_dir())?;
+        self.sessions(config)?;
+        self.cache(config)?;
+        if let Some(days) = config.usage_retention_days {
+            let before = Utc::now() - ChronoDuration::days(days.into());
+            let dropped = ledger::prune(before, self.dry_run)?;
+            self.requests += dropped;
+            if dropped > 0 && self.list {
+                let pruned = match self.dry_run {
+                    true => Msg::GcWouldPruneRequests,
+                    false => Msg::GcPrunedRequests,
+                };
+                println!(
+                    "{}",
+                    msg::fmt(pruned, &[("count", &dropped), ("days", &days)])
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn sessions(&mut self, config: &Config) -> Result<()> {
+        if config.max_sessions.is_none() && config.session_max_age_days.is_none() {
+            return Ok(());
+        }
+        // Without knowing which are bookmarked, none are safe to remove.
+        let bookmarked = match bookmarks::sessions() {
+            Ok(bookmarked) => bookmarked,
+            Err(e) => {
+                eprintln!("{}", msg::fmt(Msg::GcNoBookmarks, &[("error", &e)]));
+                return Ok(());
+            }
+        };
+        let mut sessions = Vec::new();
+        collect(&paths::data_dir().join("sessions"), "json", &mut sessions)?;
+        // Newest first, so what's past max_sessions is the oldest.
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.modified));
+        let max_age = config.session_max_age_days.map(|days| DAY * days);
+        for (i, session) in sessions.iter().enumerate() {
+            let name = session
+                .path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if protected(&name, &session.path, &bookmarked) {
+                continue;
+            }
+            let why = match age(session) {
+                Some(age) if max_age.is_some_and(|max| age > max) => {
+                    let days = age.as_secs() / DAY.as_secs();
+                    msg::fmt(Msg::GcNotSaved, &[("days", &days)])
+                }
+                _ if config.max_sessions.is_some_and(|max| i >= max) => {
+                    msg::get(Msg::GcPastMaxSessions).to_string()
+                }
+                _ => continue,
+            };
+            self.remove(session, &why)?;
+            // A lock left by a gpt that's gone, which would otherwise outlive its session.
+            if !self.dry_run {
+                let _ = lock::remove_stale(&session.path.with_extension("lock"));
+            }
+        }
+        Ok(())
+    }
+
+    fn cache(&mut self, config: &Config) -> Result<()> {
+        let mut entries = Vec::new();
+        collect(&paths::cache_dir(), "json", &mut entries)?;
+        // Oldest first, the order they go in.
+        entries.sort_by_key(|e| e.modified);
+        let ttl = Duration::from_secs(config.cache_ttl_secs);
+        let mut kept = Vec::new();
+        for entry in entries {
+            match age(&entry) {
+                Some(age) if age > ttl => self.remove(&entry, msg::get(Msg::GcExpired))?,
+                _ => kept.push(entry),
+            }
+        }
+        let Some(max) = config.cache_max_mb else {
+            return Ok(());
+        };
+        let max = max * 1024 * 1024;
+        let mut total: u64 = kept.iter().map(|e| e.size).sum();
+        for entry in &kept {
+            if total <= max {
+                break;
+            }
+            self.remove(entry, msg::get(Msg::GcPastCacheSize))?;
+            total -= entry.size;
+        }
+        Ok(())
+    }
+
+    /// Removes the temporary files under `dir` whose writer has gone.
+    fn temp_files(&mut self, dir: &Path) -> Result<()> {
+        let mut temps = Vec::new();
+        collect(dir, "tmp", &mut temps)?;
+        for temp in temps {
+            let Some(pid) = fsutil::temp_writer(&temp.path) else {
+                continue;
+            };
+            if lock::alive(pid) || age(&temp).is_none_or(|age| age < TEMP_MIN_AGE) {
+                continue;
+            }
+            self.remove(&temp, msg::get(Msg::GcCutShort))?;
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, entry: &Entry, why: &str) -> io::Result<()> {
+        if !self.dry_run {
+            match fs::remove_file(&entry.path) {
+                // Already gone, e.g. removed by another gc.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+                result => result?,
+            }
+        }
+        if self.list {
+            let removed = match self.dry_run {
+                true => Msg::GcWouldRemove,
+                false => Msg::GcRemoved,
+            };
+            let path = entry.path.display();
+            println!("{}", msg::fmt(removed, &[("path", &path), ("why", &why)]));
+        }
+        self.removed += 1;
+        self.freed += entry.size;
+        Ok(())
+    }
+
+    /// What was removed, if anything.
+    fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.removed > 0 {
+            parts.push(format!("{}, {}", files(self.removed), mb(self.freed)));
+        }
+        match self.requests {
+            0 => {}
+            1 => parts.push(msg::get(Msg::GcOneRequest).to_string()),
+            n => parts.push(msg::fmt(Msg::GcRequests, &[("count", &n)])),
+        }
+        if parts.is_empty() {
+            return None;
+        }
+        let summary = match self.dry_run {
+            true => Msg::GcWouldRemoveSummary,
+            false => Msg::GcRemovedSummary,
+        };
+        let parts = parts.join(msg::get(Msg::GcAnd));
+        Some(msg::fmt(summary, &[("parts", &parts)]))
+    }
+}
+
+/// Whether the session `name`, saved at `path`, is to be kept whatever the settings say.
+fn protected(name: &str, path: &Path, bookmarked: &BTreeSet<String>) -> bool {
+    bookmarked.contains(name) || lock::held(&path.with_extension("lock"))
+}
+
+/// The files under `dir` ending in `.ext`, in the directories under it too. Only temporary files
+/// are looked for among the hidden ones, since that's how they're named.
+fn collect(dir: &Path, ext: &str, out: &mut Vec<Entry>) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            collect(&path, ext, out)?;
+            continue;
+        }
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if path.extension().is_some_and(|e| e == ext) && hidden == (ext == "tmp") {
+            out.push(Entry {
+                path,
+                modified: meta.modified()?,
+                size: meta.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn age(entry: &Entry) -> Option<Duration> {
+    entry.modified.elapsed().ok()
+}
+
+fn files(n: usize) -> String {
+    match n {
+        1 => msg::get(Msg::OneFile).to_string(),
+        n => msg::fmt(Msg::Files, &[("count", &n)]),
+    }
+}
+
+fn mb(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
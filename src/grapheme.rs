@@ -0,0 +1,147 @@
+//! Streamed answers shown a whole character at a time. A delta can end partway through what the
+//! terminal draws as one character, like an emoji followed by a skin tone in the next chunk, or a
+//! letter whose accent comes separately; written straight away, the terminal shows the parts
+//! until the rest arrives. The last grapheme of each chunk is held back until it's known to be
+//! complete, and the column the cursor is at is tracked by display width.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// Where tabs stop, as most terminals have them.
+const TAB_WIDTH: usize = 8;
+
+#[derive(Debug, Default)]
+pub struct GraphemeBuffer {
+    // The start of a grapheme the next chunk may add to.
+    pending: String,
+    column: usize,
+}
+
+impl GraphemeBuffer {
+    /// Takes the next chunk and returns what can be shown of it now.
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+        let keep = match self.pending.grapheme_indices(true).next_back() {
+            // Nothing can join a line break, so there's no need to wait. A lone "\r" might be
+            // the start of "\r\n", though.
+            Some((_, "\n" | "\r\n")) | None => self.pending.len(),
+            Some((start, _)) => start,
+        };
+        let ready: String = self.pending.drain(..keep).collect();
+        self.advance(&ready);
+        ready
+    }
+
+    /// Whatever's still held back, once the stream has ended or paused.
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        self.advance(&rest);
+        rest
+    }
+
+    /// The display column after what's been returned so far, counting wide characters, like
+    /// most CJK ones, as two.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    fn advance(&mut self, text: &str) {
+        for grapheme in text.graphemes(true) {
+            self.column = match grapheme {
+                "\n" | "\r\n" | "\r" => 0,
+                "\t" => (self.column / TAB_WIDTH + 1) * TAB_WIDTH,
+                g => self.column + g.width(),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Emoji with a skin tone, a family joined with ZWJs, a flag, a letter with a separate accent,
+    // Hangul jamo and a CRLF: everything that's more than one code point on screen.
+    const TRICKY: &str = "👍🏽 👨\u{200d}👩\u{200d}👧 🇩🇪 e\u{301}\r\n\u{1100}\u{1161}\u{11a8}!";
+
+    /// What's shown for `chunks`, as the pieces each push returned, the last from `finish`.
+    fn shown(chunks: &[&str]) -> Vec<String> {
+        let mut buffer = GraphemeBuffer::default();
+        let mut pieces: Vec<String> = chunks.iter().map(|c| buffer.push(c)).collect();
+        pieces.push(buffer.finish());
+        pieces
+    }
+
+    fn boundaries(text: &str) -> Vec<usize> {
+        text.grapheme_indices(true)
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect()
+    }
+
+    #[test]
+    fn every_split_only_ever_shows_whole_graphemes() {
+        let whole = boundaries(TRICKY);
+        let splits: Vec<usize> = TRICKY.char_indices().map(|(i, _)| i).skip(1).collect();
+        for &at in &splits {
+            let pieces = shown(&[&TRICKY[..at], &TRICKY[at..]]);
+            assert_eq!(pieces.concat(), TRICKY, "split at {at}");
+            let mut end = 0;
+            for piece in &pieces {
+                end += piece.len();
+                assert!(
+                    whole.contains(&end),
+                    "split at {at} showed part of a grapheme"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_chunk_per_code_point_still_shows_whole_graphemes() {
+        let chars: Vec<String> = TRICKY.chars().map(String::from).collect();
+        let chunks: Vec<&str> = chars.iter().map(String::as_str).collect();
+        let pieces = shown(&chunks);
+        assert_eq!(pieces.concat(), TRICKY);
+        let whole = boundaries(TRICKY);
+        let mut end = 0;
+        for piece in pieces {
+            end += piece.len();
+            assert!(whole.contains(&end), "{end}");
+        }
+    }
+
+    #[test]
+    fn the_skin_tone_waits_for_its_emoji() {
+        let mut buffer = GraphemeBuffer::default();
+        assert_eq!(buffer.push("ok 👍"), "ok ");
+        assert_eq!(buffer.push("🏽 done"), "👍🏽 don");
+        assert_eq!(buffer.finish(), "e");
+    }
+
+    #[test]
+    fn a_line_break_is_never_held_back() {
+        let mut buffer = GraphemeBuffer::default();
+        assert_eq!(buffer.push("one\n"), "one\n");
+        assert_eq!(buffer.column(), 0);
+        // But a carriage return may be half of "\r\n".
+        assert_eq!(buffer.push("two\r"), "two");
+        assert_eq!(buffer.push("\nthree"), "\r\nthre");
+    }
+
+    #[test]
+    fn columns_count_display_width() {
+        let mut buffer = GraphemeBuffer::default();
+        buffer.push("日本");
+        buffer.finish();
+        assert_eq!(buffer.column(), 4);
+        buffer.push("e\u{301}👍🏽");
+        buffer.finish();
+        assert_eq!(buffer.column(), 7);
+        buffer.push("\tx");
+        buffer.finish();
+        assert_eq!(buffer.column(), 9);
+        buffer.push("\n");
+        assert_eq!(buffer.column(), 0);
+    }
+}
@@ -0,0 +1,85 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    session, style, Message,
+};
+
+pub struct GrepArgs {
+    pub pattern: String,
+    pub case_sensitive: bool,
+    pub all: bool,
+}
+
+pub fn parse_args(args: &str) -> Result<GrepArgs> {
+    let mut res = GrepArgs {
+        pattern: String::new(),
+        case_sensitive: false,
+        all: false,
+    };
+    let mut rest = args.trim_start();
+    loop {
+        if let Some(r) = rest.strip_prefix("-s ") {
+            res.case_sensitive = true;
+            rest = r.trim_start();
+        } else if let Some(r) = rest.strip_prefix("--all ") {
+            res.all = true;
+            rest = r.trim_start();
+        } else {
+            break;
+        }
+    }
+    if rest.is_empty() {
+        return Err(AppError::Command(msg::get(Msg::GrepUsage).to_string()));
+    }
+    res.pattern = rest.to_string();
+    Ok(res)
+}
+
+pub fn build_regex(args: &GrepArgs) -> Result<Regex> {
+    RegexBuilder::new(&args.pattern)
+        .case_insensitive(!args.case_sensitive)
+        .build()
+        .map_err(|e| AppError::Command(msg::fmt(Msg::InvalidPattern, &[("error", &e)])))
+}
+
+/// Prints each matching line as `[index] role: line`, returning the number of matches.
+pub fn print_matches(prefix: &str, context: &[Message], re: &Regex, color: bool) -> usize {
+    let mut count = 0;
+    for (i, msg) in context.iter().enumerate() {
+        for line in msg.content().lines().filter(|line| re.is_match(line)) {
+            let highlighted = re.replace_all(line, |caps: &regex::Captures| {
+                style::highlight(&caps[0], color)
+            });
+            println!("{prefix}[{i}] {}: {}", msg.role(), highlighted.trim());
+            count += 1;
+        }
+    }
+    count
+}
+
+pub fn run(args: &str, context: &[Message], color: bool) -> Result<()> {
+    let args = parse_args(args)?;
+    let re = build_regex(&args)?;
+
+    let mut count = print_matches("", context, &re, color);
+    if args.all {
+        for name in session::list()? {
+            // A single unreadable session shouldn't stop the search.
+            match session::load(&name) {
+                Ok(saved) => {
+                    count += print_matches(&format!("{name} "), &saved.messages, &re, color)
+                }
+                Err(e) => eprintln!(
+                    "{}",
+                    msg::fmt(Msg::GrepSkipped, &[("name", &name), ("error", &e)])
+                ),
+            }
+        }
+    }
+    if count == 0 {
+        println!("{}", msg::get(Msg::GrepNoMatches));
+    }
+    Ok(())
+}
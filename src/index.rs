@@ -0,0 +1,340 @@
+use async_openai::{config::OpenAIConfig, Client};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    askfile::chunk_lines,
+    chat,
+    config::Config,
+    error::{AppError, Result},
+    fsutil, input,
+    msg::{self, Msg},
+    paths, provider, schema,
+    usage::fmt_thousands,
+    AppState,
+};
+
+// Inputs per embeddings request.
+const BATCH: usize = 100;
+// Build output and dependencies, never worth searching. Hidden directories are skipped too.
+const SKIP_DIRS: &[&str] = &["target", "node_modules"];
+// Bigger files are usually generated or data.
+const MAX_FILE_BYTES: u64 = 1_000_000;
+
+/// Where the index lives and how it's built, from the `[index]` table of the config.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    /// Defaults to index.json in the data directory.
+    pub path: Option<PathBuf>,
+    pub chunk_tokens: u32,
+    /// How many chunks are put in the prompt for each question.
+    pub top_k: usize,
+    pub embedding_model: String,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        IndexConfig {
+            path: None,
+            chunk_tokens: 400,
+            top_k: 5,
+            embedding_model: "text-embedding-3-small".to_string(),
+        }
+    }
+}
+
+impl IndexConfig {
+    pub fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| paths::data_dir().join("index.json"))
+    }
+}
+
+/// Embedded chunks of every text file under `root`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Index {
+    pub root: PathBuf,
+    pub model: String,
+    /// By path relative to `root`.
+    pub files: BTreeMap<String, IndexedFile>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IndexedFile {
+    /// Of the contents, so unchanged files aren't embedded again.
+    pub hash: String,
+    pub chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Chunk {
+    /// First and last line, counting from 1.
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+impl Index {
+    /// The index at `path`, or `None` if there isn't one.
+    pub fn load(path: &Path) -> Result<Option<Index>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match schema::decode(&text) {
+            Ok(index) => Ok(Some(index)),
+            // Kept, rather than rebuilt in a shape the newer gpt couldn't read.
+            Err(e @ schema::Error::Newer(_)) => Err(AppError::Command(
+                e.describe(&msg::fmt(Msg::IndexAt, &[("path", &path.display())])),
+            )),
+            // Moved aside, so the next gpt index builds a new one.
+            Err(e) => match fsutil::quarantine(path) {
+                Ok(moved) => {
+                    let what = msg::fmt(Msg::IndexAt, &[("path", &path.display())]);
+                    eprintln!(
+                        "{}",
+                        msg::fmt(
+                            Msg::FileQuarantined,
+                            &[("what", &what), ("error", &e), ("path", &moved.display())],
+                        )
+                    );
+                    Ok(None)
+                }
+                Err(_) => Err(AppError::Command(msg::fmt(
+                    Msg::IndexCorrupt,
+                    &[("path", &path.display()), ("error", &e)],
+                ))),
+            },
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            paths::ensure_dir(dir)?;
+        }
+        let json = schema::encode(self, false).map_err(io::Error::from)?;
+        fsutil::write_atomic(path, &json)?;
+        Ok(())
+    }
+}
+
+/// Runs `gpt index <dir>`, embedding the files that changed since the last run.
+pub async fn build(dir: &Path, client: &Client<OpenAIConfig>, config: &Config) -> Result<u8> {
+    let settings = &config.index;
+    let root = fs::canonicalize(dir).map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::CantIndex,
+            &[("path", &dir.display()), ("error", &e)],
+        ))
+    })?;
+    let path = settings.path();
+    // Reusing chunks only makes sense for the same directory and embedding model.
+    let mut previous = Index::load(&path)?
+        .filter(|i| i.root == root && i.model == settings.embedding_model)
+        .map(|i| i.files)
+        .unwrap_or_default();
+
+    let mut files = Vec::new();
+    walk(&root, &mut files)?;
+    let mut index = Index {
+        root: root.clone(),
+        model: settings.embedding_model.clone(),
+        files: BTreeMap::new(),
+    };
+    let mut unchanged = 0;
+    // Chunks still to be embedded: (file, hash, [(start, end, text)]).
+    let mut pending = Vec::new();
+    for file in files {
+        let Some(text) = read_text(&file) else {
+            continue;
+        };
+        let rel = file
+            .strip_prefix(&root)
+            .unwrap_or(&file)
+            .to_string_lossy()
+            .into_owned();
+        let hash: String = Sha256::digest(text.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        if let Some(old) = previous.remove(&rel).filter(|f| f.hash == hash) {
+            index.files.insert(rel, old);
+            unchanged += 1;
+            continue;
+        }
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let chunks = chunk_lines(
+            &settings.embedding_model,
+            &lines,
+            settings.chunk_tokens,
+            settings.chunk_tokens / 8,
+        )
+        .into_iter()
+        .map(|range| (range.start + 1, range.end, lines[range].concat()))
+        .filter(|(_, _, text)| !text.trim().is_empty())
+        .collect::<Vec<_>>();
+        pending.push((rel, hash, chunks));
+    }
+
+    let texts: Vec<String> = pending
+        .iter()
+        .flat_map(|(_, _, chunks)| chunks.iter().map(|(_, _, text)| text.clone()))
+        .collect();
+    let mut embeddings = Vec::with_capacity(texts.len());
+    let mut tokens = 0;
+    for batch in texts.chunks(BATCH) {
+        eprintln!(
+            "{}",
+            msg::fmt(
+                Msg::IndexEmbedding,
+                &[
+                    ("from", &(embeddings.len() + 1)),
+                    ("to", &(embeddings.len() + batch.len())),
+                    ("total", &texts.len()),
+                ],
+            )
+        );
+        let (vectors, used) =
+            provider::embed(client, &settings.embedding_model, batch.to_vec(), config).await?;
+        embeddings.extend(vectors);
+        tokens += used;
+    }
+
+    let mut embeddings = embeddings.into_iter();
+    let changed = pending.len();
+    for (rel, hash, chunks) in pending {
+        let chunks = chunks
+            .into_iter()
+            .zip(embeddings.by_ref())
+            .map(|((start, end, text), embedding)| Chunk {
+                start,
+                end,
+                text,
+                embedding,
+            })
+            .collect();
+        index.files.insert(rel, IndexedFile { hash, chunks });
+    }
+    index.save(&path)?;
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::Indexed,
+            &[
+                ("files", &index.files.len()),
+                ("path", &root.display()),
+                ("changed", &changed),
+                ("tokens", &fmt_thousands(tokens as u64)),
+                ("unchanged", &unchanged),
+            ],
+        )
+    );
+    Ok(0)
+}
+
+/// Every file under `dir`, without following symlinks.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        let kind = entry.file_type()?;
+        if kind.is_dir() && !SKIP_DIRS.contains(&name.as_ref()) {
+            walk(&entry.path(), files)?;
+        } else if kind.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// The file's contents if it looks like text: not too big, UTF-8 and without NUL bytes.
+fn read_text(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let text = String::from_utf8(fs::read(path).ok()?).ok()?;
+    (!text.contains('\0')).then(|| input::normalize_newlines(&text))
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm(a) * norm(b)).max(f32::EPSILON)
+}
+
+/// Answers a question from the index: the closest chunks go in the prompt, numbered so the model
+/// can cite them, and the sources it cited are listed after the answer.
+pub async fn ask(
+    question: String,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let settings = state.config.index.clone();
+    if state.index.is_none() {
+        state.index = Index::load(&settings.path())?;
+    }
+    let Some(ref index) = state.index else {
+        return Err(AppError::Command(msg::fmt(
+            Msg::NoIndex,
+            &[("path", &settings.path().display())],
+        )));
+    };
+    let (vectors, _) =
+        provider::embed(client, &index.model, vec![question.clone()], &state.config).await?;
+    let query = vectors
+        .first()
+        .ok_or_else(|| AppError::Command(msg::get(Msg::NoQuestionEmbedding).to_string()))?;
+
+    let mut scored: Vec<(f32, &str, &Chunk)> = index
+        .files
+        .iter()
+        .flat_map(|(file, indexed)| indexed.chunks.iter().map(move |c| (file.as_str(), c)))
+        .map(|(file, chunk)| (cosine(query, &chunk.embedding), file, chunk))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(settings.top_k);
+
+    let mut prompt = "Answer the question using the sources below, which are excerpts from files. Cite each source you use by its number in square brackets, like [2]. If the sources don't answer the question, say so.\n\n".to_string();
+    let mut references = Vec::new();
+    for (i, (_, file, chunk)) in scored.iter().enumerate() {
+        let reference = format!("{file}:{}-{}", chunk.start, chunk.end);
+        prompt.push_str(&format!(
+            "[{}] {reference}\n```\n{}\n```\n\n",
+            i + 1,
+            chunk.text.trim_end()
+        ));
+        references.push(reference);
+    }
+    prompt.push_str(&format!("Question: {question}"));
+
+    chat::send(prompt, state.auto_pipe, client, state).await?;
+    let Some(answer) = state.context.last().filter(|m| !m.is_user()) else {
+        return Ok(());
+    };
+    let cited: Vec<_> = references
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| answer.content.contains(&format!("[{}]", i + 1)))
+        .collect();
+    if !cited.is_empty() {
+        println!("{}", msg::get(Msg::IndexReferences));
+        for (i, reference) in cited {
+            println!("  [{}] {reference}", i + 1);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! The cap on requests in flight at once. Every request to a provider waits for a slot in
+//! [`provider::stream`](crate::provider::stream) or [`provider::embed`](crate::provider::embed),
+//! whether it's the conversation's, a background title, one chunk of a file being asked about or
+//! a batch of embeddings, so nothing that fires several can go past it.
+//! Background requests are started with [`spawn`], so Ctrl+C can stop them along with the answer.
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
+};
+
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+static SLOTS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+static TASKS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// Sets the cap. Only the first call counts, and it has to come before the first request.
+pub fn init(max: usize) {
+    let _ = SLOTS.set(Arc::new(Semaphore::new(max.max(1))));
+}
+
+/// Waits for a slot, which is freed when the permit is dropped.
+pub async fn acquire() -> OwnedSemaphorePermit {
+    let slots = SLOTS.get_or_init(|| Arc::new(Semaphore::new(DEFAULT_MAX_IN_FLIGHT)));
+    // The semaphore is never closed.
+    slots
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("the request semaphore was closed")
+}
+
+/// Runs `task` in the background until it finishes or [`abort_all`] stops it.
+pub fn spawn(task: impl Future<Output = ()> + Send + 'static) {
+    let handle = tokio::spawn(task);
+    let mut tasks = TASKS.lock().unwrap_or_else(|e| e.into_inner());
+    tasks.retain(|t| !t.is_finished());
+    tasks.push(handle);
+}
+
+/// Stops every background request still running, returning how many there were.
+pub fn abort_all() -> usize {
+    let tasks = std::mem::take(&mut *TASKS.lock().unwrap_or_else(|e| e.into_inner()));
+    let mut running = 0;
+    for task in tasks.into_iter().filter(|t| !t.is_finished()) {
+        task.abort();
+        running += 1;
+    }
+    running
+}
+
+/// Gives the background requests still running up to `within` to finish, so what they used is
+/// recorded, then stops the rest, returning how many had to be stopped.
+pub async fn finish_all(within: Duration) -> usize {
+    let tasks = std::mem::take(&mut *TASKS.lock().unwrap_or_else(|e| e.into_inner()));
+    let deadline = tokio::time::Instant::now() + within;
+    let mut stopped = 0;
+    for mut task in tasks {
+        if tokio::time::timeout_at(deadline, &mut task).await.is_err() {
+            task.abort();
+            stopped += 1;
+        }
+    }
+    stopped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    // Says so on the channel when dropped, as a request's slot and connection are when aborted.
+    struct Dropped(mpsc::UnboundedSender<()>);
+
+    impl Drop for Dropped {
+        fn drop(&mut self) {
+            let _ = self.0.send(());
+        }
+    }
+
+    #[tokio::test]
+    async fn abort_all_stops_every_background_request() {
+        let (started_tx, mut started) = mpsc::unbounded_channel();
+        let (dropped_tx, mut dropped) = mpsc::unbounded_channel();
+        // More than there are slots, so some are still waiting for one when they're stopped.
+        let tasks = DEFAULT_MAX_IN_FLIGHT + 2;
+        for _ in 0..tasks {
+            let started = started_tx.clone();
+            let guard = Dropped(dropped_tx.clone());
+            spawn(async move {
+                let _guard = guard;
+                let _slot = acquire().await;
+                let _ = started.send(());
+                std::future::pending::<()>().await;
+            });
+        }
+        drop(dropped_tx);
+        started.recv().await.unwrap();
+
+        assert_eq!(abort_all(), tasks);
+        for _ in 0..tasks {
+            tokio::time::timeout(Duration::from_secs(5), dropped.recv())
+                .await
+                .unwrap()
+                .unwrap();
+        }
+        assert_eq!(abort_all(), 0);
+    }
+
+    #[tokio::test]
+    async fn finish_all_waits_for_what_finishes_in_time() {
+        let (done_tx, mut done) = mpsc::unbounded_channel();
+        spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let _ = done_tx.send(());
+        });
+        spawn(std::future::pending());
+        assert_eq!(finish_all(Duration::from_millis(500)).await, 1);
+        assert!(done.try_recv().is_ok());
+    }
+}
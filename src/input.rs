@@ -0,0 +1,341 @@
+use crossterm::{
+    event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use futures::StreamExt;
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    error::{AppError, Result},
+    AppState,
+};
+
+pub type Lines = mpsc::UnboundedReceiver<io::Result<String>>;
+
+// Where the reader sends its lines, for the lines [`Typing`] puts together while it has the keys.
+// Weak, so the channel still closes when the reader hits EOF.
+static LINES: OnceLock<mpsc::WeakUnboundedSender<io::Result<String>>> = OnceLock::new();
+
+// Set by Handover: the reader leaves the terminal alone. READING is set while it might be reading,
+// so a handover can wait for that read to finish.
+static HANDED_OVER: AtomicBool = AtomicBool::new(false);
+static READING: AtomicBool = AtomicBool::new(false);
+
+// Set while the terminal is in raw mode, where a newline doesn't go back to the start of the line.
+static RAW: AtomicBool = AtomicBool::new(false);
+
+/// Reads stdin on its own thread so the main loop can wait for input and signals at the same time.
+/// The receiver yields `None` once stdin hits EOF.
+pub fn spawn_stdin_reader() -> Lines {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let _ = LINES.set(tx.downgrade());
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        // Only a terminal can be handed over; anything else is read straight through.
+        let terminal = stdin.is_terminal();
+        let mut buf = [0; 1024];
+        // What's been read of the current line, which a pipe can hand over in pieces.
+        let mut line = Vec::new();
+        loop {
+            if terminal && !ready_to_read() {
+                continue;
+            }
+            let read = stdin.read(&mut buf);
+            READING.store(false, Ordering::SeqCst);
+            let n = match read {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            };
+            for &byte in &buf[..n] {
+                line.push(byte);
+                if byte == b'\n' {
+                    let text = normalize_newlines(&String::from_utf8_lossy(&line));
+                    line.clear();
+                    if tx.send(Ok(text)).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        // A last line without a newline still counts.
+        if !line.is_empty() {
+            let _ = tx.send(Ok(normalize_newlines(&String::from_utf8_lossy(&line))));
+        }
+    });
+    rx
+}
+
+// Waits a moment for the terminal to have something for the reader, and says whether it should read
+// it. When it does, READING is left set for the reader to clear.
+fn ready_to_read() -> bool {
+    if HANDED_OVER.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(20));
+        return false;
+    }
+    if !readable(Duration::from_millis(50)) {
+        return false;
+    }
+    READING.store(true, Ordering::SeqCst);
+    // Checked again now that a handover would wait for us, in case one started meanwhile.
+    if HANDED_OVER.load(Ordering::SeqCst) {
+        READING.store(false, Ordering::SeqCst);
+        return false;
+    }
+    true
+}
+
+#[cfg(unix)]
+fn readable(timeout: Duration) -> bool {
+    let mut fd = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: poll is given the one pollfd it reads and writes.
+    match unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) } {
+        0 => false,
+        // A resize interrupts it; any other error is left for the read to report.
+        -1 => io::Error::last_os_error().kind() != io::ErrorKind::Interrupted,
+        _ => true,
+    }
+}
+
+#[cfg(windows)]
+fn readable(timeout: Duration) -> bool {
+    use windows_sys::Win32::{
+        Foundation::WAIT_TIMEOUT,
+        System::{
+            Console::{GetStdHandle, STD_INPUT_HANDLE},
+            Threading::WaitForSingleObject,
+        },
+    };
+
+    // SAFETY: the handle comes straight from GetStdHandle.
+    unsafe {
+        WaitForSingleObject(GetStdHandle(STD_INPUT_HANDLE), timeout.as_millis() as u32)
+            != WAIT_TIMEOUT
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn readable(_timeout: Duration) -> bool {
+    true
+}
+
+/// While this is alive the reader leaves the terminal alone, so crossterm can read its events
+/// instead. Once it's dropped, the reader picks up where it left off.
+pub struct Handover(());
+
+impl Handover {
+    pub fn start() -> Handover {
+        HANDED_OVER.store(true, Ordering::SeqCst);
+        // What it was reading when this started is already on its way to the lines.
+        while READING.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        Handover(())
+    }
+}
+
+impl Drop for Handover {
+    fn drop(&mut self) {
+        HANDED_OVER.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The terminal in crossterm's raw mode while this is alive, put back however it's dropped. Keys
+/// arrive one by one as events, unechoed, and Ctrl+C is one of them rather than a signal.
+pub struct RawMode(());
+
+impl RawMode {
+    pub fn enable() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        RAW.store(true, Ordering::SeqCst);
+        Ok(RawMode(()))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        RAW.store(false, Ordering::SeqCst);
+    }
+}
+
+/// What ends a line printed while the terminal may be in raw mode.
+pub fn newline() -> &'static str {
+    match RAW.load(Ordering::SeqCst) {
+        true => "\r\n",
+        false => "\n",
+    }
+}
+
+/// Writes to `W` with each newline going back to the start of the line in raw mode too.
+pub struct RawLines<W>(pub W);
+
+impl<W: Write> Write for RawLines<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !RAW.load(Ordering::SeqCst) || !buf.contains(&b'\n') {
+            return self.0.write(buf);
+        }
+        let text = String::from_utf8_lossy(buf).replace('\n', "\r\n");
+        self.0.write_all(text.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// A key pressed while a response streams that's for the response rather than the next line.
+pub enum Pressed {
+    /// Escape: stop the response.
+    Stop,
+    /// Ctrl+C, which raw mode turns into a key.
+    Interrupt,
+}
+
+/// While a response streams, the keys are read as they're pressed so Escape can stop it. What's
+/// typed meanwhile is put together into lines, read once the response is done. The terminal is put
+/// back however it's dropped; a line still being typed then is dropped with it.
+pub struct Typing {
+    events: EventStream,
+    line: String,
+    // Dropped before the handover ends, so the terminal is back to normal before the reader resumes.
+    _raw: RawMode,
+    _handover: Handover,
+}
+
+impl Typing {
+    /// Does nothing unless `enabled`, e.g. when nobody is at the keyboard.
+    pub fn start(enabled: bool) -> Option<Typing> {
+        if !enabled {
+            return None;
+        }
+        let handover = Handover::start();
+        let raw = RawMode::enable().ok()?;
+        Some(Typing {
+            events: EventStream::new(),
+            line: String::new(),
+            _raw: raw,
+            _handover: handover,
+        })
+    }
+
+    /// Waits for Escape or Ctrl+C, keeping the lines typed until then.
+    pub async fn pressed(&mut self) -> Pressed {
+        loop {
+            let key = match self.events.next().await {
+                Some(Ok(Event::Key(key))) if key.kind != KeyEventKind::Release => key,
+                Some(Ok(_)) => continue,
+                // There's nothing more to read, so nothing more will be pressed.
+                Some(Err(_)) | None => std::future::pending().await,
+            };
+            let control = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Esc => return Pressed::Stop,
+                KeyCode::Char('c') if control => return Pressed::Interrupt,
+                KeyCode::Char(c) if !control => self.line.push(c),
+                KeyCode::Backspace => {
+                    self.line.pop();
+                }
+                KeyCode::Enter => {
+                    let line = std::mem::take(&mut self.line) + "\n";
+                    if let Some(lines) = LINES.get().and_then(|lines| lines.upgrade()) {
+                        let _ = lines.send(Ok(line));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Where the REPL's lines come from: what's typed, or a script run as if it had been typed.
+pub trait InputSource {
+    /// The next line, without its newline, or `None` once there are no more.
+    async fn next_line(&mut self, state: &mut AppState) -> Option<Result<String>>;
+
+    /// Whether there's nothing left, so the session can end without asking for more.
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    /// Shows the error the last line ended in, and says whether that ends the session.
+    fn report(&mut self, e: &AppError) -> bool {
+        println!("{e}");
+        false
+    }
+}
+
+/// What's typed or piped, from the reader [`spawn_stdin_reader`] starts.
+pub struct Stdin;
+
+impl InputSource for Stdin {
+    async fn next_line(&mut self, state: &mut AppState) -> Option<Result<String>> {
+        let lines = state.lines.as_mut()?;
+        // Lines typed while the last response was streaming are already waiting.
+        let queued = !lines.is_empty();
+        let line = match lines.recv().await? {
+            Ok(line) => line.trim_end_matches('\n').to_string(),
+            Err(e) => return Some(Err(e.into())),
+        };
+        if queued && state.interactive {
+            println!("{}", line.trim());
+        }
+        Some(Ok(line))
+    }
+}
+
+/// Turns Windows (`\r\n`) and old Mac (`\r`) line endings into `\n`, so text typed, pasted or read
+/// from a file looks the same everywhere.
+pub fn normalize_newlines(text: &str) -> String {
+    if !text.contains('\r') {
+        return text.to_string();
+    }
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Asks the user a question and waits for the answer. Returns `None` without asking when
+/// nobody is there to answer, e.g. in one-shot mode or when stdin is piped.
+pub async fn ask(state: &mut AppState, question: &str) -> Result<Option<String>> {
+    if !state.interactive {
+        return Ok(None);
+    }
+    let Some(ref mut lines) = state.lines else {
+        return Ok(None);
+    };
+    print!("{question} ");
+    io::stdout().flush()?;
+    match lines.recv().await {
+        Some(line) => Ok(Some(line?.trim().to_string())),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_line_ending_becomes_a_newline() {
+        assert_eq!(normalize_newlines("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert_eq!(normalize_newlines("\r\n\r\n"), "\n\n");
+        assert_eq!(normalize_newlines("no endings"), "no endings");
+    }
+}
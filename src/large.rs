@@ -0,0 +1,249 @@
+//! Keeps one huge message, like a pasted log file, from being sent again in full with every later
+//! request. Once it's been answered, a message over `[large_message] tokens` is kept as it is, cut
+//! down to its start and end, or replaced by a summary from a cheap model, as `action` says or the
+//! user picks. What's stored is what later requests send; the original can be kept on disk.
+
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionStreamOptions, CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::{
+    error::{AppError, Result},
+    fsutil, input, models,
+    msg::{self, Msg},
+    paths, provider, signals, style,
+    tokens::count_tokens,
+    usage::{fmt_thousands, Purpose},
+    AppState,
+};
+
+// Room left in the summarising model's window for the instructions and the summary.
+const SUMMARY_MARGIN: u32 = 2000;
+const SUMMARY_MAX_TOKENS: u32 = 1000;
+
+/// What happens to a message over the threshold.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LargeAction {
+    /// Ask each time. Without anyone to ask, it's kept.
+    #[default]
+    Ask,
+    Keep,
+    Truncate,
+    Summarize,
+}
+
+/// The `[large_message]` table of the config.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LargeMessageConfig {
+    /// Messages with more tokens than this are shortened. 0 turns it off.
+    pub tokens: u32,
+    pub action: LargeAction,
+    /// Save the original next to the sessions before it's shortened, so `:show` can point to it.
+    pub keep_original: bool,
+}
+
+impl Default for LargeMessageConfig {
+    fn default() -> Self {
+        LargeMessageConfig {
+            tokens: 8000,
+            action: LargeAction::default(),
+            keep_original: true,
+        }
+    }
+}
+
+/// Shortens the message at `index` of the context if it's over the threshold. Failing to only
+/// means it's kept as it is, which is said.
+pub async fn shrink(client: &Client<OpenAIConfig>, state: &mut AppState, index: usize) {
+    if let Err(e) = shrink_message(client, state, index).await {
+        let notice = msg::fmt(Msg::LargeKept, &[("error", &e)]);
+        println!("{}", style::dim(&notice, state.color));
+    }
+}
+
+async fn shrink_message(
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+    index: usize,
+) -> Result<()> {
+    let threshold = state.config.large_message.tokens;
+    let Some(message) = state.context.get(index).filter(|m| m.is_user()) else {
+        return Ok(());
+    };
+    // A token is at least a byte, so most messages are ruled out without running the tokenizer.
+    if threshold == 0 || message.content.len() <= threshold as usize {
+        return Ok(());
+    }
+    let text = message.content.clone();
+    let tokens = count_tokens(&state.model, &text);
+    if tokens <= threshold {
+        return Ok(());
+    }
+
+    let action = match state.config.large_message.action {
+        LargeAction::Ask => {
+            let question = msg::fmt(Msg::LargeAsk, &[("tokens", &fmt_thousands(tokens as u64))]);
+            match input::ask(state, &question).await?.as_deref() {
+                Some("t" | "T") => LargeAction::Truncate,
+                Some("s" | "S") => LargeAction::Summarize,
+                _ => LargeAction::Keep,
+            }
+        }
+        action => action,
+    };
+    let shortened = match action {
+        LargeAction::Ask | LargeAction::Keep => return Ok(()),
+        LargeAction::Truncate => truncate(&text, tokens, threshold),
+        LargeAction::Summarize => tokio::select! {
+            result = summarize(client, state, &text, tokens) => result?,
+            _ = signals::interrupt() => return Err(AppError::Cancelled),
+        },
+    };
+    let original = match state.config.large_message.keep_original {
+        true => Some(save_original(&text)?),
+        false => None,
+    };
+
+    let notice = match original {
+        Some(ref path) => msg::fmt(
+            Msg::LargeStoredOriginal,
+            &[
+                (
+                    "tokens",
+                    &fmt_thousands(count_tokens(&state.model, &shortened) as u64),
+                ),
+                ("path", &path.display()),
+            ],
+        ),
+        None => msg::fmt(
+            Msg::LargeStored,
+            &[(
+                "tokens",
+                &fmt_thousands(count_tokens(&state.model, &shortened) as u64),
+            )],
+        ),
+    };
+    let message = &mut state.context[index];
+    message.content = shortened;
+    message.original = original;
+    println!("{}", style::dim(&notice, state.color));
+    Ok(())
+}
+
+/// The start and end of the text, about `keep` tokens in all, around a marker saying how much was
+/// left out. The cut is made by bytes in proportion to the tokens, which is close enough and
+/// doesn't run the tokenizer over the text again, then moved to a line break or a space nearby.
+pub fn truncate(text: &str, tokens: u32, keep: u32) -> String {
+    let half = (text.len() as u64 * keep as u64 / tokens.max(1) as u64 / 2) as usize;
+    let mut head = floor_char_boundary(text, half);
+    if let Some(at) = ['\n', ' ']
+        .iter()
+        .find_map(|&c| text[..head].rfind(c).filter(|&at| at >= head / 2))
+    {
+        head = at;
+    }
+    let mut tail = ceil_char_boundary(text, text.len() - half);
+    if let Some(at) = ['\n', ' ']
+        .iter()
+        .find_map(|&c| text[tail..].find(c).filter(|&at| at <= half / 2))
+    {
+        tail += at + 1;
+    }
+    if head >= tail {
+        return text.to_string();
+    }
+    format!(
+        "{}\n\n[… about {} tokens left out …]\n\n{}",
+        text[..head].trim_end(),
+        fmt_thousands(tokens.saturating_sub(keep) as u64),
+        text[tail..].trim_start()
+    )
+}
+
+fn floor_char_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_char_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+/// A summary from the provider's cheap model, marked as one so the conversation's model knows
+/// what it's looking at. Text too big for that model is truncated to fit first.
+async fn summarize(
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+    text: &str,
+    tokens: u32,
+) -> Result<String> {
+    let model = state.config.provider.title_model();
+    let window = models::lookup(model).map_or(0, |info| info.context_window);
+    let fits = window.saturating_sub(SUMMARY_MARGIN);
+    let text = match tokens > fits && fits > 0 {
+        true => truncate(text, tokens, fits),
+        false => text.to_string(),
+    };
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .max_completion_tokens(SUMMARY_MAX_TOKENS)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content("Summarize the user's message so the summary can stand in for it in the rest of a conversation. Keep anything a later question might need: names, numbers, error messages, file paths and identifiers. Reply with the summary only.")
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(text)
+                .build()?
+                .into(),
+        ])
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()?;
+    println!(
+        "{}",
+        style::dim(
+            &msg::fmt(Msg::LargeSummarizing, &[("model", &model)]),
+            state.color
+        )
+    );
+    let (summary, _) = provider::complete(client, request, &state.config, Purpose::Answer).await?;
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return Err(AppError::Command(msg::get(Msg::SummaryEmpty).to_string()));
+    }
+    Ok(format!(
+        "[A summary of a message of ~{} tokens]\n\n{summary}",
+        fmt_thousands(tokens as u64)
+    ))
+}
+
+/// Saves the text under `originals` in the data directory, named after its hash so the same
+/// paste is only stored once.
+fn save_original(text: &str) -> Result<PathBuf> {
+    let dir = paths::data_dir().join("originals");
+    paths::ensure_dir(&dir)?;
+    let hash = Sha256::digest(text.as_bytes());
+    let name: String = hash[..8].iter().map(|b| format!("{b:02x}")).collect();
+    let path = dir.join(format!("{name}.txt"));
+    if !path.exists() {
+        fsutil::write_atomic(&path, text.as_bytes())?;
+    }
+    Ok(path)
+}
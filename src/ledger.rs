@@ -0,0 +1,579 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cli::{GroupBy, ReportArgs, ReportFormat},
+    config::Config,
+    error::{AppError, Result},
+    fsutil,
+    msg::{self, Msg},
+    paths, provider, schema, tokens,
+    usage::{fmt_thousands, Usage},
+};
+
+/// One request, as a line of usage.jsonl in the data directory.
+#[derive(Debug, Deserialize, Serialize)]
+struct Entry {
+    timestamp: DateTime<Utc>,
+    model: String,
+    prompt_tokens: u32,
+    cached_tokens: u32,
+    completion_tokens: u32,
+    /// USD, at the prices known when the request was made. `None` when they weren't known.
+    #[serde(default, deserialize_with = "cost")]
+    cost: Option<f64>,
+    /// The OpenAI organization billed, when one was set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    organization: Option<String>,
+    /// What gpt estimated the prompt tokens would be, for the requests whose estimate can be
+    /// compared with what was billed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimated_prompt_tokens: Option<u32>,
+}
+
+fn path() -> PathBuf {
+    paths::data_dir().join("usage.jsonl")
+}
+
+/// Adds a request to the ledger `gpt report` reads, unless `usage_ledger` is off, with the
+/// estimate of its prompt tokens when there's one to compare. Any number of instances can do this
+/// at once.
+pub fn record(config: &Config, usage: &Usage, model: &str, estimate: Option<u32>) {
+    if !config.usage_ledger {
+        return;
+    }
+    let entry = Entry {
+        timestamp: Utc::now(),
+        model: model.to_string(),
+        prompt_tokens: usage.prompt,
+        cached_tokens: usage.cached,
+        completion_tokens: usage.completion,
+        cost: usage.price(model),
+        organization: provider::organization(model, config),
+        estimated_prompt_tokens: estimate,
+    };
+    let path = path();
+    let result = schema::encode(&entry, false)
+        .map_err(io::Error::from)
+        .and_then(|line| {
+            paths::ensure_dir(&paths::data_dir())?;
+            fsutil::append_line(&path, &line)
+        });
+    if let Err(e) = result {
+        eprintln!(
+            "{}",
+            msg::fmt(
+                Msg::UsageNotRecorded,
+                &[("path", &path.display()), ("error", &e)]
+            )
+        );
+    }
+}
+
+/// Drops the requests from before `before`, returning how many there were. With `dry_run` they're
+/// only counted. Lines that can't be read are kept, since they may be from a newer gpt. Another gpt
+/// can go on recording usage meanwhile.
+pub fn prune(before: DateTime<Utc>, dry_run: bool) -> Result<usize> {
+    prune_at(&path(), before, dry_run)
+}
+
+fn prune_at(path: &Path, before: DateTime<Utc>, dry_run: bool) -> Result<usize> {
+    if dry_run {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        return Ok(split(&text, before).1);
+    }
+    let mut dropped = 0;
+    fsutil::rewrite_lines(path, |text| {
+        let (kept, n) = split(text, before);
+        dropped = n;
+        (n > 0).then(|| kept.into_bytes())
+    })?;
+    Ok(dropped)
+}
+
+// The lines of the ledger from `before` on, and how many were older.
+fn split(text: &str, before: DateTime<Utc>) -> (String, usize) {
+    let mut kept = String::new();
+    let mut dropped = 0;
+    for line in text.lines() {
+        let timestamp = serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|v| v["timestamp"].as_str()?.parse::<DateTime<Utc>>().ok());
+        match timestamp {
+            Some(timestamp) if timestamp < before => dropped += 1,
+            _ => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+    }
+    (kept, dropped)
+}
+
+/// The last `n` models requests were made to, most recent first. Empty when there's no ledger.
+pub fn recent_models(n: usize) -> Vec<String> {
+    let text = fs::read_to_string(path()).unwrap_or_default();
+    let mut models: Vec<String> = Vec::new();
+    for line in text.lines().rev() {
+        if models.len() == n {
+            break;
+        }
+        let value = serde_json::from_str::<serde_json::Value>(line).ok();
+        let Some(model) = value.as_ref().and_then(|v| v["model"].as_str()) else {
+            continue;
+        };
+        if !models.iter().any(|m| m == model) {
+            models.push(model.to_string());
+        }
+    }
+    models
+}
+
+/// Totals for one row of the report.
+#[derive(Debug, Default, Serialize)]
+struct Row {
+    requests: u32,
+    prompt_tokens: u64,
+    cached_tokens: u64,
+    completion_tokens: u64,
+    cost: f64,
+    /// Requests left out of `cost`, since their prices weren't known.
+    unpriced: u32,
+}
+
+impl Row {
+    fn add(&mut self, entry: &Entry) {
+        self.requests += 1;
+        self.prompt_tokens += entry.prompt_tokens as u64;
+        self.cached_tokens += entry.cached_tokens as u64;
+        self.completion_tokens += entry.completion_tokens as u64;
+        match entry.cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced += 1,
+        }
+    }
+}
+
+/// Runs `gpt report`, returning the exit code.
+pub fn report(args: &ReportArgs, config: &Config) -> Result<u8> {
+    let text = match fs::read_to_string(path()) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut rows: BTreeMap<String, Row> = BTreeMap::new();
+    let mut total = Row::default();
+    // A line that doesn't parse is skipped rather than spoiling the report. Those from a newer
+    // version are counted, to say the report is missing them.
+    let mut newer = 0;
+    let entries = text
+        .lines()
+        .filter_map(|l| match schema::decode::<Entry>(l) {
+            Ok(entry) => Some(entry),
+            Err(schema::Error::Newer(_)) => {
+                newer += 1;
+                None
+            }
+            Err(_) => None,
+        })
+        .collect::<Vec<_>>();
+    if newer > 0 {
+        eprintln!(
+            "{}",
+            msg::fmt(
+                Msg::UsageNewer,
+                &[("count", &newer), ("path", &path().display())]
+            )
+        );
+    }
+    let entries: Vec<(String, Entry)> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let day = entry.timestamp.with_timezone(&Local).date_naive();
+            if args.since.is_some_and(|since| day < since) {
+                return None;
+            }
+            let key = match args.group_by {
+                GroupBy::Model => entry.model.clone(),
+                GroupBy::Day => day.to_string(),
+                GroupBy::Org => entry
+                    .organization
+                    .clone()
+                    .unwrap_or_else(|| "(default)".to_string()),
+            };
+            Some((key, entry))
+        })
+        .collect();
+    let group = match args.group_by {
+        GroupBy::Model => "model",
+        GroupBy::Day => "day",
+        GroupBy::Org => "org",
+    };
+    if args.drift {
+        return drift_report(args, group, &entries);
+    }
+    for (key, entry) in entries {
+        rows.entry(key).or_default().add(&entry);
+        total.add(&entry);
+    }
+
+    let currency = &config.currency;
+    match args.format {
+        ReportFormat::Table => {
+            if rows.is_empty() {
+                println!(
+                    "{}",
+                    msg::fmt(Msg::UsageNone, &[("since", &since_text(args.since))])
+                );
+                return Ok(0);
+            }
+            let width = rows.keys().map(String::len).max().unwrap_or(0).max(5);
+            println!(
+                "{:<width$}  {:>8}  {:>13}  {:>13}  {:>13}  {:>10}",
+                group, "requests", "prompt", "cached", "completion", "cost"
+            );
+            let line = |key: &str, row: &Row| {
+                println!(
+                    "{:<width$}  {:>8}  {:>13}  {:>13}  {:>13}  {:>10}",
+                    key,
+                    row.requests,
+                    fmt_thousands(row.prompt_tokens),
+                    fmt_thousands(row.cached_tokens),
+                    fmt_thousands(row.completion_tokens),
+                    match row.unpriced == row.requests {
+                        true => "?".to_string(),
+                        false => currency.format(row.cost),
+                    }
+                )
+            };
+            for (key, row) in &rows {
+                line(key, row);
+            }
+            println!("{}", "-".repeat(width + 68));
+            line("total", &total);
+            match total.unpriced {
+                0 => {}
+                1 => println!("{}", msg::get(Msg::UsageOneUnpriced)),
+                n => println!("{}", msg::fmt(Msg::UsageUnpriced, &[("count", &n)])),
+            }
+        }
+        ReportFormat::Csv => {
+            // Costs are in the configured currency, unrounded, for spreadsheets to sum.
+            println!("{group},requests,prompt_tokens,cached_tokens,completion_tokens,cost");
+            for (key, row) in &rows {
+                println!(
+                    "{},{},{},{},{},{}",
+                    csv_field(key),
+                    row.requests,
+                    row.prompt_tokens,
+                    row.cached_tokens,
+                    row.completion_tokens,
+                    row.cost * currency.rate
+                );
+            }
+        }
+        ReportFormat::Json => {
+            let mut json = serde_json::json!({
+                "group_by": group,
+                "since": args.since.map(|d| d.to_string()),
+                "currency": currency.symbol,
+                "rows": rows,
+                "total": total,
+            });
+            scale_costs(&mut json, currency.rate);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json)
+                    .map_err(|e| AppError::Command(e.to_string()))?
+            );
+        }
+    }
+    Ok(0)
+}
+
+/// How the estimates of one row's requests compared with what was billed.
+#[derive(Debug, Default, Serialize)]
+struct DriftRow {
+    /// Those with an estimate: ones made before estimates were recorded, or through tools, have none.
+    requests: u32,
+    estimated_prompt_tokens: u64,
+    prompt_tokens: u64,
+    /// Of the totals, as a percentage of the estimate: how far off the estimates are overall.
+    drift_percent: f64,
+    /// The average of each request's drift, ignoring its sign: how far off any one of them is.
+    mean_abs_percent: f64,
+    /// The request furthest off.
+    worst_percent: f64,
+}
+
+impl DriftRow {
+    fn add(&mut self, estimate: u32, actual: u32) {
+        let drift = tokens::percent(estimate as u64, actual as u64);
+        // Kept as a sum until `finish`.
+        self.mean_abs_percent += drift.abs();
+        if drift.abs() > self.worst_percent.abs() {
+            self.worst_percent = drift;
+        }
+        self.requests += 1;
+        self.estimated_prompt_tokens += estimate as u64;
+        self.prompt_tokens += actual as u64;
+    }
+
+    fn finish(&mut self) {
+        self.drift_percent = tokens::percent(self.estimated_prompt_tokens, self.prompt_tokens);
+        self.mean_abs_percent /= self.requests.max(1) as f64;
+    }
+}
+
+/// Runs `gpt report --drift`.
+fn drift_report(args: &ReportArgs, group: &str, entries: &[(String, Entry)]) -> Result<u8> {
+    let mut rows: BTreeMap<&str, DriftRow> = BTreeMap::new();
+    let mut total = DriftRow::default();
+    for (key, entry) in entries {
+        let Some(estimate) = entry.estimated_prompt_tokens else {
+            continue;
+        };
+        rows.entry(key)
+            .or_default()
+            .add(estimate, entry.prompt_tokens);
+        total.add(estimate, entry.prompt_tokens);
+    }
+    rows.values_mut().for_each(DriftRow::finish);
+    total.finish();
+    match args.format {
+        ReportFormat::Table => {
+            if rows.is_empty() {
+                println!(
+                    "{}",
+                    msg::fmt(Msg::DriftNone, &[("since", &since_text(args.since))])
+                );
+                return Ok(0);
+            }
+            let width = rows.keys().map(|k| k.len()).max().unwrap_or(0).max(5);
+            println!(
+                "{:<width$}  {:>8}  {:>13}  {:>13}  {:>7}  {:>7}  {:>7}",
+                group, "requests", "estimated", "billed", "drift", "mean", "worst"
+            );
+            let line = |key: &str, row: &DriftRow| {
+                println!(
+                    "{:<width$}  {:>8}  {:>13}  {:>13}  {:>+6.1}%  {:>6.1}%  {:>+6.1}%",
+                    key,
+                    row.requests,
+                    fmt_thousands(row.estimated_prompt_tokens),
+                    fmt_thousands(row.prompt_tokens),
+                    row.drift_percent,
+                    row.mean_abs_percent,
+                    row.worst_percent
+                )
+            };
+            for (key, row) in &rows {
+                line(key, row);
+            }
+            println!("{}", "-".repeat(width + 67));
+            line("total", &total);
+            println!("\n{}", msg::get(Msg::DriftLegend));
+            let off: Vec<&str> = rows
+                .iter()
+                .filter(|(_, row)| {
+                    tokens::drift_worth_reporting(row.estimated_prompt_tokens, row.prompt_tokens)
+                })
+                .map(|(key, _)| *key)
+                .collect();
+            if !off.is_empty() && args.group_by == GroupBy::Model {
+                println!(
+                    "{}",
+                    msg::fmt(
+                        Msg::DriftOff,
+                        &[
+                            ("models", &off.join(", ")),
+                            ("percent", &tokens::DRIFT_WARNING),
+                            ("issues", &tokens::ISSUES),
+                        ],
+                    )
+                );
+            }
+        }
+        ReportFormat::Csv => {
+            println!("{group},requests,estimated_prompt_tokens,prompt_tokens,drift_percent,mean_abs_percent,worst_percent");
+            for (key, row) in &rows {
+                println!(
+                    "{},{},{},{},{},{},{}",
+                    csv_field(key),
+                    row.requests,
+                    row.estimated_prompt_tokens,
+                    row.prompt_tokens,
+                    row.drift_percent,
+                    row.mean_abs_percent,
+                    row.worst_percent
+                );
+            }
+        }
+        ReportFormat::Json => {
+            let json = serde_json::json!({
+                "group_by": group,
+                "since": args.since.map(|d| d.to_string()),
+                "rows": rows,
+                "total": total,
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json)
+                    .map_err(|e| AppError::Command(e.to_string()))?
+            );
+        }
+    }
+    Ok(0)
+}
+
+// Before prices could be unknown, such requests were recorded as costing this much.
+const UNKNOWN_PRICE_PLACEHOLDER: f64 = 99999.0;
+
+/// An entry's cost, reading the placeholder older versions wrote for an unknown price as unknown.
+fn cost<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<f64>, D::Error> {
+    let cost = Option::<f64>::deserialize(deserializer)?;
+    Ok(cost.filter(|&cost| cost != UNKNOWN_PRICE_PLACEHOLDER))
+}
+
+fn since_text(since: Option<NaiveDate>) -> String {
+    match since {
+        Some(since) => msg::fmt(Msg::UsageSince, &[("date", &since)]),
+        None => String::new(),
+    }
+}
+
+// Model ids don't normally need quoting, but one with a comma or quote shouldn't break the file.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// The ledger is in USD; the JSON report is in the configured currency, like everything else.
+fn scale_costs(json: &mut serde_json::Value, rate: f64) {
+    match json {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value.as_f64() {
+                    Some(cost) if key == "cost" => *value = serde_json::json!(cost * rate),
+                    _ => scale_costs(value, rate),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|v| scale_costs(v, rate)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::{process, thread};
+
+    fn entry(timestamp: DateTime<Utc>, model: &str) -> Vec<u8> {
+        let entry = Entry {
+            timestamp,
+            model: model.to_string(),
+            prompt_tokens: 10,
+            cached_tokens: 0,
+            completion_tokens: 5,
+            cost: None,
+            organization: None,
+            estimated_prompt_tokens: None,
+        };
+        schema::encode(&entry, false).unwrap()
+    }
+
+    #[test]
+    fn drift_rows_sum_the_tokens_and_keep_the_sign_of_the_worst() {
+        let mut row = DriftRow::default();
+        row.add(1000, 1100);
+        row.add(1000, 700);
+        row.add(2000, 2000);
+        row.finish();
+        assert_eq!(row.requests, 3);
+        assert_eq!(row.estimated_prompt_tokens, 4000);
+        assert_eq!(row.prompt_tokens, 3800);
+        assert!((row.drift_percent - -5.0).abs() < 1e-9);
+        // 10%, 30% and 0% off, whichever way.
+        assert!((row.mean_abs_percent - 40.0 / 3.0).abs() < 1e-9);
+        assert!((row.worst_percent - -30.0).abs() < 1e-9);
+
+        let mut empty = DriftRow::default();
+        empty.finish();
+        assert_eq!(empty.mean_abs_percent, 0.0);
+    }
+
+    #[test]
+    fn requests_recorded_during_a_prune_are_kept() {
+        let dir = std::env::temp_dir().join(format!("gpt-cli-ledger-prune-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("usage.jsonl");
+        let now = Utc::now();
+        // A day's worth of old requests for each of the last 40 days.
+        let mut old = Vec::new();
+        for day in (1..=40).rev() {
+            for _ in 0..50 {
+                old.extend(entry(now - Duration::days(day), "old"));
+                old.push(b'\n');
+            }
+        }
+        fs::write(&path, old).unwrap();
+
+        let recorders: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    for _ in 0..50 {
+                        fsutil::append_line(&path, &entry(Utc::now(), "new")).unwrap();
+                    }
+                })
+            })
+            .collect();
+        // As gc on startup would, a day further along each time.
+        let mut dropped = 0;
+        for day in (0..40).rev() {
+            dropped += prune_at(&path, now - Duration::days(day), false).unwrap();
+        }
+        for recorder in recorders {
+            recorder.join().unwrap();
+        }
+
+        assert_eq!(dropped, 40 * 50);
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 200);
+        assert!(text.lines().all(|l| l.contains("\"new\"")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_dry_run_only_counts() {
+        let dir = std::env::temp_dir().join(format!("gpt-cli-ledger-dry-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("usage.jsonl");
+        let now = Utc::now();
+        let mut text = entry(now - Duration::days(60), "old");
+        text.push(b'\n');
+        text.extend(entry(now, "new"));
+        text.push(b'\n');
+        fs::write(&path, &text).unwrap();
+        assert_eq!(prune_at(&path, now - Duration::days(30), true).unwrap(), 1);
+        assert_eq!(fs::read(&path).unwrap(), text);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+//! Advisory locks on saved sessions, so two `gpt`s attached to the same one don't overwrite each
+//! other's saves. A lock is a `<name>.lock` file next to the session holding the PID of the
+//! process attached to it. A lock whose process has gone, after a crash, is taken over.
+//!
+//! Locks are only checked and taken while holding an OS lock on `.locking` in the same directory,
+//! so two gpts that find the same stale lock can't both take it over. The OS lets go of it when
+//! gpt exits, crash or not, so it's never left held.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    paths, session,
+};
+
+/// The lock on one session, released when it's dropped.
+#[derive(Debug)]
+pub struct SessionLock {
+    name: String,
+    path: PathBuf,
+}
+
+/// What became of an attempt to lock a session.
+#[derive(Debug)]
+pub enum Attempt {
+    Locked {
+        lock: SessionLock,
+        /// The process whose lock was taken over, which was no longer running.
+        stale: Option<u32>,
+    },
+    /// Another running process has it.
+    Held(u32),
+}
+
+impl SessionLock {
+    /// Locks the session `name` for this process.
+    pub fn acquire(name: &str) -> Result<Attempt> {
+        let path = session::lock_path(name)?;
+        paths::ensure_dir(&paths::sessions_dir())?;
+        Self::acquire_at(name, path)
+    }
+
+    fn acquire_at(name: &str, path: PathBuf) -> Result<Attempt> {
+        let _guard = guard(&path)?;
+        let mut stale = None;
+        // Twice at most: once more after removing a stale lock, unless it's been recreated since.
+        for _ in 0..2 {
+            match create(&path) {
+                Ok(()) => {
+                    let lock = SessionLock {
+                        name: name.to_string(),
+                        path,
+                    };
+                    return Ok(Attempt::Locked { lock, stale });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            match holder(&path) {
+                Some(pid) if pid == process::id() => {
+                    return Err(AppError::Command(msg::fmt(
+                        Msg::LockedByUs,
+                        &[("name", &name)],
+                    )))
+                }
+                Some(pid) if alive(pid) => return Ok(Attempt::Held(pid)),
+                pid => {
+                    // A lock file that can't be read is left over from a write cut short.
+                    stale = pid;
+                    match fs::remove_file(&path) {
+                        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        match holder(&path) {
+            Some(pid) => Ok(Attempt::Held(pid)),
+            None => Err(AppError::Command(msg::fmt(
+                Msg::LockKeepsChanging,
+                &[("name", &name), ("path", &path.display())],
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        // Only if it's still ours: someone may have removed it by hand and another gpt taken it.
+        if holder(&self.path) == Some(process::id()) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Creates the lock file at `path` with this process's PID in it, failing with `AlreadyExists`
+/// if there's one. The PID is written to a file of its own first and linked into place, so
+/// another gpt never finds the lock empty, which it would take for one left over from a crash.
+fn create(path: &Path) -> io::Result<()> {
+    static CREATED: AtomicU32 = AtomicU32::new(0);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // Named as fsutil's temporary files are, so gc knows whose it is.
+    let n = CREATED.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_file_name(format!(".{name}-{n}.{}.tmp", process::id()));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp)?;
+    let result = writeln!(file, "{}", process::id())
+        .and_then(|_| file.sync_all())
+        .and_then(|_| fs::hard_link(&tmp, path));
+    let _ = fs::remove_file(&tmp);
+    match result {
+        // A file system without hard links gets the lock the simple way, where the race remains.
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+            let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+            writeln!(file, "{}", process::id())
+        }
+        result => result,
+    }
+}
+
+// Held while locks in the directory of `path` are checked and changed, released when it's dropped.
+fn guard(path: &Path) -> io::Result<File> {
+    let guard = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_file_name(".locking"))?;
+    guard.lock()?;
+    Ok(guard)
+}
+
+/// Removes the lock at `path` unless a running gpt holds it, checking under the same guard as
+/// [`SessionLock::acquire`], so a gpt taking the lock meanwhile keeps it.
+pub fn remove_stale(path: &Path) -> io::Result<()> {
+    let _guard = guard(path)?;
+    match held(path) {
+        true => Ok(()),
+        false => match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether a running gpt is attached to the session whose lock is at `path`.
+pub fn held(path: &Path) -> bool {
+    holder(path).is_some_and(alive)
+}
+
+/// Whether a process with this PID is running.
+#[cfg(unix)]
+pub fn alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 sends nothing, it only checks that the process exists.
+    let result = unsafe { libc::kill(pid, 0) };
+    // EPERM means it exists but belongs to someone else.
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+pub fn alive(pid: u32) -> bool {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, GetLastError, ERROR_ACCESS_DENIED, STILL_ACTIVE},
+        System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    };
+
+    // SAFETY: the handle is checked before use and closed after, and the exit code is a plain
+    // out-parameter.
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            // Access denied means it's there, just not ours to look at.
+            return GetLastError() == ERROR_ACCESS_DENIED;
+        }
+        let mut code = 0;
+        let ok = GetExitCodeProcess(handle, &mut code) != 0;
+        CloseHandle(handle);
+        ok && code == STILL_ACTIVE as u32
+    }
+}
+
+// Without a way to tell, a lock is assumed to be in use.
+#[cfg(not(any(unix, windows)))]
+pub fn alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{Arc, Barrier},
+        thread,
+    };
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gpt-cli-lock-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn locked(attempt: Result<Attempt>) -> Option<SessionLock> {
+        match attempt {
+            Ok(Attempt::Locked { lock, .. }) => Some(lock),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn a_second_handle_does_not_get_the_lock() {
+        let dir = dir("second");
+        let path = dir.join("notes.lock");
+        let first = locked(SessionLock::acquire_at("notes", path.clone())).unwrap();
+        assert_eq!(holder(&path), Some(process::id()));
+        let err = SessionLock::acquire_at("notes", path.clone()).unwrap_err();
+        assert!(err.to_string().contains("already locked"), "{err}");
+        drop(first);
+        assert!(!path.exists());
+        assert!(locked(SessionLock::acquire_at("notes", path)).is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_one_of_many_at_once_gets_the_lock() {
+        let dir = dir("race");
+        let path = dir.join("notes.lock");
+        for _ in 0..20 {
+            let start = Arc::new(Barrier::new(8));
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    let (start, path) = (start.clone(), path.clone());
+                    thread::spawn(move || {
+                        start.wait();
+                        locked(SessionLock::acquire_at("notes", path))
+                    })
+                })
+                .collect();
+            let locks: Vec<SessionLock> = handles
+                .into_iter()
+                .filter_map(|h| h.join().unwrap())
+                .collect();
+            assert_eq!(locks.len(), 1);
+            drop(locks);
+            assert!(!path.exists());
+        }
+        // Nothing is left behind but the guard.
+        let left: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(left, [".locking"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // A PID that was in use a moment ago and no longer is.
+    fn dead_pid() -> u32 {
+        let mut child = process::Command::new(env!("CARGO"))
+            .arg("--version")
+            .stdout(process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+        dead
+    }
+
+    #[test]
+    fn only_one_of_many_takes_over_a_stale_lock() {
+        let dir = dir("stale-race");
+        let path = dir.join("notes.lock");
+        let dead = dead_pid();
+        for _ in 0..20 {
+            fs::write(&path, format!("{dead}\n")).unwrap();
+            let start = Arc::new(Barrier::new(8));
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    let (start, path) = (start.clone(), path.clone());
+                    thread::spawn(move || {
+                        start.wait();
+                        SessionLock::acquire_at("notes", path)
+                    })
+                })
+                .collect();
+            let mut locks = Vec::new();
+            for handle in handles {
+                match handle.join().unwrap() {
+                    Ok(Attempt::Locked { lock, stale }) => locks.push((lock, stale)),
+                    // The others find the winner's lock, which has this process's PID.
+                    Err(e) => assert!(e.to_string().contains("already locked"), "{e}"),
+                    Ok(other) => panic!("{other:?}"),
+                }
+            }
+            assert_eq!(locks.len(), 1);
+            assert_eq!(locks[0].1, Some(dead));
+            assert_eq!(holder(&path), Some(process::id()));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_a_stale_lock_is_removed() {
+        let dir = dir("remove");
+        let path = dir.join("notes.lock");
+        let lock = locked(SessionLock::acquire_at("notes", path.clone())).unwrap();
+        remove_stale(&path).unwrap();
+        assert!(path.exists());
+        drop(lock);
+        fs::write(&path, format!("{}\n", dead_pid())).unwrap();
+        remove_stale(&path).unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_lock_held_by_another_running_process_is_left_alone() {
+        let dir = dir("held");
+        let path = dir.join("notes.lock");
+        // init is always running.
+        fs::write(&path, "1\n").unwrap();
+        assert!(matches!(
+            SessionLock::acquire_at("notes", path.clone()),
+            Ok(Attempt::Held(1))
+        ));
+        assert!(held(&path));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_lock_left_by_a_crash_is_taken_over() {
+        let dir = dir("stale");
+        let path = dir.join("notes.lock");
+        let dead = dead_pid();
+        fs::write(&path, format!("{dead}\n")).unwrap();
+        match SessionLock::acquire_at("notes", path.clone()) {
+            Ok(Attempt::Locked { stale, .. }) => assert_eq!(stale, Some(dead)),
+            other => panic!("{other:?}"),
+        }
+
+        // As an older gpt could leave it.
+        fs::write(&path, "").unwrap();
+        match SessionLock::acquire_at("notes", path) {
+            Ok(Attempt::Locked { stale, .. }) => assert_eq!(stale, None),
+            other => panic!("{other:?}"),
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
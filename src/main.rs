@@ -1,231 +1,1102 @@
-use async_openai::{
-    types::{
-        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
-        ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs,
-    },
-    Client,
-};
-use futures::StreamExt;
+use async_openai::{config::OpenAIConfig, Client};
+use cache::ResponseCache;
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use cli::{Args, Command, ModelsCommand};
+use commands::{parse_command, Autosave, CommandOutcome};
+use config::Config;
+use error::{AppError, Result};
+use footer::FooterStyle;
+use input::InputSource;
+use msg::Msg;
+use project::Project;
+use provider::Provider;
+use script::Script;
+use serde::{Deserialize, Serialize};
+use settings::MaxTokens;
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
     env,
-    error::Error,
-    io::{self, Write},
+    future::Future,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    pin::{pin, Pin},
+    process::ExitCode,
+    time::Duration,
 };
-use tiktoken_rs::cl100k_base;
+use tee::TeeTarget;
+use tokio::sync::oneshot;
 
-const PRICE_4: [f64; 2] = [3. / 1_000., 6. / 1_000.];
-const PRICE_4T: [f64; 2] = [1. / 1_000., 3. / 1_000.];
-const PRICE_3: [f64; 2] = [1. / 10_000., 2. / 10_000.];
+mod anthropic;
+mod ask;
+mod askfile;
+mod audit;
+mod azure;
+mod bookmarks;
+mod budget;
+mod cache;
+mod chat;
+mod cli;
+mod codectx;
+mod commands;
+mod compare;
+mod config;
+mod curl;
+mod currency;
+mod doctor;
+mod draft;
+mod duo;
+mod emit;
+mod endpoints;
+mod error;
+mod expect;
+mod fallback;
+mod filter;
+mod fix;
+mod footer;
+mod fsutil;
+mod gc;
+mod grapheme;
+mod grep;
+mod index;
+mod inflight;
+mod input;
+mod large;
+mod ledger;
+mod lock;
+mod math;
+mod mcp;
+mod models;
+mod msg;
+mod notify;
+mod overrides;
+mod paths;
+mod picker;
+mod plugins;
+mod prefix;
+mod preset;
+mod project;
+mod prompt;
+mod provider;
+mod queue;
+mod quote;
+mod ratelimit;
+mod reqdiff;
+mod review;
+mod route;
+mod safe;
+mod schema;
+mod script;
+mod search;
+mod seed;
+mod sensitive;
+mod session;
+mod settings;
+mod setup;
+mod shellcmd;
+mod signals;
+mod softwrap;
+mod stage;
+mod streamto;
+mod style;
+mod subst;
+mod summary;
+mod tee;
+mod titles;
+mod tokens;
+mod tools;
+mod translate;
+mod usage;
+mod vars;
+mod view;
+mod watch;
+mod window;
+mod wrap;
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 struct AppState {
     model: String,
-    max_tokens: u16,
+    max_tokens: MaxTokens,
+    // Sent when set, which only a length preset does.
+    temperature: Option<f32>,
+    // The length preset last switched to, whose instruction goes in the system prompt.
+    preset: preset::Preset,
     auto_pipe: bool,
-    context: Vec<ContextType>,
+    context: Vec<Message>,
+    // The most recently discarded context, for :restore.
+    last_dropped: Option<Vec<Message>>,
+    // Exchanges since the session started, used to decide when to generate a title.
+    exchanges: u32,
+    title: Option<String>,
+    // The name the session was last saved or loaded as.
+    session: Option<String>,
+    // The lock on that session, so another gpt doesn't save over it.
+    lock: Option<lock::SessionLock>,
+    pending_title: Option<oneshot::Receiver<String>>,
+    config: Config,
+    cache: ResponseCache,
+    // The last two requests sent, for :diff-request.
+    requests: reqdiff::RecentRequests,
+    // What the last request started with, to see how much of the next could hit the prompt cache.
+    prefix: prefix::PrefixTracker,
+    tee: Option<TeeTarget>,
+    // Where the next answer streams to, from :stream-to.
+    stream_to: Option<streamto::StreamTarget>,
+    // Responses are shown through this command, if set.
+    filter: Option<filter::Filter>,
+    // What answers should look like, from :expect.
+    expect: Option<expect::Expect>,
+    // An answer wasn't, which one-shot mode exits non-zero for.
+    expect_failed: bool,
+    color: bool,
+    footer: FooterStyle,
+    lines: Option<input::Lines>,
+    // With model "auto", `model` is whichever model the last message was routed to.
+    auto_route: bool,
+    last_route: Option<route::Decision>,
+    vars: vars::Vars,
+    aliases: BTreeMap<String, String>,
+    tools: tools::Tools,
+    translation: translate::Pair,
+    // Loaded by the first :ask.
+    index: Option<index::Index>,
+    // Set by :subst for the message it sends.
+    subst_once: bool,
+    // Set by :quote to go before the next message.
+    quote: Option<String>,
+    // What's been typed since :draft, until :send.
+    draft: Option<String>,
+    // Set by --print-curl and :curl on: requests are printed as curl commands before they're sent.
+    print_curl: bool,
+    // The seed the conversation started from, with --seed-file or :seed.
+    seed: Option<seed::Loaded>,
+    // The @ overrides of the message being sent, for its footer.
+    overrides: Option<String>,
+    // The models whose prompt estimates have been off by more than DRIFT_WARNING, so it's said once.
+    drift_warned: BTreeSet<String>,
+    // Off after :wrap-prompt off, so typed messages go without the prefix and suffix.
+    wrap_prompt: bool,
+    // Set by :send-only: the only messages of the context sent with the next one.
+    send_only: Option<Vec<usize>>,
+    // A --script or :script still being run.
+    script: Option<Script>,
+    // The .gpt-cli.toml the session was started under, if any.
+    project: Option<Project>,
+    // Whether there's someone to answer questions, as opposed to one-shot mode or piped input.
+    interactive: bool,
+    // When stdout isn't a terminal it only gets the model's answers; everything else goes to stderr.
+    stdout_tty: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Role {
+    Assistant,
+    User,
 }
 
-#[derive(Clone, Debug)]
-enum ContextType {
-    Assistant(String),
-    User(String),
+/// One message of the conversation. Everything but the role and content is optional, so sessions
+/// saved before it was recorded still load.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Message {
+    role: Role,
+    content: String,
+    /// When it was sent, or when the answer finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<DateTime<Utc>>,
+    /// The model that wrote an answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// How long an answer took to generate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    /// Completion tokens of an answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tokens: Option<u32>,
+    /// Prompt tokens the API counted for an answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_tokens: Option<u32>,
+    /// What an answer cost in USD, its prompt included, for totals without the API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cost_usd: Option<f64>,
+    /// The user's own note on the message, from :note. It's never sent or counted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    /// The prompt prefix and suffix a typed message was sent with, see [`wrap`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wrapper: Option<wrap::Wrapper>,
+    /// Where the message was saved in full before it was shortened, see [`large`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    original: Option<PathBuf>,
+    /// The file a message staged with :file is a copy of, see [`stage`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<stage::StagedFile>,
+    /// The base URL that answered, when there are several to fail over between.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    endpoint: Option<String>,
+}
+
+impl Message {
+    fn new(role: Role, content: String) -> Self {
+        Message {
+            role,
+            content,
+            timestamp: Some(Utc::now()),
+            model: None,
+            duration_ms: None,
+            tokens: None,
+            prompt_tokens: None,
+            cost_usd: None,
+            note: None,
+            wrapper: None,
+            original: None,
+            file: None,
+            endpoint: None,
+        }
+    }
+
+    fn user(content: String) -> Self {
+        Message::new(Role::User, content)
+    }
+
+    fn assistant(content: String) -> Self {
+        Message::new(Role::Assistant, content)
+    }
+
+    fn is_user(&self) -> bool {
+        self.role == Role::User
+    }
+
+    fn role(&self) -> &'static str {
+        match self.role {
+            Role::Assistant => "assistant",
+            Role::User => "user",
+        }
+    }
+
+    fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// The content as it's sent, with the prompt wrapper it was typed under.
+    fn sent(&self) -> Cow<'_, str> {
+        match self.wrapper {
+            Some(ref wrapper) => Cow::Owned(wrapper.apply(&self.content)),
+            None => Cow::Borrowed(&self.content),
+        }
+    }
 }
 
 impl AppState {
     fn get_model(&self) -> String {
         self.model.clone()
     }
+
+    /// The max_tokens to send with the next request.
+    fn max_tokens(&self) -> u32 {
+        self.max_tokens.resolve(&self.model).0
+    }
+
+    /// Clears the context, keeping it around for :restore.
+    fn drop_context(&mut self) {
+        if !self.context.is_empty() {
+            self.last_dropped = Some(std::mem::take(&mut self.context));
+        }
+    }
 }
 
+// Exit codes
+const EXIT_STARTUP: u8 = 1;
+const EXIT_API: u8 = 2;
+const EXIT_TOO_LONG: u8 = 3;
+// A line of a --script-exit script failed.
+const EXIT_SCRIPT: u8 = 4;
+// A one-shot answer wasn't what --expect asked for.
+const EXIT_UNEXPECTED: u8 = 5;
+// What a shell reports for a program killed by SIGINT.
+const EXIT_INTERRUPTED: u8 = 130;
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    if env::var("OPENAI_API_KEY").is_err() {
-        println!("The environment variable 'OPENAI_API_KEY' must be set to use this program.");
-        return Ok(());
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(code) => ExitCode::from(code),
+        Err(e) if e.is_broken_pipe() => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(EXIT_STARTUP)
+        }
     }
+}
 
-    let client = Client::new();
+async fn run() -> Result<u8> {
+    let args = Args::parse();
 
-    let mut state = AppState {
-        model: String::from("gpt-3.5-turbo"),
-        max_tokens: 512,
-        auto_pipe: false,
-        context: Vec::new(),
+    // Subcommands that don't talk to the API, so they work without a key.
+    if let Some(Command::Completions { shell }) = args.command {
+        cli::print_completions(shell)?;
+        return Ok(0);
+    }
+
+    if let Some(ref path) = args.config {
+        // A typo in --config shouldn't silently fall back to the defaults.
+        if !path.exists() {
+            return Err(AppError::Config(msg::fmt(
+                Msg::ConfigMissing,
+                &[("path", &path.display())],
+            )));
+        }
+    }
+    let config_file = args
+        .config
+        .clone()
+        .unwrap_or_else(paths::default_config_file);
+    let mut config = Config::load(&config_file)?;
+    // Like the keys, the environment wins over the config file.
+    if let Ok(organization) = env::var("OPENAI_ORG_ID") {
+        config.organization = Some(organization);
+    }
+    if let Ok(project) = env::var("OPENAI_PROJECT_ID") {
+        config.project = Some(project);
+    }
+    style::set_theme(config.theme);
+    if let Some(ref code) = config.lang {
+        match msg::Lang::parse(code) {
+            Some(lang) => msg::set(lang),
+            None => eprintln!(
+                "{}",
+                msg::fmt(
+                    Msg::LangUnknown,
+                    &[("lang", code), ("langs", &msg::Lang::codes())]
+                )
+            ),
+        }
+    }
+    paths::init(
+        config_file.clone(),
+        config.data_dir.clone(),
+        config.cache_dir.clone(),
+    );
+    safe::init(args.safe || config.safe_mode);
+    inflight::init(config.max_in_flight);
+    ratelimit::init(config.requests_per_minute, config.tokens_per_minute);
+    endpoints::init(&config);
+    if let Some(path) = args.emit_socket.clone().or(config.emit_socket.clone()) {
+        emit::init(path);
+    }
+    let project = match args.global {
+        true => None,
+        false => Project::find(&env::current_dir()?)?,
     };
+    // Found, but not read any further.
+    let project = match project.map(|p| (safe::check(safe::Feature::Project), p)) {
+        Some((Err(e), project)) => {
+            let ignored = msg::fmt(Msg::ProjectIgnored, &[("path", &project.path().display())]);
+            eprintln!("{e} {ignored}");
+            None
+        }
+        Some((Ok(()), project)) => Some(project),
+        None => None,
+    };
+    if let Some(ref output) = args.output {
+        safe::check_write(output)?;
+    }
+    if let Some(ref project) = project {
+        paths::set_session_namespace(project.namespace.clone());
+        if project.model.is_some() {
+            config.model = project.model.clone();
+        }
+        if project.prompt_prefix.is_some() {
+            config.prompt_prefix = project.prompt_prefix.clone();
+        }
+        if project.prompt_suffix.is_some() {
+            config.prompt_suffix = project.prompt_suffix.clone();
+        }
+    }
 
-    loop {
-        print!("{}> ", state.get_model());
-        io::stdout().flush()?;
+    // Like completions, these work without a key.
+    if let Some(Command::Report(ref report)) = args.command {
+        return ledger::report(report, &config);
+    }
+    if let Some(Command::Models(ModelsCommand::Validate { ref file })) = args.command {
+        return models::validate(&file.clone().unwrap_or_else(paths::models_file));
+    }
+    // Now that it's known where the user's models.toml is, read it.
+    let problems = models::refresh();
+    if !problems.is_empty() {
+        let path = paths::models_file();
+        eprintln!(
+            "{}",
+            msg::fmt(Msg::ModelsIgnored, &[("path", &path.display())])
+        );
+    }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let mut input = input.trim().to_string();
+    if let Some(Command::View(ref view)) = args.command {
+        return view::run(view, &config);
+    }
+    if let Some(Command::Gc { dry_run }) = args.command {
+        return gc::run(
+            &config,
+            dry_run,
+            style::color_enabled(io::stderr().is_terminal()),
+        );
+    }
+    if let Some(Command::Migrate { dry_run }) = args.command {
+        return schema::migrate(
+            &config,
+            dry_run,
+            style::color_enabled(io::stderr().is_terminal()),
+        );
+    }
+    if let Some(Command::Bookmarks { action }) = args.command {
+        bookmarks::run(action, style::color_enabled(io::stdout().is_terminal()))?;
+        return Ok(0);
+    }
+    // Before the setup, since a missing key is one of the things it reports.
+    if let Some(Command::Doctor(ref doctor)) = args.command {
+        provider::set_config_keys(config.api_keys.clone());
+        return doctor::run(doctor, &config).await;
+    }
 
-        if input.is_empty() {
-            continue;
-        }
-        if input.starts_with(':') {
-            parse_command(input, &mut state);
-            continue;
+    let has_key = env::var(config.provider.key_var()).is_ok()
+        || config.api_keys.contains_key(config.provider.name());
+    // Only someone at a terminal, starting from scratch, gets the setup.
+    let wizard = !args.no_wizard
+        && !safe::enabled()
+        && !config_file.exists()
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal();
+    if !has_key && wizard {
+        let setup = match setup::run(&config_file).await {
+            Ok(setup) => setup,
+            Err(AppError::Cancelled) => return Ok(EXIT_INTERRUPTED),
+            Err(e) => return Err(e),
+        };
+        config.provider = setup.provider;
+        config.model = setup.model.or(config.model);
+        config
+            .api_keys
+            .insert(setup.provider.name().to_string(), setup.api_key);
+    }
+    provider::set_config_keys(config.api_keys.clone());
+    provider::set_dry_run(args.dry_run);
+
+    let Some(client) = provider::client(&config)? else {
+        let no_key = msg::fmt(
+            Msg::NoApiKey,
+            &[
+                ("provider", &config.provider.name()),
+                (
+                    "var",
+                    &provider::key_var_at(config.provider, &provider::api_base(&config)),
+                ),
+                ("config", &config_file.display()),
+            ],
+        );
+        eprintln!("{no_key}");
+        if !config_file.exists() && !args.no_wizard {
+            eprintln!("{}", msg::get(Msg::NoApiKeyWizard));
         }
-        // If the input doesn't start with a '|', then we can clear all context. Otherwise, we keep building the context.
-        if !input.starts_with('|') && !state.auto_pipe {
-            state.context = Vec::new();
-        } else if input.starts_with('|') {
-            input.remove(0);
-        }
-        state.context.push(ContextType::User(input));
-
-        // Get input tokens
-        let input_tokens = count_tokens_ctx(state.context.clone());
-
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(state.get_model())
-            .max_tokens(state.max_tokens)
-            .messages(convert_context(state.context.clone()))
-            .build()?;
-
-        let mut stream = client.chat().create_stream(request).await?;
-
-        let mut response_save = String::new();
-
-        let mut lock = io::stdout().lock();
-        while let Some(result) = stream.next().await {
-            match result {
-                Ok(response) => {
-                    response.choices.iter().for_each(|chat_choice| {
-                        if let Some(ref content) = chat_choice.delta.content {
-                            write!(lock, "{}", content).unwrap();
-                            response_save.push_str(content);
-                        }
-                    });
-                }
-                Err(err) => {
-                    writeln!(lock, "An error occured: {err}").unwrap();
-                }
+        return Ok(EXIT_STARTUP);
+    };
+    if config.provider == Provider::OpenRouter {
+        let config = config.clone();
+        tokio::spawn(async move {
+            // Without the catalog, prices for OpenRouter models are unknown.
+            match provider::fetch_catalog(&config).await {
+                Ok(catalog) => models::set_catalog(catalog),
+                Err(e) => eprintln!("{e}"),
             }
-            io::stdout().flush()?;
-        }
-        println!();
-        println!();
-        let response_tokens = count_tokens(&response_save);
-        state.context.push(ContextType::Assistant(response_save));
-
-        let price = calc_price(input_tokens, response_tokens, &state);
-        println!(
-            "Prompt Tokens: {} | Completion Tokens: {} | Total Tokens: {} | Price: {:.5}p",
-            input_tokens,
-            response_tokens,
-            input_tokens + response_tokens,
-            price
-        );
-        println!();
+        });
     }
-}
+    if config.provider == Provider::OpenAI {
+        let config = config.clone();
+        tokio::spawn(async move {
+            // Only for :models, so a failure isn't worth mentioning; a bad key is reported anyway.
+            if let Ok(ids) = provider::fetch_fine_tuned(&config).await {
+                models::set_fine_tuned(ids);
+            }
+        });
+    }
+    if args.no_cache {
+        config.cache = false;
+    }
+    let cache = ResponseCache::new(
+        paths::cache_dir(),
+        Duration::from_secs(config.cache_ttl_secs),
+    );
 
-fn parse_command(command: String, state: &mut AppState) {
-    let command = command.trim().to_lowercase();
-    let (command, args) = {
-        let mut words = command.split_whitespace();
-        let cmd = words.next().unwrap();
-        let args: String = words.collect();
-        let args = args.trim().to_string();
-        (cmd, args)
+    let auto_route = args
+        .model
+        .as_ref()
+        .or(config.model.as_ref())
+        .map(String::as_str)
+        == Some(route::AUTO);
+    let model = match args.model.as_ref().or(config.model.as_ref()) {
+        Some(_) if auto_route => config.routing.cheap_model.clone(),
+        Some(name) => models::resolve(name)
+            .ok_or_else(|| AppError::Config(msg::fmt(Msg::ModelUnknown, &[("name", name)])))?,
+        None => config.provider.default_model().to_string(),
     };
 
-    if command == ":q" || command == ":quit" {
-        std::process::exit(0);
+    if let Some(Command::Flush) = args.command {
+        let color = style::color_enabled(io::stdout().is_terminal());
+        let flushed = queue::flush(&config, true, color).await?;
+        let sent = flushed.sent.len();
+        match (sent, flushed.failed, flushed.left) {
+            (0, 0, 0) => eprintln!("{}", msg::get(Msg::NothingQueued)),
+            (sent, failed, left) => {
+                let summary = msg::fmt(
+                    Msg::Flushed,
+                    &[("sent", &sent), ("failed", &failed), ("left", &left)],
+                );
+                eprintln!("{}", style::dim(&summary, color));
+            }
+        }
+        return Ok(match flushed.failed + flushed.left {
+            0 => 0,
+            _ => EXIT_API,
+        });
     }
 
-    if command == ":c" || command == ":context" {
-        state.auto_pipe = !state.auto_pipe;
-        if state.auto_pipe {
-            println!("Context will now be carried between messages.");
-        } else {
-            println!(
-                "Context will no longer be carried between messages.\nPrefix messages with '|' to temporarily keep context."
-            );
-        }
-        return;
+    if let Some(Command::Index { ref dir }) = args.command {
+        return Ok(match index::build(dir, &client, &config).await {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("{e}");
+                EXIT_API
+            }
+        });
+    }
+    if let Some(Command::Review(review)) = args.command {
+        let model = match review.model {
+            Some(ref name) => models::resolve(name)
+                .ok_or_else(|| AppError::Config(msg::fmt(Msg::ModelUnknown, &[("name", name)])))?,
+            // A review is worth the better model.
+            None if auto_route => config.routing.expensive_model.clone(),
+            None => model,
+        };
+        return Ok(match review::run(review, &client, &config, &model).await {
+            Ok(code) => code,
+            Err(e) if e.is_broken_pipe() => 0,
+            Err(e) => {
+                eprintln!("{e}");
+                EXIT_API
+            }
+        });
     }
 
-    if command == ":m" || command == ":model" {
-        match args.as_str() {
-            "3" => {
-                state.model = "gpt-3.5-turbo".to_string();
-                println!("Swapped to model gpt-3.5-turbo.");
+    if let Some(Command::Ask(ask)) = args.command {
+        let model = match ask.model {
+            Some(ref name) => models::resolve(name)
+                .ok_or_else(|| AppError::Config(msg::fmt(Msg::ModelUnknown, &[("name", name)])))?,
+            None => model,
+        };
+        let result = tokio::select! {
+            result = ask::run(ask, &client, &config, &model) => result,
+            _ = signals::interrupt() => Err(AppError::Cancelled),
+        };
+        return Ok(match result {
+            Ok(()) => 0,
+            Err(e) if e.is_broken_pipe() => 0,
+            Err(AppError::Cancelled) => EXIT_INTERRUPTED,
+            Err(e @ AppError::TooLong(_)) => {
+                eprintln!("{e}");
+                EXIT_TOO_LONG
             }
-            "4" => {
-                state.model = "gpt-4".to_string();
-                println!("Swapped to model gpt-4.");
+            Err(e) => {
+                eprintln!("{e}");
+                EXIT_API
             }
-            "4t" => {
-                state.model = "gpt-4 turbo".to_string();
-                println!("Swapped to model gpt-4 turbo.");
+        });
+    }
+
+    if let Some(Command::Cmd(cmd)) = args.command {
+        let model = match cmd.model {
+            Some(ref name) => models::resolve(name)
+                .ok_or_else(|| AppError::Config(msg::fmt(Msg::ModelUnknown, &[("name", name)])))?,
+            None => model,
+        };
+        let result = tokio::select! {
+            result = shellcmd::run(cmd, &client, &config, &model) => result,
+            _ = signals::interrupt() => Err(AppError::Cancelled),
+        };
+        return Ok(match result {
+            Ok(code) => code,
+            Err(e) if e.is_broken_pipe() => 0,
+            Err(AppError::Cancelled) => EXIT_INTERRUPTED,
+            Err(e) => {
+                eprintln!("{e}");
+                EXIT_API
             }
-            _ => println!("Unknown model. Please try again.\nPossible Options: 3, 4, 4t."),
-        }
-        return;
+        });
     }
 
-    if command == ":h" || command == ":help" {
-        println!("Commands:\n 1) :quit (q) - quits the program\n 2) :context (c) - toggles between keeping context and discarding it between messages.\n 3) :model (m) [3|4|4t]\n");
-        return;
+    // A one-shot prompt skips the check unless asked, since it's an extra round trip.
+    let check_auth = match args.prompt.is_empty() {
+        true => config.check_auth,
+        false => args.check_auth,
+    };
+    if check_auth {
+        if let Err(e) = provider::check_auth(&config).await {
+            eprintln!("{e}");
+            return Ok(EXIT_STARTUP);
+        }
     }
 
-    println!("Unknown command. Use :help to see a list of commands.");
-    return;
-}
+    let expect = args
+        .expect
+        .as_deref()
+        .or(config.expect.as_deref())
+        .map(expect::Expect::parse)
+        .transpose()
+        .map_err(AppError::Config)?;
+
+    let queue_offline = args.queue || config.queue_on_network_error;
+    gc::on_start(&config, style::color_enabled(io::stderr().is_terminal()));
+
+    let footer = match (args.verbose, args.prompt.is_empty(), config.footer) {
+        (true, _, _) => FooterStyle::Full,
+        // One-shot answers only get timing with --verbose.
+        (false, false, FooterStyle::Full) => FooterStyle::Minimal,
+        (false, _, style) => style,
+    };
+
+    let mut state = AppState {
+        model,
+        max_tokens: config.max_tokens.map_or(MaxTokens::Auto, MaxTokens::Fixed),
+        temperature: None,
+        preset: preset::Preset::default(),
+        auto_pipe: false,
+        context: Vec::new(),
+        last_dropped: None,
+        exchanges: 0,
+        title: None,
+        pending_title: None,
+        session: None,
+        lock: None,
+        requests: reqdiff::RecentRequests::default(),
+        prefix: prefix::PrefixTracker::default(),
+        vars: config.vars.clone(),
+        aliases: config.aliases.clone(),
+        tools: tools::Tools::load(&config).await,
+        index: None,
+        translation: translate::Pair::default(),
+        filter: config
+            .response_filter
+            .as_deref()
+            .and_then(filter::Filter::parse)
+            .filter(|_| match safe::check(safe::Feature::Filter) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("{e} {}", msg::get(Msg::FilterIgnored));
+                    false
+                }
+            }),
+        expect,
+        expect_failed: false,
+        config,
+        cache,
+        stdout_tty: io::stdout().is_terminal(),
+        footer,
+        lines: None,
+        auto_route,
+        last_route: None,
+        subst_once: false,
+        quote: None,
+        draft: None,
+        print_curl: args.print_curl,
+        seed: None,
+        overrides: None,
+        drift_warned: BTreeSet::new(),
+        wrap_prompt: true,
+        send_only: None,
+        script: None,
+        project,
+        interactive: false,
+        color: style::color_enabled(io::stdout().is_terminal()),
+        tee: args.output.map(|path| TeeTarget {
+            path,
+            append: false,
+        }),
+        stream_to: None,
+    };
+
+    let mut signal = pin!(signals::shutdown_signal());
+
+    // Whatever was queued while offline goes first, now there may be a connection.
+    queue::flush_on_start(&state.config, state.stdout_tty, state.color).await;
+
+    if let Some(ref path) = args.seed_file {
+        if let Err(e) = seed::load(&mut state, path).await {
+            eprintln!("{e}");
+            return Ok(EXIT_STARTUP);
+        }
+    }
 
-fn convert_context(context: Vec<ContextType>) -> Vec<ChatCompletionRequestMessage> {
-    let mut res = Vec::with_capacity(context.len());
-
-    for ctx in context {
-        let new = match ctx {
-            ContextType::User(msg) => ChatCompletionRequestUserMessageArgs::default()
-                .content(msg)
-                .build()
-                .unwrap()
-                .into(),
-            ContextType::Assistant(msg) => ChatCompletionRequestAssistantMessageArgs::default()
-                .content(msg)
-                .build()
-                .unwrap()
-                .into(),
+    if !args.prompt.is_empty() {
+        let code = tokio::select! {
+            result = chat::send_prompt(args.prompt.join(" "), &client, &mut state) => match result {
+                Ok(()) => 0,
+                Err(e) if e.is_broken_pipe() => 0,
+                Err(AppError::Cancelled) => EXIT_INTERRUPTED,
+                Err(AppError::Api(ref err)) if queue_offline && fallback::is_offline(err) => {
+                    queue_prompt(&state, err)
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    EXIT_API
+                }
+            },
+            sig = &mut signal => 128 + sig as u8,
+        };
+        let code = match code {
+            0 if state.expect_failed => EXIT_UNEXPECTED,
+            code => code,
         };
+        shutdown(&mut state).await;
+        return Ok(code);
+    }
 
-        res.push(new);
+    state.lines = Some(input::spawn_stdin_reader());
+    state.interactive = io::stdin().is_terminal() && state.stdout_tty;
+    if let Some(ref project) = state.project {
+        if state.stdout_tty {
+            let notice = msg::fmt(Msg::ProjectLoaded, &[("name", &project.namespace)]);
+            println!("{}", style::dim(&notice, state.color));
+        }
     }
 
-    return res;
+    let script = args
+        .script
+        .map(|path| Script::load(path, args.script_exit))
+        .transpose()?;
+    if state.interactive {
+        draft::restore(&mut state);
+    }
+
+    let code = match script {
+        // A script that ends the session is all there is to read.
+        Some(mut script) if script.exit => {
+            repl(&mut script, &client, &mut state, signal.as_mut()).await?
+        }
+        script => {
+            state.script = script;
+            repl(&mut input::Stdin, &client, &mut state, signal.as_mut()).await?
+        }
+    };
+
+    shutdown(&mut state).await;
+    Ok(code)
 }
 
-fn count_tokens_ctx(context: Vec<ContextType>) -> u16 {
-    let mut sum = 0;
-    for ctx in context {
-        let msg = match ctx {
-            ContextType::Assistant(m) => m,
-            ContextType::User(m) => m,
+/// Runs what's read from `source`, command or message, line by line until one ends the session.
+/// The lines of a script started with `:script` come first.
+async fn repl(
+    source: &mut impl InputSource,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+    mut signal: Pin<&mut impl Future<Output = i32>>,
+) -> Result<u8> {
+    let code = loop {
+        titles::collect(state);
+        if let Some(script) = state.script.take_if(|s| s.is_finished()) {
+            if script.exit {
+                break 0;
+            }
+        }
+        if source.is_finished() {
+            break 0;
+        }
+
+        if state.stdout_tty {
+            match state.draft {
+                Some(_) => print!("{}", draft::PROMPT),
+                None => print!("{}", state.config.prompt.render(state)),
+            }
+            io::stdout().flush()?;
+        }
+
+        // A script's lines come before anything typed.
+        let mut script = state.script.take();
+        let line = match script {
+            Some(ref mut script) => script.next_line(state).await,
+            None => tokio::select! {
+                line = source.next_line(state) => line,
+                sig = &mut signal => break 128 + sig as u8,
+                _ = signals::interrupt() => break EXIT_INTERRUPTED,
+            },
         };
-        sum += count_tokens(&msg);
-    }
+        let from_script = script.is_some();
+        state.script = script;
+        // As typed, for a draft, which keeps indentation and blank lines.
+        let Some(line) = line.transpose()? else {
+            // EOF
+            break 0;
+        };
+        let input = line.trim().to_string();
+        if state.draft.is_some() && !input.starts_with(':') {
+            if let Err(e) = draft::append(state, &line) {
+                println!("{}", msg::fmt(Msg::DraftNotSaved, &[("error", &e)]));
+            }
+            continue;
+        }
+        let input = commands::expand_alias(&input, &state.aliases).unwrap_or(input);
 
-    sum
+        if input.is_empty() {
+            continue;
+        }
+
+        let result = if input.starts_with(':') {
+            parse_command(input, state)
+        } else {
+            Ok(CommandOutcome::Send(input))
+        };
+        // What's picked in :search is sent like anything typed.
+        let result = match result {
+            Ok(CommandOutcome::Search) => tokio::select! {
+                found = search::run(state) => {
+                    found.map(|found| found.map_or(CommandOutcome::Continue, CommandOutcome::Send))
+                }
+                sig = &mut signal => break 128 + sig as u8,
+            },
+            Ok(CommandOutcome::PickModel) => tokio::select! {
+                result = picker::run(state) => result.map(|_| CommandOutcome::Continue),
+                sig = &mut signal => break 128 + sig as u8,
+            },
+            other => other,
+        };
+        let result = match result {
+            Ok(CommandOutcome::Send(input)) => {
+                let result = tokio::select! {
+                    result = chat::send_prompt(input, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                report_queue(state, matches!(result, Err(AppError::Cancelled)))?;
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Ask(question)) => {
+                let result = tokio::select! {
+                    result = index::ask(question, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::AskFile(ask)) => {
+                let result = tokio::select! {
+                    result = askfile::run(ask, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Translate(translate)) => {
+                let result = tokio::select! {
+                    result = translate::run(translate, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Duo(duo)) => {
+                let result = tokio::select! {
+                    result = duo::run(duo, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Compare(compare)) => {
+                let result = tokio::select! {
+                    result = compare::run(compare, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Fix(fix)) => {
+                let result = tokio::select! {
+                    result = fix::run(fix, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Cmd(cmd)) => {
+                let result = tokio::select! {
+                    result = shellcmd::run_repl(cmd, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Seed(path)) => {
+                let result = tokio::select! {
+                    result = seed::load(state, &path) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Stage(stage)) => {
+                let result = tokio::select! {
+                    result = stage::run(stage, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::CodeContext(cmd)) => {
+                let result = tokio::select! {
+                    result = codectx::run(cmd, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Recap) => {
+                let result = tokio::select! {
+                    result = summary::recap(client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            Ok(CommandOutcome::Watch(watch)) => {
+                let result = tokio::select! {
+                    result = watch::run(watch, client, state) => result,
+                    sig = &mut signal => break 128 + sig as u8,
+                };
+                result.map(|_| CommandOutcome::Continue)
+            }
+            other => other,
+        };
+        // Whatever was started in the background goes with the answer Ctrl+C stopped.
+        if matches!(result, Err(AppError::Cancelled)) {
+            inflight::abort_all();
+        }
+
+        // Every error ends up here, so a failed request or command never ends the session - unless stdout is gone.
+        match result {
+            Ok(CommandOutcome::Continue) => {}
+            Ok(CommandOutcome::Exit(code)) => break code,
+            Ok(
+                CommandOutcome::Send(_)
+                | CommandOutcome::Watch(_)
+                | CommandOutcome::AskFile(_)
+                | CommandOutcome::Ask(_)
+                | CommandOutcome::Translate(_)
+                | CommandOutcome::Duo(_)
+                | CommandOutcome::Compare(_)
+                | CommandOutcome::Fix(_)
+                | CommandOutcome::Cmd(_)
+                | CommandOutcome::Recap
+                | CommandOutcome::Search
+                | CommandOutcome::PickModel
+                | CommandOutcome::Seed(_)
+                | CommandOutcome::Stage(_)
+                | CommandOutcome::CodeContext(_),
+            ) => {
+                unreachable!("messages are sent and files watched above")
+            }
+            Err(e) if e.is_broken_pipe() => break 0,
+            Err(e) => {
+                let ends = match state.script {
+                    Some(ref mut script) if from_script => script.report(&e),
+                    _ => source.report(&e),
+                };
+                if ends {
+                    break EXIT_SCRIPT;
+                }
+            }
+        }
+    };
+    Ok(code)
 }
 
-fn count_tokens(text: &str) -> u16 {
-    let cl100k = cl100k_base().unwrap();
-    let tokens = cl100k.split_by_token(&text, true).unwrap();
-    return tokens.len() as u16;
+/// Mentions messages typed during the last response, which are sent next, or drops them if it was cancelled.
+fn report_queue(state: &mut AppState, cancelled: bool) -> Result<()> {
+    // Piped input is all "queued", so there's nothing worth saying.
+    if !state.interactive {
+        return Ok(());
+    }
+    let Some(ref mut lines) = state.lines else {
+        return Ok(());
+    };
+    let mut queued = 0;
+    if cancelled {
+        while let Ok(Ok(_)) = lines.try_recv() {
+            queued += 1;
+        }
+    } else {
+        queued = lines.len();
+    }
+    if queued == 0 {
+        return Ok(());
+    }
+    let notice = match (cancelled, queued) {
+        (true, 1) => msg::get(Msg::TypedAheadClearedOne).to_string(),
+        (true, n) => msg::fmt(Msg::TypedAheadCleared, &[("count", &n)]),
+        (false, 1) => msg::get(Msg::TypedAheadOne).to_string(),
+        (false, n) => msg::fmt(Msg::TypedAhead, &[("count", &n)]),
+    };
+    writeln!(
+        chat::status_writer(state.stdout_tty),
+        "{}",
+        style::dim(&notice, state.color)
+    )?;
+    Ok(())
 }
 
-fn calc_price(inp: u16, out: u16, state: &AppState) -> f64 {
-    let inp = inp as f64;
-    let out = out as f64;
-    match state.model.as_str() {
-        "gpt-3.5-turbo" => inp * PRICE_3[0] + out * PRICE_3[1],
-        "gpt-4" => inp * PRICE_4[0] + out * PRICE_4[1],
-        "gpt-4 turbo" => inp * PRICE_4T[0] + out * PRICE_4T[1],
-        _ => 99999.0,
+/// Queues the one-shot request that just failed for want of a connection, to send later.
+fn queue_prompt(state: &AppState, err: &async_openai::error::OpenAIError) -> u8 {
+    // Routing with "auto" can fail before there's a request to keep.
+    let Some(request) = state.requests.last().cloned() else {
+        eprintln!("{}", msg::fmt(Msg::ErrorApi, &[("error", err)]));
+        return EXIT_API;
+    };
+    let output = state.tee.as_ref().map(|tee| tee.path.as_path());
+    match queue::add(request, &state.config, output) {
+        Ok((id, output)) => {
+            let notice = msg::fmt(Msg::Queued, &[("id", &id), ("path", &output.display())]);
+            eprintln!("{}", style::dim(&notice, state.color));
+            0
+        }
+        Err(e) => {
+            eprintln!(
+                "{}\n{}",
+                msg::fmt(Msg::ErrorApi, &[("error", err)]),
+                msg::fmt(Msg::NotQueued, &[("error", &e)])
+            );
+            EXIT_API
+        }
+    }
+}
+
+// How long background requests, like a title, get to finish once gpt is on its way out.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// Runs on every way out of the program: :quit, EOF, SIGTERM/SIGHUP and the end of one-shot mode.
+/// Background requests get a moment to finish, so their usage reaches the ledger and a title the
+/// session. Then the attached session is saved and its lock let go.
+async fn shutdown(state: &mut AppState) {
+    let stopped = inflight::finish_all(SHUTDOWN_GRACE).await;
+    let mut status = chat::status_writer(state.stdout_tty);
+    if stopped > 0 {
+        let notice = msg::fmt(Msg::BackgroundStopped, &[("count", &stopped)]);
+        let _ = writeln!(status, "{}", style::dim(&notice, state.color));
+    }
+    if state.config.autosave {
+        let name = state.lock.as_ref().map(|l| l.name().to_string());
+        let notice = match commands::autosave(state) {
+            Ok(Some(Autosave::Saved { name, count })) => {
+                msg::fmt(Msg::Autosaved, &[("count", &count), ("name", &name)])
+            }
+            Ok(Some(Autosave::Diverged(name))) => {
+                msg::fmt(Msg::AutosaveDiverged, &[("name", &name)])
+            }
+            Ok(None) => String::new(),
+            Err(e) => msg::fmt(
+                Msg::AutosaveFailed,
+                &[("name", &name.unwrap_or_default()), ("error", &e)],
+            ),
+        };
+        if !notice.is_empty() {
+            let _ = writeln!(status, "{}", style::dim(&notice, state.color));
+        }
     }
+    // Dropping the lock removes its file.
+    state.lock = None;
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
 }
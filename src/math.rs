@@ -0,0 +1,794 @@
+//! Rewrites LaTeX math in responses as Unicode for the terminal, e.g. `\(\alpha^2 + \frac{1}{2}\)`
+//! as `α² + ½`. Only what's shown is rewritten: the context, cache and files keep the raw text.
+//!
+//! Math is `$$...$$` or `\[...\]` for display and `\(...\)` or `$...$` inline. Code spans and fenced
+//! blocks are left alone, and so are dollar signs that look like prices, as in "$5 and $10".
+
+use serde::Deserialize;
+use std::io::{self, Write};
+
+/// How math is shown, set with `math` in the config or `:math`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MathMode {
+    /// As the model wrote it.
+    #[default]
+    Off,
+    Unicode,
+}
+
+impl MathMode {
+    pub fn parse(name: &str) -> Option<MathMode> {
+        match name {
+            "off" => Some(MathMode::Off),
+            "unicode" => Some(MathMode::Unicode),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MathMode::Off => "off",
+            MathMode::Unicode => "unicode",
+        }
+    }
+}
+
+// A display block that never closes is shown as it is once it gets this long.
+const MAX_BLOCK: usize = 8192;
+
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("varpi", "ϖ"),
+    ("rho", "ρ"),
+    ("varrho", "ϱ"),
+    ("sigma", "σ"),
+    ("varsigma", "ς"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Upsilon", "Υ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("times", "×"),
+    ("cdot", "·"),
+    ("div", "÷"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("ast", "∗"),
+    ("circ", "∘"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("sim", "∼"),
+    ("simeq", "≃"),
+    ("cong", "≅"),
+    ("equiv", "≡"),
+    ("propto", "∝"),
+    ("ll", "≪"),
+    ("gg", "≫"),
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("iint", "∬"),
+    ("oint", "∮"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("gets", "←"),
+    ("leftrightarrow", "↔"),
+    ("Rightarrow", "⇒"),
+    ("implies", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("Leftrightarrow", "⇔"),
+    ("iff", "⇔"),
+    ("mapsto", "↦"),
+    ("uparrow", "↑"),
+    ("downarrow", "↓"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("ni", "∋"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("supset", "⊃"),
+    ("supseteq", "⊇"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("setminus", "∖"),
+    ("emptyset", "∅"),
+    ("varnothing", "∅"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("nexists", "∄"),
+    ("neg", "¬"),
+    ("lnot", "¬"),
+    ("land", "∧"),
+    ("wedge", "∧"),
+    ("lor", "∨"),
+    ("vee", "∨"),
+    ("oplus", "⊕"),
+    ("otimes", "⊗"),
+    ("perp", "⊥"),
+    ("parallel", "∥"),
+    ("angle", "∠"),
+    ("degree", "°"),
+    ("prime", "′"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("cdots", "⋯"),
+    ("vdots", "⋮"),
+    ("ddots", "⋱"),
+    ("hbar", "ħ"),
+    ("ell", "ℓ"),
+    ("aleph", "ℵ"),
+    ("langle", "⟨"),
+    ("rangle", "⟩"),
+    ("lfloor", "⌊"),
+    ("rfloor", "⌋"),
+    ("lceil", "⌈"),
+    ("rceil", "⌉"),
+    ("mid", "|"),
+    ("vert", "|"),
+    ("Vert", "‖"),
+    ("quad", "  "),
+    ("qquad", "    "),
+];
+
+// Sizing and spacing commands that only affect layout.
+const IGNORED: &[&str] = &[
+    "left",
+    "right",
+    "big",
+    "Big",
+    "bigg",
+    "Bigg",
+    "bigl",
+    "bigr",
+    "Bigl",
+    "Bigr",
+    "displaystyle",
+    "textstyle",
+    "limits",
+    "nolimits",
+];
+
+// Commands whose argument is shown as it is.
+const TEXT: &[&str] = &[
+    "text",
+    "textrm",
+    "textbf",
+    "textit",
+    "mathrm",
+    "mathbf",
+    "mathit",
+    "mathsf",
+    "mathtt",
+    "operatorname",
+    "boldsymbol",
+    "mbox",
+];
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('−', '⁻'),
+    ('=', '⁼'),
+    ('(', '⁽'),
+    (')', '⁾'),
+    ('a', 'ᵃ'),
+    ('b', 'ᵇ'),
+    ('c', 'ᶜ'),
+    ('d', 'ᵈ'),
+    ('e', 'ᵉ'),
+    ('f', 'ᶠ'),
+    ('g', 'ᵍ'),
+    ('h', 'ʰ'),
+    ('i', 'ⁱ'),
+    ('j', 'ʲ'),
+    ('k', 'ᵏ'),
+    ('l', 'ˡ'),
+    ('m', 'ᵐ'),
+    ('n', 'ⁿ'),
+    ('o', 'ᵒ'),
+    ('p', 'ᵖ'),
+    ('r', 'ʳ'),
+    ('s', 'ˢ'),
+    ('t', 'ᵗ'),
+    ('u', 'ᵘ'),
+    ('v', 'ᵛ'),
+    ('w', 'ʷ'),
+    ('x', 'ˣ'),
+    ('y', 'ʸ'),
+    ('z', 'ᶻ'),
+    ('T', 'ᵀ'),
+    ('′', '′'),
+    ('*', '*'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+    ('−', '₋'),
+    ('=', '₌'),
+    ('(', '₍'),
+    (')', '₎'),
+    ('a', 'ₐ'),
+    ('e', 'ₑ'),
+    ('h', 'ₕ'),
+    ('i', 'ᵢ'),
+    ('j', 'ⱼ'),
+    ('k', 'ₖ'),
+    ('l', 'ₗ'),
+    ('m', 'ₘ'),
+    ('n', 'ₙ'),
+    ('o', 'ₒ'),
+    ('p', 'ₚ'),
+    ('r', 'ᵣ'),
+    ('s', 'ₛ'),
+    ('t', 'ₜ'),
+    ('u', 'ᵤ'),
+    ('v', 'ᵥ'),
+    ('x', 'ₓ'),
+];
+
+const FRACTIONS: &[(&str, &str, char)] = &[
+    ("1", "2", '½'),
+    ("1", "3", '⅓'),
+    ("2", "3", '⅔'),
+    ("1", "4", '¼'),
+    ("3", "4", '¾'),
+    ("1", "5", '⅕'),
+    ("1", "6", '⅙'),
+    ("1", "8", '⅛'),
+];
+
+/// Converts math in text that arrives in pieces. Text is passed on as soon as it's clear it
+/// isn't math; from the first character that could start some, it's held until the line ends,
+/// or a display block closes, so every span is converted whole.
+#[derive(Default)]
+pub struct Converter {
+    in_fence: bool,
+    // The current line, of which the first `emitted` bytes have been passed on.
+    line: String,
+    emitted: usize,
+    // The lines of a display block that hasn't closed yet.
+    block: String,
+}
+
+impl Converter {
+    /// Takes the next piece of text, returning what's ready to show.
+    pub fn push(&mut self, text: &str) -> String {
+        let mut out = String::new();
+        for piece in text.split_inclusive('\n') {
+            self.line.push_str(piece);
+            if piece.ends_with('\n') {
+                out.push_str(&self.end_line());
+            } else {
+                out.push_str(&self.ready());
+            }
+        }
+        out
+    }
+
+    /// Everything still held back, for when the text has ended.
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.line)[self.emitted..].to_string();
+        self.emitted = 0;
+        let block = std::mem::take(&mut self.block);
+        if self.in_fence {
+            return block + &rest;
+        }
+        let text = block + &rest;
+        convert_prose(&text).unwrap_or(text)
+    }
+
+    fn end_line(&mut self) -> String {
+        let line = std::mem::take(&mut self.line);
+        let rest = &line[std::mem::take(&mut self.emitted)..];
+        if !self.block.is_empty() {
+            self.block.push_str(rest);
+            return match convert_prose(&self.block) {
+                Some(converted) => {
+                    self.block.clear();
+                    converted
+                }
+                None if self.block.len() > MAX_BLOCK => std::mem::take(&mut self.block),
+                None => String::new(),
+            };
+        }
+        if line.trim_start().starts_with("```") {
+            self.in_fence = !self.in_fence;
+            return rest.to_string();
+        }
+        if self.in_fence {
+            return rest.to_string();
+        }
+        match convert_prose(rest) {
+            Some(converted) => converted,
+            None => {
+                self.block = rest.to_string();
+                String::new()
+            }
+        }
+    }
+
+    /// The part of the unfinished line that can be shown already.
+    fn ready(&mut self) -> String {
+        if !self.block.is_empty() {
+            return String::new();
+        }
+        let rest = &self.line[self.emitted..];
+        let end = if self.in_fence {
+            // The line could still turn out to be the closing fence.
+            match self.line.trim_start().starts_with('`') || self.line.trim().is_empty() {
+                true => 0,
+                false => rest.len(),
+            }
+        } else {
+            rest.find(['$', '\\', '`']).unwrap_or(rest.len())
+        };
+        let ready = rest[..end].to_string();
+        self.emitted += end;
+        ready
+    }
+}
+
+/// Writes through to `inner`, converting math on the way when it's turned on.
+pub struct MathWriter<W: Write> {
+    inner: W,
+    converter: Option<Converter>,
+    // The start of a character a write ended partway through, converted with the rest of it.
+    partial: Vec<u8>,
+}
+
+impl<W: Write> MathWriter<W> {
+    pub fn new(inner: W, mode: MathMode) -> Self {
+        MathWriter {
+            inner,
+            converter: (mode == MathMode::Unicode).then(Converter::default),
+            partial: Vec::new(),
+        }
+    }
+
+    /// Writes out whatever is still held back.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(ref mut converter) = self.converter {
+            let partial = String::from_utf8_lossy(&self.partial).into_owned();
+            self.partial.clear();
+            let mut rest = converter.push(&partial);
+            rest.push_str(&converter.finish());
+            self.inner.write_all(rest.as_bytes())?;
+        }
+        self.inner.flush()
+    }
+
+    /// The writer underneath, for text that isn't to be converted.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for MathWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.converter {
+            Some(ref mut converter) => {
+                self.partial.extend_from_slice(buf);
+                let complete = match std::str::from_utf8(&self.partial) {
+                    // Only an incomplete character at the end is waited for; invalid bytes
+                    // anywhere are replaced as usual.
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    _ => self.partial.len(),
+                };
+                let bytes: Vec<u8> = self.partial.drain(..complete).collect();
+                let text = String::from_utf8_lossy(&bytes);
+                self.inner.write_all(converter.push(&text).as_bytes())?;
+                Ok(buf.len())
+            }
+            None => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Converts the math spans in prose, leaving code spans alone. Returns `None` if a display
+/// block opens and doesn't close.
+fn convert_prose(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(['$', '\\', '`']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with('`') {
+            // A code span runs to the next backtick on the same line.
+            let end = rest[1..]
+                .find(['`', '\n'])
+                .map_or(rest.len(), |j| j + 2)
+                .min(rest.len());
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if let Some(after) = rest.strip_prefix("$$") {
+            let end = after.find("$$")?;
+            out.push_str(&convert(&after[..end]));
+            rest = &after[end + 2..];
+        } else if let Some(after) = rest.strip_prefix("\\[") {
+            let end = after.find("\\]")?;
+            out.push_str(&convert(&after[..end]));
+            rest = &after[end + 2..];
+        } else if let Some((span, after)) = rest.strip_prefix("\\(").and_then(|after| {
+            after
+                .find("\\)")
+                .map(|end| (&after[..end], &after[end + 2..]))
+        }) {
+            out.push_str(&convert(span));
+            rest = after;
+        } else if let Some((span, after)) = rest.strip_prefix('$').and_then(inline_dollars) {
+            out.push_str(&convert(span));
+            rest = after;
+        } else {
+            // A lone `\` or `$`, or an escaped dollar.
+            let len = if rest.starts_with("\\$") { 2 } else { 1 };
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// Splits `x$ and more` into the math and what follows, if the `$` before it opened inline math:
+/// the math can't start or end with a space, and a closing `$` can't be followed by a digit, so
+/// prices aren't mistaken for math.
+fn inline_dollars(after: &str) -> Option<(&str, &str)> {
+    if after.starts_with(char::is_whitespace) || after.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let line = &after[..after.find('\n').unwrap_or(after.len())];
+    let mut start = 0;
+    while let Some(i) = line[start..].find('$') {
+        let end = start + i;
+        let closes = end > 0
+            && !line[..end].ends_with(char::is_whitespace)
+            && !line[..end].ends_with('\\')
+            && !line[end + 1..].starts_with(|c: char| c.is_ascii_digit());
+        if closes {
+            return Some((&after[..end], &after[end + 1..]));
+        }
+        start = end + 1;
+    }
+    None
+}
+
+/// Converts the inside of a math span.
+fn convert(math: &str) -> String {
+    let chars: Vec<char> = math.chars().collect();
+    let mut parser = Parser { chars, pos: 0 };
+    parser.sequence(false).trim().to_string()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    /// Converts up to the end, or to the closing brace when `in_group`.
+    fn sequence(&mut self, in_group: bool) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.next() {
+            match c {
+                '}' if in_group => break,
+                '{' => out.push_str(&self.sequence(true)),
+                '\\' => out.push_str(&self.command()),
+                '^' => out.push_str(&script(&self.argument(), SUPERSCRIPTS, '^')),
+                '_' => out.push_str(&script(&self.argument(), SUBSCRIPTS, '_')),
+                '~' => out.push(' '),
+                '&' => {}
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// A command's or script's argument: a group, a command or a single character.
+    fn argument(&mut self) -> String {
+        while self.peek().is_some_and(|c| c == ' ') {
+            self.pos += 1;
+        }
+        match self.next() {
+            Some('{') => self.sequence(true),
+            Some('\\') => self.command(),
+            Some(c) => c.to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Converts a command, with the `\` already read.
+    fn command(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        if name.is_empty() {
+            return match self.next() {
+                Some('\\') => "\n".to_string(),
+                Some(',' | ';' | ':' | ' ' | '!') => " ".to_string(),
+                Some(c) => c.to_string(),
+                None => String::new(),
+            };
+        }
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument();
+                let denominator = self.argument();
+                fraction(numerator.trim(), denominator.trim())
+            }
+            "sqrt" => {
+                let index = match self.peek() {
+                    Some('[') => {
+                        self.pos += 1;
+                        let start = self.pos;
+                        while self.peek().is_some_and(|c| c != ']') {
+                            self.pos += 1;
+                        }
+                        let index: String = self.chars[start..self.pos].iter().collect();
+                        self.pos += 1;
+                        index
+                    }
+                    _ => String::new(),
+                };
+                let radicand = self.argument();
+                let root = match index.trim() {
+                    "" | "2" => "√".to_string(),
+                    "3" => "∛".to_string(),
+                    "4" => "∜".to_string(),
+                    index => format!("{}√", script(index, SUPERSCRIPTS, '^')),
+                };
+                format!("{root}{}", group(radicand.trim()))
+            }
+            "mathbb" => self.argument().chars().map(double_struck).collect(),
+            name if TEXT.contains(&name) => self.argument(),
+            name if IGNORED.contains(&name) => String::new(),
+            name => match SYMBOLS.iter().find(|(n, _)| *n == name) {
+                Some((_, symbol)) => symbol.to_string(),
+                // Functions like \sin and \log read fine as their names.
+                None => name.to_string(),
+            },
+        }
+    }
+}
+
+/// `a/b` with brackets around any side that needs them, or a fraction character like ½.
+fn fraction(numerator: &str, denominator: &str) -> String {
+    if let Some((_, _, c)) = FRACTIONS
+        .iter()
+        .find(|(n, d, _)| *n == numerator && *d == denominator)
+    {
+        return c.to_string();
+    }
+    format!("{}/{}", group(numerator), group(denominator))
+}
+
+/// The text, bracketed if it's more than one term.
+fn group(text: &str) -> String {
+    let mut depth = 0;
+    let compound = text.chars().any(|c| {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        depth == 0 && [' ', '+', '-', '−', '/', '=', '·', '×', '±'].contains(&c)
+    });
+    if compound {
+        format!("({text})")
+    } else {
+        text.to_string()
+    }
+}
+
+/// The text as superscript or subscript characters, or written with `^`/`_` if any are missing.
+fn script(text: &str, table: &[(char, char)], marker: char) -> String {
+    let text = text.trim();
+    let mapped: Option<String> = text
+        .chars()
+        .filter(|c| *c != ' ')
+        .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+        .collect();
+    match mapped {
+        Some(mapped) if !mapped.is_empty() => mapped,
+        _ if text.chars().count() == 1 => format!("{marker}{text}"),
+        _ => format!("{marker}({text})"),
+    }
+}
+
+fn double_struck(c: char) -> char {
+    match c {
+        'R' => 'ℝ',
+        'N' => 'ℕ',
+        'Z' => 'ℤ',
+        'Q' => 'ℚ',
+        'C' => 'ℂ',
+        'P' => 'ℙ',
+        'H' => 'ℍ',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn streamed(pieces: &[&str]) -> String {
+        let mut converter = Converter::default();
+        let mut out: String = pieces.iter().map(|p| converter.push(p)).collect();
+        out.push_str(&converter.finish());
+        out
+    }
+
+    #[test]
+    fn greek_letters_and_symbols() {
+        assert_eq!(convert("\\alpha + \\beta \\le \\pi"), "α + β ≤ π");
+        assert_eq!(convert("\\mathbb{R} \\to \\infty"), "ℝ → ∞");
+        // Functions read fine as their names.
+        assert_eq!(convert("\\sin x"), "sin x");
+    }
+
+    #[test]
+    fn scripts_use_unicode_when_every_character_has_one() {
+        assert_eq!(convert("x^2 + y_{12}"), "x² + y₁₂");
+        assert_eq!(convert("\\sum_{i=1}^{n} i"), "∑ᵢ₌₁ⁿ i");
+        assert_eq!(convert("x^{Q}"), "x^Q");
+        assert_eq!(convert("e^{i\\pi} = -1"), "e^(iπ) = -1");
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(convert("\\frac{1}{2}"), "½");
+        assert_eq!(convert("\\dfrac{x}{y}"), "x/y");
+        assert_eq!(convert("\\frac{a+b}{2}"), "(a+b)/2");
+        assert_eq!(convert("\\frac12"), "½");
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(convert("\\sqrt{2}"), "√2");
+        assert_eq!(convert("\\sqrt{x+1}"), "√(x+1)");
+        assert_eq!(convert("\\sqrt[3]{8}"), "∛8");
+        assert_eq!(convert("\\sqrt[n]{x}"), "ⁿ√x");
+    }
+
+    #[test]
+    fn layout_commands_are_dropped() {
+        assert_eq!(convert("\\left( x \\right)"), "( x )");
+        assert_eq!(convert("\\displaystyle \\big( a \\Big)"), "( a )");
+    }
+
+    #[test]
+    fn every_kind_of_span_is_converted() {
+        assert_eq!(convert_prose("so \\(x^2\\)."), Some("so x².".to_string()));
+        assert_eq!(convert_prose("a \\[x^2\\] b"), Some("a x² b".to_string()));
+        assert_eq!(convert_prose("$$\\pi$$"), Some("π".to_string()));
+        assert_eq!(convert_prose("$x$ and $y$"), Some("x and y".to_string()));
+    }
+
+    #[test]
+    fn prices_and_code_are_not_math() {
+        for text in ["It costs $5 and $10.", "`$x$` is code", "\\$5 or $ 5"] {
+            assert_eq!(convert_prose(text).as_deref(), Some(text));
+        }
+        assert_eq!(convert_prose("$$ x"), None);
+    }
+
+    #[test]
+    fn spans_split_across_pieces_are_converted_whole() {
+        assert_eq!(
+            streamed(&["Let \\(x^", "2\\) be", " real\n"]),
+            "Let x² be real\n"
+        );
+        assert_eq!(streamed(&["$$\n\\frac{1}{", "3}\n$$\n"]), "⅓\n");
+    }
+
+    #[test]
+    fn text_is_passed_on_until_math_could_start() {
+        let mut converter = Converter::default();
+        assert_eq!(converter.push("Let "), "Let ");
+        assert_eq!(converter.push("\\(x"), "");
+        assert_eq!(converter.push("\\) be\n"), "x be\n");
+    }
+
+    #[test]
+    fn fenced_code_is_left_alone() {
+        let text = "```\n$x^2$ \\frac{1}{2}\n```\n";
+        assert_eq!(streamed(&[text]), text);
+    }
+
+    #[test]
+    fn an_unclosed_span_is_shown_as_written() {
+        assert_eq!(streamed(&["end $a"]), "end $a");
+        assert_eq!(streamed(&["$$\nx^2\n"]), "$$\nx^2\n");
+    }
+
+    #[test]
+    fn the_writer_waits_for_a_split_character() {
+        let mut writer = MathWriter::new(Vec::new(), MathMode::Unicode);
+        let text = "é \\(\\alpha\\)\n".as_bytes();
+        writer.write_all(&text[..1]).unwrap();
+        writer.write_all(&text[1..]).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(String::from_utf8(writer.inner).unwrap(), "é α\n");
+
+        let mut off = MathWriter::new(Vec::new(), MathMode::Off);
+        off.write_all(b"\\(x\\)").unwrap();
+        off.finish().unwrap();
+        assert_eq!(off.inner, b"\\(x\\)");
+    }
+}
@@ -0,0 +1,416 @@
+//! A minimal client for MCP (Model Context Protocol) servers, which offer tools the model can call.
+//! Servers are spoken to with JSON-RPC, either spawned as child processes and sent it over their
+//! stdin and stdout, or reached at a URL with the HTTP and server-sent events transport: the
+//! server names an endpoint on its event stream, requests are POSTed there, and the responses
+//! come back as events.
+
+use futures::StreamExt;
+use reqwest::{header::ACCEPT, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, process::Stdio, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::timeout,
+};
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    tools::{Source, Tool, Tools},
+};
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+// Servers get this long to start up and to answer each request.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const CALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// The program to spawn, e.g. "npx".
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// The event stream of a server reached over HTTP, used when there's no command.
+    pub url: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Server {
+    name: String,
+    transport: Transport,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+enum Transport {
+    Stdio {
+        // Kept so the process lives as long as the connection, and is killed with it.
+        _child: Box<Child>,
+        stdin: ChildStdin,
+        stdout: Lines<BufReader<ChildStdout>>,
+    },
+    Sse {
+        client: reqwest::Client,
+        /// Where messages are POSTed, as the server said on its event stream.
+        endpoint: Url,
+        /// The data of each message event.
+        messages: mpsc::UnboundedReceiver<String>,
+        _reader: Reader,
+    },
+}
+
+/// The task reading an event stream, stopped with the connection.
+#[derive(Debug)]
+struct Reader(JoinHandle<()>);
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl Server {
+    async fn start(name: &str, config: &ServerConfig) -> Result<Server> {
+        let transport = match (&config.command, &config.url) {
+            (Some(command), _) => Transport::spawn(command, config)?,
+            (None, Some(url)) => Transport::open(url).await?,
+            (None, None) => return Err(AppError::Config(msg::get(Msg::McpNoCommand).to_string())),
+        };
+        Ok(Server {
+            name: name.to_string(),
+            transport,
+            next_id: 1,
+        })
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<()> {
+        match self.transport {
+            Transport::Stdio { ref mut stdin, .. } => {
+                let mut line = message.to_string();
+                line.push('\n');
+                stdin.write_all(line.as_bytes()).await?;
+                stdin.flush().await?;
+            }
+            Transport::Sse {
+                ref client,
+                ref endpoint,
+                ..
+            } => {
+                let response = client
+                    .post(endpoint.clone())
+                    .json(message)
+                    .send()
+                    .await
+                    .map_err(|e| AppError::Config(format!("{}: {e}", self.name)))?;
+                let status = response.status();
+                if !status.is_success() {
+                    return Err(AppError::Config(msg::fmt(
+                        Msg::McpAnswered,
+                        &[("server", &self.name), ("status", &status)],
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The next message from the server, or `None` once it's gone.
+    async fn receive(&mut self) -> Result<Option<String>> {
+        match self.transport {
+            Transport::Stdio { ref mut stdout, .. } => Ok(stdout.next_line().await?),
+            Transport::Sse {
+                ref mut messages, ..
+            } => Ok(messages.recv().await),
+        }
+    }
+}
+
+impl Transport {
+    fn spawn(command: &str, config: &ServerConfig) -> Result<Transport> {
+        let mut child = Command::new(command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            // Servers log to stderr, which would end up all over the conversation.
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(AppError::Config(msg::get(Msg::McpNoPipes).to_string()));
+        };
+        Ok(Transport::Stdio {
+            _child: Box::new(child),
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+
+    /// Connects to the event stream at `url` and waits for it to name the endpoint.
+    async fn open(url: &str) -> Result<Transport> {
+        let url = Url::parse(url).map_err(|e| {
+            AppError::Config(msg::fmt(Msg::McpBadUrl, &[("url", &url), ("error", &e)]))
+        })?;
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::Config(msg::fmt(
+                    Msg::McpNoConnection,
+                    &[("url", &url), ("error", &e)],
+                ))
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AppError::Config(msg::fmt(
+                Msg::McpAnswered,
+                &[("server", &url), ("status", &status)],
+            )));
+        }
+        let (endpoint_tx, endpoint_rx) = oneshot::channel();
+        let (tx, messages) = mpsc::unbounded_channel();
+        let reader = Reader(tokio::spawn(async move {
+            let mut endpoint_tx = Some(endpoint_tx);
+            let mut bytes = response.bytes_stream();
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut event = Event::default();
+            while let Some(Ok(chunk)) = bytes.next().await {
+                // Split on whole lines before decoding, so a character cut between chunks survives.
+                buffer.extend_from_slice(&chunk);
+                while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let Some((name, data)) = event.line(&String::from_utf8_lossy(&line)) else {
+                        continue;
+                    };
+                    match name.as_deref() {
+                        Some("endpoint") => {
+                            if let Some(tx) = endpoint_tx.take() {
+                                let _ = tx.send(data);
+                            }
+                        }
+                        None | Some("message") => {
+                            if tx.send(data).is_err() {
+                                return;
+                            }
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+        }));
+        let endpoint = endpoint_rx
+            .await
+            .map_err(|_| AppError::Config(msg::fmt(Msg::McpNoEndpoint, &[("url", &url)])))?;
+        // Usually a path, relative to the stream's url.
+        let endpoint = url.join(endpoint.trim()).map_err(|e| {
+            AppError::Config(msg::fmt(
+                Msg::McpBadEndpoint,
+                &[("url", &url), ("error", &e)],
+            ))
+        })?;
+        Ok(Transport::Sse {
+            client,
+            endpoint,
+            messages,
+            _reader: reader,
+        })
+    }
+}
+
+/// A server-sent event being read, line by line.
+#[derive(Default)]
+struct Event {
+    name: Option<String>,
+    data: Option<String>,
+}
+
+impl Event {
+    /// Takes one line of the stream, returning the event's name and data when it ends one.
+    fn line(&mut self, line: &str) -> Option<(Option<String>, String)> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            let name = self.name.take();
+            return self.data.take().map(|data| (name, data));
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.name = Some(value.to_string()),
+            "data" => match self.data {
+                Some(ref mut data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            // Comments, ids and retry times.
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Server {
+    /// Sends a request and waits for its response, skipping anything else the server sends meanwhile.
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))
+            .await?;
+        loop {
+            let Some(line) = self.receive().await? else {
+                return Err(AppError::Config(msg::fmt(
+                    Msg::McpExited,
+                    &[("server", &self.name)],
+                )));
+            };
+            let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let text = error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or(msg::get(Msg::UnknownError));
+                return Err(AppError::Command(format!("{}: {text}", self.name)));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    async fn initialize(&mut self) -> Result<Vec<Value>> {
+        self.request(
+            "initialize",
+            json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": {"name": "gpt-cli", "version": env!("CARGO_PKG_VERSION")},
+            }),
+        )
+        .await?;
+        self.send(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await?;
+        let listed = self.request("tools/list", json!({})).await?;
+        Ok(listed
+            .get("tools")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+/// Starts every configured server and collects their tools.
+/// A server that fails is reported and left out, so chatting works regardless.
+pub async fn connect(configs: &BTreeMap<String, ServerConfig>, tools: &mut Tools) {
+    for (name, config) in configs {
+        let started = timeout(STARTUP_TIMEOUT, async {
+            let mut server = Server::start(name, config).await?;
+            let listed = server.initialize().await?;
+            Ok::<_, AppError>((server, listed))
+        })
+        .await;
+        let (server, listed) = match started {
+            Ok(Ok(started)) => started,
+            Ok(Err(e)) => {
+                eprintln!(
+                    "{}",
+                    msg::fmt(Msg::McpStartFailed, &[("name", &name), ("error", &e)])
+                );
+                continue;
+            }
+            Err(_) => {
+                eprintln!("{}", msg::fmt(Msg::McpStartTimeout, &[("name", &name)]));
+                continue;
+            }
+        };
+        for tool in listed {
+            let Some(remote_name) = tool.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            tools.add(Tool {
+                name: remote_name.to_string(),
+                source: Source::Mcp {
+                    server: server.name.clone(),
+                    remote_name: remote_name.to_string(),
+                },
+                description: tool
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                schema: tool
+                    .get("inputSchema")
+                    .cloned()
+                    .unwrap_or_else(|| json!({"type": "object"})),
+                enabled: true,
+            });
+        }
+        tools.servers.push(server);
+    }
+}
+
+/// Calls a tool on the named server. Failures the model should hear about come back as text.
+pub async fn call(
+    servers: &mut [Server],
+    server: &str,
+    remote_name: &str,
+    arguments: Value,
+) -> Result<String> {
+    let Some(server) = servers.iter_mut().find(|s| s.name == server) else {
+        return Ok(format!("Error: the server '{server}' isn't running."));
+    };
+    let params = json!({"name": remote_name, "arguments": arguments});
+    let result = match timeout(CALL_TIMEOUT, server.request("tools/call", params)).await {
+        Ok(result) => result?,
+        Err(_) => return Ok(format!("Error: '{remote_name}' didn't finish in time.")),
+    };
+
+    let text: Vec<&str> = result
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|part| part.get("text").and_then(Value::as_str))
+        .collect();
+    let text = text.join("\n");
+    if result.get("isError").and_then(Value::as_bool) == Some(true) {
+        Ok(format!("Error: {text}"))
+    } else {
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(stream: &str) -> Vec<(Option<String>, String)> {
+        let mut event = Event::default();
+        stream
+            .split_inclusive('\n')
+            .filter_map(|line| event.line(line))
+            .collect()
+    }
+
+    #[test]
+    fn events_are_read_as_the_spec_says() {
+        let stream = ": keep-alive\n\nevent: endpoint\ndata: /messages?id=1\n\nid: 7\ndata:{\"a\":\r\ndata: 1}\r\n\r\nevent: ping\n\n";
+        assert_eq!(
+            events(stream),
+            [
+                (Some("endpoint".to_string()), "/messages?id=1".to_string()),
+                (None, "{\"a\":\n1}".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,748 @@
+//! What's known about each model: prices, output limits and context windows, plus the shortcuts
+//! for `:model`. One registry is built from layers, later ones winning field by field: the
+//! `models.toml` built into the binary, the provider's catalog when one is fetched, and the user's
+//! own `models.toml` next to the config file.
+//!
+//! Fine-tuned models, with ids like `ft:gpt-4o-mini-2024-07-18:org::abc123`, aren't listed: they
+//! take the metadata of the model they were tuned from, at its `fine_tuned_price`.
+
+use std::{
+    fmt, fs, io,
+    path::Path,
+    sync::{Mutex, OnceLock, RwLock},
+};
+use toml::{Table, Value};
+
+use crate::{
+    currency::Currency,
+    error::{AppError, Result},
+    msg::{self, Msg},
+    paths,
+};
+
+const BUNDLED: &str = include_str!("models.toml");
+
+#[derive(Clone, Copy, Debug)]
+pub struct ModelInfo {
+    pub id: &'static str,
+    /// USD per million tokens: [input, cached input, output]. Every listed model has one; only a
+    /// fine-tuned model whose base has no `fine_tuned_price` doesn't.
+    pub price: Option<[f64; 3]>,
+    /// max_tokens used unless the user picks one.
+    pub default_output: u32,
+    /// The most the model can produce in one response.
+    pub max_output: u32,
+    /// Prompt and response tokens combined.
+    pub context_window: u32,
+    /// What models fine-tuned from this one cost instead, in the same units as `price`.
+    pub fine_tuned_price: Option<[f64; 3]>,
+    /// What the model takes besides text, when it's known.
+    pub capabilities: Option<Capabilities>,
+    /// The tokenizer its prompts are counted with.
+    pub encoding: Encoding,
+}
+
+impl ModelInfo {
+    /// The input and output prices per million tokens, as shown in tables, or `?` when unknown.
+    pub fn list_prices(&self, currency: &Currency) -> (String, String) {
+        match self.price {
+            Some([inp, _, out]) => (currency.format(inp), currency.format(out)),
+            None => ("?".to_string(), "?".to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Images in prompts.
+    pub vision: bool,
+    /// Tool calls.
+    pub tools: bool,
+    /// A JSON response format.
+    pub json: bool,
+}
+
+impl Capabilities {
+    const NAMES: [&'static str; 3] = ["vision", "tools", "json"];
+
+    /// The ones the model has, like "vision/tools", or "text only".
+    pub fn summary(&self) -> String {
+        let has = [self.vision, self.tools, self.json];
+        let names: Vec<&str> = Self::NAMES
+            .iter()
+            .zip(has)
+            .filter_map(|(name, has)| has.then_some(*name))
+            .collect();
+        match names.is_empty() {
+            true => msg::get(Msg::TextOnly).to_string(),
+            false => names.join("/"),
+        }
+    }
+}
+
+/// How a model splits text into tokens. Only OpenAI publishes its tokenizers, so every other model
+/// is counted as if it used cl100k, which is close enough for sizing a prompt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Cl100k,
+    O200k,
+}
+
+impl Encoding {
+    const NAMES: [&'static str; 2] = ["cl100k_base", "o200k_base"];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self as usize]
+    }
+
+    /// For a model that doesn't say: o200k from GPT-4o on, and for the reasoning models.
+    pub fn guess(model: &str) -> Encoding {
+        let id = model.rsplit('/').next().unwrap_or(model);
+        let id = fine_tuned(id).map_or(id, |ft| ft.base);
+        let o200k = [
+            "gpt-4o",
+            "chatgpt-4o",
+            "gpt-4.1",
+            "gpt-4.5",
+            "gpt-5",
+            "o1",
+            "o3",
+            "o4",
+        ];
+        match o200k.iter().any(|prefix| id.starts_with(prefix)) {
+            true => Encoding::O200k,
+            false => Encoding::Cl100k,
+        }
+    }
+}
+
+/// max_tokens for models we know nothing about.
+pub const GLOBAL_DEFAULT_OUTPUT: u32 = 512;
+
+/// Where a field's value came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Bundled,
+    Catalog,
+    User,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(msg::get(match self {
+            Layer::Bundled => Msg::LayerBundled,
+            Layer::Catalog => Msg::LayerCatalog,
+            Layer::User => Msg::LayerUser,
+        }))
+    }
+}
+
+/// The layer each of a model's fields came from, in the order of [`ModelInfo`]'s.
+#[derive(Clone, Copy, Debug)]
+pub struct Sources {
+    pub price: Layer,
+    pub default_output: Layer,
+    pub max_output: Layer,
+    pub context_window: Layer,
+    pub fine_tuned_price: Layer,
+    pub capabilities: Layer,
+    pub encoding: Layer,
+}
+
+#[derive(Debug)]
+struct Entry {
+    info: ModelInfo,
+    from: Sources,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    entries: Vec<Entry>,
+    shortcuts: Vec<(&'static str, &'static str)>,
+    /// What was wrong with the user's file. Bad entries are left out; the rest still apply.
+    problems: Vec<String>,
+    /// The fine-tuned models looked up so far, each made from its base model's entry.
+    fine_tuned: Mutex<Vec<&'static ModelInfo>>,
+}
+
+/// A model as a file gives it: any field can be left out to keep the one below.
+#[derive(Debug, Default)]
+struct FileEntry {
+    id: String,
+    price: Option<[f64; 3]>,
+    default_output: Option<u32>,
+    max_output: Option<u32>,
+    context_window: Option<u32>,
+    fine_tuned_price: Option<[f64; 3]>,
+    capabilities: Option<Capabilities>,
+    encoding: Option<Encoding>,
+}
+
+#[derive(Debug, Default)]
+struct ModelsFile {
+    models: Vec<FileEntry>,
+    shortcuts: Vec<(String, String)>,
+}
+
+// Models fetched from the provider at startup, e.g. OpenRouter's catalog.
+static CATALOG: OnceLock<Vec<ModelInfo>> = OnceLock::new();
+// The ids of the organization's fine-tuned models, listed by OpenAI at startup.
+static FINE_TUNED: OnceLock<Vec<String>> = OnceLock::new();
+// Built on first use and again after a refresh or once the catalog arrives. Registries are leaked
+// so lookups can hand out `&'static` entries; they're only rebuilt a handful of times per run.
+static REGISTRY: RwLock<Option<&'static Registry>> = RwLock::new(None);
+
+fn registry() -> &'static Registry {
+    if let Some(registry) = *REGISTRY.read().unwrap_or_else(|e| e.into_inner()) {
+        return registry;
+    }
+    let mut slot = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    slot.get_or_insert_with(|| Box::leak(Box::new(Registry::build())))
+}
+
+impl Registry {
+    fn build() -> Registry {
+        let mut registry = Registry::default();
+        // The built-in file ships with the binary, so anything wrong with it is a bug, not a problem
+        // for the user to fix.
+        let (bundled, _) = parse(BUNDLED);
+        registry.merge(bundled, Layer::Bundled);
+        for info in CATALOG.get().into_iter().flatten() {
+            registry.set(*info, Layer::Catalog);
+        }
+        let path = paths::models_file();
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let (user, problems) = parse(&text);
+                registry.problems = problems;
+                registry.merge(user, Layer::User);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => registry.problems.push(msg::fmt(
+                Msg::ModelsUnreadable,
+                &[("path", &path.display()), ("error", &e)],
+            )),
+        }
+        registry
+    }
+
+    fn merge(&mut self, file: ModelsFile, layer: Layer) {
+        for entry in file.models {
+            let Some(existing) = self.entries.iter_mut().find(|e| e.info.id == entry.id) else {
+                match new_model(&entry) {
+                    Some(info) => self.set(info, layer),
+                    None => self
+                        .problems
+                        .push(msg::fmt(Msg::ModelsNeedsPrice, &[("id", &entry.id)])),
+                }
+                continue;
+            };
+            let (info, from) = (&mut existing.info, &mut existing.from);
+            if let Some(price) = entry.price {
+                (info.price, from.price) = (Some(price), layer);
+            }
+            if let Some(n) = entry.default_output {
+                (info.default_output, from.default_output) = (n, layer);
+            }
+            if let Some(n) = entry.max_output {
+                (info.max_output, from.max_output) = (n, layer);
+            }
+            if let Some(n) = entry.context_window {
+                (info.context_window, from.context_window) = (n, layer);
+            }
+            if let Some(price) = entry.fine_tuned_price {
+                (info.fine_tuned_price, from.fine_tuned_price) = (Some(price), layer);
+            }
+            if let Some(capabilities) = entry.capabilities {
+                (info.capabilities, from.capabilities) = (Some(capabilities), layer);
+            }
+            if let Some(encoding) = entry.encoding {
+                (info.encoding, from.encoding) = (encoding, layer);
+            }
+        }
+        for (short, id) in file.shortcuts {
+            let (short, id): (&str, &str) = (leak(short), leak(id));
+            match self.shortcuts.iter_mut().find(|(s, _)| *s == short) {
+                Some(existing) => existing.1 = id,
+                None => self.shortcuts.push((short, id)),
+            }
+        }
+    }
+
+    /// Adds a model, or replaces every field of one already known.
+    fn set(&mut self, info: ModelInfo, layer: Layer) {
+        let from = Sources {
+            price: layer,
+            default_output: layer,
+            max_output: layer,
+            context_window: layer,
+            fine_tuned_price: layer,
+            capabilities: layer,
+            encoding: layer,
+        };
+        match self.entries.iter_mut().find(|e| e.info.id == info.id) {
+            Some(existing) => *existing = Entry { info, from },
+            None => self.entries.push(Entry { info, from }),
+        }
+    }
+}
+
+/// A model a file adds, with what's left out filled in the way the catalog does it.
+fn new_model(entry: &FileEntry) -> Option<ModelInfo> {
+    let price = Some(entry.price?);
+    let context_window = entry.context_window?;
+    let max_output = entry.max_output.unwrap_or(context_window);
+    Some(ModelInfo {
+        id: leak(entry.id.clone()),
+        price,
+        default_output: entry.default_output.unwrap_or(max_output.min(4096)),
+        max_output,
+        context_window,
+        fine_tuned_price: entry.fine_tuned_price,
+        capabilities: entry.capabilities,
+        encoding: entry.encoding.unwrap_or_else(|| Encoding::guess(&entry.id)),
+    })
+}
+
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+/// Reads a models file, returning what could be used and a description of everything that
+/// couldn't: unknown keys, malformed prices and the like.
+fn parse(text: &str) -> (ModelsFile, Vec<String>) {
+    let mut file = ModelsFile::default();
+    let mut problems = Vec::new();
+    let table: Table = match toml::from_str(text) {
+        Ok(table) => table,
+        Err(e) => return (file, vec![e.to_string()]),
+    };
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("model", Value::Array(models)) => {
+                for (i, model) in models.into_iter().enumerate() {
+                    match parse_entry(model, i + 1) {
+                        Ok(entry) => file.models.push(entry),
+                        Err(mut entry_problems) => problems.append(&mut entry_problems),
+                    }
+                }
+            }
+            ("shortcuts", Value::Table(shortcuts)) => {
+                for (short, id) in shortcuts {
+                    match id {
+                        Value::String(id) => file.shortcuts.push((short.to_lowercase(), id)),
+                        _ => {
+                            problems.push(msg::fmt(Msg::ModelsShortcutNotId, &[("short", &short)]))
+                        }
+                    }
+                }
+            }
+            ("model", _) => problems.push(msg::get(Msg::ModelsModelTables).to_string()),
+            ("shortcuts", _) => problems.push(msg::get(Msg::ModelsShortcutsTable).to_string()),
+            (key, _) => problems.push(msg::fmt(Msg::ModelsUnknownKey, &[("key", &key)])),
+        }
+    }
+    (file, problems)
+}
+
+/// The `n`th `[[model]]`, or everything wrong with it. An entry with any problem is left out
+/// whole, since half of a change could be worse than none of it.
+fn parse_entry(model: Value, n: usize) -> std::result::Result<FileEntry, Vec<String>> {
+    let Value::Table(fields) = model else {
+        return Err(vec![msg::fmt(Msg::ModelsEntryNotTable, &[("n", &n)])]);
+    };
+    let Some(Value::String(id)) = fields.get("id").filter(|id| id.as_str() != Some("")) else {
+        return Err(vec![msg::fmt(Msg::ModelsEntryNoId, &[("n", &n)])]);
+    };
+    let mut entry = FileEntry {
+        id: id.clone(),
+        ..FileEntry::default()
+    };
+    let mut problems = Vec::new();
+    for (key, value) in &fields {
+        let problem = |what: &str| {
+            msg::fmt(
+                Msg::ModelsFieldProblem,
+                &[("id", &id), ("key", &key), ("problem", &what)],
+            )
+        };
+        match key.as_str() {
+            "id" => {}
+            "price" => match parse_price(value) {
+                Ok(price) => entry.price = Some(price),
+                Err(e) => problems.push(problem(&e)),
+            },
+            "fine_tuned_price" => match parse_price(value) {
+                Ok(price) => entry.fine_tuned_price = Some(price),
+                Err(e) => problems.push(problem(&e)),
+            },
+            "capabilities" => match parse_capabilities(value) {
+                Ok(capabilities) => entry.capabilities = Some(capabilities),
+                Err(e) => problems.push(problem(&e)),
+            },
+            "encoding" => match parse_encoding(value) {
+                Ok(encoding) => entry.encoding = Some(encoding),
+                Err(e) => problems.push(problem(&e)),
+            },
+            "default_output" | "max_output" | "context_window" => {
+                let Some(tokens) = value
+                    .as_integer()
+                    .and_then(|n| u32::try_from(n).ok())
+                    .filter(|&n| n > 0)
+                else {
+                    problems.push(problem(msg::get(Msg::ModelsPositiveTokens)));
+                    continue;
+                };
+                match key.as_str() {
+                    "default_output" => entry.default_output = Some(tokens),
+                    "max_output" => entry.max_output = Some(tokens),
+                    _ => entry.context_window = Some(tokens),
+                }
+            }
+            _ => problems.push(msg::fmt(
+                Msg::ModelsEntryUnknownKey,
+                &[("id", &id), ("key", &key)],
+            )),
+        }
+    }
+    match problems.is_empty() {
+        true => Ok(entry),
+        false => Err(problems),
+    }
+}
+
+/// `[input, output]`, or `[input, cached input, output]` for models with prompt caching.
+fn parse_price(value: &Value) -> std::result::Result<[f64; 3], String> {
+    let numbers: Option<Vec<f64>> = value.as_array().and_then(|prices| {
+        prices
+            .iter()
+            .map(|p| p.as_float().or_else(|| p.as_integer().map(|n| n as f64)))
+            .collect()
+    });
+    let price = match numbers.as_deref() {
+        Some(&[input, output]) => [input, input, output],
+        Some(&[input, cached, output]) => [input, cached, output],
+        _ => return Err(msg::get(Msg::ModelsPriceShape).to_string()),
+    };
+    if price.iter().any(|p| !p.is_finite() || *p < 0.) {
+        return Err(msg::get(Msg::ModelsNegativePrice).to_string());
+    }
+    Ok(price)
+}
+
+/// A list of some of "vision", "tools" and "json".
+fn parse_capabilities(value: &Value) -> std::result::Result<Capabilities, String> {
+    let wrong = || {
+        msg::fmt(
+            Msg::ModelsCapabilities,
+            &[(
+                "names",
+                &Capabilities::NAMES.map(|n| format!("\"{n}\"")).join(", "),
+            )],
+        )
+    };
+    let mut capabilities = Capabilities::default();
+    for name in value.as_array().ok_or_else(wrong)? {
+        match name.as_str() {
+            Some("vision") => capabilities.vision = true,
+            Some("tools") => capabilities.tools = true,
+            Some("json") => capabilities.json = true,
+            _ => return Err(wrong()),
+        }
+    }
+    Ok(capabilities)
+}
+
+/// "cl100k_base" or "o200k_base".
+fn parse_encoding(value: &Value) -> std::result::Result<Encoding, String> {
+    match value.as_str() {
+        Some("cl100k_base") => Ok(Encoding::Cl100k),
+        Some("o200k_base") => Ok(Encoding::O200k),
+        _ => Err(msg::fmt(
+            Msg::ModelsEncoding,
+            &[(
+                "names",
+                &Encoding::NAMES.map(|n| format!("\"{n}\"")).join(", "),
+            )],
+        )),
+    }
+}
+
+/// Runs `gpt models validate`, returning the exit code: 1 if there's anything wrong.
+pub fn validate(path: &Path) -> Result<u8> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        AppError::Config(msg::fmt(
+            Msg::ReadFailed,
+            &[("path", &path.display()), ("error", &e)],
+        ))
+    })?;
+    let problems = check(&text);
+    if problems.is_empty() {
+        println!(
+            "{}",
+            msg::fmt(Msg::ModelsFine, &[("path", &path.display())])
+        );
+        return Ok(0);
+    }
+    for problem in &problems {
+        println!("{}: {problem}", path.display());
+    }
+    Ok(1)
+}
+
+/// Everything wrong with a models file, on its own and merged over the built-in one.
+fn check(text: &str) -> Vec<String> {
+    let (file, mut problems) = parse(text);
+    let mut registry = Registry::default();
+    registry.merge(parse(BUNDLED).0, Layer::Bundled);
+    registry.merge(file, Layer::User);
+    problems.append(&mut registry.problems);
+    for entry in &registry.entries {
+        let info = &entry.info;
+        if info.default_output > info.max_output || info.max_output > info.context_window {
+            problems.push(msg::fmt(Msg::ModelsLimits, &[("id", &info.id)]));
+        }
+    }
+    for (short, id) in &registry.shortcuts {
+        if !registry.entries.iter().any(|e| e.info.id == *id) {
+            problems.push(msg::fmt(
+                Msg::ModelsShortcutUnknown,
+                &[("short", &short), ("id", &id)],
+            ));
+        }
+    }
+    problems
+}
+
+/// Reads the user's models.toml again, returning what's wrong with it.
+pub fn refresh() -> &'static [String] {
+    *REGISTRY.write().unwrap_or_else(|e| e.into_inner()) = None;
+    problems()
+}
+
+/// What's wrong with the user's models.toml, as found when it was last read.
+pub fn problems() -> &'static [String] {
+    &registry().problems
+}
+
+/// Short names accepted by `:model` and `--model`.
+pub fn shortcuts() -> &'static [(&'static str, &'static str)] {
+    &registry().shortcuts
+}
+
+/// Resolves a shortcut or a known model id to the model id.
+/// Ids with a slash, like `anthropic/claude-3.5-sonnet`, are routed by the provider and accepted as they are.
+/// So are Claude and Gemini ids, which come in many versions, and `claude-3-5-sonnet` means its latest version.
+pub fn resolve(name: &str) -> Option<String> {
+    // Fine-tuned ids are only ever what the provider made them, case and all.
+    if name.contains('/') || fine_tuned(name).is_some() {
+        return Some(name.to_string());
+    }
+    let name = name.to_lowercase();
+    if name.starts_with("claude-") {
+        let latest = format!("{name}-latest");
+        return Some(lookup(&latest).map_or(name, |m| m.id.to_string()));
+    }
+    if name.starts_with("gemini-") {
+        return Some(name);
+    }
+    shortcuts()
+        .iter()
+        .find(|(short, _)| *short == name)
+        .map(|(_, id)| id.to_string())
+        .or_else(|| lookup(&name).map(|m| m.id.to_string()))
+}
+
+/// Whether `model` only takes its default temperature, as OpenAI's reasoning models do.
+pub fn fixed_temperature(model: &str) -> bool {
+    let id = model.rsplit('/').next().unwrap_or(model);
+    let id = fine_tuned(id).map_or(id, |ft| ft.base);
+    ["o1", "o3", "o4", "gpt-5"]
+        .iter()
+        .any(|prefix| id.starts_with(prefix))
+}
+
+/// A model's metadata. A fine-tuned model that isn't listed itself gets its base model's, priced
+/// at the base model's fine-tuned rate, or with no price if that rate isn't known.
+pub fn lookup(model: &str) -> Option<&'static ModelInfo> {
+    let registry = registry();
+    if let Some(info) = all().find(|m| m.id == model) {
+        return Some(info);
+    }
+    let base = base_entry(registry, &fine_tuned(model)?)?;
+    // Never the base model's own rate: fine-tuned ones are billed at more.
+    let price = base.info.fine_tuned_price;
+    let mut cache = registry
+        .fine_tuned
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(info) = cache.iter().find(|m| m.id == model) {
+        return Some(info);
+    }
+    let info: &'static ModelInfo = Box::leak(Box::new(ModelInfo {
+        id: leak(model.to_string()),
+        price,
+        fine_tuned_price: None,
+        ..base.info
+    }));
+    cache.push(info);
+    Some(info)
+}
+
+/// The tokenizer for `model`: its registry entry's, or its base model's for a fine-tuned one,
+/// guessed from the id for a model the registry doesn't list.
+pub fn encoding(model: &str) -> Encoding {
+    lookup(model).map_or_else(|| Encoding::guess(model), |info| info.encoding)
+}
+
+/// A model's merged metadata and where each field came from, for `:models info`.
+pub fn sources(model: &str) -> Option<(&'static ModelInfo, Sources)> {
+    let registry = registry();
+    if let Some(entry) = registry.entries.iter().find(|e| e.info.id == model) {
+        return Some((&entry.info, entry.from));
+    }
+    let base = base_entry(registry, &fine_tuned(model)?)?;
+    let from = Sources {
+        price: base.from.fine_tuned_price,
+        ..base.from
+    };
+    Some((lookup(model)?, from))
+}
+
+/// The parts of a fine-tuned model's id: `ft:<base>:<organization>:<suffix>:<job id>`, where the
+/// suffix can be empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FineTuned<'a> {
+    pub base: &'a str,
+    pub organization: &'a str,
+    pub suffix: &'a str,
+    pub id: &'a str,
+}
+
+pub fn fine_tuned(model: &str) -> Option<FineTuned<'_>> {
+    let rest = model.strip_prefix("ft:")?;
+    let parts: Vec<&str> = rest.split(':').collect();
+    let &[base, organization, suffix, id] = parts.as_slice() else {
+        return None;
+    };
+    (!base.is_empty() && !id.is_empty()).then_some(FineTuned {
+        base,
+        organization,
+        suffix,
+        id,
+    })
+}
+
+/// The entry for the model `ft` was tuned from. Fine-tuning takes a dated snapshot, like
+/// `gpt-4o-mini-2024-07-18` or `gpt-3.5-turbo-0125`, which falls back to the model it's a
+/// snapshot of when it isn't listed itself.
+fn base_entry<'r>(registry: &'r Registry, ft: &FineTuned) -> Option<&'r Entry> {
+    let find = |id: &str| registry.entries.iter().find(|e| e.info.id == id);
+    find(ft.base).or_else(|| find(undated(ft.base)?))
+}
+
+/// `model` without a date at the end: `-2024-07-18` or `-0125`.
+fn undated(model: &str) -> Option<&str> {
+    let is_date =
+        |part: &str, len: usize| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    let parts: Vec<&str> = model.rsplitn(4, '-').collect();
+    match parts.as_slice() {
+        [day, month, year, rest] if is_date(day, 2) && is_date(month, 2) && is_date(year, 4) => {
+            Some(rest)
+        }
+        _ => model
+            .rsplit_once('-')
+            .filter(|(_, date)| is_date(date, 4))
+            .map(|(rest, _)| rest),
+    }
+}
+
+/// Keeps the ids of the organization's fine-tuned models for `:models`. Only the first call has
+/// any effect.
+pub fn set_fine_tuned(ids: Vec<String>) {
+    let _ = FINE_TUNED.set(ids);
+}
+
+/// The organization's fine-tuned models, once they've been listed.
+pub fn fine_tuned_models() -> Option<&'static [String]> {
+    FINE_TUNED.get().map(Vec::as_slice)
+}
+
+/// Every model with known pricing.
+pub fn all() -> impl Iterator<Item = &'static ModelInfo> {
+    registry().entries.iter().map(|e| &e.info)
+}
+
+/// Adds the provider's catalog to the registry, under the user's file. Only the first call has
+/// any effect.
+pub fn set_catalog(models: Vec<ModelInfo>) {
+    if CATALOG.set(models).is_ok() {
+        *REGISTRY.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+pub fn catalog_loaded() -> bool {
+    CATALOG.get().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fine_tuned_model_is_priced_at_the_fine_tuned_rate() {
+        let info = lookup("ft:gpt-4o-mini-2024-07-18:org::abc123").unwrap();
+        assert_eq!(info.price, lookup("gpt-4o-mini").unwrap().fine_tuned_price);
+        assert_eq!(info.context_window, 128_000);
+    }
+
+    #[test]
+    fn fine_tuned_model_without_a_rate_has_no_price() {
+        // gpt-4 has no fine_tuned_price, so its own price would be wrong.
+        let info = lookup("ft:gpt-4-0613:org::abc123").unwrap();
+        assert_eq!(info.price, None);
+        assert_eq!(info.context_window, lookup("gpt-4").unwrap().context_window);
+    }
+
+    #[test]
+    fn fine_tuned_ids() {
+        let ft = fine_tuned("ft:gpt-4o-mini-2024-07-18:acme:support:abc123").unwrap();
+        assert_eq!(ft.base, "gpt-4o-mini-2024-07-18");
+        assert_eq!(ft.organization, "acme");
+        assert_eq!(ft.suffix, "support");
+        assert_eq!(ft.id, "abc123");
+        assert!(fine_tuned("ft:gpt-4o-mini").is_none());
+        assert!(fine_tuned("gpt-4o-mini").is_none());
+    }
+
+    #[test]
+    fn each_model_has_its_encoding() {
+        assert_eq!(encoding("gpt-4o"), Encoding::O200k);
+        assert_eq!(encoding("gpt-4"), Encoding::Cl100k);
+        // A fine-tuned model is counted like the model it was tuned from.
+        assert_eq!(
+            encoding("ft:gpt-4o-mini-2024-07-18:org::abc123"),
+            Encoding::O200k
+        );
+        assert_eq!(
+            encoding("ft:gpt-3.5-turbo-0125:org::abc123"),
+            Encoding::Cl100k
+        );
+        // Unlisted ones are guessed from the id.
+        assert_eq!(encoding("openai/gpt-4.1"), Encoding::O200k);
+        assert_eq!(encoding("o3-mini"), Encoding::O200k);
+        assert_eq!(encoding("mistral-large"), Encoding::Cl100k);
+    }
+
+    #[test]
+    fn a_file_can_set_the_encoding() {
+        let (file, problems) = parse("[[model]]\nid = \"gpt-4\"\nencoding = \"o200k_base\"\n");
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(file.models[0].encoding, Some(Encoding::O200k));
+
+        let (file, problems) = parse("[[model]]\nid = \"gpt-4\"\nencoding = \"p50k\"\n");
+        assert!(file.models.is_empty());
+        assert!(problems[0].contains("\"o200k_base\""), "{problems:?}");
+    }
+}
@@ -0,0 +1,2885 @@
+//! The catalog of what gpt itself says: `:help`, the footer, errors, what commands print and the
+//! notices around an answer, in the language `lang` picks. Answers are never translated, only what's around them.
+//!
+//! Each message is a [`Msg`], and every language is a `match` over all of them, so a language
+//! missing one doesn't compile. Messages with values in them are templates that name each one,
+//! like `{model}`, which [`fmt`] fills in; a translation can put them in whatever order its grammar
+//! wants. The letters typed to answer a question, like `[y/N]`, are the same in every language.
+//!
+//! What `:help <command>` says about each command is in [`help`], a table per language keyed by
+//! the command's name; the tests check each has every command, and that every `en` message keeps
+//! its placeholders in the other languages.
+//!
+//! To add a language, copy `de` and its help table, translate each message and add it to [`Lang`].
+
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+mod help;
+
+pub use help::Help;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    De,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::De];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+        }
+    }
+
+    /// The language for a code like `de`, or a locale like `de_AT.UTF-8`, if gpt has it.
+    pub fn parse(code: &str) -> Option<Lang> {
+        let code = code
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        Lang::ALL.into_iter().find(|lang| lang.code() == code)
+    }
+
+    /// Every language's code, for saying which there are.
+    pub fn codes() -> String {
+        Lang::ALL.map(Lang::code).join(", ")
+    }
+}
+
+// The language messages are shown in, as an index into Lang::ALL. English until set.
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+pub fn set(lang: Lang) {
+    let index = Lang::ALL.iter().position(|l| *l == lang).unwrap_or(0);
+    LANG.store(index as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    Lang::ALL
+        .get(LANG.load(Ordering::Relaxed) as usize)
+        .copied()
+        .unwrap_or(Lang::En)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    // :help
+    CategorySession,
+    CategoryModel,
+    CategoryOutput,
+    CategoryFiles,
+    HelpMore,
+    HelpUsage,
+    HelpExample,
+    NoHelp,
+    NoHelpDidYouMean,
+    UnknownCommand,
+    UnknownCommandDidYouMean,
+
+    // What each command does, as :help lists it.
+    CmdQuit,
+    CmdContext,
+    CmdClear,
+    CmdNew,
+    CmdSeed,
+    CmdRestore,
+    CmdStats,
+    CmdEndpoints,
+    CmdSummary,
+    CmdDebug,
+    CmdDiffRequest,
+    CmdCurl,
+    CmdHelp,
+    CmdShow,
+    CmdQuote,
+    CmdGrep,
+    CmdDraft,
+    CmdSend,
+    CmdSearch,
+    CmdSave,
+    CmdLoad,
+    CmdSessions,
+    CmdProject,
+    CmdTitle,
+    CmdSendOnly,
+    CmdWrapPrompt,
+    CmdNote,
+    CmdBookmark,
+    CmdBookmarks,
+    CmdVar,
+    CmdSubst,
+    CmdAlias,
+    CmdUnalias,
+    CmdTools,
+    CmdModel,
+    CmdWhy,
+    CmdModels,
+    CmdProvider,
+    CmdOrg,
+    CmdMax,
+    CmdBrief,
+    CmdNormal,
+    CmdDetailed,
+    CmdTokens,
+    CmdGet,
+    CmdSet,
+    CmdCache,
+    CmdPaths,
+    CmdTheme,
+    CmdMath,
+    CmdTranslate,
+    CmdDuo,
+    CmdCompare,
+    CmdExpect,
+    CmdFilter,
+    CmdTee,
+    CmdStreamTo,
+    CmdAsk,
+    CmdCmd,
+    CmdFix,
+    CmdFile,
+    CmdCodeContext,
+    CmdAskFile,
+    CmdScript,
+    CmdWatch,
+
+    // The footer under an answer.
+    FooterTokens,
+    FooterCachedTokens,
+    FooterEstimated,
+    FooterFromCache,
+    FooterResumed,
+    FooterRouted,
+    FooterSent,
+    FooterReused,
+    PriceUnknown,
+
+    // Errors.
+    ErrorApi,
+    ErrorIo,
+    ErrorConfig,
+    Cancelled,
+
+    // Around sending a message and streaming its answer.
+    UndefinedVars,
+    ConfirmLong,
+    NotSent,
+    ContextDropped,
+    Trimmed,
+    ExpectFailed,
+    AskingAgain,
+    StreamingTo,
+    AnswerIn,
+    Calling,
+    FilterFailed,
+    Stopped,
+    KeepPartial,
+    StreamDropped,
+    ResumeOrStop,
+    Stalled,
+    Drift,
+    OtherChoice,
+    OtherChoiceRefused,
+
+    // Starting up.
+    LangUnknown,
+    LangUnknownSet,
+    ModelsIgnored,
+    NoApiKey,
+    NoApiKeyWizard,
+    ProjectLoaded,
+    Queued,
+    NotQueued,
+
+    // Shutting down.
+    Autosaved,
+    AutosaveDiverged,
+    AutosaveFailed,
+    BackgroundStopped,
+
+    // What commands say.
+    AliasName,
+    AliasBuiltIn,
+    ContextOn,
+    ContextOff,
+    RoutingOn,
+    UnknownModel,
+    Cleared,
+    NewConversation,
+    Restored,
+    NothingToRestore,
+    MaxTokensSet,
+    BudgetLeft,
+    BudgetSession,
+    BudgetContext,
+    ThemeIs,
+    MathIs,
+    CacheOn,
+    CacheOff,
+    CacheCleared,
+    DebugModel,
+    DebugLimiter,
+    NoRequestYet,
+    CurlOn,
+    CurlOff,
+    CurlDry,
+    NotSentDryRun,
+    StreamToOff,
+    StreamToQuiet,
+    StreamToOn,
+    NothingToCompare,
+    TeeOff,
+    TeeOn,
+    FilterTee,
+    FilterIs,
+    NoFilter,
+    FilterOff,
+    FilterOn,
+    NoWrapper,
+    WrapOn,
+    WrapOff,
+    SendOnlyIs,
+    SendOnlyOff,
+    SendOnlySet,
+    NothingToBookmark,
+    Bookmarked,
+    NoNotes,
+    NoNote,
+    NoteRemoved,
+    Noted,
+    QuoteDropped,
+    QuoteWaiting,
+    SaveHeld,
+    Saved,
+    LoadHeld,
+    SessionExists,
+    SessionHeld,
+    Loaded,
+    LoadedReadOnly,
+    LoadedFork,
+    NoSessions,
+    NoTitle,
+    TitleSet,
+    NoVars,
+    VarUnset,
+    VarRemoved,
+    ModelsRead,
+    ModelIgnored,
+    NoMetadata,
+    PricePerMillion,
+    NoFineTunedPrice,
+    Unknown,
+    CatalogNotLoaded,
+    ColumnModel,
+    ColumnInput,
+    ColumnOutput,
+    ColumnWindow,
+    FineTunedModels,
+    NoAliases,
+    NoAlias,
+    AliasRemoved,
+    PathsConfig,
+    PathsConfigMissing,
+    PathsData,
+    PathsSessions,
+    PathsCache,
+    PathsTools,
+    PathsOrg,
+    PathsOrgDefault,
+    PathsProject,
+    NoTools,
+    NoTool,
+    ToolEnabled,
+    ToolDisabled,
+    ScriptRunning,
+    ExpectIs,
+    NoExpect,
+    ExpectOff,
+    ExpectSet,
+    RoutingOff,
+    NotRouted,
+    InvalidFlag,
+    ContextEmpty,
+    Shortened,
+    Wrapped,
+    ListNote,
+    ListWrapper,
+    Tokens,
+    ShortenedFrom,
+    SentWith,
+    Note,
+    JustNow,
+    MinutesAgo,
+    HoursAgo,
+    DaysAgo,
+    NotAnIndex,
+    Backwards,
+    LockTakenOver,
+    Swapped,
+    TokensWindow,
+    TokensApproximate,
+    TokensTotal,
+    TokensContextDropped,
+    NotInProject,
+    ProjectName,
+    ProjectModel,
+    ProjectSystem,
+    ProjectNoSystem,
+    ProjectFirstFile,
+    ProjectFile,
+    ProjectFileTokens,
+    ProjectFileNotSent,
+    ProjectSessions,
+    StatsTokens,
+    StatsUnpricedOne,
+    StatsUnpriced,
+    StatsPrice,
+    StatsTitles,
+    StatsRouting,
+    StatsRecaps,
+    StatsEndpoints,
+
+    // Drafts, prompt wrappers and the rest of the REPL.
+    WrapperPrefix,
+    WrapperSuffix,
+    WrapperBoth,
+    DraftRestored,
+    Drafting,
+    AlreadyDrafting,
+    DraftDropped,
+    DraftEmpty,
+    NoDraft,
+    NoDraftToSend,
+    DraftEmptyNotSent,
+    OneLine,
+    Lines,
+    ConfigMissing,
+    ProjectIgnored,
+    FilterIgnored,
+    ModelUnknown,
+    NothingQueued,
+    Flushed,
+    DraftNotSaved,
+    ScriptStoppedOne,
+    ScriptStopped,
+    TypedAheadClearedOne,
+    TypedAheadCleared,
+    TypedAheadOne,
+    TypedAhead,
+
+    // Requests queued while offline.
+    QueuedSent,
+    QueuedWaitingOne,
+    QueuedWaiting,
+    QueuedNotSent,
+    QueuedCorrupt,
+    QueuedNoKey,
+    QueuedHeader,
+    QueuedStays,
+    QueuedRejected,
+
+    // Sending a message and streaming the answer.
+    NothingAfterOverrides,
+    SecondAnswerWrong,
+    NoLongerInContext,
+    StreamFromCache,
+    FallingBack,
+    ToolRoundsStopped,
+    StoppedWithEscape,
+    StreamFinished,
+    CacheWriteFailed,
+    MessageNotSendable,
+    MessageHasNoText,
+    NothingArrived,
+    EmptyResponse,
+    ContextDroppedOne,
+
+    // The context :tokens leaves out, for one message.
+    TokensContextDroppedOne,
+
+    // :cmd and gpt cmd.
+    ExecuteNeedsTerminal,
+    NotRun,
+    CommandFailedToRun,
+    CommandExited,
+    CommandKilled,
+    ConfirmDangerous,
+    ConfirmRun,
+    CommandDestructive,
+    CommandCopied,
+    CommandNotCopied,
+    SuggestionNotJson,
+    NoSuggestion,
+    NoClipboardTool,
+
+    // gpt gc.
+    GcNothing,
+    GcFailed,
+    GcOnStart,
+    GcPrunedRequests,
+    GcWouldPruneRequests,
+    GcNoBookmarks,
+    GcNotSaved,
+    GcPastMaxSessions,
+    GcExpired,
+    GcPastCacheSize,
+    GcCutShort,
+    GcRemoved,
+    GcWouldRemove,
+    GcOneRequest,
+    GcRequests,
+    GcAnd,
+    GcRemovedSummary,
+    GcWouldRemoveSummary,
+
+    // :duo.
+    DuoUsage,
+    DuoTurns,
+    DuoTitle,
+    DuoOneTurn,
+    DuoTurnCount,
+    DuoSummary,
+    DuoHeader,
+    DuoNote,
+    DuoStopped,
+    // :compare.
+    CompareUsage,
+    CompareHeader,
+    CompareNotice,
+
+    // What each setting is, as :get lists it.
+    SettingModel,
+    SettingProvider,
+    SettingOrganization,
+    SettingMaxTokens,
+    SettingTemperature,
+    SettingPreset,
+    SettingAutoPipe,
+    SettingCache,
+    SettingFooter,
+    SettingTheme,
+    SettingMath,
+    SettingWordWrap,
+    SettingLang,
+    SettingConversationBudget,
+    SettingDowngradeOnBudget,
+    SettingBudgetScope,
+    SettingWarnOnContextDrop,
+    SettingSubstitution,
+    SettingNotifyAfterSecs,
+    SettingStallAfterSecs,
+    SettingSafeMode,
+    SettingTitleAfterExchanges,
+
+    // Setting values.
+    InvalidMaxTokens,
+    MaxTokensExplicit,
+    MaxTokensModelMax,
+    MaxTokensModelDefault,
+    MaxTokensGlobalDefault,
+    UnknownSetting,
+    InvalidBool,
+    AutoModel,
+    InvalidProvider,
+    InvalidTemperature,
+    InvalidPreset,
+    InvalidFooter,
+    InvalidTheme,
+    InvalidMath,
+    InvalidAmount,
+    InvalidBudgetScope,
+    InvalidSeconds,
+    SafeModeFixed,
+    InvalidNumber,
+
+    // :quote.
+    QuoteNoAnswer,
+    InvalidPattern,
+    QuoteNoMatch,
+    QuoteBadRange,
+    QuoteOneParagraph,
+    QuoteParagraphs,
+    QuoteUsage,
+
+    // Bookmarks.
+    NoSuchMessage,
+    NoSuchBookmark,
+    NoBookmarks,
+    BookmarkNotCopied,
+    BookmarkCopied,
+    BookmarkDeleted,
+
+    // The conversation budget.
+    BudgetOver,
+    BudgetDowngrade,
+    BudgetAsk,
+    BudgetNotSent,
+    BudgetNotSentNoTty,
+
+    // The rate limiter.
+    RateLimitWait,
+    RateLimitAvailable,
+    RateLimitUnlimited,
+    RateLimitBlocked,
+
+    // :summary.
+    SummaryNothing,
+    SummaryExchanges,
+    SummaryOneWithoutPrompt,
+    SummaryWithoutPrompts,
+    SummaryTokens,
+    SummaryCost,
+    SummaryModels,
+    SummaryOneAnswer,
+    SummaryAnswers,
+    SummaryModelLine,
+    SummaryModel,
+    SummaryLatency,
+    SummaryLongest,
+    SummaryDuration,
+    RecapEmpty,
+    RecapHeader,
+    RecapNotice,
+
+    // MCP servers.
+    McpNoCommand,
+    McpAnswered,
+    McpNoPipes,
+    McpBadUrl,
+    McpNoConnection,
+    McpNoEndpoint,
+    McpBadEndpoint,
+    McpExited,
+    UnknownError,
+    McpStartFailed,
+    McpStartTimeout,
+
+    // The usage ledger.
+    UsageNotRecorded,
+    UsageNewer,
+    UsageNone,
+    UsageOneUnpriced,
+    UsageUnpriced,
+    DriftNone,
+    DriftLegend,
+    DriftOff,
+    UsageSince,
+
+    // First-run setup.
+    SetupIntro,
+    SetupProvider,
+    SetupPickNumber,
+    SetupKey,
+    SetupWhereKey,
+    SetupSave,
+    SetupNotSaved,
+    SetupSaved,
+    SetupModels,
+    SetupDefault,
+    SetupModel,
+    SetupPickModel,
+    SetupNeedsTerminal,
+
+    // Schema versions and gpt migrate.
+    FileCorrupt,
+    FileNewer,
+    ReadFailed,
+    MigrateUnparsable,
+    MigrateNewer,
+    MigrateKeptNewer,
+    MigrateKeptUnparsable,
+    MigrateAnd,
+    MigrateKeeping,
+    MigrateNothing,
+    MigrateAtVersion,
+    MigrateWouldUpgrade,
+    MigrateUpgraded,
+    MigrateCheckedNothing,
+    MigrateChecked,
+    MigrateSummary,
+    MigrateOneEntry,
+    MigrateEntries,
+
+    // Sessions.
+    InvalidSessionName,
+    SessionNamed,
+    FileQuarantined,
+    NotASessionFile,
+    NoSuchSession,
+
+    // The file index.
+    IndexAt,
+    IndexCorrupt,
+    CantIndex,
+    IndexEmbedding,
+    Indexed,
+    NoIndex,
+    NoQuestionEmbedding,
+
+    // :watch.
+    CantWatch,
+    Watching,
+    WatchStoppedCount,
+    WatchStoppedBudget,
+    WatchHowToStop,
+    WatchBinary,
+    WatchCantRead,
+
+    // gpt review.
+    ReviewNoChanges,
+    ReviewPart,
+    GitFailedToRun,
+    GitDiffFailed,
+    ReviewNotJson,
+    ReviewNoIssues,
+    ReviewLine,
+
+    // :fix.
+    FixLastCommand,
+    FixRerun,
+    FixNotRun,
+    FixUnknownShell,
+    FixNoHome,
+    FixNoCommands,
+
+    // Providers.
+    ProviderNoKey,
+    EndpointFailed,
+    EndpointFailedDown,
+    ContentFiltered,
+    KeyRejected,
+    NoAnswerIn,
+    HttpClientFailed,
+    FineTunedNotListed,
+    CatalogNotFetched,
+    ModelRefused,
+
+    // Seeds.
+    SeedInvalid,
+    SeedTemperature,
+    SeedFilesList,
+    SeedNotString,
+    SeedMessagesTables,
+    SeedMessageNotTable,
+    SeedMessageRole,
+    SeedMessageContent,
+    SeedUnknownKeys,
+    SeedNoMatch,
+    SeedStarted,
+    WriteFailed,
+    SeedWritten,
+    SeedSystem,
+    SeedNoSystem,
+    SeedOneMessage,
+    SeedMessages,
+    OneFile,
+    Files,
+    SeedOnePath,
+    SeedPaths,
+
+    // Sensitive and binary files.
+    FileSensitive,
+    FileBinary,
+    FileNotUtf8,
+
+    // Substitutions in messages.
+    UnclosedVariable,
+    VariableNotSet,
+    UnclosedCommand,
+    SubstFailedToRun,
+    SubstFailed,
+
+    // :translate.
+    TranslateNothingToSwap,
+    TranslateNothingBack,
+    TranslateUsage,
+    TranslateNoTarget,
+
+    // gpt ask.
+    AskSkippedBinary,
+
+    // Answering about a long pasted document.
+    AskFileTooLong,
+    AskFileInParts,
+    AskFileTotal,
+
+    // The audit log.
+    AuditNotWritten,
+
+    // :codectx.
+    CodeContextSummary,
+    CodeContextWhole,
+    CodeContextUnparsed,
+    OneCrate,
+    Crates,
+    CargoFailedToRun,
+    CargoWorkspaceUnreadable,
+    CargoMetadataUnreadable,
+
+    // gpt doctor.
+    DoctorHeader,
+
+    // --emit-socket.
+    EmitFailing,
+
+    // --emit-socket problems.
+    EmitNoConnection,
+    EmitReaderBehind,
+    EmitNotWritten,
+
+    // :endpoints.
+    EndpointsOne,
+    EndpointsHeader,
+    EndpointDown,
+    EndpointFailing,
+    EndpointUp,
+    EndpointNoRequests,
+    OneRequest,
+    Requests,
+    EndpointLatency,
+    EndpointLastError,
+
+    // The model registry and gpt models validate.
+    TextOnly,
+    LayerBundled,
+    LayerCatalog,
+    LayerUser,
+    ModelsUnreadable,
+    ModelsNeedsPrice,
+    ModelsShortcutNotId,
+    ModelsModelTables,
+    ModelsShortcutsTable,
+    ModelsUnknownKey,
+    ModelsEntryNotTable,
+    ModelsEntryNoId,
+    ModelsFieldProblem,
+    ModelsPositiveTokens,
+    ModelsEntryUnknownKey,
+    ModelsPriceShape,
+    ModelsNegativePrice,
+    ModelsCapabilities,
+    ModelsEncoding,
+    ModelsFine,
+    ModelsLimits,
+    ModelsShortcutUnknown,
+
+    // Desktop notifications.
+    NotifyFinished,
+    NotifyFailed,
+
+    // The model picker.
+    PickerOneRecent,
+    PickerRecent,
+    PickerHowToSwitch,
+    PickerLabel,
+    PickerOneModel,
+    PickerModels,
+    PickerOneRecentFirst,
+    PickerRecentFirst,
+    PickerNoMatch,
+    PickerKeys,
+
+    // :grep.
+    GrepUsage,
+    GrepSkipped,
+    GrepNoMatches,
+
+    // The file index, continued.
+    IndexReferences,
+
+    // Summarised long messages.
+    SummaryEmpty,
+
+    // Session locks.
+    LockedByUs,
+    LockKeepsChanging,
+
+    // Fitting the context window.
+    WindowOverflow,
+    WindowLastTooLong,
+    WindowAskSwitch,
+    WindowAskTrim,
+    WindowSwapped,
+    WindowAborted,
+
+    // Token counts.
+    TokenizerMissing,
+    DriftDescribe,
+    BreakdownSystem,
+    BreakdownFiles,
+    BreakdownSeeded,
+    BreakdownContext,
+    BreakdownWrapper,
+    BreakdownNewMessage,
+    BreakdownOverhead,
+    BreakdownTotal,
+
+    // Variables.
+    InvalidVariableName,
+
+    // Staged files.
+    StageProjectFile,
+    StageUnchanged,
+    StageAskReplace,
+    StageUpdated,
+    StageAddedAgain,
+    StageAdded,
+
+    // :search.
+    SearchNeedsTerminal,
+    SearchNothing,
+    SearchLabel,
+    SearchNoMatch,
+    SearchEditLabel,
+
+    // Tool plugins.
+    PluginNoSchema,
+    PluginsNotAllowed,
+    PluginSchemaTimeout,
+    PluginSchemaFailed,
+
+    // Projects.
+    ProjectBadName,
+
+    // @ overrides.
+    UnknownOverride,
+
+    // gpt view.
+    ViewSkipped,
+    ViewNoSessions,
+    ViewUntitled,
+    ViewTokens,
+    PagerFailed,
+
+    // :reqdiff.
+    ReqdiffMessages,
+    ReqdiffMore,
+
+    // Long messages.
+    LargeKept,
+    LargeAsk,
+    LargeStoredOriginal,
+    LargeStored,
+    LargeSummarizing,
+
+    // Prompt cache reuse.
+    PrefixModelChanged,
+    PrefixToolsChanged,
+    PrefixSystemChanged,
+    PrefixMessageChanged,
+    PrefixLost,
+
+    // Presets.
+    PresetModelDefault,
+    PresetInstruction,
+    PresetNoInstruction,
+    PresetDescribe,
+
+    // The prompt template.
+    PromptStrayClose,
+    PromptUnclosed,
+    PromptUnknownField,
+    PromptUnknownStyle,
+
+    // Routing.
+    RouteLong,
+    RouteCode,
+    RouteShort,
+    RouteDeep,
+    RouteShallow,
+    RouteClassifierFailed,
+    RouteClassifierSlow,
+
+    // Safe mode.
+    SafeShell,
+    SafeSubstitution,
+    SafeFilter,
+    SafeTools,
+    SafeWrite,
+    SafeClipboard,
+    SafeProject,
+    SafeDisabled,
+
+    // Response filters.
+    FilterFailedToRun,
+    FilterTimeout,
+    FilterExited,
+
+    // expect.
+    ExpectBadPattern,
+    ExpectUnknown,
+
+    // :codectx, continued.
+    CodeContextAsk,
+    CodeContextLabel,
+
+    // Command-line arguments.
+    InvalidDate,
+
+    // Paths.
+    DirNotCreated,
+
+    // --emit-socket, elsewhere.
+    #[cfg_attr(unix, allow(dead_code))]
+    EmitUnixOnly,
+
+    // gpt doctor, check by check. The check names are identifiers, as in --json.
+    DoctorNoConfig,
+    DoctorKeyBoth,
+    DoctorKeyEnv,
+    DoctorKeyConfig,
+    DoctorNoKey,
+    DoctorKeyShort,
+    DoctorModel,
+    DoctorUnknownModel,
+    DoctorNoParent,
+    DoctorWritable,
+    DoctorCreatedLater,
+    DoctorNotWritable,
+    DoctorModels,
+    DoctorModelsChanged,
+    DoctorModelsProblems,
+    DoctorNone,
+    DoctorAnswered,
+    DoctorKeyRejected,
+    DoctorListing,
+    DoctorUnreachable,
+    DoctorSkippedModel,
+    DoctorSkippedKey,
+    DoctorTty,
+    DoctorNotTty,
+    DoctorOn,
+    DoctorOff,
+    DoctorTerminal,
+    DoctorColumns,
+
+    // Response filters, continued.
+    FilterTruncated,
+
+    // The tokenizer.
+    TokenizerDisabled,
+}
+
+/// The message in the current language.
+pub fn get(msg: Msg) -> &'static str {
+    text(lang(), msg)
+}
+
+/// What `:help <command>` shows about a command, in the current language, or in English for one
+/// a translation doesn't have yet.
+pub fn help(command: &str) -> &'static Help {
+    help::find(lang(), command)
+        .or_else(|| help::find(Lang::En, command))
+        .unwrap_or(&help::NONE)
+}
+
+/// The message in the current language with `args` filled in, each by the name it has in the
+/// template. A name the template doesn't have is left out, and one it has without a value is
+/// left as written.
+pub fn fmt(msg: Msg, args: &[(&str, &dyn Display)]) -> String {
+    let mut template = get(msg);
+    // A translation that lost a value, e.g. to a typo in its name, would say less than English.
+    let lost = |name: &str| {
+        let name = format!("{{{name}}}");
+        en(msg).contains(&name) && !template.contains(&name)
+    };
+    if args.iter().any(|(name, _)| lost(name)) {
+        template = en(msg);
+    }
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let (_, value) = args.iter().find(|(name, _)| *name == &after[..end])?;
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(&value.to_string());
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn text(lang: Lang, msg: Msg) -> &'static str {
+    match lang {
+        Lang::En => en(msg),
+        Lang::De => de(msg),
+    }
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::CategorySession => "Session",
+        Msg::CategoryModel => "Model",
+        Msg::CategoryOutput => "Output",
+        Msg::CategoryFiles => "Files",
+        Msg::HelpMore => "Use :help <command> for details.",
+        Msg::HelpUsage => "Usage: {usage}",
+        Msg::HelpExample => "Example: {example}",
+        Msg::NoHelp => "No help for '{topic}'. Use :help to see a list of commands.",
+        Msg::NoHelpDidYouMean => "No help for '{topic}'. Did you mean '{name}'?",
+        Msg::UnknownCommand => "Unknown command. Use :help to see a list of commands.",
+        Msg::UnknownCommandDidYouMean => "Unknown command '{command}'. Did you mean ':{name}'?",
+
+        Msg::CmdQuit => "quits the program",
+        Msg::CmdContext => "toggles between keeping context and discarding it between messages",
+        Msg::CmdClear => "clears the current context",
+        Msg::CmdNew => "starts a fresh conversation",
+        Msg::CmdSeed => "starts a conversation from a .gptseed.toml, or writes one",
+        Msg::CmdRestore => "brings back the most recently cleared context",
+        Msg::CmdStats => "shows token usage and cost for this session",
+        Msg::CmdEndpoints => "shows the health of the base URLs requests fail over between",
+        Msg::CmdSummary => "sums up the conversation in the context",
+        Msg::CmdDebug => "shows internal state such as the rate limiter",
+        Msg::CmdDiffRequest => "shows what changed between the last two requests sent",
+        Msg::CmdCurl => "prints the last request as a curl command",
+        Msg::CmdHelp => "lists commands, or shows details of one",
+        Msg::CmdShow => "lists the messages in the current context, or shows one in full",
+        Msg::CmdQuote => "starts the next message with part of the last answer, quoted",
+        Msg::CmdGrep => "searches the current context (and optionally saved sessions)",
+        Msg::CmdDraft => "writes a message over several lines, sent only by :send",
+        Msg::CmdSend => "sends the draft started with :draft",
+        Msg::CmdSearch => "searches this session's prompts and answers as you type",
+        Msg::CmdSave => "saves the current context as a named session",
+        Msg::CmdLoad => "replaces the current context with a saved session",
+        Msg::CmdSessions => "lists saved sessions",
+        Msg::CmdProject => "shows the project settings loaded from .gpt-cli.toml",
+        Msg::CmdTitle => "shows or sets the session's title",
+        Msg::CmdSendOnly => "sends only some messages of the context with the next one",
+        Msg::CmdWrapPrompt => "turns the configured prompt prefix and suffix on or off",
+        Msg::CmdNote => "attaches a private note to a message, or lists the notes",
+        Msg::CmdBookmark => "bookmarks a message, so it can be found again from any session",
+        Msg::CmdBookmarks => {
+            "lists the bookmarks of every session, or shows, copies or deletes one"
+        }
+        Msg::CmdVar => "sets, lists or removes prompt variables",
+        Msg::CmdSubst => "sends a message with ${VAR} and $(command) expanded",
+        Msg::CmdAlias => "defines a shortcut for a command or the start of a message",
+        Msg::CmdUnalias => "removes an alias",
+        Msg::CmdTools => "lists the tools the model can call, or turns one on or off",
+        Msg::CmdModel => "swaps the model used for new messages",
+        Msg::CmdWhy => "explains which model the last message was routed to",
+        Msg::CmdModels => "lists the models with known prices",
+        Msg::CmdProvider => "shows or changes the provider",
+        Msg::CmdOrg => "shows or changes the OpenAI organization requests are billed to",
+        Msg::CmdMax => "sets the most tokens a response may use",
+        Msg::CmdBrief => {
+            "asks for short answers: a low max_tokens and temperature, and at most 3 sentences"
+        }
+        Msg::CmdNormal => {
+            "goes back to the config's max_tokens and temperature, without a length instruction"
+        }
+        Msg::CmdDetailed => {
+            "allows long answers: a high max_tokens, without the brevity instruction"
+        }
+        Msg::CmdTokens => {
+            "estimates the prompt tokens of the next request, or with --detail where they come from"
+        }
+        Msg::CmdGet => "shows the current settings, or one of them",
+        Msg::CmdSet => "changes a setting for this session",
+        Msg::CmdCache => "toggles or clears the response cache",
+        Msg::CmdPaths => "shows where the config, sessions and cache are",
+        Msg::CmdTheme => "shows or changes the colours of the prompt and output",
+        Msg::CmdMath => "shows or changes how LaTeX math in answers is shown",
+        Msg::CmdTranslate => "translates text with a cheap model, leaving the conversation alone",
+        Msg::CmdDuo => "has two models talk to each other, each answering the other",
+        Msg::CmdCompare => "asks several models the same question at once",
+        Msg::CmdExpect => "warns when an answer isn't JSON, code or text matching a pattern",
+        Msg::CmdFilter => "pipes every finished response through a command and shows its output",
+        Msg::CmdTee => "copies every following response into a file",
+        Msg::CmdStreamTo => "writes the next response to a file as it streams",
+        Msg::CmdAsk => "answers a question from the files indexed with gpt index",
+        Msg::CmdCmd => "suggests a shell command for a task, or explains one",
+        Msg::CmdFix => "asks how to fix the shell command that just failed",
+        Msg::CmdFile => "puts a file in the context for the next message",
+        Msg::CmdCodeContext => "puts an outline of the cargo workspace in the context",
+        Msg::CmdAskFile => "answers a question about a file of any size",
+        Msg::CmdScript => "runs each line of a file as if it had been typed",
+        Msg::CmdWatch => "sends a file with a prompt every time it changes",
+
+        Msg::FooterTokens => "Prompt Tokens: {prompt} | Completion Tokens: {completion} | Total Tokens: {total}{estimated} | Price: {price}{cached}{resumed}",
+        Msg::FooterCachedTokens => "{prompt} ({cached} cached)",
+        Msg::FooterEstimated => " (est.)",
+        Msg::FooterFromCache => " (cached)",
+        Msg::FooterResumed => " (resumed)",
+        Msg::FooterRouted => "{model} (auto)",
+        Msg::FooterSent => "sent {sent} of {stored} context messages",
+        Msg::FooterReused => "{same} of {total} tokens same as the last request",
+        Msg::PriceUnknown => "price unknown",
+
+        Msg::ErrorApi => "API error: {error}",
+        Msg::ErrorIo => "IO error: {error}",
+        Msg::ErrorConfig => "Config error: {error}",
+        Msg::Cancelled => "Cancelled.",
+
+        Msg::UndefinedVars => "(undefined variables sent as written: {names})",
+        Msg::ConfirmLong => "This message is {tokens} tokens, over confirm_send_over_tokens ({limit}). Send it? [y/N]",
+        Msg::NotSent => "Not sent.",
+        Msg::ContextDropped => "(dropped {count} messages of context — prefix with | to keep it, or :c to always keep)",
+        Msg::Trimmed => "(trimmed {count} older messages from this request)",
+        Msg::ExpectFailed => "Warning: the answer was expected to be {shape}, but {problem}.",
+        Msg::AskingAgain => "(asking again)",
+        Msg::StreamingTo => "(streaming the answer to {path})",
+        Msg::AnswerIn => "(the answer is in {path})",
+        Msg::Calling => "(calling {tool})",
+        Msg::FilterFailed => "(the filter failed: {error})",
+        Msg::Stopped => "(stopped)",
+        Msg::KeepPartial => "Keep the partial answer in the context? [k]eep or [d]rop",
+        Msg::StreamDropped => "(the stream dropped: {error})",
+        Msg::ResumeOrStop => "[r]esume or [s]top?",
+        Msg::Stalled => "(no data for {secs}s; still waiting, press Escape to give up on it)",
+        Msg::OtherChoice => "(the provider also sent answer {index}, {chars} characters, finish_reason: {reason}; only the first is kept)",
+        Msg::OtherChoiceRefused => "(the provider also sent answer {index}, which refused: {refusal}; only the first is kept)",
+        Msg::Drift => "(the prompt's token estimate for {model} was off: {drift}. If that keeps happening, please file a bug at {url} with the model's name; gpt report --drift shows how it's gone so far)",
+
+        Msg::LangUnknown => "lang = \"{lang}\" isn't a language gpt has, so its messages are in English. It has {langs}.",
+        Msg::LangUnknownSet => "'{lang}' isn't a language gpt has. It has {langs}.",
+        Msg::ModelsIgnored => "Parts of {path} were ignored; run gpt models validate to see why.",
+        Msg::NoApiKey => "There's no API key for {provider}. Either:\n  - set the environment variable {var}\n  - or add {provider} = \"<key>\" to the [api_keys] table of {config}",
+        Msg::NoApiKeyWizard => "  - or run gpt in a terminal to be walked through setting one up",
+        Msg::ProjectLoaded => "(project {name}; :project shows what it sets)",
+        Msg::Queued => "(no connection, so it's queued as request {id}; gpt flush sends it, as does the next gpt with a connection, and the answer goes to {path})",
+        Msg::NotQueued => "Couldn't queue it: {error}",
+
+        Msg::Autosaved => "(saved {count} messages as '{name}')",
+        Msg::AutosaveDiverged => "(not saved over '{name}', which holds an earlier conversation; :save <name> keeps this one)",
+        Msg::AutosaveFailed => "Couldn't save the session '{name}': {error}",
+        Msg::BackgroundStopped => "(stopped {count} background request(s) that were still running)",
+
+        Msg::AliasName => "'{name}' can't be an alias name.",
+        Msg::AliasBuiltIn => "'{name}' is already the built-in command :{command}.",
+        Msg::ContextOn => "Context will now be carried between messages.",
+        Msg::ContextOff => "Context will no longer be carried between messages.\nPrefix messages with '|' to temporarily keep context.",
+        Msg::RoutingOn => "Routing each message to {cheap} or {expensive}. Use :why to see why.",
+        Msg::UnknownModel => "Unknown model. Please try again.\nPossible Options: {options}.",
+        Msg::Cleared => "Cleared {count} messages. Use :restore to bring them back.",
+        Msg::NewConversation => "Started a new conversation.",
+        Msg::Restored => "Restored {count} messages.",
+        Msg::NothingToRestore => "There is no cleared context to restore.",
+        Msg::MaxTokensSet => "max_tokens is now {value}.",
+        Msg::BudgetLeft => "Budget: {left} left of {budget} ({scope})",
+        Msg::BudgetSession => "this session",
+        Msg::BudgetContext => "the answers in the context",
+        Msg::ThemeIs => "The theme is {theme}.",
+        Msg::MathIs => "Math display is {math}.",
+        Msg::CacheOn => "Responses to identical requests will now be cached.",
+        Msg::CacheOff => "Response caching disabled.",
+        Msg::CacheCleared => "Removed {count} cached responses.",
+        Msg::DebugModel => "Model: {model} | Max tokens: {max_tokens}",
+        Msg::DebugLimiter => "Rate limiter:\n{limiter}",
+        Msg::NoRequestYet => "No request has been sent yet.",
+        Msg::CurlOn => "Requests will be printed as curl commands before they're sent.",
+        Msg::CurlOff => "Requests will no longer be printed as curl commands.",
+        Msg::CurlDry => "Requests will be printed as curl commands instead of being sent.",
+        Msg::NotSentDryRun => "Not sent: this is a dry run.",
+        Msg::StreamToOff => "The next response will only be shown.",
+        Msg::StreamToQuiet => "The next response will be written to {path}, and not shown.",
+        Msg::StreamToOn => "The next response will be written to {path} as it streams.",
+        Msg::NothingToCompare => "Nothing to compare until two requests have been sent.",
+        Msg::TeeOff => "Responses will no longer be copied to a file.",
+        Msg::TeeOn => "Responses will be appended to {path}.",
+        Msg::FilterTee => "Responses are shown, then piped through '{command}'.",
+        Msg::FilterIs => "Responses are piped through '{command}'.",
+        Msg::NoFilter => "Responses aren't filtered.",
+        Msg::FilterOff => "Responses will no longer be filtered.",
+        Msg::FilterOn => "Responses will be piped through '{command}'.",
+        Msg::NoWrapper => "No prompt_prefix or prompt_suffix is set in the config.",
+        Msg::WrapOn => "Messages are sent with the {wrapper}.",
+        Msg::WrapOff => "Messages are sent as typed, without the {wrapper}.",
+        Msg::SendOnlyIs => "The next message is sent with messages {indices}.",
+        Msg::SendOnlyOff => "The next message is sent with the whole context.",
+        Msg::SendOnlySet => "The next message is sent with only messages {indices} of the {count}.",
+        Msg::NothingToBookmark => "There's no answer to bookmark yet.",
+        Msg::Bookmarked => "Bookmarked message {index} as {id}.",
+        Msg::NoNotes => "No message has a note.",
+        Msg::NoNote => "Message {index} has no note.",
+        Msg::NoteRemoved => "Removed the note on message {index}.",
+        Msg::Noted => "Noted on message {index}.",
+        Msg::QuoteDropped => "Dropped the quote.",
+        Msg::QuoteWaiting => "(your next message goes after this quote; :quote off drops it)",
+        Msg::SaveHeld => "Session '{name}' is open in another gpt (pid {pid}), so it wasn't saved over. Use :save <new name> to keep this one too.",
+        Msg::Saved => "Saved {count} messages as '{name}'.",
+        Msg::LoadHeld => "Session '{name}' is open in another gpt (pid {pid}). Use :load --read-only {name} to look at it, or :load --fork <new name> {name} to carry on with a copy.",
+        Msg::SessionExists => "There's already a session called '{name}'.",
+        Msg::SessionHeld => "Session '{name}' is open in another gpt (pid {pid}).",
+        Msg::Loaded => "Loaded {count} messages from '{name}'.",
+        Msg::LoadedReadOnly => "Loaded {count} messages from '{name}', read-only. Use :save <new name> to keep changes.",
+        Msg::LoadedFork => "Loaded {count} messages from '{name}' and saved them as '{new}'.",
+        Msg::NoSessions => "No saved sessions.",
+        Msg::NoTitle => "This session doesn't have a title yet.",
+        Msg::TitleSet => "Title set.",
+        Msg::NoVars => "No variables are set.",
+        Msg::VarUnset => "Variable '{name}' isn't set.",
+        Msg::VarRemoved => "Removed {name}.",
+        Msg::ModelsRead => "Read {path} ({count} models known).",
+        Msg::ModelIgnored => "  ignored: {problem}",
+        Msg::NoMetadata => "There's no metadata for '{id}'.",
+        Msg::PricePerMillion => "{input} in, {cached} cached, {output} out per million",
+        Msg::NoFineTunedPrice => "unknown: the base model has no fine_tuned_price",
+        Msg::Unknown => "unknown",
+        Msg::CatalogNotLoaded => "OpenRouter's model list hasn't loaded (yet); showing the built-in models.",
+        Msg::ColumnModel => "model",
+        Msg::ColumnInput => "input",
+        Msg::ColumnOutput => "output",
+        Msg::ColumnWindow => "window",
+        Msg::FineTunedModels => "Fine-tuned models of your organization:",
+        Msg::NoAliases => "No aliases are defined.",
+        Msg::NoAlias => "There's no alias '{name}'.",
+        Msg::AliasRemoved => "Removed :{name}.",
+        Msg::PathsConfig => "Config file: {path}",
+        Msg::PathsConfigMissing => "Config file: {path} (doesn't exist, using defaults)",
+        Msg::PathsData => "Data:        {path}",
+        Msg::PathsSessions => "Sessions:    {path}",
+        Msg::PathsCache => "Cache:       {path}",
+        Msg::PathsTools => "Tools:       {path}",
+        Msg::PathsOrg => "Org:         {org}",
+        Msg::PathsOrgDefault => "(the key's default)",
+        Msg::PathsProject => "Project:     {project}",
+        Msg::NoTools => "No tools. See :help tools for how to add some.",
+        Msg::NoTool => "There's no tool called '{name}'.",
+        Msg::ToolEnabled => "Enabled {name}.",
+        Msg::ToolDisabled => "Disabled {name}.",
+        Msg::ScriptRunning => "A script is already running.",
+        Msg::ExpectIs => "Answers are expected to be {shape}.",
+        Msg::NoExpect => "Answers aren't checked. Use :expect json, code, code:strict or regex:<pattern>.",
+        Msg::ExpectOff => "Answers aren't checked any more.",
+        Msg::ExpectSet => "Answers are expected to be {shape} from now on.",
+        Msg::RoutingOff => "Routing is off. Use :model auto to turn it on.",
+        Msg::NotRouted => "No message has been routed yet.",
+        Msg::InvalidFlag => "'{value}' isn't a valid --{flag}.",
+        Msg::ContextEmpty => "The context is empty.",
+        Msg::Shortened => "shortened",
+        Msg::Wrapped => "wrapped",
+        Msg::ListNote => "note: {note}",
+        Msg::ListWrapper => "(new messages are sent with the {wrapper})",
+        Msg::Tokens => "{count} tokens",
+        Msg::ShortenedFrom => "Shortened; the original is in {path}",
+        Msg::SentWith => "Sent with the {wrapper}",
+        Msg::Note => "Note: {note}",
+        Msg::JustNow => "just now",
+        Msg::MinutesAgo => "{n}m ago",
+        Msg::HoursAgo => "{n}h ago",
+        Msg::DaysAgo => "{n}d ago",
+        Msg::NotAnIndex => "'{arg}' is not a message index. The context has {count} messages.",
+        Msg::Backwards => "'{range}' is backwards; write {to}-{from}.",
+        Msg::LockTakenOver => "(took over the lock on '{name}' from pid {pid}, which isn't running any more)",
+        Msg::Swapped => "Swapped to model {model} ({provider}).",
+        Msg::TokensWindow => ", of {model}'s {window} with {answer} for the answer",
+        Msg::TokensApproximate => " (a rough estimate, without the tokenizer)",
+        Msg::TokensTotal => "About {count} prompt tokens{window}{estimate}.",
+        Msg::TokensContextDropped => "(the {count} messages of context aren't counted, as the message would drop them; start it with | to keep them)",
+        Msg::NotInProject => "Not in a project: there's no {file} in this directory or above it, or gpt was started with --global.",
+        Msg::ProjectName => "Project:  {name} ({path})",
+        Msg::ProjectModel => "Model:    {model}",
+        Msg::ProjectSystem => "System:   {line} (~{tokens} tokens)",
+        Msg::ProjectNoSystem => "System:   (none)",
+        Msg::ProjectFirstFile => "Files:    {path} ({size})",
+        Msg::ProjectFile => "          {path} ({size})",
+        Msg::ProjectFileTokens => "~{tokens} tokens",
+        Msg::ProjectFileNotSent => "not sent: {error}",
+        Msg::ProjectSessions => "Sessions: {path}",
+        Msg::StatsTokens => "Requests: {requests} | Prompt Tokens: {prompt} ({cached} cached) | Completion Tokens: {completion}",
+        Msg::StatsUnpricedOne => " (not counting 1 request to a model whose prices aren't known)",
+        Msg::StatsUnpriced => " (not counting {count} requests to models whose prices aren't known)",
+        Msg::StatsPrice => "Total Price: {cost}{unpriced} | Saved by prompt caching: {saving}",
+        Msg::StatsTitles => "Titles: {count} requests | {cost}",
+        Msg::StatsRouting => "Routing: {count} requests | {cost}",
+        Msg::StatsRecaps => "Recaps: {count} requests | {cost}",
+        Msg::StatsEndpoints => "Requests by endpoint: {endpoints}",
+
+        Msg::WrapperPrefix => "prefix \"{text}\"",
+        Msg::WrapperSuffix => "suffix \"{text}\"",
+        Msg::WrapperBoth => "{prefix} and {suffix}",
+        Msg::DraftRestored => "(restored an unsent draft of {lines}: :draft show prints it, :send sends it and :draft clear drops it)",
+        Msg::Drafting => "Drafting: lines are kept until :send sends them. :draft clear drops them.",
+        Msg::AlreadyDrafting => "Already drafting, {lines} so far. :send sends it, :draft clear drops it.",
+        Msg::DraftDropped => "Dropped the draft ({lines}).",
+        Msg::DraftEmpty => "The draft is empty.",
+        Msg::NoDraft => "There's no draft. Start one with :draft.",
+        Msg::NoDraftToSend => "There's no draft to send. Start one with :draft.",
+        Msg::DraftEmptyNotSent => "The draft is empty. :draft clear stops drafting.",
+        Msg::OneLine => "1 line",
+        Msg::Lines => "{count} lines",
+        Msg::ConfigMissing => "{path} doesn't exist.",
+        Msg::ProjectIgnored => "{path} was ignored.",
+        Msg::FilterIgnored => "response_filter is ignored.",
+        Msg::ModelUnknown => "Unknown model '{name}'.",
+        Msg::NothingQueued => "Nothing is queued.",
+        Msg::Flushed => "({sent} sent, {failed} rejected, {left} still queued)",
+        Msg::DraftNotSaved => "Couldn't save the draft: {error}",
+        Msg::ScriptStoppedOne => "(stopped the script; 1 more line wasn't run)",
+        Msg::ScriptStopped => "(stopped the script; {count} more lines weren't run)",
+        Msg::TypedAheadClearedOne => "(cleared 1 queued message)",
+        Msg::TypedAheadCleared => "(cleared {count} queued messages)",
+        Msg::TypedAheadOne => "(1 message queued)",
+        Msg::TypedAhead => "({count} messages queued)",
+
+        Msg::QueuedSent => "(sent queued request {id}; the answer is in {path})",
+        Msg::QueuedWaitingOne => "(1 queued request is still waiting)",
+        Msg::QueuedWaiting => "({count} queued requests are still waiting)",
+        Msg::QueuedNotSent => "(couldn't send the queued requests: {error})",
+        Msg::QueuedCorrupt => "Queued request {id} is corrupt: {error}",
+        Msg::QueuedNoKey => "Queued request {id} is for {provider}, but there's no key for it.",
+        Msg::QueuedHeader => "(queued request {n} of {total}, {model}, from {queued})",
+        Msg::QueuedStays => "Couldn't send it ({error}); it stays queued.",
+        Msg::QueuedRejected => "Queued request {id} was rejected: {error}",
+
+        Msg::NothingAfterOverrides => "There's no message after the overrides.",
+        Msg::SecondAnswerWrong => "Warning: the second answer wasn't right either: {problem}.",
+        Msg::NoLongerInContext => "Message {index} isn't in the context any more; it has {count} messages.",
+        Msg::StreamFromCache => "{model}, from the cache",
+        Msg::FallingBack => "{model} failed ({error}), falling back to {next}",
+        Msg::ToolRoundsStopped => "Stopped after {rounds} rounds of tool calls.",
+        Msg::StoppedWithEscape => "stopped with Escape",
+        Msg::StreamFinished => "{model}, {prompt} prompt + {completion} completion tokens, {price}",
+        Msg::CacheWriteFailed => "Failed to write to the response cache: {error}",
+        Msg::MessageNotSendable => "Message {index} couldn't be sent: {error}",
+        Msg::MessageHasNoText => "it has no text",
+        Msg::NothingArrived => "nothing arrived for {secs}s",
+        Msg::EmptyResponse => "The model returned an empty response (finish_reason: {reason}).",
+        Msg::ContextDroppedOne => "(dropped 1 message of context — prefix with | to keep it, or :c to always keep)",
+
+        Msg::TokensContextDroppedOne => "(the 1 message of context isn't counted, as the message would drop it; start it with | to keep it)",
+
+        Msg::ExecuteNeedsTerminal => "Not run: --execute needs a terminal to confirm in.",
+        Msg::NotRun => "Not run.",
+        Msg::CommandFailedToRun => "Couldn't run the command: {error}",
+        Msg::CommandExited => "(the command exited with {code})",
+        Msg::CommandKilled => "(the command was killed)",
+        Msg::ConfirmDangerous => "This command was flagged as dangerous. Type yes to run it anyway:",
+        Msg::ConfirmRun => "Run it? [y/N]",
+        Msg::CommandDestructive => "Warning: this command could be destructive.",
+        Msg::CommandCopied => "(copied to the clipboard with {tool})",
+        Msg::CommandNotCopied => "(not copied: {error})",
+        Msg::SuggestionNotJson => "The model's answer wasn't valid JSON: {error}",
+        Msg::NoSuggestion => "The model didn't suggest a command.",
+        Msg::NoClipboardTool => "no clipboard tool found",
+
+        Msg::GcNothing => "(nothing to remove)",
+        Msg::GcFailed => "gc failed: {error}",
+        Msg::GcOnStart => "(gc: {summary}; see the retention settings in the config)",
+        Msg::GcPrunedRequests => "removed {count} of the requests in usage.jsonl, older than {days} days",
+        Msg::GcWouldPruneRequests => "would remove {count} of the requests in usage.jsonl, older than {days} days",
+        Msg::GcNoBookmarks => "Sessions are left alone, since the bookmarks couldn't be read: {error}",
+        Msg::GcNotSaved => "not saved for {days} days",
+        Msg::GcPastMaxSessions => "past max_sessions",
+        Msg::GcExpired => "expired",
+        Msg::GcPastCacheSize => "past cache_max_mb",
+        Msg::GcCutShort => "left by a write that was cut short",
+        Msg::GcRemoved => "removed {path} ({why})",
+        Msg::GcWouldRemove => "would remove {path} ({why})",
+        Msg::GcOneRequest => "1 request from usage.jsonl",
+        Msg::GcRequests => "{count} requests from usage.jsonl",
+        Msg::GcAnd => ", and ",
+        Msg::GcRemovedSummary => "removed {parts}",
+        Msg::GcWouldRemoveSummary => "would remove {parts}",
+
+        Msg::DuoUsage => "Usage: :duo <model a> <model b> [--system-a <prompt>] [--system-b <prompt>] [--turns <n>] [--stop <phrase>] <first message>",
+        Msg::DuoTurns => "--turns needs a number above 0, not '{value}'.",
+        Msg::DuoTitle => "{a} and {b}",
+        Msg::DuoOneTurn => "1 turn",
+        Msg::DuoTurnCount => "{count} turns",
+        Msg::DuoSummary => "({turns}, {cost} across both models; saved as '{name}', see :load or gpt view)",
+        Msg::DuoHeader => "── {side} · {model} · turn {turn} of {turns} ──",
+        Msg::DuoNote => "side {side} of :duo",
+        Msg::DuoStopped => "(stopped: {side} said the stop phrase)",
+        Msg::CompareUsage => "Usage: :compare <model>,<model>[,...] <question>",
+        Msg::CompareHeader => "── {model} ──",
+        Msg::CompareNotice => "({tokens} tokens, {price})",
+
+        Msg::SettingModel => "the model used for new messages",
+        Msg::SettingProvider => "serves models that don't pick their own: openai, openrouter, anthropic or gemini",
+        Msg::SettingOrganization => "the OpenAI organization requests are billed to (default = the key's own)",
+        Msg::SettingMaxTokens => "the most tokens a response may use: a number, auto or max",
+        Msg::SettingTemperature => "how varied answers are, 0 to 2 (default = the model's own)",
+        Msg::SettingPreset => "the length preset, as set by :brief, :normal and :detailed",
+        Msg::SettingAutoPipe => "whether context is carried between messages",
+        Msg::SettingCache => "whether identical requests are answered from the cache",
+        Msg::SettingFooter => "how much the footer shows: off, minimal or full",
+        Msg::SettingTheme => "colours for the prompt and output: dark, light or plain",
+        Msg::SettingMath => "how LaTeX math in answers is shown: off or unicode",
+        Msg::SettingWordWrap => "wrap answers at word boundaries, following the terminal's width",
+        Msg::SettingLang => "the language of gpt's own messages, not the answers: en or de",
+        Msg::SettingConversationBudget => "a soft limit on what a conversation costs, in the display currency (off = none)",
+        Msg::SettingDowngradeOnBudget => "send a message that could go over the budget to a cheaper model instead of asking",
+        Msg::SettingBudgetScope => "what the budget counts: session (until :new) or context (until it's cleared)",
+        Msg::SettingWarnOnContextDrop => "print a notice when a message drops the previous context",
+        Msg::SettingSubstitution => "expand ${VAR} and $(command) in every message",
+        Msg::SettingNotifyAfterSecs => "notify when a response takes this long (off = never)",
+        Msg::SettingStallAfterSecs => "give up on a stream that sends nothing for this long (off = never)",
+        Msg::SettingSafeMode => "whether side effects are disabled; only --safe or the config can set it",
+        Msg::SettingTitleAfterExchanges => "name the session after this many exchanges (0 = never)",
+
+        Msg::InvalidMaxTokens => "'{value}' isn't a valid max_tokens. Use a number, 'auto' or 'max'.",
+        Msg::MaxTokensExplicit => "explicit",
+        Msg::MaxTokensModelMax => "model maximum",
+        Msg::MaxTokensModelDefault => "model default",
+        Msg::MaxTokensGlobalDefault => "global default",
+        Msg::UnknownSetting => "Unknown setting '{name}'. Use :get to see every setting.",
+        Msg::InvalidBool => "'{value}' isn't a valid value. Use true or false.",
+        Msg::AutoModel => "auto (last: {model})",
+        Msg::InvalidProvider => "The provider can be openai, openrouter, anthropic or gemini.",
+        Msg::InvalidTemperature => "'{value}' isn't a temperature from 0 to 2, or default.",
+        Msg::InvalidPreset => "The preset can be brief, normal or detailed.",
+        Msg::InvalidFooter => "The footer can be off, minimal or full.",
+        Msg::InvalidTheme => "The theme can be dark, light or plain.",
+        Msg::InvalidMath => "Math display can be off or unicode.",
+        Msg::InvalidAmount => "'{value}' isn't an amount or off.",
+        Msg::InvalidBudgetScope => "The budget scope can be session or context.",
+        Msg::InvalidSeconds => "'{value}' isn't a number of seconds or off.",
+        Msg::SafeModeFixed => "safe_mode can't be changed from the REPL; start gpt with or without --safe.",
+        Msg::InvalidNumber => "'{value}' isn't a number.",
+
+        Msg::QuoteNoAnswer => "There's no answer to quote yet.",
+        Msg::InvalidPattern => "Invalid pattern: {error}",
+        Msg::QuoteNoMatch => "Nothing in the last answer matches /{pattern}/.",
+        Msg::QuoteBadRange => "The last answer has {lines} lines; {from}-{to} isn't a range of them.",
+        Msg::QuoteOneParagraph => "The last answer has only one paragraph.",
+        Msg::QuoteParagraphs => "The last answer has {count} paragraphs.",
+        Msg::QuoteUsage => "Usage: :quote <paragraph> | <from>-<to> | /regex/ | off",
+
+        Msg::NoSuchMessage => "There's no message {index}; the context has {count}.",
+        Msg::NoSuchBookmark => "There's no bookmark {id}.",
+        Msg::NoBookmarks => "No bookmarks yet. Use :bookmark to add the last answer.",
+        Msg::BookmarkNotCopied => "Couldn't copy bookmark {id}: {error}.",
+        Msg::BookmarkCopied => "Copied bookmark {id} to the clipboard with {tool}.",
+        Msg::BookmarkDeleted => "Deleted bookmark {id} ({summary}).",
+
+        Msg::BudgetOver => "could cost up to {cost}, with {left} of the {budget} conversation budget left",
+        Msg::BudgetDowngrade => "(with {model} that {over}, so it goes to {cheap})",
+        Msg::BudgetAsk => "That {over}. Send it anyway? [y/N]",
+        Msg::BudgetNotSent => "Not sent, as it could go over the conversation budget.",
+        Msg::BudgetNotSentNoTty => "Not sent: it {over}. Raise conversation_budget, or set downgrade_on_budget to send it to a cheaper model.",
+
+        Msg::RateLimitWait => "Rate limit reached, waiting {seconds}s...",
+        Msg::RateLimitAvailable => "{name}/min: {available} of {capacity} available",
+        Msg::RateLimitUnlimited => "{name}/min: unlimited",
+        Msg::RateLimitBlocked => "blocked by the API for {seconds}s",
+
+        Msg::SummaryNothing => "Nothing to summarize yet: the context has no answers.",
+        Msg::SummaryExchanges => "Exchanges: {count}",
+        Msg::SummaryOneWithoutPrompt => " (1 answer didn't record its prompt)",
+        Msg::SummaryWithoutPrompts => " ({count} answers didn't record their prompts)",
+        Msg::SummaryTokens => "Tokens: {prompt} prompt{unknown} | {completion} completion",
+        Msg::SummaryCost => "Cost: {cost}",
+        Msg::SummaryModels => "Models:",
+        Msg::SummaryOneAnswer => "1 answer",
+        Msg::SummaryAnswers => "{count} answers",
+        Msg::SummaryModelLine => "{answers} | {prompt} prompt | {completion} completion | {cost}",
+        Msg::SummaryModel => "Model: {model}",
+        Msg::SummaryLatency => "Latency: {average}s average | {longest}s longest ([{index}])",
+        Msg::SummaryLongest => "Longest message: [{index}] {role}, {tokens} tokens: {preview}",
+        Msg::SummaryDuration => "Duration: {duration} ({first} to {last})",
+        Msg::RecapEmpty => "The recap came back empty.",
+        Msg::RecapHeader => "Recap:",
+        Msg::RecapNotice => "(recap by {model}: {tokens} tokens, {price}; counted under Recaps in :stats)",
+
+        Msg::McpNoCommand => "no command or url given",
+        Msg::McpAnswered => "{server} answered {status}",
+        Msg::McpNoPipes => "couldn't talk to the process",
+        Msg::McpBadUrl => "'{url}' isn't a valid url: {error}",
+        Msg::McpNoConnection => "couldn't connect to {url}: {error}",
+        Msg::McpNoEndpoint => "{url} closed its event stream without naming an endpoint",
+        Msg::McpBadEndpoint => "{url} named an endpoint that isn't a url: {error}",
+        Msg::McpExited => "{server} exited",
+        Msg::UnknownError => "unknown error",
+        Msg::McpStartFailed => "MCP server '{name}' failed to start: {error}",
+        Msg::McpStartTimeout => "MCP server '{name}' didn't start in time.",
+
+        Msg::UsageNotRecorded => "Couldn't record usage in {path}: {error}",
+        Msg::UsageNewer => "Left out {count} of the requests in {path}, recorded by a newer gpt-cli. Upgrade gpt-cli to count them.",
+        Msg::UsageNone => "No usage recorded{since}.",
+        Msg::UsageOneUnpriced => "1 request is left out of the costs; its model's prices weren't known.",
+        Msg::UsageUnpriced => "{count} requests are left out of the costs; their models' prices weren't known.",
+        Msg::DriftNone => "No requests with an estimate to compare recorded{since}.",
+        Msg::DriftLegend => "Drift is how many more prompt tokens were billed than estimated, mean how far off a request is on average, and worst the request furthest off.",
+        Msg::DriftOff => "The estimates for {models} are off by more than {percent}%. Please file a bug at {issues} with the model's name.",
+        Msg::UsageSince => " since {date}",
+
+        Msg::SetupIntro => "No API key or config file found, so let's set one up. Press Ctrl+C to stop.",
+        Msg::SetupProvider => "Provider [1]:",
+        Msg::SetupPickNumber => "Pick a number from the list.",
+        Msg::SetupKey => "{var} (not shown):",
+        Msg::SetupWhereKey => "The key can be saved in {path}, readable only by you,\nor you can set {var} yourself each time.",
+        Msg::SetupSave => "Save it in the config file? [Y/n]",
+        Msg::SetupNotSaved => "Not saved. It's only used until the program exits.",
+        Msg::SetupSaved => "Saved to {path}.",
+        Msg::SetupModels => "Default model (prices per million prompt/completion tokens):",
+        Msg::SetupDefault => " (default)",
+        Msg::SetupModel => "Model:",
+        Msg::SetupPickModel => "Pick a number from the list, or press Enter for the default.",
+        Msg::SetupNeedsTerminal => "Setup needs answers from the terminal.",
+
+        Msg::FileCorrupt => "{what} is corrupt: {error}",
+        Msg::FileNewer => "{what} was written by a newer gpt-cli (schema version {found}, and this one reads up to {current}). Upgrade gpt-cli to read it.",
+        Msg::ReadFailed => "Couldn't read {path}: {error}",
+        Msg::MigrateUnparsable => "left alone, as it couldn't be parsed",
+        Msg::MigrateNewer => "left alone, as it's from a newer gpt-cli",
+        Msg::MigrateKeptNewer => "{entries} from a newer gpt-cli",
+        Msg::MigrateKeptUnparsable => "{entries} that couldn't be parsed",
+        Msg::MigrateAnd => " and ",
+        Msg::MigrateKeeping => " (keeping {entries} as they are)",
+        Msg::MigrateNothing => "nothing to upgrade{kept}",
+        Msg::MigrateAtVersion => "{entries} at v{version}",
+        Msg::MigrateWouldUpgrade => "would upgrade {versions} to v{current}{kept}",
+        Msg::MigrateUpgraded => "upgraded {versions} to v{current}{kept}",
+        Msg::MigrateCheckedNothing => "({current} up to date, {skipped} left alone: nothing to upgrade)",
+        Msg::MigrateChecked => "({upgraded} to upgrade, {current} up to date, {skipped} left alone: run gpt migrate to upgrade them)",
+        Msg::MigrateSummary => "({upgraded} upgraded, {current} up to date, {skipped} left alone)",
+        Msg::MigrateOneEntry => "1 entry",
+        Msg::MigrateEntries => "{count} entries",
+
+        Msg::InvalidSessionName => "Invalid session name '{name}'.",
+        Msg::SessionNamed => "Session '{name}'",
+        Msg::FileQuarantined => "{what} is corrupt ({error}), so it was moved to {path}.",
+        Msg::NotASessionFile => "{path} isn't a session file: {error}",
+        Msg::NoSuchSession => "No saved session called '{name}'.",
+
+        Msg::IndexAt => "The index at {path}",
+        Msg::IndexCorrupt => "The index at {path} is corrupt: {error}. Run gpt index again to rebuild it.",
+        Msg::CantIndex => "Can't index {path}: {error}",
+        Msg::IndexEmbedding => "(embedding chunks {from}-{to} of {total})",
+        Msg::Indexed => "Indexed {files} files under {path}: {changed} embedded ({tokens} tokens), {unchanged} unchanged.",
+        Msg::NoIndex => "There's no index at {path}. Create one with gpt index <dir>.",
+        Msg::NoQuestionEmbedding => "The embeddings API returned nothing for the question.",
+
+        Msg::CantWatch => "Can't watch {path}: {error}",
+        Msg::Watching => "Watching {path}. Type q or press Ctrl+C to stop.",
+        Msg::WatchStoppedCount => "Stopped watching after {count} requests.",
+        Msg::WatchStoppedBudget => "Stopped watching after spending the {budget} budget.",
+        Msg::WatchHowToStop => "Type q to stop watching.",
+        Msg::WatchBinary => "({path} looks like a binary file; not sent)",
+        Msg::WatchCantRead => "(can't read {path}: {error})",
+
+        Msg::ReviewNoChanges => "No changes to review.",
+        Msg::ReviewPart => "(reviewing part {part} of {parts})",
+        Msg::GitFailedToRun => "Couldn't run git: {error}",
+        Msg::GitDiffFailed => "git diff failed: {error}",
+        Msg::ReviewNotJson => "The model's review wasn't valid JSON: {error}",
+        Msg::ReviewNoIssues => "No issues found.",
+        Msg::ReviewLine => " line {line}:",
+
+        Msg::FixLastCommand => "Your last command was: {command}",
+        Msg::FixRerun => "Run it again to see what it prints? It may have side effects. [y/N]",
+        Msg::FixNotRun => "Not run. Use :fix --no-rerun <output> to paste what it printed.",
+        Msg::FixUnknownShell => "Don't know where {shell} keeps its history. Set shell_history in the config, or use :fix --no-rerun <output>.",
+        Msg::FixNoHome => "There's no home directory to find the history in.",
+        Msg::FixNoCommands => "There are no commands in {path} yet. bash only writes it when the shell exits, unless PROMPT_COMMAND runs history -a.",
+
+        Msg::ProviderNoKey => "Set the environment variable '{var}', or {provider} in the [api_keys] table of the config, to use {model}.",
+        Msg::EndpointFailed => "({base} failed: {error}; trying {next})",
+        Msg::EndpointFailedDown => "({base} failed: {error}, so it's passed over for a while; trying {next})",
+        Msg::ContentFiltered => "The response was blocked by the provider's safety filters.",
+        Msg::KeyRejected => "API key rejected by {provider} ({status}) — check {var} or {provider} in the [api_keys] table of the config.",
+        Msg::NoAnswerIn => "no answer in {seconds}s",
+        Msg::HttpClientFailed => "Couldn't set up the HTTP client: {error}",
+        Msg::FineTunedNotListed => "Couldn't list the fine-tuned models: {error}",
+        Msg::CatalogNotFetched => "Couldn't fetch the model list from {url}: {error}",
+        Msg::ModelRefused => "The model refused: {refusal}",
+
+        Msg::SeedInvalid => "{path} isn't a valid seed:\n  {problems}",
+        Msg::SeedTemperature => "temperature should be a number from 0 to 2",
+        Msg::SeedFilesList => "files should be a list of paths or globs",
+        Msg::SeedNotString => "{key} should be a string",
+        Msg::SeedMessagesTables => "messages should be written as [[messages]]",
+        Msg::SeedMessageNotTable => "message {n} isn't a table",
+        Msg::SeedMessageRole => "message {n}: role should be \"user\" or \"assistant\"",
+        Msg::SeedMessageContent => "message {n}: content should be a string",
+        Msg::SeedUnknownKeys => "(ignoring what {path} has that gpt doesn't know: {keys})",
+        Msg::SeedNoMatch => "{path}: '{glob}' doesn't match any files in {dir}.",
+        Msg::SeedStarted => "Started a new conversation from {path}: {model}, {system}, {messages} and {files} staged.",
+        Msg::WriteFailed => "Couldn't write {path}: {error}",
+        Msg::SeedWritten => "Wrote the setup to {path}: {system}, {messages} and {files}. The conversation isn't in it.",
+        Msg::SeedSystem => "a system prompt",
+        Msg::SeedNoSystem => "no system prompt",
+        Msg::SeedOneMessage => "1 message",
+        Msg::SeedMessages => "{count} messages",
+        Msg::OneFile => "1 file",
+        Msg::Files => "{count} files",
+        Msg::SeedOnePath => "1 file path",
+        Msg::SeedPaths => "{count} file paths",
+
+        Msg::FileSensitive => "{path} looks like it holds secrets (it matches {pattern}), so it wasn't sent. Put ! in front of the path, or use --force with gpt ask, to send it anyway.",
+        Msg::FileBinary => "{path} looks like a binary file, so it wasn't sent.",
+        Msg::FileNotUtf8 => "{path} isn't UTF-8 text.",
+
+        Msg::UnclosedVariable => "Unclosed '${' in the message.",
+        Msg::VariableNotSet => "Environment variable '{name}' isn't set.",
+        Msg::UnclosedCommand => "Unclosed '$(' in the message.",
+        Msg::SubstFailedToRun => "Couldn't run '{command}': {error}",
+        Msg::SubstFailed => "'{command}' failed ({status}): {error}",
+
+        Msg::TranslateNothingToSwap => "There's nothing to swap yet. Translate something first.",
+        Msg::TranslateNothingBack => "There's no translation to translate back yet.",
+        Msg::TranslateUsage => "Usage: :translate [--swap] [lang|src:lang] <text>",
+        Msg::TranslateNoTarget => "Say which language to translate into the first time, e.g. :tr de good morning.",
+
+        Msg::AskSkippedBinary => "Skipped {path}: it looks like a binary file.",
+
+        Msg::AskFileTooLong => "This message is ~{tokens} tokens, more than {model}'s {window} window.",
+        Msg::AskFileInParts => "[u]pload it in {parts} parts and ask \"{question}\" of each ({requests} requests), or [c]ontinue?",
+        Msg::AskFileTotal => "(all {requests} requests: {tokens} tokens, {cost})",
+
+        Msg::AuditNotWritten => "Couldn't write to the audit log {path}: {error}",
+
+        Msg::CodeContextSummary => "Code context for {root}: {crates} in {files}, {shown} of {total} public items, {tokens} tokens of a {budget} budget",
+        Msg::CodeContextWhole => ", and {files} whole for {tokens} more ({all} in all)",
+        Msg::CodeContextUnparsed => "Left out {files} that couldn't be parsed.",
+        Msg::OneCrate => "1 crate",
+        Msg::Crates => "{count} crates",
+        Msg::CargoFailedToRun => "Couldn't run cargo: {error}",
+        Msg::CargoWorkspaceUnreadable => "Couldn't read the cargo workspace at {path}: {error}",
+        Msg::CargoMetadataUnreadable => "Couldn't read cargo metadata: {error}",
+
+        Msg::DoctorHeader => "gpt {version} on {os}",
+
+        Msg::EmitFailing => "(--emit-socket: {problem}; events are dropped until it works again)",
+
+        Msg::EmitNoConnection => "couldn't connect to {path}: {error}",
+        Msg::EmitReaderBehind => "the reader isn't keeping up",
+        Msg::EmitNotWritten => "couldn't write to {path}: {error}",
+
+        Msg::EndpointsOne => "Requests go to {base} only. List more base URLs in api_bases in the config to fail over between them.",
+        Msg::EndpointsHeader => "Endpoints, tried in this order while they're up:",
+        Msg::EndpointDown => "down for {seconds}s more",
+        Msg::EndpointFailing => "up, {requests} failed in a row",
+        Msg::EndpointUp => "up",
+        Msg::EndpointNoRequests => "no requests yet",
+        Msg::OneRequest => "1 request",
+        Msg::Requests => "{count} requests",
+        Msg::EndpointLatency => ", {seconds}s to answer on average",
+        Msg::EndpointLastError => "last error: {error}",
+
+        Msg::TextOnly => "text only",
+        Msg::LayerBundled => "built in",
+        Msg::LayerCatalog => "provider catalog",
+        Msg::LayerUser => "your models.toml",
+        Msg::ModelsUnreadable => "couldn't read {path}: {error}",
+        Msg::ModelsNeedsPrice => "model '{id}' isn't known yet, so it needs a price and a context_window",
+        Msg::ModelsShortcutNotId => "shortcut '{short}' isn't a model id",
+        Msg::ModelsModelTables => "model should be written as [[model]]",
+        Msg::ModelsShortcutsTable => "shortcuts should be a table",
+        Msg::ModelsUnknownKey => "unknown key '{key}'",
+        Msg::ModelsEntryNotTable => "model {n} isn't a table",
+        Msg::ModelsEntryNoId => "model {n} has no id",
+        Msg::ModelsFieldProblem => "model '{id}': {key} {problem}",
+        Msg::ModelsPositiveTokens => "should be a positive number of tokens",
+        Msg::ModelsEntryUnknownKey => "model '{id}': unknown key '{key}'",
+        Msg::ModelsPriceShape => "should be [input, output] or [input, cached input, output] in USD per million tokens",
+        Msg::ModelsNegativePrice => "can't be negative",
+        Msg::ModelsCapabilities => "should be a list of some of {names}",
+        Msg::ModelsEncoding => "should be one of {names}",
+        Msg::ModelsFine => "{path} is fine.",
+        Msg::ModelsLimits => "'{id}': default_output ≤ max_output ≤ context_window doesn't hold",
+        Msg::ModelsShortcutUnknown => "shortcut '{short}' is for '{id}', which isn't known",
+
+        Msg::NotifyFinished => "{model} finished",
+        Msg::NotifyFailed => "Couldn't show a desktop notification: {error}",
+
+        Msg::PickerOneRecent => "The first is the one used last. ",
+        Msg::PickerRecent => "The first {count} are the ones used last. ",
+        Msg::PickerHowToSwitch => "* is the current model. Use :model <model> to switch.",
+        Msg::PickerLabel => "(model) ",
+        Msg::PickerOneModel => "  1 model",
+        Msg::PickerModels => "  {count} models",
+        Msg::PickerOneRecentFirst => ", the one used last first",
+        Msg::PickerRecentFirst => ", the {count} used last first",
+        Msg::PickerNoMatch => "(no model matches)",
+        Msg::PickerKeys => "Up and Down move, Enter switches, Esc cancels. * is the current model.",
+
+        Msg::GrepUsage => "Usage: :grep [-s] [--all] <pattern>",
+        Msg::GrepSkipped => "Skipping session '{name}': {error}",
+        Msg::GrepNoMatches => "No matches.",
+
+        Msg::IndexReferences => "References:",
+
+        Msg::SummaryEmpty => "the summary came back empty",
+
+        Msg::LockedByUs => "Session '{name}' is already locked by this gpt.",
+        Msg::LockKeepsChanging => "Couldn't lock session '{name}': {path} keeps changing.",
+
+        Msg::WindowOverflow => "prompt (~{prompt} tok) + max_tokens ({max_tokens}) exceeds {model}'s {window} window",
+        Msg::WindowLastTooLong => "The last message alone doesn't fit in {model}'s window.",
+        Msg::WindowAskSwitch => "[s]witch to {model}, [t]rim older messages for this request, or [a]bort?",
+        Msg::WindowAskTrim => "[t]rim older messages for this request, or [a]bort?",
+        Msg::WindowSwapped => "Swapped to model {model}.",
+        Msg::WindowAborted => "Request aborted.",
+
+        Msg::TokenizerMissing => "Warning: couldn't load the tokenizer ({error}); token counts are rough estimates.",
+        Msg::DriftDescribe => "est {estimate} / actual {actual}, {percent}%",
+        Msg::BreakdownSystem => "System prompt",
+        Msg::BreakdownFiles => "Files",
+        Msg::BreakdownSeeded => "Seed messages",
+        Msg::BreakdownContext => "Context",
+        Msg::BreakdownWrapper => "Prefix and suffix",
+        Msg::BreakdownNewMessage => "New message",
+        Msg::BreakdownOverhead => "Per-message overhead",
+        Msg::BreakdownTotal => "Total",
+
+        Msg::InvalidVariableName => "Invalid variable name '{name}'. Use letters, digits, '_' and '-', starting with a letter.",
+
+        Msg::StageProjectFile => "{path} is one of the project's files, which are sent with every request as they are then, so it's never out of date.",
+        Msg::StageUnchanged => "{path} is already in the context, unchanged.",
+        Msg::StageAskReplace => "An older copy of {path} is in the context. [r]eplace it, [k]eep both or [c]ancel?",
+        Msg::StageUpdated => "(updated {path} in context: was {old} tokens, now {tokens})",
+        Msg::StageAddedAgain => "(added {path} to the context again, {tokens} tokens, keeping the older copy)",
+        Msg::StageAdded => "(added {path} to the context, {tokens} tokens)",
+
+        Msg::SearchNeedsTerminal => ":search needs a terminal. Use :grep instead.",
+        Msg::SearchNothing => "There's nothing to search yet.",
+        Msg::SearchLabel => "(search) ",
+        Msg::SearchNoMatch => "(no match) ",
+        Msg::SearchEditLabel => "(Enter sends, Esc cancels) ",
+
+        Msg::PluginNoSchema => "Tool '{name}' has no usable schema: {error}",
+        Msg::PluginsNotAllowed => "Not loading tools missing from allow_tools: {tools}",
+        Msg::PluginSchemaTimeout => "--schema didn't finish in time",
+        Msg::PluginSchemaFailed => "--schema exited with {status}",
+
+        Msg::ProjectBadName => "{path}: '{name}' can't be used as a project name.",
+
+        Msg::UnknownOverride => "Unknown override '{written}'. Use a model shortcut like @4o, a preset like @brief, or @<setting>=<value> with one of: {settings}. Start the message with \\@ to send the @ as it is.",
+
+        Msg::ViewSkipped => "{count} of the messages in {path} couldn't be read and are left out.",
+        Msg::ViewNoSessions => "No saved sessions in {path}.",
+        Msg::ViewUntitled => "Untitled session",
+        Msg::ViewTokens => "{tokens} tokens",
+        Msg::PagerFailed => "{pager} failed: {error}",
+
+        Msg::ReqdiffMessages => "messages: {old} → {new}",
+        Msg::ReqdiffMore => "    … {count} more lines",
+
+        Msg::LargeKept => "(the message was kept as it is: {error})",
+        Msg::LargeAsk => "That message was ~{tokens} tokens, which every later request in this conversation sends again.\n[k]eep it, [t]runcate it to its start and end, or [s]ummarize it?",
+        Msg::LargeStoredOriginal => "(stored as ~{tokens} tokens; the original is in {path})",
+        Msg::LargeStored => "(stored as ~{tokens} tokens)",
+        Msg::LargeSummarizing => "(summarizing with {model})",
+
+        Msg::PrefixModelChanged => "the model changed",
+        Msg::PrefixToolsChanged => "the tools changed",
+        Msg::PrefixSystemChanged => "the system prompt changed",
+        Msg::PrefixMessageChanged => "message {index} of the request changed",
+        Msg::PrefixLost => "({what}, so only the first {same} of the last request's {previous} tokens can come from the prompt cache)",
+
+        Msg::PresetModelDefault => "the model's default",
+        Msg::PresetInstruction => "and \"{text}\" added to the system prompt",
+        Msg::PresetNoInstruction => "and no length instruction",
+        Msg::PresetDescribe => "Preset {name}: max_tokens {max_tokens}, temperature {temperature}, {instruction}.",
+
+        Msg::PromptStrayClose => "the prompt has a '{close}' without a '{open}'; write '{close}{close}' for a literal one",
+        Msg::PromptUnclosed => "the prompt has a '{open}' without a '{close}'",
+        Msg::PromptUnknownField => "the prompt has an unknown placeholder {name}; use one of {known}",
+        Msg::PromptUnknownStyle => "the prompt has an unknown style '{style}'; use one of {known}",
+
+        Msg::RouteLong => "the prompt is ~{tokens} tokens, over long_prompt_tokens ({limit})",
+        Msg::RouteCode => "the message contains a code block",
+        Msg::RouteShort => "the prompt is short (~{tokens} tokens) and has no code",
+        Msg::RouteDeep => "{model} said it needs deep reasoning",
+        Msg::RouteShallow => "{model} said it doesn't need deep reasoning",
+        Msg::RouteClassifierFailed => "the classifier failed ({error})",
+        Msg::RouteClassifierSlow => "the classifier took too long",
+
+        Msg::SafeShell => "Running shell commands",
+        Msg::SafeSubstitution => "Running $(command) in messages",
+        Msg::SafeFilter => "Piping responses through a filter",
+        Msg::SafeTools => "Calling tools",
+        Msg::SafeWrite => "Writing files outside the data directory",
+        Msg::SafeClipboard => "Copying to the clipboard",
+        Msg::SafeProject => "Loading .gpt-cli.toml",
+        Msg::SafeDisabled => "{feature} is disabled in safe mode",
+
+        Msg::FilterFailedToRun => "couldn't run '{command}': {error}",
+        Msg::FilterTimeout => "'{command}' didn't finish within {seconds}s",
+        Msg::FilterExited => "'{command}' exited with {status}",
+
+        Msg::ExpectBadPattern => "'{pattern}' isn't a valid pattern: {error}",
+        Msg::ExpectUnknown => "'{spec}' isn't something to expect. Use json, code, code:strict or regex:<pattern>.",
+
+        Msg::CodeContextAsk => "Stage it? [y]es, [n]o or [v]iew it first",
+        Msg::CodeContextLabel => "code context for {root}",
+
+        Msg::InvalidDate => "'{value}' isn't a date like 2024-06-01",
+
+        Msg::DirNotCreated => "couldn't create {path}: {error}",
+
+        Msg::EmitUnixOnly => "sockets and named pipes are only supported on Unix",
+
+        Msg::DoctorNoConfig => "{path} (doesn't exist, so the defaults are used)",
+        Msg::DoctorKeyBoth => "{key} from {var}, which wins over the one in [api_keys]",
+        Msg::DoctorKeyEnv => "{key} from {var}",
+        Msg::DoctorKeyConfig => "{key} from {provider} in [api_keys]",
+        Msg::DoctorNoKey => "none for {provider}: set {var} or {provider} in [api_keys]",
+        Msg::DoctorKeyShort => "… ({count} characters)",
+        Msg::DoctorModel => "{model}, served by {provider}",
+        Msg::DoctorUnknownModel => "'{name}' isn't a known model",
+        Msg::DoctorNoParent => "{path} (nothing above it exists)",
+        Msg::DoctorWritable => "{path} (writable)",
+        Msg::DoctorCreatedLater => "{path} (will be created when needed)",
+        Msg::DoctorNotWritable => "{path} (can't write to {existing}: {error})",
+        Msg::DoctorModels => "{count} models",
+        Msg::DoctorModelsChanged => ", {count} set by {path}",
+        Msg::DoctorModelsProblems => ", {count} problems ignored (see gpt models validate)",
+        Msg::DoctorNone => "none",
+        Msg::DoctorAnswered => "{what} answered in {took}",
+        Msg::DoctorKeyRejected => "{base} rejected the key ({code}) in {took}",
+        Msg::DoctorListing => "{base} answered {code} to listing models, in {took}",
+        Msg::DoctorUnreachable => "{base} unreachable: {error}",
+        Msg::DoctorSkippedModel => "skipped: the model isn't known",
+        Msg::DoctorSkippedKey => "skipped: there's no API key",
+        Msg::DoctorTty => "tty",
+        Msg::DoctorNotTty => "not a tty",
+        Msg::DoctorOn => "on",
+        Msg::DoctorOff => "off",
+        Msg::DoctorTerminal => "stdin {stdin}, stdout {stdout}, stderr {stderr}, color {color}",
+        Msg::DoctorColumns => ", {width} columns",
+
+        Msg::FilterTruncated => "[output truncated]",
+
+        Msg::TokenizerDisabled => "GPT_CLI_NO_TOKENIZER is set",
+    }
+}
+
+fn de(msg: Msg) -> &'static str {
+    match msg {
+        Msg::CategorySession => "Sitzung",
+        Msg::CategoryModel => "Modell",
+        Msg::CategoryOutput => "Ausgabe",
+        Msg::CategoryFiles => "Dateien",
+        Msg::HelpMore => "Einzelheiten zeigt :help <Befehl>.",
+        Msg::HelpUsage => "Aufruf: {usage}",
+        Msg::HelpExample => "Beispiel: {example}",
+        Msg::NoHelp => "Keine Hilfe zu '{topic}'. :help listet alle Befehle auf.",
+        Msg::NoHelpDidYouMean => "Keine Hilfe zu '{topic}'. Meinten Sie '{name}'?",
+        Msg::UnknownCommand => "Unbekannter Befehl. :help listet alle Befehle auf.",
+        Msg::UnknownCommandDidYouMean => "Unbekannter Befehl '{command}'. Meinten Sie ':{name}'?",
+
+        Msg::CmdQuit => "beendet das Programm",
+        Msg::CmdContext => {
+            "schaltet um, ob der Kontext zwischen Nachrichten behalten oder verworfen wird"
+        }
+        Msg::CmdClear => "leert den aktuellen Kontext",
+        Msg::CmdNew => "beginnt ein neues Gespräch",
+        Msg::CmdSeed => "beginnt ein Gespräch aus einer .gptseed.toml oder schreibt eine",
+        Msg::CmdRestore => "holt den zuletzt geleerten Kontext zurück",
+        Msg::CmdStats => "zeigt Tokenverbrauch und Kosten dieser Sitzung",
+        Msg::CmdEndpoints => {
+            "zeigt den Zustand der Basis-URLs, auf die Anfragen nacheinander ausweichen"
+        }
+        Msg::CmdSummary => "fasst das Gespräch im Kontext zusammen",
+        Msg::CmdDebug => "zeigt internen Zustand wie den der Ratenbegrenzung",
+        Msg::CmdDiffRequest => "zeigt, was sich zwischen den letzten beiden Anfragen geändert hat",
+        Msg::CmdCurl => "gibt die letzte Anfrage als curl-Befehl aus",
+        Msg::CmdHelp => "listet die Befehle auf oder zeigt Einzelheiten zu einem",
+        Msg::CmdShow => {
+            "listet die Nachrichten im aktuellen Kontext auf oder zeigt eine vollständig"
+        }
+        Msg::CmdQuote => "beginnt die nächste Nachricht mit einem Zitat aus der letzten Antwort",
+        Msg::CmdGrep => "durchsucht den aktuellen Kontext (und auf Wunsch gespeicherte Sitzungen)",
+        Msg::CmdDraft => "schreibt eine mehrzeilige Nachricht, die erst :send abschickt",
+        Msg::CmdSend => "sendet den mit :draft begonnenen Entwurf",
+        Msg::CmdSearch => "durchsucht beim Tippen die Prompts und Antworten dieser Sitzung",
+        Msg::CmdSave => "speichert den aktuellen Kontext als benannte Sitzung",
+        Msg::CmdLoad => "ersetzt den aktuellen Kontext durch eine gespeicherte Sitzung",
+        Msg::CmdSessions => "listet die gespeicherten Sitzungen auf",
+        Msg::CmdProject => "zeigt die aus .gpt-cli.toml geladenen Projekteinstellungen",
+        Msg::CmdTitle => "zeigt oder setzt den Titel der Sitzung",
+        Msg::CmdSendOnly => "sendet mit der nächsten Nachricht nur einige Nachrichten des Kontexts",
+        Msg::CmdWrapPrompt => "schaltet das konfigurierte Präfix und Suffix für Prompts ein oder aus",
+        Msg::CmdNote => "hängt eine private Notiz an eine Nachricht oder listet die Notizen auf",
+        Msg::CmdBookmark => {
+            "setzt ein Lesezeichen auf eine Nachricht, das sich aus jeder Sitzung wiederfinden lässt"
+        }
+        Msg::CmdBookmarks => {
+            "listet die Lesezeichen aller Sitzungen auf oder zeigt, kopiert oder löscht eines"
+        }
+        Msg::CmdVar => "setzt, listet oder entfernt Prompt-Variablen",
+        Msg::CmdSubst => "sendet eine Nachricht, in der ${VAR} und $(command) ersetzt sind",
+        Msg::CmdAlias => "legt ein Kürzel für einen Befehl oder einen Nachrichtenanfang an",
+        Msg::CmdUnalias => "entfernt ein Kürzel",
+        Msg::CmdTools => {
+            "listet die Werkzeuge auf, die das Modell aufrufen kann, oder schaltet eines ein oder aus"
+        }
+        Msg::CmdModel => "wechselt das Modell für neue Nachrichten",
+        Msg::CmdWhy => "erklärt, an welches Modell die letzte Nachricht geleitet wurde",
+        Msg::CmdModels => "listet die Modelle mit bekannten Preisen auf",
+        Msg::CmdProvider => "zeigt oder wechselt den Anbieter",
+        Msg::CmdOrg => "zeigt oder wechselt die OpenAI-Organisation, der Anfragen berechnet werden",
+        Msg::CmdMax => "legt fest, wie viele Tokens eine Antwort höchstens verwenden darf",
+        Msg::CmdBrief => {
+            "bittet um kurze Antworten: niedrige max_tokens und temperature, höchstens 3 Sätze"
+        }
+        Msg::CmdNormal => {
+            "kehrt zu max_tokens und temperature aus der Konfiguration zurück, ohne Längenvorgabe"
+        }
+        Msg::CmdDetailed => "erlaubt lange Antworten: hohe max_tokens, ohne die Vorgabe zur Kürze",
+        Msg::CmdTokens => {
+            "schätzt die Prompt-Tokens der nächsten Anfrage, mit --detail auch, woher sie stammen"
+        }
+        Msg::CmdGet => "zeigt die aktuellen Einstellungen oder eine davon",
+        Msg::CmdSet => "ändert eine Einstellung für diese Sitzung",
+        Msg::CmdCache => "schaltet den Antwort-Cache ein oder aus oder leert ihn",
+        Msg::CmdPaths => "zeigt, wo Konfiguration, Sitzungen und Cache liegen",
+        Msg::CmdTheme => "zeigt oder ändert die Farben von Prompt und Ausgabe",
+        Msg::CmdMath => "zeigt oder ändert, wie LaTeX-Formeln in Antworten dargestellt werden",
+        Msg::CmdTranslate => {
+            "übersetzt Text mit einem günstigen Modell, ohne das Gespräch zu verändern"
+        }
+        Msg::CmdDuo => "lässt zwei Modelle miteinander sprechen, jedes antwortet dem anderen",
+        Msg::CmdCompare => "stellt mehreren Modellen gleichzeitig dieselbe Frage",
+        Msg::CmdExpect => {
+            "warnt, wenn eine Antwort nicht JSON, Code oder zu einem Muster passender Text ist"
+        }
+        Msg::CmdFilter => "leitet jede fertige Antwort durch einen Befehl und zeigt dessen Ausgabe",
+        Msg::CmdTee => "kopiert jede folgende Antwort in eine Datei",
+        Msg::CmdStreamTo => "schreibt die nächste Antwort schon während des Streamens in eine Datei",
+        Msg::CmdAsk => "beantwortet eine Frage aus den mit gpt index indizierten Dateien",
+        Msg::CmdCmd => "schlägt einen Shell-Befehl für eine Aufgabe vor oder erklärt einen",
+        Msg::CmdFix => "fragt, wie sich der eben fehlgeschlagene Shell-Befehl beheben lässt",
+        Msg::CmdFile => "legt eine Datei für die nächste Nachricht in den Kontext",
+        Msg::CmdCodeContext => "legt eine Übersicht des Cargo-Workspace in den Kontext",
+        Msg::CmdAskFile => "beantwortet eine Frage zu einer Datei beliebiger Größe",
+        Msg::CmdScript => "führt jede Zeile einer Datei aus, als wäre sie getippt worden",
+        Msg::CmdWatch => "sendet eine Datei mit einem Prompt, sobald sie sich ändert",
+
+        Msg::FooterTokens => "Prompt-Tokens: {prompt} | Antwort-Tokens: {completion} | Tokens gesamt: {total}{estimated} | Preis: {price}{cached}{resumed}",
+        Msg::FooterCachedTokens => "{prompt} ({cached} aus dem Cache)",
+        Msg::FooterEstimated => " (geschätzt)",
+        Msg::FooterFromCache => " (aus dem Cache)",
+        Msg::FooterResumed => " (fortgesetzt)",
+        Msg::FooterRouted => "{model} (auto)",
+        Msg::FooterSent => "{sent} von {stored} Nachrichten des Kontexts gesendet",
+        Msg::FooterReused => "{same} von {total} Tokens wie bei der letzten Anfrage",
+        Msg::PriceUnknown => "Preis unbekannt",
+
+        Msg::ErrorApi => "API-Fehler: {error}",
+        Msg::ErrorIo => "E/A-Fehler: {error}",
+        Msg::ErrorConfig => "Fehler in der Konfiguration: {error}",
+        Msg::Cancelled => "Abgebrochen.",
+
+        Msg::UndefinedVars => "(undefinierte Variablen wurden unverändert gesendet: {names})",
+        Msg::ConfirmLong => "Diese Nachricht hat {tokens} Tokens, mehr als confirm_send_over_tokens ({limit}). Senden? [y/N]",
+        Msg::NotSent => "Nicht gesendet.",
+        Msg::ContextDropped => "({count} Nachrichten Kontext verworfen – mit | davor bleibt er erhalten, mit :c immer)",
+        Msg::Trimmed => "({count} ältere Nachrichten aus dieser Anfrage gekürzt)",
+        Msg::ExpectFailed => "Warnung: Als Antwort wurde {shape} erwartet, aber {problem}.",
+        Msg::AskingAgain => "(erneute Anfrage)",
+        Msg::StreamingTo => "(die Antwort wird nach {path} gestreamt)",
+        Msg::AnswerIn => "(die Antwort steht in {path})",
+        Msg::Calling => "(rufe {tool} auf)",
+        Msg::FilterFailed => "(der Filter ist fehlgeschlagen: {error})",
+        Msg::Stopped => "(angehalten)",
+        Msg::KeepPartial => "Die Teilantwort im Kontext behalten? [k]eep (behalten) oder [d]rop (verwerfen)",
+        Msg::StreamDropped => "(der Stream ist abgebrochen: {error})",
+        Msg::ResumeOrStop => "[r]esume (fortsetzen) oder [s]top (anhalten)?",
+        Msg::Stalled => "(seit {secs} s keine Daten; es wird weiter gewartet, Escape gibt auf)",
+        Msg::OtherChoice => "(der Anbieter hat auch Antwort {index} geschickt, {chars} Zeichen, finish_reason: {reason}; nur die erste wird behalten)",
+        Msg::OtherChoiceRefused => "(der Anbieter hat auch Antwort {index} geschickt, die abgelehnt hat: {refusal}; nur die erste wird behalten)",
+        Msg::Drift => "(die Token-Schätzung des Prompts für {model} lag daneben: {drift}. Falls das öfter passiert, melden Sie bitte einen Fehler mit dem Namen des Modells unter {url}; gpt report --drift zeigt den bisherigen Verlauf)",
+
+        Msg::LangUnknown => "lang = \"{lang}\" ist keine Sprache, die gpt kennt, daher bleibt es bei Englisch. Verfügbar: {langs}.",
+        Msg::LangUnknownSet => "'{lang}' ist keine Sprache, die gpt kennt. Verfügbar: {langs}.",
+        Msg::ModelsIgnored => "Teile von {path} wurden ignoriert; gpt models validate zeigt, warum.",
+        Msg::NoApiKey => "Es gibt keinen API-Schlüssel für {provider}. Entweder:\n  - die Umgebungsvariable {var} setzen\n  - oder {provider} = \"<key>\" in die Tabelle [api_keys] von {config} eintragen",
+        Msg::NoApiKeyWizard => "  - oder gpt in einem Terminal starten und sich durch die Einrichtung führen lassen",
+        Msg::ProjectLoaded => "(Projekt {name}; :project zeigt, was es einstellt)",
+        Msg::Queued => "(keine Verbindung, daher als Anfrage {id} eingereiht; gpt flush sendet sie, ebenso das nächste gpt mit Verbindung, und die Antwort landet in {path})",
+        Msg::NotQueued => "Einreihen fehlgeschlagen: {error}",
+
+        Msg::Autosaved => "({count} Nachrichten als '{name}' gespeichert)",
+        Msg::AutosaveDiverged => "('{name}' nicht überschrieben, dort liegt ein früheres Gespräch; :save <Name> behält dieses)",
+        Msg::AutosaveFailed => "Die Sitzung '{name}' konnte nicht gespeichert werden: {error}",
+        Msg::BackgroundStopped => "({count} noch laufende Hintergrundanfrage(n) abgebrochen)",
+
+        Msg::AliasName => "'{name}' kann kein Kürzel sein.",
+        Msg::AliasBuiltIn => "'{name}' ist schon der eingebaute Befehl :{command}.",
+        Msg::ContextOn => "Der Kontext wird jetzt von Nachricht zu Nachricht mitgenommen.",
+        Msg::ContextOff => "Der Kontext wird nicht mehr von Nachricht zu Nachricht mitgenommen.\nEin '|' vor einer Nachricht behält ihn für diese eine.",
+        Msg::RoutingOn => "Jede Nachricht geht an {cheap} oder {expensive}. :why zeigt, warum.",
+        Msg::UnknownModel => "Unbekanntes Modell. Bitte noch einmal versuchen.\nMöglich sind: {options}.",
+        Msg::Cleared => "{count} Nachrichten entfernt. :restore holt sie zurück.",
+        Msg::NewConversation => "Ein neues Gespräch hat begonnen.",
+        Msg::Restored => "{count} Nachrichten zurückgeholt.",
+        Msg::NothingToRestore => "Es gibt keinen geleerten Kontext, der sich zurückholen ließe.",
+        Msg::MaxTokensSet => "max_tokens ist jetzt {value}.",
+        Msg::BudgetLeft => "Budget: {left} von {budget} übrig ({scope})",
+        Msg::BudgetSession => "diese Sitzung",
+        Msg::BudgetContext => "die Antworten im Kontext",
+        Msg::ThemeIs => "Das Thema ist {theme}.",
+        Msg::MathIs => "Darstellung von Formeln: {math}.",
+        Msg::CacheOn => "Antworten auf gleiche Anfragen werden jetzt zwischengespeichert.",
+        Msg::CacheOff => "Zwischenspeichern von Antworten ausgeschaltet.",
+        Msg::CacheCleared => "{count} zwischengespeicherte Antworten entfernt.",
+        Msg::DebugModel => "Modell: {model} | Max. Tokens: {max_tokens}",
+        Msg::DebugLimiter => "Ratenbegrenzung:\n{limiter}",
+        Msg::NoRequestYet => "Es wurde noch keine Anfrage gesendet.",
+        Msg::CurlOn => "Anfragen werden vor dem Senden als curl-Befehle ausgegeben.",
+        Msg::CurlOff => "Anfragen werden nicht mehr als curl-Befehle ausgegeben.",
+        Msg::CurlDry => "Anfragen werden als curl-Befehle ausgegeben statt gesendet.",
+        Msg::NotSentDryRun => "Nicht gesendet: Dies ist ein Probelauf.",
+        Msg::StreamToOff => "Die nächste Antwort wird nur angezeigt.",
+        Msg::StreamToQuiet => "Die nächste Antwort wird nach {path} geschrieben und nicht angezeigt.",
+        Msg::StreamToOn => "Die nächste Antwort wird beim Streamen nach {path} geschrieben.",
+        Msg::NothingToCompare => "Nichts zu vergleichen, bis zwei Anfragen gesendet wurden.",
+        Msg::TeeOff => "Antworten werden nicht mehr in eine Datei kopiert.",
+        Msg::TeeOn => "Antworten werden an {path} angehängt.",
+        Msg::FilterTee => "Antworten werden gezeigt und danach durch '{command}' geleitet.",
+        Msg::FilterIs => "Antworten werden durch '{command}' geleitet.",
+        Msg::NoFilter => "Antworten werden nicht gefiltert.",
+        Msg::FilterOff => "Antworten werden nicht mehr gefiltert.",
+        Msg::FilterOn => "Antworten werden ab jetzt durch '{command}' geleitet.",
+        Msg::NoWrapper => "In der Konfiguration ist weder prompt_prefix noch prompt_suffix gesetzt.",
+        Msg::WrapOn => "Nachrichten werden mit dem {wrapper} gesendet.",
+        Msg::WrapOff => "Nachrichten werden so gesendet, wie sie getippt sind, ohne das {wrapper}.",
+        Msg::SendOnlyIs => "Die nächste Nachricht wird mit den Nachrichten {indices} gesendet.",
+        Msg::SendOnlyOff => "Die nächste Nachricht wird mit dem ganzen Kontext gesendet.",
+        Msg::SendOnlySet => "Die nächste Nachricht wird nur mit den Nachrichten {indices} von {count} gesendet.",
+        Msg::NothingToBookmark => "Es gibt noch keine Antwort, auf die sich ein Lesezeichen setzen ließe.",
+        Msg::Bookmarked => "Lesezeichen {id} auf Nachricht {index} gesetzt.",
+        Msg::NoNotes => "Keine Nachricht hat eine Notiz.",
+        Msg::NoNote => "Nachricht {index} hat keine Notiz.",
+        Msg::NoteRemoved => "Notiz an Nachricht {index} entfernt.",
+        Msg::Noted => "Notiz an Nachricht {index} angehängt.",
+        Msg::QuoteDropped => "Zitat verworfen.",
+        Msg::QuoteWaiting => "(Ihre nächste Nachricht kommt hinter dieses Zitat; :quote off verwirft es)",
+        Msg::SaveHeld => "Die Sitzung '{name}' ist in einem anderen gpt offen (PID {pid}) und wurde daher nicht überschrieben. :save <neuer Name> behält auch diese.",
+        Msg::Saved => "{count} Nachrichten als '{name}' gespeichert.",
+        Msg::LoadHeld => "Die Sitzung '{name}' ist in einem anderen gpt offen (PID {pid}). :load --read-only {name} zeigt sie, :load --fork <neuer Name> {name} macht mit einer Kopie weiter.",
+        Msg::SessionExists => "Es gibt schon eine Sitzung namens '{name}'.",
+        Msg::SessionHeld => "Die Sitzung '{name}' ist in einem anderen gpt offen (PID {pid}).",
+        Msg::Loaded => "{count} Nachrichten aus '{name}' geladen.",
+        Msg::LoadedReadOnly => "{count} Nachrichten aus '{name}' schreibgeschützt geladen. :save <neuer Name> behält Änderungen.",
+        Msg::LoadedFork => "{count} Nachrichten aus '{name}' geladen und als '{new}' gespeichert.",
+        Msg::NoSessions => "Keine gespeicherten Sitzungen.",
+        Msg::NoTitle => "Diese Sitzung hat noch keinen Titel.",
+        Msg::TitleSet => "Titel gesetzt.",
+        Msg::NoVars => "Es sind keine Variablen gesetzt.",
+        Msg::VarUnset => "Die Variable '{name}' ist nicht gesetzt.",
+        Msg::VarRemoved => "{name} entfernt.",
+        Msg::ModelsRead => "{path} gelesen ({count} Modelle bekannt).",
+        Msg::ModelIgnored => "  ignoriert: {problem}",
+        Msg::NoMetadata => "Zu '{id}' gibt es keine Metadaten.",
+        Msg::PricePerMillion => "{input} Eingabe, {cached} aus dem Cache, {output} Ausgabe pro Million",
+        Msg::NoFineTunedPrice => "unbekannt: das Basismodell hat keinen fine_tuned_price",
+        Msg::Unknown => "unbekannt",
+        Msg::CatalogNotLoaded => "Die Modellliste von OpenRouter ist (noch) nicht geladen; gezeigt werden die eingebauten Modelle.",
+        Msg::ColumnModel => "Modell",
+        Msg::ColumnInput => "Eingabe",
+        Msg::ColumnOutput => "Ausgabe",
+        Msg::ColumnWindow => "Fenster",
+        Msg::FineTunedModels => "Feinabgestimmte Modelle Ihrer Organisation:",
+        Msg::NoAliases => "Es sind keine Kürzel definiert.",
+        Msg::NoAlias => "Es gibt kein Kürzel '{name}'.",
+        Msg::AliasRemoved => ":{name} entfernt.",
+        Msg::PathsConfig => "Konfiguration: {path}",
+        Msg::PathsConfigMissing => "Konfiguration: {path} (gibt es nicht, es gelten die Voreinstellungen)",
+        Msg::PathsData => "Daten:         {path}",
+        Msg::PathsSessions => "Sitzungen:     {path}",
+        Msg::PathsCache => "Cache:         {path}",
+        Msg::PathsTools => "Werkzeuge:     {path}",
+        Msg::PathsOrg => "Organisation:  {org}",
+        Msg::PathsOrgDefault => "(die des Schlüssels)",
+        Msg::PathsProject => "Projekt:       {project}",
+        Msg::NoTools => "Keine Werkzeuge. :help tools erklärt, wie welche dazukommen.",
+        Msg::NoTool => "Es gibt kein Werkzeug namens '{name}'.",
+        Msg::ToolEnabled => "{name} eingeschaltet.",
+        Msg::ToolDisabled => "{name} ausgeschaltet.",
+        Msg::ScriptRunning => "Es läuft schon ein Skript.",
+        Msg::ExpectIs => "Als Antwort wird {shape} erwartet.",
+        Msg::NoExpect => "Antworten werden nicht geprüft. Möglich sind :expect json, code, code:strict oder regex:<Muster>.",
+        Msg::ExpectOff => "Antworten werden nicht mehr geprüft.",
+        Msg::ExpectSet => "Ab jetzt wird als Antwort {shape} erwartet.",
+        Msg::RoutingOff => "Die Modellwahl ist aus. :model auto schaltet sie ein.",
+        Msg::NotRouted => "Es wurde noch keine Nachricht an ein Modell geleitet.",
+        Msg::InvalidFlag => "'{value}' ist kein gültiger Wert für --{flag}.",
+        Msg::ContextEmpty => "Der Kontext ist leer.",
+        Msg::Shortened => "gekürzt",
+        Msg::Wrapped => "umrahmt",
+        Msg::ListNote => "Notiz: {note}",
+        Msg::ListWrapper => "(neue Nachrichten werden mit dem {wrapper} gesendet)",
+        Msg::Tokens => "{count} Tokens",
+        Msg::ShortenedFrom => "Gekürzt; das Original liegt in {path}",
+        Msg::SentWith => "Gesendet mit dem {wrapper}",
+        Msg::Note => "Notiz: {note}",
+        Msg::JustNow => "gerade eben",
+        Msg::MinutesAgo => "vor {n} Min.",
+        Msg::HoursAgo => "vor {n} Std.",
+        Msg::DaysAgo => "vor {n} Tg.",
+        Msg::NotAnIndex => "'{arg}' ist kein Nachrichtenindex. Der Kontext hat {count} Nachrichten.",
+        Msg::Backwards => "'{range}' ist verkehrt herum; richtig ist {to}-{from}.",
+        Msg::LockTakenOver => "(die Sperre auf '{name}' von PID {pid} übernommen, der nicht mehr läuft)",
+        Msg::Swapped => "Zum Modell {model} ({provider}) gewechselt.",
+        Msg::TokensWindow => ", von {window} im Fenster von {model}, mit {answer} für die Antwort",
+        Msg::TokensApproximate => " (grob geschätzt, ohne den Tokenizer)",
+        Msg::TokensTotal => "Etwa {count} Prompt-Tokens{window}{estimate}.",
+        Msg::TokensContextDropped => "(die {count} Nachrichten des Kontexts sind nicht mitgezählt, da die Nachricht sie verwerfen würde; ein | am Anfang behält sie)",
+        Msg::NotInProject => "Kein Projekt: In diesem Verzeichnis und darüber gibt es keine {file}, oder gpt wurde mit --global gestartet.",
+        Msg::ProjectName => "Projekt:   {name} ({path})",
+        Msg::ProjectModel => "Modell:    {model}",
+        Msg::ProjectSystem => "System:    {line} (~{tokens} Tokens)",
+        Msg::ProjectNoSystem => "System:    (keiner)",
+        Msg::ProjectFirstFile => "Dateien:   {path} ({size})",
+        Msg::ProjectFile => "           {path} ({size})",
+        Msg::ProjectFileTokens => "~{tokens} Tokens",
+        Msg::ProjectFileNotSent => "nicht gesendet: {error}",
+        Msg::ProjectSessions => "Sitzungen: {path}",
+        Msg::StatsTokens => "Anfragen: {requests} | Prompt-Tokens: {prompt} ({cached} aus dem Cache) | Antwort-Tokens: {completion}",
+        Msg::StatsUnpricedOne => " (ohne 1 Anfrage an ein Modell mit unbekannten Preisen)",
+        Msg::StatsUnpriced => " (ohne {count} Anfragen an Modelle mit unbekannten Preisen)",
+        Msg::StatsPrice => "Gesamtpreis: {cost}{unpriced} | Durch Prompt-Caching gespart: {saving}",
+        Msg::StatsTitles => "Titel: {count} Anfragen | {cost}",
+        Msg::StatsRouting => "Modellwahl: {count} Anfragen | {cost}",
+        Msg::StatsRecaps => "Zusammenfassungen: {count} Anfragen | {cost}",
+        Msg::StatsEndpoints => "Anfragen je Endpunkt: {endpoints}",
+
+        Msg::WrapperPrefix => "Präfix \"{text}\"",
+        Msg::WrapperSuffix => "Suffix \"{text}\"",
+        Msg::WrapperBoth => "{prefix} und {suffix}",
+        Msg::DraftRestored => "(ein nicht gesendeter Entwurf mit {lines} ist wieder da: :draft show gibt ihn aus, :send sendet ihn und :draft clear verwirft ihn)",
+        Msg::Drafting => "Entwurf: Zeilen werden aufbewahrt, bis :send sie sendet. :draft clear verwirft sie.",
+        Msg::AlreadyDrafting => "Es gibt schon einen Entwurf, bisher {lines}. :send sendet ihn, :draft clear verwirft ihn.",
+        Msg::DraftDropped => "Entwurf verworfen ({lines}).",
+        Msg::DraftEmpty => "Der Entwurf ist leer.",
+        Msg::NoDraft => "Es gibt keinen Entwurf. :draft beginnt einen.",
+        Msg::NoDraftToSend => "Es gibt keinen Entwurf zum Senden. :draft beginnt einen.",
+        Msg::DraftEmptyNotSent => "Der Entwurf ist leer. :draft clear beendet das Entwerfen.",
+        Msg::OneLine => "1 Zeile",
+        Msg::Lines => "{count} Zeilen",
+        Msg::ConfigMissing => "{path} gibt es nicht.",
+        Msg::ProjectIgnored => "{path} wurde ignoriert.",
+        Msg::FilterIgnored => "response_filter wird ignoriert.",
+        Msg::ModelUnknown => "Unbekanntes Modell '{name}'.",
+        Msg::NothingQueued => "Es ist nichts eingereiht.",
+        Msg::Flushed => "({sent} gesendet, {failed} abgelehnt, {left} noch eingereiht)",
+        Msg::DraftNotSaved => "Der Entwurf konnte nicht gespeichert werden: {error}",
+        Msg::ScriptStoppedOne => "(Skript angehalten; 1 weitere Zeile lief nicht)",
+        Msg::ScriptStopped => "(Skript angehalten; {count} weitere Zeilen liefen nicht)",
+        Msg::TypedAheadClearedOne => "(1 wartende Nachricht verworfen)",
+        Msg::TypedAheadCleared => "({count} wartende Nachrichten verworfen)",
+        Msg::TypedAheadOne => "(1 Nachricht wartet)",
+        Msg::TypedAhead => "({count} Nachrichten warten)",
+
+        Msg::QueuedSent => "(eingereihte Anfrage {id} gesendet; die Antwort steht in {path})",
+        Msg::QueuedWaitingOne => "(1 eingereihte Anfrage wartet noch)",
+        Msg::QueuedWaiting => "({count} eingereihte Anfragen warten noch)",
+        Msg::QueuedNotSent => "(die eingereihten Anfragen konnten nicht gesendet werden: {error})",
+        Msg::QueuedCorrupt => "Die eingereihte Anfrage {id} ist beschädigt: {error}",
+        Msg::QueuedNoKey => "Die eingereihte Anfrage {id} ist für {provider}, aber dafür gibt es keinen Schlüssel.",
+        Msg::QueuedHeader => "(eingereihte Anfrage {n} von {total}, {model}, vom {queued})",
+        Msg::QueuedStays => "Senden fehlgeschlagen ({error}); sie bleibt eingereiht.",
+        Msg::QueuedRejected => "Die eingereihte Anfrage {id} wurde abgelehnt: {error}",
+
+        Msg::NothingAfterOverrides => "Nach den Overrides kommt keine Nachricht.",
+        Msg::SecondAnswerWrong => "Warnung: auch die zweite Antwort war nicht richtig: {problem}.",
+        Msg::NoLongerInContext => "Nachricht {index} ist nicht mehr im Kontext; er hat {count} Nachrichten.",
+        Msg::StreamFromCache => "{model}, aus dem Cache",
+        Msg::FallingBack => "{model} ist fehlgeschlagen ({error}), weiter mit {next}",
+        Msg::ToolRoundsStopped => "Nach {rounds} Runden Tool-Aufrufen angehalten.",
+        Msg::StoppedWithEscape => "mit Escape angehalten",
+        Msg::StreamFinished => "{model}, {prompt} Prompt- + {completion} Antwort-Tokens, {price}",
+        Msg::CacheWriteFailed => "Konnte nicht in den Antwort-Cache schreiben: {error}",
+        Msg::MessageNotSendable => "Nachricht {index} konnte nicht gesendet werden: {error}",
+        Msg::MessageHasNoText => "sie hat keinen Text",
+        Msg::NothingArrived => "{secs} s lang kam nichts an",
+        Msg::EmptyResponse => "Das Modell hat eine leere Antwort geliefert (finish_reason: {reason}).",
+        Msg::ContextDroppedOne => "(1 Nachricht Kontext verworfen – mit | davor bleibt sie erhalten, mit :c immer)",
+
+        Msg::TokensContextDroppedOne => "(die 1 Nachricht des Kontexts ist nicht mitgezählt, da die Nachricht sie verwerfen würde; ein | am Anfang behält sie)",
+
+        Msg::ExecuteNeedsTerminal => "Nicht ausgeführt: --execute braucht ein Terminal zum Bestätigen.",
+        Msg::NotRun => "Nicht ausgeführt.",
+        Msg::CommandFailedToRun => "Der Befehl konnte nicht ausgeführt werden: {error}",
+        Msg::CommandExited => "(der Befehl endete mit {code})",
+        Msg::CommandKilled => "(der Befehl wurde beendet)",
+        Msg::ConfirmDangerous => "Dieser Befehl wurde als gefährlich markiert. Mit yes wird er trotzdem ausgeführt:",
+        Msg::ConfirmRun => "Ausführen? [y/N]",
+        Msg::CommandDestructive => "Warnung: dieser Befehl könnte Schaden anrichten.",
+        Msg::CommandCopied => "(mit {tool} in die Zwischenablage kopiert)",
+        Msg::CommandNotCopied => "(nicht kopiert: {error})",
+        Msg::SuggestionNotJson => "Die Antwort des Modells war kein gültiges JSON: {error}",
+        Msg::NoSuggestion => "Das Modell hat keinen Befehl vorgeschlagen.",
+        Msg::NoClipboardTool => "kein Programm für die Zwischenablage gefunden",
+
+        Msg::GcNothing => "(nichts zu entfernen)",
+        Msg::GcFailed => "gc ist fehlgeschlagen: {error}",
+        Msg::GcOnStart => "(gc: {summary}; siehe die Aufbewahrungs-Einstellungen in der Konfiguration)",
+        Msg::GcPrunedRequests => "{count} der Anfragen in usage.jsonl entfernt, älter als {days} Tage",
+        Msg::GcWouldPruneRequests => "würde {count} der Anfragen in usage.jsonl entfernen, älter als {days} Tage",
+        Msg::GcNoBookmarks => "Sitzungen bleiben unberührt, da die Lesezeichen nicht gelesen werden konnten: {error}",
+        Msg::GcNotSaved => "seit {days} Tagen nicht gespeichert",
+        Msg::GcPastMaxSessions => "über max_sessions",
+        Msg::GcExpired => "abgelaufen",
+        Msg::GcPastCacheSize => "über cache_max_mb",
+        Msg::GcCutShort => "von einem abgebrochenen Schreibvorgang übrig",
+        Msg::GcRemoved => "{path} entfernt ({why})",
+        Msg::GcWouldRemove => "würde {path} entfernen ({why})",
+        Msg::GcOneRequest => "1 Anfrage aus usage.jsonl",
+        Msg::GcRequests => "{count} Anfragen aus usage.jsonl",
+        Msg::GcAnd => " und ",
+        Msg::GcRemovedSummary => "entfernt: {parts}",
+        Msg::GcWouldRemoveSummary => "würde entfernen: {parts}",
+
+        Msg::DuoUsage => "Verwendung: :duo <Modell a> <Modell b> [--system-a <Prompt>] [--system-b <Prompt>] [--turns <n>] [--stop <Satz>] <erste Nachricht>",
+        Msg::DuoTurns => "--turns braucht eine Zahl über 0, nicht '{value}'.",
+        Msg::DuoTitle => "{a} und {b}",
+        Msg::DuoOneTurn => "1 Zug",
+        Msg::DuoTurnCount => "{count} Züge",
+        Msg::DuoSummary => "({turns}, {cost} für beide Modelle; gespeichert als '{name}', siehe :load oder gpt view)",
+        Msg::DuoHeader => "── {side} · {model} · Zug {turn} von {turns} ──",
+        Msg::DuoNote => "Seite {side} von :duo",
+        Msg::DuoStopped => "(angehalten: {side} hat den Stopp-Satz gesagt)",
+        Msg::CompareUsage => "Verwendung: :compare <Modell>,<Modell>[,...] <Frage>",
+        Msg::CompareHeader => "── {model} ──",
+        Msg::CompareNotice => "({tokens} Tokens, {price})",
+
+        Msg::SettingModel => "das Modell für neue Nachrichten",
+        Msg::SettingProvider => "bedient Modelle, die keinen eigenen wählen: openai, openrouter, anthropic oder gemini",
+        Msg::SettingOrganization => "die OpenAI-Organisation, der Anfragen berechnet werden (default = die des Schlüssels)",
+        Msg::SettingMaxTokens => "die meisten Tokens, die eine Antwort verbrauchen darf: eine Zahl, auto oder max",
+        Msg::SettingTemperature => "wie abwechslungsreich Antworten sind, 0 bis 2 (default = die des Modells)",
+        Msg::SettingPreset => "die Längenvorgabe, wie :brief, :normal und :detailed sie setzen",
+        Msg::SettingAutoPipe => "ob der Kontext von Nachricht zu Nachricht mitgenommen wird",
+        Msg::SettingCache => "ob gleiche Anfragen aus dem Cache beantwortet werden",
+        Msg::SettingFooter => "wie viel die Fußzeile zeigt: off, minimal oder full",
+        Msg::SettingTheme => "Farben für Prompt und Ausgabe: dark, light oder plain",
+        Msg::SettingMath => "wie LaTeX-Formeln in Antworten gezeigt werden: off oder unicode",
+        Msg::SettingWordWrap => "Antworten an Wortgrenzen umbrechen, passend zur Breite des Terminals",
+        Msg::SettingLang => "die Sprache von gpts eigenen Meldungen, nicht der Antworten: en oder de",
+        Msg::SettingConversationBudget => "eine weiche Grenze für die Kosten eines Gesprächs, in der Anzeigewährung (off = keine)",
+        Msg::SettingDowngradeOnBudget => "eine Nachricht, die das Budget überschreiten könnte, an ein günstigeres Modell senden statt zu fragen",
+        Msg::SettingBudgetScope => "was das Budget zählt: session (bis :new) oder context (bis er geleert wird)",
+        Msg::SettingWarnOnContextDrop => "einen Hinweis zeigen, wenn eine Nachricht den bisherigen Kontext verwirft",
+        Msg::SettingSubstitution => "${VAR} und $(command) in jeder Nachricht ersetzen",
+        Msg::SettingNotifyAfterSecs => "benachrichtigen, wenn eine Antwort so lange dauert (off = nie)",
+        Msg::SettingStallAfterSecs => "einen Stream aufgeben, der so lange nichts sendet (off = nie)",
+        Msg::SettingSafeMode => "ob Nebenwirkungen abgeschaltet sind; nur --safe oder die Konfiguration können es setzen",
+        Msg::SettingTitleAfterExchanges => "die Sitzung nach so vielen Wechseln benennen (0 = nie)",
+
+        Msg::InvalidMaxTokens => "'{value}' ist kein gültiges max_tokens. Möglich sind eine Zahl, 'auto' oder 'max'.",
+        Msg::MaxTokensExplicit => "ausdrücklich gesetzt",
+        Msg::MaxTokensModelMax => "Höchstwert des Modells",
+        Msg::MaxTokensModelDefault => "Vorgabe des Modells",
+        Msg::MaxTokensGlobalDefault => "allgemeine Vorgabe",
+        Msg::UnknownSetting => "Unbekannte Einstellung '{name}'. :get zeigt alle Einstellungen.",
+        Msg::InvalidBool => "'{value}' ist kein gültiger Wert. Möglich sind true oder false.",
+        Msg::AutoModel => "auto (zuletzt: {model})",
+        Msg::InvalidProvider => "Der Anbieter kann openai, openrouter, anthropic oder gemini sein.",
+        Msg::InvalidTemperature => "'{value}' ist keine Temperatur von 0 bis 2 und nicht default.",
+        Msg::InvalidPreset => "Die Vorgabe kann brief, normal oder detailed sein.",
+        Msg::InvalidFooter => "Die Fußzeile kann off, minimal oder full sein.",
+        Msg::InvalidTheme => "Das Farbschema kann dark, light oder plain sein.",
+        Msg::InvalidMath => "Die Formelanzeige kann off oder unicode sein.",
+        Msg::InvalidAmount => "'{value}' ist weder ein Betrag noch off.",
+        Msg::InvalidBudgetScope => "Der Budget-Bereich kann session oder context sein.",
+        Msg::InvalidSeconds => "'{value}' ist weder eine Anzahl Sekunden noch off.",
+        Msg::SafeModeFixed => "safe_mode lässt sich in der REPL nicht ändern; gpt mit oder ohne --safe starten.",
+        Msg::InvalidNumber => "'{value}' ist keine Zahl.",
+
+        Msg::QuoteNoAnswer => "Es gibt noch keine Antwort zum Zitieren.",
+        Msg::InvalidPattern => "Ungültiges Muster: {error}",
+        Msg::QuoteNoMatch => "Nichts in der letzten Antwort passt auf /{pattern}/.",
+        Msg::QuoteBadRange => "Die letzte Antwort hat {lines} Zeilen; {from}-{to} liegt nicht darin.",
+        Msg::QuoteOneParagraph => "Die letzte Antwort hat nur einen Absatz.",
+        Msg::QuoteParagraphs => "Die letzte Antwort hat {count} Absätze.",
+        Msg::QuoteUsage => "Verwendung: :quote <Absatz> | <von>-<bis> | /regex/ | off",
+
+        Msg::NoSuchMessage => "Es gibt keine Nachricht {index}; der Kontext hat {count}.",
+        Msg::NoSuchBookmark => "Es gibt kein Lesezeichen {id}.",
+        Msg::NoBookmarks => "Noch keine Lesezeichen. Mit :bookmark fügst du die letzte Antwort hinzu.",
+        Msg::BookmarkNotCopied => "Lesezeichen {id} konnte nicht kopiert werden: {error}.",
+        Msg::BookmarkCopied => "Lesezeichen {id} mit {tool} in die Zwischenablage kopiert.",
+        Msg::BookmarkDeleted => "Lesezeichen {id} gelöscht ({summary}).",
+
+        Msg::BudgetOver => "könnte bis zu {cost} kosten, bei {left} verbleibendem Gesprächsbudget von {budget}",
+        Msg::BudgetDowngrade => "(mit {model} {over}, daher geht es an {cheap})",
+        Msg::BudgetAsk => "Das {over}. Trotzdem senden? [y/N]",
+        Msg::BudgetNotSent => "Nicht gesendet, da es das Gesprächsbudget überschreiten könnte.",
+        Msg::BudgetNotSentNoTty => "Nicht gesendet: es {over}. Erhöhe conversation_budget oder setze downgrade_on_budget, um es an ein günstigeres Modell zu senden.",
+
+        Msg::RateLimitWait => "Ratenlimit erreicht, warte {seconds}s...",
+        Msg::RateLimitAvailable => "{name}/min: {available} von {capacity} verfügbar",
+        Msg::RateLimitUnlimited => "{name}/min: unbegrenzt",
+        Msg::RateLimitBlocked => "von der API für {seconds}s gesperrt",
+
+        Msg::SummaryNothing => "Noch nichts zusammenzufassen: der Kontext hat keine Antworten.",
+        Msg::SummaryExchanges => "Wechsel: {count}",
+        Msg::SummaryOneWithoutPrompt => " (1 Antwort hat ihren Prompt nicht erfasst)",
+        Msg::SummaryWithoutPrompts => " ({count} Antworten haben ihre Prompts nicht erfasst)",
+        Msg::SummaryTokens => "Tokens: {prompt} Prompt{unknown} | {completion} Antwort",
+        Msg::SummaryCost => "Kosten: {cost}",
+        Msg::SummaryModels => "Modelle:",
+        Msg::SummaryOneAnswer => "1 Antwort",
+        Msg::SummaryAnswers => "{count} Antworten",
+        Msg::SummaryModelLine => "{answers} | {prompt} Prompt | {completion} Antwort | {cost}",
+        Msg::SummaryModel => "Modell: {model}",
+        Msg::SummaryLatency => "Latenz: {average}s im Schnitt | {longest}s am längsten ([{index}])",
+        Msg::SummaryLongest => "Längste Nachricht: [{index}] {role}, {tokens} Tokens: {preview}",
+        Msg::SummaryDuration => "Dauer: {duration} ({first} bis {last})",
+        Msg::RecapEmpty => "Die Zusammenfassung kam leer zurück.",
+        Msg::RecapHeader => "Zusammenfassung:",
+        Msg::RecapNotice => "(Zusammenfassung von {model}: {tokens} Tokens, {price}; unter Recaps in :stats gezählt)",
+
+        Msg::McpNoCommand => "weder command noch url angegeben",
+        Msg::McpAnswered => "{server} antwortete mit {status}",
+        Msg::McpNoPipes => "keine Verbindung zum Prozess möglich",
+        Msg::McpBadUrl => "'{url}' ist keine gültige URL: {error}",
+        Msg::McpNoConnection => "keine Verbindung zu {url}: {error}",
+        Msg::McpNoEndpoint => "{url} hat seinen Ereignisstrom geschlossen, ohne einen Endpunkt zu nennen",
+        Msg::McpBadEndpoint => "{url} nannte einen Endpunkt, der keine URL ist: {error}",
+        Msg::McpExited => "{server} wurde beendet",
+        Msg::UnknownError => "unbekannter Fehler",
+        Msg::McpStartFailed => "MCP-Server '{name}' konnte nicht starten: {error}",
+        Msg::McpStartTimeout => "MCP-Server '{name}' ist nicht rechtzeitig gestartet.",
+
+        Msg::UsageNotRecorded => "Die Nutzung konnte nicht in {path} erfasst werden: {error}",
+        Msg::UsageNewer => "{count} der Anfragen in {path} ausgelassen, erfasst von einem neueren gpt-cli. Aktualisiere gpt-cli, um sie mitzuzählen.",
+        Msg::UsageNone => "Keine Nutzung erfasst{since}.",
+        Msg::UsageOneUnpriced => "1 Anfrage fehlt in den Kosten; die Preise ihres Modells waren unbekannt.",
+        Msg::UsageUnpriced => "{count} Anfragen fehlen in den Kosten; die Preise ihrer Modelle waren unbekannt.",
+        Msg::DriftNone => "Keine Anfragen mit einer Schätzung zum Vergleich erfasst{since}.",
+        Msg::DriftLegend => "Drift ist, wie viel mehr Prompt-Tokens abgerechnet als geschätzt wurden, mean, wie weit eine Anfrage im Schnitt danebenliegt, und worst die Anfrage, die am weitesten danebenliegt.",
+        Msg::DriftOff => "Die Schätzungen für {models} liegen um mehr als {percent}% daneben. Bitte melde einen Fehler unter {issues} mit dem Namen des Modells.",
+        Msg::UsageSince => " seit {date}",
+
+        Msg::SetupIntro => "Kein API-Schlüssel und keine Konfigurationsdatei gefunden, also richten wir eines ein. Mit Strg+C brichst du ab.",
+        Msg::SetupProvider => "Anbieter [1]:",
+        Msg::SetupPickNumber => "Wähle eine Nummer aus der Liste.",
+        Msg::SetupKey => "{var} (wird nicht angezeigt):",
+        Msg::SetupWhereKey => "Der Schlüssel kann in {path} gespeichert werden, nur für dich lesbar,\noder du setzt {var} jedes Mal selbst.",
+        Msg::SetupSave => "In der Konfigurationsdatei speichern? [Y/n]",
+        Msg::SetupNotSaved => "Nicht gespeichert. Er wird nur bis zum Ende des Programms verwendet.",
+        Msg::SetupSaved => "In {path} gespeichert.",
+        Msg::SetupModels => "Standardmodell (Preise pro Million Prompt-/Antwort-Tokens):",
+        Msg::SetupDefault => " (Standard)",
+        Msg::SetupModel => "Modell:",
+        Msg::SetupPickModel => "Wähle eine Nummer aus der Liste oder drücke Enter für den Standard.",
+        Msg::SetupNeedsTerminal => "Die Einrichtung braucht Antworten vom Terminal.",
+
+        Msg::FileCorrupt => "{what} ist beschädigt: {error}",
+        Msg::FileNewer => "{what} wurde von einem neueren gpt-cli geschrieben (Schemaversion {found}, dieses liest bis {current}). Aktualisiere gpt-cli, um es zu lesen.",
+        Msg::ReadFailed => "{path} konnte nicht gelesen werden: {error}",
+        Msg::MigrateUnparsable => "unverändert, da es nicht gelesen werden konnte",
+        Msg::MigrateNewer => "unverändert, da es von einem neueren gpt-cli stammt",
+        Msg::MigrateKeptNewer => "{entries} von einem neueren gpt-cli",
+        Msg::MigrateKeptUnparsable => "{entries}, die nicht gelesen werden konnten",
+        Msg::MigrateAnd => " und ",
+        Msg::MigrateKeeping => " ({entries} bleiben, wie sie sind)",
+        Msg::MigrateNothing => "nichts zu aktualisieren{kept}",
+        Msg::MigrateAtVersion => "{entries} in v{version}",
+        Msg::MigrateWouldUpgrade => "würde {versions} auf v{current} aktualisieren{kept}",
+        Msg::MigrateUpgraded => "{versions} auf v{current} aktualisiert{kept}",
+        Msg::MigrateCheckedNothing => "({current} aktuell, {skipped} unverändert: nichts zu aktualisieren)",
+        Msg::MigrateChecked => "({upgraded} zu aktualisieren, {current} aktuell, {skipped} unverändert: gpt migrate aktualisiert sie)",
+        Msg::MigrateSummary => "({upgraded} aktualisiert, {current} aktuell, {skipped} unverändert)",
+        Msg::MigrateOneEntry => "1 Eintrag",
+        Msg::MigrateEntries => "{count} Einträge",
+
+        Msg::InvalidSessionName => "Ungültiger Sitzungsname '{name}'.",
+        Msg::SessionNamed => "Sitzung '{name}'",
+        Msg::FileQuarantined => "{what} ist beschädigt ({error}) und wurde daher nach {path} verschoben.",
+        Msg::NotASessionFile => "{path} ist keine Sitzungsdatei: {error}",
+        Msg::NoSuchSession => "Keine gespeicherte Sitzung namens '{name}'.",
+
+        Msg::IndexAt => "Der Index unter {path}",
+        Msg::IndexCorrupt => "Der Index unter {path} ist beschädigt: {error}. Führe gpt index erneut aus, um ihn neu aufzubauen.",
+        Msg::CantIndex => "{path} kann nicht indiziert werden: {error}",
+        Msg::IndexEmbedding => "(bette Abschnitte {from}-{to} von {total} ein)",
+        Msg::Indexed => "{files} Dateien unter {path} indiziert: {changed} eingebettet ({tokens} Tokens), {unchanged} unverändert.",
+        Msg::NoIndex => "Unter {path} gibt es keinen Index. Lege einen mit gpt index <Verzeichnis> an.",
+        Msg::NoQuestionEmbedding => "Die Embeddings-API hat für die Frage nichts zurückgegeben.",
+
+        Msg::CantWatch => "{path} kann nicht beobachtet werden: {error}",
+        Msg::Watching => "Beobachte {path}. Mit q oder Strg+C beendest du es.",
+        Msg::WatchStoppedCount => "Beobachtung nach {count} Anfragen beendet.",
+        Msg::WatchStoppedBudget => "Beobachtung beendet, nachdem das Budget von {budget} ausgegeben war.",
+        Msg::WatchHowToStop => "Mit q beendest du die Beobachtung.",
+        Msg::WatchBinary => "({path} sieht nach einer Binärdatei aus; nicht gesendet)",
+        Msg::WatchCantRead => "({path} kann nicht gelesen werden: {error})",
+
+        Msg::ReviewNoChanges => "Keine Änderungen zu prüfen.",
+        Msg::ReviewPart => "(prüfe Teil {part} von {parts})",
+        Msg::GitFailedToRun => "git konnte nicht ausgeführt werden: {error}",
+        Msg::GitDiffFailed => "git diff ist fehlgeschlagen: {error}",
+        Msg::ReviewNotJson => "Die Prüfung des Modells war kein gültiges JSON: {error}",
+        Msg::ReviewNoIssues => "Keine Probleme gefunden.",
+        Msg::ReviewLine => " Zeile {line}:",
+
+        Msg::FixLastCommand => "Dein letzter Befehl war: {command}",
+        Msg::FixRerun => "Nochmal ausführen, um die Ausgabe zu sehen? Das kann Nebenwirkungen haben. [y/N]",
+        Msg::FixNotRun => "Nicht ausgeführt. Mit :fix --no-rerun <Ausgabe> fügst du ein, was er ausgegeben hat.",
+        Msg::FixUnknownShell => "Unbekannt, wo {shell} seinen Verlauf ablegt. Setze shell_history in der Konfiguration oder nutze :fix --no-rerun <Ausgabe>.",
+        Msg::FixNoHome => "Es gibt kein Home-Verzeichnis, in dem der Verlauf liegen könnte.",
+        Msg::FixNoCommands => "In {path} gibt es noch keine Befehle. bash schreibt sie erst beim Beenden der Shell, außer PROMPT_COMMAND führt history -a aus.",
+
+        Msg::ProviderNoKey => "Setze die Umgebungsvariable '{var}' oder {provider} in der Tabelle [api_keys] der Konfiguration, um {model} zu verwenden.",
+        Msg::EndpointFailed => "({base} ist fehlgeschlagen: {error}; versuche {next})",
+        Msg::EndpointFailedDown => "({base} ist fehlgeschlagen: {error}, daher wird es eine Weile übergangen; versuche {next})",
+        Msg::ContentFiltered => "Die Antwort wurde von den Sicherheitsfiltern des Anbieters blockiert.",
+        Msg::KeyRejected => "API-Schlüssel von {provider} abgelehnt ({status}) — prüfe {var} oder {provider} in der Tabelle [api_keys] der Konfiguration.",
+        Msg::NoAnswerIn => "keine Antwort in {seconds}s",
+        Msg::HttpClientFailed => "Der HTTP-Client konnte nicht eingerichtet werden: {error}",
+        Msg::FineTunedNotListed => "Die feinabgestimmten Modelle konnten nicht aufgelistet werden: {error}",
+        Msg::CatalogNotFetched => "Die Modellliste konnte nicht von {url} geladen werden: {error}",
+        Msg::ModelRefused => "Das Modell hat abgelehnt: {refusal}",
+
+        Msg::SeedInvalid => "{path} ist kein gültiger Seed:\n  {problems}",
+        Msg::SeedTemperature => "temperature sollte eine Zahl von 0 bis 2 sein",
+        Msg::SeedFilesList => "files sollte eine Liste von Pfaden oder Globs sein",
+        Msg::SeedNotString => "{key} sollte eine Zeichenkette sein",
+        Msg::SeedMessagesTables => "messages sollte als [[messages]] geschrieben werden",
+        Msg::SeedMessageNotTable => "Nachricht {n} ist keine Tabelle",
+        Msg::SeedMessageRole => "Nachricht {n}: role sollte \"user\" oder \"assistant\" sein",
+        Msg::SeedMessageContent => "Nachricht {n}: content sollte eine Zeichenkette sein",
+        Msg::SeedUnknownKeys => "(ignoriere, was {path} enthält, das gpt nicht kennt: {keys})",
+        Msg::SeedNoMatch => "{path}: '{glob}' passt auf keine Dateien in {dir}.",
+        Msg::SeedStarted => "Neues Gespräch aus {path} begonnen: {model}, {system}, {messages} und {files} bereitgestellt.",
+        Msg::WriteFailed => "{path} konnte nicht geschrieben werden: {error}",
+        Msg::SeedWritten => "Die Einrichtung wurde in {path} geschrieben: {system}, {messages} und {files}. Das Gespräch ist nicht enthalten.",
+        Msg::SeedSystem => "ein System-Prompt",
+        Msg::SeedNoSystem => "kein System-Prompt",
+        Msg::SeedOneMessage => "1 Nachricht",
+        Msg::SeedMessages => "{count} Nachrichten",
+        Msg::OneFile => "1 Datei",
+        Msg::Files => "{count} Dateien",
+        Msg::SeedOnePath => "1 Dateipfad",
+        Msg::SeedPaths => "{count} Dateipfade",
+
+        Msg::FileSensitive => "{path} sieht aus, als enthielte es Geheimnisse (es passt auf {pattern}), und wurde daher nicht gesendet. Setze ! vor den Pfad oder nutze --force mit gpt ask, um es trotzdem zu senden.",
+        Msg::FileBinary => "{path} sieht nach einer Binärdatei aus und wurde daher nicht gesendet.",
+        Msg::FileNotUtf8 => "{path} ist kein UTF-8-Text.",
+
+        Msg::UnclosedVariable => "Nicht geschlossenes '${' in der Nachricht.",
+        Msg::VariableNotSet => "Die Umgebungsvariable '{name}' ist nicht gesetzt.",
+        Msg::UnclosedCommand => "Nicht geschlossenes '$(' in der Nachricht.",
+        Msg::SubstFailedToRun => "'{command}' konnte nicht ausgeführt werden: {error}",
+        Msg::SubstFailed => "'{command}' ist fehlgeschlagen ({status}): {error}",
+
+        Msg::TranslateNothingToSwap => "Noch nichts zum Tauschen. Übersetze zuerst etwas.",
+        Msg::TranslateNothingBack => "Es gibt noch keine Übersetzung zum Zurückübersetzen.",
+        Msg::TranslateUsage => "Verwendung: :translate [--swap] [Sprache|Quelle:Sprache] <Text>",
+        Msg::TranslateNoTarget => "Gib beim ersten Mal an, in welche Sprache übersetzt wird, z. B. :tr de good morning.",
+
+        Msg::AskSkippedBinary => "{path} übersprungen: es sieht nach einer Binärdatei aus.",
+
+        Msg::AskFileTooLong => "Diese Nachricht hat ~{tokens} Tokens, mehr als das Fenster von {model} mit {window}.",
+        Msg::AskFileInParts => "In {parts} Teilen hochladen ([u]) und zu jedem \"{question}\" fragen ({requests} Anfragen), oder weiter ([c])?",
+        Msg::AskFileTotal => "(alle {requests} Anfragen: {tokens} Tokens, {cost})",
+
+        Msg::AuditNotWritten => "Das Audit-Log {path} konnte nicht geschrieben werden: {error}",
+
+        Msg::CodeContextSummary => "Code-Kontext für {root}: {crates} in {files}, {shown} von {total} öffentlichen Elementen, {tokens} Tokens eines Budgets von {budget}",
+        Msg::CodeContextWhole => ", und {files} vollständig für {tokens} mehr ({all} insgesamt)",
+        Msg::CodeContextUnparsed => "{files} ausgelassen, die nicht gelesen werden konnten.",
+        Msg::OneCrate => "1 Crate",
+        Msg::Crates => "{count} Crates",
+        Msg::CargoFailedToRun => "cargo konnte nicht ausgeführt werden: {error}",
+        Msg::CargoWorkspaceUnreadable => "Der cargo-Workspace unter {path} konnte nicht gelesen werden: {error}",
+        Msg::CargoMetadataUnreadable => "Die cargo-Metadaten konnten nicht gelesen werden: {error}",
+
+        Msg::DoctorHeader => "gpt {version} auf {os}",
+
+        Msg::EmitFailing => "(--emit-socket: {problem}; Ereignisse werden verworfen, bis es wieder geht)",
+
+        Msg::EmitNoConnection => "keine Verbindung zu {path}: {error}",
+        Msg::EmitReaderBehind => "der Leser kommt nicht hinterher",
+        Msg::EmitNotWritten => "{path} konnte nicht beschrieben werden: {error}",
+
+        Msg::EndpointsOne => "Anfragen gehen nur an {base}. Trage weitere Basis-URLs in api_bases in der Konfiguration ein, um zwischen ihnen auszuweichen.",
+        Msg::EndpointsHeader => "Endpunkte, in dieser Reihenfolge versucht, solange sie erreichbar sind:",
+        Msg::EndpointDown => "noch {seconds}s nicht erreichbar",
+        Msg::EndpointFailing => "erreichbar, {requests} nacheinander fehlgeschlagen",
+        Msg::EndpointUp => "erreichbar",
+        Msg::EndpointNoRequests => "noch keine Anfragen",
+        Msg::OneRequest => "1 Anfrage",
+        Msg::Requests => "{count} Anfragen",
+        Msg::EndpointLatency => ", im Schnitt {seconds}s bis zur Antwort",
+        Msg::EndpointLastError => "letzter Fehler: {error}",
+
+        Msg::TextOnly => "nur Text",
+        Msg::LayerBundled => "eingebaut",
+        Msg::LayerCatalog => "Katalog des Anbieters",
+        Msg::LayerUser => "deine models.toml",
+        Msg::ModelsUnreadable => "{path} konnte nicht gelesen werden: {error}",
+        Msg::ModelsNeedsPrice => "Modell '{id}' ist noch unbekannt und braucht daher price und context_window",
+        Msg::ModelsShortcutNotId => "Kürzel '{short}' ist keine Modell-ID",
+        Msg::ModelsModelTables => "model sollte als [[model]] geschrieben werden",
+        Msg::ModelsShortcutsTable => "shortcuts sollte eine Tabelle sein",
+        Msg::ModelsUnknownKey => "unbekannter Schlüssel '{key}'",
+        Msg::ModelsEntryNotTable => "Modell {n} ist keine Tabelle",
+        Msg::ModelsEntryNoId => "Modell {n} hat keine id",
+        Msg::ModelsFieldProblem => "Modell '{id}': {key} {problem}",
+        Msg::ModelsPositiveTokens => "sollte eine positive Anzahl Tokens sein",
+        Msg::ModelsEntryUnknownKey => "Modell '{id}': unbekannter Schlüssel '{key}'",
+        Msg::ModelsPriceShape => "sollte [input, output] oder [input, cached input, output] in USD pro Million Tokens sein",
+        Msg::ModelsNegativePrice => "darf nicht negativ sein",
+        Msg::ModelsCapabilities => "sollte eine Liste aus {names} sein",
+        Msg::ModelsEncoding => "sollte eins von {names} sein",
+        Msg::ModelsFine => "{path} ist in Ordnung.",
+        Msg::ModelsLimits => "'{id}': default_output ≤ max_output ≤ context_window gilt nicht",
+        Msg::ModelsShortcutUnknown => "Kürzel '{short}' steht für '{id}', das unbekannt ist",
+
+        Msg::NotifyFinished => "{model} ist fertig",
+        Msg::NotifyFailed => "Die Desktop-Benachrichtigung konnte nicht angezeigt werden: {error}",
+
+        Msg::PickerOneRecent => "Das erste ist das zuletzt verwendete. ",
+        Msg::PickerRecent => "Die ersten {count} sind die zuletzt verwendeten. ",
+        Msg::PickerHowToSwitch => "* ist das aktuelle Modell. Mit :model <Modell> wechselst du.",
+        Msg::PickerLabel => "(Modell) ",
+        Msg::PickerOneModel => "  1 Modell",
+        Msg::PickerModels => "  {count} Modelle",
+        Msg::PickerOneRecentFirst => ", das zuletzt verwendete zuerst",
+        Msg::PickerRecentFirst => ", die {count} zuletzt verwendeten zuerst",
+        Msg::PickerNoMatch => "(kein Modell passt)",
+        Msg::PickerKeys => "Hoch und Runter bewegen, Enter wechselt, Esc bricht ab. * ist das aktuelle Modell.",
+
+        Msg::GrepUsage => "Verwendung: :grep [-s] [--all] <Muster>",
+        Msg::GrepSkipped => "Überspringe Sitzung '{name}': {error}",
+        Msg::GrepNoMatches => "Keine Treffer.",
+
+        Msg::IndexReferences => "Quellen:",
+
+        Msg::SummaryEmpty => "die Zusammenfassung kam leer zurück",
+
+        Msg::LockedByUs => "Sitzung '{name}' ist bereits von diesem gpt gesperrt.",
+        Msg::LockKeepsChanging => "Sitzung '{name}' konnte nicht gesperrt werden: {path} ändert sich ständig.",
+
+        Msg::WindowOverflow => "Prompt (~{prompt} Tok.) + max_tokens ({max_tokens}) übersteigt das Fenster von {model} mit {window}",
+        Msg::WindowLastTooLong => "Die letzte Nachricht allein passt nicht in das Fenster von {model}.",
+        Msg::WindowAskSwitch => "Zu {model} wechseln ([s]), ältere Nachrichten für diese Anfrage kürzen ([t]) oder abbrechen ([a])?",
+        Msg::WindowAskTrim => "Ältere Nachrichten für diese Anfrage kürzen ([t]) oder abbrechen ([a])?",
+        Msg::WindowSwapped => "Zu Modell {model} gewechselt.",
+        Msg::WindowAborted => "Anfrage abgebrochen.",
+
+        Msg::TokenizerMissing => "Warnung: der Tokenizer konnte nicht geladen werden ({error}); die Tokenzahlen sind grobe Schätzungen.",
+        Msg::DriftDescribe => "gesch. {estimate} / tats. {actual}, {percent}%",
+        Msg::BreakdownSystem => "System-Prompt",
+        Msg::BreakdownFiles => "Dateien",
+        Msg::BreakdownSeeded => "Seed-Nachrichten",
+        Msg::BreakdownContext => "Kontext",
+        Msg::BreakdownWrapper => "Präfix und Suffix",
+        Msg::BreakdownNewMessage => "Neue Nachricht",
+        Msg::BreakdownOverhead => "Overhead pro Nachricht",
+        Msg::BreakdownTotal => "Gesamt",
+
+        Msg::InvalidVariableName => "Ungültiger Variablenname '{name}'. Erlaubt sind Buchstaben, Ziffern, '_' und '-', beginnend mit einem Buchstaben.",
+
+        Msg::StageProjectFile => "{path} gehört zu den Dateien des Projekts, die mit jeder Anfrage im aktuellen Stand gesendet werden, und ist daher nie veraltet.",
+        Msg::StageUnchanged => "{path} ist bereits unverändert im Kontext.",
+        Msg::StageAskReplace => "Eine ältere Fassung von {path} ist im Kontext. Ersetzen ([r]), beide behalten ([k]) oder abbrechen ([c])?",
+        Msg::StageUpdated => "({path} im Kontext aktualisiert: vorher {old} Tokens, jetzt {tokens})",
+        Msg::StageAddedAgain => "({path} erneut zum Kontext hinzugefügt, {tokens} Tokens, die ältere Fassung bleibt)",
+        Msg::StageAdded => "({path} zum Kontext hinzugefügt, {tokens} Tokens)",
+
+        Msg::SearchNeedsTerminal => ":search braucht ein Terminal. Nutze stattdessen :grep.",
+        Msg::SearchNothing => "Noch nichts zu durchsuchen.",
+        Msg::SearchLabel => "(Suche) ",
+        Msg::SearchNoMatch => "(kein Treffer) ",
+        Msg::SearchEditLabel => "(Enter sendet, Esc bricht ab) ",
+
+        Msg::PluginNoSchema => "Werkzeug '{name}' hat kein brauchbares Schema: {error}",
+        Msg::PluginsNotAllowed => "Lade keine Werkzeuge, die in allow_tools fehlen: {tools}",
+        Msg::PluginSchemaTimeout => "--schema wurde nicht rechtzeitig fertig",
+        Msg::PluginSchemaFailed => "--schema endete mit {status}",
+
+        Msg::ProjectBadName => "{path}: '{name}' kann nicht als Projektname verwendet werden.",
+
+        Msg::UnknownOverride => "Unbekannte Vorgabe '{written}'. Nutze ein Modellkürzel wie @4o, eine Vorgabe wie @brief oder @<Einstellung>=<Wert> mit einer von: {settings}. Beginne die Nachricht mit \\@, um das @ unverändert zu senden.",
+
+        Msg::ViewSkipped => "{count} der Nachrichten in {path} konnten nicht gelesen werden und fehlen.",
+        Msg::ViewNoSessions => "Keine gespeicherten Sitzungen in {path}.",
+        Msg::ViewUntitled => "Sitzung ohne Titel",
+        Msg::ViewTokens => "{tokens} Tokens",
+        Msg::PagerFailed => "{pager} ist fehlgeschlagen: {error}",
+
+        Msg::ReqdiffMessages => "Nachrichten: {old} → {new}",
+        Msg::ReqdiffMore => "    … {count} weitere Zeilen",
+
+        Msg::LargeKept => "(die Nachricht wurde unverändert behalten: {error})",
+        Msg::LargeAsk => "Diese Nachricht hatte ~{tokens} Tokens, die jede weitere Anfrage in diesem Gespräch erneut sendet.\nBehalten ([k]), auf Anfang und Ende kürzen ([t]) oder zusammenfassen ([s])?",
+        Msg::LargeStoredOriginal => "(als ~{tokens} Tokens gespeichert; das Original liegt in {path})",
+        Msg::LargeStored => "(als ~{tokens} Tokens gespeichert)",
+        Msg::LargeSummarizing => "(fasse mit {model} zusammen)",
+
+        Msg::PrefixModelChanged => "das Modell hat sich geändert",
+        Msg::PrefixToolsChanged => "die Werkzeuge haben sich geändert",
+        Msg::PrefixSystemChanged => "der System-Prompt hat sich geändert",
+        Msg::PrefixMessageChanged => "Nachricht {index} der Anfrage hat sich geändert",
+        Msg::PrefixLost => "({what}, daher können nur die ersten {same} der {previous} Tokens der letzten Anfrage aus dem Prompt-Cache kommen)",
+
+        Msg::PresetModelDefault => "die Vorgabe des Modells",
+        Msg::PresetInstruction => "und \"{text}\" zum System-Prompt hinzugefügt",
+        Msg::PresetNoInstruction => "und keine Längenvorgabe",
+        Msg::PresetDescribe => "Vorgabe {name}: max_tokens {max_tokens}, temperature {temperature}, {instruction}.",
+
+        Msg::PromptStrayClose => "der Prompt hat ein '{close}' ohne '{open}'; schreibe '{close}{close}' für ein wörtliches",
+        Msg::PromptUnclosed => "der Prompt hat ein '{open}' ohne '{close}'",
+        Msg::PromptUnknownField => "der Prompt hat einen unbekannten Platzhalter {name}; möglich sind {known}",
+        Msg::PromptUnknownStyle => "der Prompt hat einen unbekannten Stil '{style}'; möglich sind {known}",
+
+        Msg::RouteLong => "der Prompt hat ~{tokens} Tokens, mehr als long_prompt_tokens ({limit})",
+        Msg::RouteCode => "die Nachricht enthält einen Codeblock",
+        Msg::RouteShort => "der Prompt ist kurz (~{tokens} Tokens) und enthält keinen Code",
+        Msg::RouteDeep => "{model} meint, es braucht gründliches Nachdenken",
+        Msg::RouteShallow => "{model} meint, es braucht kein gründliches Nachdenken",
+        Msg::RouteClassifierFailed => "die Einordnung ist fehlgeschlagen ({error})",
+        Msg::RouteClassifierSlow => "die Einordnung hat zu lange gedauert",
+
+        Msg::SafeShell => "Shell-Befehle ausführen",
+        Msg::SafeSubstitution => "$(Befehl) in Nachrichten ausführen",
+        Msg::SafeFilter => "Antworten durch einen Filter leiten",
+        Msg::SafeTools => "Werkzeuge aufrufen",
+        Msg::SafeWrite => "Dateien außerhalb des Datenverzeichnisses schreiben",
+        Msg::SafeClipboard => "In die Zwischenablage kopieren",
+        Msg::SafeProject => ".gpt-cli.toml laden",
+        Msg::SafeDisabled => "{feature} ist im abgesicherten Modus abgeschaltet",
+
+        Msg::FilterFailedToRun => "'{command}' konnte nicht ausgeführt werden: {error}",
+        Msg::FilterTimeout => "'{command}' wurde nicht innerhalb von {seconds}s fertig",
+        Msg::FilterExited => "'{command}' endete mit {status}",
+
+        Msg::ExpectBadPattern => "'{pattern}' ist kein gültiges Muster: {error}",
+        Msg::ExpectUnknown => "'{spec}' ist nichts, was erwartet werden kann. Möglich sind json, code, code:strict oder regex:<Muster>.",
+
+        Msg::CodeContextAsk => "Bereitstellen? Ja ([y]), nein ([n]) oder zuerst ansehen ([v])",
+        Msg::CodeContextLabel => "Code-Kontext für {root}",
+
+        Msg::InvalidDate => "'{value}' ist kein Datum wie 2024-06-01",
+
+        Msg::DirNotCreated => "{path} konnte nicht angelegt werden: {error}",
+
+        Msg::EmitUnixOnly => "Sockets und Named Pipes werden nur unter Unix unterstützt",
+
+        Msg::DoctorNoConfig => "{path} (existiert nicht, daher gelten die Vorgaben)",
+        Msg::DoctorKeyBoth => "{key} aus {var}, das Vorrang vor dem in [api_keys] hat",
+        Msg::DoctorKeyEnv => "{key} aus {var}",
+        Msg::DoctorKeyConfig => "{key} aus {provider} in [api_keys]",
+        Msg::DoctorNoKey => "keiner für {provider}: setze {var} oder {provider} in [api_keys]",
+        Msg::DoctorKeyShort => "… ({count} Zeichen)",
+        Msg::DoctorModel => "{model}, bereitgestellt von {provider}",
+        Msg::DoctorUnknownModel => "'{name}' ist kein bekanntes Modell",
+        Msg::DoctorNoParent => "{path} (nichts darüber existiert)",
+        Msg::DoctorWritable => "{path} (beschreibbar)",
+        Msg::DoctorCreatedLater => "{path} (wird bei Bedarf angelegt)",
+        Msg::DoctorNotWritable => "{path} (in {existing} kann nicht geschrieben werden: {error})",
+        Msg::DoctorModels => "{count} Modelle",
+        Msg::DoctorModelsChanged => ", {count} aus {path}",
+        Msg::DoctorModelsProblems => ", {count} Probleme übergangen (siehe gpt models validate)",
+        Msg::DoctorNone => "keiner",
+        Msg::DoctorAnswered => "{what} antwortete in {took}",
+        Msg::DoctorKeyRejected => "{base} lehnte den Schlüssel ab ({code}), in {took}",
+        Msg::DoctorListing => "{base} antwortete {code} auf die Modellliste, in {took}",
+        Msg::DoctorUnreachable => "{base} nicht erreichbar: {error}",
+        Msg::DoctorSkippedModel => "übersprungen: das Modell ist unbekannt",
+        Msg::DoctorSkippedKey => "übersprungen: es gibt keinen API-Schlüssel",
+        Msg::DoctorTty => "Terminal",
+        Msg::DoctorNotTty => "kein Terminal",
+        Msg::DoctorOn => "an",
+        Msg::DoctorOff => "aus",
+        Msg::DoctorTerminal => "stdin {stdin}, stdout {stdout}, stderr {stderr}, Farbe {color}",
+        Msg::DoctorColumns => ", {width} Spalten",
+
+        Msg::FilterTruncated => "[Ausgabe gekürzt]",
+
+        Msg::TokenizerDisabled => "GPT_CLI_NO_TOKENIZER ist gesetzt",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::COMMANDS;
+    use std::{
+        collections::BTreeSet,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    // The source of this file, to go through every message without listing them all again.
+    const SOURCE: &str = include_str!("msg.rs");
+
+    // Each message's text in each language, as `Msg::Name => "text"` in en and de.
+    fn arms(function: &str) -> Vec<(&'static str, String)> {
+        let start = SOURCE.find(&format!("\nfn {function}(msg: Msg)")).unwrap();
+        let body = &SOURCE[start..];
+        let body = &body[..body.find("\n}\n").unwrap()];
+        body.split("Msg::")
+            .skip(2)
+            .filter_map(|arm| {
+                let (name, rest) = arm.split_once(" => ")?;
+                Some((name, literal(rest)?))
+            })
+            .collect()
+    }
+
+    // The first string literal in `code`, with its escapes left as written.
+    fn literal(code: &str) -> Option<String> {
+        let start = code.find('"')? + 1;
+        let mut end = start;
+        let bytes = code.as_bytes();
+        while bytes[end] != b'"' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        Some(code[start..end].to_string())
+    }
+
+    fn placeholders(template: &str) -> BTreeSet<&str> {
+        template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}'))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn every_language_has_every_message() {
+        let en = arms("en");
+        assert!(en.len() > 200, "only found {} messages", en.len());
+        let names = |arms: &[(&'static str, String)]| -> Vec<&'static str> {
+            arms.iter().map(|(name, _)| *name).collect()
+        };
+        assert_eq!(names(&arms("de")), names(&en));
+        for (name, text) in &en {
+            assert!(!text.trim().is_empty(), "Msg::{name} is empty");
+        }
+    }
+
+    #[test]
+    fn translations_keep_every_placeholder() {
+        let en = arms("en");
+        for (name, translated) in arms("de") {
+            let (_, english) = en.iter().find(|(n, _)| *n == name).unwrap();
+            assert_eq!(
+                placeholders(&translated),
+                placeholders(english),
+                "Msg::{name} in de"
+            );
+        }
+    }
+
+    #[test]
+    fn every_command_has_help_in_every_language() {
+        for lang in Lang::ALL {
+            for spec in COMMANDS {
+                let help = help::find(lang, spec.name)
+                    .unwrap_or_else(|| panic!(":{} has no help in {}", spec.name, lang.code()));
+                assert!(!help.usage.is_empty() && !help.details.is_empty());
+                assert!(help.example.starts_with(':'), ":{}", spec.name);
+            }
+            for (name, _) in help::table(lang) {
+                assert!(
+                    COMMANDS.iter().any(|spec| spec.name == *name),
+                    "help in {} for :{name}, which isn't a command",
+                    lang.code()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn help_text_keeps_what_is_typed() {
+        // Only the words around them are translated: commands and flags stay as they are.
+        for (name, english) in help::table(Lang::En) {
+            let german = help::find(Lang::De, name).unwrap();
+            let typed = |text: &'static str| -> BTreeSet<&'static str> {
+                text.split_whitespace()
+                    .filter(|word| word.starts_with("--") || word.starts_with(':'))
+                    .map(|word| word.trim_end_matches([',', '.', ';', ')']))
+                    .collect()
+            };
+            assert_eq!(typed(german.usage), typed(english.usage), ":{name}");
+        }
+    }
+
+    /// Every `.rs` file under `dir` but the catalog itself, with its path.
+    fn sources(dir: &Path, out: &mut Vec<(PathBuf, String)>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_stem().is_some_and(|stem| stem == "msg") {
+                continue;
+            }
+            if path.is_dir() {
+                sources(&path, out);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                out.push((path.clone(), fs::read_to_string(&path).unwrap()));
+            }
+        }
+    }
+
+    /// Where a literal shown to the user starts, after `code` at one of the places text is printed
+    /// or made into an error.
+    fn shown(code: &str) -> Option<&str> {
+        for mac in ["println!(", "eprintln!(", "print!(", "eprint!("] {
+            if let Some(args) = code.strip_prefix(mac) {
+                return Some(args.trim_start());
+            }
+        }
+        for mac in ["writeln!(", "write!("] {
+            if let Some(args) = code.strip_prefix(mac) {
+                let (_, rest) = args.split_once(',')?;
+                return Some(rest.trim_start());
+            }
+        }
+        let rest = code.strip_prefix("AppError::")?;
+        let rest = rest.trim_start_matches(|c: char| c.is_alphanumeric());
+        let rest = rest.strip_prefix('(')?;
+        Some(rest.strip_prefix("format!(").unwrap_or(rest))
+    }
+
+    // Messages are printed through the catalog, so nothing shown anywhere in src/ is a literal.
+    #[test]
+    fn notices_come_from_the_catalog() {
+        let mut files = Vec::new();
+        sources(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut files,
+        );
+        assert!(files.len() > 50, "only found {} files", files.len());
+        for (file, source) in &files {
+            let source = &source[..source.find("#[cfg(test)]").unwrap_or(source.len())];
+            for (at, _) in source.match_indices(['p', 'e', 'w', 'A']) {
+                let Some(args) = shown(&source[at..]).filter(|args| args.starts_with('"')) else {
+                    continue;
+                };
+                // Part of a longer name, like `AppError::` in `MyAppError::`.
+                if source[..at].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let text = literal(args).unwrap();
+                // Placeholders aside, a setting shown the way :get shows it, and CSV headers.
+                let words: String = text
+                    .split('{')
+                    .map(|part| part.split_once('}').map_or(part, |(_, rest)| rest))
+                    .collect();
+                let setting = words
+                    .strip_suffix(" = ")
+                    .is_some_and(|name| name.chars().all(|c| c.is_ascii_lowercase() || c == '_'));
+                let header = text.contains(',') && !text.contains(' ');
+                let wordy = words
+                    .split(|c: char| !c.is_alphabetic())
+                    .any(|word| word.chars().count() > 2);
+                assert!(
+                    !wordy || setting || header,
+                    "{}: \"{text}\"",
+                    file.display()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,1079 @@
+//! What `:help <command>` shows about a command, in each language. Kept apart from the rest of
+//! the catalog since it's most of it. A table per language, by command name, rather than a `match`
+//! over [`Msg`](super::Msg): the commands are in [`COMMANDS`](crate::commands::COMMANDS), and the
+//! tests check every one of them is here in every language.
+
+use super::Lang;
+
+pub struct Help {
+    pub usage: &'static str,
+    pub details: &'static str,
+    pub example: &'static str,
+}
+
+// What a command without help shows, which the tests make sure is never needed.
+pub(super) static NONE: Help = Help {
+    usage: "",
+    details: "",
+    example: "",
+};
+
+pub(super) fn table(lang: Lang) -> &'static [(&'static str, Help)] {
+    match lang {
+        Lang::En => EN,
+        Lang::De => DE,
+    }
+}
+
+pub(super) fn find(lang: Lang, command: &str) -> Option<&'static Help> {
+    table(lang)
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, help)| help)
+}
+
+const EN: &[(&str, Help)] = &[
+    (
+        "quit",
+        Help {
+            usage: ":quit",
+            details: "Exits after running the normal shutdown steps.",
+            example: ":q",
+        },
+    ),
+    (
+        "context",
+        Help {
+            usage: ":context",
+            details: "When off (the default), each message starts a new conversation unless it is prefixed with '|'.\nWhen on, every message is sent along with the conversation so far.",
+            example: ":c",
+        },
+    ),
+    (
+        "clear",
+        Help {
+            usage: ":clear",
+            details: "Removes every message from the context. The cleared messages can be brought back with :restore.",
+            example: ":clear",
+        },
+    ),
+    (
+        "new",
+        Help {
+            usage: ":new",
+            details: "Clears the context like :clear and also resets the session statistics shown by :stats.",
+            example: ":new",
+        },
+    ),
+    (
+        "seed",
+        Help {
+            usage: ":seed <path> | :seed export <path>",
+            details: "A seed has a `system` prompt, `messages` to start from as [[messages]] with a role and content, a `model`, a `temperature`\nand `files`, globs from the seed's directory that are staged like :file. Loading one starts a new conversation, as `--seed-file` does,\nand the system prompt and messages go ahead of the context in every request until :new.\nexport writes the current model and temperature, the system prompt and messages of the seed the conversation started from\nand the staged files, but not the conversation itself.",
+            example: ":seed review.gptseed.toml",
+        },
+    ),
+    (
+        "restore",
+        Help {
+            usage: ":restore",
+            details: "Puts back the messages dropped by the last :clear, :new, or message sent without '|'.\nThey go before any messages sent since, so the conversation stays in order. Only the most recent drop is kept.",
+            example: ":restore",
+        },
+    ),
+    (
+        "stats",
+        Help {
+            usage: ":stats",
+            details: "Shows the number of requests, prompt tokens (and how many were cached), completion tokens, the total price and how much prompt caching saved.\nWith a conversation_budget set, also what's left of it: of the session's spending, or with budget_scope = \"context\" of the answers in the context.",
+            example: ":stats",
+        },
+    ),
+    (
+        "endpoints",
+        Help {
+            usage: ":endpoints",
+            details: "With several base URLs in api_bases, requests go to the first that's up. One that can't be reached, or answers with a 5xx twice in a row, is passed over for a minute.\nShows each in the order they're tried, whether it's up, how many requests it answered and how long it took to start answering on average, and the last error of one that's failing.",
+            example: ":endpoints",
+        },
+    ),
+    (
+        "summary",
+        Help {
+            usage: ":summary [--llm]",
+            details: "Shows the number of exchanges, prompt and completion tokens, the cost, each model's share, how long answers took, the longest message and how long the conversation has gone on. It's all worked out from what the messages recorded, without a request.\nWith --llm, the provider's cheap title model also writes a 3-bullet recap; what it cost is shown under Recaps in :stats.",
+            example: ":summary --llm",
+        },
+    ),
+    (
+        "debug",
+        Help {
+            usage: ":debug",
+            details: "Prints the model, max tokens and the state of the client-side rate limiter.",
+            example: ":debug",
+        },
+    ),
+    (
+        "diff-request",
+        Help {
+            usage: ":diff-request",
+            details: "Compares the last request sent with the one before it: parameters that changed, like the model or max tokens, then the messages. Kept messages are marked =, removed ones -, added ones + and edited ones ~ with a line diff.\nUnchanged messages are cut to their first line. Answers from the cache weren't sent, so they don't count.",
+            example: ":diff-request",
+        },
+    ),
+    (
+        "curl",
+        Help {
+            usage: ":curl [on | off | dry]",
+            details: "The command sends the request the way gpt did, to the same URL with the same headers and body, so it can be run against the raw API or attached to a bug report. The API key is left as its environment variable, like $OPENAI_API_KEY.\n:curl on prints every request like this before it's sent, as --print-curl does, and :curl off stops. :curl dry prints each message's request instead of sending it, as --dry-run does: nothing is sent, and the conversation is left as it was.",
+            example: ":curl",
+        },
+    ),
+    (
+        "help",
+        Help {
+            usage: ":help [command]",
+            details: "Without arguments, lists every command. With a command name (or alias), shows its syntax and an example.",
+            example: ":help model",
+        },
+    ),
+    (
+        "show",
+        Help {
+            usage: ":show [index]",
+            details: "Without an index, lists each message with its index and first line. The indices are the ones printed by :grep.",
+            example: ":show 3",
+        },
+    ),
+    (
+        "quote",
+        Help {
+            usage: ":quote <paragraph> | <from>-<to> | /regex/ | off",
+            details: "Picks a paragraph of the last answer by number, a range of its lines, or the first paragraph matching a regular expression,\nshows it, and puts it before your next message as a > quote, so the message only needs the question about it.\nA fenced code block counts as one paragraph. Without arguments, shows the quote waiting to be sent; off drops it.",
+            example: ":quote 2",
+        },
+    ),
+    (
+        "grep",
+        Help {
+            usage: ":grep [-s] [--all] <pattern>",
+            details: "The pattern is a regular expression, matched case-insensitively unless -s is given.\nWith --all, saved sessions are searched too and matches are prefixed with the session name.",
+            example: ":grep --all lifetime",
+        },
+    ),
+    (
+        "draft",
+        Help {
+            usage: ":draft [show | clear]",
+            details: "Lines typed while drafting are kept, as typed, until :send sends them as one message, so hitting Enter halfway through doesn't send half of it. Lines starting with : are still commands, and the prompt is …> until the draft is sent.\nThe draft is saved with every line, and the next gpt picks up one that wasn't sent, even after a crash.\n:draft show prints the draft, and :draft clear drops it.",
+            example: ":draft",
+        },
+    ),
+    (
+        "send",
+        Help {
+            usage: ":send",
+            details: "The draft goes as one message, like one typed on a single line: a | at its start keeps the context.",
+            example: ":send",
+        },
+    ),
+    (
+        "search",
+        Help {
+            usage: ":search",
+            details: "Like Ctrl+R in a shell: each key narrows the search, newest match first, and Ctrl+R or Up goes to an older match (Down to a newer one). Matching ignores case.\nEnter takes the prompt that matched, or the one an answer that matched was for, to be edited and then sent with another Enter. Esc or Ctrl+C cancels either step.",
+            example: ":search",
+        },
+    ),
+    (
+        "save",
+        Help {
+            usage: ":save [name]",
+            details: "Sessions are stored as JSON in the data directory (~/.local/share/gpt-cli/sessions/). Saving over an existing name replaces it.\nWithout a name, the session's title is used (or the current time if it has none yet).",
+            example: ":save borrowck",
+        },
+    ),
+    (
+        "load",
+        Help {
+            usage: ":load [--read-only | --fork <new name>] <name>",
+            details: "Also switches to the model the session was saved with. Use :sessions to see what's saved. A session open in another gpt is locked: --read-only loads it without attaching to it, so it's never saved over, and --fork saves a copy under a new name and carries on with that. A lock left by a gpt that's no longer running is taken over.",
+            example: ":load borrowck",
+        },
+    ),
+    (
+        "sessions",
+        Help {
+            usage: ":sessions",
+            details: "Lists the name and title of every saved session. In a project, that's the project's sessions; start with --global to see the rest.",
+            example: ":sessions",
+        },
+    ),
+    (
+        "project",
+        Help {
+            usage: ":project",
+            details: "The nearest .gpt-cli.toml in the working directory or above it is loaded at startup, unless gpt was started with --global.\nIt can set `system`, a system prompt sent with every request, `files` to send with it (read again for each request),\n`model` to start with, and `name`, which sessions are saved under. Without a name, it's the directory's name and a hash of its path.",
+            example: ":project",
+        },
+    ),
+    (
+        "title",
+        Help {
+            usage: ":title [text]",
+            details: "Sessions are named automatically after a couple of exchanges (see the `title_after_exchanges` setting).\nSetting a title yourself replaces that, and it's used as the default name for :save.",
+            example: ":title Debugging the borrow checker",
+        },
+    ),
+    (
+        "send-only",
+        Help {
+            usage: ":send-only <indices> | off",
+            details: "Indices are the ones printed by :show, separated by commas, with ranges like 2-5, e.g. :send-only 2,7-9.\nThe next message is sent with just those (and the project's system prompt), while the whole context is kept\nand grows as usual. It applies to one message; without arguments, shows the selection waiting to be used.",
+            example: ":send-only 2,7,9",
+        },
+    ),
+    (
+        "wrap-prompt",
+        Help {
+            usage: ":wrap-prompt [on | off]",
+            details: "`prompt_prefix` and `prompt_suffix` in the config, or in .gpt-cli.toml, are put before and after every message you type, each as a paragraph of its own, when it's sent.\nThey're counted against the window but not shown as part of the message; :show marks the messages sent with them.\nOff lasts for the session and only affects new messages: ones already sent keep theirs. Without arguments, shows the wrapper.",
+            example: ":wrap-prompt off",
+        },
+    ),
+    (
+        "note",
+        Help {
+            usage: ":note [<index> [<text>] | unset <index>]",
+            details: "Notes are for you: they show in :show and are saved with the session, but are never sent to the model or counted.\nThe indices are the ones printed by :show. A new note on a message replaces its old one. With just an index, shows that message's note;\nwithout arguments, lists every note.",
+            example: ":note 3 this answer was wrong, see the docs",
+        },
+    ),
+    (
+        "bookmark",
+        Help {
+            usage: ":bookmark [<index>] [<label>]",
+            details: "Without an index, bookmarks the last answer. The message is copied into the bookmarks file, so deleting the session\nlater leaves the bookmark alone. :bookmarks lists them; gpt bookmarks does the same from the shell.",
+            example: ":bookmark 3 the borrow checker explanation",
+        },
+    ),
+    (
+        "bookmarks",
+        Help {
+            usage: ":bookmarks [show|copy|delete <number>]",
+            details: "The list shows each bookmark's number, when it was made, its label (or the start of the message) and where it's from.\nshow prints the message, copy puts it on the clipboard. Numbers never change, even when others are deleted.",
+            example: ":bookmarks show 3",
+        },
+    ),
+    (
+        "var",
+        Help {
+            usage: ":var [name [value] | unset <name>]",
+            details: "Prompts containing {{name}} have it replaced by the variable's value before sending.\nWithout arguments, lists the variables. Undefined variables are sent as written, with a warning.\nWrite {{{{ for a literal {{. Variables in the [vars] table of the config file are set at startup.",
+            example: ":var lang rust",
+        },
+    ),
+    (
+        "subst",
+        Help {
+            usage: ":subst <message>",
+            details: "${NAME} becomes the environment variable and $(command) the command's output.\nIf a variable is missing or a command fails, nothing is sent.\nThe `substitution` setting expands every message this way; it's off by default because pasted text could run commands.",
+            example: ":subst Summarise the changes on $(git branch --show-current)",
+        },
+    ),
+    (
+        "alias",
+        Help {
+            usage: ":alias [name [expansion]]",
+            details: "`:name rest` is replaced by the expansion followed by `rest`, before anything else.\nAn expansion starting with ':' runs a command, anything else is sent as a message.\nAliases never expand inside other aliases, and can't reuse a built-in command's name.\nWithout arguments, lists the aliases. The [aliases] table of the config file defines them at startup.",
+            example: ":alias de \"translate to German: \"",
+        },
+    ),
+    (
+        "unalias",
+        Help {
+            usage: ":unalias <name>",
+            details: "Only lasts for this session; aliases from the config file come back next time.",
+            example: ":unalias de",
+        },
+    ),
+    (
+        "tools",
+        Help {
+            usage: ":tools [enable|disable <tool>]",
+            details: "Tools come from the servers in the [mcp_servers] table of the config file, started (or connected to, for those with a url) when the program starts,\nand from executables in the tools directory (see :paths) that are listed in `allow_tools`.\nThe model can call any enabled tool while answering. All of them start enabled.",
+            example: ":tools disable fetch",
+        },
+    ),
+    (
+        "model",
+        Help {
+            usage: ":model [3|4|4t|4o|4o-mini|auto|model id]",
+            details: "On its own, opens a list of the known models and the ones used recently, with their context windows, prices per million tokens and what they take besides text. Typing filters it, Up and Down move, Enter switches and Esc cancels. Without a terminal the list is only printed.\nArguments:\n  3        gpt-3.5-turbo (default)\n  4        gpt-4\n  4t       gpt-4-turbo\n  4o       gpt-4o\n  4o-mini  gpt-4o-mini\n  auto     picks a cheap or an expensive model for each message, see :why\nFull model ids from the pricing table are accepted too.",
+            example: ":model 4o",
+        },
+    ),
+    (
+        "why",
+        Help {
+            usage: ":why",
+            details: "With :model auto, each message goes to the cheap or the expensive model from the [routing] table of the config.\nLong prompts and messages with code blocks go to the expensive one. With classify = true, the classifier model is asked about the rest.",
+            example: ":why",
+        },
+    ),
+    (
+        "models",
+        Help {
+            usage: ":models [<filter> | info <model> | refresh]",
+            details: "Shows each model's price per million tokens and its context window.\nWith provider = \"openrouter\" the list is OpenRouter's catalog, fetched at startup.\nAny id with a slash can be used with :model, listed or not.\nA models.toml next to the config file changes or adds models on top of the built-in ones; info shows a model's metadata and where each field came from, and refresh reads the file again.",
+            example: ":models claude",
+        },
+    ),
+    (
+        "provider",
+        Help {
+            usage: ":provider [openai|openrouter|anthropic|gemini]",
+            details: "Claude models always go to Anthropic and OpenAI's models to OpenAI, except ids with a slash, which are for OpenRouter.\nThe provider serves every other model. Each provider reads its key from its own variable:\nOPENAI_API_KEY, OPENROUTER_API_KEY or ANTHROPIC_API_KEY.",
+            example: ":provider anthropic",
+        },
+    ),
+    (
+        "org",
+        Help {
+            usage: ":org [<id>|default]",
+            details: "Starts as OPENAI_ORG_ID or `organization` in the config file; `project` or OPENAI_PROJECT_ID sets the project.\ndefault goes back to the key's own organization. Only requests to OpenAI are affected.",
+            example: ":org org-abc123",
+        },
+    ),
+    (
+        "max",
+        Help {
+            usage: ":max <number|auto|max>",
+            details: "auto (the default) follows the model: each model has its own default output limit.\nmax uses everything the model can produce in one response.\nThe `max_tokens` config setting fixes it for every model.",
+            example: ":max 2000",
+        },
+    ),
+    (
+        "brief",
+        Help {
+            usage: ":brief",
+            details: "Sets max_tokens, the temperature and an instruction added to the system prompt at once.\n:normal and :detailed switch to the others, replacing the instruction rather than adding to it.\nThe [presets.brief] table of the config changes what it sets, e.g. max_tokens = 200.",
+            example: ":brief",
+        },
+    ),
+    (
+        "normal",
+        Help {
+            usage: ":normal",
+            details: "Undoes :brief or :detailed. The [presets.normal] table of the config can set its own max_tokens, temperature and instruction.",
+            example: ":normal",
+        },
+    ),
+    (
+        "detailed",
+        Help {
+            usage: ":detailed",
+            details: "Sets max_tokens to 4096 and the model's own temperature, and removes the instruction :brief adds.\nThe [presets.detailed] table of the config changes what it sets, including an instruction of its own.",
+            example: ":detailed",
+        },
+    ),
+    (
+        "tokens",
+        Help {
+            usage: ":tokens [--detail] [<message>]",
+            details: "Counts what would be sent with the next message: the system prompt, the project's files, the context, prompt prefixes and suffixes, the message itself and the few tokens each message costs on top.\nWith a message that doesn't start with | (and :context off), the context isn't counted, as it would be dropped.\n--detail shows each part with its share of the total, and the 5 biggest messages of the context.",
+            example: ":tokens --detail |and what about lifetimes?",
+        },
+    ),
+    (
+        "get",
+        Help {
+            usage: ":get [setting]",
+            details: "Without a name, lists every setting with its current value.",
+            example: ":get max_tokens",
+        },
+    ),
+    (
+        "set",
+        Help {
+            usage: ":set <setting> <value>",
+            details: "Changes only last until the program exits. Use :get to see the settings that can be changed.",
+            example: ":set footer minimal",
+        },
+    ),
+    (
+        "cache",
+        Help {
+            usage: ":cache <on|off|clear>",
+            details: "When on, identical requests are answered from ~/.cache/gpt-cli/ without calling the API.\nDefaults to the `cache` config setting (off unless set).",
+            example: ":cache clear",
+        },
+    ),
+    (
+        "paths",
+        Help {
+            usage: ":paths",
+            details: "The config file comes from --config, GPT_CLI_CONFIG or the platform's config directory.\n`data_dir` and `cache_dir` in the config file move the rest.",
+            example: ":paths",
+        },
+    ),
+    (
+        "theme",
+        Help {
+            usage: ":theme [dark|light|plain]",
+            details: "dark (the default) and light pick colours that read well on that background; plain turns styling off.\nThe prompt itself comes from `prompt` in the config file, e.g. prompt = \"{model|accent}{session|dim:+ [{session}]} {context_tokens|dim}> \".\nPlaceholders: model, session, title, context_tokens and cost. {name:+text} shows text only when name isn't empty, and\n{name|bold,cyan} styles it: bold, dim, italic, underline, red, green, yellow, blue, magenta, cyan or accent (the theme's colour).\nWith NO_COLOR or when output isn't a terminal, everything is plain text.",
+            example: ":theme light",
+        },
+    ),
+    (
+        "math",
+        Help {
+            usage: ":math [off|unicode]",
+            details: "unicode rewrites math like \\(x^2 + \\frac{1}{2}\\) as x² + ½ where it can: Greek letters, super- and subscripts,\nfractions as a/b, roots and common operators. Only what's shown changes; the context, cache, :tee files and\nsaved sessions keep what the model wrote. Code blocks are left alone. off (the default) shows math as written.",
+            example: ":math unicode",
+        },
+    ),
+    (
+        "translate",
+        Help {
+            usage: ":translate [--swap] [lang|src:lang] <text>",
+            details: "The language is a code like de or a name like german, and is remembered, so `:tr <text>` uses the last one.\nsrc:lang also fixes the source language. --swap flips the pair; without text it translates the last translation back.\nThe model comes from `model` in the [translate] table of the config (gpt-4o-mini by default). The context and model are left alone.",
+            example: ":tr de good morning",
+        },
+    ),
+    (
+        "duo",
+        Help {
+            usage: ":duo <model a> <model b> [--system-a <prompt>] [--system-b <prompt>] [--turns <n>] [--stop <phrase>] <first message>",
+            details: "A answers the first message, then B answers A, and so on, each with its own system prompt. --turns counts the answers\nof both sides (6 by default); it stops sooner when an answer contains the --stop phrase. Both come from the [duo] table of\nthe config if not given. The project's prompt, the seed and the preset's instruction are the conversation's, so neither side is sent them.\nThe conversation is left alone, and the transcript is saved as a session named duo-<time>.",
+            example: ":duo gpt-4o gpt-4o-mini --system-a \"You are the interviewer.\" --system-b \"You are the candidate.\" Let's begin.",
+        },
+    ),
+    (
+        "compare",
+        Help {
+            usage: ":compare <model>,<model>[,...] <question>",
+            details: "The question goes to every model at once, with the project's system prompt but not the conversation, and the answers are\nshown in the order the models were given, each with what it cost. They stay out of the context. `max_in_flight` in the\nconfig caps how many are asked at a time, and Ctrl+C stops them all.",
+            example: ":compare 4o,4o-mini,claude-3-5-sonnet What's the difference between a process and a thread?",
+        },
+    ),
+    (
+        "expect",
+        Help {
+            usage: ":expect [json|code|code:strict|regex:<pattern>|off]",
+            details: "json needs the whole answer to parse as JSON. code needs at least one fenced code block, and code:strict nothing outside them.\nregex:<pattern> needs the answer to match somewhere. A mismatch prints a warning, and one-shot mode (gpt --expect json ...) exits with 5.\nWith expect_retry = true in the config, the model is told what was wrong and asked once more. Without an argument, shows the current one.",
+            example: ":expect json",
+        },
+    ),
+    (
+        "filter",
+        Help {
+            usage: ":filter [--tee] <command> | :filter off",
+            details: "The raw response goes to the command's stdin, and what it prints is shown instead; with --tee, after the response streams as usual.\nThe context keeps the raw response. If the command fails or takes over 10s, the raw response is shown with a warning.\n`response_filter` in the config file sets one at startup, e.g. response_filter = \"glow -\".",
+            example: ":filter glow -",
+        },
+    ),
+    (
+        "tee",
+        Help {
+            usage: ":tee <path|off>",
+            details: "Appends the raw text of each response to the file until `:tee off`.\nA response only lands in the file once it has finished streaming, and the footer is never included.",
+            example: ":tee answers.md",
+        },
+    ),
+    (
+        "stream-to",
+        Help {
+            usage: ":stream-to <path> [--quiet] | :stream-to off",
+            details: "The file is replaced, and every part of the response is written to it as it arrives, so `tail -f` or an editor that reloads files shows it growing.\nWith --quiet the response goes only to the file, and only the notices and footer are shown.\nOnce the response is complete, a comment at the end names the model, tokens and price, in the comment syntax the extension suggests (none for .json).\nOne that fails, or is stopped or cancelled, ends with a comment marking it INCOMPLETE instead.",
+            example: ":stream-to src/generated.rs --quiet",
+        },
+    ),
+    (
+        "ask",
+        Help {
+            usage: ":ask <question>",
+            details: "The chunks of the indexed files closest to the question are sent along with it, labelled with file and lines.\nThe model is asked to cite them, and the ones it cites are listed after the answer.\nThe index is built with `gpt index <dir>`; see the [index] table of the config for its location, chunk size and number of chunks.",
+            example: ":ask how are sessions saved?",
+        },
+    ),
+    (
+        "cmd",
+        Help {
+            usage: ":cmd [--execute] [--explain] <task or command>",
+            details: "Shows a single command for your shell (from $SHELL) with a one-line explanation, and copies it to the clipboard.\nWith --explain, the text is a command to explain instead. With --execute, the command runs once you answer y;\none the model flags as dangerous needs yes typed in full. The command gets no input. gpt cmd does the same from the shell.",
+            example: ":cmd find files modified in the last day and tar them",
+        },
+    ),
+    (
+        "fix",
+        Help {
+            usage: ":fix [--no-rerun <output>]",
+            details: "Finds your last command in the history file of your shell (bash, zsh or fish, from $SHELL; shell_history in the config\npoints elsewhere) and, once you answer y, runs it again to see what it prints. With --no-rerun, the output you paste is sent\ninstead. The suggested fix is offered to run like :cmd --execute, so one the model flags as dangerous needs yes typed in full.\nbash only writes its history when the shell exits, unless PROMPT_COMMAND runs history -a.",
+            example: ":fix",
+        },
+    ),
+    (
+        "file",
+        Help {
+            usage: ":file <path>",
+            details: "The file goes in the context as a message of its own, fenced and labelled with its path, and stays there for the next message even if that one starts a new conversation.\nStaging a file already in the context replaces the older copy in place, or with restage_files = \"keep-both\" or \"ask\" keeps both or asks. An unchanged file isn't added again, and the project's own files are refused since they're always sent.\nA file that looks like it holds secrets, like .env or an SSH key, is refused unless the path starts with !.",
+            example: ":file Cargo.toml",
+        },
+    ),
+    (
+        "code-context",
+        Help {
+            usage: ":code-context [--full <path>...]",
+            details: "The outline has the crates and their dependencies, the source files git doesn't ignore, and the signatures of their public items with the bodies left out, all as one message staged like :file's.\nIt's kept under code_context_tokens (8000 by default). When the signatures don't all fit, every file gets its first items before any gets more, so the whole workspace shows.\n--full adds the files after it whole, on top of that. The token count is shown before it's staged, and it can be viewed first. Running it again replaces the copy in the context.",
+            example: ":code-context --full src/state.rs",
+        },
+    ),
+    (
+        "ask-file",
+        Help {
+            usage: ":ask-file <path> <question>",
+            details: "A file too big for the model's window is split into overlapping chunks, ending at blank lines where possible.\nThe question is asked of each chunk, then one more request combines the answers; only that one is shown as it streams.\nProgress goes to stderr, and the footer is followed by the total over every request. The context is left alone.\nA file that looks like it holds secrets, like .env or an SSH key, is refused unless the path starts with !.",
+            example: ":ask-file server.log \"why did the 14:02 deploy fail?\"",
+        },
+    ),
+    (
+        "script",
+        Help {
+            usage: ":script <path>",
+            details: "Each line is a command or a message, run in order exactly as if typed: | prefixes and aliases work, and the context carries on from one line to the next. Blank lines and lines starting with # are skipped.\nA line that fails stops the script, naming the file and line number. gpt --script <path> does the same at startup, and --script-exit then ends the session instead of starting the prompt.",
+            example: ":script demo.txt",
+        },
+    ),
+    (
+        "watch",
+        Help {
+            usage: ":watch [--max-iterations <n>] [--budget <amount>] <path> <prompt>",
+            details: "Sends the file's contents with the prompt now and after every change, each as a new conversation, with a timestamp above each answer.\nType q or press Ctrl+C to go back to the prompt. Watching stops by itself after 20 requests, or --max-iterations, or once it has cost --budget, in the currency prices are shown in.\nThe context is left alone, and the cost counts towards :stats. As with :ask-file, a path starting with ! sends a file that looks like it holds secrets.",
+            example: ":watch src/lib.rs \"review this file for bugs\"",
+        },
+    ),
+];
+
+const DE: &[(&str, Help)] = &[
+    (
+        "quit",
+        Help {
+            usage: ":quit",
+            details: "Beendet das Programm, nachdem die üblichen Schritte beim Beenden gelaufen sind.",
+            example: ":q",
+        },
+    ),
+    (
+        "context",
+        Help {
+            usage: ":context",
+            details: "Aus (die Voreinstellung): Jede Nachricht beginnt ein neues Gespräch, außer sie beginnt mit '|'.\nEin: Jede Nachricht wird zusammen mit dem bisherigen Gespräch gesendet.",
+            example: ":c",
+        },
+    ),
+    (
+        "clear",
+        Help {
+            usage: ":clear",
+            details: "Entfernt alle Nachrichten aus dem Kontext. :restore holt die entfernten Nachrichten zurück.",
+            example: ":clear",
+        },
+    ),
+    (
+        "new",
+        Help {
+            usage: ":new",
+            details: "Leert den Kontext wie :clear und setzt außerdem die Statistik der Sitzung zurück, die :stats zeigt.",
+            example: ":new",
+        },
+    ),
+    (
+        "seed",
+        Help {
+            usage: ":seed <Pfad> | :seed export <Pfad>",
+            details: "Ein Seed hat einen `system`-Prompt, `messages` als Anfang des Gesprächs, als [[messages]] mit role und content, ein `model`, eine `temperature`\nund `files`, Globs relativ zum Verzeichnis des Seeds, die wie mit :file bereitgelegt werden. Das Laden beginnt ein neues Gespräch, wie `--seed-file`,\nund System-Prompt und Nachrichten gehen bis :new in jeder Anfrage dem Kontext voraus.\nexport schreibt das aktuelle Modell und die Temperatur, System-Prompt und Nachrichten des Seeds, mit dem das Gespräch begann,\nund die bereitgelegten Dateien, aber nicht das Gespräch selbst.",
+            example: ":seed review.gptseed.toml",
+        },
+    ),
+    (
+        "restore",
+        Help {
+            usage: ":restore",
+            details: "Holt die Nachrichten zurück, die das letzte :clear, :new oder eine Nachricht ohne '|' verworfen hat.\nSie kommen vor die seitdem gesendeten Nachrichten, damit das Gespräch in der richtigen Reihenfolge bleibt. Nur das zuletzt Verworfene wird aufbewahrt.",
+            example: ":restore",
+        },
+    ),
+    (
+        "stats",
+        Help {
+            usage: ":stats",
+            details: "Zeigt die Zahl der Anfragen, die Prompt-Tokens (und wie viele davon aus dem Cache kamen), die Antwort-Tokens, den Gesamtpreis und was Prompt-Caching gespart hat.\nMit conversation_budget auch, was davon übrig ist: von den Ausgaben der Sitzung oder, mit budget_scope = \"context\", von den Antworten im Kontext.",
+            example: ":stats",
+        },
+    ),
+    (
+        "endpoints",
+        Help {
+            usage: ":endpoints",
+            details: "Mit mehreren Basis-URLs in api_bases gehen Anfragen an die erste, die erreichbar ist. Eine, die nicht erreichbar ist oder zweimal hintereinander mit 5xx antwortet, wird eine Minute lang übergangen.\nZeigt jede in der Reihenfolge, in der sie versucht werden, ob sie erreichbar ist, wie viele Anfragen sie beantwortet hat, wie lange sie im Schnitt bis zum Beginn der Antwort brauchte, und den letzten Fehler einer, die ausfällt.",
+            example: ":endpoints",
+        },
+    ),
+    (
+        "summary",
+        Help {
+            usage: ":summary [--llm]",
+            details: "Zeigt die Zahl der Wechsel, Prompt- und Antwort-Tokens, die Kosten, den Anteil jedes Modells, wie lange die Antworten dauerten, die längste Nachricht und wie lange das Gespräch schon läuft. Alles wird ohne Anfrage aus dem berechnet, was die Nachrichten festgehalten haben.\nMit --llm schreibt das günstige Titelmodell des Anbieters zusätzlich eine Zusammenfassung in 3 Punkten; ihre Kosten stehen in :stats unter Zusammenfassungen.",
+            example: ":summary --llm",
+        },
+    ),
+    (
+        "debug",
+        Help {
+            usage: ":debug",
+            details: "Gibt das Modell, die maximalen Tokens und den Zustand der Ratenbegrenzung auf Client-Seite aus.",
+            example: ":debug",
+        },
+    ),
+    (
+        "diff-request",
+        Help {
+            usage: ":diff-request",
+            details: "Vergleicht die zuletzt gesendete Anfrage mit der davor: zuerst geänderte Parameter wie das Modell oder die maximalen Tokens, dann die Nachrichten. Behaltene Nachrichten sind mit = markiert, entfernte mit -, hinzugekommene mit + und bearbeitete mit ~ samt zeilenweisem Vergleich.\nUnveränderte Nachrichten werden auf ihre erste Zeile gekürzt. Antworten aus dem Cache wurden nicht gesendet und zählen daher nicht.",
+            example: ":diff-request",
+        },
+    ),
+    (
+        "curl",
+        Help {
+            usage: ":curl [on | off | dry]",
+            details: "Der Befehl sendet die Anfrage so, wie gpt es getan hat, an dieselbe URL mit denselben Headern und demselben Body, sodass er gegen die API selbst laufen oder einem Fehlerbericht beigelegt werden kann. Der API-Schlüssel bleibt als Umgebungsvariable stehen, etwa $OPENAI_API_KEY.\n:curl on gibt jede Anfrage so aus, bevor sie gesendet wird, wie --print-curl, und :curl off hört damit auf. :curl dry gibt die Anfrage jeder Nachricht aus, statt sie zu senden, wie --dry-run: Nichts wird gesendet, und das Gespräch bleibt, wie es war.",
+            example: ":curl",
+        },
+    ),
+    (
+        "help",
+        Help {
+            usage: ":help [Befehl]",
+            details: "Ohne Argumente werden alle Befehle aufgelistet. Mit einem Befehlsnamen (oder Kürzel) werden sein Aufruf und ein Beispiel gezeigt.",
+            example: ":help model",
+        },
+    ),
+    (
+        "show",
+        Help {
+            usage: ":show [Index]",
+            details: "Ohne Index wird jede Nachricht mit ihrem Index und ihrer ersten Zeile aufgelistet. Die Indizes sind dieselben, die :grep ausgibt.",
+            example: ":show 3",
+        },
+    ),
+    (
+        "quote",
+        Help {
+            usage: ":quote <Absatz> | <von>-<bis> | /Regex/ | off",
+            details: "Wählt einen Absatz der letzten Antwort über seine Nummer, einen Bereich ihrer Zeilen oder den ersten Absatz, der auf einen regulären Ausdruck passt,\nzeigt ihn und stellt ihn als >-Zitat vor Ihre nächste Nachricht, sodass diese nur noch die Frage dazu enthalten muss.\nEin eingezäunter Codeblock zählt als ein Absatz. Ohne Argumente wird das wartende Zitat gezeigt; off verwirft es.",
+            example: ":quote 2",
+        },
+    ),
+    (
+        "grep",
+        Help {
+            usage: ":grep [-s] [--all] <Muster>",
+            details: "Das Muster ist ein regulärer Ausdruck, ohne -s unabhängig von Groß- und Kleinschreibung.\nMit --all werden auch gespeicherte Sitzungen durchsucht, und vor den Treffern steht der Name der Sitzung.",
+            example: ":grep --all lifetime",
+        },
+    ),
+    (
+        "draft",
+        Help {
+            usage: ":draft [show | clear]",
+            details: "Beim Entwerfen getippte Zeilen werden so aufbewahrt, wie sie getippt wurden, bis :send sie als eine Nachricht sendet; ein Enter mittendrin sendet also keine halbe Nachricht. Zeilen, die mit : beginnen, sind weiterhin Befehle, und der Prompt ist …>, bis der Entwurf gesendet ist.\nDer Entwurf wird mit jeder Zeile gespeichert, und das nächste gpt übernimmt einen nicht gesendeten, auch nach einem Absturz.\n:draft show gibt den Entwurf aus, :draft clear verwirft ihn.",
+            example: ":draft",
+        },
+    ),
+    (
+        "send",
+        Help {
+            usage: ":send",
+            details: "Der Entwurf geht als eine Nachricht hinaus, wie eine in einer Zeile getippte: Ein | am Anfang behält den Kontext.",
+            example: ":send",
+        },
+    ),
+    (
+        "search",
+        Help {
+            usage: ":search",
+            details: "Wie Strg+R in einer Shell: Jede Taste grenzt die Suche ein, der neueste Treffer zuerst, und Strg+R oder Hoch geht zu einem älteren Treffer (Runter zu einem neueren). Groß- und Kleinschreibung spielt keine Rolle.\nEnter übernimmt den passenden Prompt, oder den, zu dem eine passende Antwort gehört, zum Bearbeiten; ein weiteres Enter sendet ihn. Esc oder Strg+C bricht jeden der beiden Schritte ab.",
+            example: ":search",
+        },
+    ),
+    (
+        "save",
+        Help {
+            usage: ":save [Name]",
+            details: "Sitzungen werden als JSON im Datenverzeichnis gespeichert (~/.local/share/gpt-cli/sessions/). Speichern unter einem vorhandenen Namen ersetzt diese Sitzung.\nOhne Namen wird der Titel der Sitzung verwendet (oder die aktuelle Uhrzeit, falls sie noch keinen hat).",
+            example: ":save borrowck",
+        },
+    ),
+    (
+        "load",
+        Help {
+            usage: ":load [--read-only | --fork <neuer Name>] <Name>",
+            details: "Wechselt auch zu dem Modell, mit dem die Sitzung gespeichert wurde. :sessions zeigt, was gespeichert ist. Eine Sitzung, die in einem anderen gpt offen ist, ist gesperrt: --read-only lädt sie, ohne sich an sie zu binden, sodass sie nie überschrieben wird, und --fork speichert eine Kopie unter einem neuen Namen und macht mit dieser weiter. Eine Sperre, die ein nicht mehr laufendes gpt hinterlassen hat, wird übernommen.",
+            example: ":load borrowck",
+        },
+    ),
+    (
+        "sessions",
+        Help {
+            usage: ":sessions",
+            details: "Listet Name und Titel jeder gespeicherten Sitzung auf. In einem Projekt sind das die Sitzungen des Projekts; wer gpt mit --global startet, sieht die übrigen.",
+            example: ":sessions",
+        },
+    ),
+    (
+        "project",
+        Help {
+            usage: ":project",
+            details: "Die nächstgelegene .gpt-cli.toml im Arbeitsverzeichnis oder darüber wird beim Start geladen, außer gpt wurde mit --global gestartet.\nSie kann `system` setzen, einen System-Prompt, der mit jeder Anfrage gesendet wird, `files`, die mitgesendet werden (für jede Anfrage neu gelesen),\n`model` für den Anfang und `name`, unter dem Sitzungen gespeichert werden. Ohne Namen sind das der Name des Verzeichnisses und ein Hash seines Pfads.",
+            example: ":project",
+        },
+    ),
+    (
+        "title",
+        Help {
+            usage: ":title [Text]",
+            details: "Sitzungen bekommen nach ein paar Wechseln automatisch einen Namen (siehe die Einstellung `title_after_exchanges`).\nEin selbst gesetzter Titel ersetzt diesen und ist der voreingestellte Name für :save.",
+            example: ":title Fehlersuche im Borrow-Checker",
+        },
+    ),
+    (
+        "send-only",
+        Help {
+            usage: ":send-only <Indizes> | off",
+            details: "Die Indizes sind die von :show, durch Kommas getrennt, mit Bereichen wie 2-5, z. B. :send-only 2,7-9.\nDie nächste Nachricht wird nur mit diesen (und dem System-Prompt des Projekts) gesendet, während der ganze Kontext erhalten bleibt\nund wie gewohnt wächst. Das gilt für eine Nachricht; ohne Argumente wird die wartende Auswahl gezeigt.",
+            example: ":send-only 2,7,9",
+        },
+    ),
+    (
+        "wrap-prompt",
+        Help {
+            usage: ":wrap-prompt [on | off]",
+            details: "`prompt_prefix` und `prompt_suffix` aus der Konfiguration oder der .gpt-cli.toml kommen beim Senden vor und hinter jede getippte Nachricht, jeweils als eigener Absatz.\nSie zählen zum Fenster, werden aber nicht als Teil der Nachricht gezeigt; :show markiert die Nachrichten, die mit ihnen gesendet wurden.\nOff gilt für die Sitzung und nur für neue Nachrichten: schon gesendete behalten ihre. Ohne Argumente wird die Umrahmung gezeigt.",
+            example: ":wrap-prompt off",
+        },
+    ),
+    (
+        "note",
+        Help {
+            usage: ":note [<Index> [<Text>] | unset <Index>]",
+            details: "Notizen sind für Sie: Sie erscheinen in :show und werden mit der Sitzung gespeichert, aber nie an das Modell gesendet oder mitgezählt.\nDie Indizes sind die von :show. Eine neue Notiz zu einer Nachricht ersetzt ihre alte. Nur mit einem Index wird die Notiz dieser Nachricht gezeigt;\nohne Argumente werden alle Notizen aufgelistet.",
+            example: ":note 3 diese Antwort war falsch, siehe Doku",
+        },
+    ),
+    (
+        "bookmark",
+        Help {
+            usage: ":bookmark [<Index>] [<Bezeichnung>]",
+            details: "Ohne Index bekommt die letzte Antwort das Lesezeichen. Die Nachricht wird in die Lesezeichendatei kopiert, sodass das spätere Löschen\nder Sitzung das Lesezeichen nicht berührt. :bookmarks listet sie auf; gpt bookmarks tut dasselbe von der Shell aus.",
+            example: ":bookmark 3 die Erklärung zum Borrow-Checker",
+        },
+    ),
+    (
+        "bookmarks",
+        Help {
+            usage: ":bookmarks [show|copy|delete <Nummer>]",
+            details: "Die Liste zeigt für jedes Lesezeichen die Nummer, wann es gesetzt wurde, die Bezeichnung (oder den Anfang der Nachricht) und woher es stammt.\nshow gibt die Nachricht aus, copy legt sie in die Zwischenablage. Nummern ändern sich nie, auch wenn andere gelöscht werden.",
+            example: ":bookmarks show 3",
+        },
+    ),
+    (
+        "var",
+        Help {
+            usage: ":var [Name [Wert] | unset <Name>]",
+            details: "In Prompts wird {{name}} vor dem Senden durch den Wert der Variablen ersetzt.\nOhne Argumente werden die Variablen aufgelistet. Nicht definierte Variablen werden so gesendet, wie sie geschrieben sind, mit einer Warnung.\n{{{{ steht für ein wörtliches {{. Die Variablen in der Tabelle [vars] der Konfigurationsdatei werden beim Start gesetzt.",
+            example: ":var lang rust",
+        },
+    ),
+    (
+        "subst",
+        Help {
+            usage: ":subst <Nachricht>",
+            details: "${NAME} wird zur Umgebungsvariablen und $(command) zur Ausgabe des Befehls.\nFehlt eine Variable oder schlägt ein Befehl fehl, wird nichts gesendet.\nDie Einstellung `substitution` ersetzt so in jeder Nachricht; sie ist standardmäßig aus, weil eingefügter Text Befehle ausführen könnte.",
+            example: ":subst Fasse die Änderungen auf $(git branch --show-current) zusammen",
+        },
+    ),
+    (
+        "alias",
+        Help {
+            usage: ":alias [Name [Ersetzung]]",
+            details: "`:name rest` wird vor allem anderen durch die Ersetzung gefolgt von `rest` ersetzt.\nEine Ersetzung, die mit ':' beginnt, führt einen Befehl aus, alles andere wird als Nachricht gesendet.\nKürzel werden nie innerhalb anderer Kürzel ersetzt und können nicht den Namen eines eingebauten Befehls tragen.\nOhne Argumente werden die Kürzel aufgelistet. Die Tabelle [aliases] der Konfigurationsdatei legt sie beim Start an.",
+            example: ":alias en \"ins Englische übersetzen: \"",
+        },
+    ),
+    (
+        "unalias",
+        Help {
+            usage: ":unalias <Name>",
+            details: "Gilt nur für diese Sitzung; Kürzel aus der Konfigurationsdatei sind beim nächsten Mal wieder da.",
+            example: ":unalias en",
+        },
+    ),
+    (
+        "tools",
+        Help {
+            usage: ":tools [enable|disable <Werkzeug>]",
+            details: "Werkzeuge kommen von den Servern in der Tabelle [mcp_servers] der Konfigurationsdatei, die beim Programmstart gestartet (oder, wenn sie eine url haben, verbunden) werden,\nund von ausführbaren Dateien im Werkzeugverzeichnis (siehe :paths), die in `allow_tools` stehen.\nDas Modell kann beim Antworten jedes eingeschaltete Werkzeug aufrufen. Anfangs sind alle eingeschaltet.",
+            example: ":tools disable fetch",
+        },
+    ),
+    (
+        "model",
+        Help {
+            usage: ":model [3|4|4t|4o|4o-mini|auto|Modell-ID]",
+            details: "Allein öffnet es eine Liste der bekannten und zuletzt verwendeten Modelle, mit ihren Kontextfenstern, Preisen pro Million Tokens und dem, was sie außer Text annehmen. Tippen filtert die Liste, Hoch und Runter bewegen, Enter wechselt und Esc bricht ab. Ohne Terminal wird die Liste nur ausgegeben.\nArgumente:\n  3        gpt-3.5-turbo (Voreinstellung)\n  4        gpt-4\n  4t       gpt-4-turbo\n  4o       gpt-4o\n  4o-mini  gpt-4o-mini\n  auto     wählt für jede Nachricht ein günstiges oder ein teures Modell, siehe :why\nVollständige Modell-IDs aus der Preistabelle werden ebenfalls angenommen.",
+            example: ":model 4o",
+        },
+    ),
+    (
+        "why",
+        Help {
+            usage: ":why",
+            details: "Mit :model auto geht jede Nachricht an das günstige oder das teure Modell aus der Tabelle [routing] der Konfiguration.\nLange Prompts und Nachrichten mit Codeblöcken gehen an das teure. Mit classify = true wird für den Rest das Klassifizierungsmodell gefragt.",
+            example: ":why",
+        },
+    ),
+    (
+        "models",
+        Help {
+            usage: ":models [<Filter> | info <Modell> | refresh]",
+            details: "Zeigt für jedes Modell den Preis pro Million Tokens und das Kontextfenster.\nMit provider = \"openrouter\" ist die Liste der Katalog von OpenRouter, beim Start abgerufen.\nJede ID mit einem Schrägstrich kann mit :model verwendet werden, ob aufgelistet oder nicht.\nEine models.toml neben der Konfigurationsdatei ändert oder ergänzt die eingebauten Modelle; info zeigt die Metadaten eines Modells und woher jedes Feld stammt, und refresh liest die Datei neu.",
+            example: ":models claude",
+        },
+    ),
+    (
+        "provider",
+        Help {
+            usage: ":provider [openai|openrouter|anthropic|gemini]",
+            details: "Claude-Modelle gehen immer an Anthropic und die Modelle von OpenAI an OpenAI, außer IDs mit einem Schrägstrich, die für OpenRouter sind.\nDer Anbieter bedient alle übrigen Modelle. Jeder Anbieter liest seinen Schlüssel aus einer eigenen Variablen:\nOPENAI_API_KEY, OPENROUTER_API_KEY oder ANTHROPIC_API_KEY.",
+            example: ":provider anthropic",
+        },
+    ),
+    (
+        "org",
+        Help {
+            usage: ":org [<ID>|default]",
+            details: "Anfangs OPENAI_ORG_ID oder `organization` aus der Konfigurationsdatei; `project` oder OPENAI_PROJECT_ID setzt das Projekt.\ndefault kehrt zur eigenen Organisation des Schlüssels zurück. Nur Anfragen an OpenAI sind betroffen.",
+            example: ":org org-abc123",
+        },
+    ),
+    (
+        "max",
+        Help {
+            usage: ":max <Zahl|auto|max>",
+            details: "auto (die Voreinstellung) richtet sich nach dem Modell: Jedes Modell hat seine eigene voreingestellte Obergrenze für die Ausgabe.\nmax nutzt alles, was das Modell in einer Antwort erzeugen kann.\nDie Einstellung `max_tokens` in der Konfiguration legt den Wert für jedes Modell fest.",
+            example: ":max 2000",
+        },
+    ),
+    (
+        "brief",
+        Help {
+            usage: ":brief",
+            details: "Setzt max_tokens, die Temperatur und eine Anweisung im System-Prompt auf einmal.\n:normal und :detailed wechseln zu den anderen und ersetzen die Anweisung, statt sie zu ergänzen.\nDie Tabelle [presets.brief] der Konfiguration ändert, was gesetzt wird, z. B. max_tokens = 200.",
+            example: ":brief",
+        },
+    ),
+    (
+        "normal",
+        Help {
+            usage: ":normal",
+            details: "Macht :brief oder :detailed rückgängig. Die Tabelle [presets.normal] der Konfiguration kann eigene max_tokens, Temperatur und Anweisung setzen.",
+            example: ":normal",
+        },
+    ),
+    (
+        "detailed",
+        Help {
+            usage: ":detailed",
+            details: "Setzt max_tokens auf 4096 und die eigene Temperatur des Modells und entfernt die Anweisung, die :brief hinzufügt.\nDie Tabelle [presets.detailed] der Konfiguration ändert, was gesetzt wird, auch mit einer eigenen Anweisung.",
+            example: ":detailed",
+        },
+    ),
+    (
+        "tokens",
+        Help {
+            usage: ":tokens [--detail] [<Nachricht>]",
+            details: "Zählt, was mit der nächsten Nachricht gesendet würde: den System-Prompt, die Dateien des Projekts, den Kontext, Präfixe und Suffixe für Prompts, die Nachricht selbst und die wenigen Tokens, die jede Nachricht zusätzlich kostet.\nMit einer Nachricht, die nicht mit | beginnt (und :context aus), wird der Kontext nicht gezählt, da er verworfen würde.\n--detail zeigt jeden Teil mit seinem Anteil an der Summe und die 5 größten Nachrichten im Kontext.",
+            example: ":tokens --detail |und was ist mit Lifetimes?",
+        },
+    ),
+    (
+        "get",
+        Help {
+            usage: ":get [Einstellung]",
+            details: "Ohne Namen wird jede Einstellung mit ihrem aktuellen Wert aufgelistet.",
+            example: ":get max_tokens",
+        },
+    ),
+    (
+        "set",
+        Help {
+            usage: ":set <Einstellung> <Wert>",
+            details: "Änderungen gelten nur, bis das Programm endet. :get zeigt die Einstellungen, die sich ändern lassen.",
+            example: ":set footer minimal",
+        },
+    ),
+    (
+        "cache",
+        Help {
+            usage: ":cache <on|off|clear>",
+            details: "Eingeschaltet werden gleiche Anfragen aus ~/.cache/gpt-cli/ beantwortet, ohne die API aufzurufen.\nVoreingestellt ist die Einstellung `cache` der Konfiguration (aus, wenn nicht gesetzt).",
+            example: ":cache clear",
+        },
+    ),
+    (
+        "paths",
+        Help {
+            usage: ":paths",
+            details: "Die Konfigurationsdatei kommt aus --config, GPT_CLI_CONFIG oder dem Konfigurationsverzeichnis der Plattform.\n`data_dir` und `cache_dir` in der Konfigurationsdatei verlegen den Rest.",
+            example: ":paths",
+        },
+    ),
+    (
+        "theme",
+        Help {
+            usage: ":theme [dark|light|plain]",
+            details: "dark (die Voreinstellung) und light wählen Farben, die auf diesem Hintergrund gut lesbar sind; plain schaltet die Formatierung ab.\nDer Prompt selbst kommt aus `prompt` in der Konfigurationsdatei, z. B. prompt = \"{model|accent}{session|dim:+ [{session}]} {context_tokens|dim}> \".\nPlatzhalter: model, session, title, context_tokens und cost. {name:+text} zeigt text nur, wenn name nicht leer ist, und\n{name|bold,cyan} formatiert es: bold, dim, italic, underline, red, green, yellow, blue, magenta, cyan oder accent (die Farbe des Themas).\nMit NO_COLOR oder wenn die Ausgabe kein Terminal ist, ist alles reiner Text.",
+            example: ":theme light",
+        },
+    ),
+    (
+        "math",
+        Help {
+            usage: ":math [off|unicode]",
+            details: "unicode schreibt Formeln wie \\(x^2 + \\frac{1}{2}\\) wo möglich als x² + ½: griechische Buchstaben, Hoch- und Tiefstellungen,\nBrüche als a/b, Wurzeln und gängige Operatoren. Nur die Anzeige ändert sich; Kontext, Cache, :tee-Dateien und\ngespeicherte Sitzungen behalten, was das Modell geschrieben hat. Codeblöcke bleiben unberührt. off (die Voreinstellung) zeigt Formeln, wie sie geschrieben sind.",
+            example: ":math unicode",
+        },
+    ),
+    (
+        "translate",
+        Help {
+            usage: ":translate [--swap] [Sprache|Quelle:Sprache] <Text>",
+            details: "Die Sprache ist ein Code wie en oder ein Name wie english und wird gemerkt, sodass `:tr <Text>` die zuletzt verwendete nimmt.\nQuelle:Sprache legt auch die Ausgangssprache fest. --swap vertauscht das Paar; ohne Text übersetzt es die letzte Übersetzung zurück.\nDas Modell kommt aus `model` in der Tabelle [translate] der Konfiguration (standardmäßig gpt-4o-mini). Kontext und Modell bleiben unberührt.",
+            example: ":tr en guten Morgen",
+        },
+    ),
+    (
+        "duo",
+        Help {
+            usage: ":duo <Modell A> <Modell B> [--system-a <Prompt>] [--system-b <Prompt>] [--turns <n>] [--stop <Satz>] <erste Nachricht>",
+            details: "A beantwortet die erste Nachricht, dann antwortet B auf A und so weiter, jedes mit seinem eigenen System-Prompt. --turns zählt die Antworten\nbeider Seiten (standardmäßig 6); es endet früher, wenn eine Antwort den Satz aus --stop enthält. Beides kommt aus der Tabelle [duo]\nder Konfiguration, wenn es nicht angegeben ist. Der Prompt des Projekts, der Seed und die Anweisung des Presets gehören zum Gespräch, also bekommt keine Seite sie.\nDas Gespräch bleibt unberührt, und das Protokoll wird als Sitzung namens duo-<Zeit> gespeichert.",
+            example: ":duo gpt-4o gpt-4o-mini --system-a \"Sie führen das Vorstellungsgespräch.\" --system-b \"Sie bewerben sich.\" Fangen wir an.",
+        },
+    ),
+    (
+        "compare",
+        Help {
+            usage: ":compare <Modell>,<Modell>[,...] <Frage>",
+            details: "Die Frage geht gleichzeitig an jedes Modell, mit dem System-Prompt des Projekts, aber ohne das Gespräch, und die Antworten\nerscheinen in der Reihenfolge der Modelle, jede mit ihren Kosten. Sie kommen nicht in den Kontext. `max_in_flight` in der\nKonfiguration begrenzt, wie viele auf einmal gefragt werden, und Strg+C hält alle an.",
+            example: ":compare 4o,4o-mini,claude-3-5-sonnet Was ist der Unterschied zwischen einem Prozess und einem Thread?",
+        },
+    ),
+    (
+        "expect",
+        Help {
+            usage: ":expect [json|code|code:strict|regex:<Muster>|off]",
+            details: "json verlangt, dass die ganze Antwort als JSON lesbar ist. code verlangt mindestens einen eingezäunten Codeblock, und code:strict nichts außerhalb davon.\nregex:<Muster> verlangt, dass die Antwort irgendwo passt. Eine Abweichung gibt eine Warnung aus, und der Einmalmodus (gpt --expect json ...) endet mit 5.\nMit expect_retry = true in der Konfiguration erfährt das Modell, was nicht stimmte, und wird noch einmal gefragt. Ohne Argument wird die aktuelle Erwartung gezeigt.",
+            example: ":expect json",
+        },
+    ),
+    (
+        "filter",
+        Help {
+            usage: ":filter [--tee] <Befehl> | :filter off",
+            details: "Die rohe Antwort geht an die Standardeingabe des Befehls, und stattdessen wird gezeigt, was er ausgibt; mit --tee danach, nachdem die Antwort wie gewohnt gestreamt wurde.\nDer Kontext behält die rohe Antwort. Schlägt der Befehl fehl oder braucht er länger als 10 s, wird die rohe Antwort mit einer Warnung gezeigt.\n`response_filter` in der Konfigurationsdatei setzt einen beim Start, z. B. response_filter = \"glow -\".",
+            example: ":filter glow -",
+        },
+    ),
+    (
+        "tee",
+        Help {
+            usage: ":tee <Pfad|off>",
+            details: "Hängt den rohen Text jeder Antwort an die Datei an, bis `:tee off`.\nEine Antwort landet erst in der Datei, wenn sie fertig gestreamt ist, und die Fußzeile kommt nie hinein.",
+            example: ":tee answers.md",
+        },
+    ),
+    (
+        "stream-to",
+        Help {
+            usage: ":stream-to <Pfad> [--quiet] | :stream-to off",
+            details: "Die Datei wird ersetzt, und jeder Teil der Antwort wird hineingeschrieben, sobald er ankommt, sodass `tail -f` oder ein Editor, der Dateien neu lädt, sie wachsen sieht.\nMit --quiet geht die Antwort nur in die Datei, und gezeigt werden nur die Hinweise und die Fußzeile.\nIst die Antwort vollständig, nennt ein Kommentar am Ende Modell, Tokens und Preis, in der Kommentarsyntax, die die Endung nahelegt (keiner bei .json).\nEine, die fehlschlägt, angehalten oder abgebrochen wird, endet stattdessen mit einem Kommentar, der sie als INCOMPLETE markiert.",
+            example: ":stream-to src/generated.rs --quiet",
+        },
+    ),
+    (
+        "ask",
+        Help {
+            usage: ":ask <Frage>",
+            details: "Die Abschnitte der indizierten Dateien, die der Frage am nächsten sind, werden mitgesendet, beschriftet mit Datei und Zeilen.\nDas Modell wird gebeten, sie zu zitieren, und die zitierten werden nach der Antwort aufgelistet.\nDer Index wird mit `gpt index <Verzeichnis>` gebaut; Ort, Abschnittsgröße und Zahl der Abschnitte stehen in der Tabelle [index] der Konfiguration.",
+            example: ":ask wie werden Sitzungen gespeichert?",
+        },
+    ),
+    (
+        "cmd",
+        Help {
+            usage: ":cmd [--execute] [--explain] <Aufgabe oder Befehl>",
+            details: "Zeigt einen einzelnen Befehl für Ihre Shell (aus $SHELL) mit einer kurzen Erklärung und kopiert ihn in die Zwischenablage.\nMit --explain ist der Text ein Befehl, der erklärt werden soll. Mit --execute läuft der Befehl, sobald Sie y antworten;\neiner, den das Modell als gefährlich markiert, braucht ein ausgeschriebenes yes. Der Befehl bekommt keine Eingabe. gpt cmd tut dasselbe von der Shell aus.",
+            example: ":cmd finde Dateien, die am letzten Tag geändert wurden, und packe sie mit tar",
+        },
+    ),
+    (
+        "fix",
+        Help {
+            usage: ":fix [--no-rerun <Ausgabe>]",
+            details: "Findet Ihren letzten Befehl in der Verlaufsdatei Ihrer Shell (bash, zsh oder fish, aus $SHELL; shell_history in der Konfiguration\nzeigt woandershin) und führt ihn, sobald Sie y antworten, erneut aus, um zu sehen, was er ausgibt. Mit --no-rerun wird stattdessen die eingefügte Ausgabe\ngesendet. Die vorgeschlagene Korrektur wird wie bei :cmd --execute zum Ausführen angeboten, sodass eine, die das Modell als gefährlich markiert, ein ausgeschriebenes yes braucht.\nbash schreibt seinen Verlauf erst, wenn die Shell endet, außer PROMPT_COMMAND führt history -a aus.",
+            example: ":fix",
+        },
+    ),
+    (
+        "file",
+        Help {
+            usage: ":file <Pfad>",
+            details: "Die Datei kommt als eigene Nachricht in den Kontext, eingezäunt und mit ihrem Pfad beschriftet, und bleibt für die nächste Nachricht dort, auch wenn diese ein neues Gespräch beginnt.\nEine Datei, die schon im Kontext ist, ersetzt die ältere Kopie an ihrer Stelle, oder mit restage_files = \"keep-both\" oder \"ask\" werden beide behalten oder es wird gefragt. Eine unveränderte Datei wird nicht erneut hinzugefügt, und die Dateien des Projekts werden abgelehnt, da sie ohnehin immer gesendet werden.\nEine Datei, die nach Geheimnissen aussieht, wie .env oder ein SSH-Schlüssel, wird abgelehnt, außer der Pfad beginnt mit !.",
+            example: ":file Cargo.toml",
+        },
+    ),
+    (
+        "code-context",
+        Help {
+            usage: ":code-context [--full <Pfad>...]",
+            details: "Die Übersicht enthält die Crates und ihre Abhängigkeiten, die Quelldateien, die git nicht ignoriert, und die Signaturen ihrer öffentlichen Elemente ohne Rümpfe, alles als eine Nachricht, die wie bei :file bereitgelegt wird.\nSie bleibt unter code_context_tokens (standardmäßig 8000). Passen nicht alle Signaturen hinein, bekommt jede Datei ihre ersten Elemente, bevor eine mehr bekommt, sodass der ganze Workspace zu sehen ist.\n--full fügt die danach genannten Dateien zusätzlich vollständig hinzu. Die Zahl der Tokens wird vor dem Bereitlegen gezeigt, und die Übersicht lässt sich vorher ansehen. Ein erneuter Aufruf ersetzt die Kopie im Kontext.",
+            example: ":code-context --full src/state.rs",
+        },
+    ),
+    (
+        "ask-file",
+        Help {
+            usage: ":ask-file <Pfad> <Frage>",
+            details: "Eine Datei, die zu groß für das Fenster des Modells ist, wird in überlappende Abschnitte geteilt, die wo möglich an Leerzeilen enden.\nDie Frage wird zu jedem Abschnitt gestellt, dann fasst eine weitere Anfrage die Antworten zusammen; nur diese wird beim Streamen gezeigt.\nDer Fortschritt geht nach stderr, und nach der Fußzeile folgt die Summe über alle Anfragen. Der Kontext bleibt unberührt.\nEine Datei, die nach Geheimnissen aussieht, wie .env oder ein SSH-Schlüssel, wird abgelehnt, außer der Pfad beginnt mit !.",
+            example: ":ask-file server.log \"warum ist das Deployment um 14:02 fehlgeschlagen?\"",
+        },
+    ),
+    (
+        "script",
+        Help {
+            usage: ":script <Pfad>",
+            details: "Jede Zeile ist ein Befehl oder eine Nachricht und läuft der Reihe nach genau so, als wäre sie getippt: |-Präfixe und Kürzel funktionieren, und der Kontext geht von einer Zeile zur nächsten weiter. Leerzeilen und Zeilen, die mit # beginnen, werden übersprungen.\nEine Zeile, die fehlschlägt, hält das Skript an und nennt Datei und Zeilennummer. gpt --script <Pfad> tut dasselbe beim Start, und --script-exit beendet danach die Sitzung, statt den Prompt zu starten.",
+            example: ":script demo.txt",
+        },
+    ),
+    (
+        "watch",
+        Help {
+            usage: ":watch [--max-iterations <n>] [--budget <Betrag>] <Pfad> <Prompt>",
+            details: "Sendet den Inhalt der Datei jetzt und nach jeder Änderung mit dem Prompt, jeweils als neues Gespräch, mit einem Zeitstempel über jeder Antwort.\nq tippen oder Strg+C drücken führt zurück zum Prompt. Das Beobachten endet von selbst nach 20 Anfragen, oder --max-iterations, oder sobald es --budget gekostet hat, in der Währung, in der Preise gezeigt werden.\nDer Kontext bleibt unberührt, und die Kosten zählen zu :stats. Wie bei :ask-file sendet ein Pfad, der mit ! beginnt, auch eine Datei, die nach Geheimnissen aussieht.",
+            example: ":watch src/lib.rs \"prüfe diese Datei auf Fehler\"",
+        },
+    ),
+];
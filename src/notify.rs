@@ -0,0 +1,48 @@
+use notify_rust::Notification;
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use crate::msg::{self, Msg};
+
+// How much of the answer the notification shows.
+const MAX_BODY_CHARS: usize = 120;
+
+// Notifications fail the same way every time (no D-Bus, an SSH session), so the failure is only reported once.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Rings the terminal bell and sends a desktop notification if a response took at least `after_secs`.
+pub fn response_finished(after_secs: Option<u64>, elapsed: Duration, model: &str, answer: &str) {
+    let Some(after_secs) = after_secs else {
+        return;
+    };
+    if elapsed < Duration::from_secs(after_secs) {
+        return;
+    }
+
+    // The bell goes to stderr so it never ends up in piped output.
+    let _ = write!(io::stderr(), "\x07");
+    let _ = io::stderr().flush();
+
+    let summary = msg::fmt(Msg::NotifyFinished, &[("model", &model)]);
+    let first_line = answer.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let mut body: String = first_line.chars().take(MAX_BODY_CHARS).collect();
+    if first_line.chars().count() > MAX_BODY_CHARS {
+        body.push('…');
+    }
+    // Showing a notification can block on the notification daemon, so keep it off the REPL's thread.
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = Notification::new()
+            .appname("gpt-cli")
+            .summary(&summary)
+            .body(&body)
+            .show()
+        {
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                eprintln!("{}", msg::fmt(Msg::NotifyFailed, &[("error", &e)]));
+            }
+        }
+    });
+}
@@ -0,0 +1,206 @@
+//! Settings for one message, written in front of it: `@4o @temp=0.2 @max=2000 explain this`.
+//! Each is a setting from [`settings::SETTINGS`] set as `:set` would, so the names and values
+//! accepted are the same, and everything goes back to how it was once the answer is in. What's
+//! stored in the context is only the message itself.
+
+use crate::{
+    error::{AppError, Result},
+    models,
+    msg::{self, Msg},
+    preset::Preset,
+    settings::{self, MaxTokens},
+    AppState,
+};
+
+/// The settings that can be changed for one message.
+const OVERRIDABLE: &[&str] = &["model", "max_tokens", "temperature", "preset", "cache"];
+
+/// Shorter names for them.
+const ALIASES: &[(&str, &str)] = &[("temp", "temperature"), ("max", "max_tokens")];
+
+/// One `@` at the start of a message: the setting and its value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Override {
+    pub setting: &'static str,
+    pub value: String,
+    /// As it was written, for the footer.
+    pub written: String,
+}
+
+/// What the overrides change, to put back afterwards.
+pub struct Saved {
+    model: String,
+    auto_route: bool,
+    max_tokens: MaxTokens,
+    temperature: Option<f32>,
+    preset: Preset,
+    cache: bool,
+}
+
+/// Splits the overrides off the start of `input`, returning them and the message. `@name` is a
+/// model shortcut like `@4o` or a preset like `@brief`, and `@setting=value` sets a setting. A
+/// message that starts with an `@` of its own is written `\@`.
+pub fn parse(input: &str) -> Result<(Vec<Override>, String)> {
+    if let Some(rest) = input.strip_prefix("\\@") {
+        return Ok((Vec::new(), format!("@{rest}")));
+    }
+    let mut overrides = Vec::new();
+    let mut rest = input.trim_start();
+    while let Some(word) = rest.strip_prefix('@') {
+        let end = word.find(char::is_whitespace).unwrap_or(word.len());
+        let (word, after) = word.split_at(end);
+        overrides.push(parse_one(word)?);
+        rest = after.trim_start();
+    }
+    Ok((overrides, rest.to_string()))
+}
+
+fn parse_one(word: &str) -> Result<Override> {
+    let written = format!("@{word}");
+    let (setting, value) = match word.split_once('=') {
+        Some((name, value)) => {
+            let name = name.to_lowercase();
+            let name = ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == name)
+                .map_or(name.as_str(), |(_, setting)| setting);
+            let setting = OVERRIDABLE
+                .iter()
+                .find(|s| **s == name)
+                .ok_or_else(|| unknown(&written))?;
+            (*setting, value.to_string())
+        }
+        None if Preset::parse(&word.to_lowercase()).is_some() => ("preset", word.to_lowercase()),
+        None => match models::resolve(word) {
+            Some(model) => ("model", model),
+            None => return Err(unknown(&written)),
+        },
+    };
+    Ok(Override {
+        setting,
+        value,
+        written,
+    })
+}
+
+fn unknown(written: &str) -> AppError {
+    let settings: Vec<String> = OVERRIDABLE
+        .iter()
+        .map(|setting| match ALIASES.iter().find(|(_, s)| s == setting) {
+            Some((alias, _)) => format!("{setting} (@{alias})"),
+            None => setting.to_string(),
+        })
+        .collect();
+    AppError::Command(msg::fmt(
+        Msg::UnknownOverride,
+        &[("written", &written), ("settings", &settings.join(", "))],
+    ))
+}
+
+/// Sets the overrides, returning what they replaced. Nothing is left changed when one of them
+/// can't be set.
+pub fn apply(state: &mut AppState, overrides: &[Override]) -> Result<Saved> {
+    let saved = Saved {
+        model: state.model.clone(),
+        auto_route: state.auto_route,
+        max_tokens: state.max_tokens,
+        temperature: state.temperature,
+        preset: state.preset,
+        cache: state.config.cache,
+    };
+    for o in overrides {
+        if let Err(e) = settings::set(state, o.setting, &o.value) {
+            restore(state, saved);
+            return Err(AppError::Command(format!("{}: {e}", o.written)));
+        }
+    }
+    Ok(saved)
+}
+
+pub fn restore(state: &mut AppState, saved: Saved) {
+    state.model = saved.model;
+    state.auto_route = saved.auto_route;
+    state.max_tokens = saved.max_tokens;
+    state.temperature = saved.temperature;
+    state.preset = saved.preset;
+    state.config.cache = saved.cache;
+}
+
+/// The overrides as they were written, for the footer.
+pub fn describe(overrides: &[Override]) -> String {
+    overrides
+        .iter()
+        .map(|o| o.written.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(input: &str) -> Vec<(&'static str, String)> {
+        let (overrides, _) = parse(input).unwrap();
+        overrides
+            .into_iter()
+            .map(|o| (o.setting, o.value))
+            .collect()
+    }
+
+    #[test]
+    fn overrides_come_off_the_front_only() {
+        let (overrides, message) = parse("  @temp=0.2   @max=2000 explain @this").unwrap();
+        assert_eq!(message, "explain @this");
+        assert_eq!(describe(&overrides), "@temp=0.2 @max=2000");
+        assert_eq!(
+            settings("@temp=0.2 @MAX=2000 @Cache=off x"),
+            [
+                ("temperature", "0.2".to_string()),
+                ("max_tokens", "2000".to_string()),
+                ("cache", "off".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse("no overrides").unwrap(),
+            (Vec::new(), "no overrides".to_string())
+        );
+    }
+
+    #[test]
+    fn names_alone_are_presets_then_models() {
+        assert_eq!(settings("@Brief x"), [("preset", "brief".to_string())]);
+        assert_eq!(
+            settings("@4o x"),
+            [("model", models::resolve("4o").unwrap())]
+        );
+    }
+
+    #[test]
+    fn the_last_of_the_same_setting_wins() {
+        // In order, so applying them leaves the last one set.
+        assert_eq!(
+            settings("@temp=1 @temperature=0 x"),
+            [
+                ("temperature", "1".to_string()),
+                ("temperature", "0".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn an_escaped_at_is_sent_as_it_is() {
+        assert_eq!(
+            parse("\\@here hi").unwrap(),
+            (Vec::new(), "@here hi".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_overrides_are_errors() {
+        for input in ["@nonsense x", "@seed=1 x", "@temp=0.2 @wat=1 x"] {
+            let err = parse(input).unwrap_err().to_string();
+            assert!(err.starts_with("Unknown override '@"), "{err}");
+            assert!(err.contains("max_tokens (@max)"), "{err}");
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use directories::ProjectDirs;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::msg::{self, Msg};
+
+/// Where everything the program reads or writes lives, decided once at startup.
+struct Locations {
+    config_file: PathBuf,
+    data_dir: PathBuf,
+    cache_dir: PathBuf,
+}
+
+static LOCATIONS: OnceLock<Locations> = OnceLock::new();
+// Set when running in a project, whose sessions are kept apart from the rest.
+static SESSION_NAMESPACE: OnceLock<String> = OnceLock::new();
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "gpt-cli")
+}
+
+// Without a home directory there's nowhere sensible, so fall back to the working directory.
+fn fallback() -> PathBuf {
+    PathBuf::from(".gpt-cli")
+}
+
+/// The config file used without `--config`: `GPT_CLI_CONFIG`, or the platform's config directory
+/// (`$XDG_CONFIG_HOME/gpt-cli/config.toml` on Linux).
+pub fn default_config_file() -> PathBuf {
+    if let Some(path) = env::var_os("GPT_CLI_CONFIG") {
+        return PathBuf::from(path);
+    }
+    project_dirs()
+        .map(|d| d.config_dir().to_path_buf())
+        .unwrap_or_else(fallback)
+        .join("config.toml")
+}
+
+fn default_data_dir() -> PathBuf {
+    project_dirs()
+        .map(|d| d.data_dir().to_path_buf())
+        .unwrap_or_else(fallback)
+}
+
+fn default_cache_dir() -> PathBuf {
+    project_dirs()
+        .map(|d| d.cache_dir().to_path_buf())
+        .unwrap_or_else(fallback)
+}
+
+/// Fixes the locations for the rest of the run. `data_dir` and `cache_dir` come from the config file, if set there.
+pub fn init(config_file: PathBuf, data_dir: Option<PathBuf>, cache_dir: Option<PathBuf>) {
+    let _ = LOCATIONS.set(Locations {
+        config_file,
+        data_dir: data_dir.unwrap_or_else(default_data_dir),
+        cache_dir: cache_dir.unwrap_or_else(default_cache_dir),
+    });
+}
+
+pub fn config_file() -> PathBuf {
+    match LOCATIONS.get() {
+        Some(l) => l.config_file.clone(),
+        None => default_config_file(),
+    }
+}
+
+pub fn cache_dir() -> PathBuf {
+    match LOCATIONS.get() {
+        Some(l) => l.cache_dir.clone(),
+        None => default_cache_dir(),
+    }
+}
+
+pub fn data_dir() -> PathBuf {
+    match LOCATIONS.get() {
+        Some(l) => l.data_dir.clone(),
+        None => default_data_dir(),
+    }
+}
+
+/// Saves and lists sessions under `projects/<namespace>` of the sessions directory from now on.
+pub fn set_session_namespace(namespace: String) {
+    let _ = SESSION_NAMESPACE.set(namespace);
+}
+
+pub fn sessions_dir() -> PathBuf {
+    let dir = data_dir().join("sessions");
+    match SESSION_NAMESPACE.get() {
+        Some(namespace) => dir.join("projects").join(namespace),
+        None => dir,
+    }
+}
+
+/// Executables offered to the model as tools, next to the config file.
+pub fn tools_dir() -> PathBuf {
+    match config_file().parent() {
+        Some(dir) => dir.join("tools"),
+        None => PathBuf::from("tools"),
+    }
+}
+
+/// Model metadata that changes or adds to the built-in table, next to the config file.
+pub fn models_file() -> PathBuf {
+    match config_file().parent() {
+        Some(dir) => dir.join("models.toml"),
+        None => PathBuf::from("models.toml"),
+    }
+}
+
+/// Creates a directory (and its parents) the first time something is written there,
+/// saying which directory it was when that fails.
+pub fn ensure_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir).map_err(|e| {
+        let message = msg::fmt(
+            Msg::DirNotCreated,
+            &[("path", &dir.display()), ("error", &e)],
+        );
+        io::Error::new(e.kind(), message)
+    })
+}
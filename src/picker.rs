@@ -0,0 +1,288 @@
+//! `:model` on its own: a list of the models to pick from, filtered as you type. It has the
+//! registry's models, the organization's fine-tuned ones and the ones used recently, each with its
+//! context window, prices and capabilities. Like `:search` it's drawn under the prompt, but the
+//! keys and resizes come from crossterm's events while the stdin reader is [`Handover`]ed, and
+//! without a terminal the list is only printed.
+
+use crossterm::{
+    cursor,
+    event::{self, Event, EventStream, KeyCode, KeyEventKind, KeyModifiers},
+    queue,
+    terminal::{Clear, ClearType},
+};
+use futures::StreamExt;
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    commands,
+    currency::Currency,
+    error::Result,
+    input::{Handover, RawMode},
+    ledger,
+    models::{self, ModelInfo},
+    msg::{self, Msg},
+    search::{head, pop_grapheme, width},
+    style,
+    usage::fmt_thousands,
+    AppState,
+};
+
+// How many models are shown at once; the list scrolls to keep the selected one in view.
+const ROWS: usize = 10;
+
+// How many of the models used recently are put first.
+const RECENT: usize = 5;
+
+struct Choice {
+    id: String,
+    info: Option<&'static ModelInfo>,
+    recent: bool,
+}
+
+/// Runs the picker, switching to the model picked. Esc or Ctrl+C leaves the model as it was.
+pub async fn run(state: &mut AppState) -> Result<()> {
+    let choices = choices();
+    if !state.interactive {
+        println!("  {}", header());
+        for choice in &choices {
+            let marker = match choice.id == state.model {
+                true => '*',
+                false => ' ',
+            };
+            println!("{marker} {}", row(choice, &state.config.currency));
+        }
+        let recent = match choices.iter().filter(|c| c.recent).count() {
+            0 => String::new(),
+            1 => msg::get(Msg::PickerOneRecent).to_string(),
+            n => msg::fmt(Msg::PickerRecent, &[("count", &n)]),
+        };
+        println!("{recent}{}", msg::get(Msg::PickerHowToSwitch));
+        return Ok(());
+    }
+    // Dropped in the reverse order, so the terminal is back to normal before the reader resumes.
+    let _handover = Handover::start();
+    let raw = RawMode::enable()?;
+    // Anything typed before the list opened wasn't meant for the filter.
+    while event::poll(Duration::ZERO)? {
+        event::read()?;
+    }
+
+    let mut filter = String::new();
+    // Starts on the current model, so Enter straight away changes nothing.
+    let mut selected = choices
+        .iter()
+        .position(|c| c.id == state.model)
+        .unwrap_or(0);
+    let mut out = io::stdout();
+    let mut events = EventStream::new();
+    // Updated from resize events, so the rows are cut to the new width.
+    let mut columns = width();
+    loop {
+        let shown = matches(&choices, &filter);
+        selected = selected.min(shown.len().saturating_sub(1));
+        let (screen, cursor) = render(
+            &shown,
+            selected,
+            &filter,
+            columns,
+            &state.model,
+            &state.config.currency,
+            state.color,
+        );
+        clear(&mut out)?;
+        // Raw mode doesn't go back to the start of the line by itself.
+        write!(out, "{}", screen.replace('\n', "\r\n"))?;
+        // Back to the filter, where what's typed goes.
+        let below = screen.matches('\n').count() as u16;
+        if below > 0 {
+            queue!(out, cursor::MoveUp(below))?;
+        }
+        queue!(out, cursor::MoveToColumn(cursor as u16))?;
+        out.flush()?;
+
+        let key = match events.next().await {
+            Some(Ok(Event::Key(key))) if key.kind != KeyEventKind::Release => key,
+            Some(Ok(Event::Resize(width, _))) => {
+                columns = width.into();
+                continue;
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => {
+                clear(&mut out)?;
+                return Err(e.into());
+            }
+            None => {
+                clear(&mut out)?;
+                return Ok(());
+            }
+        };
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                clear(&mut out)?;
+                return Ok(());
+            }
+            KeyCode::Char('c') if control => {
+                clear(&mut out)?;
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                clear(&mut out)?;
+                // What switching prints is written the usual way.
+                drop(raw);
+                if let Some(choice) = shown.get(selected) {
+                    commands::switch_model(state, choice.id.clone());
+                }
+                return Ok(());
+            }
+            KeyCode::Char(c) if !control => {
+                filter.push(c);
+                selected = 0;
+            }
+            KeyCode::Backspace => {
+                pop_grapheme(&mut filter);
+                selected = 0;
+            }
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            _ => {}
+        }
+    }
+}
+
+// Clears from the start of the cursor's line to the end of the screen.
+fn clear(out: &mut impl Write) -> io::Result<()> {
+    queue!(
+        out,
+        cursor::MoveToColumn(0),
+        Clear(ClearType::FromCursorDown)
+    )?;
+    out.flush()
+}
+
+/// The recent models first, newest first, then the rest in the registry's order.
+fn choices() -> Vec<Choice> {
+    let mut choices: Vec<Choice> = ledger::recent_models(RECENT)
+        .into_iter()
+        .map(|id| Choice {
+            info: models::lookup(&id),
+            id,
+            recent: true,
+        })
+        .collect();
+    let known = models::all().map(|info| info.id.to_string()).chain(
+        models::fine_tuned_models()
+            .unwrap_or_default()
+            .iter()
+            .cloned(),
+    );
+    for id in known {
+        if !choices.iter().any(|c| c.id == id) {
+            choices.push(Choice {
+                info: models::lookup(&id),
+                id,
+                recent: false,
+            });
+        }
+    }
+    choices
+}
+
+/// The choices whose id contains every word of `filter`, ignoring case.
+fn matches<'c>(choices: &'c [Choice], filter: &str) -> Vec<&'c Choice> {
+    let filter = filter.to_lowercase();
+    choices
+        .iter()
+        .filter(|c| {
+            let id = c.id.to_lowercase();
+            filter.split_whitespace().all(|word| id.contains(word))
+        })
+        .collect()
+}
+
+/// The column names, with prices per million tokens as in the pricing table.
+fn header() -> String {
+    format!(
+        "{:<30} {:>9} {:>8} {:>8}  capabilities",
+        "model", "window", "in/1M", "out/1M"
+    )
+}
+
+/// A model's line in the list, with prices per million tokens.
+fn row(choice: &Choice, currency: &Currency) -> String {
+    let details = match choice.info {
+        Some(info) => {
+            let (input, output) = info.list_prices(currency);
+            format!(
+                "{:>9} {input:>8} {output:>8}  {}",
+                fmt_thousands(info.context_window as u64),
+                info.capabilities.map_or("?".to_string(), |c| c.summary())
+            )
+        }
+        None => format!("{:>9} {:>8} {:>8}  ?", "?", "?", "?"),
+    };
+    format!("{:<30} {details}", choice.id)
+}
+
+/// The filter line and the rows under it, and the column the cursor goes back to.
+fn render(
+    shown: &[&Choice],
+    selected: usize,
+    filter: &str,
+    columns: usize,
+    current: &str,
+    currency: &Currency,
+    color: bool,
+) -> (String, usize) {
+    let room = columns.saturating_sub(3);
+    let label = msg::get(Msg::PickerLabel);
+    let mut count = match shown.len() {
+        1 => msg::get(Msg::PickerOneModel).to_string(),
+        n => msg::fmt(Msg::PickerModels, &[("count", &n)]),
+    };
+    match shown.iter().filter(|c| c.recent).count() {
+        0 => {}
+        1 => count.push_str(msg::get(Msg::PickerOneRecentFirst)),
+        n => count.push_str(&msg::fmt(Msg::PickerRecentFirst, &[("count", &n)])),
+    }
+    let mut screen = format!(
+        "{}{filter}{}",
+        style::dim(label, color),
+        style::dim(&count, color)
+    );
+    let cursor = label.width() + filter.width();
+    screen.push_str(&format!(
+        "\n  {}",
+        style::dim(&head(&header(), room), color)
+    ));
+    // The window of rows that has the selected one in it.
+    let first = selected.saturating_sub(ROWS - 1);
+    for (i, choice) in shown.iter().enumerate().skip(first).take(ROWS) {
+        let line = head(&row(choice, currency), room);
+        let line = match choice.id == current {
+            true => style::wrap(&line, style::accent_code(), color),
+            false => line,
+        };
+        let marker = match (i == selected, choice.id == current) {
+            (true, _) => '›',
+            (false, true) => '*',
+            (false, false) => ' ',
+        };
+        screen.push_str(&format!("\n{marker} {line}"));
+    }
+    if shown.is_empty() {
+        screen.push_str(&format!(
+            "\n  {}",
+            style::dim(msg::get(Msg::PickerNoMatch), color)
+        ));
+    }
+    screen.push_str(&format!(
+        "\n{}",
+        style::dim(msg::get(Msg::PickerKeys), color)
+    ));
+    (screen, cursor)
+}
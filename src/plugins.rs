@@ -0,0 +1,129 @@
+//! Tools that are plain executables in the tools directory.
+//!
+//! `tool --schema` prints the tool's function schema as JSON: `{"name", "description", "parameters"}`.
+//! When the model calls the tool, it's run without arguments, gets the call's arguments as JSON on
+//! stdin, and whatever it prints is the result. A non-zero exit is reported to the model as an error.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+
+use crate::{
+    error::Result,
+    msg::{self, Msg},
+    paths,
+    tools::{Source, Tool, Tools},
+};
+
+// --schema should answer straight away.
+const SCHEMA_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct Schema {
+    name: Option<String>,
+    description: Option<String>,
+    parameters: Option<Value>,
+}
+
+/// Registers the executables in the tools directory that are named in `allow_tools`.
+/// Nothing else there is ever run, not even for its schema.
+pub async fn load(allow_tools: &[String], tools: &mut Tools) {
+    let Ok(entries) = fs::read_dir(paths::tools_dir()) else {
+        return;
+    };
+    let mut skipped = Vec::new();
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !path.is_file() || file_name.starts_with('.') {
+            continue;
+        }
+        if !allow_tools.iter().any(|allowed| allowed == file_name) {
+            skipped.push(file_name.to_string());
+            continue;
+        }
+        match schema(&path).await {
+            Ok(schema) => tools.add(Tool {
+                name: schema.name.unwrap_or_else(|| file_name.to_string()),
+                source: Source::Plugin(path.clone()),
+                description: schema.description,
+                schema: schema
+                    .parameters
+                    .unwrap_or_else(|| json!({"type": "object"})),
+                enabled: true,
+            }),
+            Err(e) => eprintln!(
+                "{}",
+                msg::fmt(Msg::PluginNoSchema, &[("name", &file_name), ("error", &e)])
+            ),
+        }
+    }
+    if !skipped.is_empty() {
+        eprintln!(
+            "{}",
+            msg::fmt(Msg::PluginsNotAllowed, &[("tools", &skipped.join(", "))])
+        );
+    }
+}
+
+async fn schema(path: &Path) -> std::result::Result<Schema, String> {
+    let output = timeout(
+        SCHEMA_TIMEOUT,
+        Command::new(path)
+            .arg("--schema")
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| msg::get(Msg::PluginSchemaTimeout).to_string())?
+    .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(msg::fmt(
+            Msg::PluginSchemaFailed,
+            &[("status", &output.status)],
+        ));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())
+}
+
+/// Runs a plugin with the call's arguments on stdin.
+pub async fn call(path: &Path, arguments: &Value, timeout_secs: u64) -> Result<String> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(arguments.to_string().as_bytes()).await?;
+    }
+    let output = match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
+        Ok(output) => output?,
+        Err(_) => {
+            return Ok(format!(
+                "Error: the tool didn't finish within {timeout_secs}s."
+            ))
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() {
+        Ok(stdout.trim_end().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Ok(format!(
+            "Error: the tool exited with {}: {}",
+            output.status,
+            stderr.trim()
+        ))
+    }
+}
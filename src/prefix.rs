@@ -0,0 +1,294 @@
+//! How much of each request repeats the start of the one before. OpenAI's prompt caching reuses
+//! the longest prefix it has seen recently, so this says how much of a request could be billed at
+//! the cached rate, and notices when an edit early on means none of the conversation can be.
+
+use async_openai::types::{ChatCompletionRequestMessage, CreateChatCompletionRequest};
+
+use crate::{
+    msg::{self, Msg},
+    tokens,
+    usage::fmt_thousands,
+};
+
+/// Prompts shorter than this are never cached, so there's nothing to lose below it.
+pub const CACHE_MIN_TOKENS: usize = 1024;
+
+/// What's kept of the last request: its model and the tokens of each part, the tools first and
+/// then every message.
+#[derive(Debug, Default)]
+pub struct PrefixTracker {
+    model: String,
+    parts: Vec<Vec<usize>>,
+}
+
+/// A request compared with the one before.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reuse {
+    /// Leading tokens identical to the last request's.
+    pub same: usize,
+    /// Tokens of this request.
+    pub total: usize,
+    /// What changed before the end of the last request, if anything did while the conversation
+    /// went on.
+    pub changed: Option<Change>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Model,
+    Tools,
+    /// The message at this index of the request.
+    Message {
+        index: usize,
+        system: bool,
+    },
+}
+
+impl Reuse {
+    /// A notice for when the change threw away a prefix long enough to have been cached.
+    pub fn warning(&self, previous: usize) -> Option<String> {
+        let lost = previous.saturating_sub(self.same);
+        if previous < CACHE_MIN_TOKENS || lost < CACHE_MIN_TOKENS {
+            return None;
+        }
+        let what = match self.changed? {
+            Change::Model => msg::get(Msg::PrefixModelChanged).to_string(),
+            Change::Tools => msg::get(Msg::PrefixToolsChanged).to_string(),
+            Change::Message { system: true, .. } => msg::get(Msg::PrefixSystemChanged).to_string(),
+            Change::Message { index, .. } => {
+                msg::fmt(Msg::PrefixMessageChanged, &[("index", &index)])
+            }
+        };
+        Some(msg::fmt(
+            Msg::PrefixLost,
+            &[
+                ("what", &what),
+                ("same", &fmt_thousands(self.same as u64)),
+                ("previous", &fmt_thousands(previous as u64)),
+            ],
+        ))
+    }
+}
+
+impl PrefixTracker {
+    /// Compares `request` with the last one, then keeps it for the next. Returns `None` for the
+    /// first request, and the last request's size with the comparison otherwise.
+    pub fn compare(&mut self, request: &CreateChatCompletionRequest) -> Option<(Reuse, usize)> {
+        let mut parts = Vec::with_capacity(request.messages.len() + 1);
+        parts.push(encode(&request.model, &request.tools));
+        for message in &request.messages {
+            parts.push(encode(&request.model, message));
+        }
+        let previous = std::mem::replace(&mut self.parts, parts);
+        let model = std::mem::replace(&mut self.model, request.model.clone());
+        if previous.is_empty() {
+            return None;
+        }
+        let previous_total = previous.iter().map(Vec::len).sum();
+        let total = self.parts.iter().map(Vec::len).sum();
+        // The cache is kept per model, so nothing carries over to another one.
+        if model != request.model {
+            let reuse = Reuse {
+                same: 0,
+                total,
+                changed: Some(Change::Model),
+            };
+            return Some((reuse, previous_total));
+        }
+        let (same, changed) = common_prefix(&previous, &self.parts);
+        // Starting a new conversation changes everything on purpose. It's only worth a warning
+        // when the conversation went on, with messages from after the change sent again.
+        let continued = |part: usize| previous[part + 1..].iter().any(|p| self.parts.contains(p));
+        let changed = changed
+            .filter(|&part| continued(part))
+            .map(|part| match part {
+                0 => Change::Tools,
+                n => Change::Message {
+                    index: n - 1,
+                    system: matches!(
+                        request.messages[n - 1],
+                        ChatCompletionRequestMessage::System(_)
+                            | ChatCompletionRequestMessage::Developer(_)
+                    ),
+                },
+            });
+        let reuse = Reuse {
+            same,
+            total,
+            changed,
+        };
+        Some((reuse, previous_total))
+    }
+}
+
+/// The number of leading tokens `old` and `new` share, and the first part of `old` that isn't
+/// all of it repeated in `new`, if there's one.
+fn common_prefix(old: &[Vec<usize>], new: &[Vec<usize>]) -> (usize, Option<usize>) {
+    let mut same = 0;
+    for (i, old_part) in old.iter().enumerate() {
+        let Some(new_part) = new.get(i) else {
+            return (same, Some(i));
+        };
+        let shared = old_part
+            .iter()
+            .zip(new_part)
+            .take_while(|(a, b)| a == b)
+            .count();
+        same += shared;
+        if shared < old_part.len() || old_part.len() < new_part.len() {
+            // A part that grew is still a change to it: what followed it last time is gone.
+            let rest_differs = shared < old_part.len() || i + 1 < old.len();
+            return (same, rest_differs.then_some(i));
+        }
+    }
+    (same, None)
+}
+
+/// A part's tokens as they'd be sent, JSON and all, which is close enough to what the provider
+/// sees to find where two requests part.
+fn encode(model: &str, part: &impl serde::Serialize) -> Vec<usize> {
+    tokens::encode(model, &serde_json::to_string(part).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn request(model: &str, messages: &[Value]) -> CreateChatCompletionRequest {
+        serde_json::from_value(json!({"model": model, "messages": messages})).unwrap()
+    }
+
+    fn system(text: &str) -> Value {
+        json!({"role": "system", "content": text})
+    }
+
+    fn user(text: &str) -> Value {
+        json!({"role": "user", "content": text})
+    }
+
+    fn assistant(text: &str) -> Value {
+        json!({"role": "assistant", "content": text})
+    }
+
+    #[test]
+    fn shared_tokens_are_counted_up_to_the_first_difference() {
+        let old = vec![vec![1, 2], vec![3, 4, 5]];
+        assert_eq!(common_prefix(&old, &old), (5, None));
+        // Added to the end: everything before is reused.
+        assert_eq!(
+            common_prefix(&old, &[vec![1, 2], vec![3, 4, 5], vec![6]]),
+            (5, None)
+        );
+        // The last part grew, which is how an unfinished answer carries on.
+        assert_eq!(
+            common_prefix(&old, &[vec![1, 2], vec![3, 4, 5, 6]]),
+            (5, None)
+        );
+        assert_eq!(
+            common_prefix(&old, &[vec![1, 2], vec![3, 9, 5]]),
+            (3, Some(1))
+        );
+        assert_eq!(common_prefix(&old, &[vec![1, 2]]), (2, Some(1)));
+        // A part that grew with more after it changed what followed it.
+        assert_eq!(
+            common_prefix(&[vec![1], vec![2]], &[vec![1, 7], vec![2]]),
+            (1, Some(0))
+        );
+    }
+
+    #[test]
+    fn the_first_request_has_nothing_to_compare() {
+        let mut tracker = PrefixTracker::default();
+        assert!(tracker.compare(&request("m", &[user("hi")])).is_none());
+    }
+
+    #[test]
+    fn a_continued_conversation_reuses_all_of_the_last_request() {
+        let mut tracker = PrefixTracker::default();
+        let first = [system("Be brief."), user("hi")];
+        tracker.compare(&request("m", &first));
+        let (reuse, previous) = tracker
+            .compare(&request(
+                "m",
+                &[
+                    system("Be brief."),
+                    user("hi"),
+                    assistant("hello"),
+                    user("more"),
+                ],
+            ))
+            .unwrap();
+        assert_eq!(reuse.same, previous);
+        assert!(reuse.total > previous);
+        assert_eq!(reuse.changed, None);
+    }
+
+    #[test]
+    fn an_edited_system_prompt_is_named() {
+        let mut tracker = PrefixTracker::default();
+        tracker.compare(&request(
+            "m",
+            &[system("Be brief."), user("hi"), assistant("hello")],
+        ));
+        let (reuse, previous) = tracker
+            .compare(&request(
+                "m",
+                &[
+                    system("Be verbose."),
+                    user("hi"),
+                    assistant("hello"),
+                    user("more"),
+                ],
+            ))
+            .unwrap();
+        assert!(reuse.same < previous);
+        assert_eq!(
+            reuse.changed,
+            Some(Change::Message {
+                index: 0,
+                system: true
+            })
+        );
+    }
+
+    #[test]
+    fn a_new_conversation_is_not_a_change() {
+        let mut tracker = PrefixTracker::default();
+        tracker.compare(&request("m", &[user("hi"), assistant("hello")]));
+        let (reuse, _) = tracker.compare(&request("m", &[user("other")])).unwrap();
+        assert_eq!(reuse.changed, None);
+    }
+
+    #[test]
+    fn another_model_reuses_nothing() {
+        let mut tracker = PrefixTracker::default();
+        tracker.compare(&request("a", &[user("hi")]));
+        let (reuse, _) = tracker.compare(&request("b", &[user("hi")])).unwrap();
+        assert_eq!(reuse.same, 0);
+        assert_eq!(reuse.changed, Some(Change::Model));
+    }
+
+    #[test]
+    fn only_a_cacheable_loss_is_worth_a_warning() {
+        let reuse = |same| Reuse {
+            same,
+            total: 5000,
+            changed: Some(Change::Message {
+                index: 2,
+                system: false,
+            }),
+        };
+        assert_eq!(reuse(0).warning(CACHE_MIN_TOKENS - 1), None);
+        assert_eq!(reuse(3000).warning(3500), None);
+        assert_eq!(
+            reuse(1000).warning(4000).as_deref(),
+            Some("(message 2 of the request changed, so only the first 1,000 of the last request's 4,000 tokens can come from the prompt cache)")
+        );
+        let unchanged = Reuse {
+            changed: None,
+            ..reuse(0)
+        };
+        assert_eq!(unchanged.warning(4000), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Length presets: `:brief`, `:normal` and `:detailed` each set max_tokens, the temperature and an
+//! instruction added to the system prompt, all at once. The instruction belongs to the preset
+//! rather than being written into the prompt, so switching replaces it instead of adding another.
+
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    msg::{self, Msg},
+    settings::MaxTokens,
+    AppState,
+};
+
+/// One preset in the `[presets]` table of the config. Anything left out keeps the built-in value;
+/// an empty instruction means none.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PresetConfig {
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    pub instruction: Option<String>,
+}
+
+/// The `[presets]` table of the config.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PresetsConfig {
+    pub brief: PresetConfig,
+    pub normal: PresetConfig,
+    pub detailed: PresetConfig,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Preset {
+    Brief,
+    /// What the config sets, without an instruction.
+    #[default]
+    Normal,
+    Detailed,
+}
+
+/// What a preset sets.
+pub struct Resolved {
+    pub max_tokens: MaxTokens,
+    pub temperature: Option<f32>,
+    pub instruction: Option<String>,
+}
+
+impl Preset {
+    pub fn parse(name: &str) -> Option<Preset> {
+        match name {
+            "brief" => Some(Preset::Brief),
+            "normal" => Some(Preset::Normal),
+            "detailed" => Some(Preset::Detailed),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Brief => "brief",
+            Preset::Normal => "normal",
+            Preset::Detailed => "detailed",
+        }
+    }
+
+    /// The built-in values, overridden by the config's.
+    pub fn resolve(self, config: &Config) -> Resolved {
+        let (max_tokens, temperature, instruction, custom) = match self {
+            Preset::Brief => (
+                MaxTokens::Fixed(300),
+                Some(0.3),
+                Some("Answer in at most 3 sentences."),
+                &config.presets.brief,
+            ),
+            Preset::Normal => (
+                config.max_tokens.map_or(MaxTokens::Auto, MaxTokens::Fixed),
+                None,
+                None,
+                &config.presets.normal,
+            ),
+            Preset::Detailed => (MaxTokens::Fixed(4096), None, None, &config.presets.detailed),
+        };
+        Resolved {
+            max_tokens: custom.max_tokens.map_or(max_tokens, MaxTokens::Fixed),
+            temperature: custom.temperature.or(temperature),
+            instruction: match custom.instruction {
+                Some(ref text) => Some(text.clone()).filter(|t| !t.trim().is_empty()),
+                None => instruction.map(str::to_string),
+            },
+        }
+    }
+}
+
+/// Switches to `preset`, replacing whatever the last one set.
+pub fn apply(state: &mut AppState, preset: Preset) {
+    let resolved = preset.resolve(&state.config);
+    state.max_tokens = resolved.max_tokens;
+    state.temperature = resolved.temperature;
+    state.preset = preset;
+}
+
+/// What the current preset has set, for after switching.
+pub fn describe(state: &AppState) -> String {
+    let (max_tokens, _) = state.max_tokens.resolve(&state.model);
+    let temperature = match state.temperature {
+        Some(t) => t.to_string(),
+        None => msg::get(Msg::PresetModelDefault).to_string(),
+    };
+    let instruction = match instruction(state) {
+        Some(text) => msg::fmt(Msg::PresetInstruction, &[("text", &text)]),
+        None => msg::get(Msg::PresetNoInstruction).to_string(),
+    };
+    msg::fmt(
+        Msg::PresetDescribe,
+        &[
+            ("name", &state.preset.name()),
+            ("max_tokens", &max_tokens),
+            ("temperature", &temperature),
+            ("instruction", &instruction),
+        ],
+    )
+}
+
+/// The instruction the current preset adds to the system prompt, if any.
+pub fn instruction(state: &AppState) -> Option<String> {
+    state.preset.resolve(&state.config).instruction
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    sensitive::{self, SensitiveFiles},
+};
+
+/// Marks the root of a project, like `.git`.
+pub const FILE_NAME: &str = ".gpt-cli.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ProjectFile {
+    name: Option<String>,
+    system: Option<String>,
+    model: Option<String>,
+    prompt_prefix: Option<String>,
+    prompt_suffix: Option<String>,
+    files: Vec<PathBuf>,
+}
+
+/// The `.gpt-cli.toml` found in the working directory or one of its parents.
+#[derive(Debug)]
+pub struct Project {
+    pub root: PathBuf,
+    /// Its sessions are saved under this, so each project only lists its own.
+    pub namespace: String,
+    /// Sent as the system prompt of every request.
+    pub system: Option<String>,
+    /// The model to start with, unless --model says otherwise.
+    pub model: Option<String>,
+    /// Used instead of the config's, so a team can share its boilerplate.
+    pub prompt_prefix: Option<String>,
+    pub prompt_suffix: Option<String>,
+    /// Sent with the system prompt, read again for every request so they're never stale.
+    /// Relative paths are from `root`. One that looks like it holds secrets needs a `!` in front.
+    pub files: Vec<PathBuf>,
+}
+
+impl Project {
+    /// The nearest `.gpt-cli.toml` at or above `dir`, if there is one.
+    pub fn find(dir: &Path) -> Result<Option<Project>> {
+        let Some(root) = dir.ancestors().find(|d| d.join(FILE_NAME).is_file()) else {
+            return Ok(None);
+        };
+        let path = root.join(FILE_NAME);
+        let text = fs::read_to_string(&path)?;
+        let file: ProjectFile = toml::from_str(&text)
+            .map_err(|e| AppError::Config(format!("{}: {e}", path.display())))?;
+        // Named after the directory, with a hash of its path so two checkouts called "app" don't
+        // share sessions. A name set in the file is used as it is, so checkouts can share on purpose.
+        let namespace = match file.name {
+            Some(name) => name,
+            None => {
+                let dir_name = root
+                    .file_name()
+                    .map_or_else(|| "root".into(), |n| n.to_string_lossy());
+                let hash = Sha256::digest(root.to_string_lossy().as_bytes());
+                format!(
+                    "{dir_name}-{:02x}{:02x}{:02x}{:02x}",
+                    hash[0], hash[1], hash[2], hash[3]
+                )
+            }
+        };
+        if namespace.is_empty() || namespace.contains(['/', '\\']) || namespace.starts_with('.') {
+            return Err(AppError::Config(msg::fmt(
+                Msg::ProjectBadName,
+                &[("path", &path.display()), ("name", &namespace)],
+            )));
+        }
+        Ok(Some(Project {
+            root: root.to_path_buf(),
+            namespace,
+            system: file.system,
+            model: file.model,
+            prompt_prefix: file.prompt_prefix,
+            prompt_suffix: file.prompt_suffix,
+            files: file.files,
+        }))
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.root.join(FILE_NAME)
+    }
+
+    /// A listed file's contents, unless it can't be sent.
+    pub fn read(&self, file: &Path, sensitive_files: &SensitiveFiles) -> Result<String> {
+        let file = file.to_string_lossy();
+        let (path, force) = sensitive::parse_forced(&file);
+        sensitive::read(&self.root.join(path), sensitive_files, force)
+    }
+
+    /// The system prompt followed by each listed file in a fence labelled with its name, or
+    /// `None` if there's neither. Files that can't be sent are left out.
+    pub fn system_prompt(&self, sensitive_files: &SensitiveFiles) -> Option<String> {
+        let mut prompt = self.system.clone().unwrap_or_default();
+        for block in self.file_blocks(sensitive_files) {
+            if !prompt.is_empty() {
+                prompt.push_str("\n\n");
+            }
+            prompt.push_str(&block);
+        }
+        (!prompt.is_empty()).then_some(prompt)
+    }
+
+    /// Each listed file that can be sent, as it goes in the system prompt.
+    pub fn file_blocks(&self, sensitive_files: &SensitiveFiles) -> Vec<String> {
+        self.files
+            .iter()
+            .filter_map(|file| {
+                let text = self.read(file, sensitive_files).ok()?;
+                Some(format!(
+                    "{}:\n```\n{}\n```",
+                    file.display(),
+                    text.trim_end()
+                ))
+            })
+            .collect()
+    }
+}
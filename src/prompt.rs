@@ -0,0 +1,254 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::{
+    budget,
+    msg::{self, Msg},
+    route, style,
+    tokens::{self, estimate_prompt_tokens},
+    usage::{self, fmt_thousands},
+    AppState,
+};
+
+pub const DEFAULT: &str = "{model|accent}> ";
+
+/// The REPL prompt, from `prompt` in the config. Placeholders are written `{name}`, styled with
+/// `{name|bold,cyan}`, and `{name:+text}` shows `text` only when `name` isn't empty, e.g.
+/// `{session|dim:+ [{session}]}`. `{{` and `}}` are literal braces.
+#[derive(Clone, Debug)]
+pub struct PromptTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Text(String),
+    Field {
+        field: Field,
+        styles: Vec<Style>,
+        // Shown instead of the value, and only when the value isn't empty.
+        alt: Option<Vec<Segment>>,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Field {
+    Model,
+    Session,
+    Title,
+    ContextTokens,
+    Cost,
+    BudgetLeft,
+}
+
+const FIELDS: &[(&str, Field)] = &[
+    ("model", Field::Model),
+    ("session", Field::Session),
+    ("title", Field::Title),
+    ("context_tokens", Field::ContextTokens),
+    ("cost", Field::Cost),
+    ("budget_left", Field::BudgetLeft),
+];
+
+#[derive(Clone, Copy, Debug)]
+enum Style {
+    Code(&'static str),
+    /// Whatever the theme picks out things with.
+    Accent,
+}
+
+const STYLES: &[(&str, Style)] = &[
+    ("bold", Style::Code("1")),
+    ("dim", Style::Code("2")),
+    ("italic", Style::Code("3")),
+    ("underline", Style::Code("4")),
+    ("red", Style::Code("31")),
+    ("green", Style::Code("32")),
+    ("yellow", Style::Code("33")),
+    ("blue", Style::Code("34")),
+    ("magenta", Style::Code("35")),
+    ("cyan", Style::Code("36")),
+    ("accent", Style::Accent),
+];
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        PromptTemplate::parse(DEFAULT).expect("the default prompt is valid")
+    }
+}
+
+impl TryFrom<String> for PromptTemplate {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        PromptTemplate::parse(&text)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PromptTemplate {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        PromptTemplate::try_from(text).map_err(serde::de::Error::custom)
+    }
+}
+
+impl PromptTemplate {
+    /// Checks every placeholder and style up front, so a typo is reported when the config loads.
+    pub fn parse(text: &str) -> Result<PromptTemplate, String> {
+        let mut chars = text.chars().peekable();
+        let segments = parse_segments(&mut chars, false)?;
+        Ok(PromptTemplate { segments })
+    }
+
+    pub fn render(&self, state: &AppState) -> String {
+        render(&self.segments, state)
+    }
+}
+
+fn parse_segments(chars: &mut Peekable<Chars>, nested: bool) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            // Inside `:+...`, a closing brace always ends it.
+            '}' if nested => {
+                if !text.is_empty() {
+                    segments.push(Segment::Text(text));
+                }
+                return Ok(segments);
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => return Err(brace_error(Msg::PromptStrayClose)),
+            '{' => {
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(parse_field(chars)?);
+            }
+            c => text.push(c),
+        }
+    }
+    if nested {
+        return Err(brace_error(Msg::PromptUnclosed));
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// A message about the braces, which the catalog can't hold as they are.
+fn brace_error(message: Msg) -> String {
+    msg::fmt(message, &[("open", &'{'), ("close", &'}')])
+}
+
+// Parses `name|styles:+alt}`, just after the opening brace.
+fn parse_field(chars: &mut Peekable<Chars>) -> Result<Segment, String> {
+    let mut spec = String::new();
+    let mut alt = None;
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(':') if chars.peek() == Some(&'+') => {
+                chars.next();
+                alt = Some(parse_segments(chars, true)?);
+                break;
+            }
+            Some(c) => spec.push(c),
+            None => return Err(brace_error(Msg::PromptUnclosed)),
+        }
+    }
+    let (name, styles) = spec.split_once('|').unwrap_or((&spec, ""));
+    let name = name.trim();
+    let field = FIELDS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, f)| *f)
+        .ok_or_else(|| {
+            let known: Vec<_> = FIELDS.iter().map(|(n, _)| *n).collect();
+            msg::fmt(
+                Msg::PromptUnknownField,
+                &[
+                    ("name", &format!("{{{name}}}")),
+                    ("known", &known.join(", ")),
+                ],
+            )
+        })?;
+    let styles = styles
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            STYLES
+                .iter()
+                .find(|(n, _)| *n == s)
+                .map(|(_, style)| *style)
+                .ok_or_else(|| {
+                    let known: Vec<_> = STYLES.iter().map(|(n, _)| *n).collect();
+                    msg::fmt(
+                        Msg::PromptUnknownStyle,
+                        &[("style", &s), ("known", &known.join(", "))],
+                    )
+                })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Segment::Field { field, styles, alt })
+}
+
+fn render(segments: &[Segment], state: &AppState) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => out.push_str(text),
+            Segment::Field { field, styles, alt } => {
+                let value = value(*field, state);
+                let shown = match alt {
+                    Some(_) if value.is_empty() => continue,
+                    Some(alt) => render(alt, state),
+                    None => value,
+                };
+                if shown.is_empty() {
+                    continue;
+                }
+                let codes: Vec<&str> = styles
+                    .iter()
+                    .map(|style| match style {
+                        Style::Code(code) => code,
+                        Style::Accent => style::accent_code(),
+                    })
+                    .collect();
+                out.push_str(&style::wrap(&shown, &codes.join(";"), state.color));
+            }
+        }
+    }
+    out
+}
+
+// Empty when there's nothing to show, so `:+` can leave it out.
+fn value(field: Field, state: &AppState) -> String {
+    match field {
+        Field::Model if state.auto_route => route::AUTO.to_string(),
+        Field::Model => state.model.clone(),
+        Field::Session => state.session.clone().unwrap_or_default(),
+        Field::Title => state.title.clone().unwrap_or_default(),
+        Field::ContextTokens if state.context.is_empty() => String::new(),
+        Field::ContextTokens => {
+            let tokens = fmt_thousands(estimate_prompt_tokens(&state.model, &state.context) as u64);
+            match tokens::approximate(&state.model) {
+                true => format!("~{tokens}"),
+                false => tokens,
+            }
+        }
+        Field::Cost if usage::session().requests == 0 => String::new(),
+        Field::Cost => state.config.currency.format(usage::session().cost),
+        Field::BudgetLeft => budget::left(state)
+            .map(|left| state.config.currency.format(left.max(0.0)))
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,1351 @@
+use async_openai::{
+    config::OpenAIConfig,
+    error::{ApiError, OpenAIError},
+    types::{
+        ChatChoiceStream, ChatCompletionResponseStream, CreateChatCompletionRequest,
+        CreateChatCompletionStreamResponse, CreateEmbeddingRequestArgs, FinishReason,
+    },
+    Client,
+};
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    anthropic,
+    audit::{self, Outcome},
+    azure::{self, Azure},
+    config::Config,
+    endpoints,
+    error::{AppError, Result},
+    inflight, input, ledger,
+    models::{self, Capabilities, Encoding, ModelInfo},
+    msg::{self, Msg},
+    ratelimit, tokens,
+    usage::{self, Purpose, Usage},
+};
+
+// Keys from the [api_keys] table of the config, by provider name.
+static CONFIG_KEYS: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+
+/// Makes the keys in the config available to [`Provider::api_key`]. Only the first call counts.
+pub fn set_config_keys(keys: BTreeMap<String, String>) {
+    let _ = CONFIG_KEYS.set(keys);
+}
+
+/// The key named `name` in `[api_keys]`.
+pub fn config_key(name: &str) -> Option<String> {
+    CONFIG_KEYS.get().and_then(|keys| keys.get(name)).cloned()
+}
+
+// The OpenAI organization and project the client from [`client`] was built with.
+static CLIENT_ORG: OnceLock<(Option<String>, Option<String>)> = OnceLock::new();
+
+// Set by --dry-run and :curl dry, while nothing is to be sent.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Starts or ends a dry run, in which no request is sent: each fails straight away instead, so
+/// anything that would have gone out besides the message that's printed, like a title, doesn't.
+pub fn set_dry_run(on: bool) {
+    DRY_RUN.store(on, Ordering::Relaxed);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+fn not_sent() -> OpenAIError {
+    OpenAIError::InvalidArgument(msg::get(Msg::NotSentDryRun).to_string())
+}
+
+// Set once the key has been accepted, so it's only checked once a run.
+static AUTH_CHECKED: OnceLock<()> = OnceLock::new();
+
+// A slow answer isn't worth holding up startup for; the first request will tell.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// OpenRouter shows these on its rankings pages, so requests are attributed to the app.
+const REFERER: &str = "https://github.com/JMoogs/gpt-cli";
+const APP_TITLE: &str = "gpt-cli";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    OpenAI,
+    OpenRouter,
+    Anthropic,
+    Gemini,
+}
+
+impl Provider {
+    /// What talks to the provider's chat API.
+    pub fn backend(self) -> &'static dyn Backend {
+        static OPENAI: OpenAICompatible = OpenAICompatible(Provider::OpenAI);
+        static OPENROUTER: OpenAICompatible = OpenAICompatible(Provider::OpenRouter);
+        static GEMINI: OpenAICompatible = OpenAICompatible(Provider::Gemini);
+        match self {
+            Provider::OpenAI => &OPENAI,
+            Provider::OpenRouter => &OPENROUTER,
+            Provider::Anthropic => &anthropic::Anthropic,
+            Provider::Gemini => &GEMINI,
+        }
+    }
+
+    /// The environment variable holding the API key.
+    pub fn key_var(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "OPENAI_API_KEY",
+            Provider::OpenRouter => "OPENROUTER_API_KEY",
+            Provider::Anthropic => "ANTHROPIC_API_KEY",
+            Provider::Gemini => "GEMINI_API_KEY",
+        }
+    }
+
+    /// The provider's API key: its environment variable, or else its entry in `[api_keys]`.
+    pub fn api_key(self) -> Option<String> {
+        env::var(self.key_var())
+            .ok()
+            .or_else(|| config_key(self.name()))
+    }
+
+    pub fn default_base(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "https://api.openai.com/v1",
+            Provider::OpenRouter => "https://openrouter.ai/api/v1",
+            Provider::Anthropic => "https://api.anthropic.com/v1",
+            // Google's OpenAI-compatible endpoint.
+            Provider::Gemini => "https://generativelanguage.googleapis.com/v1beta/openai",
+        }
+    }
+
+    pub fn default_model(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "gpt-3.5-turbo",
+            Provider::OpenRouter => "openai/gpt-4o-mini",
+            Provider::Anthropic => "claude-3-5-sonnet-latest",
+            Provider::Gemini => "gemini-1.5-flash",
+        }
+    }
+
+    /// The cheap model used to name sessions.
+    pub fn title_model(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "gpt-4o-mini",
+            Provider::OpenRouter => "openai/gpt-4o-mini",
+            Provider::Anthropic => "claude-3-5-haiku-latest",
+            Provider::Gemini => "gemini-1.5-flash",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Provider> {
+        match name {
+            "openai" => Some(Provider::OpenAI),
+            "openrouter" => Some(Provider::OpenRouter),
+            "anthropic" => Some(Provider::Anthropic),
+            "gemini" => Some(Provider::Gemini),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "openai",
+            Provider::OpenRouter => "openrouter",
+            Provider::Anthropic => "anthropic",
+            Provider::Gemini => "gemini",
+        }
+    }
+
+    /// The provider that serves `model`: Claude models go to Anthropic, Gemini models to Google and
+    /// OpenAI's own to OpenAI, unless OpenRouter is configured, which serves everything else without a slash.
+    /// Anything else goes to `configured`.
+    pub fn for_model(model: &str, configured: Provider) -> Provider {
+        if model.contains('/') {
+            configured
+        } else if model.starts_with("claude") {
+            Provider::Anthropic
+        } else if model.starts_with("gemini") {
+            Provider::Gemini
+        } else if configured == Provider::OpenRouter {
+            Provider::OpenRouter
+        } else if ["gpt-", "o1", "o3", "o4", "chatgpt-", "ft:"]
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+        {
+            Provider::OpenAI
+        } else {
+            configured
+        }
+    }
+}
+
+/// A provider's chat API: where requests go, how they're authenticated and shaped, and how the
+/// answer streams back. Requests are built in OpenAI's shape for all of them, and each backend
+/// sends them as its API wants.
+pub trait Backend: Sync {
+    /// Where chat requests for `model` go, under the API's base URL.
+    fn chat_url(&self, base: &str, model: &str, config: &Config) -> String;
+
+    /// The headers that authenticate a request with `key`, and say who's billed for it.
+    fn headers(&self, key: &str, config: &Config) -> Vec<(&'static str, String)>;
+
+    /// The body sent for `request`, recorded as the JSON of a [`CreateChatCompletionRequest`].
+    fn body(&self, request: &Value) -> Value;
+
+    /// Sends `request` to the API at `base` and streams the answer back as deltas, with what it
+    /// used among them.
+    fn stream<'a>(
+        &'a self,
+        request: CreateChatCompletionRequest,
+        base: String,
+        config: &'a Config,
+    ) -> BoxFuture<'a, std::result::Result<DeltaStream, OpenAIError>>;
+}
+
+/// The APIs that take OpenAI's requests as they are.
+pub struct OpenAICompatible(Provider);
+
+impl Backend for OpenAICompatible {
+    fn chat_url(&self, base: &str, _model: &str, _config: &Config) -> String {
+        format!("{base}/chat/completions")
+    }
+
+    fn headers(&self, key: &str, config: &Config) -> Vec<(&'static str, String)> {
+        let mut headers = vec![("Authorization", format!("Bearer {key}"))];
+        // Other providers don't know OpenAI's organizations.
+        if self.0 == Provider::OpenAI {
+            if let Some(ref organization) = config.organization {
+                headers.push(("OpenAI-Organization", organization.clone()));
+            }
+            if let Some(ref project) = config.project {
+                headers.push(("OpenAI-Project", project.clone()));
+            }
+        }
+        headers
+    }
+
+    fn body(&self, request: &Value) -> Value {
+        // What a streamed request is sent with.
+        let mut body = request.clone();
+        body["stream"] = Value::Bool(true);
+        body
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: CreateChatCompletionRequest,
+        base: String,
+        config: &'a Config,
+    ) -> BoxFuture<'a, std::result::Result<DeltaStream, OpenAIError>> {
+        async move {
+            let client = build_client(self.0, base, config)
+                .map_err(|e| OpenAIError::InvalidArgument(e.to_string()))?;
+            Ok(deltas(client.chat().create_stream(request).await?))
+        }
+        .boxed()
+    }
+}
+
+/// What talks to `provider`'s API at `base`: Azure's, for a base URL on an Azure host, and
+/// otherwise the provider's own.
+pub fn backend_at(provider: Provider, base: &str) -> &'static dyn Backend {
+    match azure::is_azure(base) {
+        true => &Azure,
+        false => provider.backend(),
+    }
+}
+
+/// The environment variable holding the key for `provider`'s API at `base`.
+pub fn key_var_at(provider: Provider, base: &str) -> &'static str {
+    match azure::is_azure(base) {
+        true => azure::KEY_VAR,
+        false => provider.key_var(),
+    }
+}
+
+/// The key for `provider`'s API at `base`, see [`key_var_at`].
+pub fn api_key_at(provider: Provider, base: &str) -> Option<String> {
+    match azure::is_azure(base) {
+        true => azure::api_key(),
+        false => provider.api_key(),
+    }
+}
+
+/// A piece of a streamed answer, the same whichever provider sent it.
+#[derive(Debug)]
+pub enum Delta {
+    Text(String),
+    /// Part of a tool call. Calls arrive in pieces, each saying which call it belongs to.
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: Option<String>,
+    },
+    Usage(Usage),
+    /// The model declined to answer, explaining why.
+    Refusal(String),
+    /// Why the model stopped, as the API names it, e.g. "stop" or "length".
+    Finish(String),
+    /// A whole other answer, when the provider sent more than one.
+    Candidate(Choice),
+    /// The base URL that's answering, first in the stream when there are endpoints to fail over between.
+    Endpoint(String),
+}
+
+pub type DeltaStream = BoxStream<'static, std::result::Result<Delta, OpenAIError>>;
+
+// How many times a request the API turned down for its rate limit is sent again.
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Sends a request to whichever provider serves its model and streams the answer back, once
+/// there's a slot for it under the in-flight cap and the rate limiter allows it. A request turned
+/// down for the rate limit before anything arrived is sent again once the API's wait is over.
+///
+/// What the answer used is counted towards the session's `:stats` as `purpose`, estimated when
+/// the provider doesn't say. Nothing is sent until the stream is first polled, and a request
+/// that can't be made fails with the stream's first item.
+pub fn stream(
+    client: &Client<OpenAIConfig>,
+    request: CreateChatCompletionRequest,
+    config: &Config,
+    purpose: Purpose,
+) -> DeltaStream {
+    if dry_run() {
+        return futures::stream::once(async { Err(not_sent()) }).boxed();
+    }
+    let (client, config) = (client.clone(), config.clone());
+    let prompt = tokens::estimate_request_tokens(&request);
+    let tokens = prompt + request.max_completion_tokens.unwrap_or_default();
+    let tally = Tally {
+        purpose,
+        model: request.model.clone(),
+        prompt,
+        text: String::new(),
+        billed: false,
+    };
+    let deltas = futures::stream::once(async move {
+        let permit = inflight::acquire().await;
+        let mut retries = 0;
+        let stream = loop {
+            ratelimit::acquire(tokens).await;
+            let (first, rest) = match open(&client, request.clone(), &config).await {
+                Ok(mut stream) => (stream.next().await, Some(stream)),
+                Err(err) => (Some(Err(err)), None),
+            };
+            match first {
+                Some(Err(ref err)) if retries < RATE_LIMIT_RETRIES => {
+                    if let Some(wait) = ratelimit::rate_limit_wait(err) {
+                        ratelimit::backoff(wait);
+                        retries += 1;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            break futures::stream::iter(first).chain(futures::stream::iter(rest).flatten());
+        };
+        // The slot goes with the stream, so the request counts until its answer is read or dropped.
+        stream.map(move |delta| {
+            let _slot = &permit;
+            if let Err(ref err) = delta {
+                if let Some(wait) = ratelimit::rate_limit_wait(err) {
+                    ratelimit::backoff(wait);
+                }
+            }
+            delta
+        })
+    })
+    .flatten();
+    futures::stream::unfold(Some((deltas.boxed(), tally)), |state| async move {
+        let (mut deltas, mut tally) = state?;
+        match deltas.next().await {
+            Some(delta) => {
+                if let Ok(ref delta) = delta {
+                    tally.note(delta);
+                }
+                Some((delta, Some((deltas, tally))))
+            }
+            None => None,
+        }
+    })
+    .boxed()
+}
+
+// Counts what a request used towards the session as its answer streams in.
+struct Tally {
+    purpose: Purpose,
+    model: String,
+    // Estimated, for a provider that doesn't report usage.
+    prompt: u32,
+    text: String,
+    billed: bool,
+}
+
+impl Tally {
+    fn note(&mut self, delta: &Delta) {
+        match delta {
+            Delta::Text(text) => self.text.push_str(text),
+            Delta::Usage(used) => {
+                usage::bill(self.purpose, used, &self.model);
+                self.billed = true;
+            }
+            Delta::Endpoint(base) => usage::session().record_endpoint(base),
+            _ => {}
+        }
+    }
+}
+
+// Once the stream ends or is dropped, as it is when an answer is stopped, a provider that
+// streamed text without saying what it used is taken to have used about what the request and
+// the text come to.
+impl Drop for Tally {
+    fn drop(&mut self) {
+        if !self.billed && !self.text.is_empty() {
+            let estimate = Usage {
+                prompt: self.prompt,
+                cached: 0,
+                completion: tokens::count_tokens(&self.model, &self.text),
+            };
+            usage::bill(self.purpose, &estimate, &self.model);
+        }
+    }
+}
+
+async fn open(
+    client: &Client<OpenAIConfig>,
+    request: CreateChatCompletionRequest,
+    config: &Config,
+) -> std::result::Result<DeltaStream, OpenAIError> {
+    let provider = Provider::for_model(&request.model, config.provider);
+    if provider == config.provider && endpoints::enabled() {
+        return failover(provider, request, config).await;
+    }
+    let api_base = match provider == config.provider {
+        true => api_base(config),
+        false => provider.default_base().to_string(),
+    };
+    // The client is built for one provider and organization, sending OpenAI's way; anything else
+    // needs its own.
+    if provider == client_provider(config)
+        && !org_changed(provider, config)
+        && !azure::is_azure(&api_base)
+    {
+        return Ok(deltas(client.chat().create_stream(request).await?));
+    }
+    if api_key_at(provider, &api_base).is_none() {
+        return Err(OpenAIError::InvalidArgument(msg::fmt(
+            Msg::ProviderNoKey,
+            &[
+                ("var", &key_var_at(provider, &api_base)),
+                ("provider", &provider.name()),
+                ("model", &request.model),
+            ],
+        )));
+    }
+    backend_at(provider, &api_base)
+        .stream(request, api_base, config)
+        .await
+}
+
+/// Sends the request to the first of the endpoints that answers, saying so on stderr when one
+/// doesn't. Once an endpoint has answered, the request stays with it.
+async fn failover(
+    provider: Provider,
+    request: CreateChatCompletionRequest,
+    config: &Config,
+) -> std::result::Result<DeltaStream, OpenAIError> {
+    let bases = endpoints::order();
+    let mut last_error = None;
+    for (i, base) in bases.iter().enumerate() {
+        let start = Instant::now();
+        let connected = backend_at(provider, base)
+            .stream(request.clone(), base.clone(), config)
+            .await;
+        // A failed request usually fails with the stream's first item, before anything is said.
+        let (first, rest) = match connected {
+            Ok(mut stream) => (stream.next().await, Some(stream)),
+            Err(err) => (Some(Err(err)), None),
+        };
+        let err = match first {
+            Some(Err(err)) => err,
+            first => {
+                endpoints::succeeded(base, start.elapsed());
+                let head = futures::stream::iter(
+                    std::iter::once(Ok(Delta::Endpoint(base.clone()))).chain(first),
+                );
+                return Ok(head.chain(futures::stream::iter(rest).flatten()).boxed());
+            }
+        };
+        // Anything else would fail the same way everywhere.
+        let Some(failure) = endpoints::failure(&err) else {
+            return Err(err);
+        };
+        let down = endpoints::failed(base, failure, &err);
+        if let Some(next) = bases.get(i + 1) {
+            let failed = match down {
+                true => Msg::EndpointFailedDown,
+                false => Msg::EndpointFailed,
+            };
+            let notice = msg::fmt(failed, &[("base", &base), ("error", &err), ("next", &next)]);
+            eprint!("{notice}{}", input::newline());
+        }
+        last_error = Some(err);
+    }
+    Err(last_error.unwrap_or_else(|| OpenAIError::InvalidArgument("No endpoints.".to_string())))
+}
+
+/// The deltas in an OpenAI-compatible stream.
+pub fn deltas(stream: ChatCompletionResponseStream) -> DeltaStream {
+    stream
+        .scan(Choices::default(), |choices, result| {
+            let deltas = match result {
+                Ok(response) => choices.push(response),
+                Err(e) => vec![Err(e)],
+            };
+            futures::future::ready(Some(futures::stream::iter(deltas)))
+        })
+        .flatten()
+        .boxed()
+}
+
+/// Another answer to the same request, sent as well as the first when a request asks for more
+/// than one. Only passed on once it's finished.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Choice {
+    pub index: u32,
+    pub text: String,
+    pub refusal: String,
+    pub finish_reason: Option<String>,
+}
+
+/// Keeps the choices in a stream apart by their index. The first is the answer, and its deltas
+/// are passed on as they arrive. Any other is put together here and passed on whole as a
+/// [`Delta::Candidate`] when it finishes, rather than being interleaved with the answer.
+#[derive(Default)]
+struct Choices {
+    others: BTreeMap<u32, Choice>,
+}
+
+impl Choices {
+    fn push(
+        &mut self,
+        response: CreateChatCompletionStreamResponse,
+    ) -> Vec<std::result::Result<Delta, OpenAIError>> {
+        let mut deltas = Vec::new();
+        for choice in response.choices {
+            // The usual case: a request for one answer only ever gets choice 0.
+            if choice.index == 0 {
+                answer_deltas(choice, &mut deltas);
+                continue;
+            }
+            let other = self.others.entry(choice.index).or_insert_with(|| Choice {
+                index: choice.index,
+                ..Choice::default()
+            });
+            other
+                .text
+                .push_str(choice.delta.content.as_deref().unwrap_or_default());
+            other
+                .refusal
+                .push_str(choice.delta.refusal.as_deref().unwrap_or_default());
+            if let Some(reason) = choice.finish_reason {
+                other.finish_reason = Some(finish_reason(reason).to_string());
+                if let Some(done) = self.others.remove(&choice.index) {
+                    deltas.push(Ok(Delta::Candidate(done)));
+                }
+            }
+        }
+        if let Some(ref usage) = response.usage {
+            deltas.push(Ok(Delta::Usage(Usage::from_api(usage))));
+        }
+        deltas
+    }
+}
+
+// The deltas of what's said in the first choice.
+fn answer_deltas(
+    choice: ChatChoiceStream,
+    deltas: &mut Vec<std::result::Result<Delta, OpenAIError>>,
+) {
+    if let Some(content) = choice.delta.content {
+        deltas.push(Ok(Delta::Text(content)));
+    }
+    if let Some(refusal) = choice.delta.refusal.filter(|r| !r.is_empty()) {
+        deltas.push(Ok(Delta::Refusal(refusal)));
+    }
+    if let Some(reason) = choice.finish_reason {
+        deltas.push(Ok(Delta::Finish(finish_reason(reason).to_string())));
+    }
+    // Otherwise a blocked answer just stops, looking like an empty or cut-off response.
+    if choice.finish_reason == Some(FinishReason::ContentFilter) {
+        deltas.push(Err(OpenAIError::ApiError(ApiError {
+            message: msg::get(Msg::ContentFiltered).to_string(),
+            r#type: None,
+            param: None,
+            code: Some("content_filter".to_string()),
+        })));
+    }
+    for chunk in choice.delta.tool_calls.into_iter().flatten() {
+        let (name, arguments) = chunk
+            .function
+            .map(|f| (f.name, f.arguments))
+            .unwrap_or_default();
+        deltas.push(Ok(Delta::ToolCall {
+            index: chunk.index as usize,
+            id: chunk.id,
+            name,
+            arguments,
+        }));
+    }
+}
+
+/// Embeds `inputs` with `model`, returning their vectors in order and the tokens used. Like
+/// answers, embeddings wait for a slot under the in-flight cap and for the rate limiter, are sent
+/// again when turned down for the rate limit, and count towards the session and the ledger.
+pub async fn embed(
+    client: &Client<OpenAIConfig>,
+    model: &str,
+    inputs: Vec<String>,
+    config: &Config,
+) -> std::result::Result<(Vec<Vec<f32>>, u32), OpenAIError> {
+    if dry_run() {
+        return Err(not_sent());
+    }
+    let _slot = inflight::acquire().await;
+    let tokens = inputs.iter().map(|i| tokens::count_tokens(model, i)).sum();
+    // The client is built for the configured provider, whose base an Azure resource can be.
+    let base = match client_provider(config) == config.provider {
+        true => api_base(config),
+        false => String::new(),
+    };
+    let mut retries = 0;
+    let response = loop {
+        ratelimit::acquire(tokens).await;
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(model)
+            .input(inputs.clone())
+            .build()?;
+        let response = match azure::is_azure(&base) {
+            true => {
+                let client = azure::client(&base, model, config);
+                client.embeddings().create(request).await
+            }
+            false => client.embeddings().create(request).await,
+        };
+        match response {
+            Err(err) if retries < RATE_LIMIT_RETRIES => {
+                let Some(wait) = ratelimit::rate_limit_wait(&err) else {
+                    return Err(err);
+                };
+                ratelimit::backoff(wait);
+                retries += 1;
+            }
+            result => break result?,
+        }
+    };
+    let used = Usage {
+        prompt: response.usage.prompt_tokens,
+        cached: 0,
+        completion: 0,
+    };
+    usage::bill(Purpose::Answer, &used, model);
+    ledger::record(config, &used, model, None);
+    let mut data = response.data;
+    data.sort_by_key(|e| e.index);
+    Ok((data.into_iter().map(|e| e.embedding).collect(), used.prompt))
+}
+
+/// Sends a request and waits for the whole answer, for requests whose output isn't shown as it streams.
+pub async fn complete(
+    client: &Client<OpenAIConfig>,
+    request: CreateChatCompletionRequest,
+    config: &Config,
+    purpose: Purpose,
+) -> std::result::Result<(String, Usage), OpenAIError> {
+    let start = Instant::now();
+    let mut text = String::new();
+    let mut usage = Usage::default();
+    let result = async {
+        let mut stream = stream(client, request.clone(), config, purpose);
+        let mut refusal = String::new();
+        while let Some(delta) = stream.next().await {
+            match delta? {
+                Delta::Text(t) => text.push_str(&t),
+                Delta::Usage(u) => usage = u,
+                Delta::Refusal(r) => refusal.push_str(&r),
+                Delta::ToolCall { .. }
+                | Delta::Finish(_)
+                | Delta::Candidate(_)
+                | Delta::Endpoint(_) => {}
+            }
+        }
+        if text.trim().is_empty() && !refusal.is_empty() {
+            return Err(refused(&refusal));
+        }
+        Ok(())
+    }
+    .await;
+    let outcome = Outcome {
+        usage: &usage,
+        duration: start.elapsed(),
+        response: &text,
+        error: result.as_ref().err().map(|e| e as &dyn Display),
+    };
+    audit::record(&config.audit, &request, outcome);
+    if result.is_ok() || !text.is_empty() {
+        ledger::record(config, &usage, &request.model, None);
+    }
+    result.map(|_| (text, usage))
+}
+
+/// Checks the configured provider's key by listing its models, which costs nothing. Only a
+/// rejected key is an error: anything else is left for the first real request to report.
+pub async fn check_auth(config: &Config) -> Result<()> {
+    if AUTH_CHECKED.get().is_some() || dry_run() {
+        return Ok(());
+    }
+    let provider = config.provider;
+    let request = list_models(config)?;
+    let response = match tokio::time::timeout(AUTH_TIMEOUT, request.send()).await {
+        Ok(Ok(response)) => response,
+        _ => return Ok(()),
+    };
+    let status = response.status();
+    if status != reqwest::StatusCode::UNAUTHORIZED && status != reqwest::StatusCode::FORBIDDEN {
+        if status.is_success() {
+            let _ = AUTH_CHECKED.set(());
+        }
+        return Ok(());
+    }
+
+    let organization = response
+        .headers()
+        .get("openai-organization")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    let detail = body["error"]["message"].as_str().unwrap_or_default();
+    let mut message = msg::fmt(
+        Msg::KeyRejected,
+        &[
+            ("provider", &provider.name()),
+            ("status", &status),
+            ("var", &key_var_at(provider, &api_base(config))),
+        ],
+    );
+    if !detail.is_empty() {
+        message.push_str(&format!("\n{detail}"));
+    }
+    if detail.to_lowercase().contains("organization") {
+        if let Some(organization) = organization.or_else(|| env::var("OPENAI_ORG_ID").ok()) {
+            message.push_str(&format!(" (organization: {organization})"));
+        }
+    }
+    Err(AppError::Command(message))
+}
+
+/// Lists the configured provider's models, as [`check_auth`] does, for `gpt doctor`: the status
+/// it answered with and how long that took. Not getting an answer at all is the error.
+pub async fn probe(config: &Config) -> Result<(reqwest::StatusCode, Duration)> {
+    let start = Instant::now();
+    let response = tokio::time::timeout(AUTH_TIMEOUT, list_models(config)?.send())
+        .await
+        .map_err(|_| {
+            AppError::Command(msg::fmt(
+                Msg::NoAnswerIn,
+                &[("seconds", &AUTH_TIMEOUT.as_secs())],
+            ))
+        })?
+        .map_err(|e| AppError::Command(e.to_string()))?;
+    Ok((response.status(), start.elapsed()))
+}
+
+/// A request for the configured provider's model list, authenticated with its key.
+fn list_models(config: &Config) -> Result<reqwest::RequestBuilder> {
+    let provider = config.provider;
+    let base = api_base(config);
+    let key = api_key_at(provider, &base).unwrap_or_default();
+    let url = match azure::is_azure(&base) {
+        true => azure::models_url(&base, config),
+        false => format!("{base}/models"),
+    };
+    Ok(backend_at(provider, &base)
+        .headers(&key, config)
+        .into_iter()
+        .fold(http_client()?.get(url), |request, (name, value)| {
+            request.header(name, value)
+        }))
+}
+
+/// The base URL requests go to: `api_base` from the config, the first of `api_bases`, or the
+/// provider's own.
+pub fn api_base(config: &Config) -> String {
+    config
+        .api_base
+        .clone()
+        .or_else(|| config.api_bases.first().cloned())
+        .unwrap_or_else(|| config.provider.default_base().to_string())
+}
+
+/// The provider the client from [`client`] talks to. Anthropic's API isn't OpenAI-compatible,
+/// so with Anthropic configured it's built for OpenAI, whose models can be picked with :model.
+fn client_provider(config: &Config) -> Provider {
+    match config.provider {
+        Provider::Anthropic => Provider::OpenAI,
+        provider => provider,
+    }
+}
+
+/// Builds the client for OpenAI-compatible APIs, or returns `None` when the configured provider's key isn't set.
+pub fn client(config: &Config) -> Result<Option<Client<OpenAIConfig>>> {
+    if api_key_at(config.provider, &api_base(config)).is_none() {
+        return Ok(None);
+    }
+    let provider = client_provider(config);
+    let api_base = match provider == config.provider {
+        true => api_base(config),
+        false => provider.default_base().to_string(),
+    };
+    let _ = CLIENT_ORG.set((config.organization.clone(), config.project.clone()));
+    build_client(provider, api_base, config).map(Some)
+}
+
+/// The OpenAI organization that `model` is billed to, if one is set.
+pub fn organization(model: &str, config: &Config) -> Option<String> {
+    match Provider::for_model(model, config.provider) {
+        Provider::OpenAI => config.organization.clone(),
+        _ => None,
+    }
+}
+
+// Whether `:org` has changed the organization since the client was built.
+fn org_changed(provider: Provider, config: &Config) -> bool {
+    provider == Provider::OpenAI
+        && CLIENT_ORG.get().is_some_and(|(organization, project)| {
+            *organization != config.organization || *project != config.project
+        })
+}
+
+fn build_client(
+    provider: Provider,
+    api_base: String,
+    config: &Config,
+) -> Result<Client<OpenAIConfig>> {
+    let mut openai_config = OpenAIConfig::new()
+        .with_api_key(provider.api_key().unwrap_or_default())
+        .with_api_base(api_base);
+    // Other providers don't know OpenAI's organizations.
+    if provider == Provider::OpenAI {
+        if let Some(ref organization) = config.organization {
+            openai_config = openai_config.with_org_id(organization);
+        }
+        if let Some(ref project) = config.project {
+            openai_config = openai_config.with_project_id(project);
+        }
+    }
+    let client = Client::with_config(openai_config);
+
+    Ok(match provider {
+        Provider::OpenRouter => client.with_http_client(http_client()?),
+        _ => client,
+    })
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    let mut headers = HeaderMap::new();
+    headers.insert("HTTP-Referer", HeaderValue::from_static(REFERER));
+    headers.insert("X-Title", HeaderValue::from_static(APP_TITLE));
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|e| AppError::Config(msg::fmt(Msg::HttpClientFailed, &[("error", &e)])))
+}
+
+#[derive(Deserialize)]
+struct Catalog {
+    data: Vec<CatalogModel>,
+}
+
+#[derive(Deserialize)]
+struct CatalogModel {
+    id: String,
+    context_length: Option<u32>,
+    pricing: CatalogPricing,
+    top_provider: Option<TopProvider>,
+    architecture: Option<Architecture>,
+    supported_parameters: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Architecture {
+    #[serde(default)]
+    input_modalities: Vec<String>,
+}
+
+// OpenRouter sends prices as strings of USD per token.
+#[derive(Deserialize)]
+struct CatalogPricing {
+    prompt: String,
+    completion: String,
+    input_cache_read: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TopProvider {
+    max_completion_tokens: Option<u32>,
+}
+
+/// Lists the ids of the fine-tuned models the key's organization can use.
+pub async fn fetch_fine_tuned(config: &Config) -> Result<Vec<String>> {
+    let response = list_models(config)?
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| AppError::Command(msg::fmt(Msg::FineTunedNotListed, &[("error", &e)])))?;
+    let list: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AppError::Command(msg::fmt(Msg::FineTunedNotListed, &[("error", &e)])))?;
+    let mut ids: Vec<String> = list["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m["id"].as_str())
+        .filter(|id| models::fine_tuned(id).is_some())
+        .map(str::to_string)
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+/// Fetches OpenRouter's model catalog and turns it into price table entries.
+pub async fn fetch_catalog(config: &Config) -> Result<Vec<ModelInfo>> {
+    let url = format!("{}/models", api_base(config));
+    let catalog = get_catalog(&http_client()?, &url).await.map_err(|e| {
+        AppError::Config(msg::fmt(
+            Msg::CatalogNotFetched,
+            &[("url", &url), ("error", &e)],
+        ))
+    })?;
+
+    Ok(catalog
+        .data
+        .into_iter()
+        .map(|m| {
+            let window = m.context_length.unwrap_or(models::GLOBAL_DEFAULT_OUTPUT);
+            let max_output = m
+                .top_provider
+                .and_then(|p| p.max_completion_tokens)
+                .unwrap_or(window);
+            // Known only when OpenRouter lists the parameters the model takes.
+            let capabilities = m.supported_parameters.map(|params| {
+                let takes = |name: &str| params.iter().any(|p| p == name);
+                Capabilities {
+                    vision: m
+                        .architecture
+                        .is_some_and(|a| a.input_modalities.iter().any(|i| i == "image")),
+                    tools: takes("tools"),
+                    json: takes("response_format") || takes("structured_outputs"),
+                }
+            });
+            let encoding = Encoding::guess(&m.id);
+            ModelInfo {
+                // Loaded once per run, so leaking the ids keeps them usable like the built-in table.
+                id: Box::leak(m.id.into_boxed_str()),
+                price: catalog_price(&m.pricing),
+                default_output: max_output.min(4096),
+                max_output,
+                context_window: window,
+                fine_tuned_price: None,
+                capabilities,
+                encoding,
+            }
+        })
+        .collect())
+}
+
+// Unknown unless both the input and output prices are, rather than free.
+fn catalog_price(pricing: &CatalogPricing) -> Option<[f64; 3]> {
+    let input = per_million(&pricing.prompt)?;
+    let cached = pricing.input_cache_read.as_deref().and_then(per_million);
+    Some([
+        input,
+        cached.unwrap_or(input),
+        per_million(&pricing.completion)?,
+    ])
+}
+
+// Per token, where the table is per million. OpenRouter marks a price it can't give, like that
+// of a router choosing the model itself, as negative.
+fn per_million(usd: &str) -> Option<f64> {
+    let usd: f64 = usd.trim().parse().ok()?;
+    (usd.is_finite() && usd >= 0.0).then_some(usd * 1_000_000.)
+}
+
+async fn get_catalog(client: &reqwest::Client, url: &str) -> reqwest::Result<Catalog> {
+    client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+fn finish_reason(reason: FinishReason) -> &'static str {
+    match reason {
+        FinishReason::Stop => "stop",
+        FinishReason::Length => "length",
+        FinishReason::ToolCalls => "tool_calls",
+        FinishReason::ContentFilter => "content_filter",
+        FinishReason::FunctionCall => "function_call",
+    }
+}
+
+/// The error for an answer the model refused to give.
+pub fn refused(refusal: &str) -> OpenAIError {
+    OpenAIError::ApiError(ApiError {
+        message: msg::fmt(Msg::ModelRefused, &[("refusal", &refusal.trim())]),
+        r#type: None,
+        param: None,
+        code: Some("refusal".to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn chunk(choices: Value) -> CreateChatCompletionStreamResponse {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "gpt-4o-mini",
+            "choices": choices,
+        }))
+        .unwrap()
+    }
+
+    fn text(index: u32, content: &str) -> Value {
+        json!({"index": index, "delta": {"content": content}, "finish_reason": null})
+    }
+
+    fn finish(index: u32, reason: &str) -> Value {
+        json!({"index": index, "delta": {}, "finish_reason": reason})
+    }
+
+    fn run(chunks: Vec<Value>) -> Vec<Delta> {
+        let mut choices = Choices::default();
+        chunks
+            .into_iter()
+            .flat_map(|c| choices.push(chunk(c)))
+            .map(|d| d.unwrap())
+            .collect()
+    }
+
+    fn answer(deltas: &[Delta]) -> String {
+        deltas
+            .iter()
+            .filter_map(|d| match d {
+                Delta::Text(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn candidates(deltas: Vec<Delta>) -> Vec<Choice> {
+        deltas
+            .into_iter()
+            .filter_map(|d| match d {
+                Delta::Candidate(c) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_single_choice_goes_straight_through() {
+        let deltas = run(vec![
+            json!([{"index": 0, "delta": {"role": "assistant", "content": ""}, "finish_reason": null}]),
+            json!([text(0, "Hel")]),
+            json!([text(0, "lo")]),
+            json!([finish(0, "stop")]),
+        ]);
+        assert_eq!(answer(&deltas), "Hello");
+        assert!(matches!(deltas.last(), Some(Delta::Finish(r)) if r == "stop"));
+        assert!(candidates(deltas).is_empty());
+    }
+
+    #[test]
+    fn other_choices_are_kept_apart_from_the_answer() {
+        let deltas = run(vec![
+            json!([text(0, "one "), text(1, "uno ")]),
+            json!([text(1, "dos"), text(0, "two")]),
+            json!([finish(1, "length")]),
+            json!([finish(0, "stop")]),
+        ]);
+        assert_eq!(answer(&deltas), "one two");
+        assert_eq!(
+            candidates(deltas),
+            [Choice {
+                index: 1,
+                text: "uno dos".to_string(),
+                refusal: String::new(),
+                finish_reason: Some("length".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn a_refusal_stays_with_its_choice() {
+        let deltas = run(vec![
+            json!([text(0, "Sure."), {"index": 1, "delta": {"refusal": "I can't"}, "finish_reason": null}]),
+            json!([finish(0, "stop"), finish(1, "stop")]),
+        ]);
+        assert!(!deltas.iter().any(|d| matches!(d, Delta::Refusal(_))));
+        assert_eq!(candidates(deltas)[0].refusal, "I can't");
+    }
+
+    #[test]
+    fn an_unfinished_choice_is_not_passed_on() {
+        let deltas = run(vec![
+            json!([text(0, "a"), text(2, "b")]),
+            json!([finish(0, "stop")]),
+        ]);
+        assert!(candidates(deltas).is_empty());
+    }
+
+    // Answers the first `limited` requests with a 429 saying to try again shortly, then streams
+    // "ok" as Anthropic would, returning the address and a count of the requests it got.
+    async fn rate_limited_server(limited: usize) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counted = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                read_request(&mut socket).await;
+                let response = match counted.fetch_add(1, Ordering::SeqCst) < limited {
+                    true => http(
+                        "429 Too Many Requests",
+                        "application/json",
+                        r#"{"error":{"type":"rate_limit_error","message":"Rate limited. Please try again in 10ms."}}"#,
+                    ),
+                    false => http(
+                        "200 OK",
+                        "text/event-stream",
+                        concat!(
+                            "data: {\"type\":\"content_block_delta\",\"index\":0,",
+                            "\"delta\":{\"type\":\"text_delta\",\"text\":\"ok\"}}\n\n",
+                            "data: {\"type\":\"message_stop\"}\n\n",
+                        ),
+                    ),
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (base, hits)
+    }
+
+    async fn read_request(socket: &mut tokio::net::TcpStream) {
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        while let Ok(n @ 1..) = socket.read(&mut buf).await {
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            let Some(end) = text.find("\r\n\r\n") else {
+                continue;
+            };
+            let length = text[..end]
+                .lines()
+                .find_map(|l| {
+                    l.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|v| v.trim().parse().unwrap_or(0))
+                })
+                .unwrap_or(0);
+            if request.len() >= end + 4 + length {
+                return;
+            }
+        }
+    }
+
+    fn http(status: &str, content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn claude_request() -> CreateChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "claude-3-5-haiku-latest",
+            "messages": [{"role": "user", "content": "hi"}],
+        }))
+        .unwrap()
+    }
+
+    async fn collect(base: String) -> std::result::Result<String, OpenAIError> {
+        let config = Config {
+            provider: Provider::Anthropic,
+            api_base: Some(base),
+            ..Config::default()
+        };
+        let mut deltas = stream(&Client::new(), claude_request(), &config, Purpose::Answer);
+        let mut text = String::new();
+        while let Some(delta) = deltas.next().await {
+            if let Delta::Text(t) = delta? {
+                text.push_str(&t);
+            }
+        }
+        Ok(text)
+    }
+
+    #[tokio::test]
+    async fn a_rate_limited_request_is_sent_again() {
+        let (base, hits) = rate_limited_server(2).await;
+        assert_eq!(collect(base).await.unwrap(), "ok");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn an_answer_counts_towards_the_session() {
+        let (base, _) = rate_limited_server(0).await;
+        let before = usage::session().requests;
+        collect(base).await.unwrap();
+        // Other tests may be counted at the same time, so this one is at least among them.
+        assert!(usage::session().requests > before);
+    }
+
+    #[tokio::test]
+    async fn retries_for_the_rate_limit_run_out() {
+        let (base, hits) = rate_limited_server(usize::MAX).await;
+        let err = collect(base).await.unwrap_err();
+        assert!(ratelimit::rate_limit_wait(&err).is_some());
+        assert_eq!(hits.load(Ordering::SeqCst), 1 + RATE_LIMIT_RETRIES as usize);
+    }
+
+    #[test]
+    fn only_the_answer_can_be_blocked() {
+        let mut choices = Choices::default();
+        let deltas = choices.push(chunk(json!([finish(1, "content_filter")])));
+        assert!(deltas.iter().all(|d| d.is_ok()));
+        let deltas = choices.push(chunk(json!([finish(0, "content_filter")])));
+        assert!(deltas.iter().any(|d| d.is_err()));
+    }
+
+    #[test]
+    fn a_catalog_price_that_is_not_one_is_unknown() {
+        let pricing = |prompt: &str, completion: &str, cached: Option<&str>| CatalogPricing {
+            prompt: prompt.to_string(),
+            completion: completion.to_string(),
+            input_cache_read: cached.map(str::to_string),
+        };
+        assert_eq!(
+            catalog_price(&pricing("0.0000025", "0.00001", Some("0.00000125"))),
+            Some([2.5, 1.25, 10.0])
+        );
+        assert_eq!(
+            catalog_price(&pricing("0", "0", None)),
+            Some([0.0, 0.0, 0.0])
+        );
+        assert_eq!(catalog_price(&pricing("-1", "-1", None)), None);
+        assert_eq!(catalog_price(&pricing("0.000001", "n/a", None)), None);
+        assert_eq!(catalog_price(&pricing("", "0.000001", None)), None);
+        // Without a usable cached price, cached input costs what input does.
+        assert_eq!(
+            catalog_price(&pricing("0.000001", "0.000002", Some("-1"))),
+            Some([1.0, 1.0, 2.0])
+        );
+    }
+
+    #[test]
+    fn each_provider_has_its_backend() {
+        let config = Config {
+            organization: Some("org-1".to_string()),
+            ..Config::default()
+        };
+        let header_names = |provider: Provider| -> Vec<&str> {
+            provider
+                .backend()
+                .headers("k", &config)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+        assert_eq!(
+            header_names(Provider::OpenAI),
+            ["Authorization", "OpenAI-Organization"]
+        );
+        // The organization is OpenAI's alone.
+        assert_eq!(header_names(Provider::OpenRouter), ["Authorization"]);
+        assert_eq!(header_names(Provider::Gemini), ["Authorization"]);
+        assert_eq!(
+            header_names(Provider::Anthropic),
+            ["x-api-key", "anthropic-version"]
+        );
+        assert_eq!(
+            Provider::Gemini
+                .backend()
+                .chat_url("https://x/v1", "gemini-1.5-flash", &config),
+            "https://x/v1/chat/completions"
+        );
+        assert_eq!(
+            Provider::Anthropic.backend().chat_url(
+                "https://x/v1",
+                "claude-3-5-haiku-latest",
+                &config
+            ),
+            "https://x/v1/messages"
+        );
+        // Whatever the provider, a base on Azure is sent Azure's way.
+        let azure = backend_at(Provider::OpenAI, "https://east.openai.azure.com");
+        assert_eq!(
+            azure.chat_url("https://east.openai.azure.com", "gpt-4o", &config),
+            "https://east.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(
+            key_var_at(Provider::OpenAI, "https://east.openai.azure.com"),
+            "AZURE_OPENAI_API_KEY"
+        );
+        assert_eq!(
+            key_var_at(Provider::OpenAI, "https://api.openai.com/v1"),
+            "OPENAI_API_KEY"
+        );
+
+        let request = json!({
+            "model": "claude-3-5-haiku-latest",
+            "max_completion_tokens": 100,
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "hi"},
+            ],
+        });
+        let body = Provider::OpenAI.backend().body(&request);
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["messages"], request["messages"]);
+        let body = Provider::Anthropic.backend().body(&request);
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["messages"][0]["role"], "user");
+    }
+}
@@ -0,0 +1,230 @@
+//! One-shot requests kept for later by `--queue` when there's no connection, and sent in the
+//! order they were queued by `gpt flush` or the next time gpt starts with one. Each keeps the
+//! request as it was built, with its model, and the provider settings of the time, so changing the
+//! config in the meantime doesn't change what's sent or where.
+
+use async_openai::types::CreateChatCompletionRequest;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    chat,
+    config::Config,
+    error::{AppError, Result},
+    fallback, fsutil,
+    msg::{self, Msg},
+    paths,
+    provider::{self, Provider},
+    safe, schema, style,
+    usage::Purpose,
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Entry {
+    pub id: u32,
+    pub queued: DateTime<Utc>,
+    /// The request as it would have been sent.
+    pub request: Value,
+    pub provider: Provider,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// The file the answer is appended to.
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Queue {
+    /// The number the next entry gets.
+    next: u32,
+    entries: Vec<Entry>,
+}
+
+/// How a flush went.
+#[derive(Debug, Default)]
+pub struct Flushed {
+    /// The requests sent, and where their answers went.
+    pub sent: Vec<(u32, PathBuf)>,
+    /// Rejected by the API, so dropped from the queue rather than tried again.
+    pub failed: usize,
+    pub left: usize,
+}
+
+fn file() -> PathBuf {
+    paths::data_dir().join("queue.json")
+}
+
+fn load() -> Result<Queue> {
+    let path = file();
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Queue::default()),
+        Err(e) => return Err(e.into()),
+    };
+    schema::decode(&text).map_err(|e| AppError::Command(e.describe(&path.display().to_string())))
+}
+
+fn save(queue: &Queue) -> Result<()> {
+    paths::ensure_dir(&paths::data_dir())?;
+    let json = schema::encode(queue, true).map_err(io::Error::from)?;
+    fsutil::write_atomic(&file(), &json)?;
+    Ok(())
+}
+
+/// Queues `request`, returning its number and the file its answer will go to: `output` if given,
+/// otherwise one of its own in the data directory.
+pub fn add(request: Value, config: &Config, output: Option<&Path>) -> Result<(u32, PathBuf)> {
+    let mut queue = load()?;
+    let id = queue.next.max(1);
+    queue.next = id + 1;
+    let output = match output {
+        // Relative to where gpt was run, not where it's flushed from.
+        Some(path) => env::current_dir()?.join(path),
+        None => paths::data_dir()
+            .join("queue")
+            .join(format!("answer-{id}.md")),
+    };
+    queue.entries.push(Entry {
+        id,
+        queued: Utc::now(),
+        request,
+        provider: config.provider,
+        api_base: config.api_base.clone(),
+        organization: config.organization.clone(),
+        project: config.project.clone(),
+        output: output.clone(),
+    });
+    save(&queue)?;
+    Ok((id, output))
+}
+
+/// The number of requests waiting.
+pub fn len() -> usize {
+    load().map_or(0, |queue| queue.entries.len())
+}
+
+/// Sends whatever is queued when gpt starts, saying where the answers went. Nothing is said when
+/// there's still no connection, beyond how many are waiting.
+pub async fn flush_on_start(config: &Config, stdout_tty: bool, color: bool) {
+    // The queue waits for a run that sends things.
+    if len() == 0 || provider::dry_run() {
+        return;
+    }
+    let mut status = chat::status_writer(stdout_tty);
+    let notices = match flush(config, false, color).await {
+        Ok(flushed) => {
+            let mut notices: Vec<String> = flushed
+                .sent
+                .iter()
+                .map(|(id, output)| {
+                    msg::fmt(Msg::QueuedSent, &[("id", id), ("path", &output.display())])
+                })
+                .collect();
+            match flushed.left {
+                0 => {}
+                1 => notices.push(msg::get(Msg::QueuedWaitingOne).to_string()),
+                n => notices.push(msg::fmt(Msg::QueuedWaiting, &[("count", &n)])),
+            }
+            notices
+        }
+        Err(e) => vec![msg::fmt(Msg::QueuedNotSent, &[("error", &e)])],
+    };
+    for notice in notices {
+        let _ = writeln!(status, "{}", style::dim(&notice, color));
+    }
+}
+
+/// Sends the queued requests in order, appending each answer to its file and, with `print`, also
+/// printing it. Stops at the first that can't get through, keeping it and those after it.
+pub async fn flush(config: &Config, print: bool, color: bool) -> Result<Flushed> {
+    // Each would fail as if the API had rejected it, and be dropped.
+    if provider::dry_run() {
+        return Err(AppError::Command(msg::get(Msg::NotSentDryRun).to_string()));
+    }
+    let mut queue = load()?;
+    let mut flushed = Flushed::default();
+    let total = queue.entries.len();
+    while let Some(entry) = queue.entries.first() {
+        let config = Config {
+            provider: entry.provider,
+            api_base: entry.api_base.clone(),
+            organization: entry.organization.clone(),
+            project: entry.project.clone(),
+            ..config.clone()
+        };
+        let request: CreateChatCompletionRequest = serde_json::from_value(entry.request.clone())
+            .map_err(|e| {
+                AppError::Command(msg::fmt(
+                    Msg::QueuedCorrupt,
+                    &[("id", &entry.id), ("error", &e)],
+                ))
+            })?;
+        let Some(client) = provider::client(&config)? else {
+            return Err(AppError::Command(msg::fmt(
+                Msg::QueuedNoKey,
+                &[("id", &entry.id), ("provider", &entry.provider.name())],
+            )));
+        };
+        let header = msg::fmt(
+            Msg::QueuedHeader,
+            &[
+                ("n", &(flushed.sent.len() + flushed.failed + 1)),
+                ("total", &total),
+                ("model", &request.model),
+                (
+                    "queued",
+                    &entry.queued.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                ),
+            ],
+        );
+        if print {
+            eprintln!("{}", style::dim(&header, color));
+        }
+        match provider::complete(&client, request, &config, Purpose::Answer).await {
+            Ok((text, _)) => {
+                append(&entry.output, &text)?;
+                if print {
+                    println!("{}\n", text.trim_end());
+                }
+                flushed.sent.push((entry.id, entry.output.clone()));
+            }
+            Err(err) if fallback::is_offline(&err) || fallback::is_retryable(&err) => {
+                if print {
+                    eprintln!("{}", msg::fmt(Msg::QueuedStays, &[("error", &err)]));
+                }
+                break;
+            }
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    msg::fmt(Msg::QueuedRejected, &[("id", &entry.id), ("error", &err)])
+                );
+                flushed.failed += 1;
+            }
+        }
+        queue.entries.remove(0);
+        save(&queue)?;
+    }
+    flushed.left = queue.entries.len();
+    Ok(flushed)
+}
+
+fn append(path: &Path, text: &str) -> Result<()> {
+    safe::check_write(path)?;
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        paths::ensure_dir(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", text.trim_end())?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use regex::Regex;
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    Message,
+};
+
+/// Picks part of the last answer for `:quote`: a paragraph by number, a range of lines like
+/// `10-20`, or the paragraph with the first match of `/regex/`.
+pub fn select(context: &[Message], selection: &str) -> Result<String> {
+    let answer = context
+        .iter()
+        .rev()
+        .find(|m| !m.is_user())
+        .ok_or_else(|| AppError::Command(msg::get(Msg::QuoteNoAnswer).to_string()))?;
+    let text = answer.content.trim_end();
+
+    if let Some(pattern) = selection
+        .strip_prefix('/')
+        .and_then(|p| p.strip_suffix('/'))
+        .filter(|p| !p.is_empty())
+    {
+        let re = Regex::new(pattern)
+            .map_err(|e| AppError::Command(msg::fmt(Msg::InvalidPattern, &[("error", &e)])))?;
+        return paragraphs(text)
+            .into_iter()
+            .find(|p| re.is_match(p))
+            .map(str::to_string)
+            .ok_or_else(|| {
+                AppError::Command(msg::fmt(Msg::QuoteNoMatch, &[("pattern", &pattern)]))
+            });
+    }
+
+    if let Some((from, to)) = selection.split_once('-') {
+        let (Ok(from), Ok(to)) = (from.trim().parse::<usize>(), to.trim().parse::<usize>()) else {
+            return Err(usage());
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        if from == 0 || from > to || to > lines.len() {
+            return Err(AppError::Command(msg::fmt(
+                Msg::QuoteBadRange,
+                &[("lines", &lines.len()), ("from", &from), ("to", &to)],
+            )));
+        }
+        return Ok(lines[from - 1..to].join("\n"));
+    }
+
+    let n: usize = selection.parse().map_err(|_| usage())?;
+    let paragraphs = paragraphs(text);
+    match n.checked_sub(1).and_then(|i| paragraphs.get(i)) {
+        Some(paragraph) => Ok(paragraph.to_string()),
+        None if paragraphs.len() == 1 => Err(AppError::Command(
+            msg::get(Msg::QuoteOneParagraph).to_string(),
+        )),
+        None => Err(AppError::Command(msg::fmt(
+            Msg::QuoteParagraphs,
+            &[("count", &paragraphs.len())],
+        ))),
+    }
+}
+
+/// The text as a Markdown quote, to start the next message with.
+pub fn block(text: &str) -> String {
+    text.lines()
+        .map(|line| match line {
+            "" => ">".to_string(),
+            line => format!("> {line}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn usage() -> AppError {
+    AppError::Command(msg::get(Msg::QuoteUsage).to_string())
+}
+
+/// The text's paragraphs, split at blank lines. A fenced code block counts as one.
+fn paragraphs(text: &str) -> Vec<&str> {
+    let mut paragraphs = Vec::new();
+    let mut start = None;
+    let mut in_fence = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        match start {
+            Some(s) if blank && !in_fence => {
+                paragraphs.push(text[s..offset].trim_end());
+                start = None;
+            }
+            None if !blank => start = Some(offset),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    if let Some(s) = start {
+        paragraphs.push(text[s..].trim_end());
+    }
+    paragraphs
+}
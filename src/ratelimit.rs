@@ -0,0 +1,228 @@
+//! The client-side limit on requests and tokens per minute, shared by every request to a
+//! provider. [`provider::stream`](crate::provider::stream) waits here before each request and
+//! backs everything off when the API answers with a rate limit error.
+
+use async_openai::error::OpenAIError;
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    input,
+    msg::{self, Msg},
+};
+
+// Used when the API says we're rate limited but doesn't say for how long.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(20);
+
+static LIMITER: OnceLock<Mutex<RateLimiter>> = OnceLock::new();
+
+/// Sets the limits. Only the first call counts, and it has to come before the first request.
+pub fn init(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) {
+    let _ = LIMITER.set(Mutex::new(RateLimiter::new(
+        requests_per_minute,
+        tokens_per_minute,
+    )));
+}
+
+fn limiter() -> std::sync::MutexGuard<'static, RateLimiter> {
+    LIMITER
+        .get_or_init(|| Mutex::new(RateLimiter::new(None, None)))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Waits until a request using `tokens` tokens is allowed, then reserves it.
+pub async fn acquire(tokens: u32) {
+    let mut told = false;
+    loop {
+        // Someone else may reserve what was waited for, so it's checked again after the wait.
+        let wait = limiter().reserve(tokens);
+        if wait.is_zero() {
+            return;
+        }
+        if !told {
+            let seconds = format!("{:.1}", wait.as_secs_f64());
+            // It can come while a response streams, with the terminal in raw mode.
+            let notice = msg::fmt(Msg::RateLimitWait, &[("seconds", &seconds)]);
+            eprint!("{notice}{}", input::newline());
+            told = true;
+        }
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Blocks all requests for `wait`, e.g. after the API returned a 429.
+pub fn backoff(wait: Duration) {
+    limiter().backoff(wait);
+}
+
+pub fn describe() -> String {
+    limiter().describe()
+}
+
+#[derive(Clone, Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    per_sec: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Self {
+        let capacity = per_minute as f64;
+        Bucket {
+            capacity,
+            available: capacity,
+            per_sec: capacity / 60.,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_sec).min(self.capacity);
+        self.last = now;
+    }
+
+    /// How long until `amount` would be available. Requests bigger than the bucket only wait for a full one.
+    fn wait_for(&mut self, amount: f64) -> Duration {
+        self.refill();
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((amount - self.available) / self.per_sec)
+        }
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.refill();
+        self.available -= amount.min(self.capacity);
+    }
+}
+
+/// A client-side token bucket limiter for requests and tokens per minute.
+#[derive(Clone, Debug)]
+struct RateLimiter {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    blocked_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Self {
+        RateLimiter {
+            requests: requests_per_minute.filter(|&n| n > 0).map(Bucket::new),
+            tokens: tokens_per_minute.filter(|&n| n > 0).map(Bucket::new),
+            blocked_until: None,
+        }
+    }
+
+    /// Reserves a request using `tokens` tokens if it's allowed now, or says how long until it
+    /// would be.
+    fn reserve(&mut self, tokens: u32) -> Duration {
+        let mut wait = Duration::ZERO;
+        if let Some(until) = self.blocked_until {
+            wait = until.saturating_duration_since(Instant::now());
+        }
+        if let Some(ref mut bucket) = self.requests {
+            wait = wait.max(bucket.wait_for(1.));
+        }
+        if let Some(ref mut bucket) = self.tokens {
+            wait = wait.max(bucket.wait_for(tokens as f64));
+        }
+        if !wait.is_zero() {
+            return wait;
+        }
+        self.blocked_until = None;
+
+        if let Some(ref mut bucket) = self.requests {
+            bucket.take(1.);
+        }
+        if let Some(ref mut bucket) = self.tokens {
+            bucket.take(tokens as f64);
+        }
+        Duration::ZERO
+    }
+
+    fn backoff(&mut self, wait: Duration) {
+        self.blocked_until = Some(Instant::now() + wait);
+        // The server's view of our usage wins over ours.
+        if let Some(ref mut bucket) = self.requests {
+            bucket.available = 0.;
+        }
+        if let Some(ref mut bucket) = self.tokens {
+            bucket.available = 0.;
+        }
+    }
+
+    fn describe(&mut self) -> String {
+        let mut lines = Vec::new();
+        for (name, bucket) in [
+            ("requests", &mut self.requests),
+            ("tokens", &mut self.tokens),
+        ] {
+            match bucket {
+                Some(b) => {
+                    b.refill();
+                    let available = format!("{:.0}", b.available.max(0.));
+                    let capacity = format!("{:.0}", b.capacity);
+                    lines.push(msg::fmt(
+                        Msg::RateLimitAvailable,
+                        &[
+                            ("name", &name),
+                            ("available", &available),
+                            ("capacity", &capacity),
+                        ],
+                    ));
+                }
+                None => lines.push(msg::fmt(Msg::RateLimitUnlimited, &[("name", &name)])),
+            }
+        }
+        if let Some(until) = self.blocked_until {
+            let left = until.saturating_duration_since(Instant::now());
+            if !left.is_zero() {
+                let seconds = format!("{:.1}", left.as_secs_f64());
+                lines.push(msg::fmt(Msg::RateLimitBlocked, &[("seconds", &seconds)]));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// If `err` is a rate limit error, how long the API asked us to wait.
+///
+/// async-openai doesn't expose the response headers, so this reads the
+/// "Please try again in 1.5s" hint from the error message instead.
+pub fn rate_limit_wait(err: &OpenAIError) -> Option<Duration> {
+    let message = match err {
+        OpenAIError::ApiError(e)
+            if e.code.as_deref() == Some("rate_limit_exceeded")
+                || e.r#type.as_deref() == Some("requests")
+                || e.r#type.as_deref() == Some("tokens")
+                || e.r#type.as_deref() == Some("rate_limit_error") =>
+        {
+            e.message.clone()
+        }
+        OpenAIError::StreamError(e) if e.contains("429") => e.clone(),
+        _ => return None,
+    };
+    Some(parse_retry_hint(&message).unwrap_or(DEFAULT_BACKOFF))
+}
+
+fn parse_retry_hint(message: &str) -> Option<Duration> {
+    let rest = &message[message.find("try again in ")? + "try again in ".len()..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(rest.len());
+    let value: f64 = rest[..end].parse().ok()?;
+    if rest[end..].starts_with("ms") {
+        Some(Duration::from_secs_f64(value / 1000.))
+    } else {
+        Some(Duration::from_secs_f64(value))
+    }
+}
@@ -0,0 +1,380 @@
+//! `:diff-request`: what changed between the last two requests sent, for working out how trimming,
+//! templates and the rest combined. Messages are matched up so that one dropped from the start
+//! shows as removed rather than every later one as edited.
+
+use async_openai::types::CreateChatCompletionRequest;
+use serde_json::Value;
+use std::fmt::Write;
+
+use crate::{
+    msg::{self, Msg},
+    style,
+};
+
+// Unchanged messages and parameters are cut to this many characters.
+const PREVIEW_CHARS: usize = 60;
+// Added and removed messages show this many lines before the rest is counted instead.
+const MAX_LINES: usize = 12;
+// Bigger line diffs than this (old lines times new lines) aren't worked out.
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// The last two requests, as sent.
+#[derive(Debug, Default)]
+pub struct RecentRequests {
+    previous: Option<Value>,
+    last: Option<Value>,
+}
+
+impl RecentRequests {
+    pub fn record(&mut self, request: &CreateChatCompletionRequest) {
+        let Ok(json) = serde_json::to_value(request) else {
+            return;
+        };
+        self.previous = self.last.replace(json);
+    }
+
+    /// The last request sent, as JSON.
+    pub fn last(&self) -> Option<&Value> {
+        self.last.as_ref()
+    }
+
+    /// The diff of the last request from the one before, or `None` until two have been sent.
+    pub fn diff(&self, color: bool) -> Option<String> {
+        Some(diff(self.previous.as_ref()?, self.last.as_ref()?, color))
+    }
+}
+
+/// Every parameter that changed, then the messages: `=` kept, `-` removed, `+` added and `~`
+/// edited, with a line diff. Indices are of the old request for removed messages and the new one
+/// otherwise.
+pub fn diff(old: &Value, new: &Value, color: bool) -> String {
+    let mut out = String::new();
+    let empty = serde_json::Map::new();
+    let old_params = old.as_object().unwrap_or(&empty);
+    let new_params = new.as_object().unwrap_or(&empty);
+    let mut keys: Vec<&String> = old_params.keys().chain(new_params.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys.into_iter().filter(|k| *k != "messages") {
+        let (a, b) = (old_params.get(key), new_params.get(key));
+        if a != b {
+            let _ = writeln!(
+                out,
+                "{}",
+                style::wrap(&format!("{key}: {} → {}", param(a), param(b)), "33", color)
+            );
+        }
+    }
+
+    let no_messages = Vec::new();
+    let old_messages = old["messages"].as_array().unwrap_or(&no_messages);
+    let new_messages = new["messages"].as_array().unwrap_or(&no_messages);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    let _ = writeln!(
+        out,
+        "{}",
+        msg::fmt(
+            Msg::ReqdiffMessages,
+            &[("old", &old_messages.len()), ("new", &new_messages.len())]
+        )
+    );
+    let ops = align(old_messages, new_messages);
+    let mut i = 0;
+    while i < ops.len() {
+        match (ops[i], ops.get(i + 1)) {
+            (Op::Same(_, j), _) => {
+                let text = preview(&content(&new_messages[j]));
+                let line = format!("  = [{j}] {}: {text}", role(&new_messages[j]));
+                let _ = writeln!(out, "{}", style::dim(&line, color));
+            }
+            // A message removed where another was added is shown as one edit.
+            (Op::Removed(a), Some(&Op::Added(b))) => {
+                let (old, new) = (&old_messages[a], &new_messages[b]);
+                let header = match role(old) == role(new) {
+                    true => format!("  ~ [{b}] {}:", role(new)),
+                    false => format!("  ~ [{b}] {} → {}:", role(old), role(new)),
+                };
+                let _ = writeln!(out, "{}", style::wrap(&header, "33", color));
+                for (kind, line) in diff_lines(&content(old), &content(new)) {
+                    let (mark, code) = match kind {
+                        Line::Same => (" ", "2"),
+                        Line::Removed => ("-", "31"),
+                        Line::Added => ("+", "32"),
+                    };
+                    let _ = writeln!(
+                        out,
+                        "{}",
+                        style::wrap(&format!("    {mark} {line}"), code, color)
+                    );
+                }
+                i += 1;
+            }
+            (Op::Removed(a), _) => message(&mut out, '-', a, &old_messages[a], "31", color),
+            (Op::Added(b), _) => message(&mut out, '+', b, &new_messages[b], "32", color),
+        }
+        i += 1;
+    }
+    out
+}
+
+fn message(out: &mut String, mark: char, index: usize, msg: &Value, code: &str, color: bool) {
+    let header = format!("  {mark} [{index}] {}:", role(msg));
+    let _ = writeln!(out, "{}", style::wrap(&header, code, color));
+    let text = content(msg);
+    let lines: Vec<&str> = text.lines().collect();
+    for line in lines.iter().take(MAX_LINES) {
+        let _ = writeln!(
+            out,
+            "{}",
+            style::wrap(&format!("    {mark} {line}"), code, color)
+        );
+    }
+    if lines.len() > MAX_LINES {
+        let more = msg::fmt(Msg::ReqdiffMore, &[("count", &(lines.len() - MAX_LINES))]);
+        let _ = writeln!(out, "{}", style::dim(&more, color));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// The messages of both lists matched up by a longest common subsequence. Requests are a few
+/// dozen messages, so the quadratic table is no trouble.
+fn align(old: &[Value], new: &[Value]) -> Vec<Op> {
+    let lcs = lcs_table(old, new);
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(Op::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            // Removals go first, so an edit is a removal straight before its addition.
+            ops.push(Op::Removed(i));
+            i += 1;
+        } else {
+            ops.push(Op::Added(j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// `table[i][j]` is the length of the longest common subsequence of `a[i..]` and `b[j..]`.
+fn lcs_table<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Vec<usize>> {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = match a[i] == b[j] {
+                true => table[i + 1][j + 1] + 1,
+                false => table[i + 1][j].max(table[i][j + 1]),
+            };
+        }
+    }
+    table
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Line {
+    Same,
+    Removed,
+    Added,
+}
+
+/// A line diff of an edited message. Long runs of unchanged lines are cut down to the lines next
+/// to a change. Messages too long to diff quickly are shown as the old lines then the new.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<(Line, &'a str)> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if a.len() * b.len() > MAX_DIFF_CELLS {
+        let removed = a.iter().take(MAX_LINES).map(|&l| (Line::Removed, l));
+        let added = b.iter().take(MAX_LINES).map(|&l| (Line::Added, l));
+        return removed.chain(added).collect();
+    }
+    let lcs = lcs_table(&a, &b);
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push((Line::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push((Line::Removed, a[i]));
+            i += 1;
+        } else {
+            lines.push((Line::Added, b[j]));
+            j += 1;
+        }
+    }
+    let near_change = |k: usize| {
+        let from = k.saturating_sub(1);
+        lines[from..lines.len().min(k + 2)]
+            .iter()
+            .any(|(kind, _)| *kind != Line::Same)
+    };
+    let mut res = Vec::new();
+    let mut skipped = false;
+    for (k, &line) in lines.iter().enumerate() {
+        if line.0 == Line::Same && !near_change(k) {
+            if !skipped {
+                res.push((Line::Same, "…"));
+                skipped = true;
+            }
+            continue;
+        }
+        skipped = false;
+        res.push(line);
+    }
+    res
+}
+
+fn role(msg: &Value) -> &str {
+    msg["role"].as_str().unwrap_or("?")
+}
+
+/// A message's text, or its JSON when the content isn't a plain string, e.g. tool calls.
+fn content(msg: &Value) -> String {
+    match msg.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        _ => {
+            let mut rest = msg.clone();
+            if let Some(fields) = rest.as_object_mut() {
+                fields.remove("role");
+            }
+            rest.to_string()
+        }
+    }
+}
+
+fn param(value: Option<&Value>) -> String {
+    match value {
+        None => "(unset)".to_string(),
+        Some(value) => preview(&value.to_string()),
+    }
+}
+
+fn preview(text: &str) -> String {
+    let first = text.lines().next().unwrap_or_default();
+    let cut: String = first.chars().take(PREVIEW_CHARS).collect();
+    match cut.len() < text.len() {
+        true => format!("{cut}… ({} chars)", text.chars().count()),
+        false => cut,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(model: &str, messages: &[(&str, &str)]) -> Value {
+        json!({
+            "model": model,
+            "messages": messages
+                .iter()
+                .map(|(role, content)| json!({"role": role, "content": content}))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    #[test]
+    fn a_message_trimmed_from_the_start_is_removed_not_everything_edited() {
+        let old = request("m", &[("user", "a"), ("assistant", "b"), ("user", "c")]);
+        let new = request("m", &[("assistant", "b"), ("user", "c"), ("user", "d")]);
+        let ops = align(
+            old["messages"].as_array().unwrap(),
+            new["messages"].as_array().unwrap(),
+        );
+        assert_eq!(
+            ops,
+            [Op::Removed(0), Op::Same(1, 0), Op::Same(2, 1), Op::Added(2)]
+        );
+        let out = diff(&old, &new, false);
+        assert!(out.contains("  - [0] user:\n    - a\n"), "{out}");
+        assert!(out.contains("  + [2] user:\n    + d\n"), "{out}");
+    }
+
+    #[test]
+    fn changed_parameters_come_first() {
+        let mut old = request("gpt-4o", &[("user", "hi")]);
+        let mut new = request("gpt-4o-mini", &[("user", "hi")]);
+        old["temperature"] = json!(0.2);
+        new["seed"] = json!(7);
+        let out = diff(&old, &new, false);
+        assert!(out.starts_with(
+            "model: \"gpt-4o\" → \"gpt-4o-mini\"\nseed: (unset) → 7\ntemperature: 0.2 → (unset)\n\nmessages: 1 → 1\n"
+        ), "{out}");
+    }
+
+    #[test]
+    fn an_edited_message_shows_the_lines_that_changed() {
+        let old_text = "one\ntwo\nthree\nfour\nfive\nsix";
+        let new_text = "one\ntwo\nthree\nfour\nFIVE\nsix";
+        let out = diff(
+            &request("m", &[("system", old_text)]),
+            &request("m", &[("system", new_text)]),
+            false,
+        );
+        assert!(out.contains("  ~ [0] system:\n"), "{out}");
+        // Lines far from the change are cut down to one marker.
+        assert!(
+            out.contains("      …\n      four\n    - five\n    + FIVE\n      six\n"),
+            "{out}"
+        );
+        assert!(!out.contains(" one"), "{out}");
+    }
+
+    #[test]
+    fn unchanged_long_messages_are_cut_short() {
+        let long = "x".repeat(200);
+        let out = diff(
+            &request("a", &[("user", &long)]),
+            &request("b", &[("user", &long)]),
+            false,
+        );
+        let kept = out.lines().find(|l| l.starts_with("  = [0]")).unwrap();
+        assert!(kept.ends_with("… (200 chars)"), "{kept}");
+        assert!(kept.len() < 100);
+    }
+
+    #[test]
+    fn long_added_messages_count_the_rest() {
+        let long: String = (0..20).map(|n| format!("line {n}\n")).collect();
+        let out = diff(&request("m", &[]), &request("m", &[("user", &long)]), false);
+        assert!(out.contains("    + line 11\n    … 8 more lines\n"), "{out}");
+        assert!(!out.contains("line 12"));
+    }
+
+    #[test]
+    fn colors_only_when_asked() {
+        let old = request("m", &[("user", "a")]);
+        let new = request("m", &[("user", "b")]);
+        assert!(!diff(&old, &new, false).contains('\x1b'));
+        assert!(diff(&old, &new, true).contains("\x1b[32m    + b"));
+    }
+
+    #[test]
+    fn there_is_a_diff_once_two_requests_were_sent() {
+        let sent = |model: &str| -> CreateChatCompletionRequest {
+            serde_json::from_value(request(model, &[("user", "hi")])).unwrap()
+        };
+        let mut recent = RecentRequests::default();
+        assert!(recent.diff(false).is_none());
+        recent.record(&sent("a"));
+        assert!(recent.diff(false).is_none());
+        assert_eq!(recent.last().unwrap()["model"], "a");
+        recent.record(&sent("b"));
+        assert!(recent
+            .diff(false)
+            .unwrap()
+            .starts_with("model: \"a\" → \"b\""));
+    }
+}
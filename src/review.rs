@@ -0,0 +1,343 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionStreamOptions, CreateChatCompletionRequestArgs, ResponseFormat,
+        ResponseFormatJsonSchema,
+    },
+    Client,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    io::{self, IsTerminal, Read},
+    process,
+};
+
+use crate::{
+    cli::{ReviewArgs, ReviewFormat},
+    config::Config,
+    error::{AppError, Result},
+    footer::{self, Footer},
+    fsutil, input,
+    msg::{self, Msg},
+    provider::{self, Provider},
+    settings::MaxTokens,
+    style,
+    tokens::count_tokens,
+    usage::{Purpose, Usage},
+};
+
+const SYSTEM_PROMPT: &str = "You are reviewing a code change given as a unified diff. Report real problems in the changed code: bugs, security issues, missed edge cases and unclear code. Don't comment on style a formatter would fix, and don't praise. For `line`, give the line number in the new file from the hunk headers, or a short hint like \"near fn parse\" when unsure. Reply with JSON only, like {\"issues\": [{\"file\": \"src/lib.rs\", \"line\": \"42\", \"severity\": \"high\", \"issue\": \"...\", \"suggestion\": \"...\"}]}, where severity is high, medium or low. Reply {\"issues\": []} when there's nothing to report.";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Issue {
+    pub file: String,
+    pub line: String,
+    pub severity: Severity,
+    pub issue: String,
+    pub suggestion: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Report {
+    issues: Vec<Issue>,
+}
+
+/// One file's part of a diff.
+struct FileDiff {
+    name: String,
+    text: String,
+}
+
+/// Runs `gpt review`, returning the exit code.
+pub async fn run(
+    args: ReviewArgs,
+    client: &Client<OpenAIConfig>,
+    config: &Config,
+    model: &str,
+) -> Result<u8> {
+    let diff = read_diff(&args)?;
+    if diff.trim().is_empty() {
+        eprintln!("{}", msg::get(Msg::ReviewNoChanges));
+        return Ok(0);
+    }
+
+    let chunks = chunk(model, split_files(&diff), args.chunk_tokens)?;
+    let mut issues = Vec::new();
+    let mut usage = Usage::default();
+    for (i, chunk) in chunks.iter().enumerate() {
+        if chunks.len() > 1 {
+            eprintln!(
+                "{}",
+                msg::fmt(
+                    Msg::ReviewPart,
+                    &[("part", &(i + 1)), ("parts", &chunks.len())]
+                )
+            );
+        }
+        let (report, chunk_usage) = review_chunk(client, config, model, chunk).await?;
+        issues.extend(report.issues);
+        usage.add(&chunk_usage);
+    }
+    issues.sort_by(|a, b| a.file.cmp(&b.file).then(a.severity.cmp(&b.severity)));
+
+    match args.format {
+        ReviewFormat::Text => print_report(&issues),
+        ReviewFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&Report { issues })
+                .map_err(|e| AppError::Command(e.to_string()))?
+        ),
+    }
+    let footer = Footer {
+        usage: &usage,
+        model,
+        from_cache: false,
+        resumed: false,
+        routed: false,
+        sent: None,
+        reused: None,
+        currency: &config.currency,
+        timing: None,
+        estimated: false,
+        overrides: None,
+        drift: None,
+    };
+    footer::print(&mut io::stderr(), &footer, config.footer)?;
+    Ok(0)
+}
+
+/// The diff to review: a patch file, stdin, or `git diff`.
+fn read_diff(args: &ReviewArgs) -> Result<String> {
+    match args.patch {
+        Some(ref path) if path.as_os_str() == "-" => {
+            let mut diff = String::new();
+            io::stdin().read_to_string(&mut diff)?;
+            Ok(input::normalize_newlines(&diff))
+        }
+        Some(ref path) => fsutil::read_text(path).map_err(|e| {
+            AppError::Command(msg::fmt(
+                Msg::ReadFailed,
+                &[("path", &path.display()), ("error", &e)],
+            ))
+        }),
+        None => {
+            let mut git = process::Command::new("git");
+            git.args(["diff", "--no-color", "--no-ext-diff"]);
+            if args.cached {
+                git.arg("--cached");
+            }
+            let output = git
+                .output()
+                .map_err(|e| AppError::Command(msg::fmt(Msg::GitFailedToRun, &[("error", &e)])))?;
+            if !output.status.success() {
+                return Err(AppError::Command(msg::fmt(
+                    Msg::GitDiffFailed,
+                    &[("error", &String::from_utf8_lossy(&output.stderr).trim())],
+                )));
+            }
+            Ok(input::normalize_newlines(&String::from_utf8_lossy(
+                &output.stdout,
+            )))
+        }
+    }
+}
+
+/// Splits a diff at its `diff --git` headers. A patch without them counts as one file.
+fn split_files(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git ") || files.is_empty() {
+            files.push(FileDiff {
+                name: String::new(),
+                text: String::new(),
+            });
+        }
+        let file = files.last_mut().expect("a file was just pushed");
+        if file.name.is_empty() {
+            if let Some(name) = line
+                .strip_prefix("+++ ")
+                .filter(|n| !n.starts_with("/dev/null"))
+            {
+                file.name = name.trim().trim_start_matches("b/").to_string();
+            } else if let Some(name) = line.strip_prefix("--- ") {
+                // A deleted file only has its old name.
+                file.name = name.trim().trim_start_matches("a/").to_string();
+            }
+        }
+        file.text.push_str(line);
+    }
+    files
+}
+
+/// Packs the files into requests of at most `budget` tokens of `model`. A file too big for one request is split at
+/// its hunks, and a hunk too big for one at its lines, repeating the file's header in every part.
+fn chunk(model: &str, files: Vec<FileDiff>, budget: u32) -> Result<Vec<String>> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+    for file in files {
+        for piece in split_file(model, &file, budget)? {
+            let tokens = count_tokens(model, &piece);
+            if current_tokens + tokens > budget && !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+                current_tokens = 0;
+            }
+            current.push_str(&piece);
+            current_tokens += tokens;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    Ok(chunks)
+}
+
+fn split_file(model: &str, file: &FileDiff, budget: u32) -> Result<Vec<String>> {
+    if count_tokens(model, &file.text) <= budget {
+        return Ok(vec![file.text.clone()]);
+    }
+    let (header, hunks) = match file.text.find("\n@@") {
+        Some(i) => file.text.split_at(i + 1),
+        None => ("", file.text.as_str()),
+    };
+    let header_tokens = count_tokens(model, header);
+    let mut pieces = Vec::new();
+    let mut current = header.to_string();
+    let mut current_tokens = header_tokens;
+    for line in hunks.split_inclusive('\n') {
+        let tokens = count_tokens(model, line);
+        // Prefer to start a new part at a hunk, but start one mid-hunk rather than go over.
+        let at_hunk = line.starts_with("@@") && current_tokens > header_tokens;
+        let over = current_tokens + tokens > budget && current_tokens > header_tokens;
+        if over || (at_hunk && current_tokens > budget / 2) {
+            pieces.push(std::mem::replace(&mut current, header.to_string()));
+            current_tokens = header_tokens;
+            if !line.starts_with("@@") {
+                current.push_str(&format!("(the {} hunk continues)\n", file.name));
+            }
+        }
+        current.push_str(line);
+        current_tokens += tokens;
+    }
+    pieces.push(current);
+    Ok(pieces)
+}
+
+async fn review_chunk(
+    client: &Client<OpenAIConfig>,
+    config: &Config,
+    model: &str,
+    diff: &str,
+) -> Result<(Report, Usage)> {
+    let max_tokens = config
+        .max_tokens
+        .map_or(MaxTokens::Auto, MaxTokens::Fixed)
+        .resolve(model)
+        .0;
+    let mut request = CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .max_completion_tokens(max_tokens)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(SYSTEM_PROMPT)
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(diff)
+                .build()?
+                .into(),
+        ])
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()?;
+    // Other providers only get the instructions in the prompt.
+    if Provider::for_model(model, config.provider) == Provider::OpenAI {
+        request.response_format = Some(ResponseFormat::JsonSchema {
+            json_schema: ResponseFormatJsonSchema {
+                description: None,
+                name: "code_review".to_string(),
+                schema: Some(schema()),
+                strict: Some(true),
+            },
+        });
+    }
+
+    let (text, usage) = provider::complete(client, request, config, Purpose::Answer).await?;
+    let json = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```");
+    let report = serde_json::from_str(json)
+        .map_err(|e| AppError::Command(msg::fmt(Msg::ReviewNotJson, &[("error", &e)])))?;
+    Ok((report, usage))
+}
+
+fn schema() -> serde_json::Value {
+    let string = json!({ "type": "string" });
+    json!({
+        "type": "object",
+        "properties": {
+            "issues": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "file": string,
+                        "line": string,
+                        "severity": { "type": "string", "enum": ["high", "medium", "low"] },
+                        "issue": string,
+                        "suggestion": string,
+                    },
+                    "required": ["file", "line", "severity", "issue", "suggestion"],
+                    "additionalProperties": false,
+                },
+            },
+        },
+        "required": ["issues"],
+        "additionalProperties": false,
+    })
+}
+
+fn print_report(issues: &[Issue]) {
+    if issues.is_empty() {
+        println!("{}", msg::get(Msg::ReviewNoIssues));
+        return;
+    }
+    let color = style::color_enabled(io::stdout().is_terminal());
+    let mut file = None;
+    for issue in issues {
+        if file != Some(&issue.file) {
+            if file.is_some() {
+                println!();
+            }
+            println!("{}", issue.file);
+            file = Some(&issue.file);
+        }
+        let severity = format!("[{:?}]", issue.severity).to_lowercase();
+        let severity = match issue.severity {
+            Severity::High => style::highlight(&severity, color),
+            _ => severity,
+        };
+        let line = match issue.line.parse::<u32>() {
+            Ok(n) => msg::fmt(Msg::ReviewLine, &[("line", &n)]),
+            Err(_) if issue.line.is_empty() => String::new(),
+            Err(_) => format!(" {}:", issue.line),
+        };
+        println!("  {severity}{line} {}", issue.issue);
+        if !issue.suggestion.is_empty() {
+            println!("    {}", style::dim(&issue.suggestion, color));
+        }
+    }
+}
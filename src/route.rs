@@ -0,0 +1,143 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionStreamOptions, CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::{
+    error::Result,
+    msg::{self, Msg},
+    provider,
+    tokens::estimate_prompt_tokens,
+    usage::{fmt_thousands, Purpose},
+    AppState,
+};
+
+/// The model name that turns routing on.
+pub const AUTO: &str = "auto";
+
+// A slow classifier would cost more time than it saves money.
+const CLASSIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How `model = "auto"` picks a model, from the `[routing]` table of the config.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    pub cheap_model: String,
+    pub expensive_model: String,
+    /// Prompts estimated above this many tokens go to the expensive model.
+    pub long_prompt_tokens: u32,
+    /// Messages with a code block go to the expensive model.
+    pub code: bool,
+    /// Ask `classifier_model` whether a message that passed the other checks needs deep reasoning.
+    pub classify: bool,
+    pub classifier_model: String,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        RoutingConfig {
+            cheap_model: "gpt-4o-mini".to_string(),
+            expensive_model: "gpt-4o".to_string(),
+            long_prompt_tokens: 2000,
+            code: true,
+            classify: false,
+            classifier_model: "gpt-4o-mini".to_string(),
+        }
+    }
+}
+
+/// Which model a message went to and why, for the footer and `:why`.
+#[derive(Clone, Debug)]
+pub struct Decision {
+    pub model: String,
+    pub reason: String,
+}
+
+/// Picks the model for the conversation as it is, ending in the message about to be sent.
+pub async fn decide(client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<Decision> {
+    let routing = state.config.routing.clone();
+    let expensive = |reason: String| Decision {
+        model: routing.expensive_model.clone(),
+        reason,
+    };
+    let cheap = |reason: String| Decision {
+        model: routing.cheap_model.clone(),
+        reason,
+    };
+
+    let tokens = estimate_prompt_tokens(&state.model, &state.context);
+    if tokens > routing.long_prompt_tokens {
+        return Ok(expensive(msg::fmt(
+            Msg::RouteLong,
+            &[
+                ("tokens", &fmt_thousands(tokens as u64)),
+                ("limit", &fmt_thousands(routing.long_prompt_tokens as u64)),
+            ],
+        )));
+    }
+    let message = match state.context.last() {
+        Some(message) if message.is_user() => message.content.clone(),
+        _ => String::new(),
+    };
+    if routing.code && message.contains("```") {
+        return Ok(expensive(msg::get(Msg::RouteCode).to_string()));
+    }
+    if !routing.classify {
+        return Ok(cheap(msg::fmt(
+            Msg::RouteShort,
+            &[("tokens", &fmt_thousands(tokens as u64))],
+        )));
+    }
+
+    let answer = tokio::time::timeout(
+        CLASSIFY_TIMEOUT,
+        classify(client, state, &routing.classifier_model, message),
+    )
+    .await;
+    Ok(match answer {
+        Ok(Ok(true)) => expensive(msg::fmt(
+            Msg::RouteDeep,
+            &[("model", &routing.classifier_model)],
+        )),
+        Ok(Ok(false)) => cheap(msg::fmt(
+            Msg::RouteShallow,
+            &[("model", &routing.classifier_model)],
+        )),
+        Ok(Err(e)) => cheap(msg::fmt(Msg::RouteClassifierFailed, &[("error", &e)])),
+        Err(_) => cheap(msg::get(Msg::RouteClassifierSlow).to_string()),
+    })
+}
+
+/// Asks `model` whether the message needs deep reasoning.
+async fn classify(
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+    model: &str,
+    message: String,
+) -> Result<bool> {
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .max_completion_tokens(1u32)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content("Does answering the following message require deep reasoning, such as multi-step logic, maths, careful analysis or writing code? Reply yes or no.")
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(message)
+                .build()?
+                .into(),
+        ])
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()?;
+    let (answer, _) = provider::complete(client, request, &state.config, Purpose::Routing).await?;
+    Ok(answer.trim().to_lowercase().starts_with("yes"))
+}
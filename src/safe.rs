@@ -0,0 +1,90 @@
+//! `--safe` and `safe_mode`: for locked-down machines and untrusted directories, everything that
+//! does more than talk to the API and keep gpt's own files is switched off. It's decided once at
+//! startup, so nothing typed or pasted into the REPL can switch it back on, and every feature it
+//! covers asks [`check`] before doing anything.
+
+use std::{
+    io,
+    path::{Component, Path},
+    sync::OnceLock,
+};
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    paths,
+};
+
+static SAFE: OnceLock<bool> = OnceLock::new();
+
+/// What safe mode switches off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    /// `:cmd --execute`, `gpt cmd --execute` and `:fix` running a command.
+    Shell,
+    /// `$(command)` in messages.
+    Substitution,
+    /// `:filter` and `response_filter`.
+    Filter,
+    /// Plugins and MCP servers.
+    Tools,
+    /// Writing anywhere but the data and cache directories, like `:tee` and `-o`.
+    Write,
+    Clipboard,
+    /// Reading `.gpt-cli.toml`.
+    Project,
+}
+
+impl Feature {
+    fn describe(self) -> &'static str {
+        msg::get(match self {
+            Feature::Shell => Msg::SafeShell,
+            Feature::Substitution => Msg::SafeSubstitution,
+            Feature::Filter => Msg::SafeFilter,
+            Feature::Tools => Msg::SafeTools,
+            Feature::Write => Msg::SafeWrite,
+            Feature::Clipboard => Msg::SafeClipboard,
+            Feature::Project => Msg::SafeProject,
+        })
+    }
+}
+
+/// Turns safe mode on or off for the rest of the run. Only the first call counts.
+pub fn init(safe: bool) {
+    let _ = SAFE.set(safe);
+}
+
+pub fn enabled() -> bool {
+    SAFE.get().copied().unwrap_or(false)
+}
+
+/// The gate every feature safe mode covers goes through: why `feature` can't be used, if it can't.
+pub fn disabled(feature: Feature) -> Option<String> {
+    enabled().then(|| msg::fmt(Msg::SafeDisabled, &[("feature", &feature.describe())]))
+}
+
+/// [`disabled`] as an error.
+pub fn check(feature: Feature) -> Result<()> {
+    match disabled(feature) {
+        Some(why) => Err(AppError::Command(format!("{why}."))),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `path` may be written: always outside safe mode, and in it only under the data or
+/// cache directory.
+pub fn check_write(path: &Path) -> io::Result<()> {
+    let Some(why) = disabled(Feature::Write) else {
+        return Ok(());
+    };
+    // `..` could climb out of the directory, so it's never let through.
+    let inside =
+        |dir: &Path| path.starts_with(dir) && !path.components().any(|c| c == Component::ParentDir);
+    match inside(&paths::data_dir()) || inside(&paths::cache_dir()) {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{why}: {}", path.display()),
+        )),
+    }
+}
@@ -0,0 +1,496 @@
+//! The version of the files gpt writes: sessions, bookmarks, the queue, the response cache, the
+//! index, the usage ledger and the audit log. Each JSON object is written with a `schema_version`,
+//! and read back through the migrations that bring an older one up to [`CURRENT`], so changing a
+//! shape doesn't break the files already written. One from a newer gpt fails to load, saying so,
+//! rather than being read wrongly or overwritten. `gpt migrate` upgrades them all in place.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    error::Result,
+    fsutil,
+    msg::{self, Msg},
+    paths, style,
+};
+
+/// The version written.
+pub const CURRENT: u32 = 1;
+
+const FIELD: &str = "schema_version";
+
+/// Each brings an object from the version at its index to the next, so `MIGRATIONS[0]` upgrades
+/// version 0 to 1. A new version adds one here and bumps [`CURRENT`].
+const MIGRATIONS: &[fn(&mut Value)] = &[v0_to_v1];
+
+/// Version 0 is every file from before there were versions. Version 1 has the same shapes, with
+/// the version added.
+fn v0_to_v1(_value: &mut Value) {}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    Invalid(#[from] serde_json::Error),
+    #[error("was written by a newer gpt-cli (schema version {0}, and this one reads up to {CURRENT}). Upgrade gpt-cli to read it")]
+    Newer(u32),
+}
+
+impl Error {
+    /// What went wrong with `what`, e.g. "Session 'x'", as a sentence.
+    pub fn describe(&self, what: &str) -> String {
+        match self {
+            Error::Invalid(e) => msg::fmt(Msg::FileCorrupt, &[("what", &what), ("error", &e)]),
+            Error::Newer(found) => msg::fmt(
+                Msg::FileNewer,
+                &[("what", &what), ("found", found), ("current", &CURRENT)],
+            ),
+        }
+    }
+}
+
+/// The version `value` was written with. Files from before there were versions have none.
+pub fn version(value: &Value) -> u32 {
+    value[FIELD]
+        .as_u64()
+        .map_or(0, |v| u32::try_from(v).unwrap_or(u32::MAX))
+}
+
+/// Brings `value` up to the current version, returning the one it had.
+pub fn upgrade(value: &mut Value) -> std::result::Result<u32, Error> {
+    let found = version(value);
+    if found > CURRENT {
+        return Err(Error::Newer(found));
+    }
+    for migration in &MIGRATIONS[found as usize..] {
+        migration(value);
+    }
+    if let Value::Object(map) = value {
+        map.insert(FIELD.to_string(), CURRENT.into());
+    }
+    Ok(found)
+}
+
+/// Reads a file's JSON, of whatever version.
+pub fn decode<T: DeserializeOwned>(text: &str) -> std::result::Result<T, Error> {
+    let mut value: Value = serde_json::from_str(text)?;
+    upgrade(&mut value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// `value` as JSON of the current version.
+pub fn encode<T: Serialize>(value: &T, pretty: bool) -> serde_json::Result<Vec<u8>> {
+    let mut value = serde_json::to_value(value)?;
+    if let Value::Object(ref mut map) = value {
+        map.insert(FIELD.to_string(), CURRENT.into());
+    }
+    match pretty {
+        true => serde_json::to_vec_pretty(&value),
+        false => serde_json::to_vec(&value),
+    }
+}
+
+/// A file `gpt migrate` looks at.
+struct File {
+    path: PathBuf,
+    /// Written as it's written by the code that owns it.
+    pretty: bool,
+    /// One object a line, like the ledger, rather than one for the file.
+    lines: bool,
+}
+
+/// How a file's objects stand.
+#[derive(Default)]
+struct Found {
+    /// Of an older version, as (version, how many).
+    old: Vec<(u32, usize)>,
+    newer: usize,
+    invalid: usize,
+}
+
+/// Runs `gpt migrate`: every file in an older version is upgraded, or with `dry_run` only listed.
+/// Files from a newer gpt, and ones that don't parse, are left alone.
+pub fn migrate(config: &Config, dry_run: bool, color: bool) -> Result<u8> {
+    let mut files = Vec::new();
+    walk(&paths::data_dir().join("sessions"), true, &mut files)?;
+    for name in ["bookmarks.json", "queue.json"] {
+        files.push(File {
+            path: paths::data_dir().join(name),
+            pretty: true,
+            lines: false,
+        });
+    }
+    files.push(File {
+        path: config.index.path(),
+        pretty: false,
+        lines: false,
+    });
+    walk(&paths::cache_dir(), false, &mut files)?;
+    files.push(File {
+        path: paths::data_dir().join("usage.jsonl"),
+        pretty: false,
+        lines: true,
+    });
+    if let Some(ref path) = config.audit.path {
+        files.push(File {
+            path: path.clone(),
+            pretty: false,
+            lines: true,
+        });
+    }
+
+    let (mut upgraded, mut current, mut skipped) = (0, 0, 0);
+    for file in &files {
+        let text = match fs::read_to_string(&file.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    msg::fmt(
+                        Msg::ReadFailed,
+                        &[("path", &file.path.display()), ("error", &e)]
+                    )
+                );
+                skipped += 1;
+                continue;
+            }
+        };
+        let (found, contents) = match file.lines {
+            true => upgrade_lines(&text),
+            false => upgrade_file(&text, file.pretty),
+        };
+        let path = file.path.display();
+        if !file.lines && (found.newer > 0 || found.invalid > 0) {
+            let left = match found.newer {
+                0 => Msg::MigrateUnparsable,
+                _ => Msg::MigrateNewer,
+            };
+            println!("{path}: {}", msg::get(left));
+            skipped += 1;
+            continue;
+        }
+        // In a file of lines, only the lines that can't be upgraded are left as they are.
+        let mut kept = Vec::new();
+        if found.newer > 0 {
+            kept.push(msg::fmt(
+                Msg::MigrateKeptNewer,
+                &[("entries", &count(found.newer))],
+            ));
+        }
+        if found.invalid > 0 {
+            kept.push(msg::fmt(
+                Msg::MigrateKeptUnparsable,
+                &[("entries", &count(found.invalid))],
+            ));
+        }
+        let kept = match kept.is_empty() {
+            true => String::new(),
+            false => msg::fmt(
+                Msg::MigrateKeeping,
+                &[("entries", &kept.join(msg::get(Msg::MigrateAnd)))],
+            ),
+        };
+        if found.old.is_empty() {
+            match kept.is_empty() {
+                true => current += 1,
+                false => {
+                    println!(
+                        "{path}: {}",
+                        msg::fmt(Msg::MigrateNothing, &[("kept", &kept)])
+                    );
+                    skipped += 1;
+                }
+            }
+            continue;
+        }
+        let versions: Vec<String> = found
+            .old
+            .iter()
+            .map(|(version, n)| match file.lines {
+                true => msg::fmt(
+                    Msg::MigrateAtVersion,
+                    &[("entries", &count(*n)), ("version", version)],
+                ),
+                false => format!("v{version}"),
+            })
+            .collect();
+        let upgrade = match dry_run {
+            true => Msg::MigrateWouldUpgrade,
+            false => Msg::MigrateUpgraded,
+        };
+        let line = msg::fmt(
+            upgrade,
+            &[
+                ("versions", &versions.join(", ")),
+                ("current", &CURRENT),
+                ("kept", &kept),
+            ],
+        );
+        println!("{path}: {line}");
+        if !dry_run {
+            match file.lines {
+                // Upgraded again under the append lock, so lines another gpt has added since are
+                // kept, and none can be added until the new file is in place.
+                true => fsutil::rewrite_lines(&file.path, |text| Some(upgrade_lines(text).1))?,
+                false => fsutil::write_atomic(&file.path, &contents)?,
+            }
+        }
+        upgraded += 1;
+    }
+    let summary = match (dry_run, upgraded) {
+        (true, 0) => Msg::MigrateCheckedNothing,
+        (true, _) => Msg::MigrateChecked,
+        (false, _) => Msg::MigrateSummary,
+    };
+    let summary = msg::fmt(
+        summary,
+        &[
+            ("upgraded", &upgraded),
+            ("current", &current),
+            ("skipped", &skipped),
+        ],
+    );
+    eprintln!("{}", style::dim(&summary, color));
+    // Left alone means something to look at, so scripts can tell.
+    Ok(u8::from(skipped > 0))
+}
+
+fn upgrade_file(text: &str, pretty: bool) -> (Found, Vec<u8>) {
+    let mut found = Found::default();
+    let Ok(mut value) = serde_json::from_str::<Value>(text) else {
+        found.invalid = 1;
+        return (found, Vec::new());
+    };
+    match upgrade(&mut value) {
+        Ok(CURRENT) => {}
+        Ok(version) => found.old.push((version, 1)),
+        Err(_) => found.newer = 1,
+    }
+    let contents = match pretty {
+        true => serde_json::to_vec_pretty(&value),
+        false => serde_json::to_vec(&value),
+    };
+    (found, contents.unwrap_or_default())
+}
+
+fn upgrade_lines(text: &str) -> (Found, Vec<u8>) {
+    let mut found = Found::default();
+    let mut contents = Vec::new();
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let upgraded = match serde_json::from_str::<Value>(line) {
+            Ok(mut value) => match upgrade(&mut value) {
+                Ok(CURRENT) => None,
+                Ok(version) => {
+                    match found.old.iter_mut().find(|(v, _)| *v == version) {
+                        Some((_, n)) => *n += 1,
+                        None => found.old.push((version, 1)),
+                    }
+                    serde_json::to_vec(&value).ok()
+                }
+                Err(_) => {
+                    found.newer += 1;
+                    None
+                }
+            },
+            Err(_) => {
+                found.invalid += 1;
+                None
+            }
+        };
+        contents.extend(upgraded.unwrap_or_else(|| line.as_bytes().to_vec()));
+        contents.push(b'\n');
+    }
+    found.old.sort();
+    (found, contents)
+}
+
+/// The `.json` files under `dir` and the directories in it, as sessions are kept per project.
+fn walk(dir: &Path, pretty: bool, files: &mut Vec<File>) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            walk(&path, pretty, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(File {
+                path,
+                pretty,
+                lines: false,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn count(n: usize) -> String {
+    match n {
+        1 => msg::get(Msg::MigrateOneEntry).to_string(),
+        n => msg::fmt(Msg::MigrateEntries, &[("count", &n)]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bookmarks::Bookmark, session::Session};
+    use serde_json::json;
+
+    // A session as gpt wrote it before there were versions.
+    fn v0_session() -> Value {
+        json!({
+            "title": "Borrowing",
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "content": "why?"},
+                {"role": "assistant", "content": "because"},
+            ],
+        })
+    }
+
+    fn with_version(mut value: Value, version: u32) -> Value {
+        value[FIELD] = version.into();
+        value
+    }
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len(), CURRENT as usize);
+    }
+
+    #[test]
+    fn versions_are_read_leniently() {
+        assert_eq!(version(&json!({})), 0);
+        assert_eq!(version(&json!({"schema_version": "1"})), 0);
+        assert_eq!(version(&json!({"schema_version": 1})), 1);
+        assert_eq!(version(&json!({"schema_version": u64::MAX})), u32::MAX);
+    }
+
+    #[test]
+    fn version_0_is_upgraded_and_written_back_as_current() {
+        let session: Session = decode(&v0_session().to_string()).unwrap();
+        assert_eq!(session.title.as_deref(), Some("Borrowing"));
+        assert_eq!(session.messages.len(), 2);
+        let written: Value = serde_json::from_slice(&encode(&session, true).unwrap()).unwrap();
+        assert_eq!(version(&written), CURRENT);
+        // Nothing else changed on the way through.
+        let session: Session = decode(&written.to_string()).unwrap();
+        let again: Value = serde_json::from_slice(&encode(&session, false).unwrap()).unwrap();
+        assert_eq!(again, written);
+    }
+
+    #[test]
+    fn the_current_version_round_trips_unchanged() {
+        let bookmark = with_version(
+            json!({
+                "id": 3,
+                "label": "the fix",
+                "created": "2024-05-01T12:00:00Z",
+                "session": "notes",
+                "index": 1,
+                "role": "assistant",
+                "text": "because",
+            }),
+            CURRENT,
+        );
+        let decoded: Bookmark = decode(&bookmark.to_string()).unwrap();
+        let encoded: Value = serde_json::from_slice(&encode(&decoded, false).unwrap()).unwrap();
+        assert_eq!(encoded, bookmark);
+
+        let mut value = bookmark.clone();
+        assert_eq!(upgrade(&mut value).unwrap(), CURRENT);
+        assert_eq!(value, bookmark);
+    }
+
+    #[test]
+    fn a_newer_version_is_refused() {
+        let newer = with_version(v0_session(), CURRENT + 1);
+        let err = decode::<Session>(&newer.to_string()).unwrap_err();
+        assert!(matches!(err, Error::Newer(v) if v == CURRENT + 1));
+        assert_eq!(
+            err.describe("Session 'notes'"),
+            format!("Session 'notes' was written by a newer gpt-cli (schema version {}, and this one reads up to {CURRENT}). Upgrade gpt-cli to read it.", CURRENT + 1)
+        );
+        let mut value = newer.clone();
+        assert!(upgrade(&mut value).is_err());
+        assert_eq!(value, newer);
+    }
+
+    #[test]
+    fn invalid_json_is_corrupt() {
+        let err = decode::<Session>("{\"model\": ").unwrap_err();
+        assert!(err
+            .describe("Session 'x'")
+            .starts_with("Session 'x' is corrupt: "));
+    }
+
+    #[test]
+    fn files_are_upgraded_whole() {
+        let (found, contents) = upgrade_file(&v0_session().to_string(), false);
+        assert_eq!(found.old, [(0, 1)]);
+        let upgraded: Value = serde_json::from_slice(&contents).unwrap();
+        assert_eq!(upgraded, with_version(v0_session(), CURRENT));
+
+        let (found, _) = upgrade_file(&upgraded.to_string(), true);
+        assert!(found.old.is_empty() && found.newer == 0 && found.invalid == 0);
+        let (found, _) = upgrade_file("not json", true);
+        assert_eq!(found.invalid, 1);
+    }
+
+    #[test]
+    fn lines_are_upgraded_one_by_one_and_the_rest_left_alone() {
+        let old = json!({"model": "gpt-4o"});
+        let current = with_version(json!({"model": "gpt-4o-mini"}), CURRENT);
+        let newer = with_version(json!({"model": "gpt-9"}), CURRENT + 1).to_string();
+        let text = format!("{old}\n\n{current}\n{newer}\n{{broken\n{old}\n");
+        let (found, contents) = upgrade_lines(&text);
+        assert_eq!(found.old, [(0, 2)]);
+        assert_eq!((found.newer, found.invalid), (1, 1));
+        let upgraded = with_version(old, CURRENT).to_string();
+        assert_eq!(
+            String::from_utf8(contents).unwrap(),
+            format!("{upgraded}\n{current}\n{newer}\n{{broken\n{upgraded}\n")
+        );
+    }
+
+    #[test]
+    fn lines_appended_while_the_ledger_is_upgraded_are_kept() {
+        let dir =
+            std::env::temp_dir().join(format!("gpt-cli-schema-ledger-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("usage.jsonl");
+        let old = json!({"model": "gpt-4o"}).to_string();
+        fs::write(&path, format!("{old}\n").repeat(2000)).unwrap();
+        let appender = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let line = with_version(json!({"model": "new"}), CURRENT).to_string();
+                for _ in 0..100 {
+                    fsutil::append_line(&path, line.as_bytes()).unwrap();
+                }
+            })
+        };
+        for _ in 0..20 {
+            fsutil::rewrite_lines(&path, |text| Some(upgrade_lines(text).1)).unwrap();
+        }
+        appender.join().unwrap();
+        fsutil::rewrite_lines(&path, |text| Some(upgrade_lines(text).1)).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().filter(|l| l.contains("\"new\"")).count(), 100);
+        assert_eq!(text.lines().count(), 2100);
+        assert!(text.lines().all(|l| l.contains("\"schema_version\"")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
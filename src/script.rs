@@ -0,0 +1,84 @@
+//! `--script` and `:script`: lines of a file run one by one as if they'd been typed at the prompt,
+//! commands and messages alike, `|` prefixes and aliases included.
+
+use std::{collections::VecDeque, path::PathBuf};
+
+use crate::{
+    error::{AppError, Result},
+    fsutil,
+    input::InputSource,
+    msg::{self, Msg},
+    AppState,
+};
+
+#[derive(Debug)]
+pub struct Script {
+    pub path: PathBuf,
+    /// What's left to run, with line numbers counted from 1.
+    lines: VecDeque<(usize, String)>,
+    /// The number of the line last run, for the error it ends in.
+    last: usize,
+    /// End the session once it's finished, as `--script-exit` asks.
+    pub exit: bool,
+}
+
+impl Script {
+    /// Reads the script. Blank lines and lines starting with `#` are skipped.
+    pub fn load(path: PathBuf, exit: bool) -> Result<Script> {
+        let text = fsutil::read_text(&path).map_err(|e| {
+            AppError::Command(msg::fmt(
+                Msg::ReadFailed,
+                &[("path", &path.display()), ("error", &e)],
+            ))
+        })?;
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim().to_string()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        Ok(Script {
+            path,
+            lines,
+            last: 0,
+            exit,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+impl InputSource for Script {
+    async fn next_line(&mut self, state: &mut AppState) -> Option<Result<String>> {
+        let (n, line) = self.lines.pop_front()?;
+        self.last = n;
+        // Shown as if it had been typed.
+        if state.stdout_tty {
+            println!("{line}");
+        }
+        Some(Ok(line))
+    }
+
+    fn is_finished(&self) -> bool {
+        Script::is_finished(self)
+    }
+
+    /// Shows the error with the line number. A failed line stops the script, since what comes
+    /// after probably depends on it, and with `--script-exit` the session.
+    fn report(&mut self, e: &AppError) -> bool {
+        println!("{}:{}: {e}", self.path.display(), self.last);
+        if self.exit {
+            return true;
+        }
+        let stopped = match self.lines.len() {
+            0 => return false,
+            1 => msg::get(Msg::ScriptStoppedOne).to_string(),
+            n => msg::fmt(Msg::ScriptStopped, &[("count", &n)]),
+        };
+        println!("{stopped}");
+        self.lines.clear();
+        false
+    }
+}
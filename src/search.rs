@@ -0,0 +1,218 @@
+//! `:search`: incremental search of the session's prompts and answers, like Ctrl+R in a shell.
+//! It's drawn on the prompt's line and redrawn on every key and resize, with the keys read from
+//! crossterm's events in raw mode while the stdin reader is [`Handover`]ed, so whichever way it
+//! ends the terminal goes back to reading lines.
+
+use crossterm::event::{self, Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    doctor,
+    error::{AppError, Result},
+    input::{Handover, RawMode},
+    msg::{self, Msg},
+    style, AppState, Message,
+};
+
+// Clears the line the cursor is on and goes back to its start.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+// Used when the terminal doesn't say how wide it is.
+const DEFAULT_WIDTH: usize = 80;
+
+/// A message that matched, and the prompt Enter takes: the message itself, or for an answer the
+/// prompt it was for.
+struct Match {
+    index: usize,
+    prompt: usize,
+}
+
+/// Runs the search, returning the prompt to send once it's been picked and edited, or `None` when
+/// it's cancelled.
+pub async fn run(state: &mut AppState) -> Result<Option<String>> {
+    if !state.interactive {
+        return Err(AppError::Command(
+            msg::get(Msg::SearchNeedsTerminal).to_string(),
+        ));
+    }
+    if !state.context.iter().any(Message::is_user) {
+        println!("{}", msg::get(Msg::SearchNothing));
+        return Ok(None);
+    }
+    // Dropped in the reverse order, so the terminal is back to normal before the reader resumes.
+    let _handover = Handover::start();
+    let raw = RawMode::enable()?;
+    // Anything typed before the search started belongs to the prompt, not the search.
+    while event::poll(Duration::ZERO)? {
+        event::read()?;
+    }
+
+    let mut query = String::new();
+    let mut selected = 0;
+    // The prompt being edited, once one has been picked.
+    let mut editing: Option<String> = None;
+    let mut out = io::stdout();
+    let mut events = EventStream::new();
+    loop {
+        let line = match editing {
+            Some(ref text) => render_edit(text, state.color),
+            None => {
+                let matches = find(&state.context, &query);
+                selected = selected.min(matches.len().saturating_sub(1));
+                render_search(&state.context, &query, matches.get(selected), state.color)
+            }
+        };
+        write!(out, "{CLEAR_LINE}{line}")?;
+        out.flush()?;
+
+        let key = match events.next().await {
+            Some(Ok(Event::Key(key))) if key.kind != KeyEventKind::Release => key,
+            // The line is drawn again at the new width.
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => {
+                write!(out, "{CLEAR_LINE}")?;
+                return Err(e.into());
+            }
+            None => {
+                write!(out, "{CLEAR_LINE}")?;
+                return Ok(None);
+            }
+        };
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        // Ctrl+C is a key in raw mode, and cancels like Escape.
+        if key.code == KeyCode::Esc || (control && key.code == KeyCode::Char('c')) {
+            write!(out, "{CLEAR_LINE}")?;
+            return Ok(None);
+        }
+        match (key.code, &mut editing) {
+            (KeyCode::Enter, Some(text)) => {
+                let text = std::mem::take(text);
+                write!(out, "{CLEAR_LINE}")?;
+                // Left on screen like a typed line, so it's clear what was sent.
+                drop(raw);
+                println!("{text}");
+                return Ok(Some(text).filter(|t| !t.trim().is_empty()));
+            }
+            (KeyCode::Enter, None) => {
+                let matches = find(&state.context, &query);
+                match matches.get(selected) {
+                    Some(m) => editing = Some(state.context[m.prompt].content().to_string()),
+                    None => {
+                        write!(out, "{CLEAR_LINE}")?;
+                        return Ok(None);
+                    }
+                }
+            }
+            // Ctrl+R goes to an older match, as in a shell's search.
+            (KeyCode::Char('r'), None) if control => selected += 1,
+            (KeyCode::Char(c), Some(text)) if !control => text.push(c),
+            (KeyCode::Char(c), None) if !control => {
+                query.push(c);
+                selected = 0;
+            }
+            (KeyCode::Backspace, Some(text)) => pop_grapheme(text),
+            (KeyCode::Backspace, None) => {
+                pop_grapheme(&mut query);
+                selected = 0;
+            }
+            (KeyCode::Up, None) => selected += 1,
+            (KeyCode::Down, None) => selected = selected.saturating_sub(1),
+            _ => {}
+        }
+    }
+}
+
+/// The messages containing `query`, newest first, one for each prompt they lead to.
+fn find(context: &[Message], query: &str) -> Vec<Match> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let query = query.to_lowercase();
+    let mut matches: Vec<Match> = Vec::new();
+    for (index, msg) in context.iter().enumerate().rev() {
+        if !msg.content().to_lowercase().contains(&query) {
+            continue;
+        }
+        let Some(prompt) = (0..=index).rev().find(|&i| context[i].is_user()) else {
+            continue;
+        };
+        if !matches.iter().any(|m| m.prompt == prompt) {
+            matches.push(Match { index, prompt });
+        }
+    }
+    matches
+}
+
+fn render_search(context: &[Message], query: &str, found: Option<&Match>, color: bool) -> String {
+    let Some(found) = found else {
+        let label = match query.is_empty() {
+            true => msg::get(Msg::SearchLabel),
+            false => msg::get(Msg::SearchNoMatch),
+        };
+        return format!("{}{query}", style::dim(label, color));
+    };
+    let msg = &context[found.index];
+    let lower = query.to_lowercase();
+    let line = msg
+        .content()
+        .lines()
+        .find(|l| l.to_lowercase().contains(&lower))
+        .unwrap_or_default()
+        .trim();
+    let label = format!("{}{query} ", msg::get(Msg::SearchLabel));
+    let shown = format!("[{}] {}: {line}", found.index, msg.role());
+    let room = width().saturating_sub(label.chars().count());
+    format!("{}{}", style::dim(&label, color), head(&shown, room))
+}
+
+fn render_edit(text: &str, color: bool) -> String {
+    let label = msg::get(Msg::SearchEditLabel);
+    // A line break would end the line being redrawn, so it's shown as a symbol instead.
+    let shown = text.replace('\n', "↵");
+    let room = width().saturating_sub(label.chars().count() + 1);
+    format!("{}{}", style::dim(label, color), tail(&shown, room))
+}
+
+pub fn width() -> usize {
+    doctor::width().map_or(DEFAULT_WIDTH, usize::from)
+}
+
+/// As much of the start of `text` as fits in `room` columns.
+pub fn head(text: &str, room: usize) -> String {
+    let mut used = 0;
+    let mut res = String::new();
+    for c in text.chars() {
+        used += c.width().unwrap_or(0);
+        if used > room {
+            break;
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// As much of the end of `text` as fits in `room` columns, so what's typed stays in view.
+fn tail(text: &str, room: usize) -> String {
+    let mut used = 0;
+    let mut res: Vec<char> = Vec::new();
+    for c in text.chars().rev() {
+        used += c.width().unwrap_or(0);
+        if used > room {
+            break;
+        }
+        res.push(c);
+    }
+    res.into_iter().rev().collect()
+}
+
+pub fn pop_grapheme(text: &mut String) {
+    if let Some((start, _)) = text.grapheme_indices(true).next_back() {
+        text.truncate(start);
+    }
+}
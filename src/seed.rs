@@ -0,0 +1,488 @@
+//! Seeds: a conversation's setup in one `.gptseed.toml` file that can be handed to someone else.
+//! It has a system prompt, messages to start from (few-shot examples), the model and temperature,
+//! and globs of files to stage. `--seed-file` and `:seed <path>` start a conversation from one,
+//! and `:seed export <path>` writes the current setup out, without the conversation itself.
+//!
+//! ```toml
+//! system = "You review Rust code for soundness. Be terse."
+//! model = "4o"
+//! temperature = 0.2
+//! files = ["src/**/*.rs", "Cargo.toml"]
+//!
+//! [[messages]]
+//! role = "user"
+//! content = "Review: fn f(v: &Vec<u8>) {}"
+//!
+//! [[messages]]
+//! role = "assistant"
+//! content = "Take &[u8]: a &Vec<u8> can't be made from an array or a slice."
+//! ```
+//!
+//! The system prompt and messages go with every request of the conversation, ahead of its context,
+//! so they're kept whether the context is carried or not. Globs are from the seed file's directory,
+//! so a seed kept in a repository works wherever it's checked out.
+
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use toml::{Table, Value};
+
+use crate::{
+    commands,
+    error::{AppError, Result},
+    fsutil,
+    msg::{self, Msg},
+    route, sensitive, settings,
+    stage::{self, StagedFile},
+    style, AppState, Message,
+};
+
+/// A seed as it's read, and as [`export`] writes it.
+#[derive(Debug, Default, Serialize)]
+pub struct Seed {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Globs, or paths, from the seed file's directory. One that looks like it holds secrets
+    /// needs a `!` in front, as with `:file`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<SeedMessage>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SeedMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// The seed the conversation was started from, kept for the system prompt and `:seed export`.
+#[derive(Debug)]
+pub struct Loaded {
+    pub path: PathBuf,
+    pub seed: Seed,
+    /// The files its globs staged, so an export writes the globs rather than each file.
+    pub staged: Vec<PathBuf>,
+}
+
+/// The system prompt of the seed the conversation started from, if any.
+pub fn system(state: &AppState) -> Option<&str> {
+    state.seed.as_ref()?.seed.system.as_deref()
+}
+
+/// The messages of that seed, which go after the system prompt in every request, so carrying the
+/// context or not, they're always there to go by.
+pub fn messages(state: &AppState) -> Vec<Message> {
+    let Some(ref loaded) = state.seed else {
+        return Vec::new();
+    };
+    loaded
+        .seed
+        .messages
+        .iter()
+        .map(|message| match message.role.as_str() {
+            "assistant" => Message::assistant(message.content.clone()),
+            _ => Message::user(message.content.clone()),
+        })
+        .collect()
+}
+
+/// Reads a seed, or everything wrong with it. Keys it doesn't know are returned with it rather
+/// than refused, since they may be from a newer gpt.
+pub fn read(path: &Path) -> Result<(Seed, Vec<String>)> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::ReadFailed,
+            &[("path", &path.display()), ("error", &e)],
+        ))
+    })?;
+    let (seed, problems, unknown) = parse(&text);
+    if !problems.is_empty() {
+        return Err(AppError::Command(msg::fmt(
+            Msg::SeedInvalid,
+            &[
+                ("path", &path.display()),
+                ("problems", &problems.join("\n  ")),
+            ],
+        )));
+    }
+    Ok((seed, unknown))
+}
+
+/// The seed in `text`, what's wrong with it and the keys it doesn't know.
+fn parse(text: &str) -> (Seed, Vec<String>, Vec<String>) {
+    let mut seed = Seed::default();
+    let mut problems = Vec::new();
+    let mut unknown = Vec::new();
+    let table: Table = match toml::from_str(text) {
+        Ok(table) => table,
+        Err(e) => return (seed, vec![e.to_string()], unknown),
+    };
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("system", Value::String(s)) => seed.system = Some(s),
+            ("model", Value::String(s)) => seed.model = Some(s),
+            ("temperature", value) => match value
+                .as_float()
+                .or_else(|| value.as_integer().map(|n| n as f64))
+            {
+                Some(t) if (0.0..=2.0).contains(&t) => seed.temperature = Some(t),
+                _ => problems.push(msg::get(Msg::SeedTemperature).to_string()),
+            },
+            ("files", Value::Array(files)) => {
+                for file in files {
+                    match file {
+                        Value::String(file) if !file.is_empty() => seed.files.push(file),
+                        _ => problems.push(msg::get(Msg::SeedFilesList).to_string()),
+                    }
+                }
+            }
+            ("messages", Value::Array(messages)) => {
+                for (i, message) in messages.into_iter().enumerate() {
+                    match parse_message(message, i + 1, &mut unknown) {
+                        Ok(message) => seed.messages.push(message),
+                        Err(problem) => problems.push(problem),
+                    }
+                }
+            }
+            ("system" | "model", _) => {
+                problems.push(msg::fmt(Msg::SeedNotString, &[("key", &key)]))
+            }
+            ("files", _) => problems.push(msg::get(Msg::SeedFilesList).to_string()),
+            ("messages", _) => problems.push(msg::get(Msg::SeedMessagesTables).to_string()),
+            (key, _) => unknown.push(key.to_string()),
+        }
+    }
+    problems.dedup();
+    (seed, problems, unknown)
+}
+
+/// The `n`th `[[messages]]`.
+fn parse_message(
+    message: Value,
+    n: usize,
+    unknown: &mut Vec<String>,
+) -> std::result::Result<SeedMessage, String> {
+    let Value::Table(fields) = message else {
+        return Err(msg::fmt(Msg::SeedMessageNotTable, &[("n", &n)]));
+    };
+    let role = match fields.get("role").and_then(Value::as_str) {
+        Some(role @ ("user" | "assistant")) => role.to_string(),
+        _ => return Err(msg::fmt(Msg::SeedMessageRole, &[("n", &n)])),
+    };
+    let Some(content) = fields.get("content").and_then(Value::as_str) else {
+        return Err(msg::fmt(Msg::SeedMessageContent, &[("n", &n)]));
+    };
+    for key in fields
+        .keys()
+        .filter(|k| !["role", "content"].contains(&k.as_str()))
+    {
+        unknown.push(format!("messages.{key}"));
+    }
+    Ok(SeedMessage {
+        role,
+        content: content.to_string(),
+    })
+}
+
+/// Starts a new conversation from the seed at `path`.
+pub async fn load(state: &mut AppState, path: &Path) -> Result<()> {
+    let (seed, unknown) = read(path)?;
+    if !unknown.is_empty() {
+        let notice = msg::fmt(
+            Msg::SeedUnknownKeys,
+            &[("path", &path.display()), ("keys", &unknown.join(", "))],
+        );
+        eprintln!("{}", style::dim(&notice, state.color));
+    }
+    let dir = parent(path);
+    let dir = dir.canonicalize().unwrap_or(dir);
+    // Everything is checked first, so a seed that can't be used leaves the conversation alone.
+    let mut files = Vec::new();
+    for pattern in &seed.files {
+        let (glob, force) = sensitive::parse_forced(pattern);
+        let found = expand(&dir, glob);
+        if found.is_empty() {
+            return Err(AppError::Command(msg::fmt(
+                Msg::SeedNoMatch,
+                &[
+                    ("path", &path.display()),
+                    ("glob", &glob),
+                    ("dir", &dir.display()),
+                ],
+            )));
+        }
+        for file in found {
+            if !files.iter().any(|(f, _)| *f == file) {
+                let text = sensitive::read(&file, &state.config.sensitive_files, force)?;
+                files.push((file, text));
+            }
+        }
+    }
+    let saved = (state.model.clone(), state.auto_route, state.temperature);
+    let settings = seed
+        .model
+        .as_ref()
+        .map(|model| settings::set(state, "model", model))
+        .transpose()
+        .and_then(|_| {
+            seed.temperature
+                .map(|t| settings::set(state, "temperature", &t.to_string()))
+                .transpose()
+        });
+    if let Err(e) = settings {
+        (state.model, state.auto_route, state.temperature) = saved;
+        return Err(AppError::Command(format!("{}: {e}", path.display())));
+    }
+
+    commands::new_conversation(state);
+    let mut staged = Vec::new();
+    for (file, text) in files {
+        let label = file
+            .strip_prefix(&dir)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let content = format!("{label}:\n```\n{}\n```", text.trim_end());
+        let from = StagedFile {
+            path: file.clone(),
+            hash: stage::hash(&text),
+        };
+        stage::put(state, &label, from, content).await?;
+        staged.push(file);
+    }
+    let model = match state.auto_route {
+        true => route::AUTO,
+        false => state.model.as_str(),
+    };
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::SeedStarted,
+            &[
+                ("path", &path.display()),
+                ("model", &model),
+                ("system", &describe_system(&seed)),
+                ("messages", &describe_messages(&seed)),
+                ("files", &count(staged.len(), Msg::OneFile, Msg::Files)),
+            ],
+        )
+    );
+    state.seed = Some(Loaded {
+        path: path.to_path_buf(),
+        seed,
+        staged,
+    });
+    Ok(())
+}
+
+/// Writes the conversation's setup to `path`: the model, temperature, the seed's system prompt and
+/// messages if it started from one, and the files staged, from `path`'s directory.
+pub fn export(state: &AppState, path: &Path) -> Result<()> {
+    let dir = parent(path);
+    let dir = dir.canonicalize().unwrap_or(dir);
+    let mut seed = Seed {
+        model: Some(match state.auto_route {
+            true => route::AUTO.to_string(),
+            false => state.model.clone(),
+        }),
+        // By way of its text, so 0.2 isn't written as the f32 nearest it.
+        temperature: state.temperature.and_then(|t| t.to_string().parse().ok()),
+        ..Seed::default()
+    };
+    let mut from_seed: &[PathBuf] = &[];
+    if let Some(ref loaded) = state.seed {
+        let seed_dir = parent(&loaded.path);
+        let seed_dir = seed_dir.canonicalize().unwrap_or(seed_dir);
+        seed.system = loaded.seed.system.clone();
+        seed.messages = loaded.seed.messages.clone();
+        // The globs, moved to be from the new file's directory.
+        seed.files = loaded
+            .seed
+            .files
+            .iter()
+            .map(|pattern| {
+                let (glob, force) = sensitive::parse_forced(pattern);
+                let moved = relative(&seed_dir.join(glob), &dir);
+                match force {
+                    true => format!("!{moved}"),
+                    false => moved,
+                }
+            })
+            .collect();
+        from_seed = &loaded.staged;
+    }
+    for msg in &state.context {
+        let Some(ref file) = msg.file else {
+            continue;
+        };
+        let file = relative(&file.path, &dir);
+        if !from_seed.iter().any(|f| relative(f, &dir) == file) && !seed.files.contains(&file) {
+            seed.files.push(file);
+        }
+    }
+    let text = toml::to_string(&seed).map_err(|e| AppError::Command(e.to_string()))?;
+    fsutil::write_atomic(path, text.as_bytes()).map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::WriteFailed,
+            &[("path", &path.display()), ("error", &e)],
+        ))
+    })?;
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::SeedWritten,
+            &[
+                ("path", &path.display()),
+                ("system", &describe_system(&seed)),
+                ("messages", &describe_messages(&seed)),
+                (
+                    "files",
+                    &count(seed.files.len(), Msg::SeedOnePath, Msg::SeedPaths)
+                ),
+            ],
+        )
+    );
+    Ok(())
+}
+
+/// The files under `dir` matching `glob`, sorted. `*` and `?` match within a path's part and `**`
+/// any number of directories. A path without either is the file itself.
+fn expand(dir: &Path, glob: &str) -> Vec<PathBuf> {
+    let parts: Vec<&str> = glob
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != ".")
+        .collect();
+    let start = match Path::new(glob).is_absolute() {
+        true => PathBuf::from("/"),
+        false => dir.to_path_buf(),
+    };
+    let mut found = Vec::new();
+    walk(&start, &parts, &mut found);
+    found.sort();
+    found.dedup();
+    found
+}
+
+fn walk(dir: &Path, parts: &[&str], found: &mut Vec<PathBuf>) {
+    let Some((&part, rest)) = parts.split_first() else {
+        if dir.is_file() {
+            found.push(dir.to_path_buf());
+        }
+        return;
+    };
+    if part == "**" {
+        // None of the directories, or one more and still `**`.
+        walk(dir, rest, found);
+        for entry in entries(dir).into_iter().filter(|p| p.is_dir()) {
+            walk(&entry, parts, found);
+        }
+        return;
+    }
+    if !part.contains(['*', '?']) {
+        walk(&dir.join(part), rest, found);
+        return;
+    }
+    for entry in entries(dir) {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        // Hidden files and directories only match a pattern that starts with a dot, as in a shell.
+        if name.starts_with('.') && !part.starts_with('.') {
+            continue;
+        }
+        if matches(part, &name) {
+            walk(&entry, rest, found);
+        }
+    }
+}
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect()
+}
+
+/// Whether `name` matches `pattern`, where `*` is any run of characters and `?` any one.
+fn matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    // Where the last `*` was, and where in `name` it's matched up to, to go back to.
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// `path` from `dir`, with `..` when it's beside it, or as it is when it's elsewhere altogether.
+fn relative(path: &Path, dir: &Path) -> String {
+    let path_parts: Vec<Component> = path.components().collect();
+    let dir_parts: Vec<Component> = dir.components().collect();
+    let common = path_parts
+        .iter()
+        .zip(&dir_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Only the root in common: nothing is gained by climbing all the way up.
+    if common <= 1 && path.is_absolute() {
+        return path.display().to_string();
+    }
+    let up = dir_parts.len() - common;
+    let mut parts: Vec<String> = vec!["..".to_string(); up];
+    parts.extend(
+        path_parts[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    parts.join("/")
+}
+
+/// The directory `path` is in, `.` for a bare file name.
+fn parent(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn count(n: usize, one: Msg, many: Msg) -> String {
+    match n {
+        1 => msg::get(one).to_string(),
+        n => msg::fmt(many, &[("count", &n)]),
+    }
+}
+
+fn describe_system(seed: &Seed) -> &'static str {
+    match seed.system {
+        Some(_) => msg::get(Msg::SeedSystem),
+        None => msg::get(Msg::SeedNoSystem),
+    }
+}
+
+fn describe_messages(seed: &Seed) -> String {
+    count(seed.messages.len(), Msg::SeedOneMessage, Msg::SeedMessages)
+}
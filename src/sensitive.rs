@@ -0,0 +1,304 @@
+//! Keeps files that usually hold secrets, like `.env` or an SSH key, from being sent by accident,
+//! and binary files from being sent as garbage. A path given with `!` in front, or `--force` for
+//! `gpt ask`, is sent anyway.
+
+use serde::Deserialize;
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Component, Path},
+};
+
+use crate::{
+    error::{AppError, Result},
+    input,
+    msg::{self, Msg},
+};
+
+// How much of a file is looked at for NUL bytes.
+const BINARY_SNIFF_BYTES: usize = 1024;
+
+/// The `[sensitive_files]` table of the config. Patterns are file names where `*` matches any
+/// run of characters, like `*.pem`, or directory names ending in `/`, like `.ssh/`, which
+/// match everything under them.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SensitiveFiles {
+    /// Setting this replaces the built-in list.
+    pub patterns: Vec<String>,
+    /// Added to `patterns`, to extend the built-in list without repeating it.
+    pub extra: Vec<String>,
+    /// Sent even though they match, e.g. `.env.example`.
+    pub allow: Vec<String>,
+}
+
+impl Default for SensitiveFiles {
+    fn default() -> Self {
+        let patterns = [
+            ".env",
+            ".env.*",
+            "*.env",
+            "id_rsa",
+            "id_dsa",
+            "id_ecdsa",
+            "id_ed25519",
+            "*.pem",
+            "*.key",
+            "*.p12",
+            "*.pfx",
+            "credentials",
+            "credentials.*",
+            ".netrc",
+            ".pgpass",
+            ".npmrc",
+            ".pypirc",
+            ".ssh/",
+            ".aws/",
+            ".gnupg/",
+        ];
+        SensitiveFiles {
+            patterns: patterns.into_iter().map(String::from).collect(),
+            extra: Vec::new(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+impl SensitiveFiles {
+    /// The first pattern the path matches, unless it's allowed. The path a symlink points to is
+    /// checked too, so a harmless name can't hide a key.
+    pub fn matching(&self, path: &Path) -> Option<&str> {
+        let resolved = fs::canonicalize(path).ok();
+        let paths = [Some(path), resolved.as_deref()];
+        let matches = |pattern: &str| paths.iter().flatten().any(|p| path_matches(pattern, p));
+        if self.allow.iter().any(|p| matches(p)) {
+            return None;
+        }
+        self.patterns
+            .iter()
+            .chain(&self.extra)
+            .find(|p| matches(p))
+            .map(String::as_str)
+    }
+}
+
+/// A path as typed in the REPL: a leading `!` sends it even if it looks sensitive.
+pub fn parse_forced(path: &str) -> (&str, bool) {
+    match path.strip_prefix('!') {
+        Some(path) => (path, true),
+        None => (path, false),
+    }
+}
+
+/// Refuses a path that matches the sensitive patterns, unless it's forced.
+pub fn check(path: &Path, files: &SensitiveFiles, force: bool) -> Result<()> {
+    match files.matching(path).filter(|_| !force) {
+        Some(pattern) => Err(AppError::Command(msg::fmt(
+            Msg::FileSensitive,
+            &[("path", &path.display()), ("pattern", &pattern)],
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Reads a file to send it, after [`check`]ing it. A file that looks binary is an error too.
+pub fn read(path: &Path, files: &SensitiveFiles, force: bool) -> Result<String> {
+    check(path, files, force)?;
+    let bytes = fs::read(path).map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::ReadFailed,
+            &[("path", &path.display()), ("error", &e)],
+        ))
+    })?;
+    if is_binary(&bytes) {
+        return Err(AppError::Command(msg::fmt(
+            Msg::FileBinary,
+            &[("path", &path.display())],
+        )));
+    }
+    let text = String::from_utf8(bytes)
+        .map_err(|_| AppError::Command(msg::fmt(Msg::FileNotUtf8, &[("path", &path.display())])))?;
+    Ok(input::normalize_newlines(&text))
+}
+
+/// Whether a file starts like a binary one, without reading all of it.
+pub fn file_is_binary(path: &Path) -> io::Result<bool> {
+    let mut start = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    fs::File::open(path)?
+        .take(BINARY_SNIFF_BYTES as u64)
+        .read_to_end(&mut start)?;
+    Ok(is_binary(&start))
+}
+
+/// Text files don't have NUL bytes; nearly every binary format has one near the start.
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+fn path_matches(pattern: &str, path: &Path) -> bool {
+    match pattern.strip_suffix('/') {
+        // Only directories above the file count, not the file itself.
+        Some(dir) => path
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .any(|c| matches!(c, Component::Normal(name) if glob(dir, &name.to_string_lossy()))),
+        None => path
+            .file_name()
+            .is_some_and(|name| glob(pattern, &name.to_string_lossy())),
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` is any run of characters. Case is ignored, since
+/// `ID_RSA` on a case-insensitive file system is the same file.
+fn glob(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sensitive(path: &str) -> bool {
+        SensitiveFiles::default()
+            .matching(Path::new(path))
+            .is_some()
+    }
+
+    fn dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("gpt-cli-sensitive-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn files_that_usually_hold_secrets_are_caught() {
+        for path in [
+            ".env",
+            "app/.env.production",
+            "prod.env",
+            "/home/me/.ssh/config",
+            "keys/ID_RSA",
+            "certs/server.pem",
+            ".aws/credentials",
+            "credentials.json",
+        ] {
+            assert!(sensitive(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn ordinary_files_are_not() {
+        for path in [
+            "src/main.rs",
+            "environment.md",
+            "id_rsa.pub.txt.md",
+            "docs/ssh/notes.txt",
+            ".ssh",
+            "pem",
+        ] {
+            assert!(!sensitive(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn globs_match_the_whole_name() {
+        assert!(glob("*.pem", "a.PEM"));
+        assert!(glob("a*b*c", "aXbYc"));
+        assert!(!glob("a*b*c", "aXcYb"));
+        assert!(!glob(".env", ".env2"));
+        assert!(glob("*", ""));
+    }
+
+    #[test]
+    fn the_config_can_extend_allow_and_replace() {
+        let files = SensitiveFiles {
+            extra: vec!["*.kdbx".to_string()],
+            allow: vec![".env.example".to_string()],
+            ..SensitiveFiles::default()
+        };
+        assert_eq!(files.matching(Path::new("vault.kdbx")), Some("*.kdbx"));
+        assert_eq!(files.matching(Path::new(".env.example")), None);
+        assert_eq!(files.matching(Path::new(".env.local")), Some(".env.*"));
+
+        let replaced = SensitiveFiles {
+            patterns: vec!["secret.txt".to_string()],
+            ..SensitiveFiles::default()
+        };
+        assert!(replaced.matching(Path::new(".env")).is_none());
+        assert!(replaced.matching(Path::new("a/secret.txt")).is_some());
+    }
+
+    #[test]
+    fn forcing_sends_it_anyway() {
+        assert_eq!(parse_forced("!.env"), (".env", true));
+        assert_eq!(parse_forced(".env"), (".env", false));
+        let files = SensitiveFiles::default();
+        let err = check(Path::new(".env"), &files, false).unwrap_err();
+        assert!(err.to_string().contains("matches .env"), "{err}");
+        assert!(check(Path::new(".env"), &files, true).is_ok());
+    }
+
+    #[test]
+    fn binary_files_are_skipped() {
+        let dir = dir("binary");
+        let binary = dir.join("image.png");
+        fs::write(&binary, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        assert!(file_is_binary(&binary).unwrap());
+        let err = read(&binary, &SensitiveFiles::default(), true).unwrap_err();
+        assert!(err.to_string().contains("binary"), "{err}");
+
+        // Only the start is looked at.
+        let mut late = vec![b'a'; BINARY_SNIFF_BYTES];
+        late.push(0);
+        let text = dir.join("log.txt");
+        fs::write(&text, &late).unwrap();
+        assert!(!file_is_binary(&text).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn text_is_read_with_newlines_normalized() {
+        let dir = dir("text");
+        let path = dir.join("notes.txt");
+        fs::write(&path, "a\r\nb\r\n").unwrap();
+        assert_eq!(
+            read(&path, &SensitiveFiles::default(), false).unwrap(),
+            "a\nb\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_link_to_a_key_is_caught_by_where_it_points() {
+        let dir = dir("link");
+        fs::create_dir_all(dir.join(".ssh")).unwrap();
+        fs::write(dir.join(".ssh/id_ed25519"), "KEY").unwrap();
+        let link = dir.join("notes.txt");
+        std::os::unix::fs::symlink(dir.join(".ssh/id_ed25519"), &link).unwrap();
+        assert!(SensitiveFiles::default().matching(&link).is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{AppError, Result},
+    fsutil,
+    msg::{self, Msg},
+    paths, schema, Message,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Session {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub model: String,
+    pub messages: Vec<Message>,
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(AppError::Command(msg::fmt(
+            Msg::InvalidSessionName,
+            &[("name", &name)],
+        )));
+    }
+    Ok(())
+}
+
+pub fn path(name: &str) -> PathBuf {
+    paths::sessions_dir().join(format!("{name}.json"))
+}
+
+/// Where the lock on a session is kept, see `lock`.
+pub fn lock_path(name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    Ok(paths::sessions_dir().join(format!("{name}.lock")))
+}
+
+pub fn save(name: &str, session: &Session) -> Result<()> {
+    validate_name(name)?;
+    paths::ensure_dir(&paths::sessions_dir())?;
+    let json = schema::encode(session, true).map_err(io::Error::from)?;
+    fsutil::write_atomic(&path(name), &json)?;
+    Ok(())
+}
+
+/// Loads a saved session. One that's corrupt, e.g. from a write cut short before writes were
+/// atomic, is moved to `<name>.json.corrupt-<timestamp>`. One saved by a newer gpt is left as it is.
+pub fn load(name: &str) -> Result<Session> {
+    read(name)?.map_err(|e| {
+        let what = msg::fmt(Msg::SessionNamed, &[("name", &name)]);
+        if let schema::Error::Newer(_) = e {
+            return AppError::Command(e.describe(&what));
+        }
+        // Moved aside, so it stops showing up as a session but isn't lost.
+        match fsutil::quarantine(&path(name)) {
+            Ok(moved) => AppError::Command(msg::fmt(
+                Msg::FileQuarantined,
+                &[("what", &what), ("error", &e), ("path", &moved.display())],
+            )),
+            Err(_) => AppError::Command(e.describe(&what)),
+        }
+    })
+}
+
+/// A saved session's title, if it has one, leaving the file alone even if it's corrupt.
+pub fn title(name: &str) -> Option<String> {
+    read(name).ok()?.ok()?.title
+}
+
+/// Reads a session file without changing it, for `gpt view`. Messages that can't be read, from a
+/// file that's corrupt or was edited by hand, are left out; how many were is returned with it.
+pub fn salvage(path: &Path) -> Result<(Session, usize)> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::ReadFailed,
+            &[("path", &path.display()), ("error", &e)],
+        ))
+    })?;
+    let mut value: Value = serde_json::from_str(&text).map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::NotASessionFile,
+            &[("path", &path.display()), ("error", &e)],
+        ))
+    })?;
+    schema::upgrade(&mut value)
+        .map_err(|e| AppError::Command(e.describe(&path.display().to_string())))?;
+    if let Ok(session) = serde_json::from_value(value.clone()) {
+        return Ok((session, 0));
+    }
+    let raw = value["messages"].as_array().cloned().unwrap_or_default();
+    let messages: Vec<Message> = raw
+        .iter()
+        .filter_map(|m| serde_json::from_value(m.clone()).ok())
+        .collect();
+    let session = Session {
+        title: value["title"].as_str().map(str::to_string),
+        model: value["model"].as_str().unwrap_or("unknown").to_string(),
+        messages,
+    };
+    let skipped = raw.len() - session.messages.len();
+    Ok((session, skipped))
+}
+
+fn read(name: &str) -> Result<std::result::Result<Session, schema::Error>> {
+    validate_name(name)?;
+    let text = match fs::read_to_string(path(name)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(AppError::Command(msg::fmt(
+                Msg::NoSuchSession,
+                &[("name", &name)],
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(schema::decode(&text))
+}
+
+/// Names of all saved sessions, sorted.
+pub fn list() -> Result<Vec<String>> {
+    let entries = match fs::read_dir(paths::sessions_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(stem) = path.file_stem() {
+                names.push(stem.to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
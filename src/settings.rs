@@ -0,0 +1,332 @@
+use crate::{
+    budget::BudgetScope,
+    error::{AppError, Result},
+    footer::FooterStyle,
+    math::MathMode,
+    models,
+    msg::{self, Lang, Msg},
+    preset::{self, Preset},
+    provider::Provider,
+    route, safe,
+    style::{self, Theme},
+    AppState,
+};
+
+/// How max_tokens was chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxTokens {
+    /// Follow the model's default.
+    Auto,
+    /// Whatever the model can produce.
+    Max,
+    Fixed(u32),
+}
+
+impl MaxTokens {
+    pub fn parse(value: &str) -> Result<MaxTokens> {
+        match value {
+            "auto" => Ok(MaxTokens::Auto),
+            "max" => Ok(MaxTokens::Max),
+            n => match n.parse() {
+                Ok(n) if n > 0 => Ok(MaxTokens::Fixed(n)),
+                _ => Err(AppError::Command(msg::fmt(
+                    Msg::InvalidMaxTokens,
+                    &[("value", &value)],
+                ))),
+            },
+        }
+    }
+
+    /// The value to send for `model`, and where it came from.
+    pub fn resolve(self, model: &str) -> (u32, &'static str) {
+        let info = models::lookup(model);
+        match (self, info) {
+            (MaxTokens::Fixed(n), _) => (n, msg::get(Msg::MaxTokensExplicit)),
+            (MaxTokens::Max, Some(info)) => (info.max_output, msg::get(Msg::MaxTokensModelMax)),
+            (_, Some(info)) => (info.default_output, msg::get(Msg::MaxTokensModelDefault)),
+            (_, None) => (
+                models::GLOBAL_DEFAULT_OUTPUT,
+                msg::get(Msg::MaxTokensGlobalDefault),
+            ),
+        }
+    }
+}
+
+pub struct SettingSpec {
+    pub name: &'static str,
+    pub summary: Msg,
+}
+
+pub const SETTINGS: &[SettingSpec] = &[
+    SettingSpec {
+        name: "model",
+        summary: Msg::SettingModel,
+    },
+    SettingSpec {
+        name: "provider",
+        summary: Msg::SettingProvider,
+    },
+    SettingSpec {
+        name: "organization",
+        summary: Msg::SettingOrganization,
+    },
+    SettingSpec {
+        name: "max_tokens",
+        summary: Msg::SettingMaxTokens,
+    },
+    SettingSpec {
+        name: "temperature",
+        summary: Msg::SettingTemperature,
+    },
+    SettingSpec {
+        name: "preset",
+        summary: Msg::SettingPreset,
+    },
+    SettingSpec {
+        name: "auto_pipe",
+        summary: Msg::SettingAutoPipe,
+    },
+    SettingSpec {
+        name: "cache",
+        summary: Msg::SettingCache,
+    },
+    SettingSpec {
+        name: "footer",
+        summary: Msg::SettingFooter,
+    },
+    SettingSpec {
+        name: "theme",
+        summary: Msg::SettingTheme,
+    },
+    SettingSpec {
+        name: "math",
+        summary: Msg::SettingMath,
+    },
+    SettingSpec {
+        name: "word_wrap",
+        summary: Msg::SettingWordWrap,
+    },
+    SettingSpec {
+        name: "lang",
+        summary: Msg::SettingLang,
+    },
+    SettingSpec {
+        name: "conversation_budget",
+        summary: Msg::SettingConversationBudget,
+    },
+    SettingSpec {
+        name: "downgrade_on_budget",
+        summary: Msg::SettingDowngradeOnBudget,
+    },
+    SettingSpec {
+        name: "budget_scope",
+        summary: Msg::SettingBudgetScope,
+    },
+    SettingSpec {
+        name: "warn_on_context_drop",
+        summary: Msg::SettingWarnOnContextDrop,
+    },
+    SettingSpec {
+        name: "substitution",
+        summary: Msg::SettingSubstitution,
+    },
+    SettingSpec {
+        name: "notify_after_secs",
+        summary: Msg::SettingNotifyAfterSecs,
+    },
+    SettingSpec {
+        name: "stall_after_secs",
+        summary: Msg::SettingStallAfterSecs,
+    },
+    SettingSpec {
+        name: "safe_mode",
+        summary: Msg::SettingSafeMode,
+    },
+    SettingSpec {
+        name: "title_after_exchanges",
+        summary: Msg::SettingTitleAfterExchanges,
+    },
+];
+
+fn unknown(name: &str) -> AppError {
+    AppError::Command(msg::fmt(Msg::UnknownSetting, &[("name", &name)]))
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(AppError::Command(msg::fmt(
+            Msg::InvalidBool,
+            &[("value", &value)],
+        ))),
+    }
+}
+
+pub fn get(state: &AppState, name: &str) -> Result<String> {
+    Ok(match name {
+        "model" if state.auto_route => msg::fmt(Msg::AutoModel, &[("model", &state.model)]),
+        "model" => state.model.clone(),
+        "provider" => state.config.provider.name().to_string(),
+        "organization" => state
+            .config
+            .organization
+            .clone()
+            .unwrap_or_else(|| "default".to_string()),
+        "max_tokens" => {
+            let (n, source) = state.max_tokens.resolve(&state.model);
+            format!("{n} ({source})")
+        }
+        "temperature" => match state.temperature {
+            Some(t) => t.to_string(),
+            None => "default".to_string(),
+        },
+        "preset" => state.preset.name().to_string(),
+        "auto_pipe" => state.auto_pipe.to_string(),
+        "cache" => state.config.cache.to_string(),
+        "footer" => format!("{:?}", state.footer).to_lowercase(),
+        "theme" => style::theme().name().to_string(),
+        "math" => state.config.math.name().to_string(),
+        "word_wrap" => state.config.word_wrap.to_string(),
+        "lang" => msg::lang().code().to_string(),
+        "conversation_budget" => match state.config.conversation_budget {
+            Some(budget) => budget.to_string(),
+            None => "off".to_string(),
+        },
+        "downgrade_on_budget" => state.config.downgrade_on_budget.to_string(),
+        "budget_scope" => state.config.budget_scope.name().to_string(),
+        "warn_on_context_drop" => state.config.warn_on_context_drop.to_string(),
+        "substitution" => state.config.substitution.to_string(),
+        "notify_after_secs" => match state.config.notify_after_secs {
+            Some(secs) => secs.to_string(),
+            None => "off".to_string(),
+        },
+        "stall_after_secs" => match state.config.stall_after_secs {
+            0 => "off".to_string(),
+            secs => secs.to_string(),
+        },
+        "safe_mode" => safe::enabled().to_string(),
+        "title_after_exchanges" => state.config.title_after_exchanges.to_string(),
+        _ => return Err(unknown(name)),
+    })
+}
+
+pub fn set(state: &mut AppState, name: &str, value: &str) -> Result<()> {
+    match name {
+        "model" if value == route::AUTO => state.auto_route = true,
+        "model" => {
+            state.model = models::resolve(value).ok_or_else(|| {
+                AppError::Command(msg::fmt(Msg::ModelUnknown, &[("name", &value)]))
+            })?;
+            state.auto_route = false;
+        }
+        "provider" => {
+            state.config.provider = Provider::parse(value)
+                .ok_or_else(|| AppError::Command(msg::get(Msg::InvalidProvider).to_string()))?
+        }
+        "organization" => {
+            state.config.organization = match value {
+                "default" | "off" => None,
+                id => Some(id.to_string()),
+            }
+        }
+        "max_tokens" => state.max_tokens = MaxTokens::parse(value)?,
+        "temperature" => {
+            state.temperature = match value {
+                "default" | "off" => None,
+                _ => Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|t: &f32| (0.0..=2.0).contains(t))
+                        .ok_or_else(|| {
+                            AppError::Command(msg::fmt(
+                                Msg::InvalidTemperature,
+                                &[("value", &value)],
+                            ))
+                        })?,
+                ),
+            }
+        }
+        "preset" => {
+            let preset = Preset::parse(value)
+                .ok_or_else(|| AppError::Command(msg::get(Msg::InvalidPreset).to_string()))?;
+            preset::apply(state, preset);
+        }
+        "auto_pipe" => state.auto_pipe = parse_bool(value)?,
+        "cache" => state.config.cache = parse_bool(value)?,
+        "footer" => {
+            state.footer = match value {
+                "off" => FooterStyle::Off,
+                "minimal" => FooterStyle::Minimal,
+                "full" => FooterStyle::Full,
+                _ => return Err(AppError::Command(msg::get(Msg::InvalidFooter).to_string())),
+            }
+        }
+        "theme" => {
+            let theme = Theme::parse(value)
+                .ok_or_else(|| AppError::Command(msg::get(Msg::InvalidTheme).to_string()))?;
+            style::set_theme(theme);
+        }
+        "math" => {
+            state.config.math = MathMode::parse(value)
+                .ok_or_else(|| AppError::Command(msg::get(Msg::InvalidMath).to_string()))?
+        }
+        "lang" => {
+            let lang = Lang::parse(value).ok_or_else(|| {
+                AppError::Command(msg::fmt(
+                    Msg::LangUnknownSet,
+                    &[("lang", &value), ("langs", &Lang::codes())],
+                ))
+            })?;
+            msg::set(lang);
+        }
+        "conversation_budget" => {
+            state.config.conversation_budget = match value {
+                "off" => None,
+                _ => Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|&b: &f64| b >= 0.0)
+                        .ok_or_else(|| {
+                            AppError::Command(msg::fmt(Msg::InvalidAmount, &[("value", &value)]))
+                        })?,
+                ),
+            }
+        }
+        "word_wrap" => state.config.word_wrap = parse_bool(value)?,
+        "downgrade_on_budget" => state.config.downgrade_on_budget = parse_bool(value)?,
+        "budget_scope" => {
+            state.config.budget_scope = BudgetScope::parse(value)
+                .ok_or_else(|| AppError::Command(msg::get(Msg::InvalidBudgetScope).to_string()))?
+        }
+        "warn_on_context_drop" => state.config.warn_on_context_drop = parse_bool(value)?,
+        "substitution" => state.config.substitution = parse_bool(value)?,
+        "notify_after_secs" => {
+            state.config.notify_after_secs = match value {
+                "off" => None,
+                _ => Some(value.parse().map_err(|_| {
+                    AppError::Command(msg::fmt(Msg::InvalidSeconds, &[("value", &value)]))
+                })?),
+            }
+        }
+        "stall_after_secs" => {
+            state.config.stall_after_secs = match value {
+                "off" => 0,
+                _ => value.parse().map_err(|_| {
+                    AppError::Command(msg::fmt(Msg::InvalidSeconds, &[("value", &value)]))
+                })?,
+            }
+        }
+        // Otherwise anything pasted into the REPL could switch it off.
+        "safe_mode" => return Err(AppError::Command(msg::get(Msg::SafeModeFixed).to_string())),
+        "title_after_exchanges" => {
+            state.config.title_after_exchanges = value.parse().map_err(|_| {
+                AppError::Command(msg::fmt(Msg::InvalidNumber, &[("value", &value)]))
+            })?
+        }
+        _ => return Err(unknown(name)),
+    }
+    Ok(())
+}
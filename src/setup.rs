@@ -0,0 +1,208 @@
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use std::{
+    collections::BTreeMap,
+    io::{self, IsTerminal, Write},
+    path::Path,
+};
+
+use crate::{
+    currency::Currency,
+    error::{AppError, Result},
+    fsutil::AtomicFile,
+    input, models,
+    msg::{self, Msg},
+    paths,
+    provider::Provider,
+    signals,
+};
+
+const PROVIDERS: [Provider; 4] = [
+    Provider::OpenAI,
+    Provider::OpenRouter,
+    Provider::Anthropic,
+    Provider::Gemini,
+];
+
+/// What the first-run setup decided, already written to the config file if the user wanted.
+pub struct Setup {
+    pub provider: Provider,
+    pub model: Option<String>,
+    pub api_key: String,
+}
+
+/// Walks someone without a key or a config file through choosing a provider, entering a key and
+/// picking a default model, then writes the config file if they want the key kept there.
+pub async fn run(config_file: &Path) -> Result<Setup> {
+    println!("{}\n", msg::get(Msg::SetupIntro));
+
+    for (i, provider) in PROVIDERS.iter().enumerate() {
+        println!("  {}) {}", i + 1, provider.name());
+    }
+    let provider = loop {
+        let answer = read_line(msg::get(Msg::SetupProvider), false).await?;
+        match answer.parse::<usize>() {
+            _ if answer.is_empty() => break Provider::OpenAI,
+            Ok(n) if (1..=PROVIDERS.len()).contains(&n) => break PROVIDERS[n - 1],
+            _ => match Provider::parse(&answer) {
+                Some(provider) => break provider,
+                None => println!("{}", msg::get(Msg::SetupPickNumber)),
+            },
+        }
+    };
+
+    let api_key = loop {
+        let key = read_line(
+            &msg::fmt(Msg::SetupKey, &[("var", &provider.key_var())]),
+            true,
+        )
+        .await?;
+        if !key.is_empty() {
+            break key;
+        }
+    };
+
+    let model = pick_model(provider).await?;
+
+    println!(
+        "\n{}",
+        msg::fmt(
+            Msg::SetupWhereKey,
+            &[
+                ("path", &config_file.display()),
+                ("var", &provider.key_var())
+            ],
+        )
+    );
+    let save = read_line(msg::get(Msg::SetupSave), false).await?;
+    if save.eq_ignore_ascii_case("n") {
+        println!("{}\n", msg::get(Msg::SetupNotSaved));
+    } else {
+        write_config(config_file, provider, model.as_deref(), &api_key)?;
+        println!(
+            "{}\n",
+            msg::fmt(Msg::SetupSaved, &[("path", &config_file.display())])
+        );
+    }
+    Ok(Setup {
+        provider,
+        model,
+        api_key,
+    })
+}
+
+/// Offers the provider's known models with their prices, returning `None` for the provider's default.
+async fn pick_model(provider: Provider) -> Result<Option<String>> {
+    let choices: Vec<_> = models::all()
+        .filter(|m| Provider::for_model(m.id, provider) == provider && !m.id.contains('/'))
+        .collect();
+    // OpenRouter's catalog is only fetched once there's a key.
+    if choices.is_empty() {
+        return Ok(None);
+    }
+    let default = provider.default_model();
+    let currency = Currency::default();
+    println!("\n{}", msg::get(Msg::SetupModels));
+    for (i, info) in choices.iter().enumerate() {
+        let marker = if info.id == default {
+            msg::get(Msg::SetupDefault)
+        } else {
+            ""
+        };
+        let (input, output) = info.list_prices(&currency);
+        println!("  {}) {:<28} {input} / {output}{marker}", i + 1, info.id);
+    }
+    loop {
+        let answer = read_line(msg::get(Msg::SetupModel), false).await?;
+        match answer.parse::<usize>() {
+            _ if answer.is_empty() => return Ok(None),
+            Ok(n) if (1..=choices.len()).contains(&n) => {
+                return Ok(Some(choices[n - 1].id.to_string()))
+            }
+            _ => println!("{}", msg::get(Msg::SetupPickModel)),
+        }
+    }
+}
+
+/// Reads a line from the terminal, without echoing it if `hidden`. Ctrl+C cancels, leaving the
+/// terminal as it was.
+async fn read_line(prompt: &str, hidden: bool) -> Result<String> {
+    print!("{prompt} ");
+    io::stdout().flush()?;
+    if hidden && io::stdin().is_terminal() {
+        return read_hidden().await;
+    }
+    let read = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).map(|n| (n, line))
+    });
+    let (n, line) = tokio::select! {
+        result = read => result.map_err(|e| AppError::Command(e.to_string()))??,
+        _ = signals::interrupt() => {
+            println!();
+            return Err(AppError::Cancelled);
+        }
+    };
+    if n == 0 {
+        return Err(AppError::Command(
+            msg::get(Msg::SetupNeedsTerminal).to_string(),
+        ));
+    }
+    Ok(line.trim().to_string())
+}
+
+/// A line read key by key in raw mode, so nothing typed is shown.
+async fn read_hidden() -> Result<String> {
+    let raw = input::RawMode::enable()?;
+    let mut events = EventStream::new();
+    let mut line = String::new();
+    loop {
+        let key = match events.next().await {
+            Some(Ok(Event::Key(key))) if key.kind != KeyEventKind::Release => key,
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.into()),
+            None => break,
+        };
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => break,
+            // A key in raw mode rather than a signal.
+            KeyCode::Char('c') if control => {
+                drop(raw);
+                println!();
+                return Err(AppError::Cancelled);
+            }
+            KeyCode::Char(c) if !control => line.push(c),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            _ => {}
+        }
+    }
+    drop(raw);
+    // Enter wasn't echoed either.
+    println!();
+    Ok(line.trim().to_string())
+}
+
+fn write_config(path: &Path, provider: Provider, model: Option<&str>, key: &str) -> Result<()> {
+    let mut text = "# Written by gpt's first-run setup.\n".to_string();
+    if provider != Provider::OpenAI {
+        text.push_str(&format!("provider = \"{}\"\n", provider.name()));
+    }
+    if let Some(model) = model {
+        text.push_str(&format!("model = \"{model}\"\n"));
+    }
+    let keys = BTreeMap::from([(provider.name(), key)]);
+    text.push_str("\n[api_keys]\n");
+    text.push_str(&toml::to_string(&keys).map_err(|e| AppError::Config(e.to_string()))?);
+
+    if let Some(dir) = path.parent() {
+        paths::ensure_dir(dir)?;
+    }
+    // The file holds a key, so only its owner can read it.
+    let mut file = AtomicFile::create_private(path)?;
+    file.write_all(text.as_bytes())?;
+    file.commit()?;
+    Ok(())
+}
@@ -0,0 +1,414 @@
+//! `gpt cmd` and `:cmd`: a shell command for a task, or an explanation of one, as structured output.
+//!
+//! The command is shown with a one-line explanation and copied to the clipboard. With `--execute`
+//! it's run, but only once the user confirms, and a command the model flags as dangerous needs
+//! `yes` typed in full. Nothing is ever run without someone there to confirm it.
+
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionStreamOptions, CreateChatCompletionRequestArgs, ResponseFormat,
+        ResponseFormatJsonSchema,
+    },
+    Client,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    env,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+    process::{self, Stdio},
+};
+
+use crate::{
+    cli::CmdArgs,
+    config::Config,
+    error::{AppError, Result},
+    footer::{self, Footer},
+    input,
+    msg::{self, Msg},
+    provider::{self, Provider},
+    safe::{self, Feature},
+    settings::MaxTokens,
+    signals, style,
+    usage::{Purpose, Usage},
+    AppState,
+};
+
+/// What `:cmd` was asked.
+#[derive(Debug)]
+pub struct ShellCmd {
+    pub text: String,
+    pub execute: bool,
+    pub explain: bool,
+    /// The text is a failed command and what it printed, from `:fix`.
+    pub fix: bool,
+}
+
+impl ShellCmd {
+    /// Parses `[--execute|-x] [--explain] <task or command>`.
+    pub fn parse(args: &str) -> Option<ShellCmd> {
+        let mut cmd = ShellCmd {
+            text: String::new(),
+            execute: false,
+            explain: false,
+            fix: false,
+        };
+        let mut rest = args.trim_start();
+        loop {
+            let (flag, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            match flag {
+                "--execute" | "-x" => cmd.execute = true,
+                "--explain" => cmd.explain = true,
+                _ => break,
+            }
+            rest = after.trim_start();
+        }
+        cmd.text = rest.trim_end().to_string();
+        (!cmd.text.is_empty()).then_some(cmd)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Suggestion {
+    command: String,
+    explanation: String,
+    dangerous: bool,
+}
+
+/// Runs `gpt cmd`, returning the exit code: the command's own when it's run.
+pub async fn run(
+    args: CmdArgs,
+    client: &Client<OpenAIConfig>,
+    config: &Config,
+    model: &str,
+) -> Result<u8> {
+    let cmd = ShellCmd {
+        text: args.text.join(" "),
+        execute: args.execute,
+        explain: args.explain,
+        fix: false,
+    };
+    let (suggestion, usage) = suggest(client, config, model, &cmd).await?;
+    let color = style::color_enabled(io::stderr().is_terminal());
+    // Only the command goes to stdout, so it can be captured; the rest is for whoever's reading.
+    println!("{}", suggestion.command);
+    show_notes(&mut io::stderr(), &suggestion, &cmd, color)?;
+    let footer = Footer {
+        usage: &usage,
+        model,
+        from_cache: false,
+        resumed: false,
+        routed: false,
+        sent: None,
+        reused: None,
+        currency: &config.currency,
+        timing: None,
+        estimated: false,
+        overrides: None,
+        drift: None,
+    };
+    footer::print(&mut io::stderr(), &footer, config.footer)?;
+    if !cmd.execute {
+        return Ok(0);
+    }
+    let mut command = shell_command(&suggestion.command)?;
+
+    let stdin = io::stdin();
+    let tty = stdin.is_terminal();
+    if !tty {
+        eprintln!("{}", msg::get(Msg::ExecuteNeedsTerminal));
+        return Ok(1);
+    }
+    eprint!("{} ", confirm_prompt(suggestion.dangerous));
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    let answer = match stdin.lock().read_line(&mut answer)? {
+        0 => None,
+        _ => Some(answer),
+    };
+    if !should_run(suggestion.dangerous, cmd.execute, tty, answer.as_deref()) {
+        eprintln!("{}", msg::get(Msg::NotRun));
+        return Ok(1);
+    }
+    let status = command
+        .status()
+        .map_err(|e| AppError::Command(msg::fmt(Msg::CommandFailedToRun, &[("error", &e)])))?;
+    Ok(status.code().map_or(1, |code| code as u8))
+}
+
+/// Runs `:cmd`. The command's output goes straight to the terminal; it gets no stdin, which the REPL
+/// is reading.
+pub async fn run_repl(
+    cmd: ShellCmd,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let model = state.get_model();
+    let (suggestion, _) = tokio::select! {
+        result = suggest(client, &state.config, &model, &cmd) => result?,
+        _ = signals::interrupt() => return Err(AppError::Cancelled),
+    };
+    println!("{}", style::highlight(&suggestion.command, state.color));
+    show_notes(&mut io::stdout(), &suggestion, &cmd, state.color)?;
+    if !cmd.execute {
+        return Ok(());
+    }
+    let mut command = shell_command(&suggestion.command)?;
+    let answer = input::ask(state, &confirm_prompt(suggestion.dangerous)).await?;
+    if !should_run(
+        suggestion.dangerous,
+        cmd.execute,
+        state.interactive,
+        answer.as_deref(),
+    ) {
+        println!("{}", msg::get(Msg::NotRun));
+        return Ok(());
+    }
+    let status = command
+        .stdin(Stdio::null())
+        .status()
+        .map_err(|e| AppError::Command(msg::fmt(Msg::CommandFailedToRun, &[("error", &e)])))?;
+    match status.code() {
+        Some(0) => {}
+        Some(code) => println!("{}", msg::fmt(Msg::CommandExited, &[("code", &code)])),
+        None => println!("{}", msg::get(Msg::CommandKilled)),
+    }
+    Ok(())
+}
+
+/// Whether a suggested command runs: only with `--execute`, only with a terminal to confirm it on,
+/// and only on `y` or `yes` to the confirmation, or `yes` in full for a dangerous command so a
+/// reflexive `y` isn't enough. No answer never runs it.
+fn should_run(dangerous: bool, execute: bool, tty: bool, answer: Option<&str>) -> bool {
+    if !execute || !tty {
+        return false;
+    }
+    let Some(answer) = answer.map(|answer| answer.trim().to_lowercase()) else {
+        return false;
+    };
+    match dangerous {
+        true => answer == "yes",
+        false => answer == "y" || answer == "yes",
+    }
+}
+
+fn confirm_prompt(dangerous: bool) -> String {
+    match dangerous {
+        true => msg::get(Msg::ConfirmDangerous).to_string(),
+        false => msg::get(Msg::ConfirmRun).to_string(),
+    }
+}
+
+/// The explanation, the danger warning and where the command was copied to.
+fn show_notes(
+    out: &mut impl Write,
+    suggestion: &Suggestion,
+    cmd: &ShellCmd,
+    color: bool,
+) -> Result<()> {
+    writeln!(out, "  {}", suggestion.explanation.trim())?;
+    if suggestion.dangerous {
+        writeln!(
+            out,
+            "  {}",
+            style::highlight(msg::get(Msg::CommandDestructive), color)
+        )?;
+    }
+    // Copying the command that was asked about would only copy it back.
+    if !cmd.explain {
+        let notice = match copy_to_clipboard(&suggestion.command) {
+            Ok(tool) => msg::fmt(Msg::CommandCopied, &[("tool", &tool)]),
+            Err(e) => msg::fmt(Msg::CommandNotCopied, &[("error", &e)]),
+        };
+        writeln!(out, "  {}", style::dim(&notice, color))?;
+    }
+    Ok(())
+}
+
+/// The user's shell, from `$SHELL`, for the prompt and for running the command.
+pub fn shell() -> (String, String) {
+    if cfg!(windows) {
+        return ("cmd".to_string(), "cmd".to_string());
+    }
+    let path = env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "sh".to_string());
+    let name = Path::new(&path)
+        .file_name()
+        .map_or_else(|| path.clone(), |n| n.to_string_lossy().into_owned());
+    (path, name)
+}
+
+/// `command` run by the user's shell, unless safe mode stops it.
+pub fn shell_command(command: &str) -> Result<process::Command> {
+    safe::check(Feature::Shell)?;
+    let (path, _) = shell();
+    let mut shell = process::Command::new(path);
+    shell
+        .arg(if cfg!(windows) { "/C" } else { "-c" })
+        .arg(command);
+    Ok(shell)
+}
+
+async fn suggest(
+    client: &Client<OpenAIConfig>,
+    config: &Config,
+    model: &str,
+    cmd: &ShellCmd,
+) -> Result<(Suggestion, Usage)> {
+    let (_, shell) = shell();
+    let task = match (cmd.explain, cmd.fix) {
+        (true, _) => "Explain the shell command the user gives. For `command`, repeat it exactly as given; for `explanation`, say in one line what it does.",
+        (_, true) => "The user's shell command failed, and they give what it printed. Work out why, and write a single shell command that fixes the problem or does what they were trying to do. For `explanation`, say in a sentence or two what went wrong and what the command does.",
+        _ => "Write a single shell command that does what the user asks. For `explanation`, say in one line what it does.",
+    };
+    let system = format!(
+        "{task} The shell is {shell} on {}. Set `dangerous` to true if running the command could delete or overwrite data, change permissions or system settings, stop processes, use sudo, or otherwise be hard to undo. Reply with JSON only, like {{\"command\": \"...\", \"explanation\": \"...\", \"dangerous\": false}}.",
+        env::consts::OS
+    );
+    let max_tokens = config
+        .max_tokens
+        .map_or(MaxTokens::Auto, MaxTokens::Fixed)
+        .resolve(model)
+        .0;
+    let mut request = CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .max_completion_tokens(max_tokens)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system)
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(cmd.text.as_str())
+                .build()?
+                .into(),
+        ])
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()?;
+    // Other providers only get the instructions in the prompt.
+    if Provider::for_model(model, config.provider) == Provider::OpenAI {
+        request.response_format = Some(ResponseFormat::JsonSchema {
+            json_schema: ResponseFormatJsonSchema {
+                description: None,
+                name: "shell_command".to_string(),
+                schema: Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "command": { "type": "string" },
+                        "explanation": { "type": "string" },
+                        "dangerous": { "type": "boolean" },
+                    },
+                    "required": ["command", "explanation", "dangerous"],
+                    "additionalProperties": false,
+                })),
+                strict: Some(true),
+            },
+        });
+    }
+
+    let (text, usage) = provider::complete(client, request, config, Purpose::Answer).await?;
+    let json = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```");
+    let mut suggestion: Suggestion = serde_json::from_str(json)
+        .map_err(|e| AppError::Command(msg::fmt(Msg::SuggestionNotJson, &[("error", &e)])))?;
+    if cmd.explain {
+        // What runs is what the user wrote, whatever the model repeated.
+        suggestion.command = cmd.text.clone();
+    }
+    if suggestion.command.trim().is_empty() {
+        return Err(AppError::Command(msg::get(Msg::NoSuggestion).to_string()));
+    }
+    Ok((suggestion, usage))
+}
+
+/// Copies the text with the platform's clipboard tool, returning the tool's name.
+pub fn copy_to_clipboard(text: &str) -> std::result::Result<&'static str, String> {
+    if let Some(why) = safe::disabled(Feature::Clipboard) {
+        return Err(why);
+    }
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+    for (tool, args) in tools {
+        // wl-copy is only any use under Wayland, and the X tools under X.
+        let display = match *tool {
+            "wl-copy" => "WAYLAND_DISPLAY",
+            "xclip" | "xsel" => "DISPLAY",
+            _ => "",
+        };
+        if !display.is_empty() && env::var_os(display).is_none() {
+            continue;
+        }
+        let Ok(mut child) = process::Command::new(tool)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        if child.wait().is_ok_and(|status| status.success()) {
+            return Ok(tool);
+        }
+    }
+    Err(msg::get(Msg::NoClipboardTool).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_dangerous_command_needs_yes_in_full() {
+        for answer in ["y", "Y", " y\n", "", "n", "sure"] {
+            assert!(!should_run(true, true, true, Some(answer)), "{answer:?}");
+        }
+        assert!(should_run(true, true, true, Some("yes\n")));
+        assert!(should_run(true, true, true, Some("YES")));
+    }
+
+    #[test]
+    fn a_safe_command_runs_on_y_or_yes() {
+        assert!(should_run(false, true, true, Some("y\n")));
+        assert!(should_run(false, true, true, Some("Yes")));
+        assert!(!should_run(false, true, true, Some("\n")));
+        assert!(!should_run(false, true, true, Some("no")));
+    }
+
+    #[test]
+    fn no_answer_or_no_terminal_means_no() {
+        for dangerous in [false, true] {
+            assert!(!should_run(dangerous, true, true, None));
+            assert!(!should_run(dangerous, true, false, Some("yes")));
+        }
+    }
+
+    #[test]
+    fn nothing_runs_without_execute() {
+        for dangerous in [false, true] {
+            assert!(!should_run(dangerous, false, true, Some("yes")));
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::OnceLock;
+use tokio::sync::watch;
+
+/// Resolves with the signal number once SIGTERM or SIGHUP is received.
+#[cfg(unix)]
+pub async fn shutdown_signal() -> i32 {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (Ok(mut term), Ok(mut hup)) = (
+        signal(SignalKind::terminate()),
+        signal(SignalKind::hangup()),
+    ) else {
+        return std::future::pending().await;
+    };
+    tokio::select! {
+        _ = term.recv() => 15,
+        _ = hup.recv() => 1,
+    }
+}
+
+/// Resolves once the console window is closed or Windows is shutting down, with the number of
+/// the closest Unix signal (SIGHUP or SIGTERM) so exit codes match.
+#[cfg(windows)]
+pub async fn shutdown_signal() -> i32 {
+    use tokio::signal::windows::{ctrl_close, ctrl_logoff, ctrl_shutdown};
+
+    let (Ok(mut close), Ok(mut logoff), Ok(mut shutdown)) =
+        (ctrl_close(), ctrl_logoff(), ctrl_shutdown())
+    else {
+        return std::future::pending().await;
+    };
+    tokio::select! {
+        _ = close.recv() => 1,
+        _ = logoff.recv() => 1,
+        _ = shutdown.recv() => 15,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub async fn shutdown_signal() -> i32 {
+    std::future::pending().await
+}
+
+/// Resolves on Ctrl+C. Once this has been awaited, Ctrl+C no longer kills the program by itself.
+pub async fn interrupt() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+// Counts the times the terminal has been resized, watched from the first ResizeWatch on.
+static RESIZES: OnceLock<watch::Sender<u64>> = OnceLock::new();
+
+/// Notices the terminal being resized: SIGWINCH on Unix, a change in the console's width
+/// elsewhere. Needs the runtime the first time one is made.
+pub struct ResizeWatch(watch::Receiver<u64>);
+
+impl ResizeWatch {
+    pub fn new() -> Self {
+        let sender = RESIZES.get_or_init(|| {
+            let (sender, _) = watch::channel(0);
+            tokio::spawn(count_resizes(sender.clone()));
+            sender
+        });
+        ResizeWatch(sender.subscribe())
+    }
+
+    /// Whether the terminal has been resized since this was last asked.
+    pub fn take(&mut self) -> bool {
+        let resized = self.0.has_changed().unwrap_or(false);
+        self.0.borrow_and_update();
+        resized
+    }
+}
+
+#[cfg(unix)]
+async fn count_resizes(sender: watch::Sender<u64>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Ok(mut resizes) = signal(SignalKind::window_change()) else {
+        return;
+    };
+    while resizes.recv().await.is_some() {
+        sender.send_modify(|n| *n += 1);
+    }
+}
+
+// There's no signal for it, so the width is checked a few times a second.
+#[cfg(not(unix))]
+async fn count_resizes(sender: watch::Sender<u64>) {
+    let mut width = crate::doctor::width();
+    loop {
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        let now = crate::doctor::width();
+        if now != width {
+            width = now;
+            sender.send_modify(|n| *n += 1);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn notices_sigwinch() {
+        let mut watch = ResizeWatch::new();
+        // The handler is installed by the task, so give it a moment to start.
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!watch.take());
+        unsafe { libc::raise(libc::SIGWINCH) };
+        let noticed = async {
+            while !watch.take() {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), noticed)
+            .await
+            .unwrap();
+        assert!(!watch.take());
+    }
+}
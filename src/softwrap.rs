@@ -0,0 +1,304 @@
+//! Answers wrapped at word boundaries as they stream, with `word_wrap` on. Terminals break lines
+//! at the last column, often partway through a word; here a word that would run past the edge is
+//! moved to the next line instead, even if part of it was already shown. Only the display is
+//! wrapped: the context, cache and files get the text as the model wrote it.
+//!
+//! The width is checked again whenever the terminal is resized, so lines shown after a resize fit
+//! the new width. Fenced code blocks aren't wrapped, since a break there would change the code.
+
+use std::io::{self, Write};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{doctor, signals::ResizeWatch};
+
+// Where tabs stop, as most terminals have them.
+const TAB_WIDTH: usize = 8;
+
+pub struct SoftWrap<W: Write> {
+    inner: W,
+    width: usize,
+    // Set when the width should follow the terminal's.
+    resizes: Option<ResizeWatch>,
+    column: usize,
+    // The word the cursor is in, already shown, moved to the next line if it won't fit.
+    word: String,
+    word_width: usize,
+    // Spaces after the word, not shown until the next word is known to fit after them.
+    spaces: String,
+    spaces_width: usize,
+    // The start of the current line, enough to tell whether it opens or closes a code fence.
+    line_start: String,
+    in_fence: bool,
+    // Inside an escape sequence, which takes no room.
+    escape: Escape,
+    // The start of a character a write ended partway through.
+    partial: Vec<u8>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    Started,
+    Csi,
+}
+
+impl<W: Write> SoftWrap<W> {
+    /// Wraps at `width` columns, or passes everything through as it is when there's no width.
+    pub fn new(inner: W, width: Option<usize>) -> Self {
+        SoftWrap {
+            inner,
+            width: width.unwrap_or(0),
+            resizes: None,
+            column: 0,
+            word: String::new(),
+            word_width: 0,
+            spaces: String::new(),
+            spaces_width: 0,
+            line_start: String::new(),
+            in_fence: false,
+            escape: Escape::None,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Wraps at the terminal's width, following it when the terminal is resized.
+    pub fn terminal(inner: W) -> Self {
+        let mut wrap = SoftWrap::new(inner, doctor::width().map(usize::from));
+        wrap.resizes = Some(ResizeWatch::new());
+        wrap
+    }
+
+    /// Changes the width from the next write on. The current line is left as it is.
+    pub fn set_width(&mut self, width: usize) {
+        self.width = width;
+    }
+
+    fn wrap(&mut self, text: &str) -> io::Result<()> {
+        for grapheme in text.graphemes(true) {
+            if self.escape != Escape::None || grapheme == "\x1b" {
+                self.write_spaces()?;
+                self.escape(grapheme);
+                self.inner.write_all(grapheme.as_bytes())?;
+                continue;
+            }
+            match grapheme {
+                "\n" | "\r\n" => {
+                    if self.line_start.starts_with("```") {
+                        self.in_fence = !self.in_fence;
+                    }
+                    self.write_spaces()?;
+                    self.new_line();
+                    self.inner.write_all(grapheme.as_bytes())?;
+                }
+                _ if self.in_fence || self.width == 0 => {
+                    self.write_spaces()?;
+                    self.advance(grapheme);
+                    self.inner.write_all(grapheme.as_bytes())?;
+                }
+                " " | "\t" => {
+                    let column = self.column + self.spaces_width;
+                    let next = match grapheme {
+                        "\t" => (column / TAB_WIDTH + 1) * TAB_WIDTH,
+                        _ => column + 1,
+                    };
+                    self.spaces.push_str(grapheme);
+                    self.spaces_width += next - column;
+                    self.word.clear();
+                    self.word_width = 0;
+                }
+                g => {
+                    let width = g.width();
+                    if self.column + self.spaces_width + width <= self.width {
+                        self.write_spaces()?;
+                    } else if !self.spaces.is_empty() {
+                        // The line break takes the place of the spaces.
+                        self.spaces.clear();
+                        self.spaces_width = 0;
+                        self.new_line();
+                        self.inner.write_all(b"\n")?;
+                    } else {
+                        self.break_word(width)?;
+                    }
+                    self.advance(g);
+                    self.word.push_str(g);
+                    self.word_width += width;
+                    self.inner.write_all(g.as_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Spaces are held back until what follows them is known to fit on the line.
+    fn write_spaces(&mut self) -> io::Result<()> {
+        if !self.spaces.is_empty() {
+            let spaces = std::mem::take(&mut self.spaces);
+            self.advance(&spaces);
+            self.spaces_width = 0;
+            self.inner.write_all(spaces.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Starts a new line partway through a word, before something `width` columns wide.
+    fn break_word(&mut self, width: usize) -> io::Result<()> {
+        let start = self.column - self.word_width;
+        if !self.word.is_empty() && start > 0 && self.word_width + width <= self.width {
+            // The part of the word already shown is cleared and written again on the next line.
+            write!(self.inner, "\x1b[{}G\x1b[K\n{}", start + 1, self.word)?;
+            self.column = self.word_width;
+            self.line_start = self.word.clone();
+        } else {
+            // A word longer than a whole line is broken where it reaches the edge.
+            self.inner.write_all(b"\n")?;
+            self.new_line();
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self, text: &str) {
+        for grapheme in text.graphemes(true) {
+            self.column = match grapheme {
+                "\t" => (self.column / TAB_WIDTH + 1) * TAB_WIDTH,
+                g => self.column + g.width(),
+            };
+            if self.line_start.len() < 3 {
+                self.line_start.push_str(grapheme);
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+        self.word.clear();
+        self.word_width = 0;
+        self.line_start.clear();
+    }
+
+    // Follows an escape sequence along, so only what's drawn counts towards the width. Colours
+    // are CSI sequences, ended by a byte from @ to ~; anything else is a single character.
+    fn escape(&mut self, grapheme: &str) {
+        self.escape = match (self.escape, grapheme) {
+            (Escape::None, _) => Escape::Started,
+            (Escape::Started, "[") => Escape::Csi,
+            (Escape::Csi, g) if !g.bytes().all(|b| (0x40..=0x7e).contains(&b)) => Escape::Csi,
+            _ => Escape::None,
+        };
+        // A word can't be written again with the escape in the middle of it.
+        self.word.clear();
+        self.word_width = 0;
+    }
+}
+
+impl<W: Write> Write for SoftWrap<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(ref mut resizes) = self.resizes {
+            if resizes.take() {
+                if let Some(width) = doctor::width() {
+                    self.set_width(usize::from(width));
+                }
+            }
+        }
+        self.partial.extend_from_slice(buf);
+        let complete = match std::str::from_utf8(&self.partial) {
+            Ok(_) => self.partial.len(),
+            // Only an incomplete character at the end is waited for; invalid bytes are shown as
+            // they are.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.partial.len(),
+        };
+        let bytes: Vec<u8> = self.partial.drain(..complete).collect();
+        self.wrap(&String::from_utf8_lossy(&bytes))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrapped(width: usize, chunks: &[&str]) -> String {
+        let mut wrap = SoftWrap::new(Vec::new(), Some(width));
+        for chunk in chunks {
+            wrap.write_all(chunk.as_bytes()).unwrap();
+        }
+        String::from_utf8(wrap.inner).unwrap()
+    }
+
+    #[test]
+    fn breaks_at_spaces() {
+        assert_eq!(
+            wrapped(10, &["the quick brown fox"]),
+            "the quick\nbrown fox"
+        );
+    }
+
+    #[test]
+    fn moves_a_word_split_across_chunks() {
+        assert_eq!(
+            wrapped(8, &["one qui", "ckly"]),
+            "one quic\x1b[5G\x1b[K\nquickly"
+        );
+    }
+
+    #[test]
+    fn breaks_a_word_longer_than_the_line() {
+        assert_eq!(wrapped(4, &["abcdefgh"]), "abcd\nefgh");
+    }
+
+    #[test]
+    fn leaves_code_blocks_alone() {
+        let text = "```\nlet x = 1;\n```\nsome words";
+        assert_eq!(wrapped(5, &[text]), "```\nlet x = 1;\n```\nsome\nwords");
+    }
+
+    #[test]
+    fn escapes_take_no_room() {
+        assert_eq!(
+            wrapped(5, &["\x1b[1mab\x1b[0m cd ef"]),
+            "\x1b[1mab\x1b[0m cd\nef"
+        );
+    }
+
+    #[test]
+    fn follows_a_width_change_mid_stream() {
+        let mut wrap = SoftWrap::new(Vec::new(), Some(20));
+        wrap.write_all(b"aaaa bbbb cccc dddd").unwrap();
+        // The line is already past the new width, so it ends at the next space.
+        wrap.set_width(10);
+        wrap.write_all(b" eeee ffff gggg").unwrap();
+        wrap.set_width(30);
+        wrap.write_all(b" hhhh iiii jjjj kkkk").unwrap();
+        assert_eq!(
+            String::from_utf8(wrap.inner).unwrap(),
+            "aaaa bbbb cccc dddd\neeee ffff\ngggg hhhh iiii jjjj kkkk"
+        );
+    }
+
+    #[test]
+    fn a_character_split_between_writes_is_kept_whole() {
+        let text = "naïve 日本 👍🏽";
+        let mut wrap = SoftWrap::new(Vec::new(), Some(80));
+        for byte in text.as_bytes() {
+            wrap.write_all(std::slice::from_ref(byte)).unwrap();
+        }
+        assert_eq!(String::from_utf8(wrap.inner).unwrap(), text);
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        assert_eq!(wrapped(6, &["日本 語の"]), "日本\n語の");
+        assert_eq!(wrapped(5, &["日本語"]), "日本\n語");
+    }
+
+    #[test]
+    fn passes_through_without_a_width() {
+        let text = "a line much longer than any terminal would be wide";
+        assert_eq!(wrapped(0, &[text]), text);
+    }
+}
@@ -0,0 +1,289 @@
+//! `:file`: puts a file in the context for the next message. Each copy remembers where it came
+//! from, so staging a file again, say after editing it, replaces the copy already there instead
+//! of adding another, as `restage_files` says. An unchanged file isn't staged twice.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    chat,
+    error::{AppError, Result},
+    input,
+    msg::{self, Msg},
+    sensitive, style,
+    tokens::count_tokens,
+    usage::fmt_thousands,
+    AppState, Message,
+};
+
+/// What staging a file that's already in the context does.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Restage {
+    /// The new copy takes the old one's place.
+    #[default]
+    Replace,
+    KeepBoth,
+    Ask,
+}
+
+/// Where a staged message's file came from.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StagedFile {
+    /// Absolute, so the same file staged from elsewhere is still the same file.
+    pub path: PathBuf,
+    /// Of the contents, to tell an unchanged file from an edited one.
+    pub hash: String,
+}
+
+/// `:file <path>`, with a `!` in front of a path that looks like it holds secrets.
+pub struct Stage {
+    pub path: String,
+}
+
+/// Stages the file, or replaces the copy of it already staged.
+pub async fn run(stage: Stage, state: &mut AppState) -> Result<()> {
+    let (path, force) = sensitive::parse_forced(&stage.path);
+    let absolute = env::current_dir()?.join(path);
+    let absolute = absolute.canonicalize().unwrap_or(absolute);
+    if let Some(ref project) = state.project {
+        let listed = project.files.iter().any(|file| {
+            let (file, _) = sensitive::parse_forced(file.to_str().unwrap_or_default());
+            let file = project.root.join(file);
+            file.canonicalize().unwrap_or(file) == absolute
+        });
+        if listed {
+            return Err(AppError::Command(msg::fmt(
+                Msg::StageProjectFile,
+                &[("path", &path)],
+            )));
+        }
+    }
+    let text = sensitive::read(&absolute, &state.config.sensitive_files, force)?;
+    let staged = StagedFile {
+        path: absolute,
+        hash: hash(&text),
+    };
+    let content = format!("{path}:\n```\n{}\n```", text.trim_end());
+    put(state, path, staged, content).await
+}
+
+/// Stages `content`, called `path` in what's printed, or replaces the copy of the same file
+/// already staged, the way `:file` does.
+pub async fn put(
+    state: &mut AppState,
+    path: &str,
+    staged: StagedFile,
+    content: String,
+) -> Result<()> {
+    let tokens = count_tokens(&state.model, &content);
+    let notice = match existing(&state.context, &staged) {
+        Some((_, true)) => {
+            println!("{}", msg::fmt(Msg::StageUnchanged, &[("path", &path)]));
+            return Ok(());
+        }
+        Some((i, false)) => {
+            let replace = match state.config.restage_files {
+                Restage::Replace => true,
+                Restage::KeepBoth => false,
+                Restage::Ask => {
+                    let question = msg::fmt(Msg::StageAskReplace, &[("path", &path)]);
+                    match input::ask(state, &question).await?.as_deref() {
+                        Some("k" | "K") => false,
+                        Some("c" | "C") => return Ok(()),
+                        _ => true,
+                    }
+                }
+            };
+            match place(
+                &state.model,
+                &mut state.context,
+                replace.then_some(i),
+                content,
+                staged,
+            ) {
+                Some(old) => msg::fmt(
+                    Msg::StageUpdated,
+                    &[
+                        ("path", &path),
+                        ("old", &fmt_thousands(old as u64)),
+                        ("tokens", &fmt_thousands(tokens as u64)),
+                    ],
+                ),
+                None => msg::fmt(
+                    Msg::StageAddedAgain,
+                    &[("path", &path), ("tokens", &fmt_thousands(tokens as u64))],
+                ),
+            }
+        }
+        None => {
+            place(&state.model, &mut state.context, None, content, staged);
+            msg::fmt(
+                Msg::StageAdded,
+                &[("path", &path), ("tokens", &fmt_thousands(tokens as u64))],
+            )
+        }
+    };
+    writeln!(
+        chat::status_writer(state.stdout_tty),
+        "{}",
+        style::dim(&notice, state.color)
+    )?;
+    Ok(())
+}
+
+/// The last copy of the file in `context`, and whether it's the same as `staged`.
+fn existing(context: &[Message], staged: &StagedFile) -> Option<(usize, bool)> {
+    let i = context.iter().rposition(|m| is_from(m, &staged.path))?;
+    let same = context[i]
+        .file
+        .as_ref()
+        .is_some_and(|f| f.hash == staged.hash);
+    Some((i, same))
+}
+
+/// Puts a copy of a file in `context`, in place of the one at `replacing` or else at the end.
+/// Returns the tokens of the copy it replaced, counted for `model`.
+fn place(
+    model: &str,
+    context: &mut Vec<Message>,
+    replacing: Option<usize>,
+    content: String,
+    staged: StagedFile,
+) -> Option<u32> {
+    let Some(i) = replacing else {
+        context.push(Message {
+            file: Some(staged),
+            ..Message::user(content)
+        });
+        return None;
+    };
+    let old = count_tokens(model, &context[i].content);
+    let msg = &mut context[i];
+    msg.content = content;
+    msg.file = Some(staged);
+    msg.timestamp = Some(chrono::Utc::now());
+    Some(old)
+}
+
+/// The files staged since the last answer, at the end of `context`.
+pub fn pending(context: &[Message]) -> &[Message] {
+    let start = context
+        .iter()
+        .rposition(|m| m.file.is_none())
+        .map_or(0, |i| i + 1);
+    &context[start..]
+}
+
+/// The files staged since the last answer, taken off the end of `context`. They're for the next
+/// message, so they stay when it starts a new conversation.
+pub fn take_pending(context: &mut Vec<Message>) -> Vec<Message> {
+    let start = context.len() - pending(context).len();
+    context.split_off(start)
+}
+
+pub fn hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Whether `msg` is a copy of the file at `path`.
+fn is_from(msg: &Message, path: &Path) -> bool {
+    msg.file.as_ref().is_some_and(|f| f.path == path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "gpt-4o";
+
+    fn copy(path: &str, text: &str) -> (String, StagedFile) {
+        let staged = StagedFile {
+            path: PathBuf::from(path),
+            hash: hash(text),
+        };
+        (format!("{path}:\n```\n{text}\n```"), staged)
+    }
+
+    /// Stages `text` as `path` the way `:file` does with `restage_files` set to replace.
+    fn stage(context: &mut Vec<Message>, path: &str, text: &str) {
+        let (content, staged) = copy(path, text);
+        match existing(context, &staged) {
+            Some((_, true)) => {}
+            Some((i, false)) => {
+                place(MODEL, context, Some(i), content, staged);
+            }
+            None => {
+                place(MODEL, context, None, content, staged);
+            }
+        }
+    }
+
+    #[test]
+    fn staging_a_file_again_replaces_its_copy() {
+        let mut context = vec![Message::user("hi".to_string())];
+        stage(&mut context, "/p/Cargo.toml", "[package]");
+        stage(&mut context, "/p/main.rs", "fn main() {}");
+        stage(&mut context, "/p/Cargo.toml", "[package]\nname = \"gpt\"");
+        assert_eq!(context.len(), 3);
+        assert_eq!(
+            context[1].content,
+            "/p/Cargo.toml:\n```\n[package]\nname = \"gpt\"\n```"
+        );
+        assert_eq!(
+            context[2].file.as_ref().unwrap().path,
+            Path::new("/p/main.rs")
+        );
+    }
+
+    #[test]
+    fn an_unchanged_file_is_left_alone() {
+        let mut context = Vec::new();
+        stage(&mut context, "/p/Cargo.toml", "[package]");
+        let (_, staged) = copy("/p/Cargo.toml", "[package]");
+        assert_eq!(existing(&context, &staged), Some((0, true)));
+        stage(&mut context, "/p/Cargo.toml", "[package]");
+        assert_eq!(context.len(), 1);
+    }
+
+    #[test]
+    fn keeping_both_adds_a_second_copy() {
+        let mut context = Vec::new();
+        stage(&mut context, "/p/Cargo.toml", "[package]");
+        let (content, staged) = copy("/p/Cargo.toml", "[workspace]");
+        assert_eq!(existing(&context, &staged), Some((0, false)));
+        assert_eq!(place(MODEL, &mut context, None, content, staged), None);
+        assert_eq!(context.len(), 2);
+        // The newer copy is the one a later :file replaces.
+        let (_, staged) = copy("/p/Cargo.toml", "[dependencies]");
+        assert_eq!(existing(&context, &staged), Some((1, false)));
+    }
+
+    #[test]
+    fn sending_takes_only_what_was_staged_since_the_last_answer() {
+        let mut context = Vec::new();
+        stage(&mut context, "/p/old.rs", "old");
+        context.push(Message::user("question".to_string()));
+        context.push(Message::assistant("answer".to_string()));
+        stage(&mut context, "/p/a.rs", "a");
+        stage(&mut context, "/p/b.rs", "b");
+        assert_eq!(pending(&context).len(), 2);
+        let taken = take_pending(&mut context);
+        let paths: Vec<&Path> = taken
+            .iter()
+            .map(|m| m.file.as_ref().unwrap().path.as_path())
+            .collect();
+        assert_eq!(paths, [Path::new("/p/a.rs"), Path::new("/p/b.rs")]);
+        assert_eq!(context.len(), 3);
+        assert!(pending(&context).is_empty());
+        assert!(take_pending(&mut context).is_empty());
+    }
+}
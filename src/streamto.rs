@@ -0,0 +1,123 @@
+//! `:stream-to`: the next answer is written to a file as it streams, so `tail -f` or an editor
+//! that reloads files shows it growing. Unlike `:tee`'s copy, which only appears once the answer
+//! is complete, every delta goes straight to the file. A comment at the end, in the file's own
+//! syntax, says which model wrote it and what it cost, or that it was cut off.
+
+use chrono::Local;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Where the next answer goes, from `:stream-to`.
+#[derive(Clone, Debug)]
+pub struct StreamTarget {
+    pub path: PathBuf,
+    /// Only written to the file, not shown.
+    pub quiet: bool,
+}
+
+/// The file an answer is streaming into. One dropped before [`StreamFile::finish`], by an error
+/// or Ctrl+C, is marked as incomplete.
+pub struct StreamFile {
+    path: PathBuf,
+    file: File,
+    /// Whether what was written so far ends a line, so the comment goes on one of its own.
+    line_ended: bool,
+    done: bool,
+}
+
+impl StreamFile {
+    pub fn begin(target: &StreamTarget) -> io::Result<Self> {
+        Ok(StreamFile {
+            path: target.path.clone(),
+            file: File::create(&target.path)?,
+            line_ended: true,
+            done: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a delta, straight through to the file.
+    pub fn write(&mut self, text: &str) -> io::Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.file.write_all(text.as_bytes())?;
+        self.file.flush()?;
+        self.line_ended = text.ends_with('\n');
+        Ok(())
+    }
+
+    /// Ends the file with `about`, e.g. the model and its tokens, as a comment.
+    pub fn finish(mut self, about: &str) -> io::Result<()> {
+        self.done = true;
+        let stamp = Local::now().format("%Y-%m-%d %H:%M");
+        match comment(&self.path, &format!("gpt: {about}, {stamp}")) {
+            Some(line) => self.end_with(&line),
+            // No comment can go in, e.g. JSON, so what's there is only the answer.
+            None => self.end_with(""),
+        }
+    }
+
+    /// Marks the file as cut off by `why`.
+    pub fn interrupt(mut self, why: &str) -> io::Result<()> {
+        self.done = true;
+        self.end_with(&incomplete(&self.path, why))
+    }
+
+    fn end_with(&mut self, line: &str) -> io::Result<()> {
+        let mut text = String::new();
+        if !self.line_ended {
+            text.push('\n');
+        }
+        if !line.is_empty() {
+            text.push_str(&format!("\n{line}\n"));
+        }
+        self.file.write_all(text.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+impl Drop for StreamFile {
+    fn drop(&mut self) {
+        if !self.done {
+            let line = incomplete(&self.path, "cancelled");
+            let _ = self.end_with(&line);
+        }
+    }
+}
+
+fn incomplete(path: &Path, why: &str) -> String {
+    let text = format!("INCOMPLETE: gpt's answer stopped here ({why}).");
+    comment(path, &text).unwrap_or(text)
+}
+
+/// `text` as a comment in the language the file's extension suggests. `None` for formats without
+/// comments.
+fn comment(path: &Path, text: &str) -> Option<String> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let comment = match ext.as_str() {
+        "json" => return None,
+        "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "cs" | "java" | "kt" | "kts"
+        | "scala" | "swift" | "go" | "js" | "mjs" | "cjs" | "ts" | "tsx" | "jsx" | "dart"
+        | "zig" | "php" | "proto" | "groovy" | "jsonc" => format!("// {text}"),
+        "css" | "scss" | "less" => format!("/* {text} */"),
+        "html" | "htm" | "xml" | "svg" | "md" | "markdown" | "vue" => format!("<!-- {text} -->"),
+        "sql" | "lua" | "hs" | "elm" | "ada" => format!("-- {text}"),
+        "lisp" | "el" | "clj" | "cljs" | "scm" | "asm" | "ini" => format!("; {text}"),
+        "tex" | "erl" => format!("% {text}"),
+        "vim" => format!("\" {text}"),
+        "bat" | "cmd" => format!("REM {text}"),
+        // Shell, Python, Ruby, TOML, YAML, Makefiles and plain text.
+        _ => format!("# {text}"),
+    };
+    Some(comment)
+}
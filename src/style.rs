@@ -0,0 +1,123 @@
+// Minimal ANSI styling. Everything takes `enabled` so output degrades to plain text when piped or with NO_COLOR.
+
+use serde::Deserialize;
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    OnceLock,
+};
+
+/// Colour presets for the prompt and output, set with `theme` in the config or `:theme`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// No colours or styling at all.
+    Plain,
+}
+
+impl Theme {
+    pub fn parse(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::Dark),
+            "light" => Some(Theme::Light),
+            "plain" => Some(Theme::Plain),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::Plain => "plain",
+        }
+    }
+}
+
+// The theme is global, like the terminal it's for, so callers only need to know whether colour is on.
+static THEME: AtomicU8 = AtomicU8::new(Theme::Dark as u8);
+
+pub fn set_theme(theme: Theme) {
+    THEME.store(theme as u8, Ordering::Relaxed);
+}
+
+pub fn theme() -> Theme {
+    match THEME.load(Ordering::Relaxed) {
+        1 => Theme::Light,
+        2 => Theme::Plain,
+        _ => Theme::Dark,
+    }
+}
+
+pub fn color_enabled(stdout_tty: bool) -> bool {
+    stdout_tty && std::env::var_os("NO_COLOR").is_none() && ansi_supported()
+}
+
+/// Whether the terminal understands escape codes. Windows consoles only do once asked, so the
+/// first call turns that on for stdout and stderr.
+pub fn ansi_supported() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(enable_vt_processing)
+}
+
+#[cfg(windows)]
+fn enable_vt_processing() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
+    };
+
+    let mut enabled = true;
+    for id in [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+        // SAFETY: the handle comes straight from GetStdHandle and the mode is a plain out-parameter.
+        unsafe {
+            let handle = GetStdHandle(id);
+            let mut mode = 0;
+            // Not a console (e.g. redirected), so there's nothing to enable.
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                continue;
+            }
+            if SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) == 0 {
+                enabled = false;
+            }
+        }
+    }
+    enabled
+}
+
+#[cfg(not(windows))]
+fn enable_vt_processing() -> bool {
+    true
+}
+
+/// Wraps `text` in an SGR escape code like "1;31", unless colour is off or the theme is plain.
+pub fn wrap(text: &str, code: &str, enabled: bool) -> String {
+    if enabled && theme() != Theme::Plain && !code.is_empty() {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn highlight(text: &str, enabled: bool) -> String {
+    wrap(text, "1;31", enabled)
+}
+
+pub fn dim(text: &str, enabled: bool) -> String {
+    // Faint text all but disappears on a light background, so light uses grey instead.
+    let code = match theme() {
+        Theme::Light => "90",
+        _ => "2",
+    };
+    wrap(text, code, enabled)
+}
+
+/// The theme's colour for things worth picking out, like the model in the prompt.
+pub fn accent_code() -> &'static str {
+    match theme() {
+        Theme::Light => "1;34",
+        _ => "1;36",
+    }
+}
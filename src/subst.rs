@@ -0,0 +1,175 @@
+use std::{env, process::Command};
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    safe::{self, Feature},
+};
+
+/// The most a single `$(command)` may add to a prompt.
+const MAX_OUTPUT: usize = 4000;
+
+/// Expands `${NAME}` to the environment variable and `$(command)` to the command's output.
+///
+/// Only used when substitution is switched on, since it runs whatever commands the text contains.
+/// A missing variable or a failing command is an error, so nothing half-expanded gets sent.
+pub fn expand(text: &str) -> Result<String> {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(dollar) = rest.find('$') {
+        res.push_str(&rest[..dollar]);
+        rest = &rest[dollar..];
+        if let Some(after) = rest.strip_prefix("${") {
+            let close = after
+                .find('}')
+                .ok_or_else(|| AppError::Command(msg::get(Msg::UnclosedVariable).to_string()))?;
+            let name = &after[..close];
+            let value = env::var(name).map_err(|_| {
+                AppError::Command(msg::fmt(Msg::VariableNotSet, &[("name", &name)]))
+            })?;
+            res.push_str(&value);
+            rest = &after[close + 1..];
+        } else if let Some(after) = rest.strip_prefix("$(") {
+            let close = closing_paren(after)
+                .ok_or_else(|| AppError::Command(msg::get(Msg::UnclosedCommand).to_string()))?;
+            res.push_str(&run(&after[..close])?);
+            rest = &after[close + 1..];
+        } else {
+            res.push('$');
+            rest = &rest[1..];
+        }
+    }
+    res.push_str(rest);
+    Ok(res)
+}
+
+/// Finds the `)` that closes a `$(`, skipping nested parentheses and anything quoted.
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Some(i),
+            (None, ')') => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn run(command: &str) -> Result<String> {
+    safe::check(Feature::Substitution)?;
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::SubstFailedToRun,
+            &[("command", &command), ("error", &e)],
+        ))
+    })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::Command(msg::fmt(
+            Msg::SubstFailed,
+            &[
+                ("command", &command),
+                ("status", &output.status),
+                ("error", &stderr.trim()),
+            ],
+        )));
+    }
+
+    let mut out = String::from_utf8_lossy(&output.stdout).into_owned();
+    if out.len() > MAX_OUTPUT {
+        let mut end = MAX_OUTPUT;
+        while !out.is_char_boundary(end) {
+            end -= 1;
+        }
+        out.truncate(end);
+        out.push_str("\n[output truncated]");
+    }
+    Ok(out.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn substitution_is_off_by_default() {
+        assert!(!Config::default().substitution);
+    }
+
+    #[test]
+    fn quoted_parentheses_do_not_close_the_command() {
+        assert_eq!(closing_paren("echo ')' \")\") rest"), Some(12));
+        assert_eq!(closing_paren(r#"echo "it's (fine)") x"#), Some(18));
+        assert_eq!(closing_paren(r"echo \)) x"), Some(7));
+    }
+
+    #[test]
+    fn nested_commands_close_at_the_outer_paren() {
+        let inner = "echo $(echo (a) \"b)\")";
+        assert_eq!(closing_paren(&format!("{inner}) x")), Some(inner.len()));
+    }
+
+    #[test]
+    fn an_unclosed_command_is_an_error() {
+        assert_eq!(closing_paren("echo 'unclosed )"), None);
+        assert!(expand("see $(echo 'x)").is_err());
+        assert!(expand("see ${HOME").is_err());
+    }
+
+    #[test]
+    fn a_dollar_on_its_own_is_kept() {
+        assert_eq!(expand("costs $5, or $ 6").unwrap(), "costs $5, or $ 6");
+    }
+
+    #[test]
+    fn environment_variables_are_expanded() {
+        let home = env::var("HOME").unwrap_or_default();
+        if home.is_empty() {
+            return;
+        }
+        assert_eq!(expand("in ${HOME}/x").unwrap(), format!("in {home}/x"));
+        assert!(expand("${GPT_CLI_TEST_SURELY_UNSET}").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn commands_with_nested_quotes_are_run() {
+        assert_eq!(
+            expand(r#"branch: $(echo "it's $(echo 'a (b)')"), ok"#).unwrap(),
+            "branch: it's a (b), ok"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_failing_command_stops_the_send() {
+        let err = expand("$(echo oops >&2; exit 3)").unwrap_err().to_string();
+        assert!(err.contains("oops"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn long_output_is_cut_short() {
+        let out = expand("$(head -c 10000 /dev/zero | tr '\\0' x)").unwrap();
+        assert!(out.ends_with("[output truncated]"));
+        assert!(out.len() < MAX_OUTPUT + 40);
+    }
+}
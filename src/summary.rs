@@ -0,0 +1,255 @@
+//! `:summary`: what went on in the conversation, worked out from what each message records, so it
+//! costs nothing and works the same for a loaded session. With `--llm`, the provider's cheap model
+//! also recaps what was discussed.
+
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionStreamOptions, CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use chrono::{Local, TimeDelta};
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+    provider, style,
+    tokens::count_tokens,
+    usage::{fmt_thousands, Purpose},
+    AppState, Message,
+};
+
+// Enough of the conversation to tell what it was about, at the cheap model's prices.
+const MAX_TRANSCRIPT_CHARS: usize = 12000;
+const RECAP_MAX_TOKENS: u32 = 200;
+
+/// The answers one model wrote.
+#[derive(Default)]
+struct ModelTotals {
+    answers: u32,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    cost: f64,
+}
+
+/// Prints the statistics of the conversation in the context.
+pub fn print(state: &AppState) {
+    let context = &state.context;
+    let answers: Vec<&Message> = context.iter().filter(|m| !m.is_user()).collect();
+    if answers.is_empty() {
+        println!("{}", msg::get(Msg::SummaryNothing));
+        return;
+    }
+    let currency = &state.config.currency;
+
+    let mut models: BTreeMap<&str, ModelTotals> = BTreeMap::new();
+    // Sessions saved before prompt tokens were recorded only have the completion.
+    let mut without_prompt = 0;
+    for msg in &answers {
+        let totals = models
+            .entry(msg.model.as_deref().unwrap_or("unknown"))
+            .or_default();
+        totals.answers += 1;
+        totals.completion_tokens += msg.tokens.unwrap_or(0) as u64;
+        totals.cost += msg.cost_usd.unwrap_or(0.0);
+        match msg.prompt_tokens {
+            Some(tokens) => totals.prompt_tokens += tokens as u64,
+            None => without_prompt += 1,
+        }
+    }
+    let sum = |f: fn(&ModelTotals) -> u64| models.values().map(f).sum::<u64>();
+    let cost: f64 = models.values().map(|t| t.cost).sum();
+
+    println!(
+        "{}",
+        msg::fmt(Msg::SummaryExchanges, &[("count", &answers.len())])
+    );
+    let unknown = match without_prompt {
+        0 => String::new(),
+        1 => msg::get(Msg::SummaryOneWithoutPrompt).to_string(),
+        n => msg::fmt(Msg::SummaryWithoutPrompts, &[("count", &n)]),
+    };
+    println!(
+        "{}",
+        msg::fmt(
+            Msg::SummaryTokens,
+            &[
+                ("prompt", &fmt_thousands(sum(|t| t.prompt_tokens))),
+                ("unknown", &unknown),
+                ("completion", &fmt_thousands(sum(|t| t.completion_tokens))),
+            ],
+        )
+    );
+    println!(
+        "{}",
+        msg::fmt(Msg::SummaryCost, &[("cost", &currency.format(cost))])
+    );
+    if models.len() > 1 {
+        println!("{}", msg::get(Msg::SummaryModels));
+        let width = models.keys().map(|m| m.len()).max().unwrap_or(0);
+        for (model, totals) in &models {
+            let answers = match totals.answers {
+                1 => msg::get(Msg::SummaryOneAnswer).to_string(),
+                n => msg::fmt(Msg::SummaryAnswers, &[("count", &n)]),
+            };
+            let line = msg::fmt(
+                Msg::SummaryModelLine,
+                &[
+                    ("answers", &answers),
+                    ("prompt", &fmt_thousands(totals.prompt_tokens)),
+                    ("completion", &fmt_thousands(totals.completion_tokens)),
+                    ("cost", &currency.format(totals.cost)),
+                ],
+            );
+            println!("  {model:<width$}  {line}");
+        }
+    } else if let Some(model) = models.keys().next() {
+        println!("{}", msg::fmt(Msg::SummaryModel, &[("model", model)]));
+    }
+
+    let latencies: Vec<(usize, u64)> = context
+        .iter()
+        .enumerate()
+        .filter_map(|(i, m)| m.duration_ms.map(|ms| (i, ms)))
+        .collect();
+    if let Some(&(slowest, max)) = latencies.iter().max_by_key(|&&(_, ms)| ms) {
+        let average = latencies.iter().map(|&(_, ms)| ms).sum::<u64>() / latencies.len() as u64;
+        let average = format!("{:.1}", average as f64 / 1000.);
+        let longest = format!("{:.1}", max as f64 / 1000.);
+        println!(
+            "{}",
+            msg::fmt(
+                Msg::SummaryLatency,
+                &[
+                    ("average", &average),
+                    ("longest", &longest),
+                    ("index", &slowest),
+                ],
+            )
+        );
+    }
+
+    let mut longest = None;
+    for (i, msg) in context.iter().enumerate() {
+        let tokens = match msg.tokens {
+            Some(tokens) => tokens,
+            None => count_tokens(msg.model.as_deref().unwrap_or(&state.model), msg.content()),
+        };
+        if longest.is_none_or(|(_, most)| tokens > most) {
+            longest = Some((i, tokens));
+        }
+    }
+    if let Some((i, tokens)) = longest {
+        let msg = &context[i];
+        println!(
+            "{}",
+            msg::fmt(
+                Msg::SummaryLongest,
+                &[
+                    ("index", &i),
+                    ("role", &msg.role()),
+                    ("tokens", &fmt_thousands(tokens as u64)),
+                    ("preview", &style::dim(&preview(msg.content()), state.color)),
+                ],
+            )
+        );
+    }
+
+    let times: Vec<_> = context.iter().filter_map(|m| m.timestamp).collect();
+    if let (Some(first), Some(last)) = (times.iter().min(), times.iter().max()) {
+        println!(
+            "{}",
+            msg::fmt(
+                Msg::SummaryDuration,
+                &[
+                    ("duration", &duration(*last - *first)),
+                    (
+                        "first",
+                        &first.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                    ),
+                    ("last", &last.with_timezone(&Local).format("%H:%M")),
+                ],
+            )
+        );
+    }
+}
+
+/// Asks the provider's cheap model for a recap of the conversation, and says what it cost.
+pub async fn recap(client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<()> {
+    if state.context.is_empty() {
+        return Ok(());
+    }
+    let model = state.config.provider.title_model();
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .max_completion_tokens(RECAP_MAX_TOKENS)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content("Recap this conversation in exactly 3 short bullet points, each starting with \"- \", covering what was discussed and anything decided. Reply with the bullets only.")
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(transcript(&state.context))
+                .build()?
+                .into(),
+        ])
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()?;
+    let (text, usage) = provider::complete(client, request, &state.config, Purpose::Recap).await?;
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(AppError::Command(msg::get(Msg::RecapEmpty).to_string()));
+    }
+    println!("\n{}\n{text}", msg::get(Msg::RecapHeader));
+    let price = match usage.price(model) {
+        Some(price) => state.config.currency.format(price),
+        None => msg::get(Msg::PriceUnknown).to_string(),
+    };
+    let notice = msg::fmt(
+        Msg::RecapNotice,
+        &[
+            ("model", &model),
+            ("tokens", &fmt_thousands(usage.total() as u64)),
+            ("price", &price),
+        ],
+    );
+    println!("{}", style::dim(&notice, state.color));
+    Ok(())
+}
+
+/// The conversation as plain text, keeping its end when it's too long: that's where it got to.
+fn transcript(context: &[Message]) -> String {
+    let mut res = String::new();
+    for msg in context.iter().rev() {
+        let line = format!("{}: {}\n", msg.role(), msg.content());
+        if !res.is_empty() && res.len() + line.len() > MAX_TRANSCRIPT_CHARS {
+            break;
+        }
+        res.insert_str(0, &line);
+    }
+    res.chars().take(MAX_TRANSCRIPT_CHARS).collect()
+}
+
+fn preview(text: &str) -> String {
+    let first = text.lines().find(|l| !l.trim().is_empty());
+    let first = first.unwrap_or_default().trim();
+    match first.chars().count() > 60 {
+        true => format!("{}…", first.chars().take(59).collect::<String>()),
+        false => first.to_string(),
+    }
+}
+
+/// A span of time like "1h 23m", "4m 10s" or "12s".
+fn duration(span: TimeDelta) -> String {
+    let secs = span.num_seconds().max(0);
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
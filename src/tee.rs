@@ -0,0 +1,38 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::fsutil::AtomicFile;
+
+/// Where raw responses are copied to, set by `--output` or `:tee`.
+#[derive(Clone, Debug)]
+pub struct TeeTarget {
+    pub path: PathBuf,
+    /// `:tee` collects every response in one file, `--output` holds just the one answer.
+    pub append: bool,
+}
+
+/// Copy of a single response being streamed, only made visible once the response is complete.
+pub struct Tee {
+    file: AtomicFile,
+}
+
+impl Tee {
+    pub fn begin(target: &TeeTarget) -> io::Result<Self> {
+        let mut file = AtomicFile::create(&target.path, target.append)?;
+        if target.append && !file.is_empty() {
+            file.write_all(b"\n\n")?;
+        }
+        Ok(Tee { file })
+    }
+
+    pub fn write(&mut self, text: &str) -> io::Result<()> {
+        self.file.write_all(text.as_bytes())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.write_all(b"\n")?;
+        self.file.commit()
+    }
+}
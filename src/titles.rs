@@ -0,0 +1,117 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionStreamOptions, CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use tokio::sync::oneshot;
+
+use crate::{config::Config, inflight, provider, usage::Purpose, AppState, Message};
+
+// Only the start of a long conversation is needed to name it.
+const MAX_TRANSCRIPT_CHARS: usize = 4000;
+
+/// Starts generating a title in the background once the session has had enough exchanges.
+pub fn maybe_start(client: &Client<OpenAIConfig>, state: &mut AppState) {
+    let after = state.config.title_after_exchanges;
+    if after == 0
+        || state.exchanges < after
+        || state.title.is_some()
+        || state.pending_title.is_some()
+    {
+        return;
+    }
+
+    let transcript = transcript(&state.context);
+    let model = state.config.provider.title_model();
+    let client = client.clone();
+    let config = state.config.clone();
+    let (tx, rx) = oneshot::channel();
+    state.pending_title = Some(rx);
+    inflight::spawn(async move {
+        // Failures just mean the session stays untitled.
+        if let Some(title) = generate(&client, &config, model, transcript).await {
+            let _ = tx.send(title);
+        }
+    });
+}
+
+/// Picks up a finished background title, if there is one.
+pub fn collect(state: &mut AppState) {
+    let Some(ref mut rx) = state.pending_title else {
+        return;
+    };
+    match rx.try_recv() {
+        Ok(title) => {
+            state.pending_title = None;
+            // A title set with :title in the meantime wins.
+            if state.title.is_none() {
+                state.title = Some(title);
+            }
+        }
+        Err(oneshot::error::TryRecvError::Empty) => {}
+        Err(oneshot::error::TryRecvError::Closed) => state.pending_title = None,
+    }
+}
+
+fn transcript(context: &[Message]) -> String {
+    let mut res = String::new();
+    for msg in context {
+        res.push_str(&format!("{}: {}\n", msg.role(), msg.content()));
+        if res.len() > MAX_TRANSCRIPT_CHARS {
+            break;
+        }
+    }
+    res.chars().take(MAX_TRANSCRIPT_CHARS).collect()
+}
+
+async fn generate(
+    client: &Client<OpenAIConfig>,
+    config: &Config,
+    model: &str,
+    transcript: String,
+) -> Option<String> {
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(model)
+        .max_completion_tokens(20u32)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content("Write a 5-8 word title for this conversation. Reply with the title only, without quotes or punctuation at the end.")
+                .build()
+                .ok()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(transcript)
+                .build()
+                .ok()?
+                .into(),
+        ])
+        .stream_options(ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+        .build()
+        .ok()?;
+    let (title, _) = provider::complete(client, request, config, Purpose::Title)
+        .await
+        .ok()?;
+    let title = title.trim().trim_matches('"').trim().to_string();
+    if title.is_empty() {
+        return None;
+    }
+    Some(title)
+}
+
+/// Turns a title into something usable as a file name, e.g. "Rust Lifetimes: An Intro" -> "rust-lifetimes-an-intro".
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
@@ -0,0 +1,518 @@
+//! Counting tokens locally, for the footer when the API doesn't say, the prompt, and deciding what
+//! fits. Each count is for a model, with the tokenizer the registry gives it. If the tokenizer
+//! can't be loaded, counts fall back to a rough estimate of a token per 4 characters rather than
+//! failing: a wrong count is better than a session that can't go on.
+
+use async_openai::types::CreateChatCompletionRequest;
+use serde_json::Value;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Write,
+    hash::{Hash, Hasher},
+    sync::OnceLock,
+};
+use tiktoken_rs::{cl100k_base, o200k_base, CoreBPE};
+
+use crate::{
+    models::{self, Encoding},
+    msg::{self, Msg},
+    usage::fmt_thousands,
+    Message,
+};
+
+// How many of the biggest messages of the context a breakdown lists.
+const LARGEST: usize = 5;
+
+// Every message costs a few tokens on top of its content for the role and separators,
+// and every reply is primed with a few more.
+const TOKENS_PER_MESSAGE: u32 = 4;
+pub const TOKENS_PER_REPLY: u32 = 3;
+
+// What the fallback takes a token to be.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// How far, as a percentage, an estimate can be from the prompt tokens billed before it's worth a
+/// bug report.
+pub const DRIFT_WARNING: f64 = 10.0;
+
+// Below this many tokens, the few of overhead an estimate misses are already a big percentage.
+const DRIFT_MIN_TOKENS: u64 = 100;
+
+/// Where estimates that are consistently off are worth reporting.
+pub const ISSUES: &str = "https://github.com/JMoogs/gpt-cli/issues";
+
+// One for each Encoding, in its order.
+static BPE: [OnceLock<Option<CoreBPE>>; 2] = [OnceLock::new(), OnceLock::new()];
+
+/// The tokenizer for `model`, loaded the first time it's needed. A failure is warned about then,
+/// and only then.
+fn bpe(model: &str) -> Option<&'static CoreBPE> {
+    let encoding = models::encoding(model);
+    BPE[encoding as usize]
+        .get_or_init(|| match load(encoding) {
+            Ok(bpe) => Some(bpe),
+            Err(e) => {
+                eprintln!("{}", msg::fmt(Msg::TokenizerMissing, &[("error", &e)]));
+                None
+            }
+        })
+        .as_ref()
+}
+
+fn load(encoding: Encoding) -> Result<CoreBPE, String> {
+    // Set to see what happens without the tokenizer.
+    if std::env::var_os("GPT_CLI_NO_TOKENIZER").is_some() {
+        return Err(msg::get(Msg::TokenizerDisabled).to_string());
+    }
+    // Some builds of its data panic rather than fail, which mustn't take gpt down with them.
+    let load = match encoding {
+        Encoding::Cl100k => cl100k_base,
+        Encoding::O200k => o200k_base,
+    };
+    match std::panic::catch_unwind(load) {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err("it panicked".to_string()),
+    }
+}
+
+/// Whether counts for `model` are the rough estimate, because its tokenizer couldn't be loaded.
+pub fn approximate(model: &str) -> bool {
+    bpe(model).is_none()
+}
+
+/// The token ids of `text`, for comparing requests rather than just sizing them. Without the
+/// tokenizer, each run of 4 characters stands in for a token.
+pub fn encode(model: &str, text: &str) -> Vec<usize> {
+    match bpe(model) {
+        Some(bpe) => bpe.encode_with_special_tokens(text),
+        None => text
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(CHARS_PER_TOKEN)
+            .map(|chunk| {
+                let mut hasher = DefaultHasher::new();
+                chunk.hash(&mut hasher);
+                hasher.finish() as usize
+            })
+            .collect(),
+    }
+}
+
+pub fn count_tokens(model: &str, text: &str) -> u32 {
+    match bpe(model) {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len() as u32,
+        None => text.chars().count().div_ceil(CHARS_PER_TOKEN) as u32,
+    }
+}
+
+/// Estimates the prompt tokens of a request to `model` made from `context`, including per-message
+/// overhead.
+pub fn estimate_prompt_tokens(model: &str, context: &[Message]) -> u32 {
+    let sources = Sources {
+        model,
+        system: None,
+        files: &[],
+        seeded: &[],
+        context,
+        new_message: None,
+    };
+    breakdown(&sources).total()
+}
+
+/// Estimates the prompt tokens of a request already built, from the text of its messages. Tool
+/// definitions and images aren't counted.
+pub fn estimate_request_tokens(request: &CreateChatCompletionRequest) -> u32 {
+    let messages = serde_json::to_value(&request.messages).unwrap_or_default();
+    let mut total = TOKENS_PER_REPLY;
+    for message in messages.as_array().into_iter().flatten() {
+        total += TOKENS_PER_MESSAGE;
+        match &message["content"] {
+            Value::String(text) => total += count_tokens(&request.model, text),
+            Value::Array(parts) => {
+                for text in parts.iter().filter_map(|p| p["text"].as_str()) {
+                    total += count_tokens(&request.model, text);
+                }
+            }
+            _ => {}
+        }
+    }
+    total
+}
+
+/// How a request's [`estimate_prompt_tokens`] compares with the prompt tokens the API billed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Drift {
+    pub estimate: u32,
+    pub actual: u32,
+}
+
+impl Drift {
+    /// How much more was billed than estimated, as a percentage of the estimate.
+    pub fn percent(&self) -> f64 {
+        percent(self.estimate as u64, self.actual as u64)
+    }
+
+    pub fn worth_reporting(&self) -> bool {
+        drift_worth_reporting(self.estimate as u64, self.actual as u64)
+    }
+
+    /// Like "est 1,240 / actual 1,263, +1.9%".
+    pub fn describe(&self) -> String {
+        msg::fmt(
+            Msg::DriftDescribe,
+            &[
+                ("estimate", &fmt_thousands(self.estimate as u64)),
+                ("actual", &fmt_thousands(self.actual as u64)),
+                ("percent", &format!("{:+.1}", self.percent())),
+            ],
+        )
+    }
+}
+
+/// Whether estimating `estimate` tokens where `actual` were billed is off by enough to be a bug.
+pub fn drift_worth_reporting(estimate: u64, actual: u64) -> bool {
+    estimate >= DRIFT_MIN_TOKENS && percent(estimate, actual).abs() > DRIFT_WARNING
+}
+
+/// How much more `actual` is than `estimate`, as a percentage of `estimate`.
+pub fn percent(estimate: u64, actual: u64) -> f64 {
+    match estimate {
+        0 => 0.,
+        _ => (actual as f64 - estimate as f64) / estimate as f64 * 100.,
+    }
+}
+
+/// What a request is made from, for [`breakdown`].
+pub struct Sources<'a> {
+    /// The model the request is for, whose tokenizer counts it.
+    pub model: &'a str,
+    pub system: Option<&'a str>,
+    /// The files sent with the system prompt, each as it's sent.
+    pub files: &'a [String],
+    /// The messages of the seed the conversation started from, sent before the context.
+    pub seeded: &'a [Message],
+    /// The messages carried over, oldest first, with the files staged with `:file` among them.
+    pub context: &'a [Message],
+    pub new_message: Option<&'a Message>,
+}
+
+/// One of the biggest messages of the context.
+#[derive(Debug)]
+pub struct Largest {
+    /// Where it is in the context, as `:show` numbers it.
+    pub index: usize,
+    pub role: &'static str,
+    pub tokens: u32,
+    pub preview: String,
+}
+
+/// Where the prompt tokens of a request come from.
+#[derive(Debug, Default)]
+pub struct Breakdown {
+    pub system: u32,
+    /// The project's files and those staged with `:file`.
+    pub files: u32,
+    pub seeded: u32,
+    /// The other messages carried over, without their wrappers.
+    pub context: u32,
+    /// The prompt prefixes and suffixes of every message.
+    pub wrapper: u32,
+    /// The message being sent, without its wrapper.
+    pub new_message: u32,
+    /// What each message and the reply cost on top of their content.
+    pub overhead: u32,
+    /// The biggest messages carried over, biggest first. Staged files aren't among them.
+    pub largest: Vec<Largest>,
+}
+
+impl Breakdown {
+    pub fn total(&self) -> u32 {
+        self.system
+            + self.files
+            + self.seeded
+            + self.context
+            + self.wrapper
+            + self.new_message
+            + self.overhead
+    }
+}
+
+/// Counts the tokens of each part of a request. The system prompt and files are sent as one
+/// message.
+pub fn breakdown(sources: &Sources) -> Breakdown {
+    let count = |text: &str| count_tokens(sources.model, text);
+    let mut out = Breakdown {
+        overhead: TOKENS_PER_REPLY,
+        ..Breakdown::default()
+    };
+    if let Some(system) = sources.system {
+        out.system = count(system);
+    }
+    for file in sources.files {
+        out.files += count(file);
+    }
+    if sources.system.is_some() || !sources.files.is_empty() {
+        out.overhead += TOKENS_PER_MESSAGE;
+    }
+    for msg in sources.seeded {
+        out.seeded += count(&msg.content);
+        out.overhead += TOKENS_PER_MESSAGE;
+    }
+    // A message's wrapper is whatever sending it adds to what was typed.
+    let split = |msg: &Message| {
+        let content = count(&msg.content);
+        let sent = match msg.wrapper {
+            Some(_) => count(&msg.sent()),
+            None => content,
+        };
+        (content, sent.saturating_sub(content))
+    };
+    let mut sizes = Vec::with_capacity(sources.context.len());
+    for (index, msg) in sources.context.iter().enumerate() {
+        out.overhead += TOKENS_PER_MESSAGE;
+        if msg.file.is_some() {
+            out.files += count(&msg.content);
+            continue;
+        }
+        let (content, wrapper) = split(msg);
+        out.context += content;
+        out.wrapper += wrapper;
+        sizes.push((index, content + wrapper));
+    }
+    if let Some(msg) = sources.new_message {
+        let (content, wrapper) = split(msg);
+        out.new_message = content;
+        out.wrapper += wrapper;
+        out.overhead += TOKENS_PER_MESSAGE;
+    }
+    // Stable, so of two the same size the older comes first.
+    sizes.sort_by_key(|&(_, tokens)| std::cmp::Reverse(tokens));
+    out.largest = sizes
+        .into_iter()
+        .take(LARGEST)
+        .map(|(index, tokens)| {
+            let msg = &sources.context[index];
+            Largest {
+                index,
+                role: msg.role(),
+                tokens,
+                preview: preview(&msg.content),
+            }
+        })
+        .collect();
+    out
+}
+
+fn preview(text: &str) -> String {
+    let first = text
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or_default();
+    let first = first.trim();
+    match first.char_indices().nth(40) {
+        Some((i, _)) => format!("{}…", &first[..i]),
+        None => first.to_string(),
+    }
+}
+
+/// The breakdown as a table of tokens and shares of the total, with the biggest messages of the
+/// context under it.
+pub fn format_breakdown(breakdown: &Breakdown) -> String {
+    let total = breakdown.total();
+    let share = |n: u32| match total {
+        0 => 0.0,
+        total => n as f64 * 100.0 / total as f64,
+    };
+    let rows = [
+        (Msg::BreakdownSystem, breakdown.system),
+        (Msg::BreakdownFiles, breakdown.files),
+        (Msg::BreakdownSeeded, breakdown.seeded),
+        (Msg::BreakdownContext, breakdown.context),
+        (Msg::BreakdownWrapper, breakdown.wrapper),
+        (Msg::BreakdownNewMessage, breakdown.new_message),
+        (Msg::BreakdownOverhead, breakdown.overhead),
+    ];
+    let mut out = String::new();
+    for (row, tokens) in rows {
+        let _ = writeln!(
+            out,
+            "{:<22} {:>9} {:>6.1}%",
+            msg::get(row),
+            fmt_thousands(tokens as u64),
+            share(tokens)
+        );
+        if row == Msg::BreakdownContext {
+            for msg in &breakdown.largest {
+                let label = format!("  [{}] {}", msg.index, msg.role);
+                let _ = writeln!(
+                    out,
+                    "{label:<22} {:>9} {:>6.1}%  {}",
+                    fmt_thousands(msg.tokens as u64),
+                    share(msg.tokens),
+                    msg.preview
+                );
+            }
+        }
+    }
+    let _ = write!(
+        out,
+        "{:<22} {:>9} {:>6.1}%",
+        msg::get(Msg::BreakdownTotal),
+        fmt_thousands(total as u64),
+        100.0
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stage::StagedFile;
+
+    const MODEL: &str = "gpt-4o";
+
+    fn staged(text: &str) -> Message {
+        Message {
+            file: Some(StagedFile {
+                path: "/src/main.rs".into(),
+                hash: "0".to_string(),
+            }),
+            ..Message::user(text.to_string())
+        }
+    }
+
+    #[test]
+    fn each_part_is_counted_where_it_comes_from() {
+        let files = ["fn main() {}".to_string()];
+        let context = [
+            staged("fn helper() -> u32 { 4 }"),
+            Message::user("why does it fail".to_string()),
+            Message::assistant("because of the borrow checker, mostly".to_string()),
+        ];
+        let new_message = Message::user("and now?".to_string());
+        let out = breakdown(&Sources {
+            model: MODEL,
+            system: Some("Be brief."),
+            files: &files,
+            seeded: &[],
+            context: &context,
+            new_message: Some(&new_message),
+        });
+        assert_eq!(out.system, count_tokens(MODEL, "Be brief."));
+        assert_eq!(
+            out.files,
+            count_tokens(MODEL, "fn main() {}") + count_tokens(MODEL, "fn helper() -> u32 { 4 }")
+        );
+        assert_eq!(
+            out.context,
+            count_tokens(MODEL, "why does it fail")
+                + count_tokens(MODEL, "because of the borrow checker, mostly")
+        );
+        assert_eq!(out.new_message, count_tokens(MODEL, "and now?"));
+        // The system message, three carried over, the new one and the reply.
+        assert_eq!(out.overhead, 5 * TOKENS_PER_MESSAGE + TOKENS_PER_REPLY);
+        let largest: Vec<usize> = out.largest.iter().map(|m| m.index).collect();
+        assert_eq!(largest, [2, 1]);
+        assert_eq!(out.largest[0].role, "assistant");
+    }
+
+    #[test]
+    fn an_empty_request_is_only_the_reply() {
+        let out = breakdown(&Sources {
+            model: MODEL,
+            system: None,
+            files: &[],
+            seeded: &[],
+            context: &[],
+            new_message: None,
+        });
+        assert_eq!(out.total(), TOKENS_PER_REPLY);
+    }
+
+    #[test]
+    fn previews_are_the_first_line_cut_short() {
+        assert_eq!(preview("\n  hello\nworld"), "hello");
+        let long = "é".repeat(50);
+        assert_eq!(preview(&long), format!("{}…", "é".repeat(40)));
+    }
+
+    #[test]
+    fn the_table_is_aligned_with_shares_of_the_total() {
+        let breakdown = Breakdown {
+            system: 250,
+            files: 1500,
+            context: 200,
+            new_message: 40,
+            overhead: 10,
+            largest: vec![Largest {
+                index: 3,
+                role: "assistant",
+                tokens: 120,
+                preview: "Here's why".to_string(),
+            }],
+            ..Breakdown::default()
+        };
+        let table = format_breakdown(&breakdown);
+        let expected = [
+            "System prompt                250   12.5%",
+            "Files                      1,500   75.0%",
+            "Seed messages                  0    0.0%",
+            "Context                      200   10.0%",
+            "  [3] assistant              120    6.0%  Here's why",
+            "Prefix and suffix              0    0.0%",
+            "New message                   40    2.0%",
+            "Per-message overhead          10    0.5%",
+            "Total                      2,000  100.0%",
+        ];
+        assert_eq!(table.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn an_empty_table_has_no_shares() {
+        let table = format_breakdown(&Breakdown::default());
+        assert!(table
+            .lines()
+            .all(|l| l.contains(" 0.0%") || l.starts_with("Total")));
+        assert!(table.ends_with("Total                          0  100.0%"));
+    }
+
+    #[test]
+    fn drift_is_described_against_the_estimate() {
+        let drift = Drift {
+            estimate: 1240,
+            actual: 1263,
+        };
+        assert_eq!(drift.describe(), "est 1,240 / actual 1,263, +1.9%");
+        let drift = Drift {
+            estimate: 1000,
+            actual: 900,
+        };
+        assert_eq!(drift.describe(), "est 1,000 / actual 900, -10.0%");
+        // Nothing to compare with.
+        assert_eq!(percent(0, 50), 0.0);
+    }
+
+    #[test]
+    fn only_drift_past_the_warning_on_a_big_enough_prompt_is_reported() {
+        // Over 10% either way.
+        assert!(drift_worth_reporting(1000, 1101));
+        assert!(drift_worth_reporting(1000, 899));
+        assert!(!drift_worth_reporting(1000, 1100));
+        assert!(!drift_worth_reporting(1000, 900));
+        // A few tokens off a short prompt is a large share of it, and says nothing.
+        assert!(drift_worth_reporting(100, 200));
+        assert!(!drift_worth_reporting(99, 200));
+        assert!(!drift_worth_reporting(0, 200));
+    }
+
+    #[test]
+    fn each_model_is_counted_with_its_own_tokenizer() {
+        let text = "Привет! Как дела? Токенизаторы считают этот текст по-разному.";
+        let (gpt_4o, gpt_4) = (count_tokens("gpt-4o", text), count_tokens("gpt-4", text));
+        assert!(gpt_4o < gpt_4, "gpt-4o: {gpt_4o}, gpt-4: {gpt_4}");
+        assert_eq!(
+            count_tokens("ft:gpt-4o-mini-2024-07-18:org::abc123", text),
+            gpt_4o
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use serde_json::{json, Value};
+
+use crate::{
+    config::Config,
+    error::Result,
+    mcp, plugins,
+    safe::{self, Feature},
+};
+
+/// Where a tool runs.
+#[derive(Clone, Debug)]
+pub enum Source {
+    Mcp {
+        server: String,
+        remote_name: String,
+    },
+    /// An executable from the tools directory.
+    Plugin(std::path::PathBuf),
+}
+
+#[derive(Clone, Debug)]
+pub struct Tool {
+    /// The name the model sees, made unique across sources.
+    pub name: String,
+    pub source: Source,
+    pub description: Option<String>,
+    pub schema: Value,
+    pub enabled: bool,
+}
+
+impl Tool {
+    /// Where the tool comes from, for :tools.
+    pub fn origin(&self) -> String {
+        match self.source {
+            Source::Mcp { ref server, .. } => server.clone(),
+            Source::Plugin(_) => "plugin".to_string(),
+        }
+    }
+}
+
+/// Every tool the model can call, from MCP servers and plugins.
+#[derive(Debug, Default)]
+pub struct Tools {
+    pub servers: Vec<mcp::Server>,
+    pub tools: Vec<Tool>,
+    timeout_secs: u64,
+}
+
+impl Tools {
+    pub async fn load(config: &Config) -> Tools {
+        let mut tools = Tools {
+            timeout_secs: config.tool_timeout_secs,
+            ..Tools::default()
+        };
+        let configured = !config.allow_tools.is_empty() || !config.mcp_servers.is_empty();
+        if configured {
+            if let Err(e) = safe::check(Feature::Tools) {
+                eprintln!("{e}");
+                return tools;
+            }
+        }
+        plugins::load(&config.allow_tools, &mut tools).await;
+        mcp::connect(&config.mcp_servers, &mut tools).await;
+        tools
+    }
+
+    /// Registers a tool, renaming it if another source already took its name.
+    pub fn add(&mut self, mut tool: Tool) {
+        let mut name = exposed_name(&tool.name);
+        if self.tools.iter().any(|t| t.name == name) {
+            name = exposed_name(&format!("{}_{}", tool.origin(), tool.name));
+        }
+        tool.name = name;
+        self.tools.push(tool);
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Tool> {
+        self.tools.iter().filter(|t| t.enabled)
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Tool> {
+        self.tools.iter_mut().find(|t| t.name == name)
+    }
+
+    /// Calls a tool. The result is text for the model, even when the tool failed.
+    pub async fn call(&mut self, name: &str, arguments: &str) -> Result<String> {
+        let Some(tool) = self.enabled().find(|t| t.name == name).cloned() else {
+            return Ok(format!("Error: there is no tool called '{name}'."));
+        };
+        let arguments: Value = match arguments.trim() {
+            "" => json!({}),
+            text => match serde_json::from_str(text) {
+                Ok(arguments) => arguments,
+                Err(e) => return Ok(format!("Error: the arguments aren't valid JSON: {e}")),
+            },
+        };
+        match tool.source {
+            Source::Mcp {
+                ref server,
+                ref remote_name,
+            } => mcp::call(&mut self.servers, server, remote_name, arguments).await,
+            Source::Plugin(ref path) => plugins::call(path, &arguments, self.timeout_secs).await,
+        }
+    }
+}
+
+/// Function names may only use letters, digits, '_' and '-', up to 64 of them.
+fn exposed_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .take(64)
+        .collect()
+}
@@ -0,0 +1,174 @@
+use async_openai::{config::OpenAIConfig, Client};
+use serde::Deserialize;
+
+use crate::{
+    chat,
+    error::{AppError, Result},
+    msg::{self, Msg},
+    signals, AppState, Message,
+};
+
+// Language names worth recognising as the first word of `:tr`. Anything else needs a code.
+const LANGUAGES: &[&str] = &[
+    "arabic",
+    "chinese",
+    "czech",
+    "danish",
+    "dutch",
+    "english",
+    "finnish",
+    "french",
+    "german",
+    "greek",
+    "hebrew",
+    "hindi",
+    "hungarian",
+    "indonesian",
+    "italian",
+    "japanese",
+    "korean",
+    "norwegian",
+    "polish",
+    "portuguese",
+    "romanian",
+    "russian",
+    "spanish",
+    "swedish",
+    "thai",
+    "turkish",
+    "ukrainian",
+    "vietnamese",
+    "welsh",
+];
+
+/// The `[translate]` table of the config.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TranslateConfig {
+    /// Translations go to this model, whatever the conversation uses.
+    pub model: String,
+    /// The language `--swap` translates back into before any source language has been given.
+    pub source: String,
+}
+
+impl Default for TranslateConfig {
+    fn default() -> Self {
+        TranslateConfig {
+            model: "gpt-4o-mini".to_string(),
+            source: "english".to_string(),
+        }
+    }
+}
+
+/// The languages of the last translation, reused by a bare `:tr <text>`.
+#[derive(Clone, Debug, Default)]
+pub struct Pair {
+    /// `None` until given as `:tr en:de`; the model works it out otherwise.
+    pub source: Option<String>,
+    pub target: Option<String>,
+    /// The last translation, which a bare `:tr --swap` translates back.
+    pub last: Option<String>,
+}
+
+/// What `:translate` was asked.
+#[derive(Debug)]
+pub struct Translate {
+    pub text: String,
+    pub source: Option<String>,
+    pub target: String,
+}
+
+/// Whether `word` names a language: a two or three letter code like "de", or a name like "german".
+fn is_language(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    ((2..=3).contains(&word.len()) && word.chars().all(|c| c.is_ascii_lowercase()))
+        || LANGUAGES.contains(&lower.as_str())
+}
+
+/// Parses `[--swap] [lang | src:lang] [text]`, updating the remembered pair.
+pub fn parse(args: &str, pair: &mut Pair, config: &TranslateConfig) -> Result<Translate> {
+    let mut rest = args.trim();
+    let swap = match rest.strip_prefix("--swap") {
+        Some(after) if after.is_empty() || after.starts_with(char::is_whitespace) => {
+            rest = after.trim_start();
+            true
+        }
+        _ => false,
+    };
+    let (first, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let languages = match first.split_once(':') {
+        Some((source, target)) if is_language(source) && is_language(target) => {
+            Some((Some(source.to_string()), target.to_string()))
+        }
+        None if !swap && is_language(first) => Some((pair.source.clone(), first.to_string())),
+        _ => None,
+    };
+    if let Some((source, target)) = languages {
+        pair.source = source;
+        pair.target = Some(target);
+        rest = after.trim_start();
+    }
+    if swap {
+        let target = pair
+            .target
+            .take()
+            .ok_or_else(|| AppError::Command(msg::get(Msg::TranslateNothingToSwap).to_string()))?;
+        let source = pair.source.take().unwrap_or_else(|| config.source.clone());
+        pair.source = Some(target);
+        pair.target = Some(source);
+    }
+
+    let text = match rest {
+        "" if swap => pair
+            .last
+            .clone()
+            .ok_or_else(|| AppError::Command(msg::get(Msg::TranslateNothingBack).to_string()))?,
+        "" => return Err(AppError::Command(msg::get(Msg::TranslateUsage).to_string())),
+        text => text.to_string(),
+    };
+    let target = pair
+        .target
+        .clone()
+        .ok_or_else(|| AppError::Command(msg::get(Msg::TranslateNoTarget).to_string()))?;
+    Ok(Translate {
+        text,
+        source: pair.source.clone(),
+        target,
+    })
+}
+
+/// Sends the translation to the translation model, leaving the conversation and its model alone.
+pub async fn run(
+    translate: Translate,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    let from = match translate.source {
+        Some(ref source) => format!(" from {source}"),
+        None => String::new(),
+    };
+    let prompt = format!(
+        "Translate the text below{from} into {}. Reply with the translation only: no notes, explanations, alternatives or quotation marks around it. Keep the formatting.\n\n{}",
+        translate.target, translate.text
+    );
+
+    let saved_context = std::mem::replace(&mut state.context, vec![Message::user(prompt)]);
+    let saved_model = std::mem::replace(&mut state.model, state.config.translate.model.clone());
+    let saved_route = std::mem::replace(&mut state.auto_route, false);
+    // A translation has nothing to do with the project, the seed or the preset.
+    let result = tokio::select! {
+        result = chat::stream_with_system(client, state, None) => result,
+        _ = signals::interrupt() => Err(AppError::Cancelled),
+    };
+    if result.is_ok() {
+        state.translation.last = state
+            .context
+            .last()
+            .filter(|m| !m.is_user())
+            .map(|m| m.content.clone());
+    }
+    state.context = saved_context;
+    state.model = saved_model;
+    state.auto_route = saved_route;
+    result
+}
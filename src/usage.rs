@@ -0,0 +1,192 @@
+use async_openai::types::CompletionUsage;
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard, OnceLock},
+};
+
+use crate::models;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Usage {
+    pub prompt: u32,
+    pub cached: u32,
+    pub completion: u32,
+}
+
+impl Usage {
+    pub fn from_api(usage: &CompletionUsage) -> Self {
+        let cached = usage
+            .prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.cached_tokens)
+            .unwrap_or(0);
+        Usage {
+            prompt: usage.prompt_tokens,
+            cached,
+            completion: usage.completion_tokens,
+        }
+    }
+
+    /// Adds another request's usage, for answers that took several requests.
+    pub fn add(&mut self, other: &Usage) {
+        self.prompt += other.prompt;
+        self.cached += other.cached;
+        self.completion += other.completion;
+    }
+
+    pub fn total(&self) -> u32 {
+        self.prompt + self.completion
+    }
+
+    /// Price in USD, billing cached prompt tokens at the model's cached rate. `None` when the
+    /// model's prices aren't known.
+    pub fn price(&self, model: &str) -> Option<f64> {
+        let [inp, cached, out] = models::lookup(model)?.price?;
+        let uncached = self.prompt.saturating_sub(self.cached) as f64;
+        Some(
+            (uncached * inp + self.cached as f64 * cached + self.completion as f64 * out)
+                / 1_000_000.,
+        )
+    }
+
+    /// How much less this cost than it would have without prompt caching.
+    pub fn cache_saving(&self, model: &str) -> f64 {
+        let Some([inp, cached, _]) = models::lookup(model).and_then(|info| info.price) else {
+            return 0.0;
+        };
+        self.cached as f64 * (inp - cached) / 1_000_000.
+    }
+}
+
+/// What a request was for, which decides where `:stats` counts it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Purpose {
+    /// Anything asked for, whether an answer, a summary or an embedding.
+    Answer,
+    Title,
+    Routing,
+    Recap,
+}
+
+static SESSION: OnceLock<Mutex<SessionStats>> = OnceLock::new();
+
+/// What's been used since the REPL started or `:new`, whichever feature made the requests.
+pub fn session() -> MutexGuard<'static, SessionStats> {
+    SESSION
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Counts what a request used towards the session. Every request a provider answers is
+/// counted here, from [`provider::stream`](crate::provider::stream).
+pub fn bill(purpose: Purpose, usage: &Usage, model: &str) {
+    let mut session = session();
+    match purpose {
+        Purpose::Answer => session.record(usage, model),
+        Purpose::Title => session.record_title(usage, model),
+        Purpose::Routing => session.record_routing(usage, model),
+        Purpose::Recap => session.record_recap(usage, model),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SessionStats {
+    pub requests: u32,
+    pub prompt_tokens: u64,
+    pub cached_tokens: u64,
+    pub completion_tokens: u64,
+    /// Only of the requests whose price is known.
+    pub cost: f64,
+    /// Requests to models whose prices aren't known, so they're not in `cost`.
+    pub unpriced: u32,
+    pub cache_saving: f64,
+    // Background requests that name the session, kept apart from the conversation itself.
+    pub title_requests: u32,
+    pub title_cost: f64,
+    // Classification requests made by model routing.
+    pub routing_requests: u32,
+    pub routing_cost: f64,
+    // :summary --llm.
+    pub recap_requests: u32,
+    pub recap_cost: f64,
+    // Requests answered by each endpoint, when there are several.
+    pub endpoints: BTreeMap<String, u32>,
+}
+
+impl SessionStats {
+    pub fn record(&mut self, usage: &Usage, model: &str) {
+        self.requests += 1;
+        self.prompt_tokens += usage.prompt as u64;
+        self.cached_tokens += usage.cached as u64;
+        self.completion_tokens += usage.completion as u64;
+        match usage.price(model) {
+            Some(price) => self.cost += price,
+            None => self.unpriced += 1,
+        }
+        self.cache_saving += usage.cache_saving(model);
+    }
+
+    fn record_title(&mut self, usage: &Usage, model: &str) {
+        self.title_requests += 1;
+        self.title_cost += usage.price(model).unwrap_or(0.0);
+    }
+
+    fn record_routing(&mut self, usage: &Usage, model: &str) {
+        self.routing_requests += 1;
+        self.routing_cost += usage.price(model).unwrap_or(0.0);
+    }
+
+    fn record_recap(&mut self, usage: &Usage, model: &str) {
+        self.recap_requests += 1;
+        self.recap_cost += usage.price(model).unwrap_or(0.0);
+    }
+
+    pub fn record_endpoint(&mut self, base: &str) {
+        *self.endpoints.entry(base.to_string()).or_default() += 1;
+    }
+}
+
+/// Formats a number with thousands separators, e.g. 4812 -> "4,812".
+pub fn fmt_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut res = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i != 0 && (digits.len() - i).is_multiple_of(3) {
+            res.push(',');
+        }
+        res.push(c);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USAGE: Usage = Usage {
+        prompt: 1_000_000,
+        cached: 0,
+        completion: 1_000_000,
+    };
+
+    #[test]
+    fn price_of_a_known_model() {
+        assert_eq!(USAGE.price("gpt-4o-mini"), Some(0.75));
+    }
+
+    #[test]
+    fn price_of_an_unknown_model_is_unknown() {
+        assert_eq!(USAGE.price("no-such-model"), None);
+    }
+
+    #[test]
+    fn unknown_prices_are_kept_out_of_the_cost() {
+        let mut stats = SessionStats::default();
+        stats.record(&USAGE, "gpt-4o-mini");
+        stats.record(&USAGE, "no-such-model");
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.cost, 0.75);
+        assert_eq!(stats.unpriced, 1);
+    }
+}
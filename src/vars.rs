@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{AppError, Result},
+    msg::{self, Msg},
+};
+
+pub type Vars = BTreeMap<String, String>;
+
+pub fn validate_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::Command(msg::fmt(
+            Msg::InvalidVariableName,
+            &[("name", &name)],
+        )))
+    }
+}
+
+/// Replaces every `{{name}}` in `text` with the variable's value. `{{{{` is a literal `{{`.
+///
+/// Undefined variables are left as they were and returned so the caller can warn about them.
+pub fn substitute(text: &str, vars: &Vars) -> (String, Vec<String>) {
+    let mut res = String::with_capacity(text.len());
+    let mut undefined = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("{{") {
+        res.push_str(&rest[..open]);
+        rest = &rest[open..];
+        if let Some(after) = rest.strip_prefix("{{{{") {
+            res.push_str("{{");
+            rest = after;
+            continue;
+        }
+        let inner = &rest[2..];
+        let name = inner.find("}}").map(|close| (&inner[..close], close));
+        match name {
+            Some((name, close)) if validate_name(name.trim()).is_ok() => {
+                let name = name.trim();
+                match vars.get(name) {
+                    Some(value) => res.push_str(value),
+                    None => {
+                        res.push_str(&rest[..close + 4]);
+                        if !undefined.iter().any(|n| n == name) {
+                            undefined.push(name.to_string());
+                        }
+                    }
+                }
+                rest = &inner[close + 2..];
+            }
+            _ => {
+                res.push_str("{{");
+                rest = inner;
+            }
+        }
+    }
+    res.push_str(rest);
+    (res, undefined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vars {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn variables_are_replaced() {
+        let vars = vars(&[("lang", "rust"), ("n", "3")]);
+        let (text, undefined) = substitute("write {{n}} {{ lang }} functions in {{lang}}", &vars);
+        assert_eq!(text, "write 3 rust functions in rust");
+        assert!(undefined.is_empty());
+    }
+
+    #[test]
+    fn four_braces_are_two_literal_ones() {
+        let vars = vars(&[("lang", "rust")]);
+        assert_eq!(substitute("{{{{lang}}", &vars).0, "{{lang}}");
+        assert_eq!(substitute("a {{{{ b {{lang}}", &vars).0, "a {{ b rust");
+        assert!(substitute("{{{{lang}}", &vars).1.is_empty());
+    }
+
+    #[test]
+    fn undefined_variables_are_sent_as_written() {
+        let (text, undefined) = substitute("{{a}} and {{a}} and {{b}}", &Vars::new());
+        assert_eq!(text, "{{a}} and {{a}} and {{b}}");
+        assert_eq!(undefined, ["a", "b"]);
+    }
+
+    #[test]
+    fn braces_that_are_not_a_variable_are_left_alone() {
+        let vars = vars(&[("x", "1")]);
+        for text in ["{{not a name}}", "fn f() {{", "{{x", "}}{{"] {
+            assert_eq!(substitute(text, &vars), (text.to_string(), vec![]));
+        }
+    }
+
+    #[test]
+    fn values_are_not_substituted_again() {
+        let vars = vars(&[("a", "{{b}}"), ("b", "no")]);
+        assert_eq!(substitute("{{a}}", &vars).0, "{{b}}");
+    }
+}
@@ -0,0 +1,283 @@
+//! `gpt view`: saved sessions read without loading them or calling the API. Files aren't changed,
+//! even corrupt ones, which `:load` would move aside.
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    cli::{ViewArgs, ViewFormat},
+    config::Config,
+    error::{AppError, Result},
+    math::MathWriter,
+    msg::{self, Msg},
+    paths,
+    session::{self, Session},
+    style,
+    usage::fmt_thousands,
+    Message,
+};
+
+/// Runs `gpt view`.
+pub fn run(args: &ViewArgs, config: &Config) -> Result<u8> {
+    let Some(ref target) = args.session else {
+        return list(config);
+    };
+    let path = resolve(target);
+    let (mut session, skipped) = session::salvage(&path)?;
+    if skipped > 0 {
+        eprintln!(
+            "{}",
+            msg::fmt(
+                Msg::ViewSkipped,
+                &[("count", &skipped), ("path", &path.display())]
+            )
+        );
+    }
+    for msg in &mut session.messages {
+        if let Some(wrapper) = msg.wrapper.take().filter(|_| args.with_wrapper) {
+            msg.content = wrapper.apply(&msg.content);
+        }
+    }
+    match args.format {
+        Some(ViewFormat::Json) => {
+            let json = serde_json::to_string_pretty(&session).map_err(io::Error::from)?;
+            println!("{json}");
+        }
+        Some(ViewFormat::Md) => print!("{}", markdown(&session)),
+        None => {
+            let tty = io::stdout().is_terminal();
+            let color = style::color_enabled(tty);
+            let mut text = Vec::new();
+            render(&session, config, color, &mut text)?;
+            match tty {
+                true => page(&text)?,
+                false => io::stdout().lock().write_all(&text)?,
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// A path to a session file, or the name of a saved session.
+fn resolve(target: &str) -> PathBuf {
+    let path = PathBuf::from(target);
+    match path.is_file() || target.contains(['/', '\\']) {
+        true => path,
+        false => session::path(target),
+    }
+}
+
+/// The saved sessions as a table: name, title, when the last message was sent, the number of
+/// messages and what the answers cost, as far as the file records.
+fn list(config: &Config) -> Result<u8> {
+    let names = session::list()?;
+    if names.is_empty() {
+        println!(
+            "{}",
+            msg::fmt(
+                Msg::ViewNoSessions,
+                &[("path", &paths::sessions_dir().display())]
+            )
+        );
+        return Ok(0);
+    }
+    let rows: Vec<[String; 5]> = names
+        .iter()
+        .map(|name| match session::salvage(&session::path(name)) {
+            Ok((session, skipped)) => {
+                let last = session.messages.iter().rev().find_map(|m| m.timestamp);
+                let count = match skipped {
+                    0 => session.messages.len().to_string(),
+                    n => format!("{} ({n} bad)", session.messages.len() + n),
+                };
+                [
+                    name.clone(),
+                    session.title.clone().unwrap_or_default(),
+                    last.map(date).unwrap_or_else(|| "-".to_string()),
+                    count,
+                    cost(&session, config),
+                ]
+            }
+            Err(_) => [
+                name.clone(),
+                "(unreadable)".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+            ],
+        })
+        .collect();
+    let width = |i: usize, min: usize| {
+        rows.iter()
+            .map(|r| r[i].chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(min)
+    };
+    let (name_w, title_w) = (width(0, 4), width(1, 5).min(50));
+    println!(
+        "{:<name_w$}  {:<title_w$}  {:<16}  {:>10}  {:>10}",
+        "name", "title", "last message", "messages", "cost"
+    );
+    for row in &rows {
+        let title: String = row[1].chars().take(title_w).collect();
+        println!(
+            "{:<name_w$}  {:<title_w$}  {:<16}  {:>10}  {:>10}",
+            row[0], title, row[2], row[3], row[4]
+        );
+    }
+    Ok(0)
+}
+
+fn date(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// The total of the answers that recorded their cost. Sessions saved before costs were recorded
+/// show `?`, and ones that are partly from then show the known part as a minimum.
+fn cost(session: &Session, config: &Config) -> String {
+    let answers: Vec<&Message> = session.messages.iter().filter(|m| !m.is_user()).collect();
+    let known: Vec<f64> = answers.iter().filter_map(|m| m.cost_usd).collect();
+    match (known.len(), answers.len()) {
+        (0, 0) => config.currency.format(0.0),
+        (0, _) => "?".to_string(),
+        (k, n) if k < n => format!(">={}", config.currency.format(known.iter().sum())),
+        _ => config.currency.format(known.iter().sum()),
+    }
+}
+
+/// The session as `:show` would print each message, with math converted as the REPL would.
+fn render(session: &Session, config: &Config, color: bool, out: &mut Vec<u8>) -> Result<()> {
+    let heading = session.title.as_deref().unwrap_or("(untitled)");
+    writeln!(out, "{}", style::wrap(heading, "1", color))?;
+    writeln!(
+        out,
+        "{}\n",
+        style::dim(
+            &format!(
+                "{} · {} messages · {}",
+                session.model,
+                session.messages.len(),
+                cost(session, config)
+            ),
+            color
+        )
+    )?;
+    for (i, msg) in session.messages.iter().enumerate() {
+        let code = match msg.is_user() {
+            true => "1",
+            false => style::accent_code(),
+        };
+        let details = details(msg);
+        let header = format!("[{i}] {}", msg.role());
+        match details.is_empty() {
+            true => writeln!(out, "{}", style::wrap(&header, code, color))?,
+            false => writeln!(
+                out,
+                "{} {}",
+                style::wrap(&header, code, color),
+                style::dim(&format!("({details})"), color)
+            )?,
+        }
+        let mut math = MathWriter::new(&mut *out, config.math);
+        math.write_all(msg.content().trim_end().as_bytes())?;
+        math.write_all(b"\n")?;
+        math.finish()?;
+        if let Some(ref note) = msg.note {
+            writeln!(out, "{}", style::dim(&format!("note: {note}"), color))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn details(msg: &Message) -> String {
+    let mut details = Vec::new();
+    if let Some(t) = msg.timestamp {
+        details.push(t.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    if let Some(ref model) = msg.model {
+        details.push(model.clone());
+    }
+    if let Some(tokens) = msg.tokens {
+        details.push(msg::fmt(
+            Msg::ViewTokens,
+            &[("tokens", &fmt_thousands(tokens as u64))],
+        ));
+    }
+    details.join(" | ")
+}
+
+/// The session as a Markdown document: the title, then a heading for each message.
+fn markdown(session: &Session) -> String {
+    let mut out = format!(
+        "# {}\n\n",
+        session
+            .title
+            .as_deref()
+            .unwrap_or(msg::get(Msg::ViewUntitled))
+    );
+    for msg in &session.messages {
+        let details = details(msg);
+        match details.is_empty() {
+            true => out.push_str(&format!("## {}\n\n", msg.role())),
+            false => out.push_str(&format!("## {} ({details})\n\n", msg.role())),
+        }
+        out.push_str(msg.content().trim_end());
+        out.push_str("\n\n");
+        if let Some(ref note) = msg.note {
+            out.push_str(&format!("> Note: {note}\n\n"));
+        }
+    }
+    out
+}
+
+/// Shows `text` through `$PAGER`, `less` if that's unset. Printed as it is when there's no pager
+/// to run.
+fn page(text: &[u8]) -> Result<()> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let mut command = match cfg!(windows) {
+        true => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", &pager]);
+            command
+        }
+        false => {
+            let mut command = Command::new("sh");
+            command.args(["-c", &pager]);
+            command
+        }
+    };
+    // Quit if it fits on one screen, keep the colours and leave what was shown on the screen.
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let Ok(mut child) = command.stdin(Stdio::piped()).spawn() else {
+        io::stdout().lock().write_all(text)?;
+        return Ok(());
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe, which isn't an error.
+        match stdin.write_all(text) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        }
+    }
+    child.wait().map_err(|e| {
+        AppError::Command(msg::fmt(
+            Msg::PagerFailed,
+            &[("pager", &pager), ("error", &e)],
+        ))
+    })?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use ::notify::{event::ModifyKind, EventKind, RecursiveMode, Watcher};
+use async_openai::{config::OpenAIConfig, Client};
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    chat::{self, status_writer},
+    error::{AppError, Result},
+    fsutil,
+    msg::{self, Msg},
+    sensitive, signals, style, usage, AppState, Message,
+};
+
+// Editors often write a file in several steps; wait for them to settle before reading it.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub const DEFAULT_MAX_ITERATIONS: u32 = 20;
+
+/// What `:watch` was asked to do.
+#[derive(Debug)]
+pub struct Watch {
+    pub path: PathBuf,
+    /// Send it even if it looks like it holds secrets, as `!` in front of the path asks.
+    pub force: bool,
+    pub prompt: String,
+    /// Stops after this many requests, in case the file is being written continuously.
+    pub max_iterations: u32,
+    /// Stops once watching has cost this much, in the configured currency.
+    pub budget: Option<f64>,
+}
+
+/// Sends the file with the prompt now and after every change, each as a request of its own,
+/// until `q`, Ctrl+C or one of the limits. The context is left as it was.
+pub async fn run(watch: Watch, client: &Client<OpenAIConfig>, state: &mut AppState) -> Result<()> {
+    let saved = std::mem::take(&mut state.context);
+    let result = watch_loop(&watch, client, state).await;
+    state.context = saved;
+    result
+}
+
+async fn watch_loop(
+    watch: &Watch,
+    client: &Client<OpenAIConfig>,
+    state: &mut AppState,
+) -> Result<()> {
+    sensitive::check(&watch.path, &state.config.sensitive_files, watch.force)?;
+    let path = fs::canonicalize(&watch.path).map_err(|e| cant_watch(&watch.path, e))?;
+    // The directory is watched rather than the file, since editors that save by replacing the file would end the watch.
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let mut watcher = ::notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .map_err(|e| cant_watch(&watch.path, e))?;
+    watcher
+        .watch(path.parent().unwrap_or(&path), RecursiveMode::NonRecursive)
+        .map_err(|e| cant_watch(&watch.path, e))?;
+
+    println!(
+        "{}",
+        msg::fmt(Msg::Watching, &[("path", &watch.path.display())])
+    );
+    let start_cost = usage::session().cost;
+    let mut last_contents = None;
+    let mut iterations = 0;
+    loop {
+        if let Some(contents) = read_changed(&path, &mut last_contents, state)? {
+            iterations += 1;
+            let header = format!(
+                "── {} {} ──",
+                chrono::Local::now().format("%H:%M:%S"),
+                watch.path.display()
+            );
+            println!("{}", style::dim(&header, state.color));
+            let message = format!(
+                "{}\n\n{}:\n```\n{contents}\n```",
+                watch.prompt,
+                watch.path.display()
+            );
+            state.context = vec![Message::user(message)];
+            let result = tokio::select! {
+                result = chat::stream_response(client, state) => result,
+                _ = signals::interrupt() => return Ok(()),
+            };
+            // A failed request doesn't end the watch; the next change might go better.
+            if let Err(e) = result {
+                println!("{e}");
+            }
+            if iterations == watch.max_iterations {
+                println!(
+                    "{}",
+                    msg::fmt(Msg::WatchStoppedCount, &[("count", &iterations)])
+                );
+                return Ok(());
+            }
+            let currency = &state.config.currency;
+            let spent = (usage::session().cost - start_cost) * currency.rate;
+            if let Some(budget) = watch.budget.filter(|b| spent >= *b) {
+                println!(
+                    "{}",
+                    msg::fmt(
+                        Msg::WatchStoppedBudget,
+                        &[("budget", &currency.format(budget / currency.rate))]
+                    )
+                );
+                return Ok(());
+            }
+        }
+
+        // Wait for a change to the file, then for it to settle.
+        loop {
+            let Some(ref mut lines) = state.lines else {
+                return Ok(());
+            };
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(Ok(event)) if is_change(&event, &path) => break,
+                    Some(_) => {}
+                    None => return Ok(()),
+                },
+                line = lines.recv() => match line.transpose()? {
+                    Some(line) if matches!(line.trim(), "q" | ":q" | ":quit") => return Ok(()),
+                    Some(_) => println!("{}", msg::get(Msg::WatchHowToStop)),
+                    // EOF
+                    None => return Ok(()),
+                },
+                _ = signals::interrupt() => return Ok(()),
+            }
+        }
+        while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, events.recv()).await {}
+    }
+}
+
+fn cant_watch(path: &Path, e: impl fmt::Display) -> AppError {
+    AppError::Command(msg::fmt(
+        Msg::CantWatch,
+        &[("path", &path.display()), ("error", &e)],
+    ))
+}
+
+fn is_change(event: &::notify::Event, path: &Path) -> bool {
+    // Reading the file is an event too, and so are changes to its metadata.
+    let kind = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && !matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_)));
+    kind && event
+        .paths
+        .iter()
+        .any(|p| p.file_name() == path.file_name())
+}
+
+/// The file's contents if they've changed since the last request. Saving without changes doesn't cost a request.
+fn read_changed(
+    path: &Path,
+    last: &mut Option<String>,
+    state: &AppState,
+) -> Result<Option<String>> {
+    if sensitive::file_is_binary(path).unwrap_or(false) {
+        let notice = msg::fmt(Msg::WatchBinary, &[("path", &path.display())]);
+        writeln!(
+            status_writer(state.stdout_tty),
+            "{}",
+            style::dim(&notice, state.color)
+        )?;
+        return Ok(None);
+    }
+    let contents = match fsutil::read_text(path) {
+        Ok(contents) => contents,
+        // Mid-save, or deleted; either way there's nothing to send until it's back.
+        Err(e) => {
+            let notice = msg::fmt(
+                Msg::WatchCantRead,
+                &[("path", &path.display()), ("error", &e)],
+            );
+            writeln!(
+                status_writer(state.stdout_tty),
+                "{}",
+                style::dim(&notice, state.color)
+            )?;
+            return Ok(None);
+        }
+    };
+    if last.as_ref() == Some(&contents) {
+        return Ok(None);
+    }
+    *last = Some(contents.clone());
+    Ok(Some(contents))
+}
@@ -0,0 +1,99 @@
+use crate::{
+    error::{AppError, Result},
+    input, models,
+    msg::{self, Msg},
+    provider::Provider,
+    settings::MaxTokens,
+    tokens::{estimate_prompt_tokens, TOKENS_PER_REPLY},
+    usage::fmt_thousands,
+    AppState, Message,
+};
+
+/// Why the prompt can't be sent to `model` as it is, if it can't.
+pub fn overflow_message(model: &str, prompt: u32, max_tokens: u32) -> Option<String> {
+    let window = models::lookup(model)?.context_window;
+    (prompt + max_tokens > window).then(|| {
+        msg::fmt(
+            Msg::WindowOverflow,
+            &[
+                ("prompt", &fmt_thousands(prompt as u64)),
+                ("max_tokens", &fmt_thousands(max_tokens as u64)),
+                ("model", &model),
+                ("window", &fmt_thousands(window as u64)),
+            ],
+        )
+    })
+}
+
+/// The cheapest known model that the prompt fits in, among those there's a key for.
+pub fn cheapest_fitting(
+    prompt: u32,
+    max_tokens: MaxTokens,
+    configured: Provider,
+) -> Option<&'static str> {
+    models::all()
+        .filter(|m| prompt + max_tokens.resolve(m.id).0 <= m.context_window)
+        .filter(|m| Provider::for_model(m.id, configured).api_key().is_some())
+        .filter_map(|m| Some((m, m.price?[0])))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(m, _)| m)
+        .map(|m| m.id)
+}
+
+/// Index of the first message to send so that the rest fits the window, dropping the oldest messages.
+/// `overhead` is what goes with them whatever is dropped, like the system prompt.
+pub fn trim_start(
+    context: &[Message],
+    overhead: u32,
+    model: &str,
+    max_tokens: u32,
+) -> Result<usize> {
+    let Some(info) = models::lookup(model) else {
+        return Ok(0);
+    };
+    // Each message is counted once, since contexts can get long with million-token windows.
+    let mut total = estimate_prompt_tokens(model, context) + overhead;
+    for (start, msg) in context.iter().enumerate() {
+        if total + max_tokens <= info.context_window {
+            return Ok(start);
+        }
+        total -= estimate_prompt_tokens(model, std::slice::from_ref(msg)) - TOKENS_PER_REPLY;
+    }
+    Err(AppError::TooLong(msg::fmt(
+        Msg::WindowLastTooLong,
+        &[("model", &model)],
+    )))
+}
+
+/// Makes sure the context fits the window before anything is sent, returning the index of the first
+/// message to send. `overhead` is what's sent besides the context, counted with it. Asks what to do
+/// when it doesn't fit, unless `auto_trim` is set.
+pub async fn fit_context(state: &mut AppState, overhead: u32) -> Result<usize> {
+    let estimate = estimate_prompt_tokens(&state.model, &state.context) + overhead;
+    let Some(message) = overflow_message(&state.model, estimate, state.max_tokens()) else {
+        return Ok(0);
+    };
+    if state.config.auto_trim {
+        return trim_start(&state.context, overhead, &state.model, state.max_tokens());
+    }
+    if !state.interactive {
+        return Err(AppError::TooLong(message));
+    }
+
+    println!("{message}");
+    let cheapest = cheapest_fitting(estimate, state.max_tokens, state.config.provider);
+    let question = match cheapest {
+        Some(model) => msg::fmt(Msg::WindowAskSwitch, &[("model", &model)]),
+        None => msg::get(Msg::WindowAskTrim).to_string(),
+    };
+    let answer = input::ask(state, &question).await?.unwrap_or_default();
+    match (answer.to_lowercase().as_str(), cheapest) {
+        ("s", Some(model)) => {
+            state.model = model.to_string();
+            println!("{}", msg::fmt(Msg::WindowSwapped, &[("model", &model)]));
+            Ok(0)
+        }
+        ("t", _) => trim_start(&state.context, overhead, &state.model, state.max_tokens()),
+        _ => Err(AppError::Command(msg::get(Msg::WindowAborted).to_string())),
+    }
+}
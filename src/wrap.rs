@@ -0,0 +1,63 @@
+//! `prompt_prefix` and `prompt_suffix`: boilerplate put around every message typed when it's sent,
+//! like "Answer in British English." Each message keeps the wrapper it was sent with, so it's sent
+//! the same way again later even after `:wrap-prompt off` or a config change.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    msg::{self, Msg},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Wrapper {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+}
+
+impl Wrapper {
+    /// The configured wrapper, or `None` if there's neither a prefix nor a suffix.
+    pub fn from_config(config: &Config) -> Option<Wrapper> {
+        let set = |text: &Option<String>| text.clone().filter(|t| !t.trim().is_empty());
+        let wrapper = Wrapper {
+            prefix: set(&config.prompt_prefix),
+            suffix: set(&config.prompt_suffix),
+        };
+        (wrapper != Wrapper::default()).then_some(wrapper)
+    }
+
+    /// `text` as it's sent: the prefix and suffix each a paragraph of their own.
+    pub fn apply(&self, text: &str) -> String {
+        let mut out = String::new();
+        if let Some(ref prefix) = self.prefix {
+            out.push_str(prefix.trim_end());
+            out.push_str("\n\n");
+        }
+        out.push_str(text);
+        if let Some(ref suffix) = self.suffix {
+            out.push_str("\n\n");
+            out.push_str(suffix.trim_start());
+        }
+        out
+    }
+
+    /// Both parts on one line, for `:wrap-prompt` and `:show`.
+    pub fn describe(&self) -> String {
+        let part = |part: Msg, text: &Option<String>| {
+            let text = text.as_ref()?.trim();
+            Some(msg::fmt(part, &[("text", &text)]))
+        };
+        match (
+            part(Msg::WrapperPrefix, &self.prefix),
+            part(Msg::WrapperSuffix, &self.suffix),
+        ) {
+            (Some(prefix), Some(suffix)) => msg::fmt(
+                Msg::WrapperBoth,
+                &[("prefix", &prefix), ("suffix", &suffix)],
+            ),
+            (prefix, suffix) => prefix.or(suffix).unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::{fs, net::TcpListener};
+
+#[test]
+fn the_files_system_prompt_and_question_go_in_one_request() {
+    let api = Api::start(vec![answer("It's missing a semicolon.")]);
+    let sandbox = Sandbox::new("ask-ok", &api, "");
+    fs::write(sandbox.dir.join("main.rs"), "fn main() { 1 }\n").unwrap();
+    let output = sandbox
+        .gpt()
+        .args([
+            "ask", "-f", "main.rs", "-m", "4o-mini", "--system", "be terse",
+        ])
+        .arg("why does this fail?")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "It's missing a semicolon.\n"
+    );
+    let requests = api.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["model"], "gpt-4o-mini");
+    let messages = requests[0]["messages"].as_array().unwrap();
+    assert_eq!(messages[0]["role"], "system");
+    assert_eq!(messages[0]["content"], "be terse");
+    assert_eq!(
+        messages[1]["content"],
+        "main.rs:\n```\nfn main() { 1 }\n```\n\nwhy does this fail?"
+    );
+}
+
+#[test]
+fn a_request_that_fails_exits_with_2() {
+    // Nothing listens there once the listener is gone.
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let sandbox = Sandbox::offline(
+        "ask-down",
+        &format!("api_base = \"http://{closed}\"\ncheck_auth = false\n"),
+    );
+    let output = sandbox.gpt().args(["ask", "hello"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn a_prompt_over_the_window_exits_with_3_without_a_request() {
+    let api = Api::start(vec![answer("ok")]);
+    // Leaves about a thousand tokens of gpt-4o-mini's 128,000 for the prompt.
+    let sandbox = Sandbox::new(
+        "ask-long",
+        &api,
+        "model = \"gpt-4o-mini\"\nmax_tokens = 127000\n",
+    );
+    fs::write(sandbox.dir.join("long.txt"), "a long file ".repeat(1000)).unwrap();
+    let output = sandbox
+        .gpt()
+        .args(["ask", "-f", "long.txt", "summarise this"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{stderr}");
+    assert!(
+        stderr.contains("exceeds gpt-4o-mini's 128,000 window"),
+        "{stderr}"
+    );
+    assert!(api.requests().is_empty());
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::{
+    fs,
+    io::Write,
+    process::{Output, Stdio},
+};
+
+fn repl(sandbox: &Sandbox, input: &str) -> String {
+    let mut child = sandbox
+        .gpt()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(output.status.success(), "{stdout}");
+    stdout
+}
+
+fn bookmarks(sandbox: &Sandbox, args: &[&str]) -> Output {
+    sandbox.gpt().arg("bookmarks").args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn numbers_survive_deletes_and_text_survives_the_session() {
+    let api = Api::start(vec![answer("first answer"), answer("second answer")]);
+    let sandbox = Sandbox::new("bookmarks", &api, "");
+    let out = repl(
+        &sandbox,
+        // With the context kept, so both answers are in it.
+        ":c\none\ntwo\n:save notes\n:bookmark\n:bookmark 1 the first one\n:quit\n",
+    );
+    assert!(out.contains("Bookmarked message 3 as 1."), "{out}");
+    assert!(out.contains("Bookmarked message 1 as 2."), "{out}");
+
+    let list = stdout(&bookmarks(&sandbox, &[]));
+    assert!(list.contains("  1  "), "{list}");
+    assert!(list.contains("second answer"), "{list}");
+    assert!(list.contains("the first one"), "{list}");
+    assert!(list.contains("(notes [1])"), "{list}");
+
+    let deleted = bookmarks(&sandbox, &["delete", "1"]);
+    assert!(deleted.status.success());
+    assert!(stdout(&deleted).contains("Deleted bookmark 1 (second answer)."));
+    // The one after it keeps its number.
+    assert!(stdout(&bookmarks(&sandbox, &["show", "2"])).contains("first answer"));
+    assert!(!bookmarks(&sandbox, &["show", "1"]).status.success());
+
+    // A new one doesn't get the number that was freed.
+    let out = repl(&sandbox, ":load notes\n:bookmark\n:quit\n");
+    assert!(out.contains("as 3."), "{out}");
+
+    fs::remove_file(sandbox.data_dir().join("sessions/notes.json")).unwrap();
+    let shown = stdout(&bookmarks(&sandbox, &["show", "3"]));
+    assert!(shown.contains("second answer"), "{shown}");
+    let list = stdout(&bookmarks(&sandbox, &[]));
+    assert_eq!(list.lines().count(), 2, "{list}");
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::process::Stdio;
+
+#[test]
+fn a_closed_stdout_ends_the_program_cleanly() {
+    let api = Api::start(vec![answer(
+        "a long enough answer to be written after the reader has gone",
+    )]);
+    let sandbox = Sandbox::new("broken-pipe", &api, "");
+    let mut child = sandbox
+        .gpt()
+        .arg("hello")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Nothing reads the answer, so every write to stdout fails.
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}: {stderr}", output.status);
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    assert_eq!(api.requests().len(), 1);
+}
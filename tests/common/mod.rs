@@ -0,0 +1,249 @@
+//! What the integration tests share: a stand-in for the OpenAI API, and gpt run against it from a
+//! directory of its own so nothing from the real home directory gets in.
+
+// Each test binary uses only some of this.
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    process::Command,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Streams a scripted reply to each chat request and keeps the requests for the test to look at.
+pub struct Api {
+    pub base: String,
+    requests: Arc<Mutex<Vec<Value>>>,
+}
+
+impl Api {
+    /// The n-th chat request gets `replies[n]`, each a list of the chunks' `choices`; once they
+    /// run out the last one is sent again. A [`STALL`] in a reply stops it there with the
+    /// connection left open, and one from [`billed`] says what it used. Anything else (e.g. listing
+    /// models) gets an empty list.
+    pub fn start(replies: Vec<Vec<Value>>) -> Api {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let replies = Arc::new(replies);
+        thread::spawn(move || {
+            for mut socket in listener.incoming().flatten() {
+                let seen = seen.clone();
+                let replies = replies.clone();
+                // A stalled reply mustn't hold up the request that follows it.
+                thread::spawn(move || {
+                    let Some((path, body)) = read_request(&mut socket) else {
+                        return;
+                    };
+                    if !path.ends_with("/chat/completions") {
+                        let _ = socket.write_all(
+                            http("application/json", r#"{"object":"list","data":[]}"#).as_bytes(),
+                        );
+                        return;
+                    }
+                    let n = {
+                        let mut seen = seen.lock().unwrap();
+                        seen.push(serde_json::from_slice(&body).unwrap_or(Value::Null));
+                        seen.len() - 1
+                    };
+                    let reply = replies
+                        .get(n)
+                        .or(replies.last())
+                        .cloned()
+                        .unwrap_or_default();
+                    stream(&mut socket, reply);
+                });
+            }
+        });
+        Api { base, requests }
+    }
+
+    /// The bodies of the chat requests so far.
+    pub fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// The path and body of the next request on `socket`.
+pub fn read_request(socket: &mut TcpStream) -> Option<(String, Vec<u8>)> {
+    socket
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .ok()?;
+    let mut request = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+        let n = socket.read(&mut buf).ok()?;
+        if n == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buf[..n]);
+        let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let head = String::from_utf8_lossy(&request[..end]).to_string();
+        let length = head
+            .lines()
+            .find_map(|l| {
+                l.to_ascii_lowercase()
+                    .strip_prefix("content-length:")
+                    .map(|v| v.trim().parse().unwrap_or(0))
+            })
+            .unwrap_or(0);
+        if request.len() >= end + 4 + length {
+            let path = head
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            return Some((path, request[end + 4..end + 4 + length].to_vec()));
+        }
+    }
+}
+
+/// Stands in for a chunk to stop a reply there without ending it.
+pub const STALL: Value = Value::Null;
+
+// Written event by event, without a length, so a stall can leave the response unfinished.
+fn stream(socket: &mut TcpStream, reply: Vec<Value>) {
+    let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+    if socket.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    for choices in reply {
+        if choices.is_null() {
+            // Until the client gives up and hangs up.
+            let mut buf = [0; 64];
+            let _ = socket.set_read_timeout(None);
+            while socket.read(&mut buf).is_ok_and(|n| n > 0) {}
+            return;
+        }
+        let mut chunk = json!({
+            "id": "chatcmpl-test",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "gpt-4o-mini",
+            "choices": choices,
+        });
+        if let Some(usage) = choices.get("usage") {
+            chunk["choices"] = json!([]);
+            chunk["usage"] = usage.clone();
+        }
+        if socket
+            .write_all(format!("data: {chunk}\n\n").as_bytes())
+            .is_err()
+        {
+            return;
+        }
+    }
+    let _ = socket.write_all(b"data: [DONE]\n\n");
+}
+
+fn http(content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// A reply that streams `text` in a few pieces and stops.
+pub fn answer(text: &str) -> Vec<Value> {
+    let mut chunks: Vec<Value> = text
+        .split_inclusive(' ')
+        .map(|piece| json!([{"index": 0, "delta": {"content": piece}, "finish_reason": null}]))
+        .collect();
+    chunks.push(json!([{"index": 0, "delta": {}, "finish_reason": "stop"}]));
+    chunks
+}
+
+/// `reply`, ending with the usage chunk a request with `include_usage` gets: `prompt` prompt
+/// tokens billed, whatever was sent.
+pub fn billed(mut reply: Vec<Value>, prompt: u32) -> Vec<Value> {
+    reply.push(json!({"usage": {
+        "prompt_tokens": prompt,
+        "completion_tokens": 1,
+        "total_tokens": prompt + 1,
+    }}));
+    reply
+}
+
+/// A reply that asks for the tool `name` to be run with `arguments`.
+pub fn tool_call(name: &str, arguments: &Value) -> Vec<Value> {
+    vec![
+        json!([{"index": 0, "delta": {"role": "assistant", "tool_calls": [{
+            "index": 0,
+            "id": "call_1",
+            "type": "function",
+            "function": {"name": name, "arguments": arguments.to_string()},
+        }]}, "finish_reason": null}]),
+        json!([{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]),
+    ]
+}
+
+/// A home, config, data and cache directory for one test, removed afterwards.
+pub struct Sandbox {
+    pub dir: PathBuf,
+}
+
+impl Sandbox {
+    /// `config` is added to a config file that points at `api` and skips the startup auth check.
+    pub fn new(name: &str, api: &Api, config: &str) -> Sandbox {
+        Sandbox::offline(
+            name,
+            &format!("api_base = \"{}\"\ncheck_auth = false\n{config}", api.base),
+        )
+    }
+
+    /// For what doesn't talk to the API, like `gpt report`.
+    pub fn offline(name: &str, config: &str) -> Sandbox {
+        let dir = std::env::temp_dir().join(format!("gpt-cli-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("config")).unwrap();
+        // Spelled out rather than left to the platform, which on Windows ignores HOME.
+        fs::write(
+            dir.join("config/config.toml"),
+            format!(
+                "data_dir = '{}'\ncache_dir = '{}'\n{config}",
+                dir.join("data").display(),
+                dir.join("cache").display()
+            ),
+        )
+        .unwrap();
+        Sandbox { dir }
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.dir.join("data")
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.dir.join("config")
+    }
+
+    /// gpt, run from the sandbox with a made-up key.
+    pub fn gpt(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_gpt"));
+        command
+            .current_dir(&self.dir)
+            .env("HOME", &self.dir)
+            .env("GPT_CLI_CONFIG", self.config_dir().join("config.toml"))
+            .env("OPENAI_API_KEY", "sk-test")
+            .env("NO_COLOR", "1")
+            .env_remove("OPENAI_ORG_ID")
+            .env_remove("OPENAI_PROJECT_ID")
+            .env_remove("HISTFILE");
+        command
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
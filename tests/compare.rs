@@ -0,0 +1,46 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::fs;
+
+#[test]
+fn each_model_is_asked_and_the_conversation_is_left_alone() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("compare", &api, "title_after_exchanges = 0\n");
+    fs::write(
+        sandbox.dir.join("script.txt"),
+        ":compare 4o,4o-mini What's a monad?\nand after\n",
+    )
+    .unwrap();
+    let output = sandbox
+        .gpt()
+        .args(["--script", "script.txt", "--script-exit"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+
+    let requests = api.requests();
+    assert_eq!(requests.len(), 3, "{stdout}");
+    // Sent at once, so in no particular order.
+    let mut models: Vec<&str> = requests[..2]
+        .iter()
+        .map(|r| r["model"].as_str().unwrap())
+        .collect();
+    models.sort();
+    assert_eq!(models, ["gpt-4o", "gpt-4o-mini"]);
+    for request in &requests[..2] {
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["content"], "What's a monad?");
+    }
+    // Neither answer went into the context.
+    let messages = requests[2]["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["content"], "and after");
+
+    // Shown in the order the models were given.
+    let first = stdout.find("── gpt-4o ──").expect(&stdout);
+    let second = stdout.find("── gpt-4o-mini ──").expect(&stdout);
+    assert!(first < second, "{stdout}");
+}
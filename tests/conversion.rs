@@ -0,0 +1,45 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use serde_json::Value;
+use std::{fs, io::Write, process::Stdio};
+
+#[test]
+fn a_message_that_cannot_be_sent_is_named_and_the_turn_is_not_kept() {
+    let api = Api::start(vec![answer("never asked")]);
+    let sandbox = Sandbox::new("conversion", &api, "");
+    let sessions = sandbox.data_dir().join("sessions");
+    fs::create_dir_all(&sessions).unwrap();
+    // A session edited by hand, with a question taken out.
+    fs::write(
+        sessions.join("edited.json"),
+        r#"{"schema_version":1,"model":"gpt-4o-mini","messages":[{"role":"user","content":"  "},{"role":"assistant","content":"Paris."}]}"#,
+    )
+    .unwrap();
+
+    let mut child = sandbox
+        .gpt()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b":load edited\n:c\nand Spain?\n:save after\n:quit\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("Message 0 couldn't be sent: invalid args: it has no text"),
+        "{stdout}"
+    );
+    assert!(api.requests().is_empty());
+
+    let saved: Value =
+        serde_json::from_str(&fs::read_to_string(sessions.join("after.json")).unwrap()).unwrap();
+    assert_eq!(saved["messages"].as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{answer, billed, Api, Sandbox};
+use serde_json::Value;
+use std::fs;
+
+// Long enough for its estimate to be worth comparing.
+fn long_message(word: &str) -> String {
+    vec![word; 300].join(" ")
+}
+
+#[test]
+fn billing_far_from_the_estimate_is_shown_warned_about_once_and_recorded() {
+    let api = Api::start(vec![billed(answer("ok"), 1000)]);
+    let sandbox = Sandbox::new(
+        "drift",
+        &api,
+        "model = \"gpt-4o-mini\"\ntitle_after_exchanges = 0\n",
+    );
+    fs::write(
+        sandbox.dir.join("script.txt"),
+        format!("{}\n{}\n", long_message("apple"), long_message("pear")),
+    )
+    .unwrap();
+    let output = sandbox
+        .gpt()
+        .args(["--script", "script.txt", "--script-exit"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert_eq!(api.requests().len(), 2);
+
+    // In each footer, against what was billed.
+    assert_eq!(stderr.matches("| est ").count(), 2, "{stderr}");
+    assert!(stderr.contains(" / actual 1,000, +"), "{stderr}");
+    // The hint to file a bug comes once for the model.
+    let warning = "the prompt's token estimate for gpt-4o-mini was off";
+    assert_eq!(stderr.matches(warning).count(), 1, "{stderr}");
+    assert!(stderr.contains("gpt-cli/issues"), "{stderr}");
+
+    let output = sandbox
+        .gpt()
+        .args(["report", "--drift", "--format", "json"])
+        .output()
+        .unwrap();
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let row = &report["rows"]["gpt-4o-mini"];
+    assert_eq!(row["requests"], 2, "{report}");
+    assert_eq!(row["prompt_tokens"], 2000);
+    let estimated = row["estimated_prompt_tokens"].as_u64().unwrap();
+    assert!((600..1000).contains(&estimated), "{report}");
+    assert!(row["drift_percent"].as_f64().unwrap() > 10.0, "{report}");
+
+    let output = sandbox.gpt().args(["report", "--drift"]).output().unwrap();
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        table.contains("The estimates for gpt-4o-mini are off by more than 10%"),
+        "{table}"
+    );
+}
+
+#[test]
+fn an_estimate_close_to_the_bill_is_not_warned_about() {
+    let message = long_message("apple");
+    let run = |name: &str, prompt: u32| {
+        let api = Api::start(vec![billed(answer("ok"), prompt)]);
+        let sandbox = Sandbox::new(name, &api, "model = \"gpt-4o-mini\"\n");
+        let output = sandbox
+            .gpt()
+            .args(["--verbose", &message])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    // What the request is estimated at, from a bill far enough off to say.
+    let stderr = run("drift-far", 5000);
+    let estimate: u32 = stderr
+        .split_once("| est ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .and_then(|n| n.replace(',', "").parse().ok())
+        .unwrap_or_else(|| panic!("no estimate in {stderr}"));
+
+    let stderr = run("drift-close", estimate + estimate / 20);
+    assert!(
+        stderr.contains(&format!("| est {estimate} / actual")),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("was off"), "{stderr}");
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::fs;
+
+#[test]
+fn a_dry_run_prints_the_request_and_sends_nothing() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("dry-run", &api, "");
+    let output = sandbox
+        .gpt()
+        .args(["--dry-run", "it's", "$HOME"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(
+        stdout.starts_with(&format!("curl -N '{}/chat/completions'", api.base)),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Bearer $OPENAI_API_KEY"), "{stdout}");
+    assert!(stdout.contains(r#""content": "it'\''s $HOME""#), "{stdout}");
+    assert!(api.requests().is_empty());
+}
+
+#[test]
+fn curl_dry_leaves_the_conversation_as_it_was() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("curl-dry", &api, "title_after_exchanges = 0\n");
+    fs::write(
+        sandbox.dir.join("script.txt"),
+        "before\n:curl dry\n|not sent\n:curl off\n|after\n",
+    )
+    .unwrap();
+    let output = sandbox
+        .gpt()
+        .args(["--script", "script.txt", "--script-exit"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("not sent"), "{stdout}");
+
+    let requests = api.requests();
+    assert_eq!(requests.len(), 2, "{requests:?}");
+    let messages = requests[1]["messages"].as_array().unwrap();
+    let contents: Vec<&str> = messages
+        .iter()
+        .map(|m| m["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, ["before", "ok", "after"]);
+}
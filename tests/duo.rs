@@ -0,0 +1,48 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use serde_json::Value;
+use std::fs;
+
+fn system(request: &Value) -> Option<&str> {
+    let first = &request["messages"][0];
+    (first["role"] == "system").then(|| first["content"].as_str().unwrap())
+}
+
+#[test]
+fn each_side_is_sent_its_own_system_prompt_and_nothing_of_the_conversation() {
+    let api = Api::start(vec![answer("Who are you?"), answer("A candidate.")]);
+    let sandbox = Sandbox::new("duo", &api, "title_after_exchanges = 0\n");
+    fs::write(
+        sandbox.dir.join(".gpt-cli.toml"),
+        "system = \"from the project\"\n",
+    )
+    .unwrap();
+    fs::write(
+        sandbox.dir.join("script.txt"),
+        ":brief\n:duo 4o 4o-mini --system-a \"You interview.\" --turns 2 Begin.\nafter\n",
+    )
+    .unwrap();
+    let output = sandbox
+        .gpt()
+        .args(["--script", "script.txt", "--script-exit"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+
+    let requests = api.requests();
+    assert_eq!(requests.len(), 3, "{stdout}");
+    assert_eq!(requests[0]["model"], "gpt-4o");
+    assert_eq!(system(&requests[0]), Some("You interview."));
+    assert_eq!(requests[0]["messages"][1]["content"], "Begin.");
+    // B was given no system prompt, so it's sent none.
+    assert_eq!(requests[1]["model"], "gpt-4o-mini");
+    assert_eq!(system(&requests[1]), None);
+    assert_eq!(requests[1]["messages"][0]["content"], "Who are you?");
+
+    // The conversation has its project prompt and preset back.
+    let system = system(&requests[2]).unwrap();
+    assert!(system.starts_with("from the project"), "{system}");
+    assert!(system.contains("Answer in at most 3 sentences."), "{system}");
+}
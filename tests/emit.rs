@@ -0,0 +1,102 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{answer, Api, Sandbox, STALL};
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader},
+    os::unix::net::UnixListener,
+    process::Output,
+    thread,
+};
+
+// Runs gpt with a reader on its socket, returning what gpt did and the events the reader got.
+fn with_reader(sandbox: &Sandbox, args: &[&str]) -> (Output, Vec<Value>) {
+    let path = sandbox.dir.join("events.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let reader = thread::spawn(move || {
+        let (socket, _) = listener.accept().unwrap();
+        // Until gpt exits and the socket closes.
+        BufReader::new(socket)
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect::<Vec<Value>>()
+    });
+    let output = sandbox
+        .gpt()
+        .arg("--emit-socket")
+        .arg(&path)
+        .args(args)
+        .output()
+        .unwrap();
+    (output, reader.join().unwrap())
+}
+
+#[test]
+fn an_answer_is_a_start_its_deltas_and_a_done() {
+    let api = Api::start(vec![answer("Hello there world")]);
+    let sandbox = Sandbox::new("emit", &api, "");
+    let (output, events) = with_reader(&sandbox, &["greet me"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello there world\n"
+    );
+
+    let model = events[0]["model"].clone();
+    assert_eq!(events[0], json!({"type": "start", "model": model}));
+    let deltas: Vec<&Value> = events[1..events.len() - 1].iter().collect();
+    assert_eq!(
+        deltas,
+        [
+            &json!({"type": "delta", "text": "Hello "}),
+            &json!({"type": "delta", "text": "there "}),
+            &json!({"type": "delta", "text": "world"}),
+        ]
+    );
+    let done = events.last().unwrap();
+    assert_eq!(done["type"], "done");
+    assert_eq!(done["model"], model);
+    let usage = done["usage"].as_object().unwrap();
+    assert!(usage["prompt"].as_u64().unwrap() > 0);
+    assert!(usage["completion"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn a_failed_answer_ends_with_an_error() {
+    let api = Api::start(vec![vec![
+        json!([{"index": 0, "delta": {"content": "Hello"}, "finish_reason": null}]),
+        STALL,
+    ]]);
+    let sandbox = Sandbox::new("emit-error", &api, "stall_after_secs = 1\n");
+    let (output, events) = with_reader(&sandbox, &["greet me"]);
+    assert!(!output.status.success());
+    let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(types, ["start", "delta", "error"]);
+    assert_eq!(events[1]["text"], "Hello");
+    let message = events[2]["message"].as_str().unwrap();
+    assert!(message.contains("nothing arrived for 1s"), "{message}");
+}
+
+#[test]
+fn without_a_reader_the_answer_still_comes() {
+    let api = Api::start(vec![answer("Hello")]);
+    let sandbox = Sandbox::new("emit-nobody", &api, "");
+    let output = sandbox
+        .gpt()
+        .arg("--emit-socket")
+        .arg(sandbox.dir.join("nobody.sock"))
+        .arg("greet me")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Said once, though it's tried again when the answer starts.
+    assert_eq!(
+        stderr.matches("--emit-socket: couldn't connect").count(),
+        1,
+        "{stderr}"
+    );
+}
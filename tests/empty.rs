@@ -0,0 +1,35 @@
+mod common;
+
+use common::{Api, Sandbox};
+use serde_json::json;
+
+fn text(content: &str) -> serde_json::Value {
+    json!([{"index": 0, "delta": {"content": content}, "finish_reason": null}])
+}
+
+fn stop() -> serde_json::Value {
+    json!([{"index": 0, "delta": {}, "finish_reason": "stop"}])
+}
+
+#[test]
+fn a_whitespace_answer_prints_nothing_and_is_reported() {
+    let api = Api::start(vec![vec![text("\n\n"), text("  \t"), stop()]]);
+    let sandbox = Sandbox::new("empty", &api, "");
+    let output = sandbox.gpt().arg("hi").output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(
+        stderr.contains("The model returned an empty response (finish_reason: stop)."),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn whitespace_before_the_text_is_still_printed() {
+    let api = Api::start(vec![vec![text("\n"), text("  "), text("indented"), stop()]]);
+    let sandbox = Sandbox::new("leading-space", &api, "");
+    let output = sandbox.gpt().arg("hi").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\n  indented\n");
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use chrono::{Duration as ChronoDuration, Utc};
+use common::Sandbox;
+use serde_json::json;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::{self, Command},
+    time::{Duration, SystemTime},
+};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Writes `path`, last modified `ago`.
+fn file(path: &Path, contents: &[u8], ago: Duration) -> PathBuf {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::now() - ago)
+        .unwrap();
+    path.to_path_buf()
+}
+
+// A process that has exited, for files whose writer is gone.
+fn dead_pid() -> u32 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gpt"))
+        .arg("--version")
+        .stdout(process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    pid
+}
+
+fn usage(days_ago: i64) -> String {
+    json!({
+        "timestamp": Utc::now() - ChronoDuration::days(days_ago),
+        "model": "gpt-4o",
+        "prompt_tokens": 10,
+        "cached_tokens": 0,
+        "completion_tokens": 5,
+        "cost": 0.001,
+        "schema_version": 1,
+    })
+    .to_string()
+}
+
+#[test]
+fn gc_removes_only_what_the_retention_settings_say() {
+    let sandbox = Sandbox::offline(
+        "gc",
+        "max_sessions = 3\nsession_max_age_days = 30\ncache_ttl_secs = 86400\ncache_max_mb = 1\nusage_retention_days = 30\n",
+    );
+    let data = sandbox.data_dir();
+    let sessions = data.join("sessions");
+    let cache = sandbox.dir.join("cache");
+    let (dead, ours) = (dead_pid(), process::id());
+    let session = br#"{"schema_version":1,"model":"gpt-4o","messages":[]}"#;
+
+    let kept = [
+        file(&sessions.join("new.json"), session, Duration::ZERO),
+        file(&sessions.join("mid.json"), session, DAY * 10),
+        file(&sessions.join("proj/mid.json"), session, DAY * 20),
+        // Too old, but bookmarked.
+        file(&sessions.join("marked.json"), session, DAY * 60),
+        // Too old, but a running gpt has it.
+        file(&sessions.join("open.json"), session, DAY * 60),
+        file(
+            &sessions.join("open.lock"),
+            format!("{ours}\n").as_bytes(),
+            DAY,
+        ),
+        // A write still going, and one cut short too recently to be sure.
+        file(&sessions.join(format!(".new.json.{ours}.tmp")), b"{", DAY),
+        file(
+            &sessions.join(format!(".mid.json.{dead}.tmp")),
+            b"{",
+            Duration::ZERO,
+        ),
+        // Fresh, and what fits under cache_max_mb of the rest.
+        file(&cache.join("c.json"), &[b' '; 600 * 1024], Duration::ZERO),
+        file(&cache.join("b.json"), &[b' '; 300 * 1024], DAY / 4),
+    ];
+    let removed = [
+        // The fourth newest, past max_sessions.
+        file(&sessions.join("fourth.json"), session, DAY * 25),
+        file(&sessions.join("old.json"), session, DAY * 40),
+        file(&sessions.join(format!(".old.json.{dead}.tmp")), b"{", DAY),
+        file(&cache.join(format!(".x.json.{dead}.tmp")), b"{", DAY),
+        file(&cache.join("expired.json"), b"{}", DAY * 2),
+        file(&cache.join("a.json"), &[b' '; 600 * 1024], DAY / 2),
+    ];
+    // Left by the gpt that had old.json, so it goes with it.
+    let stale_lock = file(
+        &sessions.join("old.lock"),
+        format!("{dead}\n").as_bytes(),
+        DAY * 40,
+    );
+    fs::write(
+        data.join("bookmarks.json"),
+        json!({
+            "schema_version": 1,
+            "next": 2,
+            "bookmarks": [{
+                "id": 1,
+                "created": "2024-05-01T12:00:00Z",
+                "session": "marked",
+                "index": 1,
+                "role": "assistant",
+                "text": "keep me",
+            }],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let ledger = format!("{}\n{}\n{}\n", usage(60), usage(40), usage(1));
+    fs::write(data.join("usage.jsonl"), &ledger).unwrap();
+
+    let dry = sandbox.gpt().args(["gc", "--dry-run"]).output().unwrap();
+    assert!(dry.status.success());
+    let listed = String::from_utf8_lossy(&dry.stdout);
+    for path in &removed {
+        assert!(
+            listed.contains(&format!("would remove {}", path.display())),
+            "{path:?}\n{listed}"
+        );
+    }
+    assert_eq!(listed.lines().count(), removed.len() + 1, "{listed}");
+    assert!(listed.contains("would remove 2 of the requests in usage.jsonl"));
+    let summary = String::from_utf8_lossy(&dry.stderr);
+    assert!(
+        summary.contains("(would remove 6 files, 0.6 MB, and 2 requests from usage.jsonl)"),
+        "{summary}"
+    );
+    for path in kept.iter().chain(&removed).chain([&stale_lock]) {
+        assert!(path.exists(), "{path:?}");
+    }
+    assert_eq!(
+        fs::read_to_string(data.join("usage.jsonl")).unwrap(),
+        ledger
+    );
+
+    let output = sandbox.gpt().arg("gc").output().unwrap();
+    assert!(output.status.success());
+    for path in &kept {
+        assert!(path.exists(), "{path:?}");
+    }
+    for path in removed.iter().chain([&stale_lock]) {
+        assert!(!path.exists(), "{path:?}");
+    }
+    // Only the recent request is left.
+    let left = fs::read_to_string(data.join("usage.jsonl")).unwrap();
+    assert_eq!(left, format!("{}\n", ledger.lines().last().unwrap()));
+
+    // Nothing left to do.
+    let again = sandbox.gpt().arg("gc").output().unwrap();
+    assert!(String::from_utf8_lossy(&again.stderr).contains("(nothing to remove)"));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::{answer, read_request, tool_call, Api, Sandbox};
+use serde_json::{json, Value};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// An MCP server with one tool, reached over HTTP with server-sent events.
+fn sse_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/sse", listener.local_addr().unwrap());
+    let events: Arc<Mutex<Option<TcpStream>>> = Arc::default();
+    thread::spawn(move || {
+        for mut socket in listener.incoming().flatten() {
+            let events = events.clone();
+            thread::spawn(move || {
+                let Some((path, body)) = read_request(&mut socket) else {
+                    return;
+                };
+                if path == "/sse" {
+                    let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\n";
+                    // A comment first, which is to be skipped.
+                    let hello = ": hello\n\nevent: endpoint\ndata: /messages?session=1\n\n";
+                    socket.write_all(head.as_bytes()).unwrap();
+                    socket.write_all(hello.as_bytes()).unwrap();
+                    *events.lock().unwrap() = Some(socket.try_clone().unwrap());
+                    // Open until the client hangs up.
+                    let mut buf = [0; 64];
+                    while socket.read(&mut buf).is_ok_and(|n| n > 0) {}
+                    return;
+                }
+                assert_eq!(path, "/messages?session=1");
+                let _ = socket.write_all(
+                    b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                );
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let result = match request["method"].as_str().unwrap() {
+                    "initialize" => json!({
+                        "protocolVersion": "2024-11-05",
+                        "capabilities": {"tools": {}},
+                        "serverInfo": {"name": "weather", "version": "1"},
+                    }),
+                    "tools/list" => json!({"tools": [{
+                        "name": "get_weather",
+                        "description": "The weather in a city",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {"city": {"type": "string"}},
+                        },
+                    }]}),
+                    "tools/call" => {
+                        let city = request["params"]["arguments"]["city"].as_str().unwrap();
+                        json!({"content": [{"type": "text", "text": format!("{city}: Sunny")}]})
+                    }
+                    // Notifications get no answer.
+                    _ => return,
+                };
+                let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                let event = format!("event: message\ndata: {response}\n\n");
+                let mut events = events.lock().unwrap();
+                events
+                    .as_mut()
+                    .unwrap()
+                    .write_all(event.as_bytes())
+                    .unwrap();
+            });
+        }
+    });
+    url
+}
+
+#[test]
+fn tools_of_a_server_reached_over_sse_are_called() {
+    let api = Api::start(vec![
+        tool_call("get_weather", &json!({"city": "Paris"})),
+        answer("Sunny in Paris"),
+    ]);
+    let config = format!("[mcp_servers.weather]\nurl = \"{}\"\n", sse_server());
+    let sandbox = Sandbox::new("mcp-sse", &api, &config);
+    let output = sandbox.gpt().arg("weather in Paris?").output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("MCP server"), "{stderr}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Sunny in Paris"));
+
+    let requests = api.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0]["tools"][0]["function"]["name"], "get_weather");
+    let messages = requests[1]["messages"].as_array().unwrap();
+    let result = messages.iter().find(|m| m["role"] == "tool").unwrap();
+    assert_eq!(result["content"], "Paris: Sunny");
+}
+
+#[test]
+fn a_server_that_cant_be_reached_is_left_out() {
+    let api = Api::start(vec![answer("Hello")]);
+    // Nothing listens there once the listener is gone.
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let config = format!("[mcp_servers.weather]\nurl = \"http://{closed}/sse\"\n");
+    let sandbox = Sandbox::new("mcp-sse-down", &api, &config);
+    let output = sandbox.gpt().arg("hi").output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("MCP server 'weather' failed to start"),
+        "{stderr}"
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello\n");
+    assert!(api.requests()[0].get("tools").is_none());
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::{io::Write, process::Stdio};
+
+#[test]
+fn overrides_win_over_the_session_for_one_message() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("overrides", &api, "model = \"gpt-4o-mini\"\n");
+    let mut child = sandbox
+        .gpt()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = [
+        ":c",
+        ":set temperature 0.7",
+        ":set max_tokens 300",
+        "@4o @temp=0.2 first",
+        "second",
+        "@bogus=1 third",
+        ":quit",
+    ];
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(format!("{}\n", input.join("\n")).as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stdout}{stderr}");
+
+    let requests = api.requests();
+    // Overridden, with what wasn't left to the session.
+    assert_eq!(requests[0]["model"], "gpt-4o");
+    assert_eq!(requests[0]["temperature"], 0.2);
+    assert_eq!(requests[0]["max_completion_tokens"], 300);
+    // And back to the session's after, with only the message itself in the context.
+    assert_eq!(requests[1]["model"], "gpt-4o-mini");
+    assert_eq!(requests[1]["temperature"], 0.7);
+    assert_eq!(requests[1]["max_completion_tokens"], 300);
+    assert_eq!(requests[1]["messages"][0]["content"], "first");
+    assert!(stderr.contains("| @4o @temp=0.2 |"), "{stderr}");
+    assert_eq!(stderr.matches("@4o").count(), 1, "{stderr}");
+
+    // Refused before it was sent; what's left is the title being made.
+    assert!(stdout.contains("Unknown override '@bogus=1'"), "{stdout}");
+    assert!(!requests.iter().any(|r| r.to_string().contains("third")));
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::{
+    io::{BufRead, BufReader},
+    process::Stdio,
+};
+
+#[test]
+fn piped_output_is_only_the_answer() {
+    let api = Api::start(vec![answer("first line\nsecond line")]);
+    let sandbox = Sandbox::new("pipe-answer", &api, "");
+    let output = sandbox.gpt().arg("hello").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "first line\nsecond line\n"
+    );
+    // The footer still goes somewhere, just not into the data.
+    eprintln!("STDERR[{}]", String::from_utf8_lossy(&output.stderr));
+    assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn a_reader_that_stops_early_is_a_clean_exit() {
+    let lines: String = (1..=200).map(|n| format!("line {n}\n")).collect();
+    let api = Api::start(vec![answer(&lines)]);
+    let sandbox = Sandbox::new("pipe-head", &api, "");
+    let mut child = sandbox
+        .gpt()
+        .arg("count")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Like `| head -n 1`.
+    let mut first = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut first)
+        .unwrap();
+    assert_eq!(first, "line 1\n");
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
@@ -0,0 +1,84 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{answer, tool_call, Api, Sandbox};
+use serde_json::{json, Value};
+use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+fn with_weather(name: &str, api: &Api, config: &str) -> Sandbox {
+    let sandbox = Sandbox::new(name, api, config);
+    let tools = sandbox.config_dir().join("tools");
+    fs::create_dir_all(&tools).unwrap();
+    let weather = tools.join("weather");
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tools/weather");
+    fs::copy(fixture, &weather).unwrap();
+    fs::set_permissions(&weather, fs::Permissions::from_mode(0o755)).unwrap();
+    sandbox
+}
+
+fn tool_names(request: &Value) -> Vec<&str> {
+    request["tools"]
+        .as_array()
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|t| t["function"]["name"].as_str())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// The result the tool call in the first request got, as the second request sent it.
+fn tool_result(api: &Api) -> String {
+    let requests = api.requests();
+    assert_eq!(requests.len(), 2);
+    let messages = requests[1]["messages"].as_array().unwrap();
+    let result = messages.iter().find(|m| m["role"] == "tool").unwrap();
+    assert_eq!(result["tool_call_id"], "call_1");
+    result["content"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn the_model_can_call_an_allowed_tool() {
+    let api = Api::start(vec![
+        tool_call("weather", &json!({"city": "London"})),
+        answer("It's sunny in London."),
+    ]);
+    let sandbox = with_weather("plugin-call", &api, "allow_tools = [\"weather\"]\n");
+    let output = sandbox
+        .gpt()
+        .arg("What's the weather in London?")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert_eq!(tool_names(&api.requests()[0]), ["weather"]);
+    assert_eq!(tool_result(&api).trim(), "London: Sunny +18°C");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "It's sunny in London.\n"
+    );
+}
+
+#[test]
+fn a_failing_tool_is_an_error_for_the_model() {
+    let api = Api::start(vec![
+        tool_call("weather", &json!({})),
+        answer("Which city?"),
+    ]);
+    let sandbox = with_weather("plugin-fail", &api, "allow_tools = [\"weather\"]\n");
+    let output = sandbox.gpt().arg("What's the weather?").output().unwrap();
+    assert!(output.status.success());
+    let result = tool_result(&api);
+    assert!(result.contains("no city given"), "{result}");
+}
+
+#[test]
+fn a_tool_missing_from_allow_tools_is_not_offered() {
+    let api = Api::start(vec![answer("I can't check the weather.")]);
+    let sandbox = with_weather("plugin-not-allowed", &api, "");
+    let output = sandbox.gpt().arg("What's the weather?").output().unwrap();
+    assert!(output.status.success());
+    assert!(tool_names(&api.requests()[0]).is_empty());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::{fs, io::Write, process::Stdio};
+
+#[test]
+fn a_truncated_session_is_moved_aside_and_the_repl_carries_on() {
+    let api = Api::start(vec![answer("still here")]);
+    let sandbox = Sandbox::new("recovery", &api, "");
+    let sessions = sandbox.data_dir().join("sessions");
+    fs::create_dir_all(&sessions).unwrap();
+    // What a write cut short by a crash, before writes were atomic, could leave.
+    fs::write(
+        sessions.join("notes.json"),
+        "{\"schema_version\":1,\"model\":\"gpt-4o\",\"messages\":[{\"role\":\"us",
+    )
+    .unwrap();
+
+    let mut child = sandbox
+        .gpt()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b":load notes\nhello\n:quit\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("Session 'notes' is corrupt"), "{stdout}");
+
+    let names: Vec<String> = fs::read_dir(&sessions)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert!(!names.iter().any(|n| n == "notes.json"), "{names:?}");
+    assert!(
+        names.iter().any(|n| n.starts_with("notes.json.corrupt-")),
+        "{names:?}"
+    );
+    // What came after the failed :load still went.
+    assert_eq!(api.requests().len(), 1);
+    assert!(stdout.contains("still here"));
+}
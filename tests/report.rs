@@ -0,0 +1,125 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::Value;
+use std::{fs, path::Path, process::Output};
+
+// The fixture has two models with known prices, a fine-tune without, an unknown price recorded
+// the way older versions did, a broken line and one from a newer gpt.
+fn report(name: &str, config: &str, args: &[&str]) -> Output {
+    let sandbox = Sandbox::offline(name, config);
+    fs::create_dir_all(sandbox.data_dir()).unwrap();
+    fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/usage.jsonl"),
+        sandbox.data_dir().join("usage.jsonl"),
+    )
+    .unwrap();
+    // A report needs no key.
+    let output = sandbox
+        .gpt()
+        .env_remove("OPENAI_API_KEY")
+        .arg("report")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn json(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn close(value: &Value, expected: f64) -> bool {
+    (value.as_f64().unwrap() - expected).abs() < 1e-9
+}
+
+#[test]
+fn totals_add_up_across_models() {
+    let output = report("report-models", "", &["--format", "json"]);
+    let report = json(&output);
+    let total = &report["total"];
+    assert_eq!(total["requests"], 5);
+    assert_eq!(total["prompt_tokens"], 6600);
+    assert_eq!(total["cached_tokens"], 1200);
+    assert_eq!(total["completion_tokens"], 2150);
+    assert!(close(&total["cost"], 0.0209));
+    assert_eq!(total["unpriced"], 2);
+
+    let rows = &report["rows"];
+    assert_eq!(rows.as_object().unwrap().len(), 3);
+    assert_eq!(rows["gpt-4o"]["requests"], 2);
+    assert!(close(&rows["gpt-4o"]["cost"], 0.02));
+    assert_eq!(rows["gpt-4o-mini"]["requests"], 2);
+    assert_eq!(rows["gpt-4o-mini"]["unpriced"], 1);
+    assert!(close(&rows["gpt-4o-mini"]["cost"], 0.0009));
+    assert_eq!(rows["ft:gpt-4o-mini:acme"]["unpriced"], 1);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Left out 1 of the requests"), "{stderr}");
+}
+
+#[test]
+fn costs_are_in_the_configured_currency() {
+    let config = "[currency]\nsymbol = \"€\"\nrate = 0.5\n";
+    let json = json(&report("report-currency", config, &["--format", "json"]));
+    assert_eq!(json["currency"], "€");
+    assert!(close(&json["total"]["cost"], 0.01045));
+    assert!(close(&json["rows"]["gpt-4o"]["cost"], 0.01));
+    // Tokens aren't money.
+    assert_eq!(json["total"]["prompt_tokens"], 6600);
+
+    let output = report("report-currency-csv", config, &["--format", "csv"]);
+    let csv = String::from_utf8_lossy(&output.stdout);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("model,requests,prompt_tokens,cached_tokens,completion_tokens,cost")
+    );
+    assert_eq!(lines.next(), Some("ft:gpt-4o-mini:acme,1,100,0,50,0"));
+    assert_eq!(lines.next(), Some("gpt-4o,2,4000,1200,1000,0.01"));
+}
+
+#[test]
+fn requests_are_grouped_by_day_and_org() {
+    let report_by =
+        |name, args: &[&str]| json(&report(name, "", &[&["--format", "json"], args].concat()));
+    let days = report_by(
+        "report-days",
+        &["--group-by", "day", "--since", "2024-06-02"],
+    );
+    let days = days["rows"].as_object().unwrap();
+    assert_eq!(
+        days.keys().collect::<Vec<_>>(),
+        ["2024-06-02", "2024-06-03"]
+    );
+    assert_eq!(days["2024-06-02"]["requests"], 2);
+    assert_eq!(days["2024-06-03"]["unpriced"], 1);
+
+    let orgs = report_by("report-orgs", &["--group-by", "org"]);
+    assert_eq!(orgs["rows"]["org-a"]["requests"], 1);
+    assert_eq!(orgs["rows"]["org-b"]["prompt_tokens"], 3000);
+    assert_eq!(orgs["rows"]["(default)"]["requests"], 3);
+}
+
+#[test]
+fn the_table_says_what_it_left_out() {
+    let output = report("report-table", "", &[]);
+    let table = String::from_utf8_lossy(&output.stdout);
+    let total = table.lines().find(|l| l.starts_with("total")).unwrap();
+    assert!(
+        total.contains("6,600") && total.contains("$0.021"),
+        "{total}"
+    );
+    assert!(
+        table.contains("2 requests are left out of the costs"),
+        "{table}"
+    );
+    // A row with no known price has no cost rather than a made-up one.
+    let finetune = table.lines().find(|l| l.starts_with("ft:")).unwrap();
+    assert!(finetune.trim_end().ends_with('?'), "{finetune}");
+}
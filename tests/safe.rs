@@ -0,0 +1,118 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::{fs, io::Write, process::Stdio};
+
+fn sandbox(name: &str, api: &Api) -> Sandbox {
+    let sandbox = Sandbox::new(
+        name,
+        api,
+        "substitution = true\nallow_tools = [\"weather\"]\n",
+    );
+    fs::write(
+        sandbox.dir.join(".gpt-cli.toml"),
+        "system = \"from the project\"\n",
+    )
+    .unwrap();
+    sandbox
+}
+
+#[test]
+fn every_side_effect_is_refused_by_the_same_gate() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = sandbox("safe", &api);
+    let mut child = sandbox
+        .gpt()
+        .arg("--safe")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = [
+        "hello $(echo ran)",
+        ":fix",
+        ":filter sort",
+        ":tee out.txt",
+        ":stream-to out.txt",
+        "hi",
+        ":bookmark",
+        ":bookmarks copy 1",
+        ":set safe_mode false",
+        ":quit",
+    ];
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(format!("{}\n", input.join("\n")).as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stdout}{stderr}");
+
+    for refused in [
+        "Loading .gpt-cli.toml is disabled in safe mode.",
+        "Calling tools is disabled in safe mode.",
+    ] {
+        assert!(stderr.contains(refused), "{refused}\n{stderr}");
+    }
+    for refused in [
+        "Running $(command) in messages is disabled in safe mode.",
+        "Running shell commands is disabled in safe mode.",
+        "Piping responses through a filter is disabled in safe mode.",
+        "Writing files outside the data directory is disabled in safe mode: out.txt",
+        "Copying to the clipboard is disabled in safe mode.",
+        "safe_mode can't be changed from the REPL",
+    ] {
+        assert!(stdout.contains(refused), "{refused}\n{stdout}");
+    }
+    // Both writes were refused.
+    assert_eq!(stdout.matches("disabled in safe mode: out.txt").count(), 2);
+    assert!(!sandbox.dir.join("out.txt").exists());
+
+    // Only "hi" was sent, without the project's system prompt or the tools.
+    let requests = api.requests();
+    assert_eq!(requests.len(), 1);
+    let request = requests[0].to_string();
+    assert!(!request.contains("from the project"), "{request}");
+    assert!(requests[0].get("tools").is_none(), "{request}");
+}
+
+#[test]
+fn an_output_file_is_refused_before_anything_is_sent() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = sandbox("safe-output", &api);
+    let output = sandbox
+        .gpt()
+        .args(["--safe", "-o", "out.txt", "hi"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Writing files outside the data directory is disabled in safe mode"),
+        "{stderr}"
+    );
+    assert!(api.requests().is_empty());
+    assert!(!sandbox.dir.join("out.txt").exists());
+}
+
+#[test]
+fn without_safe_mode_the_same_things_go_ahead() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = sandbox("unsafe", &api);
+    let output = sandbox
+        .gpt()
+        .args(["-o", "out.txt", "hello $(echo ran)"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("safe mode"), "{stderr}");
+    assert!(sandbox.dir.join("out.txt").exists());
+    let request = api.requests()[0].to_string();
+    assert!(request.contains("from the project"), "{request}");
+    assert!(request.contains("hello ran"), "{request}");
+}
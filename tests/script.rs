@@ -0,0 +1,95 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use serde_json::Value;
+use std::{
+    fs,
+    io::Write,
+    process::{Output, Stdio},
+};
+
+// Without the background title request, every request is one of the script's or typed.
+const CONFIG: &str = "title_after_exchanges = 0\n";
+
+/// gpt with `--script` and what's piped to it after.
+fn run(sandbox: &Sandbox, script: &str, args: &[&str], typed: &str) -> Output {
+    fs::write(sandbox.dir.join("script.txt"), script).unwrap();
+    let mut child = sandbox
+        .gpt()
+        .args(["--script", "script.txt"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(typed.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// The last message of each request.
+fn sent(api: &Api) -> Vec<Value> {
+    api.requests()
+        .iter()
+        .map(|request| request["messages"].as_array().unwrap().last().unwrap()["content"].clone())
+        .collect()
+}
+
+#[test]
+fn a_script_runs_before_what_is_typed() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("script-then-typed", &api, CONFIG);
+    let output = run(
+        &sandbox,
+        "# set up\n:model 4o\nfrom the script\n",
+        &[],
+        "typed after\n",
+    );
+    assert!(output.status.success());
+    assert_eq!(sent(&api), ["from the script", "typed after"]);
+    assert_eq!(api.requests()[1]["model"], "gpt-4o");
+}
+
+#[test]
+fn a_failed_line_stops_the_script_with_its_number() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("script-stops", &api, CONFIG);
+    let output = run(
+        &sandbox,
+        "first\n\n:no-such-command\nnever sent\n",
+        &[],
+        "typed after\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("script.txt:3: Unknown command"), "{stdout}");
+    assert!(
+        stdout.contains("(stopped the script; 1 more line wasn't run)"),
+        "{stdout}"
+    );
+    assert_eq!(sent(&api), ["first", "typed after"]);
+}
+
+#[test]
+fn with_script_exit_nothing_typed_is_read() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("script-exit", &api, CONFIG);
+    let output = run(&sandbox, "only this\n", &["--script-exit"], "not this\n");
+    assert!(output.status.success());
+    assert_eq!(sent(&api), ["only this"]);
+
+    let output = run(
+        &sandbox,
+        "first\n:no-such-command\n",
+        &["--script-exit"],
+        "not this\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(4), "{stdout}");
+    assert!(stdout.contains("script.txt:2: Unknown command"), "{stdout}");
+}
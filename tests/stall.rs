@@ -0,0 +1,53 @@
+mod common;
+
+use common::{answer, Api, Sandbox, STALL};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+// One chunk, then nothing.
+fn stalls_after_hello() -> Vec<serde_json::Value> {
+    vec![
+        json!([{"index": 0, "delta": {"content": "Hello"}, "finish_reason": null}]),
+        STALL,
+    ]
+}
+
+#[test]
+fn a_stalled_stream_is_dropped_and_resumed() {
+    let api = Api::start(vec![stalls_after_hello(), answer(" world")]);
+    let sandbox = Sandbox::new(
+        "stall-resume",
+        &api,
+        "stall_after_secs = 1\nauto_resume = true\n",
+    );
+    let start = Instant::now();
+    let output = sandbox.gpt().arg("greet me").output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(start.elapsed() < Duration::from_secs(20));
+    assert!(stderr.contains("nothing arrived for 1s"), "{stderr}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello world\n");
+
+    // The second request carries on from what the first one got.
+    let requests = api.requests();
+    assert_eq!(requests.len(), 2);
+    let messages = requests[1]["messages"].as_array().unwrap();
+    assert!(messages
+        .iter()
+        .any(|m| m["role"] == "assistant" && m["content"] == "Hello"));
+}
+
+#[test]
+fn without_anyone_to_ask_a_stall_ends_the_answer() {
+    let api = Api::start(vec![stalls_after_hello()]);
+    let sandbox = Sandbox::new("stall-stop", &api, "stall_after_secs = 1\n");
+    let start = Instant::now();
+    let output = sandbox.gpt().arg("greet me").output().unwrap();
+    assert!(start.elapsed() < Duration::from_secs(20));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Kept, but it's still a failed request.
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    assert!(stderr.contains("nothing arrived for 1s"), "{stderr}");
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Hello"));
+    assert_eq!(api.requests().len(), 1);
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+
+// What the last message of the first request to the API said.
+fn sent(api: &Api) -> String {
+    let requests = api.requests();
+    let messages = requests[0]["messages"].as_array().unwrap();
+    messages.last().unwrap()["content"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn commands_in_a_message_are_not_run_unless_asked() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("subst-off", &api, "");
+    let output = sandbox
+        .gpt()
+        .arg("branch $(echo \"main\") in ${HOME}")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(sent(&api), "branch $(echo \"main\") in ${HOME}");
+}
+
+#[cfg(unix)]
+#[test]
+fn commands_in_a_message_are_run_when_substitution_is_on() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("subst-on", &api, "substitution = true\n");
+    let output = sandbox
+        .gpt()
+        .arg("branch $(echo \"it's 'main'\")")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(sent(&api), "branch it's 'main'");
+}
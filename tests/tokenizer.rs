@@ -0,0 +1,65 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::{io::Write, process::Stdio};
+
+// A question, answered, then what the next one would cost: stdout and stderr.
+fn ask(name: &str, tokenizer: bool) -> (String, String) {
+    let api = Api::start(vec![answer("four five six seven")]);
+    let sandbox = Sandbox::new(name, &api, "");
+    let mut gpt = sandbox.gpt();
+    if !tokenizer {
+        gpt.env("GPT_CLI_NO_TOKENIZER", "1");
+    }
+    let mut child = gpt
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"hello there\n:tokens --detail abcdefgh abcdefgh\n:quit\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stdout}{stderr}");
+    (stdout.into_owned(), stderr.into_owned())
+}
+
+#[test]
+fn without_the_tokenizer_counts_are_rough_and_say_so() {
+    let (stdout, stderr) = ask("no-tokenizer", false);
+    // Once, however many times it's counted.
+    assert_eq!(
+        stderr.matches("couldn't load the tokenizer").count(),
+        1,
+        "{stderr}"
+    );
+    // A token a 4 characters: "hello there" and "four five six seven", with what each message
+    // and the reply add.
+    assert!(
+        stderr.contains("Prompt Tokens: ~10 | Completion Tokens: ~5 | Total Tokens: ~15 (est.)"),
+        "{stderr}"
+    );
+    // The 17 characters of the message.
+    assert!(
+        stdout.contains("New message                    5"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("(a rough estimate, without the tokenizer)"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn with_the_tokenizer_nothing_is_flagged() {
+    let (stdout, stderr) = ask("tokenizer", true);
+    assert!(!stderr.contains("tokenizer"), "{stderr}");
+    assert!(!stderr.contains("(est.)"), "{stderr}");
+    assert!(!stdout.contains("rough estimate"), "{stdout}");
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{answer, Api, Sandbox};
+use std::fs;
+
+// Leaves about a thousand tokens of gpt-4o-mini's 128,000 for the prompt.
+const CONFIG: &str = "model = \"gpt-4o-mini\"\nmax_tokens = 127000\n";
+
+fn sandbox(name: &str, api: &Api, config: &str) -> Sandbox {
+    let sandbox = Sandbox::new(name, api, &format!("{CONFIG}{config}"));
+    let system = "a long project prompt ".repeat(1000);
+    fs::write(
+        sandbox.dir.join(".gpt-cli.toml"),
+        format!("system = \"{system}\"\n"),
+    )
+    .unwrap();
+    sandbox
+}
+
+#[test]
+fn the_project_prompt_counts_towards_the_window() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = sandbox("window-system", &api, "");
+    let output = sandbox.gpt().arg("hello").output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("exceeds gpt-4o-mini's 128,000 window"),
+        "{stderr}"
+    );
+    assert!(api.requests().is_empty());
+}
+
+#[test]
+fn trimming_cant_drop_the_project_prompt() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = sandbox("window-trim", &api, "auto_trim = true\n");
+    let output = sandbox.gpt().arg("hello").output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("doesn't fit in gpt-4o-mini's window"),
+        "{stderr}"
+    );
+    assert!(api.requests().is_empty());
+}
+
+#[test]
+fn without_the_project_the_same_message_is_sent() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("window-plain", &api, CONFIG);
+    let output = sandbox.gpt().arg("hello").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+    assert_eq!(api.requests().len(), 1);
+}
@@ -0,0 +1,70 @@
+//! Console input on Windows arrives with `\r\n` line endings, and files written there have them too.
+//! None of it should reach the model.
+
+#![cfg(windows)]
+
+mod common;
+
+use common::{answer, Api, Sandbox};
+use serde_json::Value;
+use std::{
+    fs,
+    io::Write,
+    process::{Output, Stdio},
+};
+
+fn run(sandbox: &Sandbox, input: &str) -> Output {
+    let mut child = sandbox
+        .gpt()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn contents(request: &Value) -> Vec<String> {
+    request["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m["content"].as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn typed_lines_lose_their_carriage_returns() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("windows-lines", &api, "");
+    let output = run(
+        &sandbox,
+        ":var lang rust\r\nwrite a {{lang}} function\r\n:quit\r\n",
+    );
+    assert!(output.status.success());
+    let requests = api.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        contents(&requests[0]).last().unwrap(),
+        "write a rust function"
+    );
+}
+
+#[test]
+fn staged_files_lose_their_carriage_returns() {
+    let api = Api::start(vec![answer("ok")]);
+    let sandbox = Sandbox::new("windows-file", &api, "");
+    fs::write(sandbox.dir.join("notes.txt"), "one\r\ntwo\r\n").unwrap();
+    let output = run(&sandbox, ":file notes.txt\r\nsummarize\r\n:quit\r\n");
+    assert!(output.status.success());
+    let sent = contents(&api.requests()[0]);
+    assert!(sent.iter().any(|c| c.contains("one\ntwo")), "{sent:?}");
+    assert!(!sent.iter().any(|c| c.contains('\r')), "{sent:?}");
+}